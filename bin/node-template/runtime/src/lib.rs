@@ -374,6 +374,18 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl sp_block_builder::BlockResourcesApi<Block> for Runtime {
+		fn block_resources() -> sp_block_builder::BlockResources {
+			Executive::block_resources()
+		}
+
+		fn extrinsic_resources(
+			extrinsic: <Block as BlockT>::Extrinsic,
+		) -> sp_block_builder::ExtrinsicResources {
+			Executive::extrinsic_resources(extrinsic)
+		}
+	}
+
 	impl sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block> for Runtime {
 		fn validate_transaction(
 			source: TransactionSource,
@@ -384,6 +396,12 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl sp_transaction_pool::runtime_api::TransactionInclusionApi<Block> for Runtime {
+		fn inclusion_results() -> Vec<sp_transaction_pool::runtime_api::InclusionResult> {
+			TransactionPayment::inclusion_results()
+		}
+	}
+
 	impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
 		fn offchain_worker(header: &<Block as BlockT>::Header) {
 			Executive::offchain_worker(header)
@@ -448,6 +466,32 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl frame_system_rpc_runtime_api::DryRunApi<Block> for Runtime {
+		fn extrinsic_events(index: u32) -> Vec<u8> {
+			codec::Encode::encode(&System::extrinsic_events(index))
+		}
+
+		fn fee_breakdown(
+			uxt: <Block as BlockT>::Extrinsic,
+			len: u32,
+		) -> Option<frame_system_rpc_runtime_api::FeeBreakdown> {
+			TransactionPayment::query_fee_details(uxt, len).inclusion_fee.map(|fee| {
+				frame_system_rpc_runtime_api::FeeBreakdown {
+					base_fee: fee.base_fee,
+					len_fee: fee.len_fee,
+					adjusted_weight_fee: fee.adjusted_weight_fee,
+				}
+			})
+		}
+
+		fn pallet_names() -> Vec<Vec<u8>> {
+			<AllPalletsWithSystem as frame_support::traits::PalletsInfoAccess>::infos()
+				.into_iter()
+				.map(|info| info.name.as_bytes().to_vec())
+				.collect()
+		}
+	}
+
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
 		fn query_info(
 			uxt: <Block as BlockT>::Extrinsic,
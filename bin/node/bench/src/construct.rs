@@ -30,8 +30,8 @@ use std::{borrow::Cow, collections::HashMap, pin::Pin, sync::Arc};
 use node_primitives::Block;
 use node_testing::bench::{BenchDb, BlockType, DatabaseType, KeyTypes, Profile};
 use sc_transaction_pool_api::{
-	ImportNotificationStream, PoolFuture, PoolStatus, ReadyTransactions, TransactionFor,
-	TransactionSource, TransactionStatusStreamFor, TxHash,
+	ImportNotificationStream, PoolFuture, PoolInspection, PoolStatus, ReadyTransactions,
+	TransactionFor, TransactionSource, TransactionStatusStreamFor, TxHash,
 };
 use sp_consensus::{Environment, Proposer};
 use sp_inherents::InherentDataProvider;
@@ -212,6 +212,14 @@ impl sc_transaction_pool_api::InPoolTransaction for PoolTransaction {
 	fn is_propagable(&self) -> bool {
 		unimplemented!()
 	}
+
+	fn bundle(&self) -> Option<&[Self::Hash]> {
+		None
+	}
+
+	fn arrival(&self) -> u64 {
+		0
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -265,6 +273,39 @@ impl sc_transaction_pool_api::TransactionPool for Transactions {
 		unimplemented!()
 	}
 
+	fn replace(
+		&self,
+		_at: &BlockId<Self::Block>,
+		_source: TransactionSource,
+		_old: TxHash<Self>,
+		_xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		unimplemented!()
+	}
+
+	fn replace_and_watch(
+		&self,
+		_at: &BlockId<Self::Block>,
+		_source: TransactionSource,
+		_old: TxHash<Self>,
+		_xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		unimplemented!()
+	}
+
+	fn submit_bundle(
+		&self,
+		_at: &BlockId<Self::Block>,
+		_source: TransactionSource,
+		_xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<TxHash<Self>>, Self::Error> {
+		unimplemented!()
+	}
+
+	fn watch(&self, _hash: &TxHash<Self>) -> Option<Pin<Box<TransactionStatusStreamFor<Self>>>> {
+		unimplemented!()
+	}
+
 	fn ready_at(
 		&self,
 		_at: NumberFor<Self::Block>,
@@ -280,6 +321,21 @@ impl sc_transaction_pool_api::TransactionPool for Transactions {
 		Box::pin(futures::future::ready(iter))
 	}
 
+	fn ready_at_hash(
+		&self,
+		_at: node_primitives::Hash,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		let iter: Box<dyn ReadyTransactions<Item = Arc<PoolTransaction>> + Send> =
+			Box::new(TransactionsIterator(self.0.clone().into_iter()));
+		Box::pin(futures::future::ready(iter))
+	}
+
 	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
 		unimplemented!()
 	}
@@ -292,6 +348,10 @@ impl sc_transaction_pool_api::TransactionPool for Transactions {
 		unimplemented!()
 	}
 
+	fn inspect(&self) -> PoolInspection<TxHash<Self>> {
+		unimplemented!()
+	}
+
 	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
 		unimplemented!()
 	}
@@ -95,6 +95,13 @@ pub enum Subcommand {
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
 
+	/// Change the state pruning mode of the database.
+	ReconfigurePruning(sc_cli::ReconfigurePruningCmd),
+
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Database maintenance utilities.
+	#[clap(subcommand)]
+	Db(sc_cli::DbSubcommand),
 }
@@ -19,7 +19,7 @@
 use super::command_helper::{inherent_benchmark_data, BenchmarkExtrinsicBuilder};
 use crate::{
 	chain_spec, service,
-	service::{new_partial, FullClient},
+	service::{block_timestamp, new_partial, FullClient},
 	Cli, Subcommand,
 };
 use frame_benchmarking_cli::*;
@@ -189,6 +189,15 @@ pub fn run() -> Result<()> {
 				Ok((cmd.run(client, backend, Some(aux_revert)), task_manager))
 			})
 		},
+		Some(Subcommand::ReconfigurePruning(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| {
+				let slot_duration = sp_consensus_babe::SlotDuration::from_millis(
+					node_runtime::constants::time::SLOT_DURATION,
+				);
+				cmd.run::<Block>(&config, Some(block_timestamp(slot_duration)))
+			})
+		},
 		#[cfg(feature = "try-runtime")]
 		Some(Subcommand::TryRuntime(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
@@ -211,5 +220,9 @@ pub fn run() -> Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run::<Block>(&config))
 		},
+		Some(Subcommand::Db(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run::<Block>(&config))
+		},
 	}
 }
@@ -27,6 +27,7 @@ use node_executor::ExecutorDispatch;
 use node_primitives::Block;
 use node_runtime::RuntimeApi;
 use sc_client_api::{BlockBackend, ExecutorProvider};
+use sc_client_db::BlockTimestamp;
 use sc_consensus_babe::{self, SlotProportion};
 use sc_executor::NativeElseWasmExecutor;
 use sc_network::{Event, NetworkService};
@@ -120,6 +121,15 @@ pub fn create_extrinsic(
 	)
 }
 
+/// Returns the time a block was authored at: the start of its BABE slot.
+pub fn block_timestamp(slot_duration: sp_consensus_babe::SlotDuration) -> BlockTimestamp<Block> {
+	Arc::new(move |header: &<Block as BlockT>::Header| {
+		sc_consensus_babe::find_pre_digest::<Block>(header)
+			.ok()
+			.map(|pre_digest| u64::from(pre_digest.slot()) * slot_duration.as_millis())
+	})
+}
+
 /// Creates a new partial node.
 pub fn new_partial(
 	config: &Configuration,
@@ -164,11 +174,14 @@ pub fn new_partial(
 		config.runtime_cache_size,
 	);
 
+	let slot_duration =
+		sp_consensus_babe::SlotDuration::from_millis(node_runtime::constants::time::SLOT_DURATION);
 	let (client, backend, keystore_container, task_manager) =
-		sc_service::new_full_parts::<Block, RuntimeApi, _>(
+		sc_service::new_full_parts_with_block_timestamp::<Block, RuntimeApi, _>(
 			config,
 			telemetry.as_ref().map(|(_, telemetry)| telemetry.handle()),
 			executor,
+			Some(block_timestamp(slot_duration)),
 		)?;
 	let client = Arc::new(client);
 
@@ -179,7 +192,7 @@ pub fn new_partial(
 
 	let select_chain = sc_consensus::LongestChain::new(backend.clone());
 
-	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
+	let transaction_pool = sc_transaction_pool::BasicPool::new_full_with_inclusion_results(
 		config.transaction_pool.clone(),
 		config.role.is_authority().into(),
 		config.prometheus_registry(),
@@ -411,13 +424,14 @@ pub fn new_full_base(
 	(with_startup_data)(&block_import, &babe_link);
 
 	if let sc_service::config::Role::Authority { .. } = &role {
-		let proposer = sc_basic_authorship::ProposerFactory::new(
+		let mut proposer = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
 			transaction_pool.clone(),
 			prometheus_registry.as_ref(),
 			telemetry.as_ref().map(|x| x.handle()),
 		);
+		proposer.enable_block_resources();
 
 		let can_author_with =
 			sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
@@ -505,6 +505,18 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, block: BlockId<Block>) -> sp_blockchain::Result<Self::State>;
 
+	/// Pin the block to keep its state and body from being pruned.
+	///
+	/// Pins are reference counted, every call has to be matched by a call to
+	/// [`Backend::unpin_block`]. Pruning is held back while the block is pinned.
+	fn pin_block(&self, hash: &Block::Hash) -> sp_blockchain::Result<()>;
+
+	/// Release a pin taken with [`Backend::pin_block`].
+	///
+	/// Data of the block that was due for pruning while it was pinned is removed once the last
+	/// pin is released.
+	fn unpin_block(&self, hash: &Block::Hash);
+
 	/// Attempts to revert the chain by `n` blocks. If `revert_finalized` is set it will attempt to
 	/// revert past any finalized block, this is unsafe and can potentially leave the node in an
 	/// inconsistent state.
@@ -364,6 +364,8 @@ impl<Block: BlockT> HeaderBackend<Block> for Blockchain<Block> {
 			},
 			number_leaves: storage.leaves.count(),
 			block_gap: None,
+			bodies_from: None,
+			justifications_from: None,
 		}
 	}
 
@@ -780,6 +782,12 @@ where
 			.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{}", block)))
 	}
 
+	fn pin_block(&self, _hash: &Block::Hash) -> sp_blockchain::Result<()> {
+		Ok(())
+	}
+
+	fn unpin_block(&self, _hash: &Block::Hash) {}
+
 	fn revert(
 		&self,
 		_n: NumberFor<Block>,
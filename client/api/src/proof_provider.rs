@@ -40,6 +40,28 @@ pub trait ProofProvider<Block: BlockT> {
 		keys: &mut dyn Iterator<Item = &[u8]>,
 	) -> sp_blockchain::Result<StorageProof>;
 
+	/// Reads storage values of the top trie and of child tries at a given block, returning a
+	/// single read proof for all of them.
+	///
+	/// Trie nodes that are part of several of the proofs, like the path to the root of a child
+	/// trie, are only included once.
+	fn read_proof_with_children(
+		&self,
+		id: &BlockId<Block>,
+		keys: &mut dyn Iterator<Item = &[u8]>,
+		child_keys: &[(ChildInfo, Vec<Vec<u8>>)],
+	) -> sp_blockchain::Result<StorageProof> {
+		let mut proofs = vec![self.read_proof(id, keys)?];
+		for (child_info, keys) in child_keys {
+			proofs.push(self.read_child_proof(
+				id,
+				child_info,
+				&mut keys.iter().map(|key| key.as_slice()),
+			)?);
+		}
+		Ok(StorageProof::merge(proofs))
+	}
+
 	/// Execute a call to a contract on top of state in a block of given hash
 	/// AND returning execution proof.
 	///
@@ -72,6 +72,8 @@ impl<Block: BlockT> HeaderBackend<Block> for TestApi {
 			number_leaves: Default::default(),
 			finalized_state: None,
 			block_gap: None,
+			bodies_from: None,
+			justifications_from: None,
 		}
 	}
 
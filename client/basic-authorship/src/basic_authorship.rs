@@ -28,7 +28,10 @@ use futures::{
 	select,
 };
 use log::{debug, error, info, trace, warn};
-use sc_block_builder::{BlockBuilderApi, BlockBuilderProvider};
+use sc_block_builder::{
+	BlockBuilder, BlockBuilderApi, BlockBuilderProvider, BlockResources, BlockResourcesApi,
+	ExtrinsicClass, ExtrinsicResources, PerClass,
+};
 use sc_client_api::backend;
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_INFO};
 use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
@@ -42,10 +45,12 @@ use sp_inherents::InherentData;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{BlakeTwo256, Block as BlockT, Hash as HashT, Header as HeaderT},
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
 	Digest, Percent, SaturatedConversion,
 };
-use std::{marker::PhantomData, pin::Pin, sync::Arc, time};
+use std::{collections::HashSet, marker::PhantomData, pin::Pin, sync::Arc, time};
 
+use crate::selector::{PrioritySelector, TransactionSelector};
 use prometheus_endpoint::Registry as PrometheusRegistry;
 use sc_proposer_metrics::{EndProposingReason, MetricsLink as PrometheusMetrics};
 
@@ -61,7 +66,7 @@ pub const DEFAULT_BLOCK_SIZE_LIMIT: usize = 4 * 1024 * 1024 + 512;
 const DEFAULT_SOFT_DEADLINE_PERCENT: Percent = Percent::from_percent(50);
 
 /// [`Proposer`] factory.
-pub struct ProposerFactory<A, B, C, PR> {
+pub struct ProposerFactory<A: TransactionPool, B, C, PR> {
 	spawn_handle: Box<dyn SpawnNamed>,
 	/// The client instance.
 	client: Arc<C>,
@@ -85,11 +90,70 @@ pub struct ProposerFactory<A, B, C, PR> {
 	telemetry: Option<TelemetryHandle>,
 	/// When estimating the block size, should the proof be included?
 	include_proof_in_block_size_estimation: bool,
+	/// Order in which the transactions of the pool are tried.
+	selector: Arc<dyn TransactionSelector<A::InPoolTransaction>>,
+	/// Reads the resources of the block per class of transactions, if enabled.
+	resources_provider: Option<Arc<dyn ResourcesProvider<B, A::Block, C>>>,
 	/// phantom member to pin the `Backend`/`ProofRecording` type.
 	_phantom: PhantomData<(B, PR)>,
 }
 
-impl<A, B, C> ProposerFactory<A, B, C, DisableProofRecording> {
+/// Reads the resources of the block being built and of its transactions from the runtime.
+///
+/// Keeps `BlockResourcesApi` out of the bounds of the proposer, so that runtimes without it can
+/// still use it.
+trait ResourcesProvider<B, Block, C>: Send + Sync {
+	/// Get the resources consumed by the block and the limits on them.
+	///
+	/// Returns `None` if the runtime of the block doesn't provide them.
+	fn block_resources(
+		&self,
+		block_builder: &BlockBuilder<Block, C, B>,
+	) -> Result<Option<BlockResources>, sp_blockchain::Error>
+	where
+		Block: BlockT,
+		C: ProvideRuntimeApi<Block>;
+
+	/// Get the resources the given extrinsic would consume when pushed.
+	fn extrinsic_resources(
+		&self,
+		block_builder: &BlockBuilder<Block, C, B>,
+		xt: &Block::Extrinsic,
+	) -> Result<ExtrinsicResources, sp_blockchain::Error>
+	where
+		Block: BlockT,
+		C: ProvideRuntimeApi<Block>;
+}
+
+/// Reads the resources from the `BlockResourcesApi` of the runtime.
+struct RuntimeResources;
+
+impl<B, Block, C> ResourcesProvider<B, Block, C> for RuntimeResources
+where
+	B: backend::Backend<Block>,
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + 'static,
+	C::Api: ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>
+		+ BlockBuilderApi<Block>
+		+ BlockResourcesApi<Block>,
+{
+	fn block_resources(
+		&self,
+		block_builder: &BlockBuilder<Block, C, B>,
+	) -> Result<Option<BlockResources>, sp_blockchain::Error> {
+		block_builder.block_resources()
+	}
+
+	fn extrinsic_resources(
+		&self,
+		block_builder: &BlockBuilder<Block, C, B>,
+		xt: &Block::Extrinsic,
+	) -> Result<ExtrinsicResources, sp_blockchain::Error> {
+		block_builder.extrinsic_resources(xt)
+	}
+}
+
+impl<A: TransactionPool, B, C> ProposerFactory<A, B, C, DisableProofRecording> {
 	/// Create a new proposer factory.
 	///
 	/// Proof recording will be disabled when using proposers built by this instance to build
@@ -110,12 +174,14 @@ impl<A, B, C> ProposerFactory<A, B, C, DisableProofRecording> {
 			telemetry,
 			client,
 			include_proof_in_block_size_estimation: false,
+			selector: Arc::new(PrioritySelector),
+			resources_provider: None,
 			_phantom: PhantomData,
 		}
 	}
 }
 
-impl<A, B, C> ProposerFactory<A, B, C, EnableProofRecording> {
+impl<A: TransactionPool, B, C> ProposerFactory<A, B, C, EnableProofRecording> {
 	/// Create a new proposer factory with proof recording enabled.
 	///
 	/// Each proposer created by this instance will record a proof while building a block.
@@ -138,6 +204,8 @@ impl<A, B, C> ProposerFactory<A, B, C, EnableProofRecording> {
 			soft_deadline_percent: DEFAULT_SOFT_DEADLINE_PERCENT,
 			telemetry,
 			include_proof_in_block_size_estimation: true,
+			selector: Arc::new(PrioritySelector),
+			resources_provider: None,
 			_phantom: PhantomData,
 		}
 	}
@@ -148,7 +216,7 @@ impl<A, B, C> ProposerFactory<A, B, C, EnableProofRecording> {
 	}
 }
 
-impl<A, B, C, PR> ProposerFactory<A, B, C, PR> {
+impl<A: TransactionPool, B, C, PR> ProposerFactory<A, B, C, PR> {
 	/// Set the default block size limit in bytes.
 	///
 	/// The default value for the block size limit is:
@@ -175,6 +243,37 @@ impl<A, B, C, PR> ProposerFactory<A, B, C, PR> {
 	pub fn set_soft_deadline(&mut self, percent: Percent) {
 		self.soft_deadline_percent = percent;
 	}
+
+	/// Set the strategy choosing the order in which the transactions of the pool are tried.
+	///
+	/// The default is [`PrioritySelector`], trying the transactions with the highest priority
+	/// first.
+	pub fn set_transaction_selector(
+		&mut self,
+		selector: impl TransactionSelector<A::InPoolTransaction> + 'static,
+	) {
+		self.selector = Arc::new(selector);
+	}
+}
+
+impl<B, Block, C, A, PR> ProposerFactory<A, B, C, PR>
+where
+	A: TransactionPool<Block = Block>,
+	B: backend::Backend<Block>,
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + 'static,
+	C::Api: ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>
+		+ BlockBuilderApi<Block>
+		+ BlockResourcesApi<Block>,
+{
+	/// Fill the block per class of transactions with the resources provided by the runtime.
+	///
+	/// Once the block is full for a class of transactions, the transactions of the other classes
+	/// are still tried. Without this, or for runtime versions without `BlockResourcesApi`, the
+	/// block is considered full as soon as transactions exhaust its resources.
+	pub fn enable_block_resources(&mut self) {
+		self.resources_provider = Some(Arc::new(RuntimeResources));
+	}
 }
 
 impl<B, Block, C, A, PR> ProposerFactory<A, B, C, PR>
@@ -216,6 +315,8 @@ where
 			telemetry: self.telemetry.clone(),
 			_phantom: PhantomData,
 			include_proof_in_block_size_estimation: self.include_proof_in_block_size_estimation,
+			selector: self.selector.clone(),
+			resources_provider: self.resources_provider.clone(),
 		};
 
 		proposer
@@ -260,6 +361,8 @@ pub struct Proposer<B, Block: BlockT, C, A: TransactionPool, PR> {
 	include_proof_in_block_size_estimation: bool,
 	soft_deadline_percent: Percent,
 	telemetry: Option<TelemetryHandle>,
+	selector: Arc<dyn TransactionSelector<A::InPoolTransaction>>,
+	resources_provider: Option<Arc<dyn ResourcesProvider<B, Block, C>>>,
 	_phantom: PhantomData<(B, PR)>,
 }
 
@@ -321,8 +424,99 @@ where
 /// If the block is full we will attempt to push at most
 /// this number of transactions before quitting for real.
 /// It allows us to increase block utilization.
+///
+/// If the runtime provides the resources of the block, this is counted for each class of
+/// transactions separately. Once the block is full for a class, the proposer quits after
+/// skipping this number of transactions of full classes in a row as well, as the classes that
+/// still have room seem to have no transactions left.
 const MAX_SKIPPED_TRANSACTIONS: usize = 8;
 
+/// Transactions skipped because they would exhaust the resources of the block.
+#[derive(Default)]
+struct Skipped {
+	/// Number of transactions skipped because they would exhaust the resources of their class.
+	count: PerClass<usize>,
+	/// Whether the block is full for each class of transactions.
+	full: PerClass<bool>,
+	/// Number of transactions skipped because they would overflow the block size limit.
+	size: usize,
+	/// Number of transactions skipped in a row because the block is full for their class.
+	in_full_classes: usize,
+}
+
+impl Skipped {
+	/// Returns whether any transaction was skipped because it would exhaust the block.
+	fn any(&self) -> bool {
+		self.size > 0 ||
+			self.count.normal > 0 ||
+			self.count.operational > 0 ||
+			self.count.mandatory > 0
+	}
+
+	/// Note a transaction that would overflow the block size limit.
+	///
+	/// Returns whether the block is full.
+	fn note_size(&mut self, before_soft_deadline: bool) -> bool {
+		is_full(&mut self.size, before_soft_deadline)
+	}
+
+	/// Note a transaction of the given class that would exhaust the resources of the block.
+	///
+	/// The block is full for the class once [`MAX_SKIPPED_TRANSACTIONS`] transactions of the
+	/// class were skipped and the soft deadline is reached. Without `per_class`, all transactions
+	/// are of the same class.
+	///
+	/// Returns whether the block is full for all classes of transactions.
+	fn note(&mut self, class: ExtrinsicClass, before_soft_deadline: bool, per_class: bool) -> bool {
+		if !is_full(self.count.get_mut(class), before_soft_deadline) {
+			return false
+		}
+
+		*self.full.get_mut(class) = true;
+		if per_class && !(self.full.normal && self.full.operational) {
+			debug!("Block is full for {:?} transactions, trying the other classes.", class);
+			return false
+		}
+		true
+	}
+
+	/// Note a transaction skipped because the block is full for its class.
+	///
+	/// Returns whether [`MAX_SKIPPED_TRANSACTIONS`] transactions were skipped in a row.
+	fn note_in_full_class(&mut self) -> bool {
+		self.in_full_classes += 1;
+		self.in_full_classes > MAX_SKIPPED_TRANSACTIONS
+	}
+
+	/// Note a transaction pushed to the block.
+	fn note_pushed(&mut self) {
+		self.in_full_classes = 0;
+	}
+}
+
+/// Count a skipped transaction in `skipped`.
+///
+/// Returns whether [`MAX_SKIPPED_TRANSACTIONS`] were skipped already and the soft deadline is
+/// reached.
+fn is_full(skipped: &mut usize, before_soft_deadline: bool) -> bool {
+	if *skipped < MAX_SKIPPED_TRANSACTIONS {
+		*skipped += 1;
+		debug!(
+			"Block seems full, but will try {} more transactions before quitting.",
+			MAX_SKIPPED_TRANSACTIONS - *skipped,
+		);
+		false
+	} else if before_soft_deadline {
+		debug!(
+			"Block seems full, but we still have time before the soft deadline, \
+			 so we will try a bit more before quitting."
+		);
+		false
+	} else {
+		true
+	}
+}
+
 impl<A, B, Block, C, PR> Proposer<B, Block, C, A, PR>
 where
 	A: TransactionPool<Block = Block>,
@@ -388,14 +582,15 @@ where
 		let soft_deadline =
 			now + time::Duration::from_micros(self.soft_deadline_percent.mul_floor(left_micros));
 		let block_timer = time::Instant::now();
-		let mut skipped = 0;
+		let mut skipped = Skipped::default();
 		let mut unqueue_invalid = Vec::new();
+		let mut tried_bundles = HashSet::new();
 
-		let mut t1 = self.transaction_pool.ready_at(self.parent_number).fuse();
+		let mut t1 = self.transaction_pool.ready_at_hash(self.parent_hash).fuse();
 		let mut t2 =
 			futures_timer::Delay::new(deadline.saturating_duration_since((self.now)()) / 8).fuse();
 
-		let mut pending_iterator = select! {
+		let pending_iterator = select! {
 			res = t1 => res,
 			_ = t2 => {
 				log::warn!(
@@ -406,9 +601,13 @@ where
 				self.transaction_pool.ready()
 			},
 		};
+		let mut pending_iterator = self.selector.select(pending_iterator);
 
 		let block_size_limit = block_size_limit.unwrap_or(self.default_block_size_limit);
 
+		// The resources left in the block, if enabled and the runtime provides them.
+		let mut resources = self.block_resources(&block_builder);
+
 		debug!("Attempting to push transactions from the pool.");
 		debug!("Pool status: {:?}", self.transaction_pool.status());
 		let mut transaction_pushed = false;
@@ -429,59 +628,127 @@ where
 				break EndProposingReason::HitDeadline
 			}
 
-			let pending_tx_data = pending_tx.data().clone();
 			let pending_tx_hash = pending_tx.hash().clone();
+			let bundle = match pending_tx.bundle() {
+				Some(hashes) if !tried_bundles.insert(hashes[0].clone()) => {
+					trace!("[{:?}] Bundle was tried already, skipping.", pending_tx_hash);
+					continue
+				},
+				Some(hashes) => match hashes
+					.iter()
+					.map(|hash| self.transaction_pool.ready_transaction(hash))
+					.collect::<Option<Vec<_>>>()
+				{
+					Some(bundle) => Some(bundle),
+					None => {
+						pending_iterator.report_invalid(&pending_tx);
+						debug!("[{:?}] Bundle is not ready completely, skipping.", pending_tx_hash);
+						continue
+					},
+				},
+				None => None,
+			};
+			let extrinsics = match bundle {
+				Some(ref bundle) => bundle.iter().map(|tx| tx.data()).collect(),
+				None => vec![pending_tx.data()],
+			};
+			let pending_tx_size = extrinsics.iter().map(|xt| xt.encoded_size()).sum::<usize>();
 
 			let block_size =
 				block_builder.estimate_block_size(self.include_proof_in_block_size_estimation);
-			if block_size + pending_tx_data.encoded_size() > block_size_limit {
+			if block_size + pending_tx_size > block_size_limit {
 				pending_iterator.report_invalid(&pending_tx);
-				if skipped < MAX_SKIPPED_TRANSACTIONS {
-					skipped += 1;
-					debug!(
-						"Transaction would overflow the block size limit, \
-						 but will try {} more transactions before quitting.",
-						MAX_SKIPPED_TRANSACTIONS - skipped,
-					);
-					continue
-				} else if now < soft_deadline {
-					debug!(
-						"Transaction would overflow the block size limit, \
-						 but we still have time before the soft deadline, so \
-						 we will try a bit more."
-					);
-					continue
-				} else {
+				debug!("[{:?}] Transaction would overflow the block size limit.", pending_tx_hash);
+				if skipped.note_size(now < soft_deadline) {
 					debug!("Reached block size limit, proceeding with proposing.");
 					break EndProposingReason::HitBlockSizeLimit
 				}
+				continue
+			}
+
+			// The class of the transaction, and whether it fits in the resources left.
+			let (class, fits) = match (&resources, &self.resources_provider) {
+				(Some(resources), Some(provider)) => match extrinsics
+					.iter()
+					.map(|xt| provider.extrinsic_resources(&block_builder, xt))
+					.collect::<Result<Vec<_>, _>>()
+				{
+					Ok(extrinsics_resources) => {
+						let mut left = resources.clone();
+						(
+							extrinsics_resources
+								.first()
+								.map_or(ExtrinsicClass::Normal, |resources| resources.class),
+							extrinsics_resources.iter().all(|resources| left.consume(resources)),
+						)
+					},
+					Err(e) => {
+						debug!(
+							"[{:?}] Failed to get the resources of the transaction: {}",
+							pending_tx_hash, e,
+						);
+						(ExtrinsicClass::Normal, true)
+					},
+				},
+				_ => (ExtrinsicClass::Normal, true),
+			};
+			let per_class = resources.is_some();
+
+			if *skipped.full.get(class) {
+				pending_iterator.report_invalid(&pending_tx);
+				trace!(
+					"[{:?}] Block is full for {:?} transactions, skipping.",
+					pending_tx_hash,
+					class
+				);
+				if skipped.note_in_full_class() {
+					debug!("No transactions of classes with room left, proceeding with proposing.");
+					break EndProposingReason::HitBlockWeightLimit
+				}
+				continue
+			}
+
+			if !fits {
+				pending_iterator.report_invalid(&pending_tx);
+				debug!(
+					"[{:?}] Transaction would exhaust the resources of the block.",
+					pending_tx_hash
+				);
+				if skipped.note(class, now < soft_deadline, per_class) {
+					debug!("Reached block weight limit, proceeding with proposing.");
+					break EndProposingReason::HitBlockWeightLimit
+				}
+				continue
 			}
 
 			trace!("[{:?}] Pushing to the block.", pending_tx_hash);
-			match sc_block_builder::BlockBuilder::push(&mut block_builder, pending_tx_data) {
+			let pushed = match bundle {
+				Some(_) => sc_block_builder::BlockBuilder::push_bundle(
+					&mut block_builder,
+					extrinsics.into_iter().cloned().collect(),
+				),
+				None => sc_block_builder::BlockBuilder::push(
+					&mut block_builder,
+					pending_tx.data().clone(),
+				),
+			};
+			match pushed {
 				Ok(()) => {
 					transaction_pushed = true;
+					skipped.note_pushed();
 					debug!("[{:?}] Pushed to the block.", pending_tx_hash);
+					if per_class {
+						resources = self.block_resources(&block_builder);
+					}
 				},
 				Err(ApplyExtrinsicFailed(Validity(e))) if e.exhausted_resources() => {
 					pending_iterator.report_invalid(&pending_tx);
-					if skipped < MAX_SKIPPED_TRANSACTIONS {
-						skipped += 1;
-						debug!(
-							"Block seems full, but will try {} more transactions before quitting.",
-							MAX_SKIPPED_TRANSACTIONS - skipped,
-						);
-					} else if (self.now)() < soft_deadline {
-						debug!(
-							"Block seems full, but we still have time before the soft deadline, \
-							 so we will try a bit more before quitting."
-						);
-					} else {
+					if skipped.note(class, (self.now)() < soft_deadline, per_class) {
 						debug!("Reached block weight limit, proceeding with proposing.");
 						break EndProposingReason::HitBlockWeightLimit
 					}
 				},
-				Err(e) if skipped > 0 => {
+				Err(e) if skipped.any() => {
 					pending_iterator.report_invalid(&pending_tx);
 					trace!(
 						"[{:?}] Ignoring invalid transaction when skipping: {}",
@@ -492,7 +759,19 @@ where
 				Err(e) => {
 					pending_iterator.report_invalid(&pending_tx);
 					debug!("[{:?}] Invalid transaction: {}", pending_tx_hash, e);
-					unqueue_invalid.push(pending_tx_hash);
+					let future = matches!(
+						e,
+						ApplyExtrinsicFailed(Validity(TransactionValidityError::Invalid(
+							InvalidTransaction::Future
+						)))
+					);
+					match bundle {
+						// The bundle may depend on transactions that are not in the block yet.
+						Some(_) if future => {},
+						Some(bundle) =>
+							unqueue_invalid.extend(bundle.iter().map(|tx| tx.hash().clone())),
+						None => unqueue_invalid.push(pending_tx_hash),
+					}
 				},
 			}
 		};
@@ -554,6 +833,15 @@ where
 
 		Ok(Proposal { block, proof, storage_changes })
 	}
+
+	/// Get the resources of the block, if enabled and the runtime provides them.
+	fn block_resources(&self, block_builder: &BlockBuilder<Block, C, B>) -> Option<BlockResources> {
+		let provider = self.resources_provider.as_ref()?;
+		provider.block_resources(block_builder).unwrap_or_else(|e| {
+			warn!("❗️ Failed to get the resources of the block: {}", e);
+			None
+		})
+	}
 }
 
 #[cfg(test)]
@@ -936,6 +1224,46 @@ mod tests {
 		assert_eq!(block.extrinsics().len(), extrinsics_num - 2);
 	}
 
+	#[test]
+	fn should_fill_the_block_with_the_resources_of_the_runtime_once_enabled() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let txpool = BasicPool::new_full(
+			Default::default(),
+			true.into(),
+			None,
+			spawner.clone(),
+			client.clone(),
+		);
+		let genesis_header = client
+			.header(&BlockId::Number(0u64))
+			.expect("header get error")
+			.expect("there should be header");
+
+		let extrinsics = (0..4u8).map(|v| Extrinsic::IncludeData(vec![v; 10])).collect::<Vec<_>>();
+		block_on(txpool.submit_at(&BlockId::number(0), SOURCE, extrinsics)).unwrap();
+		block_on(txpool.maintain(chain_event(genesis_header.clone())));
+
+		let mut proposer_factory =
+			ProposerFactory::new(spawner.clone(), client.clone(), txpool.clone(), None, None);
+		let proposer = block_on(proposer_factory.init(&genesis_header)).unwrap();
+		assert!(proposer.resources_provider.is_none());
+
+		proposer_factory.enable_block_resources();
+		let proposer = block_on(proposer_factory.init(&genesis_header)).unwrap();
+		let block_builder = client.new_block(Default::default()).unwrap();
+		assert!(proposer.block_resources(&block_builder).is_some());
+		drop(block_builder);
+
+		let deadline = time::Duration::from_secs(300);
+		let block =
+			block_on(proposer.propose(Default::default(), Default::default(), deadline, None))
+				.map(|r| r.block)
+				.unwrap();
+
+		assert_eq!(block.extrinsics().len(), 4);
+	}
+
 	#[test]
 	fn should_keep_adding_transactions_after_exhausts_resources_before_soft_deadline() {
 		// given
@@ -1073,4 +1401,111 @@ mod tests {
 			"Not enough calls to current time, which indicates the test might have ended because of deadline, not soft deadline"
 		);
 	}
+
+	#[test]
+	fn should_keep_trying_other_classes_when_block_is_full_for_one() {
+		let mut skipped = Skipped::default();
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note(ExtrinsicClass::Normal, false, true));
+		}
+		// still before the soft deadline
+		assert!(!skipped.note(ExtrinsicClass::Normal, true, true));
+		assert!(!skipped.full.normal);
+
+		assert!(!skipped.note(ExtrinsicClass::Normal, false, true));
+		assert!(skipped.full.normal);
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note(ExtrinsicClass::Operational, false, true));
+		}
+		assert!(skipped.note(ExtrinsicClass::Operational, false, true));
+	}
+
+	#[test]
+	fn should_stop_when_block_is_full_without_classes() {
+		let mut skipped = Skipped::default();
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note(ExtrinsicClass::Normal, false, false));
+		}
+		assert!(skipped.note(ExtrinsicClass::Normal, false, false));
+	}
+
+	#[test]
+	fn should_stop_when_only_transactions_of_full_classes_are_left() {
+		let mut skipped = Skipped::default();
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note_in_full_class());
+		}
+		// a transaction of a class with room resets the count
+		skipped.note_pushed();
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note_in_full_class());
+		}
+		assert!(skipped.note_in_full_class());
+	}
+
+	#[test]
+	fn should_count_transactions_overflowing_the_block_size_for_all_classes() {
+		let mut skipped = Skipped::default();
+		assert!(!skipped.any());
+
+		for _ in 0..MAX_SKIPPED_TRANSACTIONS {
+			assert!(!skipped.note_size(false));
+		}
+		assert!(skipped.any());
+		assert!(!skipped.note_size(true));
+		assert!(skipped.note_size(false));
+	}
+
+	#[test]
+	fn should_include_bundles_completely_or_not_at_all() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let txpool = BasicPool::new_full(
+			Default::default(),
+			true.into(),
+			None,
+			spawner.clone(),
+			client.clone(),
+		);
+		let genesis_header = client
+			.header(&BlockId::Number(0u64))
+			.expect("header get error")
+			.expect("there should be header");
+
+		let transfer = |from: AccountKeyring, nonce| {
+			Transfer { amount: 1, nonce, from: from.into(), to: AccountKeyring::Ferdie.into() }
+				.into_signed_tx()
+		};
+		let complete = vec![transfer(AccountKeyring::Alice, 0), transfer(AccountKeyring::Bob, 0)];
+		// The second transaction waits for a nonce that is not in the pool.
+		let incomplete =
+			vec![transfer(AccountKeyring::Charlie, 0), transfer(AccountKeyring::Charlie, 2)];
+
+		block_on(txpool.submit_bundle(&BlockId::number(0), SOURCE, incomplete)).unwrap();
+		block_on(txpool.submit_bundle(&BlockId::number(0), SOURCE, complete.clone())).unwrap();
+		block_on(txpool.submit_one(&BlockId::number(0), SOURCE, extrinsic(1))).unwrap();
+
+		block_on(txpool.maintain(chain_event(genesis_header.clone())));
+
+		let mut proposer_factory =
+			ProposerFactory::new(spawner.clone(), client.clone(), txpool.clone(), None, None);
+
+		let proposer = block_on(proposer_factory.init(&genesis_header)).unwrap();
+
+		let deadline = time::Duration::from_secs(300);
+		let block =
+			block_on(proposer.propose(Default::default(), Default::default(), deadline, None))
+				.map(|r| r.block)
+				.unwrap();
+
+		// The complete bundle is included together with Alice's next transaction.
+		assert_eq!(block.extrinsics().len(), 3);
+		assert_eq!(&block.extrinsics()[..2], &complete[..]);
+		assert_eq!(block.extrinsics()[2], extrinsic(1));
+	}
 }
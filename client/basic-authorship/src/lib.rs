@@ -71,5 +71,12 @@
 //! ```
 
 mod basic_authorship;
+mod selector;
 
-pub use crate::basic_authorship::{Proposer, ProposerFactory, DEFAULT_BLOCK_SIZE_LIMIT};
+pub use crate::{
+	basic_authorship::{Proposer, ProposerFactory, DEFAULT_BLOCK_SIZE_LIMIT},
+	selector::{
+		FirstComeFirstServedSelector, PrioritySelector, ReadyIterator, RoundRobinSelector,
+		TransactionSelector,
+	},
+};
@@ -0,0 +1,290 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Strategies choosing the order in which the proposer tries to include transactions.
+
+use std::{
+	cmp::Reverse,
+	collections::{BinaryHeap, HashMap, HashSet},
+	sync::Arc,
+};
+
+use sc_transaction_pool_api::{InPoolTransaction, ReadyTransactions};
+use sp_runtime::transaction_validity::TransactionTag;
+
+/// Iterator over the ready transactions of the pool.
+pub type ReadyIterator<T> = Box<dyn ReadyTransactions<Item = Arc<T>> + Send>;
+
+/// Strategy choosing the order in which the proposer tries to include the ready transactions of
+/// the pool.
+///
+/// Set with [`ProposerFactory::set_transaction_selector`].
+///
+/// [`ProposerFactory::set_transaction_selector`]: crate::ProposerFactory::set_transaction_selector
+pub trait TransactionSelector<T>: Send + Sync {
+	/// Order the ready transactions.
+	///
+	/// The pool returns each transaction after the ones it depends on. The returned iterator has
+	/// to keep that order, otherwise the dependent transactions fail to be included.
+	fn select(&self, ready: ReadyIterator<T>) -> ReadyIterator<T>;
+}
+
+/// Tries the transactions with the highest priority first, as ordered by the pool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrioritySelector;
+
+impl<T> TransactionSelector<T> for PrioritySelector {
+	fn select(&self, ready: ReadyIterator<T>) -> ReadyIterator<T> {
+		ready
+	}
+}
+
+/// Tries the transactions in the order they arrived in the pool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstComeFirstServedSelector;
+
+impl<T> TransactionSelector<T> for FirstComeFirstServedSelector
+where
+	T: InPoolTransaction + Send + Sync + 'static,
+{
+	fn select(&self, ready: ReadyIterator<T>) -> ReadyIterator<T> {
+		Box::new(Selected::new(ready.collect(), |_, tx| tx.arrival()))
+	}
+}
+
+/// Tries one transaction of each sender in turn.
+///
+/// The pool doesn't know about accounts, so transactions that depend on each other (like the
+/// consecutive nonces of an account) are considered to be sent by the same sender. Senders take
+/// their turn in the order of priority of their first transaction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobinSelector;
+
+impl<T> TransactionSelector<T> for RoundRobinSelector
+where
+	T: InPoolTransaction + Send + Sync + 'static,
+{
+	fn select(&self, ready: ReadyIterator<T>) -> ReadyIterator<T> {
+		let ready = ready.collect::<Vec<_>>();
+
+		// Senders are identified by the position of their first transaction.
+		let mut senders = HashMap::new();
+		let mut turns = HashMap::new();
+		let keys = ready
+			.iter()
+			.enumerate()
+			.map(|(idx, tx)| {
+				let sender =
+					tx.requires().iter().find_map(|tag| senders.get(tag).copied()).unwrap_or(idx);
+				senders.extend(tx.provides().iter().map(|tag| (tag.clone(), sender)));
+				let turn = turns.entry(sender).or_insert(0);
+				*turn += 1;
+				(*turn, sender)
+			})
+			.collect::<Vec<_>>();
+
+		Box::new(Selected::new(ready, |idx, _| keys[idx]))
+	}
+}
+
+/// Ready transactions in the order chosen by a selector.
+struct Selected<T> {
+	transactions: std::vec::IntoIter<Arc<T>>,
+	/// Tags provided by the transactions reported as invalid, or depending on those.
+	invalid_tags: HashSet<TransactionTag>,
+}
+
+impl<T: InPoolTransaction> Selected<T> {
+	/// Order the transactions by the given key, keeping each after the ones it depends on.
+	fn new<K: Ord>(transactions: Vec<Arc<T>>, key: impl Fn(usize, &T) -> K) -> Self {
+		let order = {
+			let providers = transactions
+				.iter()
+				.enumerate()
+				.flat_map(|(idx, tx)| tx.provides().iter().map(move |tag| (tag, idx)))
+				.collect::<HashMap<_, _>>();
+
+			let mut dependents = vec![Vec::new(); transactions.len()];
+			let mut missing = vec![0; transactions.len()];
+			for (idx, tx) in transactions.iter().enumerate() {
+				let dependencies = tx
+					.requires()
+					.iter()
+					.filter_map(|tag| providers.get(tag).copied())
+					.collect::<HashSet<_>>();
+				missing[idx] = dependencies.len();
+				dependencies.into_iter().for_each(|dependency| dependents[dependency].push(idx));
+			}
+
+			let mut next = (0..transactions.len())
+				.filter(|idx| missing[*idx] == 0)
+				.map(|idx| Reverse((key(idx, &transactions[idx]), idx)))
+				.collect::<BinaryHeap<_>>();
+			let mut order = Vec::with_capacity(transactions.len());
+			while let Some(Reverse((_, idx))) = next.pop() {
+				order.push(idx);
+				for &dependent in &dependents[idx] {
+					missing[dependent] -= 1;
+					if missing[dependent] == 0 {
+						next.push(Reverse((key(dependent, &transactions[dependent]), dependent)));
+					}
+				}
+			}
+			order
+		};
+
+		let mut transactions = transactions.into_iter().map(Some).collect::<Vec<_>>();
+		let transactions =
+			order.into_iter().filter_map(|idx| transactions[idx].take()).collect::<Vec<_>>();
+		Self { transactions: transactions.into_iter(), invalid_tags: HashSet::new() }
+	}
+}
+
+impl<T: InPoolTransaction> Iterator for Selected<T> {
+	type Item = Arc<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let tx = self.transactions.next()?;
+			if tx.requires().iter().any(|tag| self.invalid_tags.contains(tag)) {
+				self.invalid_tags.extend(tx.provides().iter().cloned());
+				continue
+			}
+
+			return Some(tx)
+		}
+	}
+}
+
+impl<T: InPoolTransaction> ReadyTransactions for Selected<T> {
+	fn report_invalid(&mut self, tx: &Self::Item) {
+		self.invalid_tags.extend(tx.provides().iter().cloned());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::transaction_validity::{TransactionLongevity, TransactionPriority};
+
+	#[derive(Debug, PartialEq)]
+	struct Tx {
+		hash: u64,
+		arrival: u64,
+		requires: Vec<TransactionTag>,
+		provides: Vec<TransactionTag>,
+	}
+
+	impl InPoolTransaction for Tx {
+		type Transaction = ();
+		type Hash = u64;
+
+		fn data(&self) -> &() {
+			&()
+		}
+
+		fn hash(&self) -> &u64 {
+			&self.hash
+		}
+
+		fn priority(&self) -> &TransactionPriority {
+			&0
+		}
+
+		fn longevity(&self) -> &TransactionLongevity {
+			&0
+		}
+
+		fn requires(&self) -> &[TransactionTag] {
+			&self.requires
+		}
+
+		fn provides(&self) -> &[TransactionTag] {
+			&self.provides
+		}
+
+		fn is_propagable(&self) -> bool {
+			true
+		}
+
+		fn bundle(&self) -> Option<&[u64]> {
+			None
+		}
+
+		fn arrival(&self) -> u64 {
+			self.arrival
+		}
+	}
+
+	struct Ready(std::vec::IntoIter<Arc<Tx>>);
+
+	impl Iterator for Ready {
+		type Item = Arc<Tx>;
+
+		fn next(&mut self) -> Option<Self::Item> {
+			self.0.next()
+		}
+	}
+
+	impl ReadyTransactions for Ready {
+		fn report_invalid(&mut self, _tx: &Self::Item) {}
+	}
+
+	/// Transaction `nonce` of `sender`, in the order of priority of the pool.
+	fn tx(sender: u8, nonce: u8, arrival: u64) -> Arc<Tx> {
+		Arc::new(Tx {
+			hash: (sender as u64) << 8 | nonce as u64,
+			arrival,
+			requires: if nonce > 0 { vec![vec![sender, nonce - 1]] } else { vec![] },
+			provides: vec![vec![sender, nonce]],
+		})
+	}
+
+	fn select(selector: impl TransactionSelector<Tx>, ready: Vec<Arc<Tx>>) -> Vec<u64> {
+		selector.select(Box::new(Ready(ready.into_iter()))).map(|tx| tx.hash).collect()
+	}
+
+	#[test]
+	fn priority_keeps_the_order_of_the_pool() {
+		let ready = vec![tx(1, 0, 3), tx(2, 0, 1), tx(1, 1, 2)];
+		assert_eq!(select(PrioritySelector, ready), vec![0x100, 0x200, 0x101]);
+	}
+
+	#[test]
+	fn first_come_first_served_orders_by_arrival_after_dependencies() {
+		// The second transaction of sender 1 arrived before the first one.
+		let ready = vec![tx(1, 0, 3), tx(2, 0, 4), tx(1, 1, 1), tx(3, 0, 2)];
+		assert_eq!(select(FirstComeFirstServedSelector, ready), vec![0x300, 0x100, 0x101, 0x200]);
+	}
+
+	#[test]
+	fn round_robin_alternates_senders() {
+		let ready = vec![tx(1, 0, 0), tx(1, 1, 0), tx(1, 2, 0), tx(2, 0, 0), tx(2, 1, 0)];
+		assert_eq!(select(RoundRobinSelector, ready), vec![0x100, 0x200, 0x101, 0x201, 0x102]);
+	}
+
+	#[test]
+	fn transactions_depending_on_invalid_ones_are_skipped() {
+		let ready = vec![tx(1, 0, 1), tx(1, 1, 2), tx(1, 2, 3), tx(2, 0, 4)];
+		let mut selected = FirstComeFirstServedSelector.select(Box::new(Ready(ready.into_iter())));
+
+		let first = selected.next().unwrap();
+		assert_eq!(first.hash, 0x100);
+		selected.report_invalid(&first);
+		assert_eq!(selected.map(|tx| tx.hash).collect::<Vec<_>>(), vec![0x200]);
+	}
+}
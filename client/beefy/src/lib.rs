@@ -79,6 +79,7 @@ pub fn beefy_peers_set_config(
 	let mut cfg = sc_network::config::NonDefaultSetConfig::new(protocol_name, 1024 * 1024);
 
 	cfg.allow_non_reserved(25, 25);
+	cfg.set_bandwidth_priority(sc_network::config::BandwidthPriority::High);
 	cfg.add_fallback_names(beefy_protocol_name::LEGACY_NAMES.iter().map(|&n| n.into()).collect());
 	cfg
 }
@@ -29,7 +29,8 @@
 use codec::Encode;
 
 use sp_api::{
-	ApiExt, ApiRef, Core, ProvideRuntimeApi, StorageChanges, StorageProof, TransactionOutcome,
+	ApiError, ApiExt, ApiRef, Core, ProvideRuntimeApi, StorageChanges, StorageProof,
+	TransactionOutcome,
 };
 use sp_blockchain::{ApplyExtrinsicFailed, Error};
 use sp_core::ExecutionContext;
@@ -37,10 +38,13 @@ use sp_runtime::{
 	generic::BlockId,
 	legacy,
 	traits::{Block as BlockT, Hash, HashFor, Header as HeaderT, NumberFor, One},
-	Digest,
+	ApplyExtrinsicResult, Digest,
 };
 
-pub use sp_block_builder::BlockBuilder as BlockBuilderApi;
+pub use sp_block_builder::{
+	BlockBuilder as BlockBuilderApi, BlockResources, BlockResourcesApi, ExtrinsicClass,
+	ExtrinsicResources, PerClass,
+};
 
 use sc_client_api::backend;
 
@@ -137,6 +141,8 @@ pub struct BlockBuilder<'a, Block: BlockT, A: ProvideRuntimeApi<Block>, B> {
 	extrinsics: Vec<Block::Extrinsic>,
 	api: ApiRef<'a, A::Api>,
 	version: u32,
+	/// Whether the runtime provides the resources of the block.
+	has_resources_api: bool,
 	block_id: BlockId<Block>,
 	parent_hash: Block::Hash,
 	backend: &'a B,
@@ -188,12 +194,14 @@ where
 		let version = api
 			.api_version::<dyn BlockBuilderApi<Block>>(&block_id)?
 			.ok_or_else(|| Error::VersionInvalid("BlockBuilderApi".to_string()))?;
+		let has_resources_api = api.has_api::<dyn BlockResourcesApi<Block>>(&block_id)?;
 
 		Ok(Self {
 			parent_hash,
 			extrinsics: Vec::new(),
 			api,
 			version,
+			has_resources_api,
 			block_id,
 			backend,
 			estimated_header_size,
@@ -209,23 +217,7 @@ where
 		let version = self.version;
 
 		self.api.execute_in_transaction(|api| {
-			let res = if version < 6 {
-				#[allow(deprecated)]
-				api.apply_extrinsic_before_version_6_with_context(
-					block_id,
-					ExecutionContext::BlockConstruction,
-					xt.clone(),
-				)
-				.map(legacy::byte_sized_error::convert_to_latest)
-			} else {
-				api.apply_extrinsic_with_context(
-					block_id,
-					ExecutionContext::BlockConstruction,
-					xt.clone(),
-				)
-			};
-
-			match res {
+			match apply_extrinsic(api, version, block_id, xt.clone()) {
 				Ok(Ok(_)) => {
 					extrinsics.push(xt);
 					TransactionOutcome::Commit(Ok(()))
@@ -238,6 +230,38 @@ where
 		})
 	}
 
+	/// Push a bundle of extrinsics onto the block's list of extrinsics.
+	///
+	/// The extrinsics are executed in the given order. Either all of them are pushed, or none
+	/// if any of them can't be applied or fails to dispatch.
+	pub fn push_bundle(&mut self, xts: Vec<<Block as BlockT>::Extrinsic>) -> Result<(), Error> {
+		let block_id = &self.block_id;
+		let extrinsics = &mut self.extrinsics;
+		let version = self.version;
+
+		self.api.execute_in_transaction(|api| {
+			for xt in &xts {
+				match apply_extrinsic(api, version, block_id, xt.clone()) {
+					Ok(Ok(Ok(()))) => {},
+					Ok(Ok(Err(dispatch_error))) =>
+						return TransactionOutcome::Rollback(Err(ApplyExtrinsicFailed::Dispatch(
+							dispatch_error,
+						)
+						.into())),
+					Ok(Err(tx_validity)) =>
+						return TransactionOutcome::Rollback(Err(ApplyExtrinsicFailed::Validity(
+							tx_validity,
+						)
+						.into())),
+					Err(e) => return TransactionOutcome::Rollback(Err(Error::from(e))),
+				}
+			}
+
+			extrinsics.extend(xts);
+			TransactionOutcome::Commit(Ok(()))
+		})
+	}
+
 	/// Consume the builder to build a valid `Block` containing all pushed extrinsics.
 	///
 	/// Returns the build `Block`, the changes to the storage and an optional `StorageProof`
@@ -309,13 +333,86 @@ where
 	}
 }
 
+impl<'a, Block, A, B> BlockBuilder<'a, Block, A, B>
+where
+	Block: BlockT,
+	A: ProvideRuntimeApi<Block> + 'a,
+	A::Api: BlockBuilderApi<Block>
+		+ BlockResourcesApi<Block>
+		+ ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>,
+	B: backend::Backend<Block>,
+{
+	/// Get the resources consumed by the block in the current state, and the limits on them.
+	///
+	/// Returns `None` if the runtime doesn't provide them.
+	pub fn block_resources(&self) -> Result<Option<BlockResources>, Error> {
+		if !self.has_resources_api {
+			return Ok(None)
+		}
+
+		self.api
+			.block_resources_with_context(&self.block_id, ExecutionContext::BlockConstruction)
+			.map(Some)
+			.map_err(Into::into)
+	}
+
+	/// Get the resources the given extrinsic would consume when pushed.
+	///
+	/// Must only be called if [`Self::block_resources`] returns `Some(_)`.
+	pub fn extrinsic_resources(
+		&self,
+		xt: &<Block as BlockT>::Extrinsic,
+	) -> Result<ExtrinsicResources, Error> {
+		self.api
+			.extrinsic_resources_with_context(
+				&self.block_id,
+				ExecutionContext::BlockConstruction,
+				xt.clone(),
+			)
+			.map_err(Into::into)
+	}
+}
+
+/// Apply the extrinsic using the given version of the block builder runtime api.
+fn apply_extrinsic<Block: BlockT, Api: BlockBuilderApi<Block>>(
+	api: &Api,
+	version: u32,
+	block_id: &BlockId<Block>,
+	xt: <Block as BlockT>::Extrinsic,
+) -> Result<ApplyExtrinsicResult, ApiError> {
+	if version < 6 {
+		#[allow(deprecated)]
+		api.apply_extrinsic_before_version_6_with_context(
+			block_id,
+			ExecutionContext::BlockConstruction,
+			xt,
+		)
+		.map(legacy::byte_sized_error::convert_to_latest)
+	} else {
+		api.apply_extrinsic_with_context(block_id, ExecutionContext::BlockConstruction, xt)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use sp_blockchain::HeaderBackend;
 	use sp_core::Blake2Hasher;
 	use sp_state_machine::Backend;
-	use substrate_test_runtime_client::{DefaultTestClientBuilderExt, TestClientBuilderExt};
+	use substrate_test_runtime_client::{
+		runtime::{Extrinsic, Transfer},
+		AccountKeyring, DefaultTestClientBuilderExt, TestClientBuilderExt,
+	};
+
+	fn transfer(nonce: u64) -> Extrinsic {
+		Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 1,
+			nonce,
+		}
+		.into_signed_tx()
+	}
 
 	#[test]
 	fn block_building_storage_proof_does_not_include_runtime_by_default() {
@@ -348,4 +445,56 @@ mod tests {
 			.unwrap_err()
 			.contains("Database missing expected key"),);
 	}
+
+	#[test]
+	fn bundle_is_pushed_completely_or_not_at_all() {
+		let builder = substrate_test_runtime_client::TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = builder.build();
+
+		let mut block_builder = BlockBuilder::new(
+			&client,
+			client.info().best_hash,
+			client.info().best_number,
+			RecordProof::No,
+			Default::default(),
+			&*backend,
+		)
+		.unwrap();
+
+		// The second transfer has a nonce from the future, so the first one is reverted too.
+		assert!(block_builder.push_bundle(vec![transfer(0), transfer(2)]).is_err());
+		assert!(block_builder.extrinsics.is_empty());
+
+		block_builder.push_bundle(vec![transfer(0), transfer(1)]).unwrap();
+		assert_eq!(block_builder.extrinsics, vec![transfer(0), transfer(1)]);
+
+		let block = block_builder.build().unwrap().block;
+		assert_eq!(block.extrinsics.len(), 2);
+	}
+
+	#[test]
+	fn block_resources_follow_pushed_extrinsics() {
+		let builder = substrate_test_runtime_client::TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = builder.build();
+
+		let mut block_builder = BlockBuilder::new(
+			&client,
+			client.info().best_hash,
+			client.info().best_number,
+			RecordProof::No,
+			Default::default(),
+			&*backend,
+		)
+		.unwrap();
+
+		let mut resources = block_builder.block_resources().unwrap().unwrap();
+		let extrinsic_resources = block_builder.extrinsic_resources(&transfer(0)).unwrap();
+		assert_eq!(extrinsic_resources.length, transfer(0).encoded_size() as u32);
+
+		block_builder.push(transfer(0)).unwrap();
+		assert!(resources.consume(&extrinsic_resources));
+		assert_eq!(block_builder.block_resources().unwrap(), Some(resources));
+	}
 }
@@ -0,0 +1,53 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Database related CLI utilities
+
+use super::db_migrate_cmd::DbMigrateCmd;
+use crate::{arg_enums::Database, error, CliConfiguration, SharedParams};
+use sp_runtime::traits::Block as BlockT;
+
+/// Database utilities for the cli.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum DbSubcommand {
+	/// Copy the archive RocksDB database of the chain into a new ParityDb database.
+	Migrate(DbMigrateCmd),
+}
+
+impl DbSubcommand {
+	/// Run the db subcommands
+	pub fn run<B: BlockT>(&self, config: &sc_service::Configuration) -> error::Result<()> {
+		match self {
+			DbSubcommand::Migrate(cmd) => cmd.run::<B>(config),
+		}
+	}
+}
+
+impl CliConfiguration for DbSubcommand {
+	fn shared_params(&self) -> &SharedParams {
+		match self {
+			DbSubcommand::Migrate(cmd) => cmd.shared_params(),
+		}
+	}
+
+	fn database(&self) -> error::Result<Option<Database>> {
+		match self {
+			DbSubcommand::Migrate(cmd) => cmd.database(),
+		}
+	}
+}
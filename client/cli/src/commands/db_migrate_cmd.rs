@@ -0,0 +1,85 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{arg_enums::Database, error, CliConfiguration, SharedParams};
+use sp_runtime::traits::Block as BlockT;
+
+/// The `db migrate` command used to move an archive RocksDB database to ParityDb.
+///
+/// Migrating in the other direction is not supported: ParityDb only indexes the hashes of the
+/// trie nodes, while RocksDB needs their prefixed keys. The source database is left untouched,
+/// the node has to be started with `--database paritydb` afterwards.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct DbMigrateCmd {
+	/// Database backend to migrate from. Only `rocksdb` is supported.
+	#[clap(
+		long,
+		value_name = "DB",
+		ignore_case = true,
+		default_value = "rocksdb",
+		possible_values = ["rocksdb"],
+	)]
+	pub from: Database,
+
+	/// Database backend to migrate to. Only `paritydb` is supported.
+	#[clap(
+		long,
+		value_name = "DB",
+		ignore_case = true,
+		default_value = "paritydb",
+		possible_values = ["paritydb"],
+	)]
+	pub to: Database,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl DbMigrateCmd {
+	/// Run the `db migrate` command
+	pub fn run<B: BlockT>(&self, config: &sc_service::Configuration) -> error::Result<()> {
+		let config_dir = config
+			.base_path
+			.as_ref()
+			.ok_or_else(|| error::Error::Input("Cannot migrate without a base path".into()))?
+			.config_dir(config.chain_spec.id());
+		let target = self.database_config(&config_dir, 0, self.to, &config.role)?;
+
+		if config.database.path() == target.path() {
+			return Err(error::Error::Input(format!(
+				"Source and target database are the same: {:?}",
+				target.path()
+			)))
+		}
+
+		let stats = sc_client_db::migrate_database::<B>(&config.database, &target)?;
+		println!("Migrated {} database to {:?}: {}", config.database, target.path(), stats);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbMigrateCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database(&self) -> error::Result<Option<Database>> {
+		Ok(Some(self.from))
+	}
+}
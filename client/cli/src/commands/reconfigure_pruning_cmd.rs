@@ -0,0 +1,86 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error,
+	params::{DatabaseParams, PruningParams, SharedParams},
+	CliConfiguration,
+};
+use clap::Parser;
+use sc_client_db::BlockTimestamp;
+use sc_service::Configuration;
+use sp_runtime::traits::Block as BlockT;
+
+/// The `reconfigure-pruning` command used to change the state pruning mode of an existing
+/// database.
+///
+/// The states that don't fit into the new pruning window are removed, the blocks don't have to be
+/// imported again. An archive database can be switched to a pruning mode, a pruned database can't
+/// become an archive again.
+#[derive(Debug, Clone, Parser)]
+pub struct ReconfigurePruningCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl ReconfigurePruningCmd {
+	/// Run the reconfigure-pruning command
+	///
+	/// `block_timestamp` returns the time a block was authored at, it is needed to prune the state
+	/// by age.
+	pub fn run<B: BlockT>(
+		&self,
+		config: &Configuration,
+		block_timestamp: Option<BlockTimestamp<B>>,
+	) -> error::Result<()> {
+		let state_pruning = self.pruning_params.state_pruning()?.ok_or_else(|| {
+			error::Error::Input("The new state pruning mode has to be given with --pruning".into())
+		})?;
+
+		sc_client_db::reconfigure_pruning::<B>(
+			&config.database,
+			state_pruning.clone(),
+			block_timestamp,
+		)?;
+		println!("State pruning of {} database set to {:?}", config.database, state_pruning);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ReconfigurePruningCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -29,10 +29,14 @@ use clap::Parser;
 use regex::Regex;
 use sc_service::{
 	config::{BasePath, PrometheusConfig, TransactionPoolOptions},
-	ChainSpec, Role,
+	ChainSpec, Role, RpcPeerRateLimit, RpcRateLimit, RPC_RATE_LIMITED_METHODS,
 };
 use sc_telemetry::TelemetryEndpoints;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+	net::{IpAddr, Ipv4Addr, SocketAddr},
+	num::NonZeroU32,
+	path::PathBuf,
+};
 
 /// The `run` command used to run a node.
 #[derive(Debug, Clone, Parser)]
@@ -120,6 +124,45 @@ pub struct RunCmd {
 	#[clap(long)]
 	pub rpc_max_subscriptions_per_connection: Option<usize>,
 
+	/// Limit every rate limited RPC method to this many calls per minute.
+	///
+	/// The `state_*` and `childstate_*` methods are rate limited. The limits are shared by all the
+	/// clients of the HTTP and WS servers, see `--rpc-ip-rate-limit` and
+	/// `--rpc-connection-rate-limit` to bound the share of a single client. Default is no limit.
+	#[clap(long, value_name = "CALLS")]
+	pub rpc_rate_limit: Option<NonZeroU32>,
+
+	/// Limit a single RPC method to this many calls per minute, instead of `--rpc-rate-limit`.
+	///
+	/// The method has to be one of the rate limited methods. This flag can be passed multiple
+	/// times.
+	/// Expected format is 'METHOD=CALLS', e.g. `--rpc-method-rate-limit state_getKeysPaged=10`.
+	#[clap(long, value_name = "METHOD=CALLS", parse(try_from_str = parse_method_rate_limit))]
+	pub rpc_method_rate_limit: Vec<(String, NonZeroU32)>,
+
+	/// Limit the expensive RPC methods to this many calls per minute, in total.
+	///
+	/// Expensive methods are the ones iterating over the storage or executing the runtime, like
+	/// `state_getKeysPaged`, `state_queryStorage` or `state_call`. They are additionally subject
+	/// to their own limit. Default is no limit.
+	#[clap(long, value_name = "CALLS")]
+	pub rpc_expensive_rate_limit: Option<NonZeroU32>,
+
+	/// Limit the RPC requests from a single IP address to this many requests per minute.
+	///
+	/// A request is an HTTP request or a WebSocket message. The limit is shared by all the
+	/// connections from the address, to both the HTTP and WS servers. Requests over the limit are
+	/// answered with an error. Default is no limit.
+	#[clap(long, value_name = "REQUESTS")]
+	pub rpc_ip_rate_limit: Option<NonZeroU32>,
+
+	/// Limit the RPC requests on a single connection to this many requests per minute.
+	///
+	/// A request is an HTTP request or a WebSocket message. Requests over the limit are answered
+	/// with an error. Default is no limit.
+	#[clap(long, value_name = "REQUESTS")]
+	pub rpc_connection_rate_limit: Option<NonZeroU32>,
+
 	/// Expose Prometheus exporter on all interfaces.
 	///
 	/// Default is local.
@@ -476,12 +519,27 @@ impl CliConfiguration for RunCmd {
 		Ok(self.rpc_max_subscriptions_per_connection)
 	}
 
+	fn rpc_rate_limit(&self) -> Result<RpcRateLimit> {
+		Ok(RpcRateLimit {
+			method_limit: self.rpc_rate_limit,
+			method_limits: self.rpc_method_rate_limit.iter().cloned().collect(),
+			expensive_limit: self.rpc_expensive_rate_limit,
+		})
+	}
+
+	fn rpc_peer_rate_limit(&self) -> Result<RpcPeerRateLimit> {
+		Ok(RpcPeerRateLimit {
+			ip_limit: self.rpc_ip_rate_limit,
+			connection_limit: self.rpc_connection_rate_limit,
+		})
+	}
+
 	fn ws_max_out_buffer_capacity(&self) -> Result<Option<usize>> {
 		Ok(self.ws_max_out_buffer_capacity)
 	}
 
-	fn transaction_pool(&self) -> Result<TransactionPoolOptions> {
-		Ok(self.pool_config.transaction_pool())
+	fn transaction_pool(&self, config_dir: &PathBuf) -> Result<TransactionPoolOptions> {
+		Ok(self.pool_config.transaction_pool(config_dir))
 	}
 
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
@@ -587,6 +645,21 @@ fn parse_telemetry_endpoints(s: &str) -> std::result::Result<(String, u8), Telem
 	}
 }
 
+fn parse_method_rate_limit(s: &str) -> std::result::Result<(String, NonZeroU32), String> {
+	let (method, calls) = s
+		.split_once('=')
+		.ok_or_else(|| "Expected format is 'METHOD=CALLS'".to_string())?;
+	if !RPC_RATE_LIMITED_METHODS.contains(&method) {
+		return Err(format!(
+			"{} is not rate limited, expected one of: {}",
+			method,
+			RPC_RATE_LIMITED_METHODS.join(", ")
+		))
+	}
+	let calls = calls.parse().map_err(|e| format!("Invalid number of calls: {}", e))?;
+	Ok((method.to_string(), calls))
+}
+
 /// CORS setting
 ///
 /// The type is introduced to overcome `Option<Option<T>>` handling of `clap`.
@@ -637,6 +710,18 @@ mod tests {
 		assert!(is_node_name_valid("short name").is_ok());
 	}
 
+	#[test]
+	fn parses_method_rate_limit() {
+		assert_eq!(
+			parse_method_rate_limit("state_getKeysPaged=10"),
+			Ok(("state_getKeysPaged".to_string(), NonZeroU32::new(10).unwrap())),
+		);
+		assert!(parse_method_rate_limit("state_getKeysPaged").is_err());
+		assert!(parse_method_rate_limit("state_getKeysPaged=0").is_err());
+		assert!(parse_method_rate_limit("author_submitExtrinsic=10").is_err());
+		assert!(parse_method_rate_limit("state_getKeysPagedAt=10").is_err());
+	}
+
 	#[test]
 	fn tests_node_name_bad() {
 		assert!(is_node_name_valid(
@@ -31,7 +31,7 @@ use sc_service::{
 		NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
 		TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
 	},
-	ChainSpec, KeepBlocks, TracingReceiver,
+	ChainSpec, KeepBlocks, KeepJustifications, RpcPeerRateLimit, RpcRateLimit, TracingReceiver,
 };
 use sc_tracing::logging::LoggerBuilder;
 use std::{net::SocketAddr, path::PathBuf};
@@ -145,7 +145,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 	/// Get the transaction pool options
 	///
 	/// By default this is `TransactionPoolOptions::default()`.
-	fn transaction_pool(&self) -> Result<TransactionPoolOptions> {
+	fn transaction_pool(&self, _config_dir: &PathBuf) -> Result<TransactionPoolOptions> {
 		Ok(Default::default())
 	}
 
@@ -175,7 +175,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 				node_name,
 				node_key,
 				default_listen_port,
-			)
+			)?
 		} else {
 			NetworkConfiguration::new(node_name, client_id, node_key, Some(net_config_dir))
 		})
@@ -234,18 +234,12 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		})
 	}
 
-	/// Get the state cache size.
+	/// Get the trie cache maximum size.
 	///
-	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
-	fn state_cache_size(&self) -> Result<usize> {
-		Ok(self.import_params().map(|x| x.state_cache_size()).unwrap_or_default())
-	}
-
-	/// Get the state cache child ratio (if any).
-	///
-	/// By default this is `None`.
-	fn state_cache_child_ratio(&self) -> Result<Option<usize>> {
-		Ok(Default::default())
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `None`,
+	/// which disables the cache.
+	fn trie_cache_maximum_size(&self) -> Result<Option<usize>> {
+		Ok(self.import_params().and_then(|x| x.trie_cache_maximum_size()))
 	}
 
 	/// Get the state pruning mode.
@@ -268,6 +262,16 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			.unwrap_or_else(|| Ok(KeepBlocks::All))
 	}
 
+	/// Get the justification pruning mode.
+	///
+	/// By default this is retrieved from `pruning_params` if it is available. Otherwise its
+	/// `KeepJustifications::All`.
+	fn keep_justifications(&self) -> Result<KeepJustifications> {
+		self.pruning_params()
+			.map(|x| x.keep_justifications())
+			.unwrap_or_else(|| Ok(KeepJustifications::All))
+	}
+
 	/// Get the chain ID (string).
 	///
 	/// By default this is retrieved from `SharedParams`.
@@ -375,6 +379,16 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(None)
 	}
 
+	/// Get the rate limits of the RPC calls.
+	fn rpc_rate_limit(&self) -> Result<RpcRateLimit> {
+		Ok(Default::default())
+	}
+
+	/// Get the rate limits of the RPC requests of every IP address and connection.
+	fn rpc_peer_rate_limit(&self) -> Result<RpcPeerRateLimit> {
+		Ok(Default::default())
+	}
+
 	/// Get maximum WS output buffer capacity.
 	fn ws_max_out_buffer_capacity(&self) -> Result<Option<usize>> {
 		Ok(None)
@@ -523,7 +537,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			tokio_handle,
-			transaction_pool: self.transaction_pool()?,
+			transaction_pool: self.transaction_pool(&config_dir)?,
 			network: self.network_config(
 				&chain_spec,
 				is_dev,
@@ -537,10 +551,10 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			keystore_remote,
 			keystore,
 			database: self.database_config(&config_dir, database_cache_size, database, &role)?,
-			state_cache_size: self.state_cache_size()?,
-			state_cache_child_ratio: self.state_cache_child_ratio()?,
+			trie_cache_maximum_size: self.trie_cache_maximum_size()?,
 			state_pruning: self.state_pruning()?,
 			keep_blocks: self.keep_blocks()?,
+			keep_justifications: self.keep_justifications()?,
 			wasm_method: self.wasm_method()?,
 			wasm_runtime_overrides: self.wasm_runtime_overrides(),
 			execution_strategies: self.execution_strategies(is_dev, is_validator)?,
@@ -555,6 +569,8 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			rpc_max_response_size: self.rpc_max_response_size()?,
 			rpc_id_provider: None,
 			rpc_max_subs_per_conn: self.rpc_max_subscriptions_per_connection()?,
+			rpc_rate_limit: self.rpc_rate_limit()?,
+			rpc_peer_rate_limit: self.rpc_peer_rate_limit()?,
 			ws_max_out_buffer_capacity: self.ws_max_out_buffer_capacity()?,
 			prometheus_config: self
 				.prometheus_config(DCV::prometheus_listen_port(), &chain_spec)?,
@@ -94,15 +94,22 @@ pub struct ImportParams {
 	#[clap(flatten)]
 	pub execution_strategies: ExecutionStrategiesParams,
 
-	/// Specify the state cache size.
-	#[clap(long, value_name = "Bytes", default_value = "67108864")]
-	pub state_cache_size: usize,
+	/// Specify the maximum size of the trie cache in bytes.
+	///
+	/// The trie cache is shared between all states and caches trie nodes as well as values.
+	/// Providing `0` disables the cache.
+	#[clap(long, alias = "state-cache-size", value_name = "Bytes", default_value = "67108864")]
+	pub trie_cache_size: usize,
 }
 
 impl ImportParams {
-	/// Specify the state cache size.
-	pub fn state_cache_size(&self) -> usize {
-		self.state_cache_size
+	/// Specify the trie cache maximum size.
+	pub fn trie_cache_maximum_size(&self) -> Option<usize> {
+		if self.trie_cache_size == 0 {
+			None
+		} else {
+			Some(self.trie_cache_size)
+		}
 	}
 
 	/// Get the WASM execution method from the parameters
@@ -16,11 +16,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_enums::SyncMode, params::node_key_params::NodeKeyParams};
+use crate::{arg_enums::SyncMode, error, params::node_key_params::NodeKeyParams};
 use clap::Args;
 use sc_network::{
 	config::{
-		NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig,
+		BandwidthLimits, NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig,
+		TransportConfig, WssCertificate,
 	},
 	multiaddr::Protocol,
 };
@@ -28,7 +29,13 @@ use sc_service::{
 	config::{Multiaddr, MultiaddrWithPeerId},
 	ChainSpec, ChainType,
 };
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, num::NonZeroU64, path::PathBuf};
+
+/// Path of the peer store, relative to the network configuration directory.
+const PEER_STORE_PATH: &str = "peers";
+
+/// Path of the state sync journal, relative to the network configuration directory.
+const STATE_SYNC_JOURNAL_PATH: &str = "state-sync";
 
 /// Parameters used to create the network configuration.
 #[derive(Debug, Clone, Args)]
@@ -69,6 +76,17 @@ pub struct NetworkParams {
 	#[clap(long, value_name = "PORT", conflicts_with_all = &[ "listen-addr" ])]
 	pub port: Option<u16>,
 
+	/// PEM file of the certificate chain presented on the `/wss` listen addresses.
+	///
+	/// Allows browsers to connect to the node with secure WebSockets without a reverse proxy. The
+	/// certificate must be valid for the domain name the browsers connect to.
+	#[clap(long, value_name = "PATH", requires = "wss-private-key")]
+	pub wss_certificate: Option<PathBuf>,
+
+	/// PEM file of the private key of the certificate given with `--wss-certificate`.
+	#[clap(long, value_name = "PATH", requires = "wss-certificate")]
+	pub wss_private_key: Option<PathBuf>,
+
 	/// Always forbid connecting to private IPv4 addresses (as specified in
 	/// [RFC1918](https://tools.ietf.org/html/rfc1918)), unless the address was passed with
 	/// `--reserved-nodes` or `--bootnodes`. Enabled by default for chains marked as "live" in
@@ -100,6 +118,14 @@ pub struct NetworkParams {
 	#[clap(long)]
 	pub no_mdns: bool,
 
+	/// Disable the peer store.
+	///
+	/// By default, the addresses and reputation of the known peers are kept in the network
+	/// configuration directory, so that they can be reconnected to, or kept banned, after a
+	/// restart. This disables it.
+	#[clap(long)]
+	pub no_peer_store: bool,
+
 	/// Maximum number of peers from which to ask for the same blocks in parallel.
 	///
 	/// This allows downloading announced blocks from multiple peers. Decrease to save
@@ -145,6 +171,69 @@ pub struct NetworkParams {
 		verbatim_doc_comment
 	)]
 	pub sync: SyncMode,
+
+	/// Don't download the block history after a warp or fast sync.
+	///
+	/// By default, the blocks below the one the state was downloaded at are downloaded in the
+	/// background, so that the node can serve them. This disables it.
+	#[clap(long)]
+	pub no_gap_sync: bool,
+
+	/// Maximum upload rate of the node, in KiB per second.
+	///
+	/// Limits the traffic of the notification and request-response protocols. The finality votes
+	/// are never delayed, and the blocks and state served to other nodes only use the bandwidth
+	/// left by the other protocols.
+	#[clap(long, value_name = "KIB_PER_SEC")]
+	pub max_upload_rate: Option<NonZeroU64>,
+
+	/// Maximum download rate of the node, in KiB per second.
+	///
+	/// Limits the traffic of the notification and request-response protocols. The finality votes
+	/// are never delayed, and the blocks and state downloaded from other nodes only use the
+	/// bandwidth left by the other protocols.
+	#[clap(long, value_name = "KIB_PER_SEC")]
+	pub max_download_rate: Option<NonZeroU64>,
+
+	/// Limit the bandwidth of a single notification or request-response protocol.
+	///
+	/// Takes the name of the protocol, followed by `=` and the maximum upload and download rates
+	/// in KiB per second separated by `:`. A rate left empty isn't limited. For example
+	/// `/dot/sync/2=512:` limits the upload of the block request protocol to 512 KiB per second.
+	/// Can be given multiple times.
+	#[clap(
+		long,
+		value_name = "PROTOCOL=UP:DOWN",
+		parse(try_from_str = parse_protocol_rate_limit),
+		multiple_occurrences(true)
+	)]
+	pub protocol_rate_limit: Vec<(String, BandwidthLimits)>,
+}
+
+/// Parses a rate in KiB per second into a rate in bytes per second, `None` if empty.
+fn parse_rate(s: &str) -> Result<Option<NonZeroU64>, String> {
+	if s.is_empty() {
+		return Ok(None)
+	}
+	let rate: NonZeroU64 = s.parse().map_err(|e| format!("Invalid rate `{}`: {}", s, e))?;
+	rate.checked_mul(NonZeroU64::new(1024).expect("1024 != 0; qed"))
+		.map(Some)
+		.ok_or_else(|| format!("Rate `{}` is too large", s))
+}
+
+/// Parses the value of `--protocol-rate-limit`.
+fn parse_protocol_rate_limit(s: &str) -> Result<(String, BandwidthLimits), String> {
+	let (protocol, rates) = s
+		.rsplit_once('=')
+		.ok_or_else(|| format!("Expected `PROTOCOL=UP:DOWN`, got `{}`", s))?;
+	let (upload, download) = rates
+		.split_once(':')
+		.ok_or_else(|| format!("Expected `UP:DOWN`, got `{}`", rates))?;
+	let limits = BandwidthLimits {
+		max_upload_rate: parse_rate(upload)?,
+		max_download_rate: parse_rate(download)?,
+	};
+	Ok((protocol.to_string(), limits))
 }
 
 impl NetworkParams {
@@ -159,7 +248,7 @@ impl NetworkParams {
 		node_name: &str,
 		node_key: NodeKeyConfig,
 		default_listen_port: u16,
-	) -> NetworkConfiguration {
+	) -> error::Result<NetworkConfiguration> {
 		let port = self.port.unwrap_or(default_listen_port);
 
 		let listen_addresses = if self.listen_addr.is_empty() {
@@ -208,9 +297,37 @@ impl NetworkParams {
 				is_dev || matches!(chain_type, ChainType::Local | ChainType::Development),
 		};
 
-		NetworkConfiguration {
+		let peer_store_path = if self.no_peer_store {
+			None
+		} else {
+			net_config_path.as_ref().map(|path| path.join(PEER_STORE_PATH))
+		};
+		let state_sync_journal_path =
+			net_config_path.as_ref().map(|path| path.join(STATE_SYNC_JOURNAL_PATH));
+
+		let wss_certificate = match (&self.wss_certificate, &self.wss_private_key) {
+			(Some(certificate_chain), Some(private_key)) =>
+				Some(WssCertificate::from_pem_files(certificate_chain, private_key).map_err(
+					|e| error::Error::Input(format!("Invalid secure WebSocket certificate: {}", e)),
+				)?),
+			_ => None,
+		};
+
+		let kib = NonZeroU64::new(1024).expect("1024 != 0; qed");
+		let bandwidth_limits = BandwidthLimits {
+			max_upload_rate: self.max_upload_rate.and_then(|rate| rate.checked_mul(kib)),
+			max_download_rate: self.max_download_rate.and_then(|rate| rate.checked_mul(kib)),
+		};
+		let protocol_bandwidth_limits = self
+			.protocol_rate_limit
+			.iter()
+			.map(|(protocol, limits)| (protocol.clone().into(), limits.clone()))
+			.collect();
+
+		Ok(NetworkConfiguration {
 			boot_nodes,
 			net_config_path,
+			peer_store_path,
 			default_peers_set: SetConfig {
 				in_peers: self.in_peers + self.in_peers_light,
 				out_peers: self.out_peers,
@@ -233,6 +350,8 @@ impl NetworkParams {
 				enable_mdns: !is_dev && !self.no_mdns,
 				allow_private_ipv4,
 			},
+			wss_certificate,
+			wss_trusted_certificates: Vec::new(),
 			max_parallel_downloads: self.max_parallel_downloads,
 			enable_dht_random_walk: !self.reserved_only,
 			allow_non_globals_in_dht,
@@ -240,7 +359,11 @@ impl NetworkParams {
 			yamux_window_size: None,
 			ipfs_server: self.ipfs_server,
 			sync_mode: self.sync.into(),
-		}
+			enable_gap_sync: !self.no_gap_sync,
+			state_sync_journal_path,
+			bandwidth_limits,
+			protocol_bandwidth_limits,
+		})
 	}
 }
 
@@ -288,6 +411,58 @@ mod tests {
 		assert_eq!(expected, params.network_params.reserved_nodes);
 	}
 
+	#[test]
+	fn protocol_rate_limits() {
+		let params = Cli::try_parse_from([
+			"",
+			"--protocol-rate-limit",
+			"/dot/sync/2=512:",
+			"--protocol-rate-limit",
+			"/dot/state/2=:1024",
+		])
+		.expect("Parses network params");
+
+		assert_eq!(
+			params.network_params.protocol_rate_limit,
+			vec![
+				(
+					"/dot/sync/2".to_string(),
+					BandwidthLimits {
+						max_upload_rate: NonZeroU64::new(512 * 1024),
+						max_download_rate: None,
+					}
+				),
+				(
+					"/dot/state/2".to_string(),
+					BandwidthLimits {
+						max_upload_rate: None,
+						max_download_rate: NonZeroU64::new(1024 * 1024),
+					}
+				),
+			]
+		);
+
+		assert!(Cli::try_parse_from(["", "--protocol-rate-limit", "/dot/sync/2=512"]).is_err());
+		assert!(Cli::try_parse_from(["", "--protocol-rate-limit", "/dot/sync/2=0:"]).is_err());
+	}
+
+	#[test]
+	fn wss_certificate_requires_private_key() {
+		assert!(Cli::try_parse_from(["", "--wss-certificate", "cert.pem"]).is_err());
+		assert!(Cli::try_parse_from(["", "--wss-private-key", "key.pem"]).is_err());
+
+		let params = Cli::try_parse_from([
+			"",
+			"--wss-certificate",
+			"cert.pem",
+			"--wss-private-key",
+			"key.pem",
+		])
+		.expect("Parses network params");
+		assert_eq!(params.network_params.wss_certificate, Some(PathBuf::from("cert.pem")));
+		assert_eq!(params.network_params.wss_private_key, Some(PathBuf::from("key.pem")));
+	}
+
 	#[test]
 	fn sync_ingores_case() {
 		let params = Cli::try_parse_from(["", "--sync", "wArP"]).expect("Parses network params");
@@ -18,16 +18,26 @@
 
 use crate::error;
 use clap::Args;
-use sc_service::{KeepBlocks, PruningMode};
+use sc_service::{KeepBlocks, KeepJustifications, PruningMode};
+use std::time::Duration;
 
 /// Parameters to define the pruning mode
 #[derive(Debug, Clone, PartialEq, Args)]
 pub struct PruningParams {
-	/// Specify the state pruning mode, a number of blocks to keep or 'archive'.
+	/// Specify the state pruning mode, a number of blocks to keep, a maximum age, a maximum
+	/// number of inserted bytes or 'archive'.
 	///
 	/// Default is to keep only the last 256 blocks,
 	/// otherwise, the state can be kept for all of the blocks (i.e 'archive'),
 	/// or for all of the canonical blocks (i.e 'archive-canonical').
+	///
+	/// A maximum age keeps the states of the blocks that are at most that old according to the
+	/// time they were authored at, e.g. '7d', '12h', '30m' or '90s'. The chain has to tell the
+	/// node when a block was authored. A maximum number of inserted bytes, e.g. 'inserted:20GiB'
+	/// or 'inserted:10MiB', keeps as many recent states as the trie nodes inserted by their blocks
+	/// add up to. This does not bound the size of the database on disk: nodes shared with older
+	/// states are counted again, removed keys and the overhead of the database are not counted,
+	/// and the database may compress the nodes.
 	#[clap(long, value_name = "PRUNING_MODE")]
 	pub pruning: Option<String>,
 	/// Specify the number of finalized blocks to keep in the database.
@@ -37,6 +47,17 @@ pub struct PruningParams {
 	/// NOTE: only finalized blocks are subject for removal!
 	#[clap(long, value_name = "COUNT")]
 	pub keep_blocks: Option<u32>,
+	/// Specify the justifications to keep in the database, a number of finalized blocks or
+	/// 'boundaries'.
+	///
+	/// Default is to keep all justifications. With 'boundaries' only the justifications of the
+	/// blocks at era or session boundaries are kept, which is enough to serve warp sync proofs,
+	/// and the one of the last finalized block. A number of blocks keeps the justifications at
+	/// the boundaries as well.
+	///
+	/// NOTE: only finalized blocks are subject for removal!
+	#[clap(long, value_name = "COUNT | boundaries")]
+	pub keep_justifications: Option<String>,
 }
 
 impl PruningParams {
@@ -46,10 +67,18 @@ impl PruningParams {
 			.as_ref()
 			.map(|s| match s.as_str() {
 				"archive" => Ok(PruningMode::ArchiveAll),
-				bc => bc
-					.parse()
-					.map_err(|_| error::Error::Input("Invalid pruning mode specified".to_string()))
-					.map(PruningMode::keep_blocks),
+				mode =>
+					if let Ok(blocks) = mode.parse() {
+						Ok(PruningMode::keep_blocks(blocks))
+					} else if let Some(max_age) = parse_age(mode) {
+						Ok(PruningMode::keep_age(max_age))
+					} else if let Some(max_inserted_bytes) =
+						mode.strip_prefix("inserted:").and_then(parse_bytes)
+					{
+						Ok(PruningMode::keep_inserted_bytes(max_inserted_bytes))
+					} else {
+						Err(error::Error::Input("Invalid pruning mode specified".to_string()))
+					},
 			})
 			.transpose()
 	}
@@ -61,4 +90,98 @@ impl PruningParams {
 			None => KeepBlocks::All,
 		})
 	}
+
+	/// Get the justification pruning value from the parameters
+	pub fn keep_justifications(&self) -> error::Result<KeepJustifications> {
+		match self.keep_justifications.as_deref() {
+			None => Ok(KeepJustifications::All),
+			Some("boundaries") => Ok(KeepJustifications::Boundaries),
+			Some(count) => count.parse().map(KeepJustifications::Some).map_err(|_| {
+				error::Error::Input("Invalid justification pruning mode specified".to_string())
+			}),
+		}
+	}
+}
+
+/// Parse a duration like `7d`, `12h`, `30m` or `90s`.
+fn parse_age(s: &str) -> Option<Duration> {
+	let unit_secs = match s.chars().last()? {
+		'd' => 24 * 60 * 60,
+		'h' => 60 * 60,
+		'm' => 60,
+		's' => 1,
+		_ => return None,
+	};
+	let value: u64 = s[..s.len() - 1].parse().ok()?;
+	Some(Duration::from_secs(value.checked_mul(unit_secs)?))
+}
+
+/// Parse a number of bytes like `10MiB` or `500GiB`.
+fn parse_bytes(s: &str) -> Option<u64> {
+	let (value, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+	let unit_bytes: u64 = match unit {
+		"KiB" => 1 << 10,
+		"MiB" => 1 << 20,
+		"GiB" => 1 << 30,
+		"TiB" => 1 << 40,
+		_ => return None,
+	};
+	value.parse::<u64>().ok()?.checked_mul(unit_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn state_pruning(pruning: &str) -> error::Result<Option<PruningMode>> {
+		PruningParams {
+			pruning: Some(pruning.into()),
+			keep_blocks: None,
+			keep_justifications: None,
+		}
+		.state_pruning()
+	}
+
+	fn keep_justifications(keep: &str) -> error::Result<KeepJustifications> {
+		PruningParams { pruning: None, keep_blocks: None, keep_justifications: Some(keep.into()) }
+			.keep_justifications()
+	}
+
+	#[test]
+	fn parses_state_pruning_modes() {
+		assert_eq!(state_pruning("archive").unwrap(), Some(PruningMode::ArchiveAll));
+		assert_eq!(state_pruning("1000").unwrap(), Some(PruningMode::keep_blocks(1000)));
+		assert_eq!(
+			state_pruning("7d").unwrap(),
+			Some(PruningMode::keep_age(Duration::from_secs(7 * 24 * 60 * 60)))
+		);
+		assert_eq!(
+			state_pruning("30m").unwrap(),
+			Some(PruningMode::keep_age(Duration::from_secs(30 * 60)))
+		);
+		assert_eq!(
+			state_pruning("inserted:10MiB").unwrap(),
+			Some(PruningMode::keep_inserted_bytes(10 * 1024 * 1024))
+		);
+
+		for invalid in ["", "d", "7w", "-1", "10MiB", "inserted:10MB", "inserted:GiB", "1.5d"] {
+			assert!(state_pruning(invalid).is_err(), "{} should be rejected", invalid);
+		}
+	}
+
+	#[test]
+	fn parses_justification_pruning_modes() {
+		assert_eq!(
+			PruningParams { pruning: None, keep_blocks: None, keep_justifications: None }
+				.keep_justifications()
+				.unwrap(),
+			KeepJustifications::All,
+		);
+		assert_eq!(keep_justifications("1000").unwrap(), KeepJustifications::Some(1000));
+		assert_eq!(keep_justifications("boundaries").unwrap(), KeepJustifications::Boundaries);
+
+		for invalid in ["", "all", "-1", "boundary"] {
+			assert!(keep_justifications(invalid).is_err(), "{} should be rejected", invalid);
+		}
+	}
 }
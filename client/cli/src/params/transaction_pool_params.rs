@@ -18,6 +18,11 @@
 
 use clap::Args;
 use sc_service::config::TransactionPoolOptions;
+use sp_runtime::Percent;
+use std::path::Path;
+
+/// Path of the transaction pool journal, relative to the config directory.
+const JOURNAL_PATH: &str = "txpool/journal";
 
 /// Parameters used to create the pool configuration.
 #[derive(Debug, Clone, Args)]
@@ -29,11 +34,43 @@ pub struct TransactionPoolParams {
 	/// Maximum number of kilobytes of all transactions stored in the pool.
 	#[clap(long, value_name = "COUNT", default_value = "20480")]
 	pub pool_kbytes: usize,
+
+	/// Maximum number of transactions of a single sender in the transaction pool.
+	///
+	/// Transactions that depend on each other, like the consecutive nonces of an account,
+	/// are counted as coming from the same sender.
+	#[clap(long, value_name = "COUNT")]
+	pub pool_sender_limit: Option<usize>,
+
+	/// Percentage of the transaction pool that transactions received from the network or
+	/// through RPC may take.
+	#[clap(long, value_name = "PERCENT", default_value = "100")]
+	pub pool_external_quota: u8,
+
+	/// Minimum increase of the priority, in percent, for a transaction to replace another one
+	/// in the transaction pool.
+	#[clap(long, value_name = "PERCENT", default_value = "10")]
+	pub pool_replacement_bump: u8,
+
+	/// Keep the transactions and bans of the pool in a journal and import them again after a
+	/// restart.
+	///
+	/// The transactions are validated again while the node starts, before it joins the network.
+	#[clap(long)]
+	pub pool_journal: bool,
+
+	/// Report the dispatch results and fees of included transactions to the subscribers of
+	/// `author_submitAndWatchExtrinsic`.
+	///
+	/// This requires a runtime call for every block that includes a watched transaction, and
+	/// decoding the metadata of the runtime to name the errors of failed transactions.
+	#[clap(long)]
+	pub pool_inclusion_results: bool,
 }
 
 impl TransactionPoolParams {
 	/// Fill the given `PoolConfiguration` by looking at the cli parameters.
-	pub fn transaction_pool(&self) -> TransactionPoolOptions {
+	pub fn transaction_pool(&self, config_dir: &Path) -> TransactionPoolOptions {
 		let mut opts = TransactionPoolOptions::default();
 
 		// ready queue
@@ -45,6 +82,16 @@ impl TransactionPoolParams {
 		opts.future.count = self.pool_limit / factor;
 		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
 
+		opts.sender_limit = self.pool_sender_limit;
+		opts.source_quotas.external = Percent::from_percent(self.pool_external_quota);
+		opts.replacement_bump = Percent::from_percent(self.pool_replacement_bump);
+
+		if self.pool_journal {
+			opts.journal = Some(config_dir.join(JOURNAL_PATH));
+		}
+
+		opts.inclusion_results = self.pool_inclusion_results;
+
 		opts
 	}
 }
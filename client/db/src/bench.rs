@@ -24,7 +24,7 @@ use std::{
 	sync::Arc,
 };
 
-use crate::storage_cache::{new_shared_cache, CachingState, SharedCache};
+use crate::{record_stats_state::RecordStatsState, stats::StateUsageStats};
 use hash_db::{Hasher, Prefix};
 use kvdb::{DBTransaction, KeyValueDB};
 use linked_hash_map::LinkedHashMap;
@@ -45,7 +45,7 @@ use sp_trie::{prefixed_key, MemoryDB};
 type DbState<B> =
 	sp_state_machine::TrieBackend<Arc<dyn sp_state_machine::Storage<HashFor<B>>>, HashFor<B>>;
 
-type State<B> = CachingState<DbState<B>, B>;
+type State<B> = RecordStatsState<DbState<B>, B>;
 
 struct StorageDb<Block: BlockT> {
 	db: Arc<dyn KeyValueDB>,
@@ -82,7 +82,6 @@ pub struct BenchmarkingState<B: BlockT> {
 	db: Cell<Option<Arc<dyn KeyValueDB>>>,
 	genesis: HashMap<Vec<u8>, (Vec<u8>, i32)>,
 	record: Cell<Vec<Vec<u8>>>,
-	shared_cache: SharedCache<B>, // shared cache is always empty
 	/// Key tracker for keys in the main trie.
 	/// We track the total number of reads and writes to these keys,
 	/// not de-duplicated for repeats.
@@ -118,7 +117,6 @@ impl<B: BlockT> BenchmarkingState<B> {
 			genesis: Default::default(),
 			genesis_root: Default::default(),
 			record: Default::default(),
-			shared_cache: new_shared_cache(0, (1, 10)),
 			main_key_tracker: Default::default(),
 			child_key_tracker: Default::default(),
 			whitelist: Default::default(),
@@ -167,8 +165,8 @@ impl<B: BlockT> BenchmarkingState<B> {
 		});
 		*self.state.borrow_mut() = Some(State::new(
 			DbState::<B>::new(storage_db, self.root.get()),
-			self.shared_cache.clone(),
 			None,
+			Arc::new(StateUsageStats::new()),
 		));
 		Ok(())
 	}
@@ -33,9 +33,11 @@ pub mod offchain;
 pub mod bench;
 
 mod children;
+mod migration;
 mod parity_db;
+mod reconfigure;
+mod record_stats_state;
 mod stats;
-mod storage_cache;
 #[cfg(any(feature = "rocksdb", test))]
 mod upgrade;
 mod utils;
@@ -51,8 +53,8 @@ use std::{
 };
 
 use crate::{
+	record_stats_state::RecordStatsState,
 	stats::StateUsageStats,
-	storage_cache::{new_shared_cache, CachingState, SharedCache, SyncingCachingState},
 	utils::{meta_keys, read_db, read_meta, DatabaseType, Meta},
 };
 use codec::{Decode, Encode};
@@ -77,8 +79,8 @@ use sp_database::Transaction;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{
-		Block as BlockT, Hash, HashFor, Header as HeaderT, NumberFor, One, SaturatedConversion,
-		Zero,
+		AtLeast32BitUnsigned, Block as BlockT, Hash, HashFor, Header as HeaderT, NumberFor, One,
+		SaturatedConversion, Zero,
 	},
 	Justification, Justifications, StateVersion, Storage,
 };
@@ -86,19 +88,18 @@ use sp_state_machine::{
 	backend::Backend as StateBackend, ChildStorageCollection, DBValue, IndexOperation,
 	OffchainChangesCollection, StateMachineStats, StorageCollection, UsageInfo as StateUsageInfo,
 };
-use sp_trie::{prefixed_key, MemoryDB, PrefixedMemoryDB};
+use sp_trie::{cache::SharedTrieCache, prefixed_key, MemoryDB, PrefixedMemoryDB};
 
 // Re-export the Database trait so that one can pass an implementation of it.
 pub use sc_state_db::PruningMode;
 pub use sp_database::Database;
 
 pub use bench::BenchmarkingState;
+pub use migration::{migrate_database, MigrationStats};
+pub use reconfigure::reconfigure_pruning;
 
 const CACHE_HEADERS: usize = 8;
 
-/// Default value for storage cache child ratio.
-const DEFAULT_CHILD_RATIO: (usize, usize) = (1, 10);
-
 /// DB-backed patricia trie state, transaction type is an overlay of changes to commit.
 pub type DbState<B> =
 	sp_state_machine::TrieBackend<Arc<dyn sp_state_machine::Storage<HashFor<B>>>, HashFor<B>>;
@@ -109,6 +110,15 @@ const DB_HASH_LEN: usize = 32;
 /// Hash type that this backend uses for the database.
 pub type DbHash = sp_core::H256;
 
+/// Returns the time the block with the given header was authored at, in milliseconds since the
+/// UNIX epoch.
+///
+/// The database knows nothing about the runtime or the consensus of the chain, the time is
+/// usually derived from the consensus digest of the header. It is needed by
+/// [`PruningMode::ConstrainedAge`].
+pub type BlockTimestamp<Block> =
+	Arc<dyn Fn(&<Block as BlockT>::Header) -> Option<u64> + Send + Sync>;
+
 /// An extrinsic entry in the database.
 #[derive(Debug, Encode, Decode)]
 enum DbExtrinsic<B: BlockT> {
@@ -289,10 +299,10 @@ impl<B: BlockT> StateBackend<HashFor<B>> for RefTrackingState<B> {
 
 /// Database settings.
 pub struct DatabaseSettings {
-	/// State cache size.
-	pub state_cache_size: usize,
-	/// Ratio of cache size dedicated to child tries.
-	pub state_cache_child_ratio: Option<(usize, usize)>,
+	/// The maximum trie cache size in bytes.
+	///
+	/// If `None` is given, the cache is disabled.
+	pub trie_cache_maximum_size: Option<usize>,
 	/// Requested state pruning mode.
 	pub state_pruning: Option<PruningMode>,
 	/// Where to find the database.
@@ -301,6 +311,10 @@ pub struct DatabaseSettings {
 	///
 	/// NOTE: only finalized blocks are subject for removal!
 	pub keep_blocks: KeepBlocks,
+	/// Justification pruning mode.
+	///
+	/// NOTE: only finalized blocks are subject for removal!
+	pub keep_justifications: KeepJustifications,
 }
 
 /// Block pruning settings.
@@ -312,6 +326,64 @@ pub enum KeepBlocks {
 	Some(u32),
 }
 
+impl KeepBlocks {
+	/// Returns the number of the oldest finalized block whose body is kept, `None` if bodies are
+	/// never pruned.
+	fn first_kept<N: AtLeast32BitUnsigned>(&self, finalized: N) -> Option<N> {
+		match *self {
+			KeepBlocks::All => None,
+			KeepBlocks::Some(keep) => Some(first_kept(finalized, keep)),
+		}
+	}
+}
+
+/// Justification pruning settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepJustifications {
+	/// Keep the justifications of all blocks.
+	All,
+	/// Keep the justifications of the N recent finalized blocks and of the blocks at era or
+	/// session boundaries, like [`KeepJustifications::Boundaries`] does.
+	Some(u32),
+	/// Keep the justification of the last finalized block and of the blocks at era or session
+	/// boundaries.
+	///
+	/// A block is at a boundary if its header contains a consensus log, e.g. an authority set
+	/// change or the announcement of the next epoch. These are the justifications warp sync
+	/// proofs are made of.
+	Boundaries,
+}
+
+impl KeepJustifications {
+	/// Returns the number of the oldest finalized block whose justification is kept, `None` if
+	/// justifications are never pruned.
+	fn first_kept<N: AtLeast32BitUnsigned>(&self, finalized: N) -> Option<N> {
+		match *self {
+			KeepJustifications::All => None,
+			KeepJustifications::Some(keep) => Some(first_kept(finalized, keep)),
+			KeepJustifications::Boundaries => Some(finalized),
+		}
+	}
+
+	/// Whether the justifications of the blocks at boundaries are kept when they are older than
+	/// [`KeepJustifications::first_kept`]. They are needed to serve warp sync proofs.
+	fn keeps_boundaries(&self) -> bool {
+		*self != KeepJustifications::All
+	}
+}
+
+/// Returns the number of the oldest block kept when the `keep` most recent finalized blocks are
+/// kept. The last finalized block is always kept.
+fn first_kept<N: AtLeast32BitUnsigned>(finalized: N, keep: u32) -> N {
+	(finalized + One::one()).saturating_sub(std::cmp::max(keep, 1).into())
+}
+
+/// Whether the justification of the block with the given `header` is kept by
+/// [`KeepJustifications::Some`] and [`KeepJustifications::Boundaries`].
+fn is_boundary_block<Header: HeaderT>(header: &Header) -> bool {
+	header.digest().logs().iter().any(|log| log.as_consensus().is_some())
+}
+
 /// Where to find the database..
 #[derive(Debug, Clone)]
 pub enum DatabaseSource {
@@ -462,10 +534,16 @@ pub struct BlockchainDb<Block: BlockT> {
 	leaves: RwLock<LeafSet<Block::Hash, NumberFor<Block>>>,
 	header_metadata_cache: Arc<HeaderMetadataCache<Block>>,
 	header_cache: Mutex<LinkedHashMap<Block::Hash, Option<Block::Header>>>,
+	keep_blocks: KeepBlocks,
+	keep_justifications: KeepJustifications,
 }
 
 impl<Block: BlockT> BlockchainDb<Block> {
-	fn new(db: Arc<dyn Database<DbHash>>) -> ClientResult<Self> {
+	fn new(
+		db: Arc<dyn Database<DbHash>>,
+		keep_blocks: KeepBlocks,
+		keep_justifications: KeepJustifications,
+	) -> ClientResult<Self> {
 		let meta = read_meta::<Block>(&*db, columns::HEADER)?;
 		let leaves = LeafSet::read_from_db(&*db, columns::META, meta_keys::LEAF_PREFIX)?;
 		Ok(BlockchainDb {
@@ -474,6 +552,8 @@ impl<Block: BlockT> BlockchainDb<Block> {
 			meta: Arc::new(RwLock::new(meta)),
 			header_metadata_cache: Arc::new(HeaderMetadataCache::default()),
 			header_cache: Default::default(),
+			keep_blocks,
+			keep_justifications,
 		})
 	}
 
@@ -533,6 +613,8 @@ impl<Block: BlockT> sc_client_api::blockchain::HeaderBackend<Block> for Blockcha
 			finalized_state: meta.finalized_state,
 			number_leaves: self.leaves.read().count(),
 			block_gap: meta.block_gap,
+			bodies_from: self.keep_blocks.first_kept(meta.finalized_number),
+			justifications_from: self.keep_justifications.first_kept(meta.finalized_number),
 		}
 	}
 
@@ -730,7 +812,7 @@ impl<Block: BlockT> HeaderMetadata<Block> for BlockchainDb<Block> {
 
 /// Database transaction
 pub struct BlockImportOperation<Block: BlockT> {
-	old_state: SyncingCachingState<RefTrackingState<Block>, Block>,
+	old_state: RecordStatsState<RefTrackingState<Block>, Block>,
 	db_updates: PrefixedMemoryDB<HashFor<Block>>,
 	storage_updates: StorageCollection,
 	child_storage_updates: ChildStorageCollection,
@@ -800,7 +882,7 @@ impl<Block: BlockT> BlockImportOperation<Block> {
 impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block>
 	for BlockImportOperation<Block>
 {
-	type State = SyncingCachingState<RefTrackingState<Block>, Block>;
+	type State = RecordStatsState<RefTrackingState<Block>, Block>;
 
 	fn state(&self) -> ClientResult<Option<&Self::State>> {
 		Ok(Some(&self.old_state))
@@ -1009,13 +1091,25 @@ pub struct Backend<Block: BlockT> {
 	offchain_storage: offchain::LocalStorage,
 	blockchain: BlockchainDb<Block>,
 	canonicalization_delay: u64,
-	shared_cache: SharedCache<Block>,
+	shared_trie_cache: Option<SharedTrieCache<HashFor<Block>>>,
 	import_lock: Arc<RwLock<()>>,
 	is_archive: bool,
-	keep_blocks: KeepBlocks,
 	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
 	state_usage: Arc<StateUsageStats>,
 	genesis_state: RwLock<Option<Arc<DbGenesisStorage<Block>>>>,
+	pinned_blocks: Mutex<HashMap<Block::Hash, PinnedBlock>>,
+	block_timestamp: RwLock<Option<BlockTimestamp<Block>>>,
+}
+
+/// A block pinned through [`sc_client_api::Backend::pin_block`].
+#[derive(Default)]
+struct PinnedBlock {
+	/// Number of pins held on the block.
+	refs: u32,
+	/// Number of those pins that also pinned the state in the state db.
+	state_refs: u32,
+	/// The body was due for pruning while the block was pinned.
+	prune_body: bool,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -1053,16 +1147,30 @@ impl<Block: BlockT> Backend<Block> {
 		let db = kvdb_memorydb::create(crate::utils::NUM_COLUMNS);
 		let db = sp_database::as_database(db);
 		let db_setting = DatabaseSettings {
-			state_cache_size: 16777216,
-			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_maximum_size: Some(16 * 1024 * 1024),
 			state_pruning: Some(PruningMode::keep_blocks(keep_blocks)),
 			source: DatabaseSource::Custom { db, require_create_flag: true },
 			keep_blocks: KeepBlocks::Some(keep_blocks),
+			keep_justifications: KeepJustifications::All,
 		};
 
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
 	}
 
+	/// Set how the time a block was authored at is determined.
+	///
+	/// Has to be set before blocks are imported if the state is pruned by age, their states can't
+	/// be canonicalized otherwise and the import fails.
+	pub fn set_block_timestamp(&self, block_timestamp: BlockTimestamp<Block>) {
+		*self.block_timestamp.write() = Some(block_timestamp);
+	}
+
+	/// Whether the state pruning mode of the database needs to know when blocks were authored,
+	/// see [`Backend::set_block_timestamp`].
+	pub fn requires_block_timestamp(&self) -> bool {
+		self.storage.state_db.pruning_mode().requires_timestamps()
+	}
+
 	/// Expose the Database that is used by this backend.
 	/// The second argument is the Column that stores the State.
 	///
@@ -1104,7 +1212,8 @@ impl<Block: BlockT> Backend<Block> {
 
 		let state_pruning_used = state_db.pruning_mode();
 		let is_archive_pruning = state_pruning_used.is_archive();
-		let blockchain = BlockchainDb::new(db.clone())?;
+		let blockchain =
+			BlockchainDb::new(db.clone(), config.keep_blocks, config.keep_justifications)?;
 
 		let storage_db =
 			StorageDb { db: db.clone(), state_db, prefix_keys: !db.supports_ref_counting() };
@@ -1116,16 +1225,14 @@ impl<Block: BlockT> Backend<Block> {
 			offchain_storage,
 			blockchain,
 			canonicalization_delay,
-			shared_cache: new_shared_cache(
-				config.state_cache_size,
-				config.state_cache_child_ratio.unwrap_or(DEFAULT_CHILD_RATIO),
-			),
+			shared_trie_cache: config.trie_cache_maximum_size.map(SharedTrieCache::new),
 			import_lock: Default::default(),
 			is_archive: is_archive_pruning,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1)),
 			state_usage: Arc::new(StateUsageStats::new()),
-			keep_blocks: config.keep_blocks,
 			genesis_state: RwLock::new(None),
+			pinned_blocks: Default::default(),
+			block_timestamp: RwLock::new(None),
 		};
 
 		// Older DB versions have no last state key. Check if the state is available and set it.
@@ -1163,10 +1270,7 @@ impl<Block: BlockT> Backend<Block> {
 		transaction: &mut Transaction<DbHash>,
 		route_to: Block::Hash,
 		best_to: (NumberFor<Block>, Block::Hash),
-	) -> ClientResult<(Vec<Block::Hash>, Vec<Block::Hash>)> {
-		let mut enacted = Vec::default();
-		let mut retracted = Vec::default();
-
+	) -> ClientResult<()> {
 		let (best_number, best_hash) = best_to;
 
 		let meta = self.blockchain.meta.read();
@@ -1197,13 +1301,11 @@ impl<Block: BlockT> Backend<Block> {
 					return Err(::sp_blockchain::Error::NotInFinalizedChain)
 				}
 
-				retracted.push(r.hash);
 				utils::remove_number_to_key_mapping(transaction, columns::KEY_LOOKUP, r.number)?;
 			}
 
 			// canonicalize: set the number lookup to map to this block's hash.
 			for e in tree_route.enacted() {
-				enacted.push(e.hash);
 				utils::insert_number_to_key_mapping(
 					transaction,
 					columns::KEY_LOOKUP,
@@ -1222,7 +1324,7 @@ impl<Block: BlockT> Backend<Block> {
 			best_hash,
 		)?;
 
-		Ok((enacted, retracted))
+		Ok(())
 	}
 
 	fn ensure_sequential_finalization(
@@ -1275,8 +1377,9 @@ impl<Block: BlockT> Backend<Block> {
 		&self,
 		transaction: &mut Transaction<DbHash>,
 		hash: Block::Hash,
-		number: NumberFor<Block>,
+		header: &Block::Header,
 	) -> ClientResult<()> {
+		let number = *header.number();
 		let number_u64 = number.saturated_into::<u64>();
 		if number_u64 > self.canonicalization_delay {
 			let new_canonical = number_u64 - self.canonicalization_delay;
@@ -1303,10 +1406,12 @@ impl<Block: BlockT> Backend<Block> {
 			}
 
 			trace!(target: "db", "Canonicalize block #{} ({:?})", new_canonical, hash);
-			let commit =
-				self.storage.state_db.canonicalize_block(&hash).map_err(
-					sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
-				)?;
+			// The imported block is not in the database yet.
+			let timestamp = self
+				.state_pruning_timestamp(&hash, (new_canonical == number_u64).then_some(header))?;
+			let commit = self.storage.state_db.canonicalize_block(&hash, timestamp).map_err(
+				sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
+			)?;
 			apply_state_commit(transaction, commit);
 		}
 		Ok(())
@@ -1351,16 +1456,30 @@ impl<Block: BlockT> Backend<Block> {
 			// blocks are keyed by number + hash.
 			let lookup_key = utils::number_and_hash_to_lookup_key(number, hash)?;
 
-			let (enacted, retracted) = if pending_block.leaf_state.is_best() {
-				self.set_head_with_transaction(&mut transaction, parent_hash, (number, hash))?
-			} else {
-				(Default::default(), Default::default())
-			};
+			if pending_block.leaf_state.is_best() {
+				self.set_head_with_transaction(&mut transaction, parent_hash, (number, hash))?;
+			}
 
 			utils::insert_hash_to_key_mapping(&mut transaction, columns::KEY_LOOKUP, number, hash)?;
 
 			transaction.set_from_vec(columns::HEADER, &lookup_key, pending_block.header.encode());
-			if let Some(body) = pending_block.body {
+
+			// Old blocks, e.g. the ones downloaded by gap sync after a warp sync, are finalized
+			// already. Don't store what would have been pruned anyway.
+			let keep_body = self
+				.blockchain
+				.keep_blocks
+				.first_kept(last_finalized_num)
+				.map_or(true, |first_kept| number >= first_kept);
+			let keep_justifications =
+				match self.blockchain.keep_justifications.first_kept(last_finalized_num) {
+					Some(first_kept) if number < first_kept =>
+						self.blockchain.keep_justifications.keeps_boundaries() &&
+							is_boundary_block(&pending_block.header),
+					_ => true,
+				};
+
+			if let Some(body) = pending_block.body.filter(|_| keep_body) {
 				// If we have any index operations we save block in the new format with indexed
 				// extrinsic headers Otherwise we save the body as a single blob.
 				if operation.index_ops.is_empty() {
@@ -1371,10 +1490,12 @@ impl<Block: BlockT> Backend<Block> {
 					transaction.set_from_vec(columns::BODY_INDEX, &lookup_key, body);
 				}
 			}
-			if let Some(body) = pending_block.indexed_body {
+			if let Some(body) = pending_block.indexed_body.filter(|_| keep_body) {
 				apply_indexed_body::<Block>(&mut transaction, body);
 			}
-			if let Some(justifications) = pending_block.justifications {
+			if let Some(justifications) =
+				pending_block.justifications.filter(|_| keep_justifications)
+			{
 				transaction.set_from_vec(
 					columns::JUSTIFICATIONS,
 					&lookup_key,
@@ -1458,9 +1579,12 @@ impl<Block: BlockT> Backend<Block> {
 				apply_state_commit(&mut transaction, commit);
 				if number <= last_finalized_num {
 					// Canonicalize in the db when re-importing existing blocks with state.
-					let commit = self.storage.state_db.canonicalize_block(&hash).map_err(
-						sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
-					)?;
+					let timestamp =
+						self.state_pruning_timestamp(&hash, Some(&pending_block.header))?;
+					let commit =
+						self.storage.state_db.canonicalize_block(&hash, timestamp).map_err(
+							sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
+						)?;
 					apply_state_commit(&mut transaction, commit);
 					meta_updates.push(MetaUpdate {
 						hash,
@@ -1488,7 +1612,7 @@ impl<Block: BlockT> Backend<Block> {
 
 			self.state_usage.merge_sm(operation.old_state.usage_info());
 			// release state reference so that it can be finalized
-			let cache = operation.old_state.into_cache_changes();
+			drop(operation.old_state);
 
 			if finalized {
 				// TODO: ensure best chain contains this block.
@@ -1502,7 +1626,7 @@ impl<Block: BlockT> Backend<Block> {
 				)?;
 			} else {
 				// canonicalize blocks which are old enough, regardless of finality.
-				self.force_delayed_canonicalize(&mut transaction, hash, *header.number())?
+				self.force_delayed_canonicalize(&mut transaction, hash, header)?
 			}
 
 			if !existing_header {
@@ -1577,20 +1701,19 @@ impl<Block: BlockT> Backend<Block> {
 				is_finalized: finalized,
 				with_state: operation.commit_state,
 			});
-			Some((pending_block.header, number, hash, enacted, retracted, is_best, cache))
+			Some((pending_block.header, hash))
 		} else {
 			None
 		};
 
-		let cache_update = if let Some(set_head) = operation.set_head {
+		if let Some(set_head) = operation.set_head {
 			if let Some(header) =
 				sc_client_api::blockchain::HeaderBackend::header(&self.blockchain, set_head)?
 			{
 				let number = header.number();
 				let hash = header.hash();
 
-				let (enacted, retracted) =
-					self.set_head_with_transaction(&mut transaction, hash, (*number, hash))?;
+				self.set_head_with_transaction(&mut transaction, hash, (*number, hash))?;
 				meta_updates.push(MetaUpdate {
 					hash,
 					number: *number,
@@ -1598,40 +1721,24 @@ impl<Block: BlockT> Backend<Block> {
 					is_finalized: false,
 					with_state: false,
 				});
-				Some((enacted, retracted))
 			} else {
 				return Err(sp_blockchain::Error::UnknownBlock(format!(
 					"Cannot set head {:?}",
 					set_head
 				)))
 			}
-		} else {
-			None
-		};
+		}
 
 		self.storage.db.commit(transaction)?;
 
 		// Apply all in-memory state changes.
 		// Code beyond this point can't fail.
 
-		if let Some((header, number, hash, enacted, retracted, is_best, mut cache)) = imported {
+		if let Some((header, hash)) = imported {
 			trace!(target: "db", "DB Commit done {:?}", hash);
 			let header_metadata = CachedHeaderMetadata::from(&header);
 			self.blockchain.insert_header_metadata(header_metadata.hash, header_metadata);
 			cache_header(&mut self.blockchain.header_cache.lock(), hash, Some(header));
-			cache.sync_cache(
-				&enacted,
-				&retracted,
-				operation.storage_updates,
-				operation.child_storage_updates,
-				Some(hash),
-				Some(number),
-				is_best,
-			);
-		}
-
-		if let Some((enacted, retracted)) = cache_update {
-			self.shared_cache.write().sync(&enacted, &retracted);
 		}
 
 		for m in meta_updates {
@@ -1668,10 +1775,10 @@ impl<Block: BlockT> Backend<Block> {
 				.map(|c| f_num.saturated_into::<u64>() > c)
 				.unwrap_or(true)
 		{
-			let commit =
-				self.storage.state_db.canonicalize_block(&f_hash).map_err(
-					sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
-				)?;
+			let timestamp = self.state_pruning_timestamp(&f_hash, Some(f_header))?;
+			let commit = self.storage.state_db.canonicalize_block(&f_hash, timestamp).map_err(
+				sp_blockchain::Error::from_state_db::<sc_state_db::Error<io::Error>>,
+			)?;
 			apply_state_commit(transaction, commit);
 		}
 
@@ -1691,7 +1798,9 @@ impl<Block: BlockT> Backend<Block> {
 		finalized: NumberFor<Block>,
 		displaced: &FinalizationDisplaced<Block::Hash, NumberFor<Block>>,
 	) -> ClientResult<()> {
-		if let KeepBlocks::Some(keep_blocks) = self.keep_blocks {
+		self.prune_justifications(transaction, finalized)?;
+
+		if let KeepBlocks::Some(keep_blocks) = self.blockchain.keep_blocks {
 			// Always keep the last finalized block
 			let keep = std::cmp::max(keep_blocks, 1);
 			if finalized >= keep.into() {
@@ -1723,11 +1832,46 @@ impl<Block: BlockT> Backend<Block> {
 		Ok(())
 	}
 
+	fn prune_justifications(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		finalized: NumberFor<Block>,
+	) -> ClientResult<()> {
+		let number = match self.blockchain.keep_justifications.first_kept(finalized) {
+			Some(first_kept) if !first_kept.is_zero() => first_kept - One::one(),
+			_ => return Ok(()),
+		};
+		let id = BlockId::<Block>::number(number);
+
+		if self.blockchain.keep_justifications.keeps_boundaries() &&
+			self.blockchain.header(id)?.map_or(false, |header| is_boundary_block(&header))
+		{
+			return Ok(())
+		}
+
+		debug!(target: "db", "Removing justifications of block #{}", number);
+		utils::remove_from_db(
+			transaction,
+			&*self.storage.db,
+			columns::KEY_LOOKUP,
+			columns::JUSTIFICATIONS,
+			id,
+		)
+	}
+
 	fn prune_block(
 		&self,
 		transaction: &mut Transaction<DbHash>,
 		id: BlockId<Block>,
 	) -> ClientResult<()> {
+		if let Some(hash) = self.blockchain.block_hash_from_id(&id)? {
+			if let Some(pinned) = self.pinned_blocks.lock().get_mut(&hash) {
+				debug!(target: "db", "Keeping pinned block #{}", id);
+				pinned.prune_body = true;
+				return Ok(())
+			}
+		}
+
 		debug!(target: "db", "Removing block #{}", id);
 		utils::remove_from_db(
 			transaction,
@@ -1763,20 +1907,80 @@ impl<Block: BlockT> Backend<Block> {
 		Ok(())
 	}
 
-	fn empty_state(&self) -> ClientResult<SyncingCachingState<RefTrackingState<Block>, Block>> {
+	fn empty_state(&self) -> ClientResult<RecordStatsState<RefTrackingState<Block>, Block>> {
 		let root = EmptyStorage::<Block>::new().0; // Empty trie
-		let db_state = DbState::<Block>::new(self.storage.clone(), root);
+		let db_state = self.db_state(self.storage.clone(), root);
 		let state = RefTrackingState::new(db_state, self.storage.clone(), None);
-		let caching_state = CachingState::new(state, self.shared_cache.clone(), None);
-		Ok(SyncingCachingState::new(
-			caching_state,
-			self.state_usage.clone(),
-			self.blockchain.meta.clone(),
-			self.import_lock.clone(),
-		))
+		Ok(RecordStatsState::new(state, None, self.state_usage.clone()))
+	}
+
+	/// Returns the timestamp of the block `hash` in milliseconds, if the state pruning mode
+	/// requires it.
+	///
+	/// `header` is looked up in the database when it is not given.
+	fn state_pruning_timestamp(
+		&self,
+		hash: &Block::Hash,
+		header: Option<&Block::Header>,
+	) -> ClientResult<Option<u64>> {
+		if !self.storage.state_db.pruning_mode().requires_timestamps() {
+			return Ok(None)
+		}
+
+		let timestamp = match header {
+			Some(header) => block_timestamp::<Block>(self.block_timestamp.read().as_ref(), header)?,
+			None => {
+				let header = self.blockchain.header(BlockId::Hash(*hash))?.ok_or_else(|| {
+					sp_blockchain::Error::UnknownBlock(format!("Missing header {:?}", hash))
+				})?;
+				block_timestamp::<Block>(self.block_timestamp.read().as_ref(), &header)?
+			},
+		};
+		Ok(Some(timestamp))
+	}
+
+	/// Create a [`DbState`] with the given `root`, reading through the shared trie cache if it
+	/// is enabled.
+	fn db_state(
+		&self,
+		storage: Arc<dyn sp_state_machine::Storage<HashFor<Block>>>,
+		root: Block::Hash,
+	) -> DbState<Block> {
+		match self.shared_trie_cache.as_ref() {
+			Some(cache) => DbState::<Block>::new_with_cache(storage, root, cache.local_cache()),
+			None => DbState::<Block>::new(storage, root),
+		}
 	}
 }
 
+/// Returns the timestamp of the block with the given `header` in milliseconds.
+///
+/// The genesis block is not authored, its timestamp is zero. Fails if the timestamp of any other
+/// block is not known.
+fn block_timestamp<Block: BlockT>(
+	block_timestamp: Option<&BlockTimestamp<Block>>,
+	header: &Block::Header,
+) -> ClientResult<u64> {
+	if header.number().is_zero() {
+		return Ok(0)
+	}
+
+	let block_timestamp = block_timestamp.ok_or_else(|| {
+		sp_blockchain::Error::Backend(
+			"State pruning by age requires the time blocks were authored at, \
+			 but no block timestamp has been set for the database"
+				.into(),
+		)
+	})?;
+	block_timestamp(header).ok_or_else(|| {
+		sp_blockchain::Error::Backend(format!(
+			"State pruning by age requires the time blocks were authored at, \
+			 the time of block {:?} is not known",
+			header.hash(),
+		))
+	})
+}
+
 fn apply_state_commit(
 	transaction: &mut Transaction<DbHash>,
 	commit: sc_state_db::CommitSet<Vec<u8>>,
@@ -1895,16 +2099,13 @@ where
 impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 	type BlockImportOperation = BlockImportOperation<Block>;
 	type Blockchain = BlockchainDb<Block>;
-	type State = SyncingCachingState<RefTrackingState<Block>, Block>;
+	type State = RecordStatsState<RefTrackingState<Block>, Block>;
 	type OffchainStorage = offchain::LocalStorage;
 
 	fn begin_operation(&self) -> ClientResult<Self::BlockImportOperation> {
-		let mut old_state = self.empty_state()?;
-		old_state.disable_syncing();
-
 		Ok(BlockImportOperation {
 			pending_block: None,
-			old_state,
+			old_state: self.empty_state()?,
 			db_updates: PrefixedMemoryDB::default(),
 			storage_updates: Default::default(),
 			child_storage_updates: Default::default(),
@@ -1927,7 +2128,6 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		} else {
 			operation.old_state = self.state_at(block)?;
 		}
-		operation.old_state.disable_syncing();
 
 		operation.commit_state = true;
 		Ok(())
@@ -2028,8 +2228,9 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			)
 		});
 		let database_cache = MemorySize::from_bytes(0);
-		let state_cache =
-			MemorySize::from_bytes(self.shared_cache.read().used_storage_cache_size());
+		let state_cache = MemorySize::from_bytes(
+			self.shared_trie_cache.as_ref().map_or(0, |c| c.used_memory_size()),
+		);
 		let state_db = self.storage.state_db.memory_info();
 
 		Some(UsageInfo {
@@ -2050,6 +2251,59 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		})
 	}
 
+	fn pin_block(&self, hash: &Block::Hash) -> ClientResult<()> {
+		// Fails if the block is unknown.
+		self.blockchain.header_metadata(*hash)?;
+
+		let pinned_state = match self.storage.state_db.pin(hash) {
+			Ok(()) => true,
+			// Archive nodes never prune the state of canonical blocks.
+			Err(_) if self.is_archive => false,
+			Err(_) =>
+				return Err(sp_blockchain::Error::UnknownBlock(format!(
+					"State already discarded for {:?}",
+					hash
+				))),
+		};
+
+		let mut pinned_blocks = self.pinned_blocks.lock();
+		let pinned = pinned_blocks.entry(*hash).or_default();
+		pinned.refs += 1;
+		if pinned_state {
+			pinned.state_refs += 1;
+		}
+		Ok(())
+	}
+
+	fn unpin_block(&self, hash: &Block::Hash) {
+		let prune_body = {
+			let mut pinned_blocks = self.pinned_blocks.lock();
+			let pinned = match pinned_blocks.get_mut(hash) {
+				Some(pinned) => pinned,
+				None => return,
+			};
+			if pinned.state_refs > 0 {
+				pinned.state_refs -= 1;
+				self.storage.state_db.unpin(hash);
+			}
+			pinned.refs -= 1;
+			if pinned.refs > 0 {
+				return
+			}
+			pinned_blocks.remove(hash).map_or(false, |pinned| pinned.prune_body)
+		};
+
+		if prune_body {
+			let mut transaction = Transaction::new();
+			let result = self
+				.prune_block(&mut transaction, BlockId::Hash(*hash))
+				.and_then(|()| self.storage.db.commit(transaction).map_err(Into::into));
+			if let Err(e) = result {
+				warn!(target: "db", "Failed to remove unpinned block {:?}: {}", hash, e);
+			}
+		}
+	}
+
 	fn revert(
 		&self,
 		n: NumberFor<Block>,
@@ -2224,15 +2478,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			if let Some(genesis_state) = &*self.genesis_state.read() {
 				let db_state = DbState::<Block>::new(genesis_state.clone(), genesis_state.root);
 				let state = RefTrackingState::new(db_state, self.storage.clone(), None);
-				let caching_state = CachingState::new(state, self.shared_cache.clone(), None);
-				let mut state = SyncingCachingState::new(
-					caching_state,
-					self.state_usage.clone(),
-					self.blockchain.meta.clone(),
-					self.import_lock.clone(),
-				);
-				state.disable_syncing();
-				return Ok(state)
+				return Ok(RecordStatsState::new(state, None, self.state_usage.clone()))
 			}
 		}
 
@@ -2253,16 +2499,9 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 				}
 				if let Ok(()) = self.storage.state_db.pin(&hash) {
 					let root = hdr.state_root;
-					let db_state = DbState::<Block>::new(self.storage.clone(), root);
+					let db_state = self.db_state(self.storage.clone(), root);
 					let state = RefTrackingState::new(db_state, self.storage.clone(), Some(hash));
-					let caching_state =
-						CachingState::new(state, self.shared_cache.clone(), Some(hash));
-					Ok(SyncingCachingState::new(
-						caching_state,
-						self.state_usage.clone(),
-						self.blockchain.meta.clone(),
-						self.import_lock.clone(),
-					))
+					Ok(RecordStatsState::new(state, Some(hash), self.state_usage.clone()))
 				} else {
 					Err(sp_blockchain::Error::UnknownBlock(format!(
 						"State already discarded for {:?}",
@@ -2438,11 +2677,11 @@ pub(crate) mod tests {
 
 		let backend = Backend::<Block>::new(
 			DatabaseSettings {
-				state_cache_size: 16777216,
-				state_cache_child_ratio: Some((50, 100)),
+				trie_cache_maximum_size: Some(16 * 1024 * 1024),
 				state_pruning: Some(PruningMode::keep_blocks(1)),
 				source: DatabaseSource::Custom { db: backing, require_create_flag: false },
 				keep_blocks: KeepBlocks::All,
+				keep_justifications: KeepJustifications::All,
 			},
 			0,
 		)
@@ -3139,6 +3378,7 @@ pub(crate) mod tests {
 			backend.commit_operation(op).unwrap();
 		}
 		let bc = backend.blockchain();
+		assert_eq!(bc.info().bodies_from, Some(3));
 		assert_eq!(None, bc.body(BlockId::hash(blocks[0])).unwrap());
 		assert_eq!(None, bc.body(BlockId::hash(blocks[1])).unwrap());
 		assert_eq!(None, bc.body(BlockId::hash(blocks[2])).unwrap());
@@ -3146,6 +3386,142 @@ pub(crate) mod tests {
 		assert_eq!(Some(vec![4.into()]), bc.body(BlockId::hash(blocks[4])).unwrap());
 	}
 
+	fn new_test_with_justification_pruning(
+		keep_justifications: KeepJustifications,
+	) -> Backend<Block> {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		Backend::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: None,
+				state_pruning: Some(PruningMode::keep_blocks(2)),
+				source: DatabaseSource::Custom { db, require_create_flag: true },
+				keep_blocks: KeepBlocks::All,
+				keep_justifications,
+			},
+			0,
+		)
+		.unwrap()
+	}
+
+	/// Import blocks `0..5` and finalize `1..5` with a justification each. Block 2 is at a
+	/// session boundary.
+	fn insert_finalized_blocks_with_justifications(backend: &Backend<Block>) -> Vec<H256> {
+		use sp_runtime::{testing::Digest, DigestItem};
+
+		let mut blocks: Vec<H256> = Vec::new();
+		for number in 0..5u64 {
+			let mut digest = Digest::default();
+			if number == 2 {
+				digest.push(DigestItem::Consensus(CONS0_ENGINE_ID, vec![1]));
+			}
+			let header = Header {
+				number,
+				parent_hash: blocks.last().cloned().unwrap_or_default(),
+				state_root: BlakeTwo256::trie_root(Vec::new(), StateVersion::V1),
+				digest,
+				extrinsics_root: Default::default(),
+			};
+			let block_id = if number == 0 {
+				BlockId::Hash(Default::default())
+			} else {
+				BlockId::Number(number - 1)
+			};
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, block_id).unwrap();
+			op.set_block_data(header.clone(), Some(Vec::new()), None, None, NewBlockState::Best)
+				.unwrap();
+			backend.commit_operation(op).unwrap();
+			if number > 0 {
+				backend
+					.finalize_block(
+						BlockId::Hash(header.hash()),
+						Some((CONS0_ENGINE_ID, vec![number as u8])),
+					)
+					.unwrap();
+			}
+			blocks.push(header.hash());
+		}
+		blocks
+	}
+
+	#[test]
+	fn prune_justifications_on_finalize() {
+		let backend = new_test_with_justification_pruning(KeepJustifications::Some(2));
+		let blocks = insert_finalized_blocks_with_justifications(&backend);
+
+		let bc = backend.blockchain();
+		assert_eq!(bc.info().justifications_from, Some(3));
+		assert_eq!(bc.info().bodies_from, None);
+		assert_eq!(None, bc.justifications(BlockId::hash(blocks[1])).unwrap());
+		// The justification at the session boundary is needed for warp sync proofs.
+		assert_eq!(
+			Some(Justifications::from((CONS0_ENGINE_ID, vec![2]))),
+			bc.justifications(BlockId::hash(blocks[2])).unwrap()
+		);
+		assert_eq!(
+			Some(Justifications::from((CONS0_ENGINE_ID, vec![3]))),
+			bc.justifications(BlockId::hash(blocks[3])).unwrap()
+		);
+		assert_eq!(
+			Some(Justifications::from((CONS0_ENGINE_ID, vec![4]))),
+			bc.justifications(BlockId::hash(blocks[4])).unwrap()
+		);
+	}
+
+	#[test]
+	fn keep_justifications_at_boundaries() {
+		let backend = new_test_with_justification_pruning(KeepJustifications::Boundaries);
+		let blocks = insert_finalized_blocks_with_justifications(&backend);
+
+		let bc = backend.blockchain();
+		assert_eq!(bc.info().justifications_from, Some(4));
+		assert_eq!(None, bc.justifications(BlockId::hash(blocks[1])).unwrap());
+		assert_eq!(
+			Some(Justifications::from((CONS0_ENGINE_ID, vec![2]))),
+			bc.justifications(BlockId::hash(blocks[2])).unwrap()
+		);
+		assert_eq!(None, bc.justifications(BlockId::hash(blocks[3])).unwrap());
+		assert_eq!(
+			Some(Justifications::from((CONS0_ENGINE_ID, vec![4]))),
+			bc.justifications(BlockId::hash(blocks[4])).unwrap()
+		);
+	}
+
+	#[test]
+	fn prune_state_by_block_timestamp() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: None,
+				state_pruning: Some(PruningMode::keep_age(std::time::Duration::from_secs(2))),
+				source: DatabaseSource::Custom { db, require_create_flag: true },
+				keep_blocks: KeepBlocks::All,
+				keep_justifications: KeepJustifications::All,
+			},
+			0,
+		)
+		.unwrap();
+
+		// The genesis block needs no timestamp, the blocks after it can't be canonicalized
+		// without one.
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		assert!(
+			insert_block(&backend, 1, genesis, None, Default::default(), Vec::new(), None).is_err()
+		);
+
+		backend.set_block_timestamp(Arc::new(|header: &Header| Some(header.number * 1000)));
+		let mut blocks = vec![genesis];
+		for number in 1..6 {
+			let hash =
+				insert_header(&backend, number, blocks[blocks.len() - 1], None, Default::default());
+			blocks.push(hash);
+		}
+
+		for (number, hash) in blocks.iter().enumerate() {
+			assert_eq!(backend.have_state_at(hash, number as u64), number >= 3);
+		}
+	}
+
 	#[test]
 	fn prune_blocks_on_finalize_with_fork() {
 		let backend = Backend::<Block>::new_test_with_tx_storage(2, 10);
@@ -3207,6 +3583,59 @@ pub(crate) mod tests {
 		assert_eq!(Some(vec![4.into()]), bc.body(BlockId::hash(blocks[4])).unwrap());
 	}
 
+	#[test]
+	fn pinned_blocks_are_not_pruned() {
+		let backend = Backend::<Block>::new_test_with_tx_storage(1, 10);
+		let mut blocks = Vec::new();
+		let mut prev_hash = Default::default();
+		for i in 0..5 {
+			let hash = insert_block(
+				&backend,
+				i,
+				prev_hash,
+				None,
+				Default::default(),
+				vec![i.into()],
+				None,
+			)
+			.unwrap();
+			blocks.push(hash);
+			prev_hash = hash;
+		}
+
+		backend.pin_block(&blocks[1]).unwrap();
+		backend.pin_block(&blocks[1]).unwrap();
+		assert!(backend.pin_block(&H256::random()).is_err());
+
+		for i in 1..5 {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(blocks[4])).unwrap();
+			op.mark_finalized(BlockId::Hash(blocks[i]), None).unwrap();
+			backend.commit_operation(op).unwrap();
+		}
+
+		let bc = backend.blockchain();
+		assert_eq!(None, bc.body(BlockId::hash(blocks[0])).unwrap());
+		assert_eq!(Some(vec![1.into()]), bc.body(BlockId::hash(blocks[1])).unwrap());
+		assert_eq!(None, bc.body(BlockId::hash(blocks[2])).unwrap());
+		assert!(backend.have_state_at(&blocks[1], 1));
+		assert!(backend.state_at(BlockId::Hash(blocks[1])).is_ok());
+
+		// The block is kept until the last pin is released.
+		backend.unpin_block(&blocks[1]);
+		assert_eq!(Some(vec![1.into()]), bc.body(BlockId::hash(blocks[1])).unwrap());
+		backend.unpin_block(&blocks[1]);
+		assert_eq!(None, bc.body(BlockId::hash(blocks[1])).unwrap());
+		assert_eq!(Some(vec![4.into()]), bc.body(BlockId::hash(blocks[4])).unwrap());
+
+		// Pruning of the state catches up with the next finalized block.
+		let hash =
+			insert_block(&backend, 5, blocks[4], None, Default::default(), vec![5.into()], None)
+				.unwrap();
+		backend.finalize_block(BlockId::Hash(hash), None).unwrap();
+		assert!(!backend.have_state_at(&blocks[1], 1));
+	}
+
 	#[test]
 	fn indexed_data_block_body() {
 		let backend = Backend::<Block>::new_test_with_tx_storage(1, 10);
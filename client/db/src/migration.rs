@@ -0,0 +1,331 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Migration of a RocksDB database to ParityDb.
+//!
+//! Migrating from ParityDb to RocksDB is not supported: the state column of ParityDb is hash
+//! indexed, so the prefixed keys RocksDB stores the trie nodes under can not be recovered.
+//!
+//! The backends don't store trie nodes the same way. RocksDB keys every node by its prefixed
+//! key and `sc-state-db` keeps track of the references, while ParityDb keys nodes by their hash
+//! and counts references itself. Every prefixed node found in the source is inserted as one
+//! reference of the node in the target. This is only correct as long as no node is ever removed,
+//! so the source has to use one of the archive pruning modes and may not contain any journal of
+//! non-canonical blocks.
+
+use std::{fmt, path::Path};
+
+use sc_state_db::PruningMode;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_database::{ColumnId, Database, Transaction};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{
+	columns,
+	utils::{self, DatabaseType, NUM_COLUMNS},
+	DatabaseSource, DbHash, StateMetaDb, DB_HASH_LEN,
+};
+
+/// Changes are committed to the target database whenever their size exceeds this many bytes.
+const COMMIT_BATCH_SIZE: usize = 64 * 1024 * 1024;
+
+/// Statistics of a finished database migration.
+#[derive(Debug, Default, Clone)]
+pub struct MigrationStats {
+	/// Number of keys written to the target database.
+	pub keys: u64,
+	/// Total size in bytes of the keys and values written to the target database.
+	pub bytes: u64,
+}
+
+impl fmt::Display for MigrationStats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} keys, {} bytes", self.keys, self.bytes)
+	}
+}
+
+fn migration_error(message: impl Into<String>) -> ClientError {
+	ClientError::Backend(format!("Database migration failed: {}", message.into()))
+}
+
+/// Copy every column of the database at `source` into a newly created database at `target`
+/// and check that all the values were written.
+///
+/// The source has to be a RocksDB database with archive state pruning, the target has to be a
+/// ParityDb database that does not exist yet. The source database is left untouched.
+pub fn migrate_database<Block: BlockT>(
+	source: &DatabaseSource,
+	target: &DatabaseSource,
+) -> ClientResult<MigrationStats> {
+	if !is_rocksdb(source) {
+		return Err(migration_error(format!("can not migrate from a {} database", source)))
+	}
+
+	let target_path = match target {
+		DatabaseSource::ParityDb { path } => path,
+		_ => return Err(migration_error(format!("can not migrate to a {} database", target))),
+	};
+	if !is_empty_dir(target_path)? {
+		return Err(migration_error(format!("{:?} already exists and is not empty", target_path)))
+	}
+
+	let source_db = utils::open_database::<Block>(source, DatabaseType::Full, false)?;
+	check_source_state(&*source_db)?;
+
+	let target_db = utils::open_database::<Block>(target, DatabaseType::Full, true)?;
+
+	let mut stats = MigrationStats::default();
+	for column in 0..NUM_COLUMNS {
+		let column_stats = copy_column(&*source_db, &*target_db, column)?;
+		log::info!(target: "db", "Migrated column {}: {}", column, column_stats);
+		stats.keys += column_stats.keys;
+		stats.bytes += column_stats.bytes;
+	}
+
+	for column in 0..NUM_COLUMNS {
+		verify_column(&*source_db, &*target_db, column)?;
+	}
+	log::info!(target: "db", "Migrated database to {:?}: {}", target_path, stats);
+
+	Ok(stats)
+}
+
+fn is_rocksdb(source: &DatabaseSource) -> bool {
+	match source {
+		#[cfg(feature = "rocksdb")]
+		DatabaseSource::RocksDb { .. } => true,
+		_ => false,
+	}
+}
+
+fn is_empty_dir(path: &Path) -> ClientResult<bool> {
+	match std::fs::read_dir(path) {
+		Ok(mut entries) => Ok(entries.next().is_none()),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+		Err(e) => Err(migration_error(format!("can not read {:?}: {}", path, e))),
+	}
+}
+
+/// Make sure that the state of the source database can be converted.
+fn check_source_state(db: &dyn Database<DbHash>) -> ClientResult<()> {
+	let mode = sc_state_db::fetch_stored_pruning_mode(&StateMetaDb(db))
+		.map_err(sp_blockchain::Error::from_state_db)?;
+	match mode {
+		Some(PruningMode::ArchiveAll) | Some(PruningMode::ArchiveCanonical) => {},
+		Some(PruningMode::Constrained(_)) |
+		Some(PruningMode::ConstrainedAge { .. }) |
+		Some(PruningMode::ConstrainedInsertedBytes { .. }) =>
+			return Err(migration_error(
+				"the source database prunes its state, only archive databases can be migrated",
+			)),
+		None => return Err(migration_error("the source database has no state pruning mode stored")),
+	}
+
+	let journal =
+		iter_column(db, columns::STATE_META)?.any(|(key, _)| sc_state_db::is_journal_key(&key));
+	if journal {
+		return Err(migration_error(
+			"the source database contains blocks that are not canonicalized yet, \
+			 use the `revert` command to remove the unfinalized blocks first",
+		))
+	}
+
+	Ok(())
+}
+
+fn iter_column<'a>(
+	db: &'a dyn Database<DbHash>,
+	column: ColumnId,
+) -> ClientResult<sp_database::ColumnIter<'a>> {
+	db.iter_column(column)
+		.map_err(|e| migration_error(format!("column {} can not be iterated: {}", column, e)))
+}
+
+/// Whether `key` is the reference counter of a value in a ref counted column of the source.
+fn is_ref_counter(column: ColumnId, key: &[u8]) -> bool {
+	column == columns::TRANSACTION && key.len() == DB_HASH_LEN + 1
+}
+
+/// Returns the number of references of `key` in a ref counted column of the source.
+fn ref_count(db: &dyn Database<DbHash>, column: ColumnId, key: &[u8]) -> u32 {
+	let mut counter_key = key.to_vec();
+	counter_key.push(0);
+	db.get(column, &counter_key)
+		.and_then(|counter| counter.try_into().ok())
+		.map_or(1, u32::from_le_bytes)
+}
+
+/// Convert a key of the source into the key used for the same value in the target.
+fn target_key(target: &dyn Database<DbHash>, column: ColumnId, mut key: Vec<u8>) -> Vec<u8> {
+	if column == columns::STATE {
+		target.sanitize_key(&mut key);
+	}
+	key
+}
+
+fn copy_column(
+	source: &dyn Database<DbHash>,
+	target: &dyn Database<DbHash>,
+	column: ColumnId,
+) -> ClientResult<MigrationStats> {
+	let mut stats = MigrationStats::default();
+	let mut transaction = Transaction::new();
+	let mut batch_size = 0;
+
+	for (key, value) in iter_column(source, column)? {
+		if is_ref_counter(column, &key) {
+			continue
+		}
+
+		let size = key.len() + value.len();
+		if column == columns::TRANSACTION {
+			let hash = DbHash::from_slice(&key);
+			for _ in 0..ref_count(source, column, &key) {
+				transaction.store(column, hash, value.clone());
+			}
+		} else {
+			let key = target_key(target, column, key);
+			transaction.set_from_vec(column, &key, value);
+		}
+
+		stats.keys += 1;
+		stats.bytes += size as u64;
+		batch_size += size;
+		if batch_size >= COMMIT_BATCH_SIZE {
+			target.commit(std::mem::take(&mut transaction))?;
+			batch_size = 0;
+		}
+	}
+	target.commit(transaction)?;
+
+	Ok(stats)
+}
+
+fn verify_column(
+	source: &dyn Database<DbHash>,
+	target: &dyn Database<DbHash>,
+	column: ColumnId,
+) -> ClientResult<()> {
+	for (key, value) in iter_column(source, column)? {
+		if is_ref_counter(column, &key) {
+			continue
+		}
+
+		let key = target_key(target, column, key);
+		if target.get(column, &key).as_ref() != Some(&value) {
+			return Err(migration_error(format!(
+				"value of key {} in column {} differs after migration",
+				sp_core::hexdisplay::HexDisplay::from(&key),
+				column,
+			)));
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{tests::Block, Backend, DatabaseSettings, KeepBlocks, KeepJustifications};
+	use sc_client_api::backend::{Backend as _, BlockImportOperation as _, NewBlockState};
+	use sp_blockchain::HeaderBackend;
+	use sp_core::storage::Storage;
+	use sp_runtime::{generic::BlockId, testing::Header, traits::Header as _, StateVersion};
+	use sp_state_machine::Backend as _;
+
+	fn open_backend(source: &DatabaseSource, state_pruning: PruningMode) -> Backend<Block> {
+		Backend::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: None,
+				state_pruning: Some(state_pruning),
+				source: source.clone(),
+				keep_blocks: KeepBlocks::All,
+				keep_justifications: KeepJustifications::All,
+			},
+			0,
+		)
+		.unwrap()
+	}
+
+	fn insert_genesis(backend: &Backend<Block>) -> <Block as BlockT>::Hash {
+		let storage = vec![(vec![1, 3, 5], vec![2, 4, 6]), (vec![1, 2, 3], vec![9, 9, 9])];
+
+		let mut op = backend.begin_operation().unwrap();
+		let mut header = Header {
+			number: 0,
+			parent_hash: Default::default(),
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		header.state_root = op
+			.old_state
+			.storage_root(
+				storage.iter().map(|(x, y)| (&x[..], Some(&y[..]))),
+				StateVersion::default(),
+			)
+			.0;
+		op.reset_storage(
+			Storage { top: storage.into_iter().collect(), children_default: Default::default() },
+			StateVersion::default(),
+		)
+		.unwrap();
+		op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Final)
+			.unwrap();
+		backend.commit_operation(op).unwrap();
+
+		header.hash()
+	}
+
+	#[test]
+	fn migrates_rocksdb_to_paritydb() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let source =
+			DatabaseSource::RocksDb { path: db_dir.path().join("rocksdb"), cache_size: 128 };
+		let target = DatabaseSource::ParityDb { path: db_dir.path().join("paritydb") };
+
+		let hash = insert_genesis(&open_backend(&source, PruningMode::ArchiveAll));
+
+		let stats = migrate_database::<Block>(&source, &target).unwrap();
+		assert!(stats.keys > 0);
+
+		let backend = open_backend(&target, PruningMode::ArchiveAll);
+		assert_eq!(backend.blockchain().info().best_hash, hash);
+		let state = backend.state_at(BlockId::Hash(hash)).unwrap();
+		assert_eq!(state.storage(&[1, 3, 5]).unwrap(), Some(vec![2, 4, 6]));
+		assert_eq!(state.storage(&[1, 2, 3]).unwrap(), Some(vec![9, 9, 9]));
+		drop(state);
+		drop(backend);
+
+		// The target database exists now and is never overwritten.
+		assert!(migrate_database::<Block>(&source, &target).is_err());
+	}
+
+	#[test]
+	fn pruned_database_is_not_migrated() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let source =
+			DatabaseSource::RocksDb { path: db_dir.path().join("rocksdb"), cache_size: 128 };
+		let target = DatabaseSource::ParityDb { path: db_dir.path().join("paritydb") };
+
+		insert_genesis(&open_backend(&source, PruningMode::keep_blocks(256)));
+
+		assert!(migrate_database::<Block>(&source, &target).is_err());
+	}
+}
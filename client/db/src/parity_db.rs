@@ -20,9 +20,37 @@ use crate::{
 	utils::{DatabaseType, NUM_COLUMNS},
 };
 /// A `Database` adapter for parity-db.
-use sp_database::{error::DatabaseError, Change, ColumnId, Database, Transaction};
+use sp_database::{
+	error::DatabaseError, Change, ColumnId, ColumnIter, Database, Transaction, ValueIter,
+};
+use std::sync::{mpsc, Arc};
+
+/// Number of values buffered by the iterators over the values of hash-indexed columns.
+const VALUE_ITER_BUFFER: usize = 1024;
 
-struct DbAdapter(parity_db::Db);
+/// Columns that are created with an ordered (btree) index, so that they can be iterated in the
+/// order of their keys and scanned by prefix.
+///
+/// Only applies to newly created databases: the index of a column is part of the on-disk format
+/// of ParityDb and can't be changed afterwards, so the columns of existing databases keep the
+/// hash index they were created with. Hash-indexed columns don't store their keys, so only their
+/// values can be iterated, with [`Database::iter_column_values`]. Iterating them by key fails with
+/// an error that explains how to get a database with ordered columns.
+const ORDERED_COLUMNS: [u32; 6] = [
+	columns::META,
+	columns::KEY_LOOKUP,
+	columns::HEADER,
+	columns::BODY,
+	columns::JUSTIFICATIONS,
+	columns::AUX,
+];
+
+struct DbAdapter {
+	db: Arc<parity_db::Db>,
+	path: std::path::PathBuf,
+	/// Whether the column with the given index uses an ordered index.
+	ordered: Vec<bool>,
+}
 
 fn handle_err<T>(result: parity_db::Result<T>) -> T {
 	match result {
@@ -39,7 +67,7 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 	db_type: DatabaseType,
 	create: bool,
 	upgrade: bool,
-) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
+) -> parity_db::Result<Arc<dyn Database<H>>> {
 	let mut config = parity_db::Options::with_columns(path, NUM_COLUMNS as u8);
 
 	match db_type {
@@ -70,9 +98,22 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 		},
 	}
 
+	let existing_meta = parity_db::Options::load_metadata(path)?;
+	match &existing_meta {
+		// Keep the index type of existing databases, it can not be changed after creation.
+		Some(meta) =>
+			for (column, stored) in config.columns.iter_mut().zip(meta.columns.iter()) {
+				column.btree_index = stored.btree_index;
+			},
+		None =>
+			for i in ORDERED_COLUMNS {
+				config.columns[i as usize].btree_index = true;
+			},
+	}
+
 	if upgrade {
 		log::info!("Upgrading database metadata.");
-		if let Some(meta) = parity_db::Options::load_metadata(path)? {
+		if let Some(meta) = existing_meta {
 			config.write_metadata_with_version(path, &meta.salt, Some(meta.version))?;
 		}
 	}
@@ -83,7 +124,22 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 		parity_db::Db::open(&config)?
 	};
 
-	Ok(std::sync::Arc::new(DbAdapter(db)))
+	let ordered = config.columns.iter().map(|c| c.btree_index).collect();
+	Ok(Arc::new(DbAdapter { db: Arc::new(db), path: path.to_path_buf(), ordered }))
+}
+
+/// The error returned when iterating a column of an existing database by key, that was created
+/// before the column got an ordered index.
+fn not_ordered_error(path: &std::path::Path, col: ColumnId) -> DatabaseError {
+	DatabaseError(
+		format!(
+			"Column {} of the ParityDb database at {:?} was created without an ordered index and \
+			 can't be iterated by key. Only databases created by this version index the column: \
+			 create one with the `db migrate` command from a RocksDB database, or by syncing anew",
+			col, path,
+		)
+		.into(),
+	)
 }
 
 fn ref_counted_column(col: u32) -> bool {
@@ -93,7 +149,7 @@ fn ref_counted_column(col: u32) -> bool {
 impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	fn commit(&self, transaction: Transaction<H>) -> Result<(), DatabaseError> {
 		let mut not_ref_counted_column = Vec::new();
-		let result = self.0.commit(transaction.0.into_iter().filter_map(|change| {
+		let result = self.db.commit(transaction.0.into_iter().filter_map(|change| {
 			Some(match change {
 				Change::Set(col, key, value) => (col as u8, key, Some(value)),
 				Change::Remove(col, key) => (col as u8, key, None),
@@ -141,15 +197,69 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	}
 
 	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
-		handle_err(self.0.get(col as u8, key))
+		handle_err(self.db.get(col as u8, key))
 	}
 
 	fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
-		handle_err(self.0.get_size(col as u8, key)).is_some()
+		handle_err(self.db.get_size(col as u8, key)).is_some()
 	}
 
 	fn value_size(&self, col: ColumnId, key: &[u8]) -> Option<usize> {
-		handle_err(self.0.get_size(col as u8, key)).map(|s| s as usize)
+		handle_err(self.db.get_size(col as u8, key)).map(|s| s as usize)
+	}
+
+	fn iter_column<'a>(&'a self, col: ColumnId) -> Result<ColumnIter<'a>, DatabaseError> {
+		if !self.ordered.get(col as usize).copied().unwrap_or(false) {
+			return Err(not_ordered_error(&self.path, col))
+		}
+
+		let mut iter = handle_err(self.db.iter(col as u8));
+		Ok(Box::new(std::iter::from_fn(move || handle_err(iter.next()))))
+	}
+
+	fn iter_prefix<'a>(
+		&'a self,
+		col: ColumnId,
+		prefix: &'a [u8],
+	) -> Result<ColumnIter<'a>, DatabaseError> {
+		if !self.ordered.get(col as usize).copied().unwrap_or(false) {
+			return Err(not_ordered_error(&self.path, col))
+		}
+
+		let mut iter = handle_err(self.db.iter(col as u8));
+		handle_err(iter.seek(prefix));
+		Ok(Box::new(
+			std::iter::from_fn(move || handle_err(iter.next()))
+				.take_while(move |(key, _)| key.starts_with(prefix)),
+		))
+	}
+
+	fn iter_column_values<'a>(&'a self, col: ColumnId) -> Result<ValueIter<'a>, DatabaseError> {
+		if col as usize >= self.ordered.len() {
+			return Err(DatabaseError::unsupported(format!("iterating unknown column {}", col)))
+		}
+		if self.ordered[col as usize] {
+			let iter = <Self as Database<H>>::iter_column(self, col)?;
+			return Ok(Box::new(iter.map(|(_, value)| value)))
+		}
+
+		// ParityDb only offers a callback to walk hash-indexed columns, so the walk happens on
+		// a separate thread that stops as soon as the iterator is dropped.
+		let (sender, receiver) = mpsc::sync_channel(VALUE_ITER_BUFFER);
+		let db = self.db.clone();
+		let walk_sender = sender.clone();
+		let spawned = std::thread::Builder::new().name("parity-db-iter".into()).spawn(move || {
+			let result =
+				db.iter_column_while(col as u8, |state| walk_sender.send(Ok(state.value)).is_ok());
+			if let Err(e) = result {
+				let _ = walk_sender.send(Err(e));
+			}
+		});
+		if let Err(e) = spawned {
+			let _ = sender.send(Err(parity_db::Error::Io(e)));
+		}
+		drop(sender);
+		Ok(Box::new(receiver.into_iter().map(handle_err)))
 	}
 
 	fn supports_ref_counting(&self) -> bool {
@@ -160,3 +270,37 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 		let _prefix = key.drain(0..key.len() - crate::DB_HASH_LEN);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+
+	#[test]
+	fn iterates_values_of_hash_indexed_columns() {
+		let dir = tempfile::tempdir().unwrap();
+		let db = open::<H256>(dir.path(), DatabaseType::Full, true, false).unwrap();
+
+		let mut tx = Transaction::new();
+		tx.set(columns::AUX, b"aux", b"1");
+		tx.set(columns::STATE_META, b"meta1", b"2");
+		tx.set(columns::STATE_META, b"meta2", b"3");
+		db.commit(tx).unwrap();
+
+		// Ordered columns can be iterated with their keys.
+		let aux: Vec<_> = db.iter_column(columns::AUX).unwrap().collect();
+		assert_eq!(aux, vec![(b"aux".to_vec(), b"1".to_vec())]);
+		let aux: Vec<_> = db.iter_column_values(columns::AUX).unwrap().collect();
+		assert_eq!(aux, vec![b"1".to_vec()]);
+
+		// The keys of hash-indexed columns are not stored.
+		assert!(db.iter_column(columns::STATE_META).is_err());
+		assert!(db.iter_prefix(columns::STATE_META, b"meta").is_err());
+		let mut values: Vec<_> = db.iter_column_values(columns::STATE_META).unwrap().collect();
+		values.sort();
+		assert_eq!(values, vec![b"2".to_vec(), b"3".to_vec()]);
+
+		// Dropping the iterator early stops the walk.
+		assert_eq!(db.iter_column_values(columns::STATE_META).unwrap().take(1).count(), 1);
+	}
+}
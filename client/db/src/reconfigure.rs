@@ -0,0 +1,677 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Changing the state pruning mode of an existing database.
+//!
+//! Switching between the constrained pruning modes is done by `sc-state-db`: the pruning window
+//! is loaded with the new limits and the blocks that don't fit into it anymore are pruned.
+//!
+//! A database that used one of the archive modes has no pruning journal, so the trie nodes to
+//! delete when a block is pruned are not known. The journal is rebuilt from the states: going
+//! back from the last finalized block, the nodes of every state are compared with the nodes of
+//! the state of the parent block until the window is full. Only the parts of the tries that
+//! differ are walked.
+//!
+//! The nodes of the states of the window are marked in the database while the window is built,
+//! every node that is not marked is removed afterwards. This requires a database that can be
+//! iterated and that does not count the references of the nodes itself, i.e. RocksDB.
+
+use std::collections::BTreeMap;
+
+use sc_state_db::{ChangeSet, PruningMode, PruningWindowBuilder};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{
+	hexdisplay::HexDisplay,
+	storage::{well_known_keys, ChildInfo},
+};
+use sp_database::{Database, Transaction};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, HashFor, Header as HeaderT, Zero},
+	SaturatedConversion,
+};
+use sp_trie::DBValue;
+use trie_db::{
+	node::{Node, NodeHandle, Value},
+	NibbleVec, NodeCodec as _, TrieLayout,
+};
+
+use crate::{
+	apply_state_commit, block_timestamp, columns,
+	utils::{self, DatabaseType},
+	BlockTimestamp, DatabaseSource, DbHash, StateMetaDb,
+};
+
+/// Changes are committed to the database whenever this many trie nodes have been marked or
+/// removed.
+const REMOVAL_BATCH_SIZE: usize = 64 * 1024;
+
+/// Prefix of the keys that mark the trie nodes to keep, in the state meta column.
+const MARK_PREFIX: &[u8] = b"reconfigure_pruning_keep:";
+
+/// Layout used to decode the trie nodes, the encoding of the nodes is the same for all state
+/// versions.
+type Layout<Block> = sp_trie::LayoutV1<HashFor<Block>>;
+
+fn reconfigure_error(message: impl Into<String>) -> ClientError {
+	ClientError::Backend(format!("Reconfiguring state pruning failed: {}", message.into()))
+}
+
+/// Change the state pruning mode of the database at `source` to `requested`.
+///
+/// The states that don't fit into the pruning window of the new mode are removed right away.
+/// Switching from a constrained mode to an archive mode is not possible, the pruned states can't
+/// be restored.
+///
+/// `timestamp` is needed to switch to [`PruningMode::ConstrainedAge`], see
+/// [`Backend::set_block_timestamp`](crate::Backend::set_block_timestamp).
+pub fn reconfigure_pruning<Block: BlockT>(
+	source: &DatabaseSource,
+	requested: PruningMode,
+	timestamp: Option<BlockTimestamp<Block>>,
+) -> ClientResult<()> {
+	let db = utils::open_database::<Block>(source, DatabaseType::Full, false)?;
+	let stored = sc_state_db::fetch_stored_pruning_mode(&StateMetaDb(&*db))
+		.map_err(sp_blockchain::Error::from_state_db)?
+		.ok_or_else(|| reconfigure_error("the database has no state pruning mode stored"))?;
+
+	if stored == requested {
+		log::info!(target: "db", "State pruning mode is already {:?}", requested);
+		return Ok(())
+	}
+
+	// `ArchiveAll` does not keep track of the blocks that are not finalized yet, their states
+	// would never be canonicalized by any other mode.
+	if stored.is_archive() {
+		check_all_finalized::<Block>(&*db)?;
+	}
+
+	if stored.is_archive() && !requested.is_archive() {
+		return build_pruning_window::<Block>(&*db, requested, timestamp.as_ref())
+	}
+
+	let commit = sc_state_db::reconfigure_pruning::<Block::Hash, Vec<u8>, _>(
+		&StateMetaDb(&*db),
+		requested.clone(),
+		!db.supports_ref_counting(),
+		|hash| {
+			read_header::<Block>(&*db, BlockId::Hash(*hash))
+				.and_then(|header| block_timestamp::<Block>(timestamp.as_ref(), &header))
+				.map(Some)
+				.map_err(|e| sp_database::error::DatabaseError(Box::new(e)))
+		},
+	)
+	.map_err(sp_blockchain::Error::from_state_db)?;
+	let pruned = commit.data.deleted.len();
+
+	let mut transaction = Transaction::new();
+	apply_state_commit(&mut transaction, commit);
+	db.commit(transaction)?;
+
+	log::info!(
+		target: "db",
+		"Changed state pruning mode from {:?} to {:?}, removed {} trie nodes",
+		stored,
+		requested,
+		pruned,
+	);
+	Ok(())
+}
+
+fn check_all_finalized<Block: BlockT>(db: &dyn Database<DbHash>) -> ClientResult<()> {
+	let meta = utils::read_meta::<Block>(db, columns::HEADER)?;
+	let journal = db
+		.iter_column(columns::STATE_META)
+		.map_err(|e| {
+			reconfigure_error(format!("the state meta column can not be iterated: {}", e))
+		})?
+		.any(|(key, _)| sc_state_db::is_journal_key(&key));
+
+	if meta.best_hash != meta.finalized_hash || journal {
+		return Err(reconfigure_error(
+			"the database contains blocks that are not finalized yet, \
+			 use the `revert` command to remove the unfinalized blocks first",
+		))
+	}
+	Ok(())
+}
+
+/// Create the pruning window of an archive database and remove all the trie nodes that don't
+/// belong to any of the states of the window.
+fn build_pruning_window<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	requested: PruningMode,
+	timestamp: Option<&BlockTimestamp<Block>>,
+) -> ClientResult<()> {
+	if db.supports_ref_counting() {
+		return Err(reconfigure_error(
+			"the database counts the references of trie nodes itself, \
+			 the nodes used by the pruned states of an archive database can not be determined",
+		))
+	}
+
+	let meta = utils::read_meta::<Block>(db, columns::HEADER)?;
+	let mut header = read_header::<Block>(db, BlockId::Hash(meta.finalized_hash))?;
+	let mut builder = PruningWindowBuilder::new(
+		requested.clone(),
+		true,
+		meta.finalized_hash,
+		meta.finalized_number.saturated_into(),
+	);
+
+	// Marks left behind by an interrupted attempt only keep nodes that may have to be removed.
+	remove_marks(db)?;
+	let mut marks = Marks::new(db);
+
+	// The state of the block before the oldest block of the window is kept as well.
+	let base = loop {
+		let parent = if header.number().is_zero() {
+			None
+		} else {
+			match utils::read_header::<Block>(
+				db,
+				columns::KEY_LOOKUP,
+				columns::HEADER,
+				BlockId::Hash(*header.parent_hash()),
+			)? {
+				// The states of a warp synced database start with the state of the synced block.
+				Some(parent) if has_state(db, parent.state_root()) => Some(parent),
+				_ => break Some(header),
+			}
+		};
+
+		let mut changes = ChangeSet { inserted: Vec::new(), deleted: Vec::new() };
+		StateDiff::new(
+			db,
+			parent.as_ref().map(|parent| parent.state_root()),
+			Some(header.state_root()),
+		)
+		.walk::<Block>(
+			|key| Ok(changes.deleted.push(key)),
+			|key, value| Ok(changes.inserted.push((key, value))),
+		)?;
+		let inserted: Vec<_> = changes.inserted.iter().map(|(key, _)| key.clone()).collect();
+		let timestamp = if requested.requires_timestamps() {
+			Some(block_timestamp::<Block>(timestamp, &header)?)
+		} else {
+			None
+		};
+		if !builder.push_front(header.hash(), timestamp, changes) {
+			break Some(header)
+		}
+
+		for key in inserted {
+			marks.mark(&key)?;
+		}
+
+		match parent {
+			Some(parent) => header = parent,
+			None => break None,
+		}
+	};
+
+	if let Some(base) = base {
+		StateDiff::new(db, None, Some(base.state_root()))
+			.walk::<Block>(|_| Ok(()), |key, _| marks.mark(&key))?;
+	}
+	marks.flush()?;
+
+	// The window is stored first: if removing the nodes gets interrupted the remaining nodes
+	// just waste space.
+	let first_block = builder.first_block();
+	let mut transaction = Transaction::new();
+	apply_state_commit(&mut transaction, builder.into_commit());
+	db.commit(transaction)?;
+
+	let mut pruned = 0;
+	let mut transaction = Transaction::new();
+	let nodes = db
+		.iter_column(columns::STATE)
+		.map_err(|e| reconfigure_error(format!("the state column can not be iterated: {}", e)))?;
+	for (key, _) in nodes {
+		if db.contains(columns::STATE_META, &mark_key(&key)) {
+			continue
+		}
+
+		transaction.remove(columns::STATE, &key);
+		pruned += 1;
+		if pruned % REMOVAL_BATCH_SIZE == 0 {
+			db.commit(std::mem::take(&mut transaction))?;
+		}
+	}
+	db.commit(transaction)?;
+	remove_marks(db)?;
+
+	log::info!(
+		target: "db",
+		"Changed state pruning mode to {:?}, kept the states from block #{}, removed {} trie nodes",
+		requested,
+		first_block.saturating_sub(1),
+		pruned,
+	);
+	Ok(())
+}
+
+fn read_header<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	id: BlockId<Block>,
+) -> ClientResult<Block::Header> {
+	utils::read_header(db, columns::KEY_LOOKUP, columns::HEADER, id)?
+		.ok_or_else(|| ClientError::UnknownBlock(format!("Missing header {}", id)))
+}
+
+fn has_state(db: &dyn Database<DbHash>, root: &impl AsRef<[u8]>) -> bool {
+	db.contains(columns::STATE, &node_key(&[], &NibbleVec::new(), root.as_ref()))
+}
+
+/// Key of the mark of the trie node with the given database `key`, in the state meta column.
+fn mark_key(key: &[u8]) -> Vec<u8> {
+	let mut mark = MARK_PREFIX.to_vec();
+	mark.extend_from_slice(key);
+	mark
+}
+
+/// Remove all the marks from the database.
+fn remove_marks(db: &dyn Database<DbHash>) -> ClientResult<()> {
+	let marks = db.iter_prefix(columns::STATE_META, MARK_PREFIX).map_err(|e| {
+		reconfigure_error(format!("the state meta column can not be iterated: {}", e))
+	})?;
+	let mut transaction = Transaction::new();
+	for (removed, (key, _)) in marks.enumerate() {
+		transaction.remove(columns::STATE_META, &key);
+		if (removed + 1) % REMOVAL_BATCH_SIZE == 0 {
+			db.commit(std::mem::take(&mut transaction))?;
+		}
+	}
+	db.commit(transaction)?;
+	Ok(())
+}
+
+/// Marks the trie nodes to keep in the database, the marks are written in batches.
+struct Marks<'a> {
+	db: &'a dyn Database<DbHash>,
+	transaction: Transaction<DbHash>,
+	pending: usize,
+}
+
+impl<'a> Marks<'a> {
+	fn new(db: &'a dyn Database<DbHash>) -> Self {
+		Marks { db, transaction: Transaction::new(), pending: 0 }
+	}
+
+	fn mark(&mut self, key: &[u8]) -> ClientResult<()> {
+		self.transaction.set(columns::STATE_META, &mark_key(key), &[]);
+		self.pending += 1;
+		if self.pending == REMOVAL_BATCH_SIZE {
+			self.flush()?;
+		}
+		Ok(())
+	}
+
+	fn flush(&mut self) -> ClientResult<()> {
+		self.pending = 0;
+		self.db.commit(std::mem::take(&mut self.transaction))?;
+		Ok(())
+	}
+}
+
+/// Database key of the trie node with the given `hash` at `prefix` in the trie stored in
+/// `keyspace`.
+fn node_key(keyspace: &[u8], prefix: &NibbleVec, hash: &[u8]) -> Vec<u8> {
+	let (prefix, last) = prefix.as_prefix();
+	let mut key = Vec::with_capacity(keyspace.len() + prefix.len() + 1 + hash.len());
+	key.extend_from_slice(keyspace);
+	key.extend_from_slice(prefix);
+	key.extend(last);
+	key.extend_from_slice(hash);
+	key
+}
+
+/// Position of a trie node in a state.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct NodePosition {
+	/// Keyspace of the trie, empty for the top trie.
+	keyspace: Vec<u8>,
+	/// Nibbles of the key prefix of the node.
+	nibbles: Vec<u8>,
+	/// Whether this is a value that is stored in a node of its own, after the node that refers to
+	/// it.
+	is_value: bool,
+}
+
+/// Database keys of the nodes of the old and the new state at a position.
+#[derive(Default)]
+struct PositionNodes {
+	old: Option<Vec<u8>>,
+	new: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+	Old,
+	New,
+}
+
+/// Walks the trie nodes of two states and of their child tries at once, to find the nodes that
+/// are only part of one of them.
+///
+/// The nodes are visited in the order of their positions, so a node is visited after the nodes
+/// above it and the nodes of both states at a position are visited together. A node that is part
+/// of both states is skipped with all the nodes below it. Only the nodes next to the visited
+/// nodes are kept in memory.
+struct StateDiff<'a> {
+	db: &'a dyn Database<DbHash>,
+	pending: BTreeMap<NodePosition, PositionNodes>,
+}
+
+impl<'a> StateDiff<'a> {
+	/// Compare the state with the given `old_root` to the state with the given `new_root`, a
+	/// missing state has no nodes.
+	fn new<H: AsRef<[u8]>>(
+		db: &'a dyn Database<DbHash>,
+		old_root: Option<&H>,
+		new_root: Option<&H>,
+	) -> Self {
+		let mut diff = StateDiff { db, pending: BTreeMap::new() };
+		for (side, root) in [(Side::Old, old_root), (Side::New, new_root)] {
+			if let Some(root) = root {
+				diff.add(side, &[], &NibbleVec::new(), false, root.as_ref());
+			}
+		}
+		diff
+	}
+
+	/// Walk the states, `deleted` is called with the keys of the nodes that are only part of the
+	/// old state and `inserted` with the keys and the values of the nodes that are only part of
+	/// the new state.
+	fn walk<Block: BlockT>(
+		mut self,
+		mut deleted: impl FnMut(Vec<u8>) -> ClientResult<()>,
+		mut inserted: impl FnMut(Vec<u8>, DBValue) -> ClientResult<()>,
+	) -> ClientResult<()> {
+		while let Some(position) = self.pending.keys().next().cloned() {
+			let nodes = self.pending.remove(&position).unwrap_or_default();
+			if nodes.old.is_some() && nodes.old == nodes.new {
+				continue
+			}
+
+			if let Some(key) = nodes.old {
+				let value = self.fetch(&key)?;
+				if !position.is_value {
+					self.add_children::<Block>(Side::Old, &position, &value)?;
+				}
+				deleted(key)?;
+			}
+			if let Some(key) = nodes.new {
+				let value = self.fetch(&key)?;
+				if !position.is_value {
+					self.add_children::<Block>(Side::New, &position, &value)?;
+				}
+				inserted(key, value)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn fetch(&self, key: &[u8]) -> ClientResult<DBValue> {
+		self.db.get(columns::STATE, key).ok_or_else(|| {
+			reconfigure_error(format!("missing trie node {}", HexDisplay::from(&key)))
+		})
+	}
+
+	/// Add the node with the given `hash` of one side to the nodes to visit.
+	fn add(
+		&mut self,
+		side: Side,
+		keyspace: &[u8],
+		prefix: &NibbleVec,
+		is_value: bool,
+		hash: &[u8],
+	) {
+		let position = NodePosition {
+			keyspace: keyspace.to_vec(),
+			nibbles: (0..prefix.len()).map(|index| prefix.at(index)).collect(),
+			is_value,
+		};
+		let key = node_key(keyspace, prefix, hash);
+		let nodes = self.pending.entry(position).or_default();
+		match side {
+			Side::Old => nodes.old = Some(key),
+			Side::New => nodes.new = Some(key),
+		}
+	}
+
+	/// Add the nodes referred to by the trie node at `position` with the given `data`.
+	fn add_children<Block: BlockT>(
+		&mut self,
+		side: Side,
+		position: &NodePosition,
+		data: &[u8],
+	) -> ClientResult<()> {
+		let mut prefix = NibbleVec::new();
+		for nibble in &position.nibbles {
+			prefix.push(*nibble);
+		}
+		self.add_node_children::<Block>(side, &position.keyspace, &mut prefix, data)
+	}
+
+	fn add_node_children<Block: BlockT>(
+		&mut self,
+		side: Side,
+		keyspace: &[u8],
+		prefix: &mut NibbleVec,
+		data: &[u8],
+	) -> ClientResult<()> {
+		let node = <Layout<Block> as TrieLayout>::Codec::decode(data)
+			.map_err(|e| reconfigure_error(format!("can not decode trie node: {:?}", e)))?;
+		let (partial, children, value) = match node {
+			Node::Empty => return Ok(()),
+			Node::Leaf(partial, value) => (partial, Default::default(), Some(value)),
+			Node::NibbledBranch(partial, children, value) => (partial, children, value),
+			Node::Extension(..) | Node::Branch(..) =>
+				return Err(reconfigure_error("unexpected trie node type")),
+		};
+
+		let prefix_len = prefix.len();
+		prefix.append_partial(partial.right());
+		if let Some(value) = value {
+			self.add_value(side, keyspace, prefix, value)?;
+		}
+		for (index, child) in children.into_iter().enumerate() {
+			if let Some(child) = child {
+				prefix.push(index as u8);
+				match child {
+					NodeHandle::Hash(hash) => self.add(side, keyspace, prefix, false, hash),
+					NodeHandle::Inline(data) =>
+						self.add_node_children::<Block>(side, keyspace, prefix, data)?,
+				}
+				prefix.pop();
+			}
+		}
+		prefix.drop_lasts(prefix.len() - prefix_len);
+		Ok(())
+	}
+
+	/// Add the value stored under the key `prefix` if it is stored in a node of its own, and the
+	/// root of the child trie it is the root of.
+	fn add_value(
+		&mut self,
+		side: Side,
+		keyspace: &[u8],
+		prefix: &NibbleVec,
+		value: Value,
+	) -> ClientResult<()> {
+		if let Value::Node(hash, _) = value {
+			self.add(side, keyspace, prefix, true, hash);
+		}
+
+		if let (storage_key, None) = prefix.as_prefix() {
+			if keyspace.is_empty() && well_known_keys::is_default_child_storage_key(storage_key) {
+				let root = match value {
+					Value::Inline(value) => value.to_vec(),
+					Value::Node(hash, _) => self.fetch(&node_key(keyspace, prefix, hash))?,
+				};
+				let child_info = ChildInfo::new_default(
+					&storage_key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..],
+				);
+				self.add(side, child_info.keyspace(), &NibbleVec::new(), false, &root);
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{tests::Block, Backend, DatabaseSettings, KeepBlocks, KeepJustifications};
+	use sc_client_api::backend::{Backend as _, BlockImportOperation as _, NewBlockState};
+	use sp_core::{storage::Storage, H256};
+	use sp_runtime::{testing::Header, StateVersion};
+	use sp_state_machine::Backend as _;
+	use std::sync::Arc;
+
+	fn open_backend(
+		db: &Arc<dyn Database<DbHash>>,
+		state_pruning: PruningMode,
+		create: bool,
+	) -> Backend<Block> {
+		Backend::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: None,
+				state_pruning: Some(state_pruning),
+				source: DatabaseSource::Custom { db: db.clone(), require_create_flag: create },
+				keep_blocks: KeepBlocks::All,
+				keep_justifications: KeepJustifications::All,
+			},
+			0,
+		)
+		.unwrap()
+	}
+
+	fn source(db: &Arc<dyn Database<DbHash>>) -> DatabaseSource {
+		DatabaseSource::Custom { db: db.clone(), require_create_flag: false }
+	}
+
+	/// Import blocks `0..blocks`, block `n` sets the key `[1]` to `[n]` and adds the key `[2, n]`.
+	fn import_blocks(backend: &Backend<Block>, blocks: u64, state: NewBlockState) -> Vec<H256> {
+		let mut hashes = Vec::new();
+		for number in 0..blocks {
+			let changes =
+				vec![(vec![1], Some(vec![number as u8])), (vec![2, number as u8], Some(vec![0]))];
+
+			let mut op = backend.begin_operation().unwrap();
+			let mut header = Header {
+				number,
+				parent_hash: hashes.last().cloned().unwrap_or_default(),
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			if number == 0 {
+				header.state_root = op
+					.old_state
+					.storage_root(
+						changes.iter().map(|(k, v)| (&k[..], v.as_deref())),
+						StateVersion::V1,
+					)
+					.0;
+				op.reset_storage(
+					Storage {
+						top: changes.into_iter().map(|(k, v)| (k, v.unwrap())).collect(),
+						children_default: Default::default(),
+					},
+					StateVersion::V1,
+				)
+				.unwrap();
+			} else {
+				backend.begin_state_operation(&mut op, BlockId::Number(number - 1)).unwrap();
+				let (root, overlay) = op.old_state.storage_root(
+					changes.iter().map(|(k, v)| (&k[..], v.as_deref())),
+					StateVersion::V1,
+				);
+				op.update_db_storage(overlay).unwrap();
+				header.state_root = root;
+				op.update_storage(changes, Vec::new()).unwrap();
+			}
+			hashes.push(header.hash());
+			op.set_block_data(header, Some(vec![]), None, None, state).unwrap();
+			backend.commit_operation(op).unwrap();
+		}
+		hashes
+	}
+
+	fn assert_state(backend: &Backend<Block>, number: u64) {
+		let state = backend.state_at(BlockId::Number(number)).unwrap();
+		assert_eq!(state.storage(&[1]).unwrap(), Some(vec![number as u8]));
+		assert_eq!(state.storage(&[2, number as u8]).unwrap(), Some(vec![0]));
+	}
+
+	#[test]
+	fn archive_database_is_pruned() {
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		import_blocks(&open_backend(&db, PruningMode::ArchiveAll, true), 6, NewBlockState::Final);
+
+		reconfigure_pruning::<Block>(&source(&db), PruningMode::keep_blocks(2), None).unwrap();
+
+		let backend = open_backend(&db, PruningMode::keep_blocks(2), false);
+		assert!(backend.state_at(BlockId::Number(3)).is_err());
+		for number in 4..6 {
+			assert_state(&backend, number);
+		}
+		// Only the nodes of the states in the window are left.
+		assert_eq!(db.iter_prefix(columns::STATE_META, MARK_PREFIX).unwrap().count(), 0);
+		let nodes = db.iter_column(columns::STATE).unwrap().count();
+		drop(backend);
+		let reference = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		import_blocks(
+			&open_backend(&reference, PruningMode::keep_blocks(2), true),
+			6,
+			NewBlockState::Final,
+		);
+		assert_eq!(nodes, reference.iter_column(columns::STATE).unwrap().count());
+	}
+
+	#[test]
+	fn window_is_made_smaller() {
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		import_blocks(
+			&open_backend(&db, PruningMode::keep_blocks(4), true),
+			6,
+			NewBlockState::Final,
+		);
+
+		reconfigure_pruning::<Block>(&source(&db), PruningMode::keep_blocks(2), None).unwrap();
+
+		let backend = open_backend(&db, PruningMode::keep_blocks(2), false);
+		assert!(backend.state_at(BlockId::Number(3)).is_err());
+		for number in 4..6 {
+			assert_state(&backend, number);
+		}
+	}
+
+	#[test]
+	fn unfinalized_blocks_are_not_pruned() {
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		import_blocks(&open_backend(&db, PruningMode::ArchiveAll, true), 6, NewBlockState::Best);
+
+		assert!(
+			reconfigure_pruning::<Block>(&source(&db), PruningMode::keep_blocks(2), None).is_err()
+		);
+	}
+}
@@ -731,6 +731,8 @@ pub fn grandpa_peers_set_config(
 		fallback_names: grandpa_protocol_name::LEGACY_NAMES.iter().map(|&n| n.into()).collect(),
 		// Notifications reach ~256kiB in size at the time of writing on Kusama and Polkadot.
 		max_notification_size: 1024 * 1024,
+		// Votes must never wait for the traffic of the other protocols.
+		bandwidth_priority: sc_network::config::BandwidthPriority::High,
 		set_config: sc_network::config::SetConfig {
 			in_peers: 0,
 			out_peers: 0,
@@ -129,14 +129,13 @@ impl<Block: BlockT> WarpSyncProof<Block> {
 				break
 			}
 
+			// The header is last in the set and contains a standard change signal, so it was
+			// finalized with a justification. Justification pruning keeps the ones at authority set
+			// changes, so it is only missing if the block was imported without it.
 			let justification = blockchain
 				.justifications(BlockId::Number(*last_block))?
 				.and_then(|just| just.into_justification(GRANDPA_ENGINE_ID))
-				.expect(
-					"header is last in set and contains standard change signal; \
-					must have justification; \
-					qed.",
-				);
+				.ok_or(Error::MissingData)?;
 
 			let justification = GrandpaJustification::<Block>::decode(&mut &justification[..])?;
 
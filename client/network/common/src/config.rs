@@ -42,3 +42,17 @@ impl fmt::Debug for ProtocolId {
 		fmt::Debug::fmt(self.as_ref(), f)
 	}
 }
+
+/// Priority of the traffic of a protocol when the bandwidth of the node is limited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BandwidthPriority {
+	/// Bulk traffic, such as the download of blocks or state. Only uses the part of the bandwidth
+	/// that the other protocols leave free.
+	Low,
+	/// Regular traffic.
+	#[default]
+	Normal,
+	/// Traffic that must never be delayed, such as finality votes. It still counts towards the
+	/// limits of the node, at the expense of the other protocols.
+	High,
+}
@@ -18,6 +18,7 @@
 
 //! Collection of generic data structures for request-response protocols.
 
+use crate::config::BandwidthPriority;
 use futures::channel::{mpsc, oneshot};
 use libp2p::PeerId;
 use sc_peerset::ReputationChange;
@@ -67,6 +68,10 @@ pub struct ProtocolConfig {
 	/// advertise support for this protocol, but any incoming request will lead to an error being
 	/// sent back.
 	pub inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+
+	/// Priority of the requests and responses of the protocol when the bandwidth of the node is
+	/// limited.
+	pub bandwidth_priority: BandwidthPriority,
 }
 
 /// A single request received by a peer on a request-response protocol.
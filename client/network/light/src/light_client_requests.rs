@@ -21,7 +21,10 @@
 /// For incoming light client requests.
 pub mod handler;
 
-use sc_network_common::{config::ProtocolId, request_responses::ProtocolConfig};
+use sc_network_common::{
+	config::{BandwidthPriority, ProtocolId},
+	request_responses::ProtocolConfig,
+};
 
 use std::time::Duration;
 
@@ -39,5 +42,6 @@ pub fn generate_protocol_config(protocol_id: &ProtocolId) -> ProtocolConfig {
 		max_response_size: 16 * 1024 * 1024,
 		request_timeout: Duration::from_secs(15),
 		inbound_queue: None,
+		bandwidth_priority: BandwidthPriority::Normal,
 	}
 }
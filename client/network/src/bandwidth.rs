@@ -0,0 +1,337 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Limits of the bandwidth used by the notification and request-response protocols.
+//!
+//! Every protocol has a [`ProtocolBandwidth`], shared by all the connections, which counts the
+//! bytes sent and received by the protocol against token buckets: one for the protocol if limits
+//! were configured for it, and one for the whole node. The buckets refill at the configured rate
+//! and hold at most one second worth of traffic. A single message can bring a bucket below zero,
+//! in which case the following messages wait until it has refilled.
+//!
+//! The limits of the whole node are shared according to the [`BandwidthPriority`] of the
+//! protocols:
+//!
+//! - [`BandwidthPriority::High`] traffic is never delayed by the limits of the node, but counts
+//!   towards them.
+//! - [`BandwidthPriority::Normal`] traffic waits until the bucket of the node isn't empty.
+//! - [`BandwidthPriority::Low`] traffic waits until a quarter of the bucket of the node is
+//!   available, so that it never uses the bandwidth needed by the other protocols.
+//!
+//! The limits of a protocol apply to all its traffic, whatever its priority. The traffic of the
+//! other protocols (Kademlia, identify, ping, bitswap) isn't limited.
+
+use crate::config::{BandwidthLimits, BandwidthPriority};
+use parking_lot::Mutex;
+use sc_utils::token_bucket::TokenBucket;
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	fmt,
+	num::NonZeroU64,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+
+/// Part of the bucket of the node that [`BandwidthPriority::Low`] traffic leaves to the other
+/// protocols.
+const LOW_PRIORITY_RESERVE: f64 = 0.25;
+
+/// Minimum time to wait before trying again once a limit is reached, to avoid waking up for every
+/// few bytes.
+const MIN_DELAY: Duration = Duration::from_millis(10);
+
+/// Direction of the traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	/// Data received from the remotes.
+	In,
+	/// Data sent to the remotes.
+	Out,
+}
+
+impl Direction {
+	/// Label of the direction in the metrics.
+	pub fn label(self) -> &'static str {
+		match self {
+			Self::In => "in",
+			Self::Out => "out",
+		}
+	}
+
+	fn index(self) -> usize {
+		match self {
+			Self::In => 0,
+			Self::Out => 1,
+		}
+	}
+}
+
+/// Upload and download buckets.
+#[derive(Debug, Default)]
+struct Buckets([Option<Mutex<TokenBucket>>; 2]);
+
+impl Buckets {
+	fn new(limits: &BandwidthLimits) -> Self {
+		// One token per byte.
+		let bucket = |rate: Option<NonZeroU64>| {
+			rate.map(|rate| {
+				Mutex::new(TokenBucket::new(rate.get(), Duration::from_secs(1), Instant::now()))
+			})
+		};
+		Self([bucket(limits.max_download_rate), bucket(limits.max_upload_rate)])
+	}
+
+	fn get(&self, direction: Direction) -> Option<&Mutex<TokenBucket>> {
+		self.0[direction.index()].as_ref()
+	}
+}
+
+/// Bandwidth limits of the node, shared by all the protocols.
+pub struct BandwidthLimiter {
+	/// Buckets of the node as a whole.
+	node: Arc<Buckets>,
+	/// Limits of the protocols, by protocol name.
+	protocol_limits: HashMap<Cow<'static, str>, BandwidthLimits>,
+	/// Protocols registered with [`BandwidthLimiter::protocol`].
+	protocols: Mutex<Vec<Arc<ProtocolBandwidth>>>,
+}
+
+impl BandwidthLimiter {
+	/// Creates a new limiter from the limits of the node and of the individual protocols.
+	pub fn new(
+		limits: &BandwidthLimits,
+		protocol_limits: HashMap<Cow<'static, str>, BandwidthLimits>,
+	) -> Self {
+		Self {
+			node: Arc::new(Buckets::new(limits)),
+			protocol_limits,
+			protocols: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Creates a limiter that only counts the traffic.
+	#[cfg(test)]
+	pub fn unlimited() -> Self {
+		Self::new(&BandwidthLimits::default(), HashMap::new())
+	}
+
+	/// Registers the protocol `name` and returns the handle counting its traffic.
+	pub fn protocol(
+		&self,
+		name: Cow<'static, str>,
+		priority: BandwidthPriority,
+	) -> Arc<ProtocolBandwidth> {
+		let own = self.protocol_limits.get(&name).map(Buckets::new).unwrap_or_default();
+		let protocol = Arc::new(ProtocolBandwidth {
+			name,
+			priority,
+			own,
+			node: self.node.clone(),
+			bytes: Default::default(),
+			throttled: Default::default(),
+		});
+		self.protocols.lock().push(protocol.clone());
+		protocol
+	}
+
+	/// Returns the registered protocols.
+	pub fn protocols(&self) -> Vec<Arc<ProtocolBandwidth>> {
+		self.protocols.lock().clone()
+	}
+}
+
+/// Counts the traffic of a protocol against the bandwidth limits.
+pub struct ProtocolBandwidth {
+	name: Cow<'static, str>,
+	priority: BandwidthPriority,
+	/// Buckets of the protocol itself.
+	own: Buckets,
+	/// Buckets of the node as a whole.
+	node: Arc<Buckets>,
+	/// Number of bytes counted, by direction.
+	bytes: [AtomicU64; 2],
+	/// Number of times the traffic was delayed by the limits, by direction.
+	throttled: [AtomicU64; 2],
+}
+
+impl fmt::Debug for ProtocolBandwidth {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProtocolBandwidth")
+			.field("name", &self.name)
+			.field("priority", &self.priority)
+			.finish()
+	}
+}
+
+impl ProtocolBandwidth {
+	/// Returns the name of the protocol.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Returns the number of bytes sent or received by the protocol.
+	pub fn bytes(&self, direction: Direction) -> u64 {
+		self.bytes[direction.index()].load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of times the traffic of the protocol was delayed by the limits.
+	pub fn throttled(&self, direction: Direction) -> u64 {
+		self.throttled[direction.index()].load(Ordering::Relaxed)
+	}
+
+	/// Counts `size` bytes about to be sent.
+	///
+	/// If the limits are reached, nothing is counted and the time to wait before trying again is
+	/// returned instead.
+	pub fn try_upload(&self, size: usize) -> Result<(), Duration> {
+		self.try_count(Direction::Out, Some(size))
+	}
+
+	/// Returns the time to wait before receiving more data, if the download limits are reached.
+	pub fn download_delay(&self) -> Option<Duration> {
+		self.try_count(Direction::In, None).err()
+	}
+
+	/// Counts `size` bytes received.
+	pub fn on_download(&self, size: usize) {
+		let now = Instant::now();
+		for bucket in [self.own.get(Direction::In), self.node.get(Direction::In)]
+			.into_iter()
+			.flatten()
+		{
+			let mut bucket = bucket.lock();
+			bucket.refill(now);
+			bucket.consume(size as f64);
+		}
+		self.bytes[Direction::In.index()].fetch_add(size as u64, Ordering::Relaxed);
+	}
+
+	/// Checks the limits in the given direction, and counts `size` bytes if they aren't reached.
+	fn try_count(&self, direction: Direction, size: Option<usize>) -> Result<(), Duration> {
+		let now = Instant::now();
+		// The buckets are always locked in the same order.
+		let mut own = self.own.get(direction).map(|bucket| bucket.lock());
+		let mut node = self.node.get(direction).map(|bucket| bucket.lock());
+
+		let mut wait = None;
+		if let Some(own) = own.as_mut() {
+			own.refill(now);
+			wait = own.wait_for(0.0);
+		}
+		if let Some(node) = node.as_mut() {
+			node.refill(now);
+			let node_wait = match self.priority {
+				BandwidthPriority::High => None,
+				BandwidthPriority::Normal => node.wait_for(0.0),
+				BandwidthPriority::Low => node.wait_for(node.capacity() * LOW_PRIORITY_RESERVE),
+			};
+			wait = wait.max(node_wait);
+		}
+
+		if let Some(wait) = wait {
+			self.throttled[direction.index()].fetch_add(1, Ordering::Relaxed);
+			return Err(wait.max(MIN_DELAY))
+		}
+
+		if let Some(size) = size {
+			for bucket in own.iter_mut().chain(node.iter_mut()) {
+				bucket.consume(size as f64);
+			}
+			self.bytes[direction.index()].fetch_add(size as u64, Ordering::Relaxed);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn limits(rate: u64) -> BandwidthLimits {
+		BandwidthLimits {
+			max_upload_rate: NonZeroU64::new(rate),
+			max_download_rate: NonZeroU64::new(rate),
+		}
+	}
+
+	#[test]
+	fn unlimited_traffic_is_counted() {
+		let limiter = BandwidthLimiter::unlimited();
+		let protocol = limiter.protocol("/foo".into(), BandwidthPriority::Low);
+
+		protocol.try_upload(1_000_000).unwrap();
+		protocol.on_download(500);
+		assert!(protocol.download_delay().is_none());
+
+		assert_eq!(protocol.bytes(Direction::Out), 1_000_000);
+		assert_eq!(protocol.bytes(Direction::In), 500);
+		assert_eq!(protocol.throttled(Direction::Out), 0);
+		assert_eq!(limiter.protocols().len(), 1);
+	}
+
+	#[test]
+	fn high_priority_is_never_delayed_by_node_limits() {
+		let limiter = BandwidthLimiter::new(&limits(1000), HashMap::new());
+		let votes = limiter.protocol("/votes".into(), BandwidthPriority::High);
+		let gossip = limiter.protocol("/gossip".into(), BandwidthPriority::Normal);
+
+		votes.try_upload(5000).unwrap();
+		votes.try_upload(5000).unwrap();
+		// The votes used the whole bandwidth of the node.
+		assert!(gossip.try_upload(1).is_err());
+		assert_eq!(gossip.throttled(Direction::Out), 1);
+		assert_eq!(gossip.bytes(Direction::Out), 0);
+
+		votes.on_download(5000);
+		assert!(votes.download_delay().is_none());
+		assert!(gossip.download_delay().is_some());
+	}
+
+	#[test]
+	fn low_priority_leaves_a_reserve() {
+		let limiter = BandwidthLimiter::new(&limits(1000), HashMap::new());
+		let blocks = limiter.protocol("/blocks".into(), BandwidthPriority::Low);
+		let gossip = limiter.protocol("/gossip".into(), BandwidthPriority::Normal);
+
+		blocks.try_upload(800).unwrap();
+		// Less than the reserve of the node is left.
+		let wait = blocks.try_upload(1).unwrap_err();
+		assert!(wait >= MIN_DELAY && wait <= Duration::from_millis(100));
+		gossip.try_upload(300).unwrap();
+		assert!(gossip.try_upload(1).is_err());
+	}
+
+	#[test]
+	fn protocol_limits_apply_whatever_the_priority() {
+		let limiter = BandwidthLimiter::new(
+			&BandwidthLimits::default(),
+			[("/votes".into(), limits(1000))].into_iter().collect(),
+		);
+		let votes = limiter.protocol("/votes".into(), BandwidthPriority::High);
+		let gossip = limiter.protocol("/gossip".into(), BandwidthPriority::Normal);
+
+		votes.try_upload(2000).unwrap();
+		let wait = votes.try_upload(1).unwrap_err();
+		assert!(wait >= Duration::from_millis(900));
+		gossip.try_upload(2000).unwrap();
+	}
+}
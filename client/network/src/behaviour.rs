@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
+	bandwidth::BandwidthLimiter,
 	bitswap::Bitswap,
 	discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
 	peer_info,
@@ -228,6 +229,7 @@ where
 		// All remaining request protocol configs.
 		mut request_response_protocols: Vec<ProtocolConfig>,
 		peerset: PeersetHandle,
+		bandwidth: &BandwidthLimiter,
 	) -> Result<Self, request_responses::RegisterError> {
 		// Extract protocol name and add to `request_response_protocols`.
 		let block_request_protocol_name = block_request_protocol_config.name.to_string();
@@ -252,6 +254,7 @@ where
 			request_responses: request_responses::RequestResponsesBehaviour::new(
 				request_response_protocols.into_iter(),
 				peerset,
+				bandwidth,
 			)?,
 			events: VecDeque::new(),
 			block_request_protocol_name,
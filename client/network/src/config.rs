@@ -22,7 +22,7 @@
 //! See the documentation of [`Params`].
 
 pub use sc_network_common::{
-	config::ProtocolId,
+	config::{BandwidthPriority, ProtocolId},
 	request_responses::{
 		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
 	},
@@ -56,6 +56,7 @@ use std::{
 	future::Future,
 	io::{self, Write},
 	net::Ipv4Addr,
+	num::NonZeroU64,
 	path::{Path, PathBuf},
 	pin::Pin,
 	str,
@@ -397,6 +398,9 @@ impl Default for SyncMode {
 pub struct NetworkConfiguration {
 	/// Directory path to store network-specific configuration. None means nothing will be saved.
 	pub net_config_path: Option<PathBuf>,
+	/// Path of the file keeping the known peers and their reputation across restarts. `None`
+	/// means that they are not kept.
+	pub peer_store_path: Option<PathBuf>,
 	/// Multiaddresses to listen for incoming connections.
 	pub listen_addresses: Vec<Multiaddr>,
 	/// Multiaddresses to advertise. Detected automatically if empty.
@@ -422,10 +426,27 @@ pub struct NetworkConfiguration {
 	pub node_name: String,
 	/// Configuration for the transport layer.
 	pub transport: TransportConfig,
+	/// Certificate presented on the secure WebSocket (`/wss`) listen addresses. `None` means
+	/// that the node can't listen on `/wss` addresses.
+	pub wss_certificate: Option<WssCertificate>,
+	/// DER-encoded certificates of the authorities trusted when dialing `/wss` addresses, in
+	/// addition to the usual root certificate authorities of the web.
+	pub wss_trusted_certificates: Vec<Vec<u8>>,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
 	/// Initial syncing mode.
 	pub sync_mode: SyncMode,
+	/// Download the block history that is missing after a warp or fast sync in the background.
+	pub enable_gap_sync: bool,
+	/// Path of the journal used to resume a warp or fast sync after a restart. `None` means that
+	/// the downloaded state is only kept in memory.
+	pub state_sync_journal_path: Option<PathBuf>,
+	/// Limits of the bandwidth used by the notification and request-response protocols of the
+	/// node as a whole.
+	pub bandwidth_limits: BandwidthLimits,
+	/// Limits of the bandwidth used by individual notification or request-response protocols, by
+	/// protocol name.
+	pub protocol_bandwidth_limits: HashMap<Cow<'static, str>, BandwidthLimits>,
 
 	/// True if Kademlia random discovery should be enabled.
 	///
@@ -474,6 +495,7 @@ impl NetworkConfiguration {
 		let default_peers_set = SetConfig::default();
 		Self {
 			net_config_path,
+			peer_store_path: None,
 			listen_addresses: Vec::new(),
 			public_addresses: Vec::new(),
 			boot_nodes: Vec::new(),
@@ -485,8 +507,14 @@ impl NetworkConfiguration {
 			client_version: client_version.into(),
 			node_name: node_name.into(),
 			transport: TransportConfig::Normal { enable_mdns: false, allow_private_ipv4: true },
+			wss_certificate: None,
+			wss_trusted_certificates: Vec::new(),
 			max_parallel_downloads: 5,
 			sync_mode: SyncMode::Full,
+			enable_gap_sync: true,
+			state_sync_journal_path: None,
+			bandwidth_limits: BandwidthLimits::default(),
+			protocol_bandwidth_limits: HashMap::new(),
 			enable_dht_random_walk: true,
 			allow_non_globals_in_dht: false,
 			kademlia_disjoint_query_paths: false,
@@ -526,6 +554,15 @@ impl NetworkConfiguration {
 	}
 }
 
+/// Limits of the rate at which data is sent and received.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthLimits {
+	/// Maximum upload rate, in bytes per second. `None` for no limit.
+	pub max_upload_rate: Option<NonZeroU64>,
+	/// Maximum download rate, in bytes per second. `None` for no limit.
+	pub max_download_rate: Option<NonZeroU64>,
+}
+
 /// Configuration for a set of nodes.
 #[derive(Clone, Debug)]
 pub struct SetConfig {
@@ -572,6 +609,8 @@ pub struct NonDefaultSetConfig {
 	pub fallback_names: Vec<Cow<'static, str>>,
 	/// Maximum allowed size of single notifications.
 	pub max_notification_size: u64,
+	/// Priority of the notifications of this set when the bandwidth of the node is limited.
+	pub bandwidth_priority: BandwidthPriority,
 	/// Base configuration.
 	pub set_config: SetConfig,
 }
@@ -583,6 +622,7 @@ impl NonDefaultSetConfig {
 			notifications_protocol,
 			max_notification_size,
 			fallback_names: Vec::new(),
+			bandwidth_priority: BandwidthPriority::Normal,
 			set_config: SetConfig {
 				in_peers: 0,
 				out_peers: 0,
@@ -610,12 +650,20 @@ impl NonDefaultSetConfig {
 	pub fn add_fallback_names(&mut self, fallback_names: Vec<Cow<'static, str>>) {
 		self.fallback_names.extend(fallback_names);
 	}
+
+	/// Modifies the priority of the notifications of this set when the bandwidth of the node is
+	/// limited.
+	pub fn set_bandwidth_priority(&mut self, priority: BandwidthPriority) {
+		self.bandwidth_priority = priority;
+	}
 }
 
 /// Configuration for the transport layer.
 #[derive(Clone, Debug)]
 pub enum TransportConfig {
-	/// Normal transport mode.
+	/// Normal transport mode, over TCP, WebSocket and secure WebSocket.
+	///
+	/// QUIC is not supported yet, listening on a `/quic` address is an error.
 	Normal {
 		/// If true, the network will use mDNS to discover other libp2p nodes on the local network
 		/// and connect to them if they support the same chain.
@@ -632,6 +680,55 @@ pub enum TransportConfig {
 	MemoryOnly,
 }
 
+/// Certificate of the node for the secure WebSocket transport.
+#[derive(Clone)]
+pub struct WssCertificate {
+	/// DER-encoded X.509 certificate chain, starting with the certificate of the node.
+	pub certificate_chain: Vec<Vec<u8>>,
+	/// DER-encoded private key of the certificate, in either PKCS#8 or PKCS#1 format.
+	pub private_key: Vec<u8>,
+}
+
+impl WssCertificate {
+	/// Read the certificate chain and its private key from PEM files.
+	///
+	/// The private key is the first PKCS#8 or PKCS#1 key found in `private_key`.
+	pub fn from_pem_files(certificate_chain: &Path, private_key: &Path) -> io::Result<Self> {
+		let invalid_data = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+		let read = |path: &Path| -> io::Result<Vec<rustls_pemfile::Item>> {
+			rustls_pemfile::read_all(&mut io::BufReader::new(fs::File::open(path)?))
+		};
+
+		let certificate_chain = read(certificate_chain)?
+			.into_iter()
+			.filter_map(|item| match item {
+				rustls_pemfile::Item::X509Certificate(certificate) => Some(certificate),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		if certificate_chain.is_empty() {
+			return Err(invalid_data("no certificate found".into()))
+		}
+
+		let private_key = read(private_key)?
+			.into_iter()
+			.find_map(|item| match item {
+				rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) =>
+					Some(key),
+				_ => None,
+			})
+			.ok_or_else(|| invalid_data("no private key found".into()))?;
+
+		Ok(Self { certificate_chain, private_key })
+	}
+}
+
+impl fmt::Debug for WssCertificate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("WssCertificate").finish_non_exhaustive()
+	}
+}
+
 /// The policy for connections to non-reserved peers.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum NonReservedPeerMode {
@@ -832,4 +929,20 @@ mod tests {
 		let kp2 = NodeKeyConfig::Ed25519(Secret::New).into_keypair().unwrap();
 		assert!(secret_bytes(&kp1) != secret_bytes(&kp2));
 	}
+
+	#[test]
+	fn test_wss_certificate_from_pem_files() {
+		let res = Path::new(env!("CARGO_MANIFEST_DIR")).join("test").join("res");
+		let certificate =
+			WssCertificate::from_pem_files(&res.join("localhost.crt"), &res.join("localhost.key"))
+				.unwrap();
+		assert_eq!(certificate.certificate_chain.len(), 1);
+		assert!(crate::transport::build_wss_tls_config(Some(&certificate), &[]).is_ok());
+
+		// The certificate file doesn't contain a private key.
+		let err =
+			WssCertificate::from_pem_files(&res.join("localhost.crt"), &res.join("localhost.crt"))
+				.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
 }
@@ -95,6 +95,7 @@ const MAX_KNOWN_EXTERNAL_ADDRESSES: usize = 32;
 pub struct DiscoveryConfig {
 	local_peer_id: PeerId,
 	permanent_addresses: Vec<(PeerId, Multiaddr)>,
+	known_addresses: Vec<(PeerId, Multiaddr)>,
 	dht_random_walk: bool,
 	allow_private_ipv4: bool,
 	allow_non_globals_in_dht: bool,
@@ -110,6 +111,7 @@ impl DiscoveryConfig {
 		Self {
 			local_peer_id: local_public_key.to_peer_id(),
 			permanent_addresses: Vec::new(),
+			known_addresses: Vec::new(),
 			dht_random_walk: true,
 			allow_private_ipv4: true,
 			allow_non_globals_in_dht: false,
@@ -135,6 +137,17 @@ impl DiscoveryConfig {
 		self
 	}
 
+	/// Set addresses of nodes known from a previous run, e.g. read from the peer store.
+	///
+	/// Contrary to permanent addresses, they are removed if they fail to reach the node.
+	pub fn with_known_addresses<I>(&mut self, known_addresses: I) -> &mut Self
+	where
+		I: IntoIterator<Item = (PeerId, Multiaddr)>,
+	{
+		self.known_addresses.extend(known_addresses);
+		self
+	}
+
 	/// Whether the discovery behaviour should periodically perform a random
 	/// walk on the DHT to discover peers.
 	pub fn with_dht_random_walk(&mut self, value: bool) -> &mut Self {
@@ -184,6 +197,7 @@ impl DiscoveryConfig {
 		let Self {
 			local_peer_id,
 			permanent_addresses,
+			known_addresses,
 			dht_random_walk,
 			allow_private_ipv4,
 			allow_non_globals_in_dht,
@@ -209,7 +223,7 @@ impl DiscoveryConfig {
 				let store = MemoryStore::new(local_peer_id);
 				let mut kad = Kademlia::with_config(local_peer_id, store, config);
 
-				for (peer_id, addr) in &permanent_addresses {
+				for (peer_id, addr) in permanent_addresses.iter().chain(&known_addresses) {
 					kad.add_address(peer_id, addr.clone());
 				}
 
@@ -217,9 +231,17 @@ impl DiscoveryConfig {
 			})
 			.collect();
 
+		let mut ephemeral_addresses = HashMap::<_, Vec<_>>::new();
+		for (peer_id, addr) in known_addresses {
+			let addrs_list = ephemeral_addresses.entry(peer_id).or_default();
+			if !addrs_list.contains(&addr) {
+				addrs_list.push(addr);
+			}
+		}
+
 		DiscoveryBehaviour {
 			permanent_addresses,
-			ephemeral_addresses: HashMap::new(),
+			ephemeral_addresses,
 			kademlias,
 			next_kad_random_query: if dht_random_walk {
 				Some(Delay::new(Duration::new(0, 0)))
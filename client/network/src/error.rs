@@ -67,6 +67,9 @@ pub enum Error {
 		/// Name of the protocol registered multiple times.
 		protocol: Cow<'static, str>,
 	},
+	/// The certificate of the secure WebSocket transport can't be used.
+	#[error("Invalid secure WebSocket certificate: {0}")]
+	InvalidWssCertificate(#[source] libp2p::websocket::tls::Error),
 }
 
 // Make `Debug` use the `Display` implementation.
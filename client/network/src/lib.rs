@@ -244,9 +244,11 @@
 //!
 //! More precise usage details are still being worked on and will likely change in the future.
 
+mod bandwidth;
 mod behaviour;
 mod discovery;
 mod peer_info;
+mod peer_store;
 mod protocol;
 mod request_responses;
 mod schema;
@@ -0,0 +1,323 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Store keeping the known peers of the node across restarts.
+//!
+//! The addresses at which we managed to dial peers, the last time we were connected to them and
+//! their reputation in the peerset are written to a file at most once every [`WRITE_INTERVAL`],
+//! and when the network worker is dropped. On startup they seed the discovery mechanism and the
+//! peerset.
+//!
+//! Reputations don't decay while the node is stopped, so that peers banned right before a restart
+//! are still banned after it.
+
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use codec::{Decode, Encode};
+use libp2p::{Multiaddr, PeerId};
+
+/// Version of the format of the peer store file.
+const PEER_STORE_VERSION: u8 = 1;
+
+/// Minimum time between two writes of the peer store while the node is running.
+const WRITE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum number of peers kept in the store. The ones seen the most recently are kept.
+const MAX_PEERS: usize = 1000;
+
+/// Maximum number of addresses kept for each peer.
+const MAX_ADDRESSES: usize = 4;
+
+/// Peers that were not seen for this long are forgotten.
+const EXPIRY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Peer as encoded in the peer store file.
+#[derive(Encode, Decode)]
+struct StoredPeer {
+	peer_id: Vec<u8>,
+	addresses: Vec<Vec<u8>>,
+	last_seen: u64,
+	reputation: i32,
+}
+
+/// Peer known by the peer store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PeerEntry {
+	/// Addresses at which we managed to dial the peer, the most recent first.
+	pub addresses: Vec<Multiaddr>,
+	/// Last time we were connected to the peer or heard of it, in seconds since the UNIX epoch.
+	pub last_seen: u64,
+	/// Reputation of the peer in the peerset.
+	pub reputation: i32,
+}
+
+impl PeerEntry {
+	/// Returns true if the peer is banned by the peerset.
+	pub fn is_banned(&self) -> bool {
+		self.reputation < sc_peerset::BANNED_THRESHOLD
+	}
+}
+
+/// File store of the peers known by the node.
+pub(crate) struct PeerStore {
+	path: PathBuf,
+	peers: HashMap<PeerId, PeerEntry>,
+	last_write: Instant,
+}
+
+fn unix_time() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+impl PeerStore {
+	/// Open the peer store at `path` and read the peers it contains.
+	///
+	/// The store starts empty if the file does not exist or can't be read.
+	pub fn open(path: PathBuf) -> Self {
+		let peers = match Self::read(&path) {
+			Ok(peers) => peers,
+			Err(e) => {
+				log::warn!(
+					target: "sub-libp2p",
+					"Failed to read the peer store {:?}: {}",
+					path,
+					e,
+				);
+				HashMap::new()
+			},
+		};
+
+		Self { path, peers, last_write: Instant::now() }
+	}
+
+	fn read(path: &Path) -> io::Result<HashMap<PeerId, PeerEntry>> {
+		let data = match fs::read(path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+			Err(e) => return Err(e),
+		};
+
+		let invalid_data = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+		let stored: Vec<StoredPeer> = match data.split_first() {
+			Some((&PEER_STORE_VERSION, mut peers)) =>
+				Decode::decode(&mut peers).map_err(|e| invalid_data(e.to_string()))?,
+			Some((version, _)) =>
+				return Err(invalid_data(format!("unsupported peer store version {}", version))),
+			None => return Ok(HashMap::new()),
+		};
+
+		stored
+			.into_iter()
+			.map(|peer| {
+				let peer_id =
+					PeerId::from_bytes(&peer.peer_id).map_err(|e| invalid_data(e.to_string()))?;
+				let addresses = peer
+					.addresses
+					.into_iter()
+					.map(|addr| Multiaddr::try_from(addr).map_err(|e| invalid_data(e.to_string())))
+					.collect::<io::Result<_>>()?;
+				let entry =
+					PeerEntry { addresses, last_seen: peer.last_seen, reputation: peer.reputation };
+				Ok((peer_id, entry))
+			})
+			.collect()
+	}
+
+	/// Returns the peers of the store.
+	pub fn peers(&self) -> impl Iterator<Item = (&PeerId, &PeerEntry)> {
+		self.peers.iter()
+	}
+
+	/// Returns the reputation of the peers of the store, to be restored in the peerset.
+	pub fn reputations(&self) -> Vec<(PeerId, i32)> {
+		self.peers
+			.iter()
+			.filter(|(_, entry)| entry.reputation != 0)
+			.map(|(peer_id, entry)| (*peer_id, entry.reputation))
+			.collect()
+	}
+
+	/// Returns the addresses of the peers of the store that aren't banned.
+	pub fn known_addresses(&self) -> Vec<(PeerId, Multiaddr)> {
+		self.peers
+			.iter()
+			.filter(|(_, entry)| !entry.is_banned())
+			.flat_map(|(peer_id, entry)| {
+				entry.addresses.iter().map(|addr| (*peer_id, addr.clone()))
+			})
+			.collect()
+	}
+
+	/// Note that we are connected to `peer_id`, through a connection that we dialed at `address`
+	/// if any.
+	pub fn on_connected(&mut self, peer_id: PeerId, address: Option<Multiaddr>) {
+		let entry = self.peers.entry(peer_id).or_insert_with(|| PeerEntry {
+			addresses: Vec::new(),
+			last_seen: 0,
+			reputation: 0,
+		});
+		entry.last_seen = unix_time();
+
+		if let Some(address) = address {
+			entry.addresses.retain(|a| *a != address);
+			entry.addresses.insert(0, address);
+			entry.addresses.truncate(MAX_ADDRESSES);
+		}
+	}
+
+	/// Note that a connection to `peer_id` was closed.
+	pub fn on_disconnected(&mut self, peer_id: &PeerId) {
+		if let Some(entry) = self.peers.get_mut(peer_id) {
+			entry.last_seen = unix_time();
+		}
+	}
+
+	/// Replace the reputations of the peers with the ones of the peerset.
+	///
+	/// Peers that are unknown to the peerset have a reputation of zero.
+	pub fn set_reputations(&mut self, reputations: impl IntoIterator<Item = (PeerId, i32)>) {
+		for entry in self.peers.values_mut() {
+			entry.reputation = 0;
+		}
+
+		let now = unix_time();
+		for (peer_id, reputation) in reputations {
+			self.peers
+				.entry(peer_id)
+				.or_insert_with(|| PeerEntry {
+					addresses: Vec::new(),
+					last_seen: now,
+					reputation: 0,
+				})
+				.reputation = reputation;
+		}
+	}
+
+	/// Returns true if the store was not written for [`WRITE_INTERVAL`].
+	pub fn should_write(&self) -> bool {
+		self.last_write.elapsed() >= WRITE_INTERVAL
+	}
+
+	/// Write the peers to the store file.
+	///
+	/// The peers that can't be reached and have a neutral reputation, the ones that were not seen
+	/// for a long time and the ones in excess of [`MAX_PEERS`] are forgotten.
+	pub fn write(&mut self) -> io::Result<()> {
+		self.last_write = Instant::now();
+
+		let expiry = unix_time().saturating_sub(EXPIRY.as_secs());
+		self.peers.retain(|_, entry| {
+			(!entry.addresses.is_empty() || entry.reputation != 0) && entry.last_seen >= expiry
+		});
+		if self.peers.len() > MAX_PEERS {
+			let mut last_seen =
+				self.peers.values().map(|entry| entry.last_seen).collect::<Vec<_>>();
+			last_seen.sort_unstable_by(|a, b| b.cmp(a));
+			let oldest_kept = last_seen[MAX_PEERS - 1];
+			self.peers.retain(|_, entry| entry.last_seen >= oldest_kept);
+		}
+
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let stored = self
+			.peers
+			.iter()
+			.map(|(peer_id, entry)| StoredPeer {
+				peer_id: peer_id.to_bytes(),
+				addresses: entry.addresses.iter().map(|addr| addr.to_vec()).collect(),
+				last_seen: entry.last_seen,
+				reputation: entry.reputation,
+			})
+			.collect::<Vec<_>>();
+
+		// Write a new file first, so that the store is never left incomplete.
+		let mut data = vec![PEER_STORE_VERSION];
+		stored.encode_to(&mut data);
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, data)?;
+		fs::rename(tmp_path, &self.path)?;
+
+		log::debug!(target: "sub-libp2p", "Wrote {} peers to the peer store", stored.len());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_store_is_empty() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = PeerStore::open(dir.path().join("network").join("peers"));
+		assert_eq!(store.peers().count(), 0);
+	}
+
+	#[test]
+	fn peers_are_read_back() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("network").join("peers");
+		let dialed = PeerId::random();
+		let banned = PeerId::random();
+		let address: Multiaddr = "/ip4/1.2.3.4/tcp/30333".parse().unwrap();
+
+		let mut store = PeerStore::open(path.clone());
+		store.on_connected(dialed, Some(address.clone()));
+		store.on_connected(banned, None);
+		store.set_reputations(vec![(dialed, 10), (banned, sc_peerset::BANNED_THRESHOLD - 1)]);
+		store.write().unwrap();
+
+		let store = PeerStore::open(path);
+		assert_eq!(store.peers().count(), 2);
+		assert_eq!(store.known_addresses(), vec![(dialed, address)]);
+		let mut reputations = store.reputations();
+		reputations.sort_by_key(|(_, reputation)| *reputation);
+		assert_eq!(reputations, vec![(banned, sc_peerset::BANNED_THRESHOLD - 1), (dialed, 10)]);
+	}
+
+	#[test]
+	fn neutral_peers_without_address_are_forgotten() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers");
+		let peer_id = PeerId::random();
+
+		let mut store = PeerStore::open(path.clone());
+		store.on_connected(peer_id, None);
+		store.set_reputations(vec![(peer_id, 0)]);
+		store.write().unwrap();
+
+		assert_eq!(PeerStore::open(path).peers().count(), 0);
+	}
+
+	#[test]
+	fn unknown_version_is_rejected() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers");
+		fs::write(&path, [PEER_STORE_VERSION + 1, 0]).unwrap();
+
+		let err = PeerStore::read(&path).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
+}
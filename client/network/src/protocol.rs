@@ -17,7 +17,9 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
+	bandwidth::BandwidthLimiter,
 	config, error,
+	peer_store::PeerStore,
 	request_responses::RequestFailure,
 	utils::{interval, LruHashSet},
 	warp_request_handler::{EncodedProof, WarpSyncProvider},
@@ -48,7 +50,7 @@ use prometheus_endpoint::{register, Gauge, GaugeVec, Opts, PrometheusError, Regi
 use prost::Message as _;
 use sc_client_api::{BlockBackend, HeaderBackend, ProofProvider};
 use sc_consensus::import_queue::{BlockImportError, BlockImportStatus, IncomingBlock, Origin};
-use sc_network_common::config::ProtocolId;
+use sc_network_common::config::{BandwidthPriority, ProtocolId};
 use sc_network_sync::{
 	message::{
 		BlockAnnounce, BlockAttributes, BlockData, BlockRequest, BlockResponse, BlockState,
@@ -243,6 +245,8 @@ pub struct ProtocolConfig {
 	pub max_parallel_downloads: u32,
 	/// Enable state sync.
 	pub sync_mode: config::SyncMode,
+	/// Download the block history that is missing after a warp or fast sync.
+	pub gap_sync: bool,
 }
 
 impl ProtocolConfig {
@@ -262,7 +266,12 @@ impl ProtocolConfig {
 
 impl Default for ProtocolConfig {
 	fn default() -> ProtocolConfig {
-		Self { roles: Roles::FULL, max_parallel_downloads: 5, sync_mode: config::SyncMode::Full }
+		Self {
+			roles: Roles::FULL,
+			max_parallel_downloads: 5,
+			sync_mode: config::SyncMode::Full,
+			gap_sync: true,
+		}
 	}
 }
 
@@ -311,7 +320,10 @@ where
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		metrics_registry: Option<&Registry>,
 		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
-	) -> error::Result<(Protocol<B, Client>, sc_peerset::PeersetHandle, Vec<(PeerId, Multiaddr)>)> {
+		peer_store: Option<&PeerStore>,
+		bandwidth: &BandwidthLimiter,
+	) -> error::Result<(Protocol<B, Client>, sc_peerset::PeersetHandle, Vec<(PeerId, Multiaddr)>)>
+	{
 		let info = chain.info();
 		let sync = ChainSync::new(
 			config.sync_mode(),
@@ -319,6 +331,8 @@ where
 			block_announce_validator,
 			config.max_parallel_downloads,
 			warp_sync_provider,
+			config.gap_sync,
+			network_config.state_sync_journal_path.clone(),
 		)
 		.map_err(Box::new)?;
 
@@ -367,6 +381,14 @@ where
 				bootnodes.push(bootnode.peer_id);
 			}
 
+			// Peers of the store that we can reach are discovered like the bootnodes.
+			for (peer_id, entry) in peer_store.iter().flat_map(|store| store.peers()) {
+				if !entry.is_banned() && !entry.addresses.is_empty() && !bootnodes.contains(peer_id)
+				{
+					bootnodes.push(*peer_id);
+				}
+			}
+
 			// Set number 0 is used for block announces.
 			sets.push(sc_peerset::SetConfig {
 				in_peers: network_config.default_peers_set.in_peers,
@@ -396,7 +418,10 @@ where
 				});
 			}
 
-			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig { sets })
+			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig {
+				sets,
+				reputations: peer_store.map(|store| store.reputations()).unwrap_or_default(),
+			})
 		};
 
 		let block_announces_protocol: Cow<'static, str> =
@@ -412,10 +437,11 @@ where
 					.encode();
 
 			let sync_protocol_config = notifications::ProtocolConfig {
-				name: block_announces_protocol,
+				name: block_announces_protocol.clone(),
 				fallback_names: Vec::new(),
 				handshake: block_announces_handshake,
 				max_notification_size: MAX_BLOCK_ANNOUNCE_SIZE,
+				bandwidth: bandwidth.protocol(block_announces_protocol, BandwidthPriority::Normal),
 			};
 
 			Notifications::new(
@@ -427,6 +453,8 @@ where
 							fallback_names: s.fallback_names.clone(),
 							handshake: hs,
 							max_notification_size: s.max_notification_size,
+							bandwidth: bandwidth
+								.protocol(s.notifications_protocol.clone(), s.bandwidth_priority),
 						},
 					),
 				),
@@ -501,6 +529,11 @@ where
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the reputation of all the peers known by the peerset.
+	pub fn peerset_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.behaviour.peerset_reputations()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.peers.len()
@@ -610,6 +643,23 @@ where
 		request: BlockRequest<B>,
 		response: sc_network_sync::schema::v1::BlockResponse,
 	) -> CustomMessageOutcome<B> {
+		let decode_from = |from: &[u8]| -> Result<Option<NumberFor<B>>, codec::Error> {
+			if from.is_empty() {
+				Ok(None)
+			} else {
+				Decode::decode(&mut &from[..]).map(Some)
+			}
+		};
+		match (decode_from(&response.bodies_from), decode_from(&response.justifications_from)) {
+			(Ok(bodies_from), Ok(justifications_from)) =>
+				self.sync.on_peer_ranges(&peer_id, bodies_from, justifications_from),
+			(Err(err), _) | (_, Err(err)) => {
+				debug!(target: "sync", "Failed to decode block response from {}: {}", peer_id, err);
+				self.peerset_handle.report_peer(peer_id, rep::BAD_MESSAGE);
+				return CustomMessageOutcome::None
+			},
+		}
+
 		let blocks = response
 			.blocks
 			.into_iter()
@@ -1527,7 +1577,8 @@ where
 								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
 							},
 							RequestFailure::Network(OutboundFailure::ConnectionClosed) |
-							RequestFailure::NotConnected => {
+							RequestFailure::NotConnected |
+							RequestFailure::Throttled => {
 								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
 							},
 							RequestFailure::UnknownProtocol => {
@@ -1579,7 +1630,7 @@ where
 			let event = prepare_block_request(&mut self.peers, *id, request);
 			self.pending_messages.push_back(event);
 		}
-		if let Some((id, request)) = self.sync.state_request() {
+		for (id, request) in self.sync.state_requests() {
 			let event = prepare_state_request(&mut self.peers, id, request);
 			self.pending_messages.push_back(event);
 		}
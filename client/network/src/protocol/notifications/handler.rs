@@ -57,9 +57,12 @@
 //! It is illegal to send a [`NotifsHandlerIn::Open`] before a previously-emitted
 //! [`NotifsHandlerIn::Open`] has gotten an answer.
 
-use crate::protocol::notifications::upgrade::{
-	NotificationsHandshakeError, NotificationsIn, NotificationsInSubstream, NotificationsOut,
-	NotificationsOutSubstream, UpgradeCollec,
+use crate::{
+	bandwidth::ProtocolBandwidth,
+	protocol::notifications::upgrade::{
+		NotificationsHandshakeError, NotificationsIn, NotificationsInSubstream, NotificationsOut,
+		NotificationsOutSubstream, UpgradeCollec,
+	},
 };
 
 use bytes::BytesMut;
@@ -68,6 +71,7 @@ use futures::{
 	lock::{Mutex as FuturesMutex, MutexGuard as FuturesMutexGuard},
 	prelude::*,
 };
+use futures_timer::Delay;
 use libp2p::{
 	core::{
 		upgrade::{InboundUpgrade, OutboundUpgrade},
@@ -153,6 +157,8 @@ pub struct ProtocolConfig {
 	pub handshake: Arc<RwLock<Vec<u8>>>,
 	/// Maximum allowed size for a notification.
 	pub max_notification_size: u64,
+	/// Counts the notifications against the bandwidth limits.
+	pub bandwidth: Arc<ProtocolBandwidth>,
 }
 
 /// Fields specific for each individual protocol.
@@ -165,6 +171,12 @@ struct Protocol {
 
 	/// Current state of the substreams for this protocol.
 	state: State,
+
+	/// If `Some`, no notification is sent until the bandwidth limits might allow it again.
+	upload_delay: Option<Delay>,
+
+	/// If `Some`, no notification is received until the bandwidth limits might allow it again.
+	download_delay: Option<Delay>,
 }
 
 /// See the module-level documentation to learn about the meaning of these variants.
@@ -253,7 +265,13 @@ impl IntoConnectionHandler for NotifsHandlerProto {
 						config.max_notification_size,
 					);
 
-					Protocol { config, in_upgrade, state: State::Closed { pending_opening: false } }
+					Protocol {
+						config,
+						in_upgrade,
+						state: State::Closed { pending_opening: false },
+						upload_delay: None,
+						download_delay: None,
+					}
 				})
 				.collect(),
 			peer_id: *peer_id,
@@ -731,22 +749,31 @@ impl ConnectionHandler for NotifsHandler {
 		// For each open substream, try send messages from `notifications_sink_rx` to the
 		// substream.
 		for protocol_index in 0..self.protocols.len() {
+			let Protocol { config, state, upload_delay, .. } = &mut self.protocols[protocol_index];
 			if let State::Open {
 				notifications_sink_rx, out_substream: Some(out_substream), ..
-			} = &mut self.protocols[protocol_index].state
+			} = state
 			{
+				if let Some(delay) = upload_delay {
+					if delay.poll_unpin(cx).is_pending() {
+						continue
+					}
+					*upload_delay = None;
+				}
+
 				loop {
 					// Only proceed with `out_substream.poll_ready_unpin` if there is an element
 					// available in `notifications_sink_rx`. This avoids waking up the task when
 					// a substream is ready to send if there isn't actually something to send.
-					match Pin::new(&mut *notifications_sink_rx).as_mut().poll_peek(cx) {
+					let size = match Pin::new(&mut *notifications_sink_rx).as_mut().poll_peek(cx) {
 						Poll::Ready(Some(&NotificationsSinkMessage::ForceClose)) =>
 							return Poll::Ready(ConnectionHandlerEvent::Close(
 								NotifsHandlerError::SyncNotificationsClogged,
 							)),
-						Poll::Ready(Some(&NotificationsSinkMessage::Notification { .. })) => {},
+						Poll::Ready(Some(NotificationsSinkMessage::Notification { message })) =>
+							message.len(),
 						Poll::Ready(None) | Poll::Pending => break,
-					}
+					};
 
 					// Before we extract the element from `notifications_sink_rx`, check that the
 					// substream is ready to accept a message.
@@ -755,6 +782,16 @@ impl ConnectionHandler for NotifsHandler {
 						Poll::Pending => break,
 					}
 
+					// Leave the message in `notifications_sink_rx` if the bandwidth limits are
+					// reached.
+					if let Err(wait) = config.bandwidth.try_upload(size) {
+						let mut delay = Delay::new(wait);
+						// Register the waker.
+						let _ = delay.poll_unpin(cx);
+						*upload_delay = Some(delay);
+						break
+					}
+
 					// Now that the substream is ready for a message, grab what to send.
 					let message = match notifications_sink_rx.poll_next_unpin(cx) {
 						Poll::Ready(Some(NotificationsSinkMessage::Notification { message })) =>
@@ -803,22 +840,42 @@ impl ConnectionHandler for NotifsHandler {
 
 		// Poll inbound substreams.
 		for protocol_index in 0..self.protocols.len() {
+			let Protocol { config, state, download_delay, .. } =
+				&mut self.protocols[protocol_index];
 			// Inbound substreams being closed is always tolerated, except for the
 			// `OpenDesiredByRemote` state which might need to be switched back to `Closed`.
-			match &mut self.protocols[protocol_index].state {
+			match state {
 				State::Closed { .. } |
 				State::Open { in_substream: None, .. } |
 				State::Opening { in_substream: None } => {},
 
-				State::Open { in_substream: in_substream @ Some(_), .. } =>
+				State::Open { in_substream: in_substream @ Some(_), .. } => {
+					// Stop reading from the substream while the bandwidth limits are reached,
+					// which in turn slows down the remote.
+					if let Some(delay) = download_delay {
+						if delay.poll_unpin(cx).is_pending() {
+							continue
+						}
+						*download_delay = None;
+					}
+					if let Some(wait) = config.bandwidth.download_delay() {
+						let mut delay = Delay::new(wait);
+						// Register the waker.
+						let _ = delay.poll_unpin(cx);
+						*download_delay = Some(delay);
+						continue
+					}
+
 					match Stream::poll_next(Pin::new(in_substream.as_mut().unwrap()), cx) {
 						Poll::Pending => {},
 						Poll::Ready(Some(Ok(message))) => {
+							config.bandwidth.on_download(message.len());
 							let event = NotifsHandlerOut::Notification { protocol_index, message };
 							return Poll::Ready(ConnectionHandlerEvent::Custom(event))
 						},
 						Poll::Ready(None) | Poll::Ready(Some(Err(_))) => *in_substream = None,
-					},
+					}
+				},
 
 				State::OpenDesiredByRemote { in_substream, pending_opening } =>
 					match NotificationsInSubstream::poll_process(Pin::new(in_substream), cx) {
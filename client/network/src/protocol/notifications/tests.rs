@@ -18,7 +18,11 @@
 
 #![cfg(test)]
 
-use crate::protocol::notifications::{Notifications, NotificationsOut, ProtocolConfig};
+use crate::{
+	bandwidth::BandwidthLimiter,
+	config::BandwidthPriority,
+	protocol::notifications::{Notifications, NotificationsOut, ProtocolConfig},
+};
 
 use futures::prelude::*;
 use libp2p::{
@@ -75,6 +79,7 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		});
 
 		let behaviour = CustomProtoWithAddr {
@@ -85,6 +90,8 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 					fallback_names: Vec::new(),
 					handshake: Vec::new(),
 					max_notification_size: 1024 * 1024,
+					bandwidth: BandwidthLimiter::unlimited()
+						.protocol("/foo".into(), BandwidthPriority::Normal),
 				}),
 			),
 			addrs: addrs
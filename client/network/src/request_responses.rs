@@ -33,12 +33,19 @@
 //!
 //! - If provided, a ["requests processing"](ProtocolConfig::inbound_queue) channel
 //! is used to handle incoming requests.
+//!
+//! - Requests and responses are held back while the bandwidth limits of their protocol are
+//! reached, and no new request is sent while the download limits are reached.
 
-use crate::ReputationChange;
+use crate::{
+	bandwidth::{BandwidthLimiter, ProtocolBandwidth},
+	ReputationChange,
+};
 use futures::{
 	channel::{mpsc, oneshot},
 	prelude::*,
 };
+use futures_timer::Delay;
 use libp2p::{
 	core::{
 		connection::{ConnectionId, ListenerId},
@@ -56,9 +63,10 @@ use libp2p::{
 use sc_network_common::request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig};
 use std::{
 	borrow::Cow,
-	collections::{hash_map::Entry, HashMap},
+	collections::{hash_map::Entry, HashMap, VecDeque},
 	io, iter,
 	pin::Pin,
+	sync::Arc,
 	task::{Context, Poll},
 	time::{Duration, Instant},
 };
@@ -66,6 +74,9 @@ use std::{
 pub use libp2p::request_response::{InboundFailure, OutboundFailure, RequestId};
 use sc_peerset::{PeersetHandle, BANNED_THRESHOLD};
 
+/// Maximum number of requests and responses of a protocol held back by the bandwidth limits.
+const MAX_THROTTLED: usize = 1024;
+
 /// Event generated by the [`RequestResponsesBehaviour`].
 #[derive(Debug)]
 pub enum Event {
@@ -174,6 +185,83 @@ pub struct RequestResponsesBehaviour {
 	/// Pending message request, holds `MessageRequest` as a Future state to poll it
 	/// until we get a response from `Peerset`
 	message_request: Option<MessageRequest>,
+
+	/// Bandwidth accounting of the protocols, by name.
+	throttles: HashMap<Cow<'static, str>, ProtocolThrottle>,
+
+	/// Fires when the requests and responses held back by the bandwidth limits should be tried
+	/// again, with the time at which it fires.
+	throttle_delay: Option<(Instant, Delay)>,
+}
+
+/// Bandwidth accounting of a protocol.
+struct ProtocolThrottle {
+	bandwidth: Arc<ProtocolBandwidth>,
+	/// Requests and responses held back by the bandwidth limits, in the order they were emitted.
+	queue: VecDeque<Throttled>,
+}
+
+impl ProtocolThrottle {
+	/// Holds back a message until the bandwidth limits allow it.
+	///
+	/// If [`MAX_THROTTLED`] messages are held back already, the message is dropped and a request
+	/// fails with [`RequestFailure::Throttled`].
+	fn push(&mut self, protocol_name: &str, message: Throttled) {
+		if self.queue.len() >= MAX_THROTTLED {
+			self.queue.retain(|message| !message.is_obsolete());
+		}
+		if self.queue.len() < MAX_THROTTLED {
+			self.queue.push_back(message);
+			return
+		}
+
+		log::debug!(
+			target: "sub-libp2p",
+			"Too many messages held back by the bandwidth limits on protocol {:?}, dropping one",
+			protocol_name,
+		);
+		if let Throttled::Request { pending_response, .. } = message {
+			let _ = pending_response.send(Err(RequestFailure::Throttled));
+		}
+	}
+}
+
+/// Request or response held back by the bandwidth limits.
+enum Throttled {
+	Request {
+		target: PeerId,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+		connect: IfDisconnected,
+	},
+	Response {
+		request_id: RequestId,
+		inner_channel: ResponseChannel<Result<Vec<u8>, ()>>,
+		payload: Vec<u8>,
+		sent_feedback: Option<oneshot::Sender<()>>,
+	},
+}
+
+impl Throttled {
+	/// Returns true if nobody is waiting for the message anymore.
+	fn is_obsolete(&self) -> bool {
+		match self {
+			Self::Request { pending_response, .. } => pending_response.is_canceled(),
+			Self::Response { inner_channel, .. } => !inner_channel.is_open(),
+		}
+	}
+
+	/// Counts the message against the bandwidth limits of its protocol.
+	fn try_send(&self, bandwidth: &ProtocolBandwidth) -> Result<(), Duration> {
+		match self {
+			// Don't ask for more data while the download limits are reached.
+			Self::Request { request, .. } => match bandwidth.download_delay() {
+				Some(wait) => Err(wait),
+				None => bandwidth.try_upload(request.len()),
+			},
+			Self::Response { payload, .. } => bandwidth.try_upload(payload.len()),
+		}
+	}
 }
 
 // This is a state of processing incoming request Message.
@@ -205,8 +293,10 @@ impl RequestResponsesBehaviour {
 	pub fn new(
 		list: impl Iterator<Item = ProtocolConfig>,
 		peerset: PeersetHandle,
+		bandwidth: &BandwidthLimiter,
 	) -> Result<Self, RegisterError> {
 		let mut protocols = HashMap::new();
+		let mut throttles = HashMap::new();
 		for protocol in list {
 			let mut cfg = RequestResponseConfig::default();
 			cfg.set_connection_keep_alive(Duration::from_secs(10));
@@ -227,10 +317,15 @@ impl RequestResponsesBehaviour {
 				cfg,
 			);
 
-			match protocols.entry(protocol.name) {
+			match protocols.entry(protocol.name.clone()) {
 				Entry::Vacant(e) => e.insert((rq_rp, protocol.inbound_queue)),
 				Entry::Occupied(e) => return Err(RegisterError::DuplicateProtocol(e.key().clone())),
 			};
+			let throttle = ProtocolThrottle {
+				bandwidth: bandwidth.protocol(protocol.name.clone(), protocol.bandwidth_priority),
+				queue: VecDeque::new(),
+			};
+			throttles.insert(protocol.name, throttle);
 		}
 
 		Ok(Self {
@@ -241,6 +336,8 @@ impl RequestResponsesBehaviour {
 			send_feedback: Default::default(),
 			peerset,
 			message_request: None,
+			throttles,
+			throttle_delay: None,
 		})
 	}
 
@@ -260,12 +357,20 @@ impl RequestResponsesBehaviour {
 	) {
 		if let Some((protocol, _)) = self.protocols.get_mut(protocol_name) {
 			if protocol.is_connected(target) || connect.should_connect() {
-				let request_id = protocol.send_request(target, request);
-				let prev_req_id = self.pending_requests.insert(
-					(protocol_name.to_string().into(), request_id).into(),
-					(Instant::now(), pending_response),
-				);
-				debug_assert!(prev_req_id.is_none(), "Expect request id to be unique.");
+				let throttle =
+					self.throttles.get_mut(protocol_name).expect("same keys as `protocols`; qed");
+				let request =
+					Throttled::Request { target: *target, request, pending_response, connect };
+				if throttle.queue.is_empty() {
+					match request.try_send(&throttle.bandwidth) {
+						Ok(()) => {
+							self.send_throttled(Cow::Owned(protocol_name.to_string()), request);
+							return
+						},
+						Err(wait) => schedule_retry(&mut self.throttle_delay, wait),
+					}
+				}
+				throttle.push(protocol_name, request);
 			} else if pending_response.send(Err(RequestFailure::NotConnected)).is_err() {
 				log::debug!(
 					target: "sub-libp2p",
@@ -284,6 +389,72 @@ impl RequestResponsesBehaviour {
 		}
 	}
 
+	/// Sends a request or response that is allowed by the bandwidth limits.
+	fn send_throttled(&mut self, protocol_name: Cow<'static, str>, message: Throttled) {
+		let protocol = match self.protocols.get_mut(&protocol_name) {
+			Some((protocol, _)) => protocol,
+			None => return,
+		};
+
+		match message {
+			Throttled::Request { target, request, pending_response, connect } => {
+				if pending_response.is_canceled() {
+					return
+				}
+				// The peer might have disconnected while the request was held back.
+				if !protocol.is_connected(&target) && !connect.should_connect() {
+					let _ = pending_response.send(Err(RequestFailure::NotConnected));
+					return
+				}
+
+				let request_id = protocol.send_request(&target, request);
+				let prev_req_id = self
+					.pending_requests
+					.insert((protocol_name, request_id).into(), (Instant::now(), pending_response));
+				debug_assert!(prev_req_id.is_none(), "Expect request id to be unique.");
+			},
+			Throttled::Response { request_id, inner_channel, payload, sent_feedback } => {
+				if protocol.send_response(inner_channel, Ok(payload)).is_err() {
+					// Note: Failure is handled further below when receiving
+					// `InboundFailure` event from `RequestResponse` behaviour.
+					log::debug!(
+						target: "sub-libp2p",
+						"Failed to send response for {:?} on protocol {:?} due to a \
+						 timeout or due to the connection to the peer being closed. \
+						 Dropping response",
+						request_id, protocol_name,
+					);
+				} else if let Some(sent_feedback) = sent_feedback {
+					self.send_feedback.insert((protocol_name, request_id).into(), sent_feedback);
+				}
+			},
+		}
+	}
+
+	/// Sends the requests and responses held back by the bandwidth limits that are now allowed.
+	fn send_throttled_queues(&mut self) {
+		let mut allowed = Vec::new();
+		for (protocol_name, throttle) in &mut self.throttles {
+			while let Some(message) = throttle.queue.front() {
+				// Don't count the messages nobody is waiting for anymore.
+				if message.is_obsolete() {
+					throttle.queue.pop_front();
+					continue
+				}
+				if let Err(wait) = message.try_send(&throttle.bandwidth) {
+					schedule_retry(&mut self.throttle_delay, wait);
+					break
+				}
+				let message = throttle.queue.pop_front().expect("front is `Some`; qed");
+				allowed.push((protocol_name.clone(), message));
+			}
+		}
+
+		for (protocol_name, message) in allowed {
+			self.send_throttled(protocol_name, message);
+		}
+	}
+
 	fn new_handler_with_replacement(
 		&mut self,
 		protocol: String,
@@ -554,20 +725,23 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 				};
 
 				if let Ok(payload) = result {
-					if let Some((protocol, _)) = self.protocols.get_mut(&*protocol_name) {
-						if protocol.send_response(inner_channel, Ok(payload)).is_err() {
-							// Note: Failure is handled further below when receiving
-							// `InboundFailure` event from `RequestResponse` behaviour.
-							log::debug!(
-								target: "sub-libp2p",
-								"Failed to send response for {:?} on protocol {:?} due to a \
-								 timeout or due to the connection to the peer being closed. \
-								 Dropping response",
-								request_id, protocol_name,
-							);
-						} else if let Some(sent_feedback) = sent_feedback {
-							self.send_feedback
-								.insert((protocol_name, request_id).into(), sent_feedback);
+					if let Some(throttle) = self.throttles.get_mut(&*protocol_name) {
+						let response = Throttled::Response {
+							request_id,
+							inner_channel,
+							payload,
+							sent_feedback,
+						};
+						if throttle.queue.is_empty() {
+							match response.try_send(&throttle.bandwidth) {
+								Ok(()) => self.send_throttled(protocol_name, response),
+								Err(wait) => {
+									schedule_retry(&mut self.throttle_delay, wait);
+									throttle.push(&protocol_name, response);
+								},
+							}
+						} else {
+							throttle.push(&protocol_name, response);
 						}
 					}
 				}
@@ -581,6 +755,8 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 
 			// Poll request-responses protocols.
 			for (protocol, (behaviour, resp_builder)) in &mut self.protocols {
+				let bandwidth =
+					&self.throttles.get(protocol).expect("same keys as `protocols`; qed").bandwidth;
 				while let Poll::Ready(ev) = behaviour.poll(cx, params) {
 					let ev = match ev {
 						// Main events we are interested in.
@@ -623,6 +799,7 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 							message:
 								RequestResponseMessage::Request { request_id, request, channel, .. },
 						} => {
+							bandwidth.on_download(request.len());
 							self.pending_responses_arrival_time
 								.insert((protocol.clone(), request_id).into(), Instant::now());
 
@@ -653,6 +830,7 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 							message: RequestResponseMessage::Response { request_id, response },
 							..
 						} => {
+							bandwidth.on_download(response.as_ref().map_or(0, |r| r.len()));
 							let (started, delivered) = match self
 								.pending_requests
 								.remove(&(protocol.clone(), request_id).into())
@@ -775,11 +953,31 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 				}
 			}
 
+			// Send the requests and responses held back by the bandwidth limits once they might
+			// be allowed.
+			if let Some((_, delay)) = &mut self.throttle_delay {
+				if delay.poll_unpin(cx).is_ready() {
+					self.throttle_delay = None;
+					self.send_throttled_queues();
+					continue 'poll_all
+				}
+			}
+
 			break Poll::Pending
 		}
 	}
 }
 
+/// Makes sure that the requests and responses held back by the bandwidth limits are tried again
+/// after `wait`.
+fn schedule_retry(throttle_delay: &mut Option<(Instant, Delay)>, wait: Duration) {
+	let deadline = Instant::now() + wait;
+	match throttle_delay {
+		Some((current, _)) if *current <= deadline => {},
+		_ => *throttle_delay = Some((deadline, Delay::new(wait))),
+	}
+}
+
 /// Error when registering a protocol.
 #[derive(Debug, thiserror::Error)]
 pub enum RegisterError {
@@ -800,6 +998,8 @@ pub enum RequestFailure {
 	Refused,
 	#[error("The remote replied, but the local node is no longer interested in the response.")]
 	Obsolete,
+	#[error("Too many requests are held back by the bandwidth limits.")]
+	Throttled,
 	/// Problem on the network.
 	#[error("Problem on the network: {0}")]
 	Network(OutboundFailure),
@@ -942,6 +1142,7 @@ impl RequestResponseCodec for GenericCodec {
 mod tests {
 	use super::*;
 
+	use crate::config::{BandwidthLimits, BandwidthPriority};
 	use futures::{
 		channel::{mpsc, oneshot},
 		executor::LocalPool,
@@ -958,10 +1159,17 @@ mod tests {
 		Multiaddr,
 	};
 	use sc_peerset::{Peerset, PeersetConfig, SetConfig};
-	use std::{iter, time::Duration};
+	use std::{iter, num::NonZeroU64, time::Duration};
 
 	fn build_swarm(
 		list: impl Iterator<Item = ProtocolConfig>,
+	) -> (Swarm<RequestResponsesBehaviour>, Multiaddr, Peerset) {
+		build_swarm_with_bandwidth(list, &BandwidthLimiter::unlimited())
+	}
+
+	fn build_swarm_with_bandwidth(
+		list: impl Iterator<Item = ProtocolConfig>,
+		bandwidth: &BandwidthLimiter,
 	) -> (Swarm<RequestResponsesBehaviour>, Multiaddr, Peerset) {
 		let keypair = Keypair::generate_ed25519();
 
@@ -982,11 +1190,12 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
 
-		let behaviour = RequestResponsesBehaviour::new(list, handle).unwrap();
+		let behaviour = RequestResponsesBehaviour::new(list, handle, bandwidth).unwrap();
 
 		let mut swarm = Swarm::new(transport, behaviour, keypair.public().to_peer_id());
 		let listen_addr: Multiaddr = format!("/memory/{}", rand::random::<u64>()).parse().unwrap();
@@ -1034,6 +1243,7 @@ mod tests {
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: Some(tx),
+					bandwidth_priority: BandwidthPriority::Normal,
 				};
 
 				build_swarm(iter::once(protocol_config))
@@ -1101,6 +1311,104 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn requests_are_held_back_by_bandwidth_limits() {
+		let protocol_name = "/test/req-resp/1";
+		let mut pool = LocalPool::new();
+
+		let protocol_config = |inbound_queue| ProtocolConfig {
+			name: From::from(protocol_name),
+			max_request_size: 1024,
+			max_response_size: 1024 * 1024,
+			request_timeout: Duration::from_secs(30),
+			inbound_queue,
+			bandwidth_priority: BandwidthPriority::Normal,
+		};
+
+		// `swarm[0]` answers the requests.
+		let (tx, mut rx) = mpsc::channel::<IncomingRequest>(64);
+		pool.spawner()
+			.spawn_obj(
+				async move {
+					while let Some(rq) = rx.next().await {
+						let _ = rq.pending_response.send(super::OutgoingResponse {
+							result: Ok(rq.payload),
+							reputation_changes: Vec::new(),
+							sent_feedback: None,
+						});
+					}
+				}
+				.boxed()
+				.into(),
+			)
+			.unwrap();
+		let (mut server, server_addr, peerset) = build_swarm(iter::once(protocol_config(Some(tx))));
+		pool.spawner().spawn_obj(loop_peerset(peerset).boxed().into()).unwrap();
+		pool.spawner()
+			.spawn_obj(
+				async move {
+					loop {
+						server.select_next_some().await;
+					}
+				}
+				.boxed()
+				.into(),
+			)
+			.unwrap();
+
+		// `swarm[1]` can send 100 bytes per second on the protocol.
+		let limits =
+			BandwidthLimits { max_upload_rate: NonZeroU64::new(100), max_download_rate: None };
+		let bandwidth = BandwidthLimiter::new(
+			&Default::default(),
+			iter::once((protocol_name.into(), limits)).collect(),
+		);
+		let (mut swarm, _, peerset) =
+			build_swarm_with_bandwidth(iter::once(protocol_config(None)), &bandwidth);
+		pool.spawner().spawn_obj(loop_peerset(peerset).boxed().into()).unwrap();
+		Swarm::dial(&mut swarm, server_addr).unwrap();
+
+		let started = Instant::now();
+		pool.run_until(async move {
+			let mut receivers = Vec::new();
+			let mut finished = 0;
+
+			while finished < 3 {
+				match swarm.select_next_some().await {
+					SwarmEvent::ConnectionEstablished { peer_id, .. } =>
+						for i in 0..3 {
+							let (sender, receiver) = oneshot::channel();
+							swarm.behaviour_mut().send_request(
+								&peer_id,
+								protocol_name,
+								vec![i; 100],
+								sender,
+								IfDisconnected::ImmediateError,
+							);
+							receivers.push(receiver);
+						},
+					SwarmEvent::Behaviour(Event::RequestFinished { result, .. }) => {
+						result.unwrap();
+						finished += 1;
+					},
+					_ => {},
+				}
+			}
+
+			for (i, receiver) in receivers.into_iter().enumerate() {
+				assert_eq!(receiver.await.unwrap().unwrap(), vec![i as u8; 100]);
+			}
+		});
+
+		// The first two requests empty the bucket of the protocol, which takes a second to refill
+		// before the third one is sent.
+		assert!(started.elapsed() >= Duration::from_millis(900));
+		let protocol = &bandwidth.protocols()[0];
+		assert_eq!(protocol.bytes(crate::bandwidth::Direction::Out), 300);
+		assert_eq!(protocol.bytes(crate::bandwidth::Direction::In), 300);
+		assert!(protocol.throttled(crate::bandwidth::Direction::Out) > 0);
+	}
+
 	#[test]
 	fn max_response_size_exceeded() {
 		let protocol_name = "/test/req-resp/1";
@@ -1134,6 +1442,7 @@ mod tests {
 					max_response_size: 8, // <-- important for the test
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: Some(tx),
+					bandwidth_priority: BandwidthPriority::Normal,
 				};
 
 				build_swarm(iter::once(protocol_config))
@@ -1230,6 +1539,7 @@ mod tests {
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: None,
+					bandwidth_priority: BandwidthPriority::Normal,
 				},
 				ProtocolConfig {
 					name: From::from(protocol_name_2),
@@ -1237,6 +1547,7 @@ mod tests {
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: None,
+					bandwidth_priority: BandwidthPriority::Normal,
 				},
 			];
 
@@ -1254,6 +1565,7 @@ mod tests {
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: Some(tx_1),
+					bandwidth_priority: BandwidthPriority::Normal,
 				},
 				ProtocolConfig {
 					name: From::from(protocol_name_2),
@@ -1261,6 +1573,7 @@ mod tests {
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
 					inbound_queue: Some(tx_2),
+					bandwidth_priority: BandwidthPriority::Normal,
 				},
 			];
 
@@ -1369,4 +1682,44 @@ mod tests {
 			assert_eq!(response_receiver_2.await.unwrap().unwrap(), b"this is a response");
 		});
 	}
+
+	#[test]
+	fn throttled_queue_is_bounded() {
+		let new_request = || {
+			let (sender, receiver) = oneshot::channel();
+			let request = Throttled::Request {
+				target: PeerId::random(),
+				request: Vec::new(),
+				pending_response: sender,
+				connect: IfDisconnected::ImmediateError,
+			};
+			(request, receiver)
+		};
+
+		let mut throttle = ProtocolThrottle {
+			bandwidth: BandwidthLimiter::unlimited()
+				.protocol("/test/req-resp/1".into(), BandwidthPriority::Normal),
+			queue: VecDeque::new(),
+		};
+		let mut receivers = Vec::new();
+		for _ in 0..MAX_THROTTLED {
+			let (request, receiver) = new_request();
+			throttle.push("test", request);
+			receivers.push(receiver);
+		}
+
+		// The queue is full.
+		let (request, mut receiver) = new_request();
+		throttle.push("test", request);
+		assert_eq!(throttle.queue.len(), MAX_THROTTLED);
+		assert!(matches!(receiver.try_recv(), Ok(Some(Err(RequestFailure::Throttled)))));
+
+		// The requests nobody is waiting for anymore make room.
+		receivers.truncate(MAX_THROTTLED - 1);
+		let (request, mut receiver) = new_request();
+		throttle.push("test", request);
+		assert_eq!(throttle.queue.len(), MAX_THROTTLED);
+		assert!(matches!(receiver.try_recv(), Ok(None)));
+		assert!(throttle.queue.iter().all(|message| !message.is_obsolete()));
+	}
 }
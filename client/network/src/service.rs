@@ -28,6 +28,7 @@
 //! which is then processed by [`NetworkWorker::poll`].
 
 use crate::{
+	bandwidth::BandwidthLimiter,
 	behaviour::{self, Behaviour, BehaviourOut},
 	bitswap::Bitswap,
 	config::{parse_str_addr, Params, TransportConfig},
@@ -36,6 +37,7 @@ use crate::{
 	network_state::{
 		NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer,
 	},
+	peer_store::PeerStore,
 	protocol::{
 		self, event::Event, message::generic::Roles, NotificationsSink, NotifsHandlerError,
 		PeerInfo, Protocol, Ready,
@@ -153,6 +155,20 @@ where
 			params.network_config.listen_addresses.iter(),
 			&params.network_config.transport,
 		)?;
+		// There is no QUIC transport, see `build_transport`.
+		let quic_addresses: Vec<_> = params
+			.network_config
+			.listen_addresses
+			.iter()
+			.filter(|x| x.iter().any(|y| matches!(y, libp2p::core::multiaddr::Protocol::Quic)))
+			.cloned()
+			.collect();
+		if !quic_addresses.is_empty() {
+			return Err(Error::AddressesForAnotherTransport {
+				transport: params.network_config.transport.clone(),
+				addresses: quic_addresses,
+			})
+		}
 		ensure_addresses_consistent_with_transport(
 			params.network_config.boot_nodes.iter().map(|x| &x.multiaddr),
 			&params.network_config.transport,
@@ -207,11 +223,18 @@ where
 			None => (None, None),
 		};
 
+		let peer_store = params.network_config.peer_store_path.clone().map(PeerStore::open);
+		let bandwidth_limiter = Arc::new(BandwidthLimiter::new(
+			&params.network_config.bandwidth_limits,
+			params.network_config.protocol_bandwidth_limits.clone(),
+		));
+
 		let (protocol, peerset_handle, mut known_addresses) = Protocol::new(
 			protocol::ProtocolConfig {
 				roles: From::from(&params.role),
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
 				sync_mode: params.network_config.sync_mode.clone(),
+				gap_sync: params.network_config.enable_gap_sync,
 			},
 			params.chain.clone(),
 			params.protocol_id.clone(),
@@ -225,6 +248,8 @@ where
 			params.block_announce_validator,
 			params.metrics_registry.as_ref(),
 			warp_sync_provider,
+			peer_store.as_ref(),
+			&bandwidth_limiter,
 		)?;
 
 		// List of multiaddresses that we know in the network.
@@ -271,6 +296,9 @@ where
 			let discovery_config = {
 				let mut config = DiscoveryConfig::new(local_public.clone());
 				config.with_permanent_addresses(known_addresses);
+				config.with_known_addresses(
+					peer_store.iter().flat_map(|store| store.known_addresses()),
+				);
 				config.discovery_limit(
 					u64::from(params.network_config.default_peers_set.out_peers) + 15,
 				);
@@ -301,6 +329,12 @@ where
 					TransportConfig::Normal { .. } => false,
 				};
 
+				let wss_tls_config = transport::build_wss_tls_config(
+					params.network_config.wss_certificate.as_ref(),
+					&params.network_config.wss_trusted_certificates,
+				)
+				.map_err(Error::InvalidWssCertificate)?;
+
 				// The yamux buffer size limit is configured to be equal to the maximum frame size
 				// of all protocols. 10 bytes are added to each limit for the length prefix that
 				// is not included in the upper layer protocols limit but is still present in the
@@ -341,6 +375,7 @@ where
 				transport::build_transport(
 					local_identity.clone(),
 					config_mem,
+					wss_tls_config,
 					params.network_config.yamux_window_size,
 					yamux_maximum_buffer_size,
 				)
@@ -360,6 +395,7 @@ where
 					params.light_client_request_protocol_config,
 					params.network_config.request_response_protocols,
 					peerset_handle.clone(),
+					&bandwidth_limiter,
 				);
 
 				match result {
@@ -399,6 +435,7 @@ where
 				registry,
 				MetricSources {
 					bandwidth: bandwidth.clone(),
+					protocol_bandwidth: bandwidth_limiter,
 					major_syncing: is_major_syncing.clone(),
 					connected_peers: num_connected.clone(),
 				},
@@ -462,6 +499,7 @@ where
 			tx_handler_controller,
 			metrics,
 			boot_node_ids,
+			peer_store,
 		})
 	}
 
@@ -686,6 +724,22 @@ where
 	pub fn reserved_peers(&self) -> impl Iterator<Item = &PeerId> {
 		self.network_service.behaviour().user_protocol().reserved_peers()
 	}
+
+	/// Write the known peers to the peer store, unless it was written recently and `force` is
+	/// `false`.
+	fn write_peer_store(&mut self, force: bool) {
+		let peer_store = match self.peer_store.as_mut() {
+			Some(peer_store) if force || peer_store.should_write() => peer_store,
+			_ => return,
+		};
+
+		peer_store.set_reputations(
+			self.network_service.behaviour_mut().user_protocol_mut().peerset_reputations(),
+		);
+		if let Err(e) = peer_store.write() {
+			warn!(target: "sub-libp2p", "Failed to write the peer store: {}", e);
+		}
+	}
 }
 
 impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
@@ -791,9 +845,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 	/// a receiver. With a `NotificationSender` at hand, sending a notification is done in two
 	/// steps:
 	///
-	/// 1.  [`NotificationSender::ready`] is used to wait for the sender to become ready
+	/// 1. [`NotificationSender::ready`] is used to wait for the sender to become ready
 	/// for another notification, yielding a [`NotificationSenderReady`] token.
-	/// 2.  [`NotificationSenderReady::send`] enqueues the notification for sending. This operation
+	/// 2. [`NotificationSenderReady::send`] enqueues the notification for sending. This operation
 	/// can only fail if the underlying notification substream or connection has suddenly closed.
 	///
 	/// An error is returned by [`NotificationSenderReady::send`] if there exists no open
@@ -1500,6 +1554,8 @@ where
 	peers_notifications_sinks: Arc<Mutex<HashMap<(PeerId, Cow<'static, str>), NotificationsSink>>>,
 	/// Controller for the handler of incoming and outgoing transactions.
 	tx_handler_controller: transactions::TransactionsHandlerController<H>,
+	/// Store keeping the known peers across restarts, if enabled.
+	peer_store: Option<PeerStore>,
 }
 
 impl<B, H, Client> Future for NetworkWorker<B, H, Client>
@@ -1749,6 +1805,7 @@ where
 									RequestFailure::UnknownProtocol => "unknown-protocol",
 									RequestFailure::Refused => "refused",
 									RequestFailure::Obsolete => "obsolete",
+									RequestFailure::Throttled => "throttled",
 									RequestFailure::Network(OutboundFailure::DialFailure) =>
 										"dial-failure",
 									RequestFailure::Network(OutboundFailure::Timeout) => "timeout",
@@ -1906,6 +1963,14 @@ where
 						debug!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 					}
 
+					if let Some(peer_store) = this.peer_store.as_mut() {
+						let address = match &endpoint {
+							ConnectedPoint::Dialer { address, .. } => Some(address.clone()),
+							ConnectedPoint::Listener { .. } => None,
+						};
+						peer_store.on_connected(peer_id, address);
+					}
+
 					if let Some(metrics) = this.metrics.as_ref() {
 						let direction = match endpoint {
 							ConnectedPoint::Dialer { .. } => "out",
@@ -1925,6 +1990,9 @@ where
 					num_established,
 				}) => {
 					debug!(target: "sub-libp2p", "Libp2p => Disconnected({:?}, {:?})", peer_id, cause);
+					if let Some(peer_store) = this.peer_store.as_mut() {
+						peer_store.on_disconnected(&peer_id);
+					}
 					if let Some(metrics) = this.metrics.as_ref() {
 						let direction = match endpoint {
 							ConnectedPoint::Dialer { .. } => "out",
@@ -2146,10 +2214,29 @@ where
 			);
 		}
 
+		this.write_peer_store(false);
+
 		Poll::Pending
 	}
 }
 
+impl<B, H, Client> Drop for NetworkWorker<B, H, Client>
+where
+	B: BlockT + 'static,
+	H: ExHashT,
+	Client: HeaderBackend<B>
+		+ BlockBackend<B>
+		+ HeaderMetadata<B, Error = sp_blockchain::Error>
+		+ ProofProvider<B>
+		+ Send
+		+ Sync
+		+ 'static,
+{
+	fn drop(&mut self) {
+		self.write_peer_store(true);
+	}
+}
+
 impl<B, H, Client> Unpin for NetworkWorker<B, H, Client>
 where
 	B: BlockT + 'static,
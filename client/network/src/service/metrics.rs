@@ -16,7 +16,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::transport::BandwidthSinks;
+use crate::{
+	bandwidth::{BandwidthLimiter, Direction},
+	transport::BandwidthSinks,
+};
 use prometheus_endpoint::{
 	self as prometheus, Counter, CounterVec, Gauge, GaugeVec, HistogramOpts, MetricSource, Opts,
 	PrometheusError, Registry, SourcedCounter, SourcedGauge, U64,
@@ -34,6 +37,8 @@ pub use prometheus_endpoint::{Histogram, HistogramVec};
 /// Registers all networking metrics with the given registry.
 pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics, PrometheusError> {
 	BandwidthCounters::register(registry, sources.bandwidth)?;
+	ProtocolBandwidthCounters::register(registry, sources.protocol_bandwidth.clone())?;
+	ProtocolThrottledCounters::register(registry, sources.protocol_bandwidth)?;
 	MajorSyncingGauge::register(registry, sources.major_syncing)?;
 	NumConnectedGauge::register(registry, sources.connected_peers)?;
 	Metrics::register(registry)
@@ -42,6 +47,7 @@ pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics,
 /// Predefined metric sources that are fed directly into prometheus.
 pub struct MetricSources {
 	pub bandwidth: Arc<BandwidthSinks>,
+	pub protocol_bandwidth: Arc<BandwidthLimiter>,
 	pub major_syncing: Arc<AtomicBool>,
 	pub connected_peers: Arc<AtomicUsize>,
 }
@@ -285,6 +291,87 @@ impl MetricSource for BandwidthCounters {
 	}
 }
 
+/// The bandwidth usage of the notification and request-response protocols.
+#[derive(Clone)]
+pub struct ProtocolBandwidthCounters(Arc<BandwidthLimiter>);
+
+impl ProtocolBandwidthCounters {
+	/// Registers the `ProtocolBandwidthCounters` metric whose values are
+	/// obtained from the protocols of the given limiter.
+	fn register(
+		registry: &Registry,
+		limiter: Arc<BandwidthLimiter>,
+	) -> Result<(), PrometheusError> {
+		prometheus::register(
+			SourcedCounter::new(
+				&Opts::new(
+					"substrate_sub_libp2p_protocol_bytes_total",
+					"Total bandwidth usage of the notification and request-response protocols",
+				)
+				.variable_label("protocol")
+				.variable_label("direction"),
+				ProtocolBandwidthCounters(limiter),
+			)?,
+			registry,
+		)?;
+
+		Ok(())
+	}
+}
+
+impl MetricSource for ProtocolBandwidthCounters {
+	type N = u64;
+
+	fn collect(&self, mut set: impl FnMut(&[&str], Self::N)) {
+		for protocol in self.0.protocols() {
+			for direction in [Direction::In, Direction::Out] {
+				set(&[protocol.name(), direction.label()], protocol.bytes(direction));
+			}
+		}
+	}
+}
+
+/// The number of times the traffic of the protocols was delayed by the bandwidth limits.
+#[derive(Clone)]
+pub struct ProtocolThrottledCounters(Arc<BandwidthLimiter>);
+
+impl ProtocolThrottledCounters {
+	/// Registers the `ProtocolThrottledCounters` metric whose values are
+	/// obtained from the protocols of the given limiter.
+	fn register(
+		registry: &Registry,
+		limiter: Arc<BandwidthLimiter>,
+	) -> Result<(), PrometheusError> {
+		prometheus::register(
+			SourcedCounter::new(
+				&Opts::new(
+					"substrate_sub_libp2p_protocol_throttled_total",
+					"Total number of times the traffic of a protocol was delayed by the bandwidth \
+					limits",
+				)
+				.variable_label("protocol")
+				.variable_label("direction"),
+				ProtocolThrottledCounters(limiter),
+			)?,
+			registry,
+		)?;
+
+		Ok(())
+	}
+}
+
+impl MetricSource for ProtocolThrottledCounters {
+	type N = u64;
+
+	fn collect(&self, mut set: impl FnMut(&[&str], Self::N)) {
+		for protocol in self.0.protocols() {
+			for direction in [Direction::In, Direction::Out] {
+				set(&[protocol.name(), direction.label()], protocol.throttled(direction));
+			}
+		}
+	}
+}
+
 /// The "major syncing" metric.
 #[derive(Clone)]
 pub struct MajorSyncingGauge(Arc<AtomicBool>);
@@ -159,6 +159,7 @@ fn build_nodes_one_proto() -> (
 			notifications_protocol: PROTOCOL_NAME,
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
+			bandwidth_priority: config::BandwidthPriority::Normal,
 			set_config: Default::default(),
 		}],
 		listen_addresses: vec![listen_addr.clone()],
@@ -171,6 +172,7 @@ fn build_nodes_one_proto() -> (
 			notifications_protocol: PROTOCOL_NAME,
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
+			bandwidth_priority: config::BandwidthPriority::Normal,
 			set_config: config::SetConfig {
 				reserved_nodes: vec![config::MultiaddrWithPeerId {
 					multiaddr: listen_addr,
@@ -349,6 +351,7 @@ fn lots_of_incoming_peers_works() {
 			notifications_protocol: PROTOCOL_NAME,
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
+			bandwidth_priority: config::BandwidthPriority::Normal,
 			set_config: config::SetConfig { in_peers: u32::MAX, ..Default::default() },
 		}],
 		transport: config::TransportConfig::MemoryOnly,
@@ -368,6 +371,7 @@ fn lots_of_incoming_peers_works() {
 				notifications_protocol: PROTOCOL_NAME,
 				fallback_names: Vec::new(),
 				max_notification_size: 1024 * 1024,
+				bandwidth_priority: config::BandwidthPriority::Normal,
 				set_config: config::SetConfig {
 					reserved_nodes: vec![config::MultiaddrWithPeerId {
 						multiaddr: listen_addr.clone(),
@@ -481,6 +485,7 @@ fn fallback_name_working() {
 			notifications_protocol: NEW_PROTOCOL_NAME.clone(),
 			fallback_names: vec![PROTOCOL_NAME],
 			max_notification_size: 1024 * 1024,
+			bandwidth_priority: config::BandwidthPriority::Normal,
 			set_config: Default::default(),
 		}],
 		listen_addresses: vec![listen_addr.clone()],
@@ -493,6 +498,7 @@ fn fallback_name_working() {
 			notifications_protocol: PROTOCOL_NAME,
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
+			bandwidth_priority: config::BandwidthPriority::Normal,
 			set_config: config::SetConfig {
 				reserved_nodes: vec![config::MultiaddrWithPeerId {
 					multiaddr: listen_addr,
@@ -561,6 +567,17 @@ fn ensure_listen_addresses_consistent_with_transport_not_memory() {
 	});
 }
 
+#[test]
+#[should_panic(expected = "don't match the transport")]
+fn ensure_listen_addresses_are_not_quic() {
+	let listen_addr = config::build_multiaddr![Ip4([127, 0, 0, 1]), Udp(0_u16), Quic];
+
+	let _ = build_test_full_node(config::NetworkConfiguration {
+		listen_addresses: vec![listen_addr.clone()],
+		..config::NetworkConfiguration::new("test-node", "test-client", Default::default(), None)
+	});
+}
+
 #[test]
 #[should_panic(expected = "don't match the transport")]
 fn ensure_boot_node_addresses_consistent_with_transport_memory() {
@@ -25,17 +25,28 @@ use libp2p::{
 		transport::{Boxed, OptionalTransport},
 		upgrade,
 	},
-	dns, identity, mplex, noise, tcp, websocket, PeerId, Transport,
+	dns, identity, mplex, noise, tcp,
+	websocket::{self, tls},
+	PeerId, Transport,
 };
 use std::{sync::Arc, time::Duration};
 
+use crate::config::WssCertificate;
+
 pub use self::bandwidth::BandwidthSinks;
 
 /// Builds the transport that serves as a common ground for all connections.
 ///
+/// The transport supports TCP, WebSocket and secure WebSocket addresses. QUIC is not supported:
+/// the version of libp2p in use has no QUIC transport, so adding it requires upgrading libp2p
+/// first.
+///
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
 /// addresses with the format `/memory/...` are allowed.
 ///
+/// `wss_tls_config` is the TLS configuration of the secure WebSocket transport, as returned by
+/// [`build_wss_tls_config`].
+///
 /// `yamux_window_size` is the maximum size of the Yamux receive windows. `None` to leave the
 /// default (256kiB).
 ///
@@ -49,23 +60,27 @@ pub use self::bandwidth::BandwidthSinks;
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
+	wss_tls_config: tls::Config,
 	yamux_window_size: Option<u32>,
 	yamux_maximum_buffer_size: usize,
 ) -> (Boxed<(PeerId, StreamMuxerBox)>, Arc<BandwidthSinks>) {
 	// Build the base layer of the transport.
 	let transport = if !memory_only {
-		let desktop_trans = tcp::TcpConfig::new().nodelay(true);
-		let desktop_trans = websocket::WsConfig::new(desktop_trans)
-			.or_transport(tcp::TcpConfig::new().nodelay(true));
-		let dns_init = futures::executor::block_on(dns::DnsConfig::system(desktop_trans));
-		EitherTransport::Left(if let Ok(dns) = dns_init {
-			EitherTransport::Left(dns)
-		} else {
-			let desktop_trans = tcp::TcpConfig::new().nodelay(true);
-			let desktop_trans = websocket::WsConfig::new(desktop_trans)
-				.or_transport(tcp::TcpConfig::new().nodelay(true));
-			EitherTransport::Right(desktop_trans.map_err(dns::DnsErr::Transport))
-		})
+		// The WebSocket transport is on top of the DNS transport, so that the domain name of
+		// `/dns/.../wss` addresses is known when verifying the certificate of the remote.
+		let dns_trans = || {
+			let tcp_trans = tcp::TcpConfig::new().nodelay(true);
+			let dns_init = futures::executor::block_on(dns::DnsConfig::system(tcp_trans));
+			if let Ok(dns) = dns_init {
+				EitherTransport::Left(dns)
+			} else {
+				let tcp_trans = tcp::TcpConfig::new().nodelay(true);
+				EitherTransport::Right(tcp_trans.map_err(dns::DnsErr::Transport))
+			}
+		};
+		let mut ws_trans = websocket::WsConfig::new(dns_trans());
+		ws_trans.set_tls_config(wss_tls_config);
+		EitherTransport::Left(ws_trans.or_transport(dns_trans()))
 	} else {
 		EitherTransport::Right(OptionalTransport::some(
 			libp2p::core::transport::MemoryTransport::default(),
@@ -120,3 +135,25 @@ pub fn build_transport(
 
 	(transport, bandwidth)
 }
+
+/// Builds the TLS configuration of the secure WebSocket transport.
+///
+/// The node listens on `/wss` addresses with `certificate`, if any, and trusts the authorities of
+/// `trusted_certificates` in addition to the usual root certificate authorities of the web when
+/// dialing `/wss` addresses.
+pub fn build_wss_tls_config(
+	certificate: Option<&WssCertificate>,
+	trusted_certificates: &[Vec<u8>],
+) -> Result<tls::Config, tls::Error> {
+	let mut builder = tls::Config::builder();
+	if let Some(certificate) = certificate {
+		builder.server(
+			tls::PrivateKey::new(certificate.private_key.clone()),
+			certificate.certificate_chain.iter().cloned().map(tls::Certificate::new),
+		)?;
+	}
+	for trusted in trusted_certificates {
+		builder.add_trust(&tls::Certificate::new(trusted.clone()))?;
+	}
+	Ok(builder.finish())
+}
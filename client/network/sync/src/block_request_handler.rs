@@ -32,7 +32,7 @@ use lru::LruCache;
 use prost::Message;
 use sc_client_api::BlockBackend;
 use sc_network_common::{
-	config::ProtocolId,
+	config::{BandwidthPriority, ProtocolId},
 	request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig},
 };
 use sp_blockchain::HeaderBackend;
@@ -71,6 +71,7 @@ pub fn generate_protocol_config(protocol_id: &ProtocolId) -> ProtocolConfig {
 		max_response_size: 16 * 1024 * 1024,
 		request_timeout: Duration::from_secs(20),
 		inbound_queue: None,
+		bandwidth_priority: BandwidthPriority::Low,
 	}
 }
 
@@ -392,7 +393,14 @@ where
 			}
 		}
 
-		Ok(BlockResponse { blocks })
+		// Let the requester know which ranges we can serve, so that it doesn't need to ask for
+		// pruned data.
+		let info = self.client.info();
+		Ok(BlockResponse {
+			blocks,
+			bodies_from: info.bodies_from.map(|n| n.encode()).unwrap_or_default(),
+			justifications_from: info.justifications_from.map(|n| n.encode()).unwrap_or_default(),
+		})
 	}
 }
 
@@ -19,7 +19,7 @@
 use crate::message;
 use libp2p::PeerId;
 use log::trace;
-use sp_runtime::traits::{Block as BlockT, NumberFor, One};
+use sp_runtime::traits::{Block as BlockT, NumberFor, One, SaturatedConversion};
 use std::{
 	cmp,
 	collections::{BTreeMap, HashMap},
@@ -181,15 +181,56 @@ impl<B: BlockT> BlockCollection<B> {
 	/// Get a valid chain of blocks ordered in descending order and ready for importing into
 	/// the blockchain.
 	pub fn ready_blocks(&mut self, from: NumberFor<B>) -> Vec<BlockData<B>> {
+		self.take_ready_blocks(from, None)
+	}
+
+	/// Same as [`Self::ready_blocks`], but the blocks after `last` are left in the collection.
+	pub fn ready_blocks_up_to(
+		&mut self,
+		from: NumberFor<B>,
+		last: NumberFor<B>,
+	) -> Vec<BlockData<B>> {
+		self.take_ready_blocks(from, Some(last))
+	}
+
+	/// Returns the number of the block following the ones [`Self::ready_blocks`] would return.
+	pub fn ready_blocks_end(&self, from: NumberFor<B>) -> NumberFor<B> {
+		let mut prev = from;
+		for (&start, range_data) in &self.blocks {
+			if start > prev {
+				break
+			}
+			match range_data {
+				BlockRangeState::Complete(blocks) => prev = start + (blocks.len() as u32).into(),
+				BlockRangeState::Queued { .. } => continue,
+				_ => break,
+			}
+		}
+		prev
+	}
+
+	fn take_ready_blocks(
+		&mut self,
+		from: NumberFor<B>,
+		last: Option<NumberFor<B>>,
+	) -> Vec<BlockData<B>> {
 		let mut ready = Vec::new();
+		let mut remaining = None;
 
 		let mut prev = from;
 		for (&start, range_data) in &mut self.blocks {
-			if start > prev {
+			if start > prev || last.map_or(false, |last| start > last) {
 				break
 			}
 			let len = match range_data {
 				BlockRangeState::Complete(blocks) => {
+					// Split the range if it goes beyond `last`.
+					if let Some(last) = last {
+						let keep = (last + One::one() - start).saturated_into::<usize>();
+						if blocks.len() > keep {
+							remaining = Some((last + One::one(), blocks.split_off(keep)));
+						}
+					}
 					let len = (blocks.len() as u32).into();
 					prev = start + len;
 					// Remove all elements from `blocks` and add them to `ready`
@@ -200,6 +241,13 @@ impl<B: BlockT> BlockCollection<B> {
 				_ => break,
 			};
 			*range_data = BlockRangeState::Queued { len };
+			if remaining.is_some() {
+				break
+			}
+		}
+
+		if let Some((start, blocks)) = remaining {
+			self.blocks.insert(start, BlockRangeState::Complete(blocks));
 		}
 
 		if let Some(BlockData { block, .. }) = ready.first() {
@@ -372,6 +420,36 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn ready_blocks_up_to_leaves_following_blocks() {
+		let mut bc: BlockCollection<Block> = BlockCollection::new();
+		let peer = PeerId::random();
+		let blocks = generate_blocks(20);
+
+		bc.insert(1, blocks[1..11].to_vec(), peer.clone());
+		bc.insert(11, blocks[11..20].to_vec(), peer.clone());
+		assert_eq!(bc.ready_blocks_end(1), 20);
+
+		let ready = bc.ready_blocks_up_to(1, 5);
+		assert_eq!(
+			ready,
+			blocks[1..6]
+				.iter()
+				.map(|b| BlockData { block: b.clone(), origin: Some(peer.clone()) })
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(bc.ready_blocks_end(6), 20);
+
+		let ready = bc.ready_blocks(6);
+		assert_eq!(
+			ready,
+			blocks[6..20]
+				.iter()
+				.map(|b| BlockData { block: b.clone(), origin: Some(peer.clone()) })
+				.collect::<Vec<_>>()
+		);
+	}
+
 	#[test]
 	fn no_duplicate_requests_on_fork() {
 		let mut bc = BlockCollection::new();
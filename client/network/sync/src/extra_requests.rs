@@ -305,9 +305,13 @@ impl<'a, B: BlockT> Matcher<'a, B> {
 		}
 
 		while let Some(request) = self.extras.pending_requests.pop_front() {
-			for (peer, sync) in
-				peers.iter().filter(|(_, sync)| sync.state == PeerSyncState::Available)
-			{
+			// Ask the peers that advertised they keep the justification first. The others may
+			// still have it if the block is at an era or session boundary.
+			let (keeping, pruning): (Vec<_>, Vec<_>) = peers
+				.iter()
+				.filter(|(_, sync)| sync.state == PeerSyncState::Available)
+				.partition(|(_, sync)| sync.justifications_from.map_or(true, |n| request.1 >= n));
+			for (peer, sync) in keeping.into_iter().chain(pruning) {
 				// only ask peers that have synced at least up to the block number that we're asking
 				// the extra for
 				if sync.best_number < request.1 {
@@ -574,6 +578,8 @@ mod tests {
 				best_hash: Hash::random(),
 				best_number: u64::arbitrary(g),
 				state: ArbitraryPeerSyncState::arbitrary(g).0,
+				bodies_from: None,
+				justifications_from: None,
 			};
 			ArbitraryPeerSync(ps)
 		}
@@ -73,6 +73,7 @@ use std::{
 	collections::{hash_map::Entry, HashMap, HashSet},
 	fmt,
 	ops::Range,
+	path::PathBuf,
 	pin::Pin,
 	sync::Arc,
 };
@@ -92,6 +93,14 @@ const MAX_DOWNLOAD_AHEAD: u32 = 2048;
 /// common block of a node.
 const MAX_BLOCKS_TO_LOOK_BACKWARDS: u32 = MAX_DOWNLOAD_AHEAD / 2;
 
+/// Maximum headers to request in a single packet when downloading the hash chain of the block
+/// history.
+const MAX_GAP_HEADERS_TO_REQUEST: u32 = 128;
+
+/// Interval between the blocks of the history whose hash is kept to check the downloaded blocks
+/// against.
+const GAP_CHECKPOINT_INTERVAL: u32 = MAX_BLOCKS_TO_REQUEST as u32;
+
 /// Maximum number of concurrent block announce validations.
 ///
 /// If the queue reaches the maximum, we drop any new block
@@ -196,10 +205,162 @@ impl Default for AllowedRequests {
 	}
 }
 
+/// Download of the block history that is missing after a warp or fast sync.
+///
+/// The headers are first downloaded backwards from the block following the gap, checking that
+/// they form a hash chain. The blocks are then downloaded forwards from the start of the gap and
+/// only imported once they are linked to a block of the chain.
+///
+/// The headers are downloaded from one peer at a time, [`MAX_GAP_HEADERS_TO_REQUEST`] per round
+/// trip, so this first pass is bound by the latency of the peers. The checkpoints are only kept
+/// in memory: they survive a restart of the sync, but all the headers are downloaded again after
+/// the node restarts.
 struct GapSync<B: BlockT> {
 	blocks: BlockCollection<B>,
 	best_queued_number: NumberFor<B>,
+	/// Hash of the block at `best_queued_number`.
+	best_queued_hash: B::Hash,
+	/// First block of the gap when the download started.
+	start: NumberFor<B>,
 	target: NumberFor<B>,
+	/// Number and hash of the highest block whose header is not downloaded yet, `None` once all
+	/// the headers are downloaded.
+	next_header: Option<(NumberFor<B>, B::Hash)>,
+	/// Whether a peer is downloading headers.
+	downloading_headers: bool,
+	/// Hashes of the blocks of the chain at each [`GAP_CHECKPOINT_INTERVAL`], and of the target.
+	checkpoints: HashMap<NumberFor<B>, B::Hash>,
+}
+
+impl<B: BlockT> GapSync<B> {
+	fn progress(&self) -> GapSyncProgress<B> {
+		GapSyncProgress {
+			start: self.start,
+			target: self.target,
+			lowest_header: self.next_header.map_or(self.start, |(n, _)| n + One::one()),
+			best_queued_number: self.best_queued_number,
+		}
+	}
+
+	/// Get a request for the next headers of the chain, unless another peer is downloading them.
+	fn header_request(&mut self, peer: &PeerSync<B>) -> Option<(NumberFor<B>, BlockRequest<B>)> {
+		let (number, hash) = self.next_header?;
+		if self.downloading_headers || peer.best_number < number {
+			return None
+		}
+		self.downloading_headers = true;
+
+		let max = std::cmp::min(
+			(number + One::one() - self.start).saturated_into::<u32>(),
+			MAX_GAP_HEADERS_TO_REQUEST,
+		);
+		let request = message::generic::BlockRequest {
+			id: 0,
+			fields: BlockAttributes::HEADER,
+			from: message::FromBlock::Hash(hash),
+			to: None,
+			direction: message::Direction::Descending,
+			max: Some(max),
+		};
+		Some((number, request))
+	}
+
+	/// Handle the headers of a response to a request made with [`Self::header_request`], in
+	/// ascending order.
+	fn on_headers(
+		&mut self,
+		who: &PeerId,
+		blocks: &[message::BlockData<B>],
+	) -> Result<(), BadPeer> {
+		self.downloading_headers = false;
+		let mut next_header = match self.next_header {
+			Some(next_header) => next_header,
+			None => return Ok(()),
+		};
+
+		for block in blocks.iter().rev() {
+			let header = match &block.header {
+				Some(header) if block.hash == next_header.1 => header,
+				_ => {
+					debug!(
+						target: "sync",
+						"Block history header from {} is not part of the chain: {:?}",
+						who,
+						block.hash,
+					);
+					return Err(BadPeer(*who, rep::BAD_BLOCK))
+				},
+			};
+			let number = *header.number();
+			if number == self.target || (number % GAP_CHECKPOINT_INTERVAL.into()).is_zero() {
+				self.checkpoints.insert(number, block.hash);
+			}
+			if number <= self.start {
+				debug!(target: "sync", "Block history headers are downloaded");
+				self.next_header = None;
+				return Ok(())
+			}
+			next_header = (number - One::one(), *header.parent_hash());
+		}
+
+		self.next_header = Some(next_header);
+		Ok(())
+	}
+
+	/// Get the downloaded blocks that are ready to be imported, up to the last one whose hash can
+	/// be checked against the chain. The following ones stay in the collection.
+	fn ready_blocks(&mut self) -> Result<Vec<blocks::BlockData<B>>, BadPeer> {
+		let from = self.best_queued_number + One::one();
+		let end = self.blocks.ready_blocks_end(from);
+		if end <= from {
+			return Ok(Vec::new())
+		}
+		let last = end - One::one();
+		let checkpoint = if last >= self.target {
+			self.target
+		} else {
+			last - last % GAP_CHECKPOINT_INTERVAL.into()
+		};
+		let mut expected = match self.checkpoints.get(&checkpoint) {
+			Some(hash) if checkpoint >= from => *hash,
+			_ => return Ok(Vec::new()),
+		};
+
+		let blocks = self.blocks.ready_blocks_up_to(from, checkpoint);
+		for block_data in blocks.iter().rev() {
+			match &block_data.block.header {
+				Some(header) if block_data.block.hash == expected =>
+					expected = *header.parent_hash(),
+				_ => {
+					debug!(
+						target: "sync",
+						"Block history block is not part of the chain: {:?}",
+						block_data.block.hash,
+					);
+					// Download the blocks again.
+					if let Some(first) = blocks.first() {
+						self.blocks.clear_queued(&first.block.hash);
+					}
+					return match block_data.origin {
+						Some(who) => Err(BadPeer(who, rep::BAD_BLOCK)),
+						None => Ok(Vec::new()),
+					}
+				},
+			}
+		}
+		if expected != self.best_queued_hash {
+			debug!(target: "sync", "Block history blocks are not linked to #{}", self.best_queued_number);
+			if let Some(first) = blocks.first() {
+				self.blocks.clear_queued(&first.block.hash);
+			}
+			return Ok(Vec::new())
+		}
+
+		if let Some(last) = blocks.last() {
+			self.best_queued_hash = last.block.hash;
+		}
+		Ok(blocks)
+	}
 }
 
 /// The main data structure which contains all the state for a chains
@@ -248,6 +409,10 @@ pub struct ChainSync<B: BlockT, Client> {
 	import_existing: bool,
 	/// Gap download process.
 	gap_sync: Option<GapSync<B>>,
+	/// Whether to download the block history that is missing after a warp or fast sync.
+	enable_gap_sync: bool,
+	/// Path of the journal used to resume the state sync after a restart, if any.
+	state_sync_journal: Option<PathBuf>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -265,6 +430,13 @@ pub struct PeerSync<B: BlockT> {
 	/// The state of syncing this peer is in for us, generally categories
 	/// into `Available` or "busy" with something as defined by `PeerSyncState`.
 	pub state: PeerSyncState<B>,
+	/// The number of the oldest block whose body the peer can serve, as advertised in its last
+	/// block response. `None` if the peer keeps all bodies or hasn't told us yet.
+	pub bodies_from: Option<NumberFor<B>>,
+	/// The number of the oldest block whose justification the peer can serve, as advertised in
+	/// its last block response. Older justifications may still be kept at era or session
+	/// boundaries. `None` if the peer keeps all justifications or hasn't told us yet.
+	pub justifications_from: Option<NumberFor<B>>,
 }
 
 impl<B: BlockT> PeerSync<B> {
@@ -322,6 +494,8 @@ pub enum PeerSyncState<B: BlockT> {
 	DownloadingWarpProof,
 	/// Actively downloading block history after warp sync.
 	DownloadingGap(NumberFor<B>),
+	/// Downloading the headers of the block history backwards, starting from the given Number.
+	DownloadingGapHeaders(NumberFor<B>),
 }
 
 impl<B: BlockT> PeerSyncState<B> {
@@ -354,6 +528,22 @@ pub struct Status<B: BlockT> {
 	pub state_sync: Option<StateDownloadProgress>,
 	/// Warp sync in progress, if any.
 	pub warp_sync: Option<WarpSyncProgress<B>>,
+	/// Block history download in progress, if any.
+	pub gap_sync: Option<GapSyncProgress<B>>,
+}
+
+/// Progress of the download of the block history that is missing after a warp or fast sync.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GapSyncProgress<B: BlockT> {
+	/// First missing block when the download started.
+	pub start: NumberFor<B>,
+	/// Last missing block.
+	pub target: NumberFor<B>,
+	/// Lowest block whose header is downloaded. The headers are downloaded backwards from
+	/// `target`, before the blocks are downloaded forwards from `start`.
+	pub lowest_header: NumberFor<B>,
+	/// Highest block of the history queued for import.
+	pub best_queued_number: NumberFor<B>,
 }
 
 /// A peer did not behave as expected and should be reported.
@@ -517,6 +707,8 @@ where
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
 		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+		enable_gap_sync: bool,
+		state_sync_journal: Option<PathBuf>,
 	) -> Result<Self, ClientError> {
 		let mut sync = Self {
 			client,
@@ -539,8 +731,15 @@ where
 			warp_sync_provider,
 			import_existing: false,
 			gap_sync: None,
+			enable_gap_sync,
+			state_sync_journal,
 		};
 		sync.reset_sync_start_point()?;
+		if let (SyncMode::LightState { skip_proofs, .. }, Some(path)) =
+			(&sync.mode, &sync.state_sync_journal)
+		{
+			sync.state_sync = StateSync::resume(sync.client.clone(), path, *skip_proofs);
+		}
 		Ok(sync)
 	}
 
@@ -624,6 +823,7 @@ where
 			queued_blocks: self.queue_blocks.len() as u32,
 			state_sync: self.state_sync.as_ref().map(|s| s.progress()),
 			warp_sync: warp_sync_progress,
+			gap_sync: self.gap_sync.as_ref().map(|s| s.progress()),
 		}
 	}
 
@@ -688,6 +888,8 @@ where
 							best_hash,
 							best_number,
 							state: PeerSyncState::Available,
+							bodies_from: None,
+							justifications_from: None,
 						},
 					);
 					return Ok(None)
@@ -732,6 +934,8 @@ where
 						best_hash,
 						best_number,
 						state,
+						bodies_from: None,
+						justifications_from: None,
 					},
 				);
 
@@ -740,8 +944,11 @@ where
 					{
 						log::debug!(target: "sync", "Starting warp state sync.");
 						if let Some(provider) = &self.warp_sync_provider {
-							self.warp_sync =
-								Some(WarpSync::new(self.client.clone(), provider.clone()));
+							self.warp_sync = Some(WarpSync::new(
+								self.client.clone(),
+								provider.clone(),
+								self.state_sync_journal.clone(),
+							));
 						}
 					}
 				}
@@ -765,6 +972,8 @@ where
 						best_hash,
 						best_number,
 						state: PeerSyncState::Available,
+						bodies_from: None,
+						justifications_from: None,
 					},
 				);
 				self.allowed_requests.add(&who);
@@ -779,6 +988,22 @@ where
 		self.on_block_queued(best_hash, best_number);
 	}
 
+	/// Signal the ranges of blocks `who` can serve bodies and justifications for, as advertised
+	/// in a block response.
+	///
+	/// Bodies and justifications of older blocks are not requested from the peer.
+	pub fn on_peer_ranges(
+		&mut self,
+		who: &PeerId,
+		bodies_from: Option<NumberFor<B>>,
+		justifications_from: Option<NumberFor<B>>,
+	) {
+		if let Some(peer) = self.peers.get_mut(who) {
+			peer.bodies_from = bodies_from;
+			peer.justifications_from = justifications_from;
+		}
+	}
+
 	/// Schedule a justification request for the given block.
 	pub fn request_justification(&mut self, hash: &B::Hash, number: NumberFor<B>) {
 		let client = &self.client;
@@ -891,8 +1116,9 @@ where
 		let attrs = self.required_block_attributes();
 		let blocks = &mut self.blocks;
 		let fork_targets = &mut self.fork_targets;
-		let last_finalized =
-			std::cmp::min(self.best_queued_number, self.client.info().finalized_number);
+		let info = self.client.info();
+		let last_finalized = std::cmp::min(self.best_queued_number, info.finalized_number);
+		let bodies_from = info.bodies_from;
 		let best_queued = self.best_queued_number;
 		let client = &self.client;
 		let queue = &self.queue_blocks;
@@ -958,16 +1184,30 @@ where
 				trace!(target: "sync", "Downloading fork {:?} from {}", hash, id);
 				peer.state = PeerSyncState::DownloadingStale(hash);
 				Some((id, req))
-			} else if let Some((range, req)) = gap_sync.as_mut().and_then(|sync| {
-				peer_gap_block_request(
+			} else if let Some((number, req)) =
+				gap_sync.as_mut().and_then(|sync| sync.header_request(peer))
+			{
+				peer.state = PeerSyncState::DownloadingGapHeaders(number);
+				trace!(
+					target: "sync",
+					"New gap header request for {}, (best:{}) {:?}",
 					id,
-					peer,
-					&mut sync.blocks,
-					attrs,
-					sync.target,
-					sync.best_queued_number,
-				)
-			}) {
+					peer.best_number,
+					req,
+				);
+				Some((id, req))
+			} else if let Some((range, req)) =
+				gap_sync.as_mut().filter(|sync| sync.next_header.is_none()).and_then(|sync| {
+					peer_gap_block_request(
+						id,
+						peer,
+						&mut sync.blocks,
+						attrs,
+						sync.target,
+						sync.best_queued_number,
+						bodies_from,
+					)
+				}) {
 				peer.state = PeerSyncState::DownloadingGap(range.start);
 				trace!(
 					target: "sync",
@@ -985,50 +1225,53 @@ where
 		Either::Right(iter)
 	}
 
-	/// Get a state request, if any.
-	pub fn state_request(&mut self) -> Option<(PeerId, StateRequest)> {
+	/// Get the state requests to send, if any.
+	///
+	/// The key ranges of the state are downloaded from different peers in parallel.
+	pub fn state_requests(&mut self) -> Vec<(PeerId, StateRequest)> {
+		let mut requests = Vec::new();
 		if self.allowed_requests.is_empty() {
-			return None
-		}
-		if (self.state_sync.is_some() || self.warp_sync.is_some()) &&
-			self.peers.iter().any(|(_, peer)| peer.state == PeerSyncState::DownloadingState)
-		{
-			// Only one pending state request is allowed.
-			return None
+			return requests
 		}
-		if let Some(sync) = &self.state_sync {
+		if let Some(sync) = &mut self.state_sync {
 			if sync.is_complete() {
-				return None
+				return requests
 			}
 
 			for (id, peer) in self.peers.iter_mut() {
 				if peer.state.is_available() && peer.common_number >= sync.target_block_num() {
-					peer.state = PeerSyncState::DownloadingState;
-					let request = sync.next_request();
+					let request = match sync.next_request(*id) {
+						Some(request) => request,
+						None => break,
+					};
 					trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
-					self.allowed_requests.clear();
-					return Some((*id, request))
+					peer.state = PeerSyncState::DownloadingState;
+					requests.push((*id, request));
 				}
 			}
 		}
-		if let Some(sync) = &self.warp_sync {
+		if let Some(sync) = &mut self.warp_sync {
 			if sync.is_complete() {
-				return None
+				return requests
 			}
-			if let (Some(request), Some(target)) =
-				(sync.next_state_request(), sync.target_block_number())
-			{
+			if let Some(target) = sync.target_block_number() {
 				for (id, peer) in self.peers.iter_mut() {
 					if peer.state.is_available() && peer.best_number >= target {
+						let request = match sync.next_state_request(*id) {
+							Some(request) => request,
+							None => break,
+						};
 						trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
 						peer.state = PeerSyncState::DownloadingState;
-						self.allowed_requests.clear();
-						return Some((*id, request))
+						requests.push((*id, request));
 					}
 				}
 			}
 		}
-		None
+		if !requests.is_empty() {
+			self.allowed_requests.clear();
+		}
+		requests
 	}
 
 	/// Get a warp sync request, if any.
@@ -1111,8 +1354,7 @@ where
 							}
 							gap = true;
 							let blocks: Vec<_> = gap_sync
-								.blocks
-								.ready_blocks(gap_sync.best_queued_number + One::one())
+								.ready_blocks()?
 								.into_iter()
 								.map(|block_data| {
 									let justifications =
@@ -1142,6 +1384,21 @@ where
 							return Err(BadPeer(*who, rep::NO_BLOCK))
 						}
 					},
+					PeerSyncState::DownloadingGapHeaders(_) => {
+						peer.state = PeerSyncState::Available;
+						if let Some(gap_sync) = &mut self.gap_sync {
+							let valid = validate_blocks::<B>(&blocks, who, Some(request));
+							if let Err(e) = valid {
+								gap_sync.downloading_headers = false;
+								return Err(e)
+							}
+							gap_sync.on_headers(who, &blocks)?;
+							Vec::new()
+						} else {
+							debug!(target: "sync", "Unexpected gap header response from {}", who);
+							return Err(BadPeer(*who, rep::NO_BLOCK))
+						}
+					},
 					PeerSyncState::DownloadingStale(_) => {
 						peer.state = PeerSyncState::Available;
 						if blocks.is_empty() {
@@ -1319,7 +1576,7 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import(response)
+			sync.import(who, response)
 		} else if let Some(sync) = &mut self.warp_sync {
 			debug!(
 				target: "sync",
@@ -1328,7 +1585,7 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import_state(response)
+			sync.import_state(who, response)
 		} else {
 			debug!(target: "sync", "Ignored obsolete state response from {}", who);
 			return Err(BadPeer(*who, rep::NOT_REQUESTED))
@@ -1355,6 +1612,7 @@ where
 			state::ImportResult::Continue => Ok(OnStateData::Continue),
 			state::ImportResult::BadResponse => {
 				debug!(target: "sync", "Bad state data received from {}", who);
+				self.restart_stale_state_sync();
 				Err(BadPeer(*who, rep::BAD_BLOCK))
 			},
 		}
@@ -1521,6 +1779,9 @@ where
 		for (_, hash) in &results {
 			self.queue_blocks.remove(hash);
 			self.blocks.clear_queued(hash);
+			if let Some(gap_sync) = &mut self.gap_sync {
+				gap_sync.blocks.clear_queued(hash);
+			}
 		}
 		for (result, hash) in results {
 			if has_error {
@@ -1576,6 +1837,7 @@ where
 							self.state_sync.as_ref().map_or(0, |s| s.progress().size / (1024 * 1024)),
 						);
 						self.state_sync = None;
+						self.remove_state_sync_journal();
 						self.mode = SyncMode::Full;
 						output.extend(self.restart());
 					}
@@ -1590,6 +1852,7 @@ where
 							self.warp_sync.as_ref().map_or(0, |s| s.progress().total_bytes / (1024 * 1024)),
 						);
 						self.warp_sync = None;
+						self.remove_state_sync_journal();
 						self.mode = SyncMode::Full;
 						output.extend(self.restart());
 					}
@@ -1642,6 +1905,9 @@ where
 				},
 				e @ Err(BlockImportError::UnknownParent) | e @ Err(BlockImportError::Other(_)) => {
 					warn!(target: "sync", "💔 Error importing block {:?}: {}", hash, e.unwrap_err());
+					if self.state_sync.is_some() || self.warp_sync.is_some() {
+						self.remove_state_sync_journal();
+					}
 					self.state_sync = None;
 					self.warp_sync = None;
 					output.extend(self.restart());
@@ -1685,8 +1951,12 @@ where
 							number,
 							hash,
 						);
-						self.state_sync =
-							Some(StateSync::new(self.client.clone(), header, *skip_proofs));
+						self.state_sync = Some(StateSync::new(
+							self.client.clone(),
+							header,
+							*skip_proofs,
+							self.state_sync_journal.as_deref(),
+						));
 						self.allowed_requests.set_all();
 					}
 				}
@@ -2068,7 +2338,20 @@ where
 	pub fn peer_disconnected(&mut self, who: &PeerId) -> Option<OnBlockData<B>> {
 		self.blocks.clear_peer_download(who);
 		if let Some(gap_sync) = &mut self.gap_sync {
-			gap_sync.blocks.clear_peer_download(who)
+			gap_sync.blocks.clear_peer_download(who);
+			if let Some(PeerSyncState::DownloadingGapHeaders(_)) =
+				self.peers.get(who).map(|p| &p.state)
+			{
+				gap_sync.downloading_headers = false;
+			}
+		}
+		if let Some(PeerSyncState::DownloadingState) = self.peers.get(who).map(|p| &p.state) {
+			if let Some(sync) = &mut self.state_sync {
+				sync.on_request_failed(who);
+			} else if let Some(sync) = &mut self.warp_sync {
+				sync.on_state_request_failed(who);
+			}
+			self.restart_stale_state_sync();
 		}
 		self.peers.remove(who);
 		self.extra_justifications.peer_disconnected(who);
@@ -2085,6 +2368,34 @@ where
 		}
 	}
 
+	/// Give up a resumed state sync if no peer is able to provide its state, which may have been
+	/// pruned since it was started. The state of a more recent block is downloaded instead.
+	fn restart_stale_state_sync(&mut self) {
+		if self.state_sync.as_ref().map_or(false, |sync| sync.is_stale()) {
+			warn!(target: "sync", "💔 Unable to resume the state sync, starting over");
+			self.state_sync = None;
+			self.remove_state_sync_journal();
+		}
+		if self.warp_sync.as_ref().map_or(false, |sync| sync.is_stale()) {
+			warn!(target: "sync", "💔 Unable to resume the warp sync, starting over");
+			self.remove_state_sync_journal();
+			self.warp_sync = self.warp_sync_provider.as_ref().map(|provider| {
+				WarpSync::new(
+					self.client.clone(),
+					provider.clone(),
+					self.state_sync_journal.clone(),
+				)
+			});
+		}
+	}
+
+	/// Remove the journal of the state sync, once the state is imported or can't be.
+	fn remove_state_sync_journal(&self) {
+		if let Some(path) = &self.state_sync_journal {
+			state::remove_journal(path);
+		}
+	}
+
 	/// Restart the sync process. This will reset all pending block requests and return an iterator
 	/// of new block requests to make to peers. Peers that were downloading finality data (i.e.
 	/// their state was `DownloadingJustification`) are unaffected and will stay in the same state.
@@ -2155,13 +2466,47 @@ where
 			}
 		}
 
-		if let Some((start, end)) = info.block_gap {
-			debug!(target: "sync", "Starting gap sync #{} - #{}", start, end);
-			self.gap_sync = Some(GapSync {
-				best_queued_number: start - One::one(),
-				target: end,
-				blocks: BlockCollection::new(),
-			});
+		match info.block_gap {
+			Some((start, end)) if self.enable_gap_sync => {
+				debug!(target: "sync", "Starting gap sync #{} - #{}", start, end);
+				let best_queued_hash = self.client.hash(start - One::one())?.unwrap_or_default();
+				let gap_sync = match self.gap_sync.take() {
+					// Keep the headers downloaded before the restart.
+					Some(gap_sync) if gap_sync.target == end => GapSync {
+						best_queued_number: start - One::one(),
+						best_queued_hash,
+						blocks: BlockCollection::new(),
+						downloading_headers: false,
+						..gap_sync
+					},
+					_ => {
+						let next = self.client.header(BlockId::Number(end + One::one()))?;
+						let target_hash = match next {
+							Some(header) => *header.parent_hash(),
+							None => {
+								warn!(
+									target: "sync",
+									"💔 Unable to download the block history, block #{} is missing",
+									end + One::one(),
+								);
+								return Ok(())
+							},
+						};
+						GapSync {
+							best_queued_number: start - One::one(),
+							best_queued_hash,
+							start,
+							target: end,
+							blocks: BlockCollection::new(),
+							next_header: Some((end, target_hash)),
+							downloading_headers: false,
+							checkpoints: HashMap::new(),
+						}
+					},
+				};
+				self.gap_sync = Some(gap_sync);
+			},
+			_ => self.gap_sync = None,
 		}
 		trace!(target: "sync", "Restarted sync at #{} ({:?})", self.best_queued_number, self.best_queued_hash);
 		Ok(())
@@ -2381,6 +2726,7 @@ fn peer_gap_block_request<B: BlockT>(
 	attrs: message::BlockAttributes,
 	target: NumberFor<B>,
 	common_number: NumberFor<B>,
+	bodies_from: Option<NumberFor<B>>,
 ) -> Option<(Range<NumberFor<B>>, BlockRequest<B>)> {
 	let range = blocks.needed_blocks(
 		*id,
@@ -2391,6 +2737,27 @@ fn peer_gap_block_request<B: BlockT>(
 		MAX_DOWNLOAD_AHEAD,
 	)?;
 
+	// Gap blocks are finalized already, the database would not store the bodies of the ones
+	// outside of the block pruning window.
+	let attrs = match bodies_from {
+		Some(bodies_from) if range.end <= bodies_from =>
+			attrs.difference(BlockAttributes::BODY | BlockAttributes::INDEXED_BODY),
+		_ => attrs,
+	};
+
+	// Leave the range to a peer that didn't prune the bodies.
+	let peer_pruned_bodies = peer.bodies_from.map_or(false, |from| range.start < from);
+	if attrs.contains(BlockAttributes::BODY) && peer_pruned_bodies {
+		trace!(
+			target: "sync",
+			"Peer {} can't serve the bodies of the gap blocks {:?}",
+			id,
+			range,
+		);
+		blocks.clear_peer_download(id);
+		return None
+	}
+
 	// The end is not part of the range.
 	let last = range.end.saturating_sub(One::one());
 	let from = message::FromBlock::Number(last);
@@ -2614,9 +2981,16 @@ mod test {
 		let block_announce_validator = Box::new(DefaultBlockAnnounceValidator);
 		let peer_id = PeerId::random();
 
-		let mut sync =
-			ChainSync::new(SyncMode::Full, client.clone(), block_announce_validator, 1, None)
-				.unwrap();
+		let mut sync = ChainSync::new(
+			SyncMode::Full,
+			client.clone(),
+			block_announce_validator,
+			1,
+			None,
+			true,
+			None,
+		)
+		.unwrap();
 
 		let (a1_hash, a1_number) = {
 			let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
@@ -2668,6 +3042,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -2836,6 +3212,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -2951,6 +3329,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -3094,6 +3474,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -3224,6 +3606,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -3354,6 +3738,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -3387,6 +3773,8 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			true,
+			None,
 		)
 		.unwrap();
 
@@ -3417,4 +3805,140 @@ mod test {
 		let state = AncestorSearchState::<Block>::BinarySearch(1, 3);
 		assert!(handle_ancestor_search_state(&state, 2, true).is_none());
 	}
+
+	/// Build a chain of `n` blocks, and a gap sync for the blocks `1..n` of a node that only has
+	/// the genesis and the last block.
+	fn chain_with_gap(n: usize) -> (Vec<Block>, GapSync<Block>) {
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.info().genesis_hash;
+		let blocks = (0..n).map(|_| build_block(&mut client, None, false)).collect::<Vec<_>>();
+		let target = n as u64 - 1;
+		let gap_sync = GapSync {
+			blocks: BlockCollection::new(),
+			best_queued_number: 0,
+			best_queued_hash: genesis,
+			start: 1,
+			target,
+			next_header: Some((target, *blocks[n - 1].header().parent_hash())),
+			downloading_headers: true,
+			checkpoints: HashMap::new(),
+		};
+		(blocks, gap_sync)
+	}
+
+	/// Build a block at the same height as `block`, but on another fork.
+	fn fork_of(block: &Block) -> Block {
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let mut fork = build_block(&mut client, None, true);
+		while fork.header().number() < block.header().number() {
+			fork = build_block(&mut client, None, true);
+		}
+		fork
+	}
+
+	#[test]
+	fn gap_sync_downloads_headers_of_the_chain() {
+		let (blocks, mut gap_sync) = chain_with_gap(10);
+		let peer = PeerId::random();
+
+		// A header that is not the next one of the chain is rejected.
+		let response = create_block_response(vec![blocks[7].clone(), fork_of(&blocks[8])]);
+		assert_eq!(
+			gap_sync.on_headers(&peer, &response.blocks),
+			Err(BadPeer(peer, rep::BAD_BLOCK)),
+		);
+		assert!(!gap_sync.downloading_headers);
+		assert_eq!(gap_sync.next_header, Some((9, blocks[8].hash())));
+		assert!(gap_sync.checkpoints.is_empty());
+
+		let response = create_block_response(blocks[4..9].to_vec());
+		gap_sync.on_headers(&peer, &response.blocks).unwrap();
+		assert_eq!(gap_sync.next_header, Some((4, blocks[3].hash())));
+		assert_eq!(gap_sync.checkpoints, [(9, blocks[8].hash())].into_iter().collect());
+
+		let response = create_block_response(blocks[..4].to_vec());
+		gap_sync.on_headers(&peer, &response.blocks).unwrap();
+		assert_eq!(gap_sync.next_header, None);
+		assert_eq!(gap_sync.progress().lowest_header, 1);
+	}
+
+	#[test]
+	fn gap_sync_imports_blocks_linked_to_a_checkpoint() {
+		let (blocks, mut gap_sync) = chain_with_gap(10);
+		let peer = PeerId::random();
+		gap_sync
+			.blocks
+			.insert(1, create_block_response(blocks[..9].to_vec()).blocks, peer);
+
+		// No checkpoint to check the blocks against before the headers are downloaded.
+		assert_eq!(gap_sync.ready_blocks(), Ok(Vec::new()));
+
+		let response = create_block_response(blocks[..9].to_vec());
+		gap_sync.on_headers(&peer, &response.blocks).unwrap();
+		let ready = gap_sync.ready_blocks().unwrap();
+		assert_eq!(
+			ready.iter().map(|b| b.block.hash).collect::<Vec<_>>(),
+			blocks[..9].iter().map(|b| b.hash()).collect::<Vec<_>>(),
+		);
+		assert_eq!(gap_sync.best_queued_hash, blocks[8].hash());
+	}
+
+	#[test]
+	fn gap_sync_reports_peers_sending_blocks_not_linked_to_a_checkpoint() {
+		let (blocks, mut gap_sync) = chain_with_gap(10);
+		let genesis = gap_sync.best_queued_hash;
+		let peer = PeerId::random();
+		let response = create_block_response(blocks[..9].to_vec());
+		gap_sync.on_headers(&peer, &response.blocks).unwrap();
+
+		// A block that doesn't match the hash chain down from the checkpoint.
+		let mut response = create_block_response(blocks[..9].to_vec());
+		response.blocks[4] = create_block_response(vec![fork_of(&blocks[4])]).blocks.remove(0);
+		gap_sync.blocks.insert(1, response.blocks, peer);
+		assert_eq!(gap_sync.ready_blocks(), Err(BadPeer(peer, rep::BAD_BLOCK)));
+		assert_eq!(gap_sync.best_queued_hash, genesis);
+		// The blocks are downloaded again.
+		assert_eq!(gap_sync.blocks.ready_blocks_end(1), 1);
+
+		// Blocks of the chain that are not linked to the last queued block.
+		gap_sync
+			.blocks
+			.insert(1, create_block_response(blocks[..9].to_vec()).blocks, peer);
+		gap_sync.best_queued_hash = Hash::random();
+		assert_eq!(gap_sync.ready_blocks(), Ok(Vec::new()));
+		assert_eq!(gap_sync.blocks.ready_blocks_end(1), 1);
+	}
+
+	#[test]
+	fn gap_block_request_skips_peers_with_pruned_bodies() {
+		let mut blocks = BlockCollection::<Block>::new();
+		let mut peer = PeerSync {
+			peer_id: PeerId::random(),
+			common_number: 0,
+			best_hash: Hash::random(),
+			best_number: 100,
+			state: PeerSyncState::Available,
+			bodies_from: Some(50),
+			justifications_from: None,
+		};
+		let attrs = BlockAttributes::HEADER | BlockAttributes::BODY;
+
+		// The peer pruned the bodies of the range, which is left to other peers.
+		assert!(peer_gap_block_request(&peer.peer_id, &peer, &mut blocks, attrs, 100, 0, None)
+			.is_none());
+
+		// Bodies are not requested when we would not store them anyway.
+		let (range, request) =
+			peer_gap_block_request(&peer.peer_id, &peer, &mut blocks, attrs, 100, 0, Some(70))
+				.unwrap();
+		assert_eq!(range.start, 1);
+		assert!(!request.fields.contains(BlockAttributes::BODY));
+		blocks.clear_peer_download(&peer.peer_id);
+
+		peer.bodies_from = None;
+		let (range, request) =
+			peer_gap_block_request(&peer.peer_id, &peer, &mut blocks, attrs, 100, 0, None).unwrap();
+		assert_eq!(range.start, 1);
+		assert!(request.fields.contains(BlockAttributes::BODY));
+	}
 }
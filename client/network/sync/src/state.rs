@@ -17,16 +17,90 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! State sync support.
+//!
+//! The key space of the top trie is split in ranges that are downloaded in parallel, from
+//! different peers. A child trie is downloaded with the range containing its storage key. The
+//! verified chunks of state are written to a journal, so that the download can be resumed after
+//! a restart.
+//!
+//! The ranges only split the top trie: the content of a child trie is never split, so a single
+//! large child trie is downloaded from one peer at a time.
 
+mod journal;
+
+use self::journal::{Chunk, Journal, JournalTarget};
 use crate::schema::v1::{StateEntry, StateRequest, StateResponse};
 use codec::{Decode, Encode};
-use log::debug;
+use libp2p::PeerId;
+use log::{debug, info, warn};
 use sc_client_api::{CompactProof, ProofProvider};
 use sc_consensus::ImportedState;
 use smallvec::SmallVec;
 use sp_core::storage::well_known_keys;
 use sp_runtime::traits::{Block as BlockT, Header, NumberFor};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	iter,
+	path::Path,
+	sync::Arc,
+};
+
+/// Number of failed requests after which a resumed state sync that did not receive any state is
+/// given up. Peers may have pruned the state of its target in the meantime.
+const MAX_RESUME_FAILURES: u32 = 5;
+
+/// Size of the key space used to report progress, see [`key_position`].
+const KEY_SPACE: u32 = 1 << 16;
+
+/// Returns the keys splitting the key space of the top trie in ranges.
+fn range_boundaries() -> Vec<Vec<u8>> {
+	let mut boundaries: Vec<_> = (1..16u8).map(|i| vec![i << 4]).collect();
+	// Child tries can be large, split their storage keys further.
+	for i in 1..4u8 {
+		let mut key = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec();
+		key.push(i << 6);
+		boundaries.push(key);
+	}
+	boundaries.sort();
+	boundaries
+}
+
+/// Position of a key in the key space, based on its first two bytes.
+fn key_position(key: &[u8]) -> u32 {
+	let byte = |i| key.get(i).copied().unwrap_or(0) as u32;
+	byte(0) << 8 | byte(1)
+}
+
+/// Range of keys of the top trie.
+struct KeyRange {
+	/// Keys of the last downloaded entry of the top trie and, if any, of the child trie being
+	/// downloaded. The download continues after them.
+	cursor: SmallVec<[Vec<u8>; 2]>,
+	/// Last key of the range, `None` for the last range.
+	end: Option<Vec<u8>>,
+	/// Position of the start of the range in the key space.
+	start_position: u32,
+	/// Whether all the entries of the range are downloaded.
+	complete: bool,
+	/// Peer downloading the range, if any.
+	peer: Option<PeerId>,
+}
+
+impl KeyRange {
+	/// Returns the part of the key space covered by the downloaded entries.
+	fn progress(&self) -> u32 {
+		let end = self.end.as_ref().map_or(KEY_SPACE, |end| key_position(end));
+		let position = if self.complete {
+			end
+		} else {
+			self.cursor
+				.first()
+				.map_or(self.start_position, |key| key_position(key))
+				.min(end)
+		};
+		position.saturating_sub(self.start_position)
+	}
+}
 
 /// State sync state machine. Accumulates partial state data until it
 /// is ready to be imported.
@@ -34,12 +108,15 @@ pub struct StateSync<B: BlockT, Client> {
 	target_block: B::Hash,
 	target_header: B::Header,
 	target_root: B::Hash,
-	last_key: SmallVec<[Vec<u8>; 2]>,
-	state: HashMap<Vec<u8>, (Vec<(Vec<u8>, Vec<u8>)>, Vec<Vec<u8>>)>,
+	ranges: Vec<KeyRange>,
+	state: HashMap<Vec<u8>, (BTreeMap<Vec<u8>, Vec<u8>>, Vec<Vec<u8>>)>,
 	complete: bool,
 	client: Arc<Client>,
 	imported_bytes: u64,
 	skip_proof: bool,
+	journal: Option<Journal>,
+	/// Number of failed requests since the state sync was resumed, until some state is received.
+	resume_failures: Option<u32>,
 }
 
 /// Reported state download progress.
@@ -61,50 +138,196 @@ pub enum ImportResult<B: BlockT> {
 	BadResponse,
 }
 
+/// Remove the state sync journal at `path`, once the state is imported or can't be.
+pub fn remove_journal(path: &Path) {
+	if let Err(e) = Journal::remove(path) {
+		warn!(target: "sync", "Failed to remove the state sync journal {:?}: {}", path, e);
+	}
+}
+
 impl<B, Client> StateSync<B, Client>
 where
 	B: BlockT,
 	Client: ProofProvider<B> + Send + Sync + 'static,
 {
 	///  Create a new instance.
-	pub fn new(client: Arc<Client>, target: B::Header, skip_proof: bool) -> Self {
+	///
+	/// The downloaded state is written to a new journal at `journal_path`, if any.
+	pub fn new(
+		client: Arc<Client>,
+		target: B::Header,
+		skip_proof: bool,
+		journal_path: Option<&Path>,
+	) -> Self {
+		let journal = journal_path.and_then(|path| {
+			match Journal::create(path, &JournalTarget { header: &target, skip_proof }) {
+				Ok(journal) => Some(journal),
+				Err(e) => {
+					warn!(
+						target: "sync",
+						"Failed to create the state sync journal {:?}: {}",
+						path,
+						e,
+					);
+					None
+				},
+			}
+		});
+		Self::with_journal(client, target, skip_proof, journal)
+	}
+
+	/// Resume the state sync from the journal at `path`, if there is one for a download with or
+	/// without proofs as requested.
+	pub fn resume(client: Arc<Client>, path: &Path, skip_proof: bool) -> Option<Self> {
+		let (journal, target, chunks) = match Journal::open::<B::Header>(path) {
+			Ok(Some(journal)) => journal,
+			Ok(None) => return None,
+			Err(e) => {
+				warn!(target: "sync", "Failed to read the state sync journal {:?}: {}", path, e);
+				return None
+			},
+		};
+		if target.skip_proof != skip_proof {
+			return None
+		}
+
+		let mut sync = Self::with_journal(client, target.header, skip_proof, Some(journal));
+		for chunk in chunks {
+			if !sync.apply(chunk) {
+				warn!(target: "sync", "Invalid state sync journal {:?}", path);
+				return None
+			}
+		}
+		// The node stopped before the state was imported, download it again.
+		if sync.ranges.iter().all(|range| range.complete) {
+			remove_journal(path);
+			return None
+		}
+
+		info!(
+			target: "sync",
+			"Resuming state sync of #{} ({}) with {} MiB downloaded",
+			sync.target_block_num(),
+			sync.target_block,
+			sync.imported_bytes / (1024 * 1024),
+		);
+		sync.resume_failures = Some(0);
+		Some(sync)
+	}
+
+	fn with_journal(
+		client: Arc<Client>,
+		target: B::Header,
+		skip_proof: bool,
+		journal: Option<Journal>,
+	) -> Self {
+		let boundaries = range_boundaries();
+		let starts: Vec<_> = iter::once(None).chain(boundaries.iter().cloned().map(Some)).collect();
+		let ends = boundaries.into_iter().map(Some).chain(iter::once(None));
+		let ranges = starts
+			.into_iter()
+			.zip(ends)
+			.map(|(start, end)| KeyRange {
+				start_position: start.as_ref().map_or(0, |start| key_position(start)),
+				cursor: start.into_iter().collect(),
+				end,
+				complete: false,
+				peer: None,
+			})
+			.collect();
+
 		Self {
 			client,
 			target_block: target.hash(),
 			target_root: *target.state_root(),
 			target_header: target,
-			last_key: SmallVec::default(),
+			ranges,
 			state: HashMap::default(),
 			complete: false,
 			imported_bytes: 0,
 			skip_proof,
+			journal,
+			resume_failures: None,
+		}
+	}
+
+	///  Validate and import a state response from `who`.
+	pub fn import(&mut self, who: &PeerId, response: StateResponse) -> ImportResult<B> {
+		let index = match self.ranges.iter().position(|range| range.peer.as_ref() == Some(who)) {
+			Some(index) => index,
+			None => {
+				debug!(target: "sync", "Unexpected state response from {}", who);
+				return ImportResult::BadResponse
+			},
+		};
+		self.ranges[index].peer = None;
+
+		let chunk = match self.verify(index, response) {
+			Some(chunk) => chunk,
+			None => {
+				if let Some(failures) = &mut self.resume_failures {
+					*failures += 1;
+				}
+				return ImportResult::BadResponse
+			},
+		};
+		self.resume_failures = None;
+
+		if let Some(journal) = &mut self.journal {
+			if let Err(e) = journal.append(&chunk) {
+				warn!(
+					target: "sync",
+					"Failed to write to the state sync journal {:?}: {}",
+					journal.path(),
+					e,
+				);
+				self.journal = None;
+			}
+		}
+		self.apply(chunk);
+
+		if self.ranges.iter().all(|range| range.complete) {
+			self.complete = true;
+			let state = std::mem::take(&mut self.state).into_iter().map(
+				|(root, (key_values, storage_keys))| {
+					(root, (key_values.into_iter().collect(), storage_keys))
+				},
+			);
+			ImportResult::Import(
+				self.target_block,
+				self.target_header.clone(),
+				ImportedState { block: self.target_block, state: state.into() },
+			)
+		} else {
+			ImportResult::Continue
 		}
 	}
 
-	///  Validate and import a state response.
-	pub fn import(&mut self, response: StateResponse) -> ImportResult<B> {
+	/// Verify a response to a request for the range at `index` and turn it into a chunk.
+	fn verify(&self, index: usize, response: StateResponse) -> Option<Chunk> {
 		if response.entries.is_empty() && response.proof.is_empty() {
 			debug!(target: "sync", "Bad state response");
-			return ImportResult::BadResponse
+			return None
 		}
 		if !self.skip_proof && response.proof.is_empty() {
 			debug!(target: "sync", "Missing proof");
-			return ImportResult::BadResponse
+			return None
 		}
-		let complete = if !self.skip_proof {
+		let range = &self.ranges[index];
+		let (levels, cursor, proof_size) = if !self.skip_proof {
 			debug!(target: "sync", "Importing state from {} trie nodes", response.proof.len());
 			let proof_size = response.proof.len() as u64;
 			let proof = match CompactProof::decode(&mut response.proof.as_ref()) {
 				Ok(proof) => proof,
 				Err(e) => {
 					debug!(target: "sync", "Error decoding proof: {:?}", e);
-					return ImportResult::BadResponse
+					return None
 				},
 			};
 			let (values, completed) = match self.client.verify_range_proof(
 				self.target_root,
 				proof,
-				self.last_key.as_slice(),
+				range.cursor.as_slice(),
 			) {
 				Err(e) => {
 					debug!(
@@ -112,71 +335,41 @@ where
 						"StateResponse failed proof verification: {}",
 						e,
 					);
-					return ImportResult::BadResponse
+					return None
 				},
 				Ok(values) => values,
 			};
 			debug!(target: "sync", "Imported with {} keys", values.len());
 
-			let complete = completed == 0;
-			if !complete && !values.update_last_key(completed, &mut self.last_key) {
-				debug!(target: "sync", "Error updating key cursor, depth: {}", completed);
-			};
-
-			for values in values.0 {
-				let key_values = if values.state_root.is_empty() {
-					// Read child trie roots.
-					values
-						.key_values
-						.into_iter()
-						.filter(|key_value| {
-							if well_known_keys::is_child_storage_key(key_value.0.as_slice()) {
-								self.state
-									.entry(key_value.1.clone())
-									.or_default()
-									.1
-									.push(key_value.0.clone());
-								false
-							} else {
-								true
-							}
-						})
-						.collect()
-				} else {
-					values.key_values
-				};
-				let mut entry = self.state.entry(values.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child_trie with same root.
-					// Warning this will not work with parallel download.
-				} else if entry.0.is_empty() {
-					for (key, _value) in key_values.iter() {
-						self.imported_bytes += key.len() as u64;
-					}
-
-					entry.0 = key_values;
-				} else {
-					for (key, value) in key_values {
-						self.imported_bytes += key.len() as u64;
-						entry.0.push((key, value))
-					}
+			let cursor = if completed == 0 {
+				None
+			} else {
+				let mut cursor = range.cursor.clone();
+				if !values.update_last_key(completed, &mut cursor) {
+					debug!(target: "sync", "Error updating key cursor, depth: {}", completed);
 				}
-			}
-			self.imported_bytes += proof_size;
-			complete
+				Some(cursor)
+			};
+			let levels = values.0.into_iter().map(|level| (level.state_root, level.key_values));
+			(levels.collect::<Vec<_>>(), cursor, proof_size)
 		} else {
+			if response.entries.is_empty() {
+				debug!(target: "sync", "Bad state response");
+				return None
+			}
+			let mut cursor = range.cursor.clone();
 			let mut complete = true;
 			// if the trie is a child trie and one of its parent trie is empty,
 			// the parent cursor stays valid.
 			// Empty parent trie content only happens when all the response content
 			// is part of a single child trie.
-			if self.last_key.len() == 2 && response.entries[0].entries.is_empty() {
+			if cursor.len() == 2 && response.entries[0].entries.is_empty() {
 				// Do not remove the parent trie position.
-				self.last_key.pop();
+				cursor.pop();
 			} else {
-				self.last_key.clear();
+				cursor.clear();
 			}
-			for state in response.entries {
+			for state in &response.entries {
 				debug!(
 					target: "sync",
 					"Importing state from {:?} to {:?}",
@@ -186,56 +379,143 @@ where
 
 				if !state.complete {
 					if let Some(e) = state.entries.last() {
-						self.last_key.push(e.key.clone());
+						cursor.push(e.key.clone());
 					}
 					complete = false;
 				}
-				let is_top = state.state_root.is_empty();
-				let entry = self.state.entry(state.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child trie with same root.
-				} else {
-					let mut child_roots = Vec::new();
-					for StateEntry { key, value } in state.entries {
-						// Skip all child key root (will be recalculated on import).
-						if is_top && well_known_keys::is_child_storage_key(key.as_slice()) {
-							child_roots.push((value, key));
-						} else {
-							self.imported_bytes += key.len() as u64;
-							entry.0.push((key, value))
-						}
-					}
-					for (root, storage_key) in child_roots {
-						self.state.entry(root).or_default().1.push(storage_key);
+			}
+			let levels = response.entries.into_iter().map(|state| {
+				let key_values =
+					state.entries.into_iter().map(|StateEntry { key, value }| (key, value));
+				(state.state_root, key_values.collect())
+			});
+			(levels.collect(), if complete { None } else { Some(cursor) }, 0)
+		};
+		Some(self.range_chunk(index, levels, cursor, proof_size))
+	}
+
+	/// Build the chunk of the range at `index` from the downloaded entries, leaving out the ones
+	/// beyond the end of the range.
+	///
+	/// `cursor` is the position to continue the download from, `None` if the end of the trie
+	/// was reached.
+	fn range_chunk(
+		&self,
+		index: usize,
+		levels: Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>,
+		cursor: Option<SmallVec<[Vec<u8>; 2]>>,
+		proof_size: u64,
+	) -> Chunk {
+		let range = &self.ranges[index];
+		let mut complete = cursor.is_none();
+		// The content of the child trie being downloaded belongs to the range.
+		let mut child_tries: HashSet<Vec<u8>> = match range.cursor.as_slice() {
+			[storage_key, _] => self
+				.state
+				.iter()
+				.filter(|(_, (_, storage_keys))| storage_keys.contains(storage_key))
+				.map(|(root, _)| root.clone())
+				.collect(),
+			_ => HashSet::new(),
+		};
+
+		let mut child_roots = Vec::new();
+		let mut chunk_levels = Vec::new();
+		for (root, mut key_values) in levels {
+			if root.is_empty() {
+				if let Some(end) = &range.end {
+					if let Some(beyond) = key_values.iter().position(|(key, _)| key > end) {
+						key_values.truncate(beyond);
+						complete = true;
 					}
 				}
+				// Skip all child key root (will be recalculated on import).
+				key_values.retain(|(key, value)| {
+					if well_known_keys::is_child_storage_key(key) {
+						child_tries.insert(value.clone());
+						child_roots.push((value.clone(), key.clone()));
+						false
+					} else {
+						true
+					}
+				});
+			} else if !child_tries.contains(&root) {
+				continue
 			}
-			complete
+			chunk_levels.push((root, key_values));
+		}
+
+		let cursor = match cursor {
+			Some(cursor) if !complete => cursor.into_vec(),
+			_ => Vec::new(),
 		};
-		if complete {
-			self.complete = true;
-			ImportResult::Import(
-				self.target_block,
-				self.target_header.clone(),
-				ImportedState {
-					block: self.target_block,
-					state: std::mem::take(&mut self.state).into(),
-				},
-			)
-		} else {
-			ImportResult::Continue
+		Chunk {
+			range: index as u32,
+			cursor,
+			complete,
+			child_roots,
+			levels: chunk_levels,
+			proof_size,
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_request(&self) -> StateRequest {
-		StateRequest {
+	/// Add a verified chunk to the downloaded state.
+	///
+	/// Returns false if the chunk is for an unknown range.
+	fn apply(&mut self, chunk: Chunk) -> bool {
+		let range = match self.ranges.get_mut(chunk.range as usize) {
+			Some(range) => range,
+			None => return false,
+		};
+		range.cursor = SmallVec::from_vec(chunk.cursor);
+		range.complete = chunk.complete;
+
+		for (root, storage_key) in chunk.child_roots {
+			let storage_keys = &mut self.state.entry(root).or_default().1;
+			if !storage_keys.contains(&storage_key) {
+				storage_keys.push(storage_key);
+			}
+		}
+		// The same child trie may be downloaded with several ranges.
+		for (root, key_values) in chunk.levels {
+			let entry = &mut self.state.entry(root).or_default().0;
+			for (key, value) in key_values {
+				let key_len = key.len() as u64;
+				if entry.insert(key, value).is_none() {
+					self.imported_bytes += key_len;
+				}
+			}
+		}
+		self.imported_bytes += chunk.proof_size;
+		true
+	}
+
+	/// Produce the next state request to send to `who`, if a range is left to download.
+	pub fn next_request(&mut self, who: PeerId) -> Option<StateRequest> {
+		let range = self.ranges.iter_mut().find(|range| !range.complete && range.peer.is_none())?;
+		range.peer = Some(who);
+		Some(StateRequest {
 			block: self.target_block.encode(),
-			start: self.last_key.clone().into_vec(),
+			start: range.cursor.clone().into_vec(),
 			no_proof: self.skip_proof,
+		})
+	}
+
+	/// Note that the request sent to `who` failed, so that its range is downloaded again.
+	pub fn on_request_failed(&mut self, who: &PeerId) {
+		for range in self.ranges.iter_mut().filter(|range| range.peer.as_ref() == Some(who)) {
+			range.peer = None;
+			if let Some(failures) = &mut self.resume_failures {
+				*failures += 1;
+			}
 		}
 	}
 
+	/// Returns true if the state sync was resumed and no peer is able to provide its state.
+	pub fn is_stale(&self) -> bool {
+		self.resume_failures.map_or(false, |failures| failures >= MAX_RESUME_FAILURES)
+	}
+
 	/// Check if the state is complete.
 	pub fn is_complete(&self) -> bool {
 		self.complete
@@ -253,8 +533,199 @@ where
 
 	/// Returns state sync estimated progress.
 	pub fn progress(&self) -> StateDownloadProgress {
-		let cursor = *self.last_key.get(0).and_then(|last| last.get(0)).unwrap_or(&0u8);
-		let percent_done = cursor as u32 * 100 / 256;
-		StateDownloadProgress { percentage: percent_done, size: self.imported_bytes }
+		let done: u32 = self.ranges.iter().map(KeyRange::progress).sum();
+		StateDownloadProgress { percentage: done * 100 / KEY_SPACE, size: self.imported_bytes }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::schema::v1::KeyValueStateEntry;
+	use sp_blockchain::HeaderBackend;
+	use sp_runtime::generic::BlockId;
+	use substrate_test_runtime_client::{
+		runtime::Block, DefaultTestClientBuilderExt, TestClient, TestClientBuilder,
+		TestClientBuilderExt,
+	};
+
+	fn state_sync(journal: Option<&Path>) -> StateSync<Block, TestClient> {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let target = client.header(&BlockId::Number(0)).unwrap().unwrap();
+		StateSync::new(client, target, true, journal)
+	}
+
+	fn child_storage_key(name: &[u8]) -> Vec<u8> {
+		[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, name].concat()
+	}
+
+	/// Index of the range containing `key`.
+	fn range_of(sync: &StateSync<Block, TestClient>, key: &[u8]) -> usize {
+		sync.ranges
+			.iter()
+			.position(|range| range.end.as_ref().map_or(true, |end| key <= end.as_slice()))
+			.unwrap()
+	}
+
+	fn key_values(keys: &[&[u8]]) -> Vec<(Vec<u8>, Vec<u8>)> {
+		keys.iter().map(|key| (key.to_vec(), vec![1])).collect()
+	}
+
+	#[test]
+	fn ranges_cover_the_key_space() {
+		let boundaries = range_boundaries();
+		assert!(boundaries.windows(2).all(|w| w[0] < w[1]));
+
+		let child_key = [well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, b"child"].concat();
+		let child_ranges = boundaries
+			.iter()
+			.filter(|boundary| boundary.starts_with(well_known_keys::CHILD_STORAGE_KEY_PREFIX))
+			.count();
+		assert_eq!(child_ranges, 3);
+		assert!(boundaries.iter().any(|boundary| boundary > &child_key));
+	}
+
+	#[test]
+	fn range_progress() {
+		let mut range = KeyRange {
+			cursor: SmallVec::from_vec(vec![vec![0x10]]),
+			end: Some(vec![0x20]),
+			start_position: key_position(&[0x10]),
+			complete: false,
+			peer: None,
+		};
+		assert_eq!(range.progress(), 0);
+		range.cursor = SmallVec::from_vec(vec![vec![0x18, 0x00, 0x01], vec![1]]);
+		assert_eq!(range.progress(), 0x800);
+		// Keys beyond the end of the range are not counted.
+		range.cursor = SmallVec::from_vec(vec![vec![0x30]]);
+		assert_eq!(range.progress(), 0x1000);
+		range.complete = true;
+		assert_eq!(range.progress(), 0x1000);
+	}
+
+	#[test]
+	fn range_chunk_drops_entries_beyond_the_range() {
+		let sync = state_sync(None);
+		assert_eq!(sync.ranges[0].end, Some(vec![0x10]));
+
+		let levels = vec![(Vec::new(), key_values(&[&[0x05], &[0x10], &[0x11]]))];
+		let chunk = sync.range_chunk(0, levels, Some(SmallVec::from_vec(vec![vec![0x11]])), 0);
+		assert_eq!(chunk.levels, vec![(Vec::new(), key_values(&[&[0x05], &[0x10]]))]);
+		assert!(chunk.complete);
+		assert!(chunk.cursor.is_empty());
+
+		let levels = vec![(Vec::new(), key_values(&[&[0x05]]))];
+		let chunk = sync.range_chunk(0, levels, Some(SmallVec::from_vec(vec![vec![0x05]])), 0);
+		assert!(!chunk.complete);
+		assert_eq!(chunk.cursor, vec![vec![0x05]]);
+	}
+
+	#[test]
+	fn range_chunk_keeps_the_child_tries_of_the_range() {
+		let mut sync = state_sync(None);
+		let storage_key = child_storage_key(b"a");
+		let index = range_of(&sync, &storage_key);
+		let (root, other_root) = (vec![1; 32], vec![2; 32]);
+
+		let levels = vec![
+			(Vec::new(), vec![(storage_key.clone(), root.clone())]),
+			(root.clone(), key_values(&[b"k1"])),
+			(other_root.clone(), key_values(&[b"k1"])),
+		];
+		let cursor = SmallVec::from_vec(vec![storage_key.clone(), b"k1".to_vec()]);
+		let chunk = sync.range_chunk(index, levels, Some(cursor), 0);
+		assert_eq!(chunk.child_roots, vec![(root.clone(), storage_key.clone())]);
+		assert_eq!(
+			chunk.levels,
+			vec![(Vec::new(), Vec::new()), (root.clone(), key_values(&[b"k1"]))]
+		);
+		assert!(sync.apply(chunk));
+
+		// The rest of the child trie being downloaded belongs to the range, even without the
+		// child root in the top trie.
+		let levels = vec![(root.clone(), key_values(&[b"k2"])), (other_root, key_values(&[b"k2"]))];
+		let chunk = sync.range_chunk(index, levels, None, 0);
+		assert_eq!(chunk.levels, vec![(root, key_values(&[b"k2"]))]);
+		assert!(chunk.complete);
+	}
+
+	#[test]
+	fn child_tries_shared_by_ranges_are_deduplicated() {
+		let mut sync = state_sync(None);
+		let (first, second) = (child_storage_key(&[0x00]), child_storage_key(&[0xff]));
+		let (first_range, second_range) = (range_of(&sync, &first), range_of(&sync, &second));
+		assert_ne!(first_range, second_range);
+		// Both child tries have the same content, and so the same root.
+		let root = vec![1; 32];
+		let chunk = |index: usize, storage_key: &Vec<u8>| {
+			let levels = vec![
+				(Vec::new(), vec![(storage_key.clone(), root.clone())]),
+				(root.clone(), key_values(&[b"key"])),
+			];
+			sync.range_chunk(index, levels, None, 0)
+		};
+		let (first_chunk, second_chunk) =
+			(chunk(first_range, &first), chunk(second_range, &second));
+
+		assert!(sync.apply(first_chunk));
+		assert!(sync.apply(second_chunk));
+		// A range downloaded again, e.g. when the journal is read back.
+		let first_chunk = sync.range_chunk(
+			first_range,
+			vec![
+				(Vec::new(), vec![(first.clone(), root.clone())]),
+				(root.clone(), key_values(&[b"key"])),
+			],
+			None,
+			0,
+		);
+		assert!(sync.apply(first_chunk));
+
+		let (key_values, storage_keys) = &sync.state[&root];
+		assert_eq!(key_values.len(), 1);
+		assert_eq!(storage_keys, &vec![first, second]);
+		assert_eq!(sync.imported_bytes, b"key".len() as u64);
+	}
+
+	#[test]
+	fn state_sync_is_resumed_from_the_journal() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("state-sync");
+		let peer = PeerId::random();
+
+		let mut sync = state_sync(Some(&path));
+		let request = sync.next_request(peer).unwrap();
+		assert!(request.start.is_empty());
+		let response = StateResponse {
+			entries: vec![KeyValueStateEntry {
+				state_root: Vec::new(),
+				entries: vec![StateEntry { key: vec![0x01], value: vec![1] }],
+				complete: false,
+			}],
+			proof: Vec::new(),
+		};
+		assert!(matches!(sync.import(&peer, response), ImportResult::Continue));
+		let (progress, target) = (sync.progress(), sync.target());
+		drop(sync);
+
+		// A download with proofs can't use the state downloaded without them.
+		assert!(StateSync::<Block, TestClient>::resume(
+			Arc::new(TestClientBuilder::new().build()),
+			&path,
+			false
+		)
+		.is_none());
+
+		let mut sync = StateSync::<Block, TestClient>::resume(
+			Arc::new(TestClientBuilder::new().build()),
+			&path,
+			true,
+		)
+		.unwrap();
+		assert_eq!(sync.target(), target);
+		assert_eq!(sync.progress(), progress);
+		assert_eq!(sync.state[&Vec::new()].0.get(&vec![0x01]), Some(&vec![1]));
+		assert_eq!(sync.next_request(peer).unwrap().start, vec![vec![0x01]]);
 	}
 }
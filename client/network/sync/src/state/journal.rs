@@ -0,0 +1,231 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Journal of the state sync, used to resume it after a restart.
+//!
+//! The journal starts with the target of the state sync, followed by the verified chunks of
+//! state in the order they were downloaded. Chunks are appended as they are received, so that
+//! the file never needs to be rewritten. A chunk that was only partly written when the node
+//! stopped is dropped when the journal is opened again.
+
+use codec::{Decode, Encode};
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+/// Version of the format of the journal file.
+const JOURNAL_VERSION: u8 = 1;
+
+/// Target of the state sync, as written at the start of the journal.
+#[derive(Encode, Decode)]
+pub(super) struct JournalTarget<H> {
+	/// Header of the block whose state is downloaded.
+	pub header: H,
+	/// Whether the state is downloaded without proofs.
+	pub skip_proof: bool,
+}
+
+/// Verified chunk of state downloaded for a key range.
+#[derive(Debug, PartialEq, Eq, Encode, Decode)]
+pub(super) struct Chunk {
+	/// Index of the key range.
+	pub range: u32,
+	/// Keys to continue the download of the range from, empty once it is complete.
+	pub cursor: Vec<Vec<u8>>,
+	/// Whether the download of the range is complete.
+	pub complete: bool,
+	/// Roots of the child tries found in the chunk, with their storage key.
+	pub child_roots: Vec<(Vec<u8>, Vec<u8>)>,
+	/// Key-values of the chunk, for the top trie (empty root) and the child tries.
+	pub levels: Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>,
+	/// Size of the proof of the chunk.
+	pub proof_size: u64,
+}
+
+/// Append-only file of downloaded state chunks.
+pub(super) struct Journal {
+	path: PathBuf,
+	file: File,
+}
+
+impl Journal {
+	/// Create a new journal for `target` at `path`, replacing any previous one.
+	pub fn create<H: Encode>(path: &Path, target: &JournalTarget<H>) -> io::Result<Self> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let mut data = vec![JOURNAL_VERSION];
+		target.encode().encode_to(&mut data);
+		fs::write(path, data)?;
+		let file = OpenOptions::new().append(true).open(path)?;
+		Ok(Self { path: path.to_owned(), file })
+	}
+
+	/// Open the journal at `path` and read its target and chunks.
+	///
+	/// Returns `None` if there is no journal.
+	pub fn open<H: Decode>(
+		path: &Path,
+	) -> io::Result<Option<(Self, JournalTarget<H>, Vec<Chunk>)>> {
+		let data = match fs::read(path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e),
+		};
+
+		let invalid_data = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+		let mut input = match data.split_first() {
+			Some((&JOURNAL_VERSION, records)) => records,
+			Some((version, _)) =>
+				return Err(invalid_data(format!("unsupported journal version {}", version))),
+			None => return Ok(None),
+		};
+
+		let mut records = Vec::new();
+		let mut valid_len = data.len() - input.len();
+		while !input.is_empty() {
+			match Vec::<u8>::decode(&mut input) {
+				Ok(record) => {
+					records.push(record);
+					valid_len = data.len() - input.len();
+				},
+				// The last record was not completely written.
+				Err(_) => break,
+			}
+		}
+
+		let mut records = records.into_iter();
+		let target = match records.next() {
+			Some(record) =>
+				JournalTarget::decode(&mut &record[..]).map_err(|e| invalid_data(e.to_string()))?,
+			None => return Ok(None),
+		};
+		let chunks = records
+			.map(|record| Chunk::decode(&mut &record[..]).map_err(|e| invalid_data(e.to_string())))
+			.collect::<io::Result<_>>()?;
+
+		let file = OpenOptions::new().append(true).open(path)?;
+		if (valid_len as u64) < file.metadata()?.len() {
+			file.set_len(valid_len as u64)?;
+		}
+		Ok(Some((Self { path: path.to_owned(), file }, target, chunks)))
+	}
+
+	/// Append a chunk to the journal.
+	pub fn append(&mut self, chunk: &Chunk) -> io::Result<()> {
+		self.file.write_all(&chunk.encode().encode())
+	}
+
+	/// Returns the path of the journal.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Remove the journal at `path`, if any.
+	pub fn remove(path: &Path) -> io::Result<()> {
+		match fs::remove_file(path) {
+			Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chunk(range: u32, complete: bool) -> Chunk {
+		Chunk {
+			range,
+			cursor: if complete { Vec::new() } else { vec![vec![range as u8, 1]] },
+			complete,
+			child_roots: vec![(vec![1; 32], b":child_storage:default:child".to_vec())],
+			levels: vec![
+				(Vec::new(), vec![(vec![range as u8], vec![1, 2, 3])]),
+				(vec![1; 32], vec![(vec![4], vec![5])]),
+			],
+			proof_size: 100,
+		}
+	}
+
+	#[test]
+	fn missing_journal_is_none() {
+		let dir = tempfile::tempdir().unwrap();
+		assert!(Journal::open::<u64>(&dir.path().join("state-sync")).unwrap().is_none());
+	}
+
+	#[test]
+	fn chunks_are_read_back() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("network").join("state-sync");
+
+		let mut journal =
+			Journal::create(&path, &JournalTarget { header: 42u64, skip_proof: true }).unwrap();
+		journal.append(&chunk(0, false)).unwrap();
+		journal.append(&chunk(3, true)).unwrap();
+		drop(journal);
+
+		let (mut journal, target, chunks) = Journal::open::<u64>(&path).unwrap().unwrap();
+		assert_eq!((target.header, target.skip_proof), (42, true));
+		assert_eq!(chunks, vec![chunk(0, false), chunk(3, true)]);
+
+		// Chunks are appended after the existing ones.
+		journal.append(&chunk(1, false)).unwrap();
+		let (_, _, chunks) = Journal::open::<u64>(&path).unwrap().unwrap();
+		assert_eq!(chunks, vec![chunk(0, false), chunk(3, true), chunk(1, false)]);
+	}
+
+	#[test]
+	fn incomplete_chunk_is_dropped() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("state-sync");
+
+		let mut journal =
+			Journal::create(&path, &JournalTarget { header: 42u64, skip_proof: false }).unwrap();
+		journal.append(&chunk(0, false)).unwrap();
+		let len = fs::metadata(&path).unwrap().len();
+		journal.append(&chunk(1, false)).unwrap();
+		drop(journal);
+		// Cut the last chunk in the middle, as if the node stopped while writing it.
+		OpenOptions::new().write(true).open(&path).unwrap().set_len(len + 10).unwrap();
+
+		let (mut journal, _, chunks) = Journal::open::<u64>(&path).unwrap().unwrap();
+		assert_eq!(chunks, vec![chunk(0, false)]);
+		assert_eq!(fs::metadata(&path).unwrap().len(), len);
+
+		journal.append(&chunk(2, true)).unwrap();
+		let (_, _, chunks) = Journal::open::<u64>(&path).unwrap().unwrap();
+		assert_eq!(chunks, vec![chunk(0, false), chunk(2, true)]);
+	}
+
+	#[test]
+	fn unknown_version_is_rejected() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("state-sync");
+		fs::write(&path, [JOURNAL_VERSION + 1, 0]).unwrap();
+
+		let err = Journal::open::<u64>(&path).err().unwrap();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+		Journal::remove(&path).unwrap();
+		assert!(!path.exists());
+		Journal::remove(&path).unwrap();
+	}
+}
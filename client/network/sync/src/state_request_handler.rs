@@ -29,7 +29,7 @@ use lru::LruCache;
 use prost::Message;
 use sc_client_api::ProofProvider;
 use sc_network_common::{
-	config::ProtocolId,
+	config::{BandwidthPriority, ProtocolId},
 	request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig},
 };
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
@@ -58,6 +58,7 @@ pub fn generate_protocol_config(protocol_id: &ProtocolId) -> ProtocolConfig {
 		max_response_size: 16 * 1024 * 1024,
 		request_timeout: Duration::from_secs(40),
 		inbound_queue: None,
+		bandwidth_priority: BandwidthPriority::Low,
 	}
 }
 
@@ -25,11 +25,12 @@ use crate::{
 	schema::v1::{StateRequest, StateResponse},
 	state::{ImportResult, StateSync},
 };
+use libp2p::PeerId;
 use sc_client_api::ProofProvider;
 use sp_blockchain::HeaderBackend;
 use sp_finality_grandpa::{AuthorityList, SetId};
 use sp_runtime::traits::{Block as BlockT, NumberFor, Zero};
-use std::{fmt, sync::Arc};
+use std::{fmt, path::PathBuf, sync::Arc};
 
 enum Phase<B: BlockT, Client> {
 	WarpProof { set_id: SetId, authorities: AuthorityList, last_hash: B::Hash },
@@ -86,6 +87,7 @@ pub struct WarpSync<B: BlockT, Client> {
 	client: Arc<Client>,
 	warp_sync_provider: Arc<dyn WarpSyncProvider<B>>,
 	total_proof_bytes: u64,
+	state_sync_journal: Option<PathBuf>,
 }
 
 impl<B, Client> WarpSync<B, Client>
@@ -94,24 +96,55 @@ where
 	Client: HeaderBackend<B> + ProofProvider<B> + 'static,
 {
 	///  Create a new instance.
-	pub fn new(client: Arc<Client>, warp_sync_provider: Arc<dyn WarpSyncProvider<B>>) -> Self {
-		let last_hash = client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
-		let phase = Phase::WarpProof {
-			set_id: 0,
-			authorities: warp_sync_provider.current_authorities(),
-			last_hash,
+	///
+	/// The state download is resumed from the journal at `state_sync_journal`, if any, instead
+	/// of downloading the warp proofs again.
+	pub fn new(
+		client: Arc<Client>,
+		warp_sync_provider: Arc<dyn WarpSyncProvider<B>>,
+		state_sync_journal: Option<PathBuf>,
+	) -> Self {
+		let resumed = state_sync_journal
+			.as_deref()
+			.and_then(|path| StateSync::resume(client.clone(), path, false));
+		let phase = match resumed {
+			Some(state_sync) => Phase::State(state_sync),
+			None => {
+				let last_hash =
+					client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
+				Phase::WarpProof {
+					set_id: 0,
+					authorities: warp_sync_provider.current_authorities(),
+					last_hash,
+				}
+			},
 		};
-		Self { client, warp_sync_provider, phase, total_proof_bytes: 0 }
+		Self { client, warp_sync_provider, phase, total_proof_bytes: 0, state_sync_journal }
 	}
 
-	///  Validate and import a state response.
-	pub fn import_state(&mut self, response: StateResponse) -> ImportResult<B> {
+	///  Validate and import a state response from `who`.
+	pub fn import_state(&mut self, who: &PeerId, response: StateResponse) -> ImportResult<B> {
 		match &mut self.phase {
 			Phase::WarpProof { .. } => {
 				log::debug!(target: "sync", "Unexpected state response");
 				ImportResult::BadResponse
 			},
-			Phase::State(sync) => sync.import(response),
+			Phase::State(sync) => sync.import(who, response),
+		}
+	}
+
+	/// Note that the state request sent to `who` failed.
+	pub fn on_state_request_failed(&mut self, who: &PeerId) {
+		if let Phase::State(sync) = &mut self.phase {
+			sync.on_request_failed(who);
+		}
+	}
+
+	/// Returns true if the state download was resumed and no peer is able to provide the state.
+	pub fn is_stale(&self) -> bool {
+		match &self.phase {
+			Phase::WarpProof { .. } => false,
+			Phase::State(sync) => sync.is_stale(),
 		}
 	}
 
@@ -139,7 +172,12 @@ where
 					Ok(VerificationResult::Complete(new_set_id, _, header)) => {
 						log::debug!(target: "sync", "Verified complete proof, set_id={:?}", new_set_id);
 						self.total_proof_bytes += response.0.len() as u64;
-						let state_sync = StateSync::new(self.client.clone(), header, false);
+						let state_sync = StateSync::new(
+							self.client.clone(),
+							header,
+							false,
+							self.state_sync_journal.as_deref(),
+						);
 						self.phase = Phase::State(state_sync);
 						WarpProofImportResult::Success
 					},
@@ -148,11 +186,11 @@ where
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_state_request(&self) -> Option<StateRequest> {
-		match &self.phase {
+	/// Produce the next state request to send to `who`, if any.
+	pub fn next_state_request(&mut self, who: PeerId) -> Option<StateRequest> {
+		match &mut self.phase {
 			Phase::WarpProof { .. } => None,
-			Phase::State(sync) => Some(sync.next_request()),
+			Phase::State(sync) => sync.next_request(who),
 		}
 	}
 
@@ -23,7 +23,7 @@ use futures::{
 };
 use log::debug;
 use sc_network_common::{
-	config::ProtocolId,
+	config::{BandwidthPriority, ProtocolId},
 	request_responses::{
 		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
 	},
@@ -82,6 +82,7 @@ pub fn generate_request_response_config(protocol_id: ProtocolId) -> RequestRespo
 		max_response_size: MAX_RESPONSE_SIZE,
 		request_timeout: Duration::from_secs(10),
 		inbound_queue: None,
+		bandwidth_priority: BandwidthPriority::Low,
 	}
 }
 
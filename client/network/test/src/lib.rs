@@ -25,6 +25,7 @@ mod sync;
 use std::{
 	borrow::Cow,
 	collections::HashMap,
+	path::Path,
 	pin::Pin,
 	sync::Arc,
 	task::{Context as FutureContext, Poll},
@@ -33,7 +34,7 @@ use std::{
 
 use async_std::future::timeout;
 use futures::{future::BoxFuture, prelude::*};
-use libp2p::{build_multiaddr, PeerId};
+use libp2p::{build_multiaddr, multiaddr::Protocol, PeerId};
 use log::trace;
 use parking_lot::Mutex;
 use sc_block_builder::{BlockBuilder, BlockBuilderProvider};
@@ -51,7 +52,7 @@ use sc_network::{
 	block_request_handler::BlockRequestHandler,
 	config::{
 		MultiaddrWithPeerId, NetworkConfiguration, NonDefaultSetConfig, NonReservedPeerMode,
-		ProtocolConfig, Role, SyncMode, TransportConfig,
+		ProtocolConfig, Role, SyncMode, TransportConfig, WssCertificate,
 	},
 	light_client_requests::handler::LightClientRequestHandler,
 	state_request_handler::StateRequestHandler,
@@ -686,6 +687,9 @@ pub struct FullPeerConfig {
 	pub extra_storage: Option<sp_core::storage::Storage>,
 	/// Enable transaction indexing.
 	pub storage_chain: bool,
+	/// Whether the peer listens on a loopback secure WebSocket address instead of an in-memory
+	/// one. It can then only be connected to the peers that also have this enabled.
+	pub wss: bool,
 }
 
 pub trait TestNetFactory: Sized
@@ -782,13 +786,29 @@ where
 			None,
 		));
 
-		let listen_addr = build_multiaddr![Memory(rand::random::<u64>())];
-
 		let mut network_config =
 			NetworkConfiguration::new("test-node", "test-client", Default::default(), None);
 		network_config.sync_mode = config.sync_mode;
-		network_config.transport = TransportConfig::MemoryOnly;
-		network_config.listen_addresses = vec![listen_addr.clone()];
+		// The address of a secure websocket is only known once the network bound its port.
+		let listen_addr = if config.wss {
+			let res = Path::new(env!("CARGO_MANIFEST_DIR")).join("res");
+			network_config.wss_certificate = Some(
+				WssCertificate::from_pem_files(
+					&res.join("localhost.crt"),
+					&res.join("localhost.key"),
+				)
+				.expect("The test certificate is valid"),
+			);
+			network_config.wss_trusted_certificates =
+				vec![std::fs::read(res.join("ca.der")).expect("The test authority is readable")];
+			network_config.listen_addresses = vec!["/ip4/127.0.0.1/tcp/0/wss".parse().unwrap()];
+			None
+		} else {
+			let listen_addr = build_multiaddr![Memory(rand::random::<u64>())];
+			network_config.transport = TransportConfig::MemoryOnly;
+			network_config.listen_addresses = vec![listen_addr.clone()];
+			Some(listen_addr)
+		};
 		network_config.allow_non_globals_in_dht = true;
 		network_config.extra_sets = config
 			.notifications_protocols
@@ -797,6 +817,7 @@ where
 				notifications_protocol: p,
 				fallback_names: Vec::new(),
 				max_notification_size: 1024 * 1024,
+				bandwidth_priority: Default::default(),
 				set_config: Default::default(),
 			})
 			.collect();
@@ -845,7 +866,7 @@ where
 			protocol_config
 		};
 
-		let network = NetworkWorker::new(sc_network::config::Params {
+		let mut network = NetworkWorker::new(sc_network::config::Params {
 			role: if config.is_authority { Role::Authority } else { Role::Full },
 			executor: None,
 			transactions_handler_executor: Box::new(|task| {
@@ -869,6 +890,23 @@ where
 
 		trace!(target: "test_network", "Peer identifier: {}", network.service().local_peer_id());
 
+		let listen_addr = listen_addr.unwrap_or_else(|| {
+			let port = futures::executor::block_on(futures::future::poll_fn(|cx| {
+				let _ = network.poll_unpin(cx);
+				let port =
+					network.listen_addresses().flat_map(|addr| addr.iter()).find_map(|protocol| {
+						match protocol {
+							Protocol::Tcp(port) => Some(port),
+							_ => None,
+						}
+					});
+				port.map_or(Poll::Pending, Poll::Ready)
+			}));
+			// The certificate of the test authority is only valid for `localhost`, which must
+			// be dialed by name for the certificate to be verified.
+			format!("/dns4/localhost/tcp/{}/wss", port).parse().unwrap()
+		});
+
 		self.mut_peers(move |peers| {
 			for peer in peers.iter_mut() {
 				peer.network
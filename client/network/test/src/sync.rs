@@ -55,6 +55,19 @@ fn sync_peers_works() {
 	}));
 }
 
+#[test]
+fn sync_over_secure_websocket_works() {
+	sp_tracing::try_init_simple();
+	let mut net = TestNet::new(0);
+	net.add_full_peer_with_config(FullPeerConfig { wss: true, ..Default::default() });
+	net.add_full_peer_with_config(FullPeerConfig { wss: true, ..Default::default() });
+
+	net.peer(0).push_blocks(10, false);
+	net.block_until_sync();
+	assert!(net.peer(1).client().header(&BlockId::Number(10)).unwrap().is_some());
+	assert!(net.peers()[0].blockchain_canon_equals(&net.peers()[1]));
+}
+
 #[test]
 fn sync_cycle_from_offline_to_syncing_to_offline() {
 	sp_tracing::try_init_simple();
@@ -218,6 +218,12 @@ impl From<u64> for IncomingIndex {
 pub struct PeersetConfig {
 	/// List of sets of nodes the peerset manages.
 	pub sets: Vec<SetConfig>,
+
+	/// Initial reputation of nodes, e.g. the ones saved by a previous run of the node.
+	///
+	/// Nodes whose reputation is below [`BANNED_THRESHOLD`] are considered as banned until their
+	/// reputation decays back above it.
+	pub reputations: Vec<(PeerId, i32)>,
 }
 
 /// Configuration for a single set of nodes.
@@ -300,6 +306,10 @@ impl Peerset {
 			}
 		};
 
+		for (peer_id, reputation) in config.reputations {
+			peerset.data.peer_reputation(peer_id).set_reputation(reputation);
+		}
+
 		for (set, set_config) in config.sets.into_iter().enumerate() {
 			for node in set_config.reserved_nodes {
 				peerset.data.add_no_slot_node(set, node);
@@ -717,6 +727,19 @@ impl Peerset {
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
 	}
+
+	/// Returns the current reputation of all the peers that we have discovered.
+	pub fn reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.update_time();
+
+		self.data
+			.peers()
+			.cloned()
+			.collect::<Vec<_>>()
+			.into_iter()
+			.map(|peer_id| (peer_id, self.data.peer_reputation(peer_id).reputation()))
+			.collect()
+	}
 }
 
 impl Stream for Peerset {
@@ -812,6 +835,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: true,
 			}],
+			reputations: Vec::new(),
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
@@ -845,6 +869,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -876,6 +901,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: true,
 			}],
+			reputations: Vec::new(),
 		};
 
 		let (mut peerset, _) = Peerset::from_config(config);
@@ -897,6 +923,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -923,6 +950,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		});
 
 		// We ban a node by setting its reputation under the threshold.
@@ -956,6 +984,34 @@ mod tests {
 		futures::executor::block_on(fut);
 	}
 
+	#[test]
+	fn test_peerset_initial_reputations() {
+		let banned = PeerId::random();
+		let bootnode = PeerId::random();
+		let (mut peerset, _handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![banned, bootnode],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			reputations: vec![(banned, BANNED_THRESHOLD - 1), (bootnode, 100)],
+		});
+
+		// Only the node that isn't banned gets connected to.
+		let mut peerset = assert_messages(
+			peerset,
+			vec![Message::Connect { set_id: SetId::from(0), peer_id: bootnode }],
+		);
+		peerset.incoming(SetId::from(0), banned, IncomingIndex(1));
+		let mut peerset = assert_messages(peerset, vec![Message::Reject(IncomingIndex(1))]);
+
+		let mut reputations = peerset.reputations();
+		reputations.sort_by_key(|(_, reputation)| *reputation);
+		assert_eq!(reputations, vec![(banned, BANNED_THRESHOLD - 1), (bootnode, 100)]);
+	}
+
 	#[test]
 	fn test_relloc_after_banned() {
 		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
@@ -966,6 +1022,7 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			reputations: Vec::new(),
 		});
 
 		// We ban a node by setting its reputation under the threshold.
@@ -51,6 +51,9 @@ pub enum Error {
 	/// Invalid session keys encoding.
 	#[error("Session keys are not encoded correctly")]
 	InvalidSessionKeys,
+	/// The extrinsic to watch is not in the pool.
+	#[error("Extrinsic is not in the pool")]
+	UnknownExtrinsic,
 	/// Call to an unsafe RPC was denied.
 	#[error(transparent)]
 	UnsafeRpcCalled(#[from] crate::policy::UnsafeRpcError),
@@ -86,6 +89,12 @@ const POOL_NO_TAGS: i32 = POOL_INVALID_TX + 9;
 const POOL_INVALID_BLOCK_ID: i32 = POOL_INVALID_TX + 10;
 /// The pool is not accepting future transactions.
 const POOL_FUTURE_TX: i32 = POOL_INVALID_TX + 11;
+/// The transaction to replace is not in the pool.
+const POOL_REPLACED_NOT_FOUND: i32 = POOL_INVALID_TX + 12;
+/// The transaction does not take the slot of the transaction to replace.
+const POOL_INVALID_REPLACEMENT: i32 = POOL_INVALID_TX + 13;
+/// The bundle does not contain any transaction.
+const POOL_EMPTY_BUNDLE: i32 = POOL_INVALID_TX + 14;
 
 impl From<Error> for JsonRpseeError {
 	fn from(e: Error) -> Self {
@@ -141,6 +150,11 @@ impl From<Error> for JsonRpseeError {
 				format!("Priority is too low: ({} vs {})", old, new),
 				Some("The transaction has too low priority to replace another transaction already in the pool.")
 			)),
+			Error::Pool(PoolError::TooLowPriorityIncrease { required, new }) => CallError::Custom(ErrorObject::owned(
+				POOL_TOO_LOW_PRIORITY,
+				format!("Priority increase is too low: ({} vs {})", required, new),
+				Some("The transaction needs a higher priority to replace another transaction already in the pool.")
+			)),
 			Error::Pool(PoolError::CycleDetected) =>
 				CallError::Custom(ErrorObject::owned(
 				POOL_CYCLE_DETECTED,
@@ -176,6 +190,23 @@ impl From<Error> for JsonRpseeError {
 					None::<()>,
 				))
 			},
+			Error::Pool(PoolError::ReplacedTransactionNotFound) => {
+				CallError::Custom(ErrorObject::owned(
+					POOL_REPLACED_NOT_FOUND,
+					"The transaction to replace is not in the pool",
+					None::<()>,
+				))
+			},
+			Error::Pool(PoolError::InvalidReplacement) => CallError::Custom(ErrorObject::owned(
+				POOL_INVALID_REPLACEMENT,
+				"Invalid replacement",
+				Some("The transaction does not provide any of the tags of the transaction to replace"),
+			)),
+			Error::Pool(PoolError::EmptyBundle) => CallError::Custom(ErrorObject::owned(
+				POOL_EMPTY_BUNDLE,
+				"Empty bundle",
+				Some("The bundle does not contain any transaction"),
+			)),
 			Error::UnsafeRpcCalled(e) => e.into(),
 			e => CallError::Failed(e.into()),
 		}.into()
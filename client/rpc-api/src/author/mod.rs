@@ -32,6 +32,21 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[method(name = "author_submitExtrinsic")]
 	async fn submit_extrinsic(&self, extrinsic: Bytes) -> RpcResult<Hash>;
 
+	/// Replace the pending extrinsic with the given hash by a hex-encoded extrinsic.
+	///
+	/// The replacement needs to take the same slot as the pending extrinsic (e.g. the same
+	/// nonce of an account) and to increase its priority by the minimum bump required by the
+	/// pool. Watchers of the pending extrinsic are notified that it was usurped.
+	#[method(name = "author_replaceExtrinsic")]
+	async fn replace_extrinsic(&self, hash: Hash, extrinsic: Bytes) -> RpcResult<Hash>;
+
+	/// Submit an ordered bundle of hex-encoded extrinsics for inclusion in block.
+	///
+	/// The extrinsics are included together and in the given order, or not at all. The bundle
+	/// is not propagated, so it has to be submitted to a node authoring blocks.
+	#[method(name = "author_submitBundle")]
+	async fn submit_bundle(&self, extrinsics: Vec<Bytes>) -> RpcResult<Vec<Hash>>;
+
 	/// Insert a key into the keystore.
 	#[method(name = "author_insertKey")]
 	fn insert_key(&self, key_type: String, suri: String, public: Bytes) -> RpcResult<()>;
@@ -75,4 +90,26 @@ pub trait AuthorApi<Hash, BlockHash> {
 		item = TransactionStatus<Hash, BlockHash>,
 	)]
 	fn watch_extrinsic(&self, bytes: Bytes);
+
+	/// Replace the pending extrinsic with the given hash by a hex-encoded extrinsic and watch
+	/// the new one.
+	///
+	/// See `author_replaceExtrinsic` for the requirements of the replacement. Watchers of the
+	/// pending extrinsic are notified that it was usurped.
+	#[subscription(
+		name = "author_replaceAndWatchExtrinsic" => "author_replacedExtrinsicUpdate",
+		unsubscribe = "author_unwatchReplacedExtrinsic",
+		item = TransactionStatus<Hash, BlockHash>,
+	)]
+	fn replace_and_watch_extrinsic(&self, hash: Hash, bytes: Bytes);
+
+	/// Watch an extrinsic that is already in the pool, e.g. after a restart of the node.
+	///
+	/// The first notification is the current status of the extrinsic.
+	#[subscription(
+		name = "author_watchExtrinsicByHash" => "author_watchedExtrinsicUpdate",
+		unsubscribe = "author_unwatchExtrinsicByHash",
+		item = TransactionStatus<Hash, BlockHash>,
+	)]
+	fn watch_extrinsic_by_hash(&self, hash: Hash);
 }
@@ -23,8 +23,10 @@
 #![warn(missing_docs)]
 
 mod policy;
+mod rate_limit;
 
 pub use policy::DenyUnsafe;
+pub use rate_limit::{RateLimit, RateLimitConfig, RateLimitError, RATE_LIMITED_METHODS};
 
 pub mod author;
 pub mod chain;
@@ -33,3 +35,4 @@ pub mod dev;
 pub mod offchain;
 pub mod state;
 pub mod system;
+pub mod txpool;
@@ -0,0 +1,320 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate limiting of RPC calls.
+//!
+//! Contains a `RateLimit` type that RPC implementations check before serving a call, in the
+//! same way as `DenyUnsafe`. Every method gets its own token bucket and the methods considered
+//! expensive additionally share one budget.
+//!
+//! The budgets are shared by all the clients of the node, as the method implementations are not
+//! told which connection or address a call comes from. How much of them a single client can use
+//! is bounded by the per-IP and per-connection limits of the RPC servers, see
+//! `sc_rpc_server::PeerRateLimit`.
+
+use std::{
+	collections::HashMap,
+	num::NonZeroU32,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use jsonrpsee::{
+	core::Error as JsonRpseeError,
+	types::{
+		error::{CallError, ErrorCode},
+		ErrorObject,
+	},
+};
+use parking_lot::Mutex;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sc_utils::token_bucket::TokenBucket;
+
+/// Methods checking the rate limits.
+pub const RATE_LIMITED_METHODS: &[&str] = &[
+	"state_call",
+	"state_getKeys",
+	"state_getPairs",
+	"state_getKeysPaged",
+	"state_getStorage",
+	"state_getStorageHash",
+	"state_getStorageSize",
+	"state_getMetadata",
+	"state_getRuntimeVersion",
+	"state_queryStorage",
+	"state_queryStorageAt",
+	"state_getReadProof",
+	"state_getReadProofWithChildren",
+	"state_getReadProofRange",
+	"state_traceBlock",
+	"state_traceBlockDiff",
+	"childstate_getKeys",
+	"childstate_getKeysPaged",
+	"childstate_getStorage",
+	"childstate_getStorageEntries",
+	"childstate_getStorageHash",
+	"childstate_getStorageSize",
+	"childstate_getChildReadProof",
+];
+
+/// Limits of the RPC calls, in calls per minute.
+///
+/// A limit of `n` calls per minute allows bursts of up to `n` calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitConfig {
+	/// Limit of every method that has no limit of its own.
+	pub method_limit: Option<NonZeroU32>,
+	/// Limits of individual methods, by method name.
+	pub method_limits: HashMap<String, NonZeroU32>,
+	/// Limit shared by all the expensive methods.
+	pub expensive_limit: Option<NonZeroU32>,
+}
+
+impl RateLimitConfig {
+	/// Returns `true` if no call is ever limited.
+	pub fn is_unlimited(&self) -> bool {
+		self.method_limit.is_none() &&
+			self.method_limits.is_empty() &&
+			self.expensive_limit.is_none()
+	}
+}
+
+/// Bucket of `limit` calls per minute.
+fn bucket(limit: NonZeroU32, now: Instant) -> TokenBucket {
+	TokenBucket::new(limit.get().into(), Duration::from_secs(60), now)
+}
+
+#[derive(Debug)]
+struct Buckets {
+	methods: HashMap<&'static str, TokenBucket>,
+	expensive: Option<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct Inner {
+	config: RateLimitConfig,
+	buckets: Mutex<Buckets>,
+	rate_limited: Option<CounterVec<U64>>,
+}
+
+/// Shared rate limiter of RPC calls.
+///
+/// Cheap to clone, all clones share the same budgets.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit(Option<Arc<Inner>>);
+
+impl RateLimit {
+	/// Create a rate limiter enforcing the given `config`.
+	///
+	/// Rate limited calls are counted in `registry` if given.
+	pub fn new(
+		config: RateLimitConfig,
+		registry: Option<&Registry>,
+	) -> Result<Self, PrometheusError> {
+		if config.is_unlimited() {
+			return Ok(Self::default())
+		}
+
+		let rate_limited = match registry {
+			Some(registry) => Some(register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_rpc_calls_rate_limited",
+						"Number of RPC calls rejected because of the rate limits.",
+					),
+					&["method"],
+				)?,
+				registry,
+			)?),
+			None => None,
+		};
+		let expensive = config.expensive_limit.map(|limit| bucket(limit, Instant::now()));
+
+		Ok(Self(Some(Arc::new(Inner {
+			config,
+			buckets: Mutex::new(Buckets { methods: HashMap::new(), expensive }),
+			rate_limited,
+		}))))
+	}
+
+	/// Returns `Ok(())` if `method` may be called now, otherwise returns
+	/// `Err(RateLimitError)`.
+	pub fn check(&self, method: &'static str) -> Result<(), RateLimitError> {
+		self.check_at(method, false, Instant::now())
+	}
+
+	/// Same as [`RateLimit::check`], for a method that also consumes the budget of the
+	/// expensive methods.
+	pub fn check_expensive(&self, method: &'static str) -> Result<(), RateLimitError> {
+		self.check_at(method, true, Instant::now())
+	}
+
+	fn check_at(
+		&self,
+		method: &'static str,
+		expensive: bool,
+		now: Instant,
+	) -> Result<(), RateLimitError> {
+		debug_assert!(RATE_LIMITED_METHODS.contains(&method), "{} isn't listed", method);
+		let inner = match &self.0 {
+			Some(inner) => inner,
+			None => return Ok(()),
+		};
+
+		let mut buckets = inner.buckets.lock();
+		let Buckets { methods, expensive: expensive_bucket } = &mut *buckets;
+
+		let limit = inner.config.method_limits.get(method).or(inner.config.method_limit.as_ref());
+		let mut method_bucket =
+			limit.map(|limit| methods.entry(method).or_insert_with(|| bucket(*limit, now)));
+		let mut expensive_bucket = expensive_bucket.as_mut().filter(|_| expensive);
+
+		let has_token = |bucket: &mut TokenBucket| {
+			bucket.refill(now);
+			bucket.tokens() >= 1.0
+		};
+		let allowed = method_bucket.as_deref_mut().map_or(true, has_token) &&
+			expensive_bucket.as_deref_mut().map_or(true, has_token);
+		if !allowed {
+			drop(buckets);
+			if let Some(rate_limited) = &inner.rate_limited {
+				rate_limited.with_label_values(&[method]).inc();
+			}
+			return Err(RateLimitError { method })
+		}
+
+		for bucket in method_bucket.into_iter().chain(expensive_bucket) {
+			bucket.consume(1.0);
+		}
+		Ok(())
+	}
+
+	/// Tokens left in the bucket of `method`.
+	#[cfg(test)]
+	fn tokens(&self, method: &'static str) -> Option<f64> {
+		self.0.as_ref()?.buckets.lock().methods.get(method).map(TokenBucket::tokens)
+	}
+}
+
+/// Signifies that an RPC call was rejected because of the rate limits.
+#[derive(Debug)]
+pub struct RateLimitError {
+	method: &'static str,
+}
+
+impl std::fmt::Display for RateLimitError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Rate limit of RPC call `{}` exceeded, try again later", self.method)
+	}
+}
+
+impl std::error::Error for RateLimitError {}
+
+impl From<RateLimitError> for CallError {
+	fn from(e: RateLimitError) -> CallError {
+		CallError::Custom(ErrorObject::owned(
+			ErrorCode::ServerIsBusy.code(),
+			e.to_string(),
+			None::<()>,
+		))
+	}
+}
+
+impl From<RateLimitError> for JsonRpseeError {
+	fn from(e: RateLimitError) -> JsonRpseeError {
+		JsonRpseeError::Call(e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	fn limit(n: u32) -> Option<NonZeroU32> {
+		NonZeroU32::new(n)
+	}
+
+	#[test]
+	fn unlimited_by_default() {
+		let rate_limit = RateLimit::new(Default::default(), None).unwrap();
+		for _ in 0..1000 {
+			assert!(rate_limit.check_expensive("state_getKeysPaged").is_ok());
+		}
+	}
+
+	#[test]
+	fn methods_have_separate_buckets() {
+		let config = RateLimitConfig {
+			method_limit: limit(2),
+			method_limits: [("state_getKeysPaged".to_string(), NonZeroU32::new(1).unwrap())]
+				.into_iter()
+				.collect(),
+			expensive_limit: None,
+		};
+		let rate_limit = RateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+
+		assert!(rate_limit.check_at("state_getKeysPaged", false, now).is_ok());
+		assert!(rate_limit.check_at("state_getKeysPaged", false, now).is_err());
+
+		assert!(rate_limit.check_at("state_getStorage", false, now).is_ok());
+		assert!(rate_limit.check_at("state_getStorage", false, now).is_ok());
+		let err = rate_limit.check_at("state_getStorage", false, now).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"Rate limit of RPC call `state_getStorage` exceeded, try again later"
+		);
+
+		// Half a minute later one more call to `state_getStorage` is allowed.
+		let later = now + Duration::from_secs(30);
+		assert!(rate_limit.check_at("state_getStorage", false, later).is_ok());
+		assert!(rate_limit.check_at("state_getStorage", false, later).is_err());
+		assert_eq!(rate_limit.tokens("state_getStorage"), Some(0.0));
+	}
+
+	#[test]
+	fn expensive_methods_share_a_budget() {
+		let config = RateLimitConfig { expensive_limit: limit(2), ..Default::default() };
+		let rate_limit = RateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+
+		assert!(rate_limit.check_at("state_getKeysPaged", true, now).is_ok());
+		assert!(rate_limit.check_at("state_queryStorage", true, now).is_ok());
+		assert!(rate_limit.check_at("state_getPairs", true, now).is_err());
+		// Cheap methods are not affected.
+		assert!(rate_limit.check_at("state_getStorage", false, now).is_ok());
+	}
+
+	#[test]
+	fn rejected_calls_consume_no_tokens() {
+		let config = RateLimitConfig {
+			method_limit: limit(10),
+			expensive_limit: limit(1),
+			..Default::default()
+		};
+		let rate_limit = RateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+
+		assert!(rate_limit.check_at("state_queryStorage", true, now).is_ok());
+		for _ in 0..5 {
+			assert!(rate_limit.check_at("state_queryStorage", true, now).is_err());
+		}
+		assert_eq!(rate_limit.tokens("state_queryStorage"), Some(9.0));
+	}
+}
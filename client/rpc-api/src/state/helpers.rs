@@ -19,7 +19,10 @@
 //! Substrate state API helpers.
 
 use serde::{Deserialize, Serialize};
-use sp_core::Bytes;
+use sp_core::{
+	storage::{PrefixedStorageKey, StorageData, StorageKey},
+	Bytes,
+};
 
 /// ReadProof struct returned by the RPC
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -30,3 +33,61 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Keys of a child trie to prove, passed to `state_getReadProofWithChildren` and
+/// `state_getReadProofRange`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildReadProofKeys {
+	/// Prefixed storage key of the child trie.
+	pub child_storage_key: PrefixedStorageKey,
+	/// Keys of the child trie to prove.
+	pub keys: Vec<StorageKey>,
+}
+
+/// Proof of the same storage entries at several blocks, returned by `state_getReadProofRange`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadProofRange<Hash> {
+	/// Hashes of the blocks used to generate the proof, in the requested order.
+	pub at: Vec<Hash>,
+	/// Trie nodes proving the storage entries at every block. Nodes shared by the states of
+	/// several blocks are only included once.
+	pub proof: Vec<Bytes>,
+}
+
+/// Storage changes made by a block, returned by `state_traceBlockDiff`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStorageDiff<Hash> {
+	/// Hash of the block that made the changes.
+	pub block: Hash,
+	/// Hash of the parent block, the old values are read from its state.
+	pub parent_hash: Hash,
+	/// Changes to the main trie, ordered by key.
+	pub top: Vec<StorageDiff>,
+	/// Changes to the child tries, ordered by child storage key.
+	pub children: Vec<ChildStorageDiff>,
+}
+
+/// Changes made by a block to a single child trie.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildStorageDiff {
+	/// Prefixed storage key of the child trie.
+	pub child_storage_key: PrefixedStorageKey,
+	/// Changes to the child trie, ordered by key.
+	pub changes: Vec<StorageDiff>,
+}
+
+/// A storage key whose value was changed or deleted by a block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff {
+	/// The storage key.
+	pub key: StorageKey,
+	/// Value before the block, `None` if the key didn't exist.
+	pub old_value: Option<StorageData>,
+	/// Value after the block, `None` if the key was deleted.
+	pub new_value: Option<StorageData>,
+}
@@ -28,7 +28,9 @@ use sp_version::RuntimeVersion;
 pub mod error;
 pub mod helpers;
 
-pub use self::helpers::ReadProof;
+pub use self::helpers::{
+	BlockStorageDiff, ChildReadProofKeys, ChildStorageDiff, ReadProof, ReadProofRange, StorageDiff,
+};
 
 /// Substrate state API
 #[rpc(client, server)]
@@ -107,6 +109,27 @@ pub trait StateApi<Hash> {
 	#[method(name = "state_getReadProof", blocking)]
 	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> RpcResult<ReadProof<Hash>>;
 
+	/// Returns a single proof of storage entries of the main trie and of child tries at a
+	/// specific block's state.
+	#[method(name = "state_getReadProofWithChildren", blocking)]
+	fn read_proof_with_children(
+		&self,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+		hash: Option<Hash>,
+	) -> RpcResult<ReadProof<Hash>>;
+
+	/// Returns a single proof of the same storage entries at the state of every given block.
+	///
+	/// Child trie entries are proven as well if `child_keys` is given.
+	#[method(name = "state_getReadProofRange", blocking)]
+	fn read_proof_range(
+		&self,
+		keys: Vec<StorageKey>,
+		child_keys: Option<Vec<ChildReadProofKeys>>,
+		blocks: Vec<Hash>,
+	) -> RpcResult<ReadProofRange<Hash>>;
+
 	/// New runtime version subscription
 	#[subscription(
 		name = "state_subscribeRuntimeVersion" => "state_runtimeVersion",
@@ -148,7 +171,7 @@ pub trait StateApi<Hash> {
 	/// ## Node recommendations
 	///
 	/// - Use fast SSD disk storage.
-	/// - Run node flags to increase DB read speed (i.e. `--state-cache-size`, `--db-cache`).
+	/// - Run node flags to increase DB read speed (i.e. `--trie-cache-size`, `--db-cache`).
 	///
 	/// ## Creating tracing enabled WASM runtimes
 	///
@@ -285,4 +308,31 @@ pub trait StateApi<Hash> {
 		storage_keys: Option<String>,
 		methods: Option<String>,
 	) -> RpcResult<sp_rpc::tracing::TraceBlockResponse>;
+
+	/// Re-execute the given block and return every storage key whose value it changed, in the
+	/// main trie and in the child tries, with the values before and after the block. Keys that
+	/// were written with the value they already had are not returned.
+	///
+	/// If `prefixes` is given, only the keys starting with one of the prefixes are returned, e.g.
+	/// the storage prefixes of the pallets one is interested in. The changes of a child trie are
+	/// returned if its prefixed storage key starts with one of the prefixes.
+	///
+	/// ### Example
+	///
+	/// Changes of the `System` pallet (`twox_128("System")`) made by a block:
+	///
+	/// ```text
+	/// curl \
+	/// 	-H "Content-Type: application/json" \
+	/// 	-d '{"id":1, "jsonrpc":"2.0", "method": "state_traceBlockDiff", \
+	/// 		"params": ["0xb246acf1adea1f801ce15c77a5fa7d8f2eb8fed466978bcee172cc02cf64e264", \
+	/// 		["0x26aa394eea5630e07c48ae0c9558cef7"]]}' \
+	/// 	http://localhost:9933/
+	/// ```
+	#[method(name = "state_traceBlockDiff", blocking)]
+	fn trace_block_diff(
+		&self,
+		block: Hash,
+		prefixes: Option<Vec<StorageKey>>,
+	) -> RpcResult<BlockStorageDiff<Hash>>;
 }
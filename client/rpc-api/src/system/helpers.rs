@@ -93,6 +93,26 @@ pub struct SyncState<Number> {
 	/// Height of the highest block learned from the network. Missing if no block is known yet.
 	#[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
 	pub highest_block: Option<Number>,
+	/// Download of the block history missing after a warp or fast sync. Missing if the history
+	/// is not being downloaded.
+	#[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+	pub gap_sync: Option<GapSyncState<Number>>,
+}
+
+/// The state of the download of the block history missing after a warp or fast sync.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GapSyncState<Number> {
+	/// Height of the first missing block when the download started.
+	pub starting_block: Number,
+	/// Height of the last missing block.
+	pub target_block: Number,
+	/// Height of the lowest block whose header is downloaded. The headers are downloaded
+	/// backwards from the target block to check that they are part of the chain, then the blocks
+	/// are downloaded from the starting block.
+	pub lowest_header: Number,
+	/// Height of the highest block of the history downloaded so far.
+	pub current_block: Number,
 }
 #[cfg(test)]
 mod tests {
@@ -132,6 +152,7 @@ mod tests {
 				starting_block: 12u32,
 				current_block: 50u32,
 				highest_block: Some(128u32),
+				gap_sync: None,
 			})
 			.unwrap(),
 			r#"{"startingBlock":12,"currentBlock":50,"highestBlock":128}"#,
@@ -142,9 +163,26 @@ mod tests {
 				starting_block: 12u32,
 				current_block: 50u32,
 				highest_block: None,
+				gap_sync: None,
 			})
 			.unwrap(),
 			r#"{"startingBlock":12,"currentBlock":50}"#,
 		);
+
+		assert_eq!(
+			::serde_json::to_string(&SyncState {
+				starting_block: 12u32,
+				current_block: 50u32,
+				highest_block: Some(128u32),
+				gap_sync: Some(GapSyncState {
+					starting_block: 1u32,
+					target_block: 11u32,
+					lowest_header: 5u32,
+					current_block: 0u32,
+				}),
+			})
+			.unwrap(),
+			r#"{"startingBlock":12,"currentBlock":50,"highestBlock":128,"gapSync":{"startingBlock":1,"targetBlock":11,"lowestHeader":5,"currentBlock":0}}"#,
+		);
 	}
 }
@@ -23,7 +23,7 @@ use jsonrpsee::{
 	proc_macros::rpc,
 };
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{GapSyncState, Health, NodeRole, PeerInfo, SyncState, SystemInfo};
 
 pub mod error;
 pub mod helpers;
@@ -0,0 +1,38 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Substrate transaction pool inspection API.
+//!
+//! Reports the status of the transaction pool and the details of its transactions, which helps
+//! to find out why a transaction is not included in blocks.
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use sc_transaction_pool_api::{PoolInspection, PoolStatus};
+
+/// Substrate transaction pool RPC API
+#[rpc(client, server)]
+pub trait TxPoolApi<Hash> {
+	/// Returns the number and size of the transactions in the pool.
+	#[method(name = "txpool_status")]
+	fn status(&self) -> RpcResult<PoolStatus>;
+
+	/// Returns the transactions in the pool together with their tags, source, the
+	/// transactions they depend on and the number of transactions of each chain.
+	#[method(name = "txpool_inspect")]
+	fn inspect(&self) -> RpcResult<PoolInspection<Hash>>;
+}
@@ -21,13 +21,19 @@
 #![warn(missing_docs)]
 
 use jsonrpsee::{
+	core::Error as JsonRpseeError,
 	http_server::{AccessControlBuilder, HttpServerBuilder, HttpServerHandle},
-	ws_server::{WsServerBuilder, WsServerHandle},
+	ws_server::{WsServerBuilder, WsServerHandle, WsShutdownWaiter},
 	RpcModule,
 };
+use rate_limit::{GateHandle, Protocol};
 use std::{error::Error as StdError, net::SocketAddr};
+use tokio::net::TcpListener;
 
-pub use crate::middleware::{RpcMetrics, RpcMiddleware};
+pub use crate::{
+	middleware::{RpcMetrics, RpcMiddleware},
+	rate_limit::{PeerRateLimit, PeerRateLimitConfig},
+};
 pub use jsonrpsee::core::{
 	id_providers::{RandomIntegerIdProvider, RandomStringIdProvider},
 	traits::IdProvider,
@@ -45,11 +51,35 @@ const WS_MAX_CONNECTIONS: usize = 100;
 const WS_MAX_SUBS_PER_CONN: usize = 1024;
 
 pub mod middleware;
+pub mod rate_limit;
+
+/// Handle of a running HTTP server.
+#[derive(Debug)]
+pub struct HttpServer {
+	handle: HttpServerHandle,
+	_gate: Option<GateHandle>,
+}
 
-/// Type alias for http server
-pub type HttpServer = HttpServerHandle;
-/// Type alias for ws server
-pub type WsServer = WsServerHandle;
+impl HttpServer {
+	/// Requests the server to stop. Returns an error if the server was already stopped.
+	pub fn stop(self) -> Result<tokio::task::JoinHandle<()>, JsonRpseeError> {
+		self.handle.stop()
+	}
+}
+
+/// Handle of a running WS server.
+#[derive(Debug)]
+pub struct WsServer {
+	handle: WsServerHandle,
+	_gate: Option<GateHandle>,
+}
+
+impl WsServer {
+	/// Requests the server to stop. Returns an error if the server was already stopped.
+	pub fn stop(self) -> Result<WsShutdownWaiter, JsonRpseeError> {
+		self.handle.stop()
+	}
+}
 
 /// WebSocket specific settings on the server.
 pub struct WsConfig {
@@ -78,15 +108,17 @@ impl WsConfig {
 }
 
 /// Start HTTP server listening on given address.
+#[allow(clippy::too_many_arguments)]
 pub async fn start_http<M: Send + Sync + 'static>(
 	addrs: [SocketAddr; 2],
 	cors: Option<&Vec<String>>,
 	max_payload_in_mb: Option<usize>,
 	max_payload_out_mb: Option<usize>,
 	metrics: Option<RpcMetrics>,
+	rate_limit: PeerRateLimit,
 	rpc_api: RpcModule<M>,
 	rt: tokio::runtime::Handle,
-) -> Result<HttpServerHandle, Box<dyn StdError + Send + Sync>> {
+) -> Result<HttpServer, Box<dyn StdError + Send + Sync>> {
 	let max_payload_in = payload_size_or_default(max_payload_in_mb);
 	let max_payload_out = payload_size_or_default(max_payload_out_mb);
 
@@ -104,20 +136,29 @@ pub async fn start_http<M: Send + Sync + 'static>(
 		.max_response_body_size(max_payload_out as u32)
 		.set_access_control(acl.build())
 		.health_api("/health", "system_health")?
-		.custom_tokio_runtime(rt);
+		.custom_tokio_runtime(rt.clone());
 
 	let rpc_api = build_rpc_api(rpc_api);
+	let (gate_listener, listen_addrs) = bind_gate(&addrs, &rate_limit).await?;
 	let (handle, addr) = if let Some(metrics) = metrics {
 		let middleware = RpcMiddleware::new(metrics, "http".into());
 		let builder = builder.set_middleware(middleware);
-		let server = builder.build(&addrs[..]).await?;
+		let server = builder.build(&listen_addrs[..]).await?;
 		let addr = server.local_addr();
 		(server.start(rpc_api)?, addr)
 	} else {
-		let server = builder.build(&addrs[..]).await?;
+		let server = builder.build(&listen_addrs[..]).await?;
 		let addr = server.local_addr();
 		(server.start(rpc_api)?, addr)
 	};
+	let (gate, addr) = start_gate(
+		gate_listener,
+		addr,
+		Protocol::Http,
+		rate_limit,
+		(max_payload_in, max_payload_out),
+		&rt,
+	)?;
 
 	log::info!(
 		"Running JSON-RPC HTTP server: addr={}, allowed origins={:?}",
@@ -125,19 +166,21 @@ pub async fn start_http<M: Send + Sync + 'static>(
 		cors
 	);
 
-	Ok(handle)
+	Ok(HttpServer { handle, _gate: gate })
 }
 
 /// Start WS server listening on given address.
+#[allow(clippy::too_many_arguments)]
 pub async fn start_ws<M: Send + Sync + 'static>(
 	addrs: [SocketAddr; 2],
 	cors: Option<&Vec<String>>,
 	ws_config: WsConfig,
 	metrics: Option<RpcMetrics>,
+	rate_limit: PeerRateLimit,
 	rpc_api: RpcModule<M>,
 	rt: tokio::runtime::Handle,
 	id_provider: Option<Box<dyn IdProvider>>,
-) -> Result<WsServerHandle, Box<dyn StdError + Send + Sync>> {
+) -> Result<WsServer, Box<dyn StdError + Send + Sync>> {
 	let (max_payload_in, max_payload_out, max_connections, max_subs_per_conn) =
 		ws_config.deconstruct();
 
@@ -156,7 +199,7 @@ pub async fn start_ws<M: Send + Sync + 'static>(
 		.max_connections(max_connections)
 		.max_subscriptions_per_connection(max_subs_per_conn)
 		.ping_interval(std::time::Duration::from_secs(30))
-		.custom_tokio_runtime(rt)
+		.custom_tokio_runtime(rt.clone())
 		.set_access_control(acl.build());
 
 	if let Some(provider) = id_provider {
@@ -166,17 +209,26 @@ pub async fn start_ws<M: Send + Sync + 'static>(
 	};
 
 	let rpc_api = build_rpc_api(rpc_api);
+	let (gate_listener, listen_addrs) = bind_gate(&addrs, &rate_limit).await?;
 	let (handle, addr) = if let Some(metrics) = metrics {
 		let middleware = RpcMiddleware::new(metrics, "ws".into());
 		let builder = builder.set_middleware(middleware);
-		let server = builder.build(&addrs[..]).await?;
+		let server = builder.build(&listen_addrs[..]).await?;
 		let addr = server.local_addr();
 		(server.start(rpc_api)?, addr)
 	} else {
-		let server = builder.build(&addrs[..]).await?;
+		let server = builder.build(&listen_addrs[..]).await?;
 		let addr = server.local_addr();
 		(server.start(rpc_api)?, addr)
 	};
+	let (gate, addr) = start_gate(
+		gate_listener,
+		addr,
+		Protocol::Ws,
+		rate_limit,
+		(max_payload_in as usize, max_payload_out as usize),
+		&rt,
+	)?;
 
 	log::info!(
 		"Running JSON-RPC WS server: addr={}, allowed origins={:?}",
@@ -184,7 +236,54 @@ pub async fn start_ws<M: Send + Sync + 'static>(
 		cors
 	);
 
-	Ok(handle)
+	Ok(WsServer { handle, _gate: gate })
+}
+
+/// Binds `addrs` for the rate limiting gate if `rate_limit` is enabled.
+///
+/// Returns the listener of the gate and the addresses the server itself should listen on,
+/// which are loopback addresses only reachable through the gate if it is enabled.
+async fn bind_gate(
+	addrs: &[SocketAddr],
+	rate_limit: &PeerRateLimit,
+) -> std::io::Result<(Option<TcpListener>, Vec<SocketAddr>)> {
+	if !rate_limit.is_enabled() {
+		return Ok((None, addrs.to_vec()))
+	}
+
+	let listener = TcpListener::bind(addrs).await?;
+	Ok((Some(listener), vec![SocketAddr::from(([127, 0, 0, 1], 0))]))
+}
+
+/// Starts the rate limiting gate on `listener`, forwarding to the server at `server_addr`.
+///
+/// `max_sizes` are the maximal sizes of the requests and responses of the server. Returns the
+/// gate and the address to connect to.
+fn start_gate(
+	listener: Option<TcpListener>,
+	server_addr: Result<SocketAddr, JsonRpseeError>,
+	protocol: Protocol,
+	rate_limit: PeerRateLimit,
+	max_sizes: (usize, usize),
+	rt: &tokio::runtime::Handle,
+) -> Result<(Option<GateHandle>, Result<SocketAddr, JsonRpseeError>), JsonRpseeError> {
+	match listener {
+		Some(listener) => {
+			let addr = listener.local_addr()?;
+			let (max_request_size, max_response_size) = max_sizes;
+			let gate = rate_limit::start_gate(
+				listener,
+				server_addr?,
+				protocol,
+				rate_limit,
+				max_request_size,
+				max_response_size,
+				rt,
+			);
+			Ok((Some(gate), Ok(addr)))
+		},
+		None => Ok((None, server_addr)),
+	}
 }
 
 fn format_allowed_hosts(addrs: &[SocketAddr]) -> Vec<String> {
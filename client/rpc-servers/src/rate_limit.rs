@@ -0,0 +1,774 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate limiting of RPC requests by remote address and by connection.
+//!
+//! The jsonrpsee middleware is neither told which peer or connection a call comes from, nor able
+//! to reject a call. These limits are therefore enforced by a gate serving the public address of
+//! the server: it accepts the connections, counts the HTTP requests and WebSocket messages of
+//! every connection and IP address, answers the ones over the limits with a JSON-RPC error and
+//! forwards the others to the jsonrpsee server, which listens on a loopback address.
+
+use std::{
+	collections::HashMap,
+	convert::Infallible,
+	net::{IpAddr, SocketAddr},
+	num::NonZeroU32,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use futures::future::{self, Either};
+use hyper::{
+	body::{Bytes, HttpBody},
+	client::HttpConnector,
+	header::{self, HeaderValue},
+	server::conn::Http,
+	service::service_fn,
+	upgrade::Upgraded,
+	Body, Client, Request, Response, StatusCode, Version,
+};
+use jsonrpsee::types::error::ErrorCode;
+use parking_lot::Mutex;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sc_utils::token_bucket::TokenBucket;
+use serde_json::Value;
+use soketto::{
+	connection::{Builder, Error as WsError, Mode, Sender},
+	Data,
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+/// Message of the error answering the requests over the limits.
+const RATE_LIMITED_MESSAGE: &str = "Rate limit of RPC requests exceeded, try again later";
+
+/// Pause after a failed `accept`, which is usually caused by running out of file descriptors.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// Limits of the RPC requests of a single peer, in requests per minute.
+///
+/// A request is an HTTP request or a WebSocket message, so a batch counts as a single request.
+/// A limit of `n` requests per minute allows bursts of up to `n` requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeerRateLimitConfig {
+	/// Limit shared by all the connections from the same IP address.
+	pub ip_limit: Option<NonZeroU32>,
+	/// Limit of every connection.
+	pub connection_limit: Option<NonZeroU32>,
+}
+
+impl PeerRateLimitConfig {
+	/// Returns `true` if no request is ever rejected.
+	pub fn is_unlimited(&self) -> bool {
+		self.ip_limit.is_none() && self.connection_limit.is_none()
+	}
+}
+
+/// Bucket of `limit` requests per minute.
+fn bucket(limit: NonZeroU32, now: Instant) -> TokenBucket {
+	TokenBucket::new(limit.get().into(), Duration::from_secs(60), now)
+}
+
+fn is_full(bucket: &mut TokenBucket, now: Instant) -> bool {
+	bucket.refill(now);
+	bucket.tokens() >= bucket.capacity()
+}
+
+/// Bucket of an IP address, kept as long as it has connections or is not full.
+#[derive(Debug)]
+struct IpBucket {
+	bucket: TokenBucket,
+	connections: usize,
+}
+
+#[derive(Debug)]
+struct Inner {
+	config: PeerRateLimitConfig,
+	ips: Mutex<HashMap<IpAddr, IpBucket>>,
+	rate_limited: Option<CounterVec<U64>>,
+}
+
+/// Rate limiter of the RPC requests of every IP address and connection.
+///
+/// Cheap to clone, all clones share the same per-IP budgets.
+#[derive(Debug, Clone, Default)]
+pub struct PeerRateLimit(Option<Arc<Inner>>);
+
+impl PeerRateLimit {
+	/// Create a rate limiter enforcing the given `config`.
+	///
+	/// Rejected requests are counted in `registry` if given.
+	pub fn new(
+		config: PeerRateLimitConfig,
+		registry: Option<&Registry>,
+	) -> Result<Self, PrometheusError> {
+		if config.is_unlimited() {
+			return Ok(Self::default())
+		}
+
+		let rate_limited = match registry {
+			Some(registry) => Some(register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_rpc_requests_rate_limited",
+						"Number of RPC requests rejected because of the per-IP or per-connection \
+						rate limits.",
+					),
+					&["protocol"],
+				)?,
+				registry,
+			)?),
+			None => None,
+		};
+
+		Ok(Self(Some(Arc::new(Inner { config, ips: Mutex::new(HashMap::new()), rate_limited }))))
+	}
+
+	/// Returns `true` if requests may be rejected.
+	pub fn is_enabled(&self) -> bool {
+		self.0.is_some()
+	}
+
+	/// Budget of a new connection from `ip`.
+	fn connection(&self, ip: IpAddr, now: Instant) -> Option<ConnectionLimit> {
+		let inner = self.0.as_ref()?;
+
+		if let Some(limit) = inner.config.ip_limit {
+			let mut ips = inner.ips.lock();
+			if !ips.contains_key(&ip) {
+				ips.retain(|_, entry| entry.connections > 0 || !is_full(&mut entry.bucket, now));
+			}
+			ips.entry(ip)
+				.or_insert_with(|| IpBucket { bucket: bucket(limit, now), connections: 0 })
+				.connections += 1;
+		}
+
+		Some(ConnectionLimit {
+			inner: inner.clone(),
+			ip,
+			bucket: inner.config.connection_limit.map(|limit| bucket(limit, now)),
+		})
+	}
+
+	/// Number of IP addresses with a bucket.
+	#[cfg(test)]
+	fn ips(&self) -> usize {
+		self.0.as_ref().map_or(0, |inner| inner.ips.lock().len())
+	}
+}
+
+/// Budget of a single connection, which also draws from the budget of its IP address.
+#[derive(Debug)]
+struct ConnectionLimit {
+	inner: Arc<Inner>,
+	ip: IpAddr,
+	bucket: Option<TokenBucket>,
+}
+
+impl ConnectionLimit {
+	/// Takes a token for a request. Returns `false`, and takes no token, if the request exceeds
+	/// one of the limits.
+	fn try_acquire(&mut self, now: Instant) -> bool {
+		let mut ips = self.inner.ips.lock();
+		let ip_bucket = ips.get_mut(&self.ip).map(|entry| &mut entry.bucket);
+		let mut buckets = self.bucket.iter_mut().chain(ip_bucket).collect::<Vec<_>>();
+
+		for bucket in buckets.iter_mut() {
+			bucket.refill(now);
+		}
+		if buckets.iter().any(|bucket| bucket.tokens() < 1.0) {
+			return false
+		}
+		for bucket in buckets {
+			bucket.consume(1.0);
+		}
+		true
+	}
+
+	/// Same as [`ConnectionLimit::try_acquire`], reporting the rejected requests.
+	fn acquire(&mut self, protocol: Protocol) -> bool {
+		if self.try_acquire(Instant::now()) {
+			return true
+		}
+
+		log::debug!(
+			target: "rpc",
+			"Rejecting {} request from {}, rate limit exceeded",
+			protocol.label(),
+			self.ip,
+		);
+		if let Some(rate_limited) = &self.inner.rate_limited {
+			rate_limited.with_label_values(&[protocol.label()]).inc();
+		}
+		false
+	}
+}
+
+impl Drop for ConnectionLimit {
+	fn drop(&mut self) {
+		let mut ips = self.inner.ips.lock();
+		if let Some(entry) = ips.get_mut(&self.ip) {
+			entry.connections -= 1;
+			if entry.connections == 0 && is_full(&mut entry.bucket, Instant::now()) {
+				ips.remove(&self.ip);
+			}
+		}
+	}
+}
+
+/// Protocol of the server behind a gate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Protocol {
+	Http,
+	Ws,
+}
+
+impl Protocol {
+	fn label(&self) -> &'static str {
+		match self {
+			Protocol::Http => "http",
+			Protocol::Ws => "ws",
+		}
+	}
+}
+
+/// Handle of a running gate, which stops accepting connections when dropped.
+#[derive(Debug)]
+pub(crate) struct GateHandle(JoinHandle<()>);
+
+impl Drop for GateHandle {
+	fn drop(&mut self) {
+		self.0.abort();
+	}
+}
+
+/// Shared state of the connections of a gate.
+#[derive(Debug)]
+struct Gate {
+	target: SocketAddr,
+	protocol: Protocol,
+	client: Client<HttpConnector>,
+	max_request_size: usize,
+	max_response_size: usize,
+}
+
+/// Accepts the connections of `listener` and forwards their requests to the server at `target`,
+/// answering the requests exceeding the limits with an error.
+///
+/// The sizes bound the WebSocket messages read by the gate, and the HTTP requests read to answer
+/// them.
+pub(crate) fn start_gate(
+	listener: TcpListener,
+	target: SocketAddr,
+	protocol: Protocol,
+	rate_limit: PeerRateLimit,
+	max_request_size: usize,
+	max_response_size: usize,
+	rt: &tokio::runtime::Handle,
+) -> GateHandle {
+	let gate = Arc::new(Gate {
+		target,
+		protocol,
+		client: Client::new(),
+		max_request_size,
+		max_response_size,
+	});
+	let task = rt.spawn(async move {
+		loop {
+			let (socket, remote) = match listener.accept().await {
+				Ok(accepted) => accepted,
+				Err(e) => {
+					log::debug!(target: "rpc", "Error while accepting an RPC connection: {}", e);
+					tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+					continue
+				},
+			};
+			let limit = Arc::new(Mutex::new(rate_limit.connection(remote.ip(), Instant::now())));
+			let gate = gate.clone();
+			let service = service_fn(move |request| {
+				let gate = gate.clone();
+				let limit = limit.clone();
+				async move { Ok::<_, Infallible>(gate.handle(request, limit).await) }
+			});
+			tokio::spawn(async move {
+				let connection = Http::new().serve_connection(socket, service).with_upgrades();
+				if let Err(e) = connection.await {
+					log::trace!(target: "rpc", "RPC connection from {} closed: {}", remote, e);
+				}
+			});
+		}
+	});
+	GateHandle(task)
+}
+
+impl Gate {
+	async fn handle(
+		self: Arc<Self>,
+		mut request: Request<Body>,
+		limit: Arc<Mutex<Option<ConnectionLimit>>>,
+	) -> Response<Body> {
+		let allowed = limit.lock().as_mut().map_or(true, |limit| limit.acquire(self.protocol));
+		if !allowed {
+			let body = read_body(request.into_body(), self.max_request_size).await;
+			let response = rate_limited_response(&body.unwrap_or_default()).unwrap_or_default();
+			return json_response(StatusCode::OK, response)
+		}
+
+		let is_upgrade =
+			self.protocol == Protocol::Ws && request.headers().contains_key(header::UPGRADE);
+		if !is_upgrade {
+			return self.forward(request).await
+		}
+
+		let client_upgrade = hyper::upgrade::on(&mut request);
+		let mut response = self.forward(request).await;
+		if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+			return response
+		}
+		let server_upgrade = hyper::upgrade::on(&mut response);
+		tokio::spawn(async move {
+			let relayed = match future::try_join(client_upgrade, server_upgrade).await {
+				Ok((client, server)) => self.relay(client, server, limit).await,
+				Err(e) => return log::debug!(target: "rpc", "WebSocket upgrade failed: {}", e),
+			};
+			if let Err(e) = relayed {
+				log::trace!(target: "rpc", "WebSocket connection closed: {}", e);
+			}
+		});
+		response
+	}
+
+	/// Forwards `request` to the server and returns its response.
+	async fn forward(&self, request: Request<Body>) -> Response<Body> {
+		let (mut parts, body) = request.into_parts();
+		// HTTP/2 requests carry the host in the URI, which is replaced.
+		if let Some(authority) = parts.uri.authority() {
+			if !parts.headers.contains_key(header::HOST) {
+				if let Ok(host) = HeaderValue::from_str(authority.as_str()) {
+					parts.headers.insert(header::HOST, host);
+				}
+			}
+		}
+		let path = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+		parts.uri = match format!("http://{}{}", self.target, path).parse() {
+			Ok(uri) => uri,
+			Err(_) => return empty_response(StatusCode::BAD_REQUEST),
+		};
+		parts.version = Version::HTTP_11;
+
+		match self.client.request(Request::from_parts(parts, body)).await {
+			Ok(response) => response,
+			Err(e) => {
+				log::debug!(target: "rpc", "Failed to forward an RPC request: {}", e);
+				empty_response(StatusCode::BAD_GATEWAY)
+			},
+		}
+	}
+
+	/// Relays the messages of an upgraded WebSocket connection, answering the ones of the client
+	/// exceeding the limits with an error.
+	async fn relay(
+		&self,
+		client: Upgraded,
+		server: Upgraded,
+		limit: Arc<Mutex<Option<ConnectionLimit>>>,
+	) -> Result<(), WsError> {
+		let mut client = Builder::new(client.compat(), Mode::Server);
+		client.set_max_message_size(self.max_request_size);
+		let (client_sender, mut client_receiver) = client.finish();
+		let mut server = Builder::new(server.compat(), Mode::Client);
+		server.set_max_message_size(self.max_response_size);
+		let (mut server_sender, mut server_receiver) = server.finish();
+		// Written to by both directions.
+		let client_sender = futures::lock::Mutex::new(client_sender);
+
+		let requests = async {
+			let mut message = Vec::new();
+			loop {
+				message.clear();
+				let data = match client_receiver.receive_data(&mut message).await {
+					Ok(data) => data,
+					Err(e) => {
+						let _ = server_sender.close().await;
+						return Err(e)
+					},
+				};
+				let allowed =
+					limit.lock().as_mut().map_or(true, |limit| limit.acquire(self.protocol));
+				if allowed {
+					send(&mut server_sender, data, &message).await?;
+				} else if let Some(response) = rate_limited_response(&message) {
+					send(&mut *client_sender.lock().await, Data::Text(0), response.as_bytes())
+						.await?;
+				}
+			}
+		};
+		let responses = async {
+			let mut message = Vec::new();
+			loop {
+				message.clear();
+				let data = match server_receiver.receive_data(&mut message).await {
+					Ok(data) => data,
+					Err(e) => {
+						let _ = client_sender.lock().await.close().await;
+						return Err(e)
+					},
+				};
+				send(&mut *client_sender.lock().await, data, &message).await?;
+			}
+		};
+
+		futures::pin_mut!(requests, responses);
+		match future::select(requests, responses).await {
+			Either::Left((result, _)) | Either::Right((result, _)) => result,
+		}
+	}
+}
+
+/// Sends `message` as `data` over a WebSocket connection.
+async fn send(
+	sender: &mut Sender<Compat<Upgraded>>,
+	data: Data,
+	message: &[u8],
+) -> Result<(), WsError> {
+	match data {
+		Data::Text(_) => match std::str::from_utf8(message) {
+			Ok(message) => sender.send_text(message).await?,
+			Err(_) => sender.send_binary(message).await?,
+		},
+		Data::Binary(_) => sender.send_binary(message).await?,
+	}
+	sender.flush().await
+}
+
+/// Reads up to `max_size` bytes of `body`, returns `None` if it is longer or fails.
+async fn read_body(mut body: Body, max_size: usize) -> Option<Bytes> {
+	let mut bytes = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk.ok()?;
+		if bytes.len() + chunk.len() > max_size {
+			return None
+		}
+		bytes.extend_from_slice(&chunk);
+	}
+	Some(bytes.into())
+}
+
+/// JSON-RPC response to the calls of a rejected `request`.
+///
+/// Returns `None` if the request only contains notifications, which are not answered.
+fn rate_limited_response(request: &[u8]) -> Option<String> {
+	let error = |id: &Value| {
+		serde_json::json!({
+			"jsonrpc": "2.0",
+			"error": { "code": ErrorCode::ServerIsBusy.code(), "message": RATE_LIMITED_MESSAGE },
+			"id": id,
+		})
+	};
+	let response = match serde_json::from_slice::<Value>(request) {
+		Ok(Value::Array(calls)) => {
+			let errors =
+				calls.iter().filter_map(|call| call.get("id")).map(error).collect::<Vec<_>>();
+			if errors.is_empty() {
+				return None
+			}
+			Value::Array(errors)
+		},
+		Ok(call @ Value::Object(_)) => error(call.get("id")?),
+		// Invalid requests are answered anyway, their id is unknown.
+		_ => error(&Value::Null),
+	};
+	Some(response.to_string())
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+	let mut response = Response::new(Body::from(body));
+	*response.status_mut() = status;
+	response
+		.headers_mut()
+		.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
+	response
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+	let mut response = Response::new(Body::empty());
+	*response.status_mut() = status;
+	response
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::{
+		io::{AsyncRead, AsyncWrite},
+		net::{TcpSocket, TcpStream},
+	};
+
+	fn limit(n: u32) -> Option<NonZeroU32> {
+		NonZeroU32::new(n)
+	}
+
+	fn localhost(n: u8) -> IpAddr {
+		IpAddr::from([127, 0, 0, n])
+	}
+
+	const CALL: &str = r#"{"jsonrpc":"2.0","method":"system_name","id":1}"#;
+	const RESULT: &str = r#"{"jsonrpc":"2.0","result":"node","id":1}"#;
+
+	fn rate_limited(id: u32) -> Value {
+		serde_json::json!({
+			"jsonrpc": "2.0",
+			"error": { "code": ErrorCode::ServerIsBusy.code(), "message": RATE_LIMITED_MESSAGE },
+			"id": id,
+		})
+	}
+
+	/// Starts a gate enforcing `config` in front of a server answering every request with
+	/// `RESULT`.
+	async fn start_test_gate(
+		protocol: Protocol,
+		config: PeerRateLimitConfig,
+	) -> (SocketAddr, GateHandle) {
+		let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let target = server.local_addr().unwrap();
+		tokio::spawn(async move {
+			loop {
+				let (socket, _) = server.accept().await.unwrap();
+				tokio::spawn(async move {
+					match protocol {
+						Protocol::Http => serve_http(socket).await,
+						Protocol::Ws => serve_ws(socket).await,
+					}
+				});
+			}
+		});
+
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		let gate = start_gate(
+			listener,
+			target,
+			protocol,
+			PeerRateLimit::new(config, None).unwrap(),
+			1024,
+			1024,
+			&tokio::runtime::Handle::current(),
+		);
+		(addr, gate)
+	}
+
+	async fn serve_http(socket: TcpStream) {
+		let service =
+			service_fn(|_| async { Ok::<_, Infallible>(Response::new(Body::from(RESULT))) });
+		let _ = Http::new().serve_connection(socket, service).await;
+	}
+
+	async fn serve_ws(socket: TcpStream) {
+		let mut server = soketto::handshake::Server::new(socket.compat());
+		let key = server.receive_request().await.unwrap().key();
+		let accept = soketto::handshake::server::Response::Accept { key, protocol: None };
+		server.send_response(&accept).await.unwrap();
+		let (mut sender, mut receiver) = server.into_builder().finish();
+		let mut message = Vec::new();
+		while receiver.receive_data(&mut message).await.is_ok() {
+			sender.send_text(RESULT).await.unwrap();
+			sender.flush().await.unwrap();
+		}
+	}
+
+	/// Connects to `addr` from the local address `ip`.
+	async fn connect(ip: IpAddr, addr: SocketAddr) -> TcpStream {
+		let socket = TcpSocket::new_v4().unwrap();
+		socket.bind(SocketAddr::new(ip, 0)).unwrap();
+		socket.connect(addr).await.unwrap()
+	}
+
+	/// Client sending HTTP requests over a single connection.
+	struct HttpClient(hyper::client::conn::SendRequest<Body>);
+
+	impl HttpClient {
+		async fn new(ip: IpAddr, addr: SocketAddr) -> Self {
+			let (sender, connection) =
+				hyper::client::conn::handshake(connect(ip, addr).await).await.unwrap();
+			tokio::spawn(connection);
+			Self(sender)
+		}
+
+		async fn call(&mut self) -> Value {
+			let request = Request::post("/")
+				.header(header::HOST, "localhost")
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(Body::from(CALL))
+				.unwrap();
+			let response = self.0.send_request(request).await.unwrap();
+			let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+			serde_json::from_slice(&body).unwrap()
+		}
+	}
+
+	/// Client sending WebSocket messages over a single connection.
+	struct WsClient<T: AsyncRead + AsyncWrite + Unpin> {
+		sender: Sender<Compat<T>>,
+		receiver: soketto::connection::Receiver<Compat<T>>,
+	}
+
+	impl WsClient<TcpStream> {
+		async fn new(ip: IpAddr, addr: SocketAddr) -> Self {
+			let host = addr.to_string();
+			let mut client =
+				soketto::handshake::Client::new(connect(ip, addr).await.compat(), &host, "/");
+			assert!(matches!(
+				client.handshake().await.unwrap(),
+				soketto::handshake::ServerResponse::Accepted { .. }
+			));
+			let (sender, receiver) = client.into_builder().finish();
+			Self { sender, receiver }
+		}
+
+		async fn call(&mut self) -> Value {
+			self.sender.send_text(CALL).await.unwrap();
+			self.sender.flush().await.unwrap();
+			let mut message = Vec::new();
+			self.receiver.receive_data(&mut message).await.unwrap();
+			serde_json::from_slice(&message).unwrap()
+		}
+	}
+
+	#[tokio::test]
+	async fn http_requests_over_the_limit_of_a_connection_are_rejected() {
+		let config = PeerRateLimitConfig { connection_limit: limit(2), ..Default::default() };
+		let (addr, _gate) = start_test_gate(Protocol::Http, config).await;
+		let result: Value = serde_json::from_str(RESULT).unwrap();
+
+		let mut first = HttpClient::new(localhost(1), addr).await;
+		assert_eq!(first.call().await, result);
+		assert_eq!(first.call().await, result);
+		assert_eq!(first.call().await, rate_limited(1));
+
+		// Another connection has a budget of its own.
+		let mut second = HttpClient::new(localhost(1), addr).await;
+		assert_eq!(second.call().await, result);
+		assert_eq!(first.call().await, rate_limited(1));
+	}
+
+	#[tokio::test]
+	async fn ws_messages_over_the_limit_of_an_ip_address_are_rejected() {
+		// The handshake is a request as well.
+		let config = PeerRateLimitConfig { ip_limit: limit(3), ..Default::default() };
+		let (addr, _gate) = start_test_gate(Protocol::Ws, config).await;
+		let result: Value = serde_json::from_str(RESULT).unwrap();
+
+		let mut first = WsClient::new(localhost(1), addr).await;
+		assert_eq!(first.call().await, result);
+		assert_eq!(first.call().await, result);
+		assert_eq!(first.call().await, rate_limited(1));
+
+		// The connections of another address have a budget of their own.
+		let mut second = WsClient::new(localhost(2), addr).await;
+		assert_eq!(second.call().await, result);
+		assert_eq!(second.call().await, result);
+		assert_eq!(first.call().await, rate_limited(1));
+	}
+
+	#[test]
+	fn answers_every_call_of_a_rejected_request() {
+		let response = |request: &str| {
+			rate_limited_response(request.as_bytes())
+				.map(|response| serde_json::from_str::<Value>(&response).unwrap())
+		};
+
+		assert_eq!(response(CALL), Some(rate_limited(1)));
+		let batch = r#"[
+			{"jsonrpc":"2.0","method":"system_name","id":1},
+			{"jsonrpc":"2.0","method":"system_health"},
+			{"jsonrpc":"2.0","method":"system_name","id":2}
+		]"#;
+		assert_eq!(response(batch), Some(Value::Array(vec![rate_limited(1), rate_limited(2)])));
+		// Notifications are not answered.
+		assert_eq!(response(r#"{"jsonrpc":"2.0","method":"system_name"}"#), None);
+		assert_eq!(response(r#"[{"jsonrpc":"2.0","method":"system_name"}]"#), None);
+		// Invalid requests are answered without an id.
+		assert_eq!(response("{").unwrap()["id"], Value::Null);
+	}
+
+	#[test]
+	fn unlimited_by_default() {
+		let rate_limit = PeerRateLimit::new(Default::default(), None).unwrap();
+		assert!(!rate_limit.is_enabled());
+		assert!(rate_limit.connection(localhost(1), Instant::now()).is_none());
+	}
+
+	#[test]
+	fn connections_have_separate_buckets() {
+		let config = PeerRateLimitConfig { connection_limit: limit(2), ..Default::default() };
+		let rate_limit = PeerRateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+		let mut first = rate_limit.connection(localhost(1), now).unwrap();
+		let mut second = rate_limit.connection(localhost(1), now).unwrap();
+
+		assert!(first.try_acquire(now));
+		assert!(first.try_acquire(now));
+		assert!(!first.try_acquire(now));
+		assert!(second.try_acquire(now));
+
+		// Half a minute later one more request is allowed.
+		let later = now + Duration::from_secs(30);
+		assert!(first.try_acquire(later));
+		assert!(!first.try_acquire(later));
+	}
+
+	#[test]
+	fn connections_share_the_bucket_of_their_ip() {
+		let config = PeerRateLimitConfig { ip_limit: limit(3), connection_limit: limit(2) };
+		let rate_limit = PeerRateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+		let mut first = rate_limit.connection(localhost(1), now).unwrap();
+		let mut second = rate_limit.connection(localhost(1), now).unwrap();
+		let mut other = rate_limit.connection(localhost(2), now).unwrap();
+
+		assert!(first.try_acquire(now));
+		assert!(first.try_acquire(now));
+		assert!(second.try_acquire(now));
+		assert!(!second.try_acquire(now));
+		// A rejected request consumes no tokens of the connection.
+		assert_eq!(second.bucket.as_ref().map(TokenBucket::tokens), Some(1.0));
+		assert!(other.try_acquire(now));
+	}
+
+	#[test]
+	fn ip_buckets_are_removed_once_refilled() {
+		let config = PeerRateLimitConfig { ip_limit: limit(2), ..Default::default() };
+		let rate_limit = PeerRateLimit::new(config, None).unwrap();
+		let now = Instant::now();
+
+		// Unused, so the bucket is removed with the connection.
+		drop(rate_limit.connection(localhost(1), now));
+		assert_eq!(rate_limit.ips(), 0);
+
+		let mut connection = rate_limit.connection(localhost(2), now).unwrap();
+		assert!(connection.try_acquire(now));
+		drop(connection);
+		assert_eq!(rate_limit.ips(), 1);
+
+		// The bucket of the second address is full again by the time the next address connects.
+		let later = now + Duration::from_secs(30);
+		let _connection = rate_limit.connection(localhost(3), later).unwrap();
+		assert_eq!(rate_limit.ips(), 1);
+	}
+}
@@ -0,0 +1,93 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! API trait of the chain head.
+
+use crate::chain_head::event::{ChainHeadEvent, FollowEvent};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use sp_core::{
+	storage::{PrefixedStorageKey, StorageData, StorageKey},
+	Bytes,
+};
+
+#[rpc(client, server)]
+pub trait ChainHeadApi<Hash> {
+	/// Track the state of the head of the chain: the finalized, non-finalized and best blocks.
+	///
+	/// The first event of the subscription carries the identifier that has to be passed as
+	/// `follow_subscription` to the other functions of this API. Every block reported by the
+	/// subscription is pinned until it is unpinned with `chainHead_unpin`.
+	#[subscription(
+		name = "chainHead_follow",
+		unsubscribe = "chainHead_unfollow",
+		item = FollowEvent<Hash>,
+	)]
+	fn follow(&self, runtime_updates: bool);
+
+	/// Retrieves the body (the list of SCALE encoded extrinsics) of a pinned block.
+	#[subscription(
+		name = "chainHead_body",
+		unsubscribe = "chainHead_stopBody",
+		item = ChainHeadEvent<Vec<Bytes>>,
+	)]
+	fn body(&self, follow_subscription: String, hash: Hash);
+
+	/// Retrieves the SCALE encoded header of a pinned block.
+	///
+	/// Returns `None` if the follow subscription is not active anymore.
+	#[method(name = "chainHead_header", blocking)]
+	fn header(&self, follow_subscription: String, hash: Hash) -> RpcResult<Option<Bytes>>;
+
+	/// Retrieves a storage value of a pinned block.
+	///
+	/// The value is read from the child trie `child_key` if it is given.
+	#[subscription(
+		name = "chainHead_storage",
+		unsubscribe = "chainHead_stopStorage",
+		item = ChainHeadEvent<Option<StorageData>>,
+	)]
+	fn storage(
+		&self,
+		follow_subscription: String,
+		hash: Hash,
+		key: StorageKey,
+		child_key: Option<PrefixedStorageKey>,
+	);
+
+	/// Call into the runtime of a pinned block.
+	///
+	/// Only available if the follow subscription was started with `runtime_updates` set.
+	#[subscription(
+		name = "chainHead_call",
+		unsubscribe = "chainHead_stopCall",
+		item = ChainHeadEvent<Bytes>,
+	)]
+	fn call(
+		&self,
+		follow_subscription: String,
+		hash: Hash,
+		function: String,
+		call_parameters: Bytes,
+	);
+
+	/// Unpin a block reported by the follow subscription.
+	///
+	/// The block can not be queried through this subscription anymore and may be pruned.
+	#[method(name = "chainHead_unpin", blocking)]
+	fn unpin(&self, follow_subscription: String, hash: Hash) -> RpcResult<()>;
+}
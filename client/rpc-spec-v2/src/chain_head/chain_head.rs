@@ -0,0 +1,448 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! API implementation for `chainHead`.
+
+use crate::{
+	chain_head::{
+		api::ChainHeadApiServer,
+		error::Error as ChainHeadRpcError,
+		event::{
+			BestBlockChanged, ChainHeadEvent, ChainHeadResult, ErrorEvent, Finalized, FollowEvent,
+			Initialized, NewBlock, RuntimeEvent, RuntimeVersionEvent,
+		},
+		subscription::{SubscriptionError, SubscriptionManagement},
+	},
+	SubscriptionTaskExecutor,
+};
+use codec::Encode;
+use futures::{stream, FutureExt, StreamExt};
+use jsonrpsee::{
+	core::{id_providers::RandomStringIdProvider, traits::IdProvider, RpcResult},
+	types::SubscriptionId,
+	PendingSubscription,
+};
+use sc_client_api::{
+	Backend, BlockBackend, BlockImportNotification, BlockchainEvents, CallExecutor,
+	ExecutorProvider, FinalityNotification, StorageProvider,
+};
+use serde::Serialize;
+use sp_blockchain::{
+	Backend as BlockchainBackend, HeaderBackend, HeaderMetadata, Result as ClientResult,
+};
+use sp_core::{
+	storage::{ChildInfo, ChildType, PrefixedStorageKey, StorageData, StorageKey},
+	Bytes,
+};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT},
+};
+use std::{collections::VecDeque, sync::Arc};
+
+/// Maximum number of blocks a follow subscription may pin before it is stopped.
+const MAX_PINNED_BLOCKS: usize = 512;
+
+/// Length of the identifiers of the follow subscriptions.
+const SUBSCRIPTION_ID_LEN: usize = 16;
+
+/// An API for chain head RPC calls.
+pub struct ChainHead<BE: Backend<Block>, Block: BlockT, Client> {
+	/// Substrate client.
+	client: Arc<Client>,
+	/// Backend of the chain.
+	backend: Arc<BE>,
+	/// Executor to spawn subscriptions.
+	executor: SubscriptionTaskExecutor,
+	/// The follow subscriptions and the blocks they pinned.
+	subscriptions: Arc<SubscriptionManagement<Block, BE>>,
+}
+
+impl<BE: Backend<Block>, Block: BlockT, Client> ChainHead<BE, Block, Client> {
+	/// Create a new instance of the chain head API.
+	pub fn new(client: Arc<Client>, backend: Arc<BE>, executor: SubscriptionTaskExecutor) -> Self {
+		let subscriptions =
+			Arc::new(SubscriptionManagement::new(backend.clone(), MAX_PINNED_BLOCKS));
+		Self { client, backend, executor, subscriptions }
+	}
+}
+
+fn generate_subscription_id() -> String {
+	match RandomStringIdProvider::new(SUBSCRIPTION_ID_LEN).next_id() {
+		SubscriptionId::Str(id) => id.into_owned(),
+		SubscriptionId::Num(id) => id.to_string(),
+	}
+}
+
+/// A notification handled by a follow subscription.
+enum Notification<Block: BlockT> {
+	NewBlock(BlockImportNotification<Block>),
+	Finalized(FinalityNotification<Block>),
+}
+
+/// Generates the events of a single follow subscription.
+struct ChainHeadFollower<BE: Backend<Block>, Block: BlockT, Client> {
+	client: Arc<Client>,
+	backend: Arc<BE>,
+	subscriptions: Arc<SubscriptionManagement<Block, BE>>,
+	sub_id: String,
+	runtime_updates: bool,
+}
+
+impl<BE, Block, Client> ChainHeadFollower<BE, Block, Client>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: HeaderBackend<Block>
+		+ HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ ExecutorProvider<Block>
+		+ 'static,
+{
+	/// The runtime of `hash`, or `None` if runtime updates are disabled or the runtime is the
+	/// same as the one of `parent`.
+	fn runtime_event(
+		&self,
+		hash: Block::Hash,
+		parent: Option<Block::Hash>,
+	) -> Option<RuntimeEvent> {
+		if !self.runtime_updates {
+			return None
+		}
+
+		let executor = self.client.executor();
+		let version = match executor.runtime_version(&BlockId::Hash(hash)) {
+			Ok(version) => version,
+			Err(e) => return Some(RuntimeEvent::Invalid(ErrorEvent { error: e.to_string() })),
+		};
+		let parent_version =
+			parent.and_then(|parent| executor.runtime_version(&BlockId::Hash(parent)).ok());
+		if parent_version.as_ref() == Some(&version) {
+			return None
+		}
+
+		Some(RuntimeEvent::Valid(RuntimeVersionEvent { spec: version }))
+	}
+
+	/// Pin a new block and report it, unless it was already reported.
+	fn new_block(
+		&self,
+		hash: Block::Hash,
+		parent_hash: Block::Hash,
+	) -> Result<Option<FollowEvent<Block::Hash>>, SubscriptionError> {
+		if !self.subscriptions.pin_block(&self.sub_id, hash)? {
+			return Ok(None)
+		}
+
+		Ok(Some(FollowEvent::NewBlock(NewBlock {
+			block_hash: hash,
+			parent_block_hash: parent_hash,
+			new_runtime: self.runtime_event(hash, Some(parent_hash)),
+		})))
+	}
+
+	/// The first events of the subscription: the finalized block, all of its descendants and
+	/// the best block.
+	fn initial_events(&self) -> Result<Vec<FollowEvent<Block::Hash>>, SubscriptionError> {
+		let info = self.client.info();
+		let finalized = info.finalized_hash;
+		self.subscriptions.pin_block(&self.sub_id, finalized)?;
+
+		let mut events = vec![FollowEvent::Initialized(Initialized {
+			follow_subscription: self.sub_id.clone(),
+			finalized_block_hash: finalized,
+			finalized_block_runtime: self.runtime_event(finalized, None),
+		})];
+
+		// Report the descendants of the finalized block, parents first.
+		for leaf in self.backend.blockchain().leaves()? {
+			let route = sp_blockchain::tree_route(&*self.client, finalized, leaf)?;
+			// Forks below the finalized block are about to be pruned.
+			if !route.retracted().is_empty() {
+				continue
+			}
+			for block in route.enacted() {
+				let parent_hash = self.client.header_metadata(block.hash)?.parent;
+				events.extend(self.new_block(block.hash, parent_hash)?);
+			}
+		}
+
+		events.push(FollowEvent::BestBlockChanged(BestBlockChanged {
+			best_block_hash: info.best_hash,
+		}));
+		Ok(events)
+	}
+
+	/// Hashes of all the blocks of the branches ending at `stale_heads`, down to the canonical
+	/// chain.
+	fn pruned_blocks(&self, stale_heads: &[Block::Hash]) -> ClientResult<Vec<Block::Hash>> {
+		let mut pruned = Vec::new();
+		for head in stale_heads {
+			let mut hash = *head;
+			loop {
+				let header = self.client.header_metadata(hash)?;
+				if self.client.hash(header.number)? == Some(hash) {
+					break
+				}
+				pruned.push(hash);
+				hash = header.parent;
+			}
+		}
+		Ok(pruned)
+	}
+
+	fn handle(&self, notification: Notification<Block>) -> Vec<FollowEvent<Block::Hash>> {
+		let result = match notification {
+			Notification::NewBlock(notification) => self
+				.new_block(notification.hash, *notification.header.parent_hash())
+				.map(|event| {
+					let best = notification.is_new_best.then_some(FollowEvent::BestBlockChanged(
+						BestBlockChanged { best_block_hash: notification.hash },
+					));
+					event.into_iter().chain(best).collect()
+				}),
+			Notification::Finalized(notification) => self
+				.pruned_blocks(&notification.stale_heads)
+				.map(|pruned_block_hashes| {
+					let mut finalized_block_hashes = notification.tree_route.to_vec();
+					finalized_block_hashes.push(notification.hash);
+					vec![FollowEvent::Finalized(Finalized {
+						finalized_block_hashes,
+						pruned_block_hashes,
+					})]
+				})
+				.map_err(Into::into),
+		};
+
+		result.unwrap_or_else(|e| {
+			log::debug!(target: "rpc-spec-v2", "Stopping follow subscription {}: {}", self.sub_id, e);
+			vec![FollowEvent::Stop]
+		})
+	}
+}
+
+impl<BE, Block, Client> ChainHead<BE, Block, Client>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: HeaderBackend<Block> + Send + Sync + 'static,
+{
+	/// Answer a query about a block pinned by `follow_subscription` with a single event.
+	///
+	/// The query is run on a blocking task.
+	fn spawn_query<T, F>(
+		&self,
+		pending: PendingSubscription,
+		follow_subscription: &str,
+		hash: &Block::Hash,
+		query: F,
+	) where
+		T: Serialize + Send + 'static,
+		F: FnOnce(&Client) -> ChainHeadEvent<T> + Send + 'static,
+	{
+		let event_fn: Box<dyn FnOnce(&Client) -> ChainHeadEvent<T> + Send> =
+			if !self.subscriptions.contains_subscription(follow_subscription) {
+				Box::new(|_| ChainHeadEvent::Disjoint)
+			} else if !self.subscriptions.contains_block(follow_subscription, hash) {
+				pending.reject(ChainHeadRpcError::InvalidBlock);
+				return
+			} else {
+				Box::new(query)
+			};
+
+		let client = self.client.clone();
+		let fut = async move {
+			let event = event_fn(&client);
+			if let Some(mut sink) = pending.accept() {
+				let _ = sink.send(&event);
+			}
+		};
+
+		self.executor
+			.spawn_blocking("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+	}
+}
+
+fn query_result<T>(result: ClientResult<Option<T>>, what: &str) -> ChainHeadEvent<T> {
+	match result {
+		Ok(Some(result)) => ChainHeadEvent::Done(ChainHeadResult { result }),
+		Ok(None) =>
+			ChainHeadEvent::Inaccessible(ErrorEvent { error: format!("{} not found", what) }),
+		Err(e) => ChainHeadEvent::Error(ErrorEvent { error: e.to_string() }),
+	}
+}
+
+impl<BE, Block, Client> ChainHeadApiServer<Block::Hash> for ChainHead<BE, Block, Client>
+where
+	Block: BlockT + 'static,
+	Block::Header: Unpin,
+	BE: Backend<Block> + 'static,
+	Client: BlockBackend<Block>
+		+ ExecutorProvider<Block>
+		+ HeaderBackend<Block>
+		+ HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ BlockchainEvents<Block>
+		+ StorageProvider<Block, BE>
+		+ Send
+		+ Sync
+		+ 'static,
+{
+	fn follow(&self, pending: PendingSubscription, runtime_updates: bool) {
+		let sub_id = generate_subscription_id();
+		if !self.subscriptions.insert_subscription(sub_id.clone(), runtime_updates) {
+			pending.reject(ChainHeadRpcError::Client(sp_blockchain::Error::Application(
+				"Duplicate follow subscription id".into(),
+			)));
+			return
+		}
+
+		// Subscribe before reading the initial blocks, so that no block can be missed. Blocks
+		// that are seen twice are only reported once because they are pinned already.
+		let notifications = stream::select(
+			self.client.import_notification_stream().map(Notification::NewBlock),
+			self.client.finality_notification_stream().map(Notification::Finalized),
+		);
+
+		let follower = ChainHeadFollower {
+			client: self.client.clone(),
+			backend: self.backend.clone(),
+			subscriptions: self.subscriptions.clone(),
+			sub_id: sub_id.clone(),
+			runtime_updates,
+		};
+		let initial = follower.initial_events().unwrap_or_else(|e| {
+			log::debug!(target: "rpc-spec-v2", "Stopping follow subscription {}: {}", sub_id, e);
+			vec![FollowEvent::Stop]
+		});
+
+		// The stream ends right after a `Stop` event.
+		let events = stream::unfold(
+			(follower, notifications, VecDeque::from(initial), false),
+			|(follower, mut notifications, mut queue, stopped)| async move {
+				loop {
+					if stopped {
+						return None
+					}
+					if let Some(event) = queue.pop_front() {
+						let stopped = matches!(event, FollowEvent::Stop);
+						return Some((event, (follower, notifications, queue, stopped)))
+					}
+					let notification = notifications.next().await?;
+					queue.extend(follower.handle(notification));
+				}
+			},
+		)
+		.boxed();
+
+		let subscriptions = self.subscriptions.clone();
+		let fut = async move {
+			if let Some(mut sink) = pending.accept() {
+				sink.pipe_from_stream(events).await;
+			}
+			subscriptions.remove_subscription(&sub_id);
+		};
+
+		self.executor.spawn("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+	}
+
+	fn body(&self, pending: PendingSubscription, follow_subscription: String, hash: Block::Hash) {
+		self.spawn_query(pending, &follow_subscription, &hash, move |client| {
+			let body = client.block_body(&BlockId::Hash(hash)).map(|body| {
+				body.map(|extrinsics| {
+					extrinsics.iter().map(|x| x.encode().into()).collect::<Vec<Bytes>>()
+				})
+			});
+			query_result(body, "Body")
+		})
+	}
+
+	fn header(&self, follow_subscription: String, hash: Block::Hash) -> RpcResult<Option<Bytes>> {
+		if !self.subscriptions.contains_subscription(&follow_subscription) {
+			return Ok(None)
+		}
+		if !self.subscriptions.contains_block(&follow_subscription, &hash) {
+			return Err(ChainHeadRpcError::InvalidBlock.into())
+		}
+
+		self.client
+			.header(BlockId::Hash(hash))
+			.map(|header| header.map(|header| header.encode().into()))
+			.map_err(|e| ChainHeadRpcError::Client(e).into())
+	}
+
+	fn storage(
+		&self,
+		pending: PendingSubscription,
+		follow_subscription: String,
+		hash: Block::Hash,
+		key: StorageKey,
+		child_key: Option<PrefixedStorageKey>,
+	) {
+		self.spawn_query(pending, &follow_subscription, &hash, move |client| {
+			let id = BlockId::Hash(hash);
+			let value = match child_key {
+				Some(child_key) => match ChildType::from_prefixed_key(&child_key) {
+					Some((ChildType::ParentKeyId, child_key)) =>
+						client.child_storage(&id, &ChildInfo::new_default(child_key), &key),
+					None => Err(sp_blockchain::Error::InvalidChildStorageKey),
+				},
+				None => client.storage(&id, &key),
+			};
+			// A missing value is a valid result.
+			query_result(value.map(Some::<Option<StorageData>>), "Storage")
+		})
+	}
+
+	fn call(
+		&self,
+		pending: PendingSubscription,
+		follow_subscription: String,
+		hash: Block::Hash,
+		function: String,
+		call_parameters: Bytes,
+	) {
+		if self.subscriptions.runtime_updates(&follow_subscription) == Some(false) {
+			pending.reject(ChainHeadRpcError::RuntimeUpdatesDisabled);
+			return
+		}
+
+		self.spawn_query(pending, &follow_subscription, &hash, move |client| {
+			let output = client
+				.executor()
+				.call(
+					&BlockId::Hash(hash),
+					&function,
+					&call_parameters,
+					client.execution_extensions().strategies().other,
+					None,
+				)
+				.map(|output| Some(Bytes(output)));
+			query_result(output, "Call output")
+		})
+	}
+
+	fn unpin(&self, follow_subscription: String, hash: Block::Hash) -> RpcResult<()> {
+		if !self.subscriptions.contains_subscription(&follow_subscription) {
+			// The subscription ended and all of its blocks are unpinned already.
+			return Ok(())
+		}
+		if !self.subscriptions.unpin_block(&follow_subscription, &hash) {
+			return Err(ChainHeadRpcError::InvalidBlock.into())
+		}
+		Ok(())
+	}
+}
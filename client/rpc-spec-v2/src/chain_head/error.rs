@@ -0,0 +1,58 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Error helpers for the chain head API.
+
+use jsonrpsee::{
+	core::Error as JsonRpseeError,
+	types::error::{CallError, ErrorObject},
+};
+
+/// Chain head API errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	/// The block hash is not pinned by the follow subscription.
+	#[error("Invalid block hash")]
+	InvalidBlock,
+	/// The follow subscription was started without runtime updates.
+	#[error("The follow subscription was started without runtime updates")]
+	RuntimeUpdatesDisabled,
+	/// Client error.
+	#[error("Client error: {0}")]
+	Client(#[from] sp_blockchain::Error),
+}
+
+/// Base error code for all chain head errors.
+const BASE_ERROR: i32 = 7000;
+
+impl From<Error> for ErrorObject<'static> {
+	fn from(e: Error) -> Self {
+		let code = match e {
+			Error::InvalidBlock => BASE_ERROR + 1,
+			Error::RuntimeUpdatesDisabled => BASE_ERROR + 2,
+			Error::Client(_) => BASE_ERROR + 3,
+		};
+		ErrorObject::owned(code, e.to_string(), None::<()>)
+	}
+}
+
+impl From<Error> for JsonRpseeError {
+	fn from(e: Error) -> Self {
+		CallError::Custom(e.into()).into()
+	}
+}
@@ -0,0 +1,194 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Events of the chain head API.
+
+use serde::{Deserialize, Serialize};
+use sp_version::RuntimeVersion;
+
+/// The runtime of a block changed and is valid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeVersionEvent {
+	/// The runtime version.
+	pub spec: RuntimeVersion,
+}
+
+/// The runtime of a block as reported by a follow subscription with runtime updates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type")]
+pub enum RuntimeEvent {
+	/// The runtime is valid.
+	Valid(RuntimeVersionEvent),
+	/// The runtime could not be read.
+	Invalid(ErrorEvent),
+}
+
+/// An error that happened while producing an event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorEvent {
+	/// Reason of the error.
+	pub error: String,
+}
+
+/// The first event of a follow subscription.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Initialized<Hash> {
+	/// Identifier of the follow subscription, to be passed to the other chain head functions.
+	pub follow_subscription: String,
+	/// The hash of the latest finalized block.
+	pub finalized_block_hash: Hash,
+	/// The runtime of the finalized block.
+	///
+	/// Only present if the subscription was started with runtime updates.
+	pub finalized_block_runtime: Option<RuntimeEvent>,
+}
+
+/// A new non-finalized block was added to the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewBlock<Hash> {
+	/// The hash of the new block.
+	pub block_hash: Hash,
+	/// The parent hash of the new block.
+	pub parent_block_hash: Hash,
+	/// The runtime of the new block if it differs from the runtime of its parent.
+	///
+	/// Always `None` if the subscription was started without runtime updates.
+	pub new_runtime: Option<RuntimeEvent>,
+}
+
+/// The best block of the chain changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BestBlockChanged<Hash> {
+	/// The hash of the new best block.
+	pub best_block_hash: Hash,
+}
+
+/// Blocks were finalized.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finalized<Hash> {
+	/// The hashes of the finalized blocks, ordered by increasing block number.
+	pub finalized_block_hashes: Vec<Hash>,
+	/// The hashes of the blocks of forks that can never be finalized anymore.
+	pub pruned_block_hashes: Vec<Hash>,
+}
+
+/// An event of a `chainHead_follow` subscription.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum FollowEvent<Hash> {
+	/// The first event of the subscription.
+	Initialized(Initialized<Hash>),
+	/// A new non-finalized block was added.
+	NewBlock(NewBlock<Hash>),
+	/// The best block of the chain changed.
+	BestBlockChanged(BestBlockChanged<Hash>),
+	/// Blocks were finalized.
+	Finalized(Finalized<Hash>),
+	/// The subscription was stopped by the node, no more events are sent.
+	///
+	/// All the blocks pinned by the subscription are unpinned.
+	Stop,
+}
+
+/// The result of a chain head query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainHeadResult<T> {
+	/// The result of the query.
+	pub result: T,
+}
+
+/// The event of a `chainHead_body`, `chainHead_storage` or `chainHead_call` subscription.
+///
+/// Exactly one event is sent before the subscription ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum ChainHeadEvent<T> {
+	/// The query succeeded.
+	Done(ChainHeadResult<T>),
+	/// The data of the block is not available anymore.
+	Inaccessible(ErrorEvent),
+	/// The query failed.
+	Error(ErrorEvent),
+	/// The follow subscription is not active anymore.
+	Disjoint,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn follow_events_serialize() {
+		let event: FollowEvent<String> = FollowEvent::Initialized(Initialized {
+			follow_subscription: "sub".into(),
+			finalized_block_hash: "0x1".into(),
+			finalized_block_runtime: None,
+		});
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"initialized","followSubscription":"sub","finalizedBlockHash":"0x1","finalizedBlockRuntime":null}"#;
+		assert_eq!(ser, exp);
+		assert_eq!(serde_json::from_str::<FollowEvent<String>>(exp).unwrap(), event);
+
+		let event: FollowEvent<String> = FollowEvent::NewBlock(NewBlock {
+			block_hash: "0x2".into(),
+			parent_block_hash: "0x1".into(),
+			new_runtime: Some(RuntimeEvent::Invalid(ErrorEvent { error: "oops".into() })),
+		});
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"newBlock","blockHash":"0x2","parentBlockHash":"0x1","newRuntime":{"type":"invalid","error":"oops"}}"#;
+		assert_eq!(ser, exp);
+		assert_eq!(serde_json::from_str::<FollowEvent<String>>(exp).unwrap(), event);
+
+		let event: FollowEvent<String> = FollowEvent::Finalized(Finalized {
+			finalized_block_hashes: vec!["0x1".into(), "0x2".into()],
+			pruned_block_hashes: vec!["0x3".into()],
+		});
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"finalized","finalizedBlockHashes":["0x1","0x2"],"prunedBlockHashes":["0x3"]}"#;
+		assert_eq!(ser, exp);
+
+		let ser = serde_json::to_string(&FollowEvent::<String>::Stop).unwrap();
+		assert_eq!(ser, r#"{"event":"stop"}"#);
+	}
+
+	#[test]
+	fn chain_head_events_serialize() {
+		let event = ChainHeadEvent::Done(ChainHeadResult { result: "0x00".to_string() });
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"done","result":"0x00"}"#;
+		assert_eq!(ser, exp);
+		assert_eq!(serde_json::from_str::<ChainHeadEvent<String>>(exp).unwrap(), event);
+
+		let event = ChainHeadEvent::<String>::Error(ErrorEvent { error: "oops".into() });
+		let ser = serde_json::to_string(&event).unwrap();
+		assert_eq!(ser, r#"{"event":"error","error":"oops"}"#);
+
+		let ser = serde_json::to_string(&ChainHeadEvent::<String>::Disjoint).unwrap();
+		assert_eq!(ser, r#"{"event":"disjoint"}"#);
+	}
+}
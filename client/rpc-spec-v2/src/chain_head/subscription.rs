@@ -0,0 +1,204 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Bookkeeping of the follow subscriptions and the blocks they pinned.
+
+use parking_lot::RwLock;
+use sc_client_api::Backend;
+use sp_runtime::traits::Block as BlockT;
+use std::{
+	collections::{hash_map::Entry, HashMap, HashSet},
+	marker::PhantomData,
+	sync::Arc,
+};
+
+/// Error of pinning a block for a subscription.
+#[derive(Debug, thiserror::Error)]
+pub enum SubscriptionError {
+	/// The subscription does not exist.
+	#[error("Invalid subscription")]
+	InvalidSubscription,
+	/// The subscription pinned too many blocks and has to be stopped.
+	#[error("Exceeded the maximum number of pinned blocks")]
+	TooManyPinnedBlocks,
+	/// Client error.
+	#[error("Client error: {0}")]
+	Client(#[from] sp_blockchain::Error),
+}
+
+/// State of a single follow subscription.
+struct SubscriptionState<Block: BlockT> {
+	/// The subscription was started with runtime updates.
+	runtime_updates: bool,
+	/// The blocks pinned by the subscription.
+	pinned: HashSet<Block::Hash>,
+}
+
+/// Keeps track of the follow subscriptions and pins their blocks in the backend.
+pub struct SubscriptionManagement<Block: BlockT, BE: Backend<Block>> {
+	subscriptions: RwLock<HashMap<String, SubscriptionState<Block>>>,
+	backend: Arc<BE>,
+	/// Maximum number of blocks a single subscription may pin.
+	max_pinned_blocks: usize,
+	_phantom: PhantomData<Block>,
+}
+
+impl<Block: BlockT, BE: Backend<Block>> SubscriptionManagement<Block, BE> {
+	/// Create a new instance.
+	pub fn new(backend: Arc<BE>, max_pinned_blocks: usize) -> Self {
+		Self {
+			subscriptions: Default::default(),
+			backend,
+			max_pinned_blocks,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Register a new subscription.
+	///
+	/// Returns `false` if a subscription with the same id already exists.
+	pub fn insert_subscription(&self, sub_id: String, runtime_updates: bool) -> bool {
+		match self.subscriptions.write().entry(sub_id) {
+			Entry::Occupied(_) => false,
+			Entry::Vacant(entry) => {
+				entry.insert(SubscriptionState { runtime_updates, pinned: Default::default() });
+				true
+			},
+		}
+	}
+
+	/// Remove a subscription and unpin all of its blocks.
+	pub fn remove_subscription(&self, sub_id: &str) {
+		if let Some(state) = self.subscriptions.write().remove(sub_id) {
+			for hash in state.pinned {
+				self.backend.unpin_block(&hash);
+			}
+		}
+	}
+
+	/// Returns whether the subscription exists and was started with runtime updates.
+	pub fn runtime_updates(&self, sub_id: &str) -> Option<bool> {
+		self.subscriptions.read().get(sub_id).map(|state| state.runtime_updates)
+	}
+
+	/// Returns whether the subscription exists.
+	pub fn contains_subscription(&self, sub_id: &str) -> bool {
+		self.subscriptions.read().contains_key(sub_id)
+	}
+
+	/// Returns whether the block is pinned by the subscription.
+	pub fn contains_block(&self, sub_id: &str, hash: &Block::Hash) -> bool {
+		self.subscriptions
+			.read()
+			.get(sub_id)
+			.map_or(false, |state| state.pinned.contains(hash))
+	}
+
+	/// Pin the block for the subscription.
+	///
+	/// Returns `false` if the block was already pinned by the subscription.
+	pub fn pin_block(&self, sub_id: &str, hash: Block::Hash) -> Result<bool, SubscriptionError> {
+		let mut subscriptions = self.subscriptions.write();
+		let state = subscriptions.get_mut(sub_id).ok_or(SubscriptionError::InvalidSubscription)?;
+		if state.pinned.contains(&hash) {
+			return Ok(false)
+		}
+		if state.pinned.len() >= self.max_pinned_blocks {
+			return Err(SubscriptionError::TooManyPinnedBlocks)
+		}
+
+		self.backend.pin_block(&hash)?;
+		state.pinned.insert(hash);
+		Ok(true)
+	}
+
+	/// Unpin the block for the subscription.
+	///
+	/// Returns `false` if the block was not pinned by the subscription.
+	pub fn unpin_block(&self, sub_id: &str, hash: &Block::Hash) -> bool {
+		let mut subscriptions = self.subscriptions.write();
+		let unpinned =
+			subscriptions.get_mut(sub_id).map_or(false, |state| state.pinned.remove(hash));
+		if unpinned {
+			self.backend.unpin_block(hash);
+		}
+		unpinned
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_client_api::in_mem::Backend as InMemBackend;
+	use sp_core::H256;
+	use substrate_test_runtime_client::runtime::Block;
+
+	fn subscriptions(
+		max_pinned_blocks: usize,
+	) -> SubscriptionManagement<Block, InMemBackend<Block>> {
+		SubscriptionManagement::new(Arc::new(InMemBackend::new()), max_pinned_blocks)
+	}
+
+	#[test]
+	fn subscriptions_are_unique() {
+		let subs = subscriptions(10);
+		assert!(subs.insert_subscription("sub".into(), true));
+		assert!(!subs.insert_subscription("sub".into(), false));
+		assert_eq!(subs.runtime_updates("sub"), Some(true));
+		assert_eq!(subs.runtime_updates("other"), None);
+
+		subs.remove_subscription("sub");
+		assert!(!subs.contains_subscription("sub"));
+		assert!(subs.insert_subscription("sub".into(), false));
+	}
+
+	#[test]
+	fn blocks_are_pinned_per_subscription() {
+		let subs = subscriptions(10);
+		let hash = H256::repeat_byte(1);
+		subs.insert_subscription("a".into(), false);
+		subs.insert_subscription("b".into(), false);
+
+		assert!(subs.pin_block("a", hash).unwrap());
+		assert!(!subs.pin_block("a", hash).unwrap());
+		assert!(subs.contains_block("a", &hash));
+		assert!(!subs.contains_block("b", &hash));
+		assert!(matches!(subs.pin_block("c", hash), Err(SubscriptionError::InvalidSubscription)));
+
+		assert!(!subs.unpin_block("b", &hash));
+		assert!(subs.unpin_block("a", &hash));
+		assert!(!subs.contains_block("a", &hash));
+		assert!(!subs.unpin_block("a", &hash));
+	}
+
+	#[test]
+	fn pinned_blocks_are_limited() {
+		let subs = subscriptions(2);
+		subs.insert_subscription("sub".into(), false);
+
+		assert!(subs.pin_block("sub", H256::repeat_byte(1)).unwrap());
+		assert!(subs.pin_block("sub", H256::repeat_byte(2)).unwrap());
+		assert!(matches!(
+			subs.pin_block("sub", H256::repeat_byte(3)),
+			Err(SubscriptionError::TooManyPinnedBlocks)
+		));
+
+		subs.unpin_block("sub", &H256::repeat_byte(1));
+		assert!(subs.pin_block("sub", H256::repeat_byte(3)).unwrap());
+	}
+}
@@ -0,0 +1,230 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use assert_matches::assert_matches;
+use codec::Encode;
+use jsonrpsee::{
+	core::{server::rpc_module::Subscription, Error as RpcError},
+	types::error::CallError,
+	RpcModule,
+};
+use sc_block_builder::BlockBuilderProvider;
+use sp_consensus::BlockOrigin;
+use sp_core::{
+	storage::{StorageData, StorageKey},
+	testing::TaskExecutor,
+	Bytes, H256,
+};
+use sp_runtime::generic::BlockId;
+use std::{sync::Arc, time::Duration};
+use substrate_test_runtime_client::{prelude::*, runtime::Block};
+
+type Hash = H256;
+
+const KEY: &[u8] = b":mock";
+const VALUE: &[u8] = b"hello world";
+
+async fn next_event<T: serde::de::DeserializeOwned>(sub: &mut Subscription) -> T {
+	let (event, _) = tokio::time::timeout(Duration::from_secs(60), sub.next())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	event
+}
+
+type ChainHeadRpc = RpcModule<ChainHead<Backend, Block, TestClient>>;
+
+fn setup_api() -> (Arc<TestClient>, ChainHeadRpc) {
+	let builder = TestClientBuilder::new().add_extra_storage(KEY.to_vec(), VALUE.to_vec());
+	let backend = builder.backend();
+	let client = Arc::new(builder.build());
+	let api = ChainHead::new(client.clone(), backend, Arc::new(TaskExecutor::default())).into_rpc();
+	(client, api)
+}
+
+/// Start a follow subscription and return it together with its id.
+async fn follow(api: &ChainHeadRpc, runtime_updates: bool) -> (Subscription, String) {
+	let mut sub = api.subscribe("chainHead_follow", [runtime_updates]).await.unwrap();
+	let sub_id = match next_event::<FollowEvent<Hash>>(&mut sub).await {
+		FollowEvent::Initialized(initialized) => initialized.follow_subscription,
+		event => panic!("Unexpected event {:?}", event),
+	};
+	assert_matches!(next_event(&mut sub).await, FollowEvent::<Hash>::BestBlockChanged(_));
+	(sub, sub_id)
+}
+
+#[tokio::test]
+async fn follow_reports_blocks() {
+	let (mut client, api) = setup_api();
+	let genesis_hash = client.genesis_hash();
+
+	let mut sub = api.subscribe("chainHead_follow", [true]).await.unwrap();
+	assert_matches!(
+		next_event(&mut sub).await,
+		FollowEvent::<Hash>::Initialized(Initialized {
+			finalized_block_hash,
+			finalized_block_runtime: Some(RuntimeEvent::Valid(_)),
+			..
+		}) if finalized_block_hash == genesis_hash
+	);
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::BestBlockChanged(BestBlockChanged { best_block_hash: genesis_hash }),
+	);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let hash = block.header.hash();
+	client.import(BlockOrigin::Own, block).await.unwrap();
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::NewBlock(NewBlock {
+			block_hash: hash,
+			parent_block_hash: genesis_hash,
+			new_runtime: None,
+		}),
+	);
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::BestBlockChanged(BestBlockChanged { best_block_hash: hash }),
+	);
+
+	client.finalize_block(BlockId::Hash(hash), None).unwrap();
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::Finalized(Finalized {
+			finalized_block_hashes: vec![hash],
+			pruned_block_hashes: vec![],
+		}),
+	);
+}
+
+#[tokio::test]
+async fn follow_reports_existing_blocks() {
+	let (mut client, api) = setup_api();
+	let genesis_hash = client.genesis_hash();
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let hash = block.header.hash();
+	client.import(BlockOrigin::Own, block).await.unwrap();
+
+	let mut sub = api.subscribe("chainHead_follow", [false]).await.unwrap();
+	assert_matches!(
+		next_event(&mut sub).await,
+		FollowEvent::<Hash>::Initialized(Initialized { finalized_block_hash, .. })
+			if finalized_block_hash == genesis_hash
+	);
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::NewBlock(NewBlock {
+			block_hash: hash,
+			parent_block_hash: genesis_hash,
+			new_runtime: None,
+		}),
+	);
+	assert_eq!(
+		next_event::<FollowEvent<Hash>>(&mut sub).await,
+		FollowEvent::BestBlockChanged(BestBlockChanged { best_block_hash: hash }),
+	);
+}
+
+#[tokio::test]
+async fn query_pinned_block() {
+	let (client, api) = setup_api();
+	let genesis_hash = client.genesis_hash();
+	let (_sub, sub_id) = follow(&api, true).await;
+
+	let header: Option<Bytes> =
+		api.call("chainHead_header", (sub_id.clone(), genesis_hash)).await.unwrap();
+	let expected = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().encode();
+	assert_eq!(header, Some(expected.into()));
+
+	let mut body = api.subscribe("chainHead_body", (sub_id.clone(), genesis_hash)).await.unwrap();
+	assert_eq!(
+		next_event::<ChainHeadEvent<Vec<Bytes>>>(&mut body).await,
+		ChainHeadEvent::Done(ChainHeadResult { result: vec![] }),
+	);
+
+	let mut storage = api
+		.subscribe(
+			"chainHead_storage",
+			(sub_id.clone(), genesis_hash, StorageKey(KEY.to_vec()), None::<()>),
+		)
+		.await
+		.unwrap();
+	assert_eq!(
+		next_event::<ChainHeadEvent<Option<StorageData>>>(&mut storage).await,
+		ChainHeadEvent::Done(ChainHeadResult { result: Some(StorageData(VALUE.to_vec())) }),
+	);
+
+	let mut call = api
+		.subscribe("chainHead_call", (sub_id.clone(), genesis_hash, "Core_version", Bytes(vec![])))
+		.await
+		.unwrap();
+	assert_matches!(next_event::<ChainHeadEvent<Bytes>>(&mut call).await, ChainHeadEvent::Done(_));
+
+	// Blocks that are not pinned can not be queried.
+	let invalid = H256::repeat_byte(0x42);
+	assert_matches!(
+		api.call::<_, Option<Bytes>>("chainHead_header", (sub_id.clone(), invalid))
+			.await,
+		Err(RpcError::Call(CallError::Custom(_)))
+	);
+	assert_matches!(
+		api.subscribe("chainHead_body", (sub_id.clone(), invalid)).await,
+		Err(RpcError::Call(CallError::Custom(_)))
+	);
+
+	// Unknown follow subscriptions are reported as disjoint.
+	let mut body = api.subscribe("chainHead_body", ("unknown", genesis_hash)).await.unwrap();
+	assert_eq!(next_event::<ChainHeadEvent<Vec<Bytes>>>(&mut body).await, ChainHeadEvent::Disjoint,);
+}
+
+#[tokio::test]
+async fn call_requires_runtime_updates() {
+	let (client, api) = setup_api();
+	let genesis_hash = client.genesis_hash();
+	let (_sub, sub_id) = follow(&api, false).await;
+
+	assert_matches!(
+		api.subscribe("chainHead_call", (sub_id, genesis_hash, "Core_version", Bytes(vec![])))
+			.await,
+		Err(RpcError::Call(CallError::Custom(_)))
+	);
+}
+
+#[tokio::test]
+async fn unpin_releases_blocks() {
+	let (client, api) = setup_api();
+	let genesis_hash = client.genesis_hash();
+	let (_sub, sub_id) = follow(&api, false).await;
+
+	let _: () = api.call("chainHead_unpin", (sub_id.clone(), genesis_hash)).await.unwrap();
+	assert_matches!(
+		api.call::<_, ()>("chainHead_unpin", (sub_id.clone(), genesis_hash)).await,
+		Err(RpcError::Call(CallError::Custom(_)))
+	);
+	assert_matches!(
+		api.call::<_, Option<Bytes>>("chainHead_header", (sub_id, genesis_hash)).await,
+		Err(RpcError::Call(CallError::Custom(_)))
+	);
+
+	// Unpinning blocks of a finished subscription is a no-op.
+	let _: () = api.call("chainHead_unpin", ("unknown", genesis_hash)).await.unwrap();
+}
@@ -0,0 +1,138 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Events of the transaction API.
+
+use serde::{Deserialize, Serialize};
+
+/// The transaction was broadcasted to other nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBroadcasted {
+	/// Number of peers the transaction was broadcasted to.
+	pub num_peers: usize,
+}
+
+/// A block that includes the transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBlock<Hash> {
+	/// The hash of the block.
+	pub hash: Hash,
+	/// The index of the transaction in the body of the block, if it could be found.
+	pub index: Option<usize>,
+}
+
+/// The transaction was included in a block of the best chain, or was retracted from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBestChainBlockIncluded<Hash> {
+	/// The block of the best chain that includes the transaction, or `None` if the block was
+	/// retracted.
+	pub block: Option<TransactionBlock<Hash>>,
+}
+
+/// The transaction was included in a finalized block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionFinalized<Hash> {
+	/// The finalized block that includes the transaction.
+	pub block: TransactionBlock<Hash>,
+}
+
+/// The transaction failed or is invalid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionError {
+	/// Reason of the error.
+	pub error: String,
+}
+
+/// The transaction was dropped from the pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDropped {
+	/// The transaction was broadcasted to other nodes before it was dropped.
+	pub broadcasted: bool,
+	/// Reason of the drop.
+	pub error: String,
+}
+
+/// An event of a `transaction_submitAndWatch` subscription.
+///
+/// `Finalized`, `Error`, `Invalid` and `Dropped` are final, no event follows them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum TransactionEvent<Hash> {
+	/// The transaction was validated and is in the pool.
+	Validated,
+	/// The transaction was broadcasted to other nodes.
+	Broadcasted(TransactionBroadcasted),
+	/// The transaction was included in a block of the best chain, or was retracted from it.
+	BestChainBlockIncluded(TransactionBestChainBlockIncluded<Hash>),
+	/// The transaction was included in a finalized block.
+	Finalized(TransactionFinalized<Hash>),
+	/// The transaction could not be processed by the node.
+	Error(TransactionError),
+	/// The transaction is invalid.
+	Invalid(TransactionError),
+	/// The transaction was dropped from the pool.
+	Dropped(TransactionDropped),
+}
+
+impl<Hash> TransactionEvent<Hash> {
+	/// Whether no event can follow this one.
+	pub fn is_final(&self) -> bool {
+		matches!(self, Self::Finalized(_) | Self::Error(_) | Self::Invalid(_) | Self::Dropped(_))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn transaction_events_serialize() {
+		let event: TransactionEvent<String> = TransactionEvent::Validated;
+		assert_eq!(serde_json::to_string(&event).unwrap(), r#"{"event":"validated"}"#);
+
+		let event: TransactionEvent<String> =
+			TransactionEvent::Broadcasted(TransactionBroadcasted { num_peers: 2 });
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"broadcasted","numPeers":2}"#;
+		assert_eq!(ser, exp);
+		assert_eq!(serde_json::from_str::<TransactionEvent<String>>(exp).unwrap(), event);
+
+		let event: TransactionEvent<String> =
+			TransactionEvent::BestChainBlockIncluded(TransactionBestChainBlockIncluded {
+				block: Some(TransactionBlock { hash: "0x1".into(), index: Some(3) }),
+			});
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"bestChainBlockIncluded","block":{"hash":"0x1","index":3}}"#;
+		assert_eq!(ser, exp);
+		assert_eq!(serde_json::from_str::<TransactionEvent<String>>(exp).unwrap(), event);
+
+		let event: TransactionEvent<String> =
+			TransactionEvent::Dropped(TransactionDropped { broadcasted: true, error: "x".into() });
+		let ser = serde_json::to_string(&event).unwrap();
+		assert_eq!(ser, r#"{"event":"dropped","broadcasted":true,"error":"x"}"#);
+		assert!(event.is_final());
+		assert!(!TransactionEvent::<String>::Validated.is_final());
+	}
+}
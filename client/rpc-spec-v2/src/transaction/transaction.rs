@@ -0,0 +1,215 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! API implementation for submitting transactions.
+
+use crate::{
+	transaction::{
+		api::TransactionApiServer,
+		event::{
+			TransactionBestChainBlockIncluded, TransactionBlock, TransactionBroadcasted,
+			TransactionDropped, TransactionError, TransactionEvent, TransactionFinalized,
+		},
+	},
+	SubscriptionTaskExecutor,
+};
+use codec::{Decode, Encode};
+use futures::{future, FutureExt, StreamExt};
+use jsonrpsee::PendingSubscription;
+use sc_client_api::BlockBackend;
+use sc_transaction_pool_api::{
+	error::IntoPoolError, BlockHash, TransactionFor, TransactionPool, TransactionSource,
+	TransactionStatus,
+};
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::sync::Arc;
+
+/// An API for transaction RPC calls.
+pub struct Transaction<Pool, Client> {
+	/// Substrate client.
+	client: Arc<Client>,
+	/// Transactions pool.
+	pool: Arc<Pool>,
+	/// Executor to spawn subscriptions.
+	executor: SubscriptionTaskExecutor,
+}
+
+impl<Pool, Client> Transaction<Pool, Client> {
+	/// Create a new instance of the transaction API.
+	pub fn new(client: Arc<Client>, pool: Arc<Pool>, executor: SubscriptionTaskExecutor) -> Self {
+		Transaction { client, pool, executor }
+	}
+}
+
+/// Currently we treat all RPC transactions as externals.
+///
+/// Possibly in the future we could allow opt-in for special treatment
+/// of such transactions, so that the block authors can inject
+/// some unique transactions via RPC and have them included in the pool.
+const TX_SOURCE: TransactionSource = TransactionSource::External;
+
+/// Converts the statuses of a transaction in the pool into transaction events.
+struct TransactionWatcher<Block: BlockT, Client> {
+	client: Arc<Client>,
+	/// The encoded transaction, to find it in the body of the blocks.
+	xt: Bytes,
+	/// The transaction was broadcasted to at least one peer.
+	broadcasted: bool,
+	_phantom: std::marker::PhantomData<Block>,
+}
+
+impl<Block, Client> TransactionWatcher<Block, Client>
+where
+	Block: BlockT,
+	Client: BlockBackend<Block>,
+{
+	fn block(&self, hash: Block::Hash) -> TransactionBlock<Block::Hash> {
+		let index = self.client.block_body(&BlockId::Hash(hash)).ok().flatten().and_then(|body| {
+			body.iter().position(|extrinsic| extrinsic.encode()[..] == self.xt[..])
+		});
+		TransactionBlock { hash, index }
+	}
+
+	fn handle<Hash>(
+		&mut self,
+		status: TransactionStatus<Hash, Block::Hash>,
+	) -> Option<TransactionEvent<Block::Hash>> {
+		Some(match status {
+			TransactionStatus::Future | TransactionStatus::Ready => TransactionEvent::Validated,
+			// Only the status at the best block is reported.
+			TransactionStatus::ReadyAtLeaf(_) | TransactionStatus::InvalidAtLeaf(_) => return None,
+			TransactionStatus::Broadcast(peers) => {
+				self.broadcasted |= !peers.is_empty();
+				TransactionEvent::Broadcasted(TransactionBroadcasted { num_peers: peers.len() })
+			},
+			TransactionStatus::InBlock(hash) =>
+				TransactionEvent::BestChainBlockIncluded(TransactionBestChainBlockIncluded {
+					block: Some(self.block(hash)),
+				}),
+			TransactionStatus::InBlockWithResult { block, result } =>
+				TransactionEvent::BestChainBlockIncluded(TransactionBestChainBlockIncluded {
+					block: Some(TransactionBlock {
+						hash: block,
+						index: Some(result.inclusion.index as usize),
+					}),
+				}),
+			TransactionStatus::Retracted(_) =>
+				TransactionEvent::BestChainBlockIncluded(TransactionBestChainBlockIncluded {
+					block: None,
+				}),
+			TransactionStatus::Finalized(hash) =>
+				TransactionEvent::Finalized(TransactionFinalized { block: self.block(hash) }),
+			TransactionStatus::FinalizedWithResult { block, result } =>
+				TransactionEvent::Finalized(TransactionFinalized {
+					block: TransactionBlock {
+						hash: block,
+						index: Some(result.inclusion.index as usize),
+					},
+				}),
+			TransactionStatus::FinalityTimeout(_) =>
+				TransactionEvent::Dropped(TransactionDropped {
+					broadcasted: self.broadcasted,
+					error: "Maximum number of finality watchers has been exceeded".into(),
+				}),
+			TransactionStatus::Usurped(_) => TransactionEvent::Invalid(TransactionError {
+				error: "Extrinsic was rendered invalid by another extrinsic".into(),
+			}),
+			TransactionStatus::Dropped => TransactionEvent::Dropped(TransactionDropped {
+				broadcasted: self.broadcasted,
+				error: "Extrinsic dropped from the pool due to exceeding limits".into(),
+			}),
+			TransactionStatus::SenderLimitExceeded =>
+				TransactionEvent::Dropped(TransactionDropped {
+					broadcasted: self.broadcasted,
+					error: "Extrinsic dropped from the pool as its sender has too many extrinsics"
+						.into(),
+				}),
+			TransactionStatus::SourceQuotaExceeded =>
+				TransactionEvent::Dropped(TransactionDropped {
+					broadcasted: self.broadcasted,
+					error:
+						"Extrinsic dropped from the pool due to exceeding the quota of its source"
+							.into(),
+				}),
+			TransactionStatus::Invalid => TransactionEvent::Invalid(TransactionError {
+				error: "Extrinsic marked as invalid".into(),
+			}),
+		})
+	}
+}
+
+impl<Pool, Client> TransactionApiServer<BlockHash<Pool>> for Transaction<Pool, Client>
+where
+	Pool: TransactionPool + Sync + Send + 'static,
+	Pool::Hash: Unpin,
+	<Pool::Block as BlockT>::Hash: Unpin,
+	Client: HeaderBackend<Pool::Block> + BlockBackend<Pool::Block> + Send + Sync + 'static,
+{
+	fn submit_and_watch(&self, pending: PendingSubscription, xt: Bytes) {
+		let best_block_hash = self.client.info().best_hash;
+		let client = self.client.clone();
+		let pool = self.pool.clone();
+
+		let fut = async move {
+			let mut sink = match pending.accept() {
+				Some(sink) => sink,
+				_ => return,
+			};
+
+			let decoded = match TransactionFor::<Pool>::decode(&mut &xt[..]) {
+				Ok(decoded) => decoded,
+				Err(e) => {
+					let _ = sink.send(&TransactionEvent::<BlockHash<Pool>>::Invalid(
+						TransactionError {
+							error: format!("Extrinsic bytes cannot be decoded: {}", e),
+						},
+					));
+					return
+				},
+			};
+
+			let stream = match pool
+				.submit_and_watch(&BlockId::hash(best_block_hash), TX_SOURCE, decoded)
+				.await
+			{
+				Ok(stream) => stream,
+				Err(e) => {
+					let event = match e.into_pool_error() {
+						Ok(e) => TransactionEvent::<BlockHash<Pool>>::Invalid(TransactionError {
+							error: e.to_string(),
+						}),
+						Err(e) =>
+							TransactionEvent::Error(TransactionError { error: e.to_string() }),
+					};
+					let _ = sink.send(&event);
+					return
+				},
+			};
+
+			let mut watcher =
+				TransactionWatcher { client, xt, broadcasted: false, _phantom: Default::default() };
+			// The pool ends the stream after the final status of the transaction.
+			let events = stream.filter_map(move |status| future::ready(watcher.handle(status)));
+			sink.pipe_from_stream(events.boxed()).await;
+		};
+
+		self.executor.spawn("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+	}
+}
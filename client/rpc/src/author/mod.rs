@@ -107,6 +107,44 @@ where
 			})
 	}
 
+	async fn replace_extrinsic(&self, hash: TxHash<P>, ext: Bytes) -> RpcResult<TxHash<P>> {
+		let xt = match Decode::decode(&mut &ext[..]) {
+			Ok(xt) => xt,
+			Err(err) => return Err(Error::Client(Box::new(err)).into()),
+		};
+		let best_block_hash = self.client.info().best_hash;
+		self.pool
+			.replace(&generic::BlockId::hash(best_block_hash), TX_SOURCE, hash, xt)
+			.await
+			.map_err(|e| {
+				e.into_pool_error()
+					.map(|e| Error::Pool(e))
+					.unwrap_or_else(|e| Error::Verification(Box::new(e)))
+					.into()
+			})
+	}
+
+	async fn submit_bundle(&self, exts: Vec<Bytes>) -> RpcResult<Vec<TxHash<P>>> {
+		let xts = match exts
+			.iter()
+			.map(|ext| Decode::decode(&mut &ext[..]))
+			.collect::<std::result::Result<Vec<_>, _>>()
+		{
+			Ok(xts) => xts,
+			Err(err) => return Err(Error::Client(Box::new(err)).into()),
+		};
+		let best_block_hash = self.client.info().best_hash;
+		self.pool
+			.submit_bundle(&generic::BlockId::hash(best_block_hash), TX_SOURCE, xts)
+			.await
+			.map_err(|e| {
+				e.into_pool_error()
+					.map(|e| Error::Pool(e))
+					.unwrap_or_else(|e| Error::Verification(Box::new(e)))
+					.into()
+			})
+	}
+
 	fn insert_key(&self, key_type: String, suri: String, public: Bytes) -> RpcResult<()> {
 		self.deny_unsafe.check_if_safe()?;
 
@@ -214,4 +252,69 @@ where
 
 		self.executor.spawn("substrate-rpc-subscription", Some("rpc"), fut.boxed());
 	}
+
+	fn replace_and_watch_extrinsic(
+		&self,
+		pending: PendingSubscription,
+		hash: TxHash<P>,
+		xt: Bytes,
+	) {
+		let best_block_hash = self.client.info().best_hash;
+		let dxt = match TransactionFor::<P>::decode(&mut &xt[..]).map_err(|e| Error::from(e)) {
+			Ok(dxt) => dxt,
+			Err(e) => {
+				pending.reject(JsonRpseeError::from(e));
+				return
+			},
+		};
+
+		let replace = self
+			.pool
+			.replace_and_watch(&generic::BlockId::hash(best_block_hash), TX_SOURCE, hash, dxt)
+			.map_err(|e| {
+				e.into_pool_error()
+					.map(error::Error::from)
+					.unwrap_or_else(|e| error::Error::Verification(Box::new(e)))
+			});
+
+		let fut = async move {
+			let stream = match replace.await {
+				Ok(stream) => stream,
+				Err(err) => {
+					pending.reject(JsonRpseeError::from(err));
+					return
+				},
+			};
+
+			let mut sink = match pending.accept() {
+				Some(sink) => sink,
+				_ => return,
+			};
+
+			sink.pipe_from_stream(stream).await;
+		};
+
+		self.executor.spawn("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+	}
+
+	fn watch_extrinsic_by_hash(&self, pending: PendingSubscription, hash: TxHash<P>) {
+		let stream = match self.pool.watch(&hash) {
+			Some(stream) => stream,
+			None => {
+				pending.reject(JsonRpseeError::from(Error::UnknownExtrinsic));
+				return
+			},
+		};
+
+		let fut = async move {
+			let mut sink = match pending.accept() {
+				Some(sink) => sink,
+				_ => return,
+			};
+
+			sink.pipe_from_stream(stream).await;
+		};
+
+		self.executor.spawn("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+	}
 }
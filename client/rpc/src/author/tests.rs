@@ -148,6 +148,156 @@ async fn author_should_watch_extrinsic() {
 	assert_eq!(&sub_id, sub.subscription_id());
 }
 
+#[tokio::test]
+async fn author_should_replace_extrinsic() {
+	let api = TestSetup::into_rpc();
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+	let xt_hash: H256 = blake2_256(&xt).into();
+
+	let mut sub = api
+		.subscribe("author_submitAndWatchExtrinsic", [to_hex(&xt, true)])
+		.await
+		.unwrap();
+	let (tx, _) = timeout_secs(10, sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_matches!(tx, TransactionStatus::Ready);
+
+	let replacement = |amount| {
+		let tx = Transfer {
+			amount,
+			nonce: 0,
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+		};
+		tx.into_signed_tx().encode()
+	};
+
+	// The replacement does not increase the priority.
+	assert_matches!(
+		api.call::<_, H256>("author_replaceExtrinsic", (xt_hash, to_hex(&replacement(0), true))).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.code() == 1014
+	);
+
+	let new_xt = replacement(5);
+	let new_hash: H256 = blake2_256(&new_xt).into();
+	let response: H256 = api
+		.call("author_replaceExtrinsic", (xt_hash, to_hex(&new_xt, true)))
+		.await
+		.unwrap();
+	assert_eq!(response, new_hash);
+
+	let (tx, _) = timeout_secs(10, sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_eq!(tx, TransactionStatus::Usurped(new_hash));
+
+	// The replaced extrinsic is gone.
+	assert_matches!(
+		api.call::<_, H256>("author_replaceExtrinsic", (xt_hash, to_hex(&replacement(10), true))).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.code() == 1022
+	);
+}
+
+#[tokio::test]
+async fn author_should_replace_and_watch_extrinsic() {
+	let api = TestSetup::into_rpc();
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+	let xt_hash: H256 = blake2_256(&xt).into();
+
+	let mut sub = api
+		.subscribe("author_submitAndWatchExtrinsic", [to_hex(&xt, true)])
+		.await
+		.unwrap();
+	let (tx, _) = timeout_secs(10, sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_matches!(tx, TransactionStatus::Ready);
+
+	let new_xt = Transfer {
+		amount: 5,
+		nonce: 0,
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Bob.into(),
+	}
+	.into_signed_tx()
+	.encode();
+	let new_hash: H256 = blake2_256(&new_xt).into();
+	let mut new_sub = api
+		.subscribe("author_replaceAndWatchExtrinsic", (xt_hash, to_hex(&new_xt, true)))
+		.await
+		.unwrap();
+
+	// Both subscriptions are notified about the replacement.
+	let (tx, _) = timeout_secs(10, sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_eq!(tx, TransactionStatus::Usurped(new_hash));
+	let (tx, _) = timeout_secs(10, new_sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_matches!(tx, TransactionStatus::Ready);
+}
+
+#[tokio::test]
+async fn author_should_submit_bundle() {
+	let api = TestSetup::into_rpc();
+	let xts = vec![uxt(AccountKeyring::Alice, 0).encode(), uxt(AccountKeyring::Bob, 0).encode()];
+	let xt_hashes = xts.iter().map(|xt| blake2_256(xt).into()).collect::<Vec<H256>>();
+
+	let hashes: Vec<H256> = api
+		.call("author_submitBundle", [xts.iter().map(|xt| to_hex(xt, true)).collect::<Vec<_>>()])
+		.await
+		.unwrap();
+	assert_eq!(hashes, xt_hashes);
+
+	let pending: Vec<Bytes> =
+		api.call("author_pendingExtrinsics", EmptyParams::new()).await.unwrap();
+	assert_eq!(pending.len(), 2);
+
+	assert_matches!(
+		api.call::<_, Vec<H256>>("author_submitBundle", [Vec::<String>::new()]).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.code() == 1024
+	);
+}
+
+#[tokio::test]
+async fn author_should_watch_extrinsic_by_hash() {
+	let api = TestSetup::into_rpc();
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+	let xt_hash: H256 = blake2_256(&xt).into();
+
+	let failed_sub = api.subscribe("author_watchExtrinsicByHash", [xt_hash]).await;
+	assert_matches!(
+		failed_sub,
+		Err(RpcError::Call(CallError::Failed(err))) if err.to_string() == "Extrinsic is not in the pool"
+	);
+
+	let _ = api
+		.call::<_, H256>("author_submitExtrinsic", [to_hex(&xt, true)])
+		.await
+		.unwrap();
+
+	let mut sub = api.subscribe("author_watchExtrinsicByHash", [xt_hash]).await.unwrap();
+	let (tx, sub_id) = timeout_secs(10, sub.next::<TransactionStatus<H256, Block>>())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	assert_matches!(tx, TransactionStatus::Ready);
+	assert_eq!(&sub_id, sub.subscription_id());
+}
+
 #[tokio::test]
 async fn author_should_return_watch_validation_error() {
 	const METHOD: &'static str = "author_submitAndWatchExtrinsic";
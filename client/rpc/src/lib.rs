@@ -29,7 +29,7 @@ pub use jsonrpsee::core::{
 	},
 	traits::IdProvider as RpcSubscriptionIdProvider,
 };
-pub use sc_rpc_api::DenyUnsafe;
+pub use sc_rpc_api::{DenyUnsafe, RateLimit, RateLimitConfig, RATE_LIMITED_METHODS};
 
 pub mod author;
 pub mod chain;
@@ -37,6 +37,7 @@ pub mod dev;
 pub mod offchain;
 pub mod state;
 pub mod system;
+pub mod txpool;
 
 #[cfg(any(test, feature = "test-helpers"))]
 pub mod testing;
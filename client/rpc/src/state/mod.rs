@@ -32,7 +32,7 @@ use jsonrpsee::{
 	ws_server::PendingSubscription,
 };
 
-use sc_rpc_api::{state::ReadProof, DenyUnsafe};
+use sc_rpc_api::{state::ReadProof, DenyUnsafe, RateLimit};
 use sp_core::{
 	storage::{PrefixedStorageKey, StorageChangeSet, StorageData, StorageKey},
 	Bytes,
@@ -52,6 +52,9 @@ use sp_blockchain::{HeaderBackend, HeaderMetadata};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+/// Maximum number of blocks a single `state_getReadProofRange` call may prove.
+const READ_PROOF_RANGE_MAX_BLOCKS: u32 = 256;
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 where
@@ -145,6 +148,23 @@ where
 		keys: Vec<StorageKey>,
 	) -> Result<ReadProof<Block::Hash>, Error>;
 
+	/// Returns a single proof of storage entries of the main trie and of child tries at a
+	/// specific block's state.
+	fn read_proof_with_children(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+	) -> Result<ReadProof<Block::Hash>, Error>;
+
+	/// Returns a single proof of the same storage entries at the state of every given block.
+	fn read_proof_range(
+		&self,
+		blocks: Vec<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+	) -> Result<ReadProofRange<Block::Hash>, Error>;
+
 	/// Trace storage changes for block
 	fn trace_block(
 		&self,
@@ -154,6 +174,13 @@ where
 		methods: Option<String>,
 	) -> Result<sp_rpc::tracing::TraceBlockResponse, Error>;
 
+	/// Re-execute the block and return the storage changes it made.
+	fn trace_block_diff(
+		&self,
+		block: Block::Hash,
+		prefixes: Option<Vec<StorageKey>>,
+	) -> Result<BlockStorageDiff<Block::Hash>, Error>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(&self, sink: PendingSubscription);
 
@@ -166,6 +193,7 @@ pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	executor: SubscriptionTaskExecutor,
 	deny_unsafe: DenyUnsafe,
+	rate_limit: RateLimit,
 	rpc_max_payload: Option<usize>,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 where
@@ -192,7 +220,10 @@ where
 		rpc_max_payload,
 	));
 	let backend = Box::new(self::state_full::FullState::new(client, executor, rpc_max_payload));
-	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
+	(
+		State { backend, deny_unsafe, rate_limit: rate_limit.clone() },
+		ChildState { backend: child_backend, rate_limit },
+	)
 }
 
 /// State API with subscriptions support.
@@ -200,6 +231,8 @@ pub struct State<Block, Client> {
 	backend: Box<dyn StateBackend<Block, Client>>,
 	/// Whether to deny unsafe calls
 	deny_unsafe: DenyUnsafe,
+	/// Rate limits of the calls
+	rate_limit: RateLimit,
 }
 
 impl<Block, Client> StateApiServer<Block::Hash> for State<Block, Client>
@@ -208,6 +241,7 @@ where
 	Client: Send + Sync + 'static,
 {
 	fn call(&self, method: String, data: Bytes, block: Option<Block::Hash>) -> RpcResult<Bytes> {
+		self.rate_limit.check_expensive("state_call")?;
 		self.backend.call(block, method, data).map_err(Into::into)
 	}
 
@@ -216,6 +250,7 @@ where
 		key_prefix: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageKey>> {
+		self.rate_limit.check_expensive("state_getKeys")?;
 		self.backend.storage_keys(block, key_prefix).map_err(Into::into)
 	}
 
@@ -225,6 +260,7 @@ where
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<(StorageKey, StorageData)>> {
 		self.deny_unsafe.check_if_safe()?;
+		self.rate_limit.check_expensive("state_getPairs")?;
 		self.backend.storage_pairs(block, key_prefix).map_err(Into::into)
 	}
 
@@ -235,6 +271,7 @@ where
 		start_key: Option<StorageKey>,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageKey>> {
+		self.rate_limit.check_expensive("state_getKeysPaged")?;
 		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
 			return Err(JsonRpseeError::from(Error::InvalidCount {
 				value: count,
@@ -251,6 +288,7 @@ where
 		key: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Option<StorageData>> {
+		self.rate_limit.check("state_getStorage")?;
 		self.backend.storage(block, key).map_err(Into::into)
 	}
 
@@ -259,18 +297,22 @@ where
 		key: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Option<Block::Hash>> {
+		self.rate_limit.check("state_getStorageHash")?;
 		self.backend.storage_hash(block, key).map_err(Into::into)
 	}
 
 	fn storage_size(&self, key: StorageKey, block: Option<Block::Hash>) -> RpcResult<Option<u64>> {
+		self.rate_limit.check("state_getStorageSize")?;
 		self.backend.storage_size(block, key).map_err(Into::into)
 	}
 
 	fn metadata(&self, block: Option<Block::Hash>) -> RpcResult<Bytes> {
+		self.rate_limit.check("state_getMetadata")?;
 		self.backend.metadata(block).map_err(Into::into)
 	}
 
 	fn runtime_version(&self, at: Option<Block::Hash>) -> RpcResult<RuntimeVersion> {
+		self.rate_limit.check("state_getRuntimeVersion")?;
 		self.backend.runtime_version(at).map_err(Into::into)
 	}
 
@@ -281,6 +323,7 @@ where
 		to: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageChangeSet<Block::Hash>>> {
 		self.deny_unsafe.check_if_safe()?;
+		self.rate_limit.check_expensive("state_queryStorage")?;
 		self.backend.query_storage(from, to, keys).map_err(Into::into)
 	}
 
@@ -289,6 +332,7 @@ where
 		keys: Vec<StorageKey>,
 		at: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageChangeSet<Block::Hash>>> {
+		self.rate_limit.check_expensive("state_queryStorageAt")?;
 		self.backend.query_storage_at(keys, at).map_err(Into::into)
 	}
 
@@ -297,9 +341,40 @@ where
 		keys: Vec<StorageKey>,
 		block: Option<Block::Hash>,
 	) -> RpcResult<ReadProof<Block::Hash>> {
+		self.rate_limit.check_expensive("state_getReadProof")?;
 		self.backend.read_proof(block, keys).map_err(Into::into)
 	}
 
+	fn read_proof_with_children(
+		&self,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+		block: Option<Block::Hash>,
+	) -> RpcResult<ReadProof<Block::Hash>> {
+		self.rate_limit.check_expensive("state_getReadProofWithChildren")?;
+		self.backend
+			.read_proof_with_children(block, keys, child_keys)
+			.map_err(Into::into)
+	}
+
+	fn read_proof_range(
+		&self,
+		keys: Vec<StorageKey>,
+		child_keys: Option<Vec<ChildReadProofKeys>>,
+		blocks: Vec<Block::Hash>,
+	) -> RpcResult<ReadProofRange<Block::Hash>> {
+		self.rate_limit.check_expensive("state_getReadProofRange")?;
+		if blocks.len() > READ_PROOF_RANGE_MAX_BLOCKS as usize {
+			return Err(JsonRpseeError::from(Error::InvalidCount {
+				value: blocks.len() as u32,
+				max: READ_PROOF_RANGE_MAX_BLOCKS,
+			}))
+		}
+		self.backend
+			.read_proof_range(blocks, keys, child_keys.unwrap_or_default())
+			.map_err(Into::into)
+	}
+
 	/// Re-execute the given block with the tracing targets given in `targets`
 	/// and capture all state changes.
 	///
@@ -313,11 +388,25 @@ where
 		methods: Option<String>,
 	) -> RpcResult<sp_rpc::tracing::TraceBlockResponse> {
 		self.deny_unsafe.check_if_safe()?;
+		self.rate_limit.check_expensive("state_traceBlock")?;
 		self.backend
 			.trace_block(block, targets, storage_keys, methods)
 			.map_err(Into::into)
 	}
 
+	/// Re-execute the given block and return all the storage changes it made.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	fn trace_block_diff(
+		&self,
+		block: Block::Hash,
+		prefixes: Option<Vec<StorageKey>>,
+	) -> RpcResult<BlockStorageDiff<Block::Hash>> {
+		self.deny_unsafe.check_if_safe()?;
+		self.rate_limit.check_expensive("state_traceBlockDiff")?;
+		self.backend.trace_block_diff(block, prefixes).map_err(Into::into)
+	}
+
 	fn subscribe_runtime_version(&self, sink: PendingSubscription) {
 		self.backend.subscribe_runtime_version(sink)
 	}
@@ -405,6 +494,8 @@ where
 /// Child state API with subscriptions support.
 pub struct ChildState<Block, Client> {
 	backend: Box<dyn ChildStateBackend<Block, Client>>,
+	/// Rate limits of the calls
+	rate_limit: RateLimit,
 }
 
 impl<Block, Client> ChildStateApiServer<Block::Hash> for ChildState<Block, Client>
@@ -418,6 +509,7 @@ where
 		key_prefix: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageKey>> {
+		self.rate_limit.check_expensive("childstate_getKeys")?;
 		self.backend.storage_keys(block, storage_key, key_prefix).map_err(Into::into)
 	}
 
@@ -429,6 +521,7 @@ where
 		start_key: Option<StorageKey>,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<StorageKey>> {
+		self.rate_limit.check_expensive("childstate_getKeysPaged")?;
 		self.backend
 			.storage_keys_paged(block, storage_key, prefix, count, start_key)
 			.map_err(Into::into)
@@ -440,6 +533,7 @@ where
 		key: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Option<StorageData>> {
+		self.rate_limit.check("childstate_getStorage")?;
 		self.backend.storage(block, storage_key, key).map_err(Into::into)
 	}
 
@@ -449,6 +543,7 @@ where
 		keys: Vec<StorageKey>,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Vec<Option<StorageData>>> {
+		self.rate_limit.check_expensive("childstate_getStorageEntries")?;
 		self.backend.storage_entries(block, storage_key, keys).map_err(Into::into)
 	}
 
@@ -458,6 +553,7 @@ where
 		key: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Option<Block::Hash>> {
+		self.rate_limit.check("childstate_getStorageHash")?;
 		self.backend.storage_hash(block, storage_key, key).map_err(Into::into)
 	}
 
@@ -467,6 +563,7 @@ where
 		key: StorageKey,
 		block: Option<Block::Hash>,
 	) -> RpcResult<Option<u64>> {
+		self.rate_limit.check("childstate_getStorageSize")?;
 		self.backend.storage_size(block, storage_key, key).map_err(Into::into)
 	}
 
@@ -476,6 +573,7 @@ where
 		keys: Vec<StorageKey>,
 		block: Option<Block::Hash>,
 	) -> RpcResult<ReadProof<Block::Hash>> {
+		self.rate_limit.check_expensive("childstate_getChildReadProof")?;
 		self.backend
 			.read_child_proof(block, child_storage_key, keys)
 			.map_err(Into::into)
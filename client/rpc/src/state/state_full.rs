@@ -18,7 +18,7 @@
 
 //! State API backend for full nodes.
 
-use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData, sync::Arc};
 
 use super::{
 	client_err,
@@ -31,10 +31,12 @@ use futures::{future, stream, FutureExt, StreamExt};
 use jsonrpsee::{core::Error as JsonRpseeError, PendingSubscription};
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider, ProofProvider,
-	StorageProvider,
+	StorageProof, StorageProvider,
 };
-use sc_rpc_api::state::ReadProof;
-use sp_api::{CallApiAt, Metadata, ProvideRuntimeApi};
+use sc_rpc_api::state::{
+	BlockStorageDiff, ChildReadProofKeys, ChildStorageDiff, ReadProof, ReadProofRange, StorageDiff,
+};
+use sp_api::{CallApiAt, CallApiAtParams, Metadata, OverlayedChanges, ProvideRuntimeApi};
 use sp_blockchain::{
 	CachedHeaderMetadata, Error as ClientError, HeaderBackend, HeaderMetadata,
 	Result as ClientResult,
@@ -43,9 +45,12 @@ use sp_core::{
 	storage::{
 		ChildInfo, ChildType, PrefixedStorageKey, StorageChangeSet, StorageData, StorageKey,
 	},
-	Bytes,
+	Bytes, ExecutionContext,
+};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT},
 };
-use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 use sp_version::RuntimeVersion;
 
 /// Ranges to query in state_queryStorage.
@@ -357,6 +362,50 @@ where
 			.map_err(client_err)
 	}
 
+	fn read_proof_with_children(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+	) -> std::result::Result<ReadProof<Block::Hash>, Error> {
+		self.block_or_best(block)
+			.and_then(|block| {
+				let child_keys = child_read_proof_keys(child_keys)?;
+				self.client
+					.read_proof_with_children(
+						&BlockId::Hash(block),
+						&mut keys.iter().map(|key| key.0.as_ref()),
+						&child_keys,
+					)
+					.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
+					.map(|proof| ReadProof { at: block, proof })
+			})
+			.map_err(client_err)
+	}
+
+	fn read_proof_range(
+		&self,
+		blocks: Vec<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
+	) -> std::result::Result<ReadProofRange<Block::Hash>, Error> {
+		let child_keys = child_read_proof_keys(child_keys).map_err(client_err)?;
+		let proofs = blocks
+			.iter()
+			.map(|block| {
+				self.client.read_proof_with_children(
+					&BlockId::Hash(*block),
+					&mut keys.iter().map(|key| key.0.as_ref()),
+					&child_keys,
+				)
+			})
+			.collect::<ClientResult<Vec<_>>>()
+			.map_err(client_err)?;
+
+		let proof = StorageProof::merge(proofs).iter_nodes().map(|node| node.into()).collect();
+		Ok(ReadProofRange { at: blocks, proof })
+	}
+
 	fn subscribe_runtime_version(&self, pending: PendingSubscription) {
 		let client = self.client.clone();
 
@@ -468,6 +517,95 @@ where
 		.trace_block()
 		.map_err(|e| invalid_block::<Block>(block, None, e.to_string()))
 	}
+
+	fn trace_block_diff(
+		&self,
+		block: Block::Hash,
+		prefixes: Option<Vec<StorageKey>>,
+	) -> std::result::Result<BlockStorageDiff<Block::Hash>, Error> {
+		let id = BlockId::Hash(block);
+		let missing =
+			|what: &str| invalid_block::<Block>(block, None, format!("{} not found", what));
+		let mut header =
+			self.client.header(id).map_err(client_err)?.ok_or_else(|| missing("Header"))?;
+		let extrinsics = self
+			.client
+			.block_body(&id)
+			.map_err(client_err)?
+			.ok_or_else(|| missing("Body"))?;
+		let parent_hash = *header.parent_hash();
+		let parent_id = BlockId::Hash(parent_hash);
+		// Remove all `Seal`s as they are added by the consensus engines after building the block.
+		header.digest_mut().logs.retain(|d| d.as_seal().is_none());
+
+		let overlay = RefCell::new(OverlayedChanges::default());
+		self.client
+			.call_api_at(CallApiAtParams {
+				at: &parent_id,
+				function: "Core_execute_block",
+				native_call: None::<fn() -> std::result::Result<(), sp_api::ApiError>>,
+				arguments: Block::new(header, extrinsics).encode(),
+				overlayed_changes: &overlay,
+				storage_transaction_cache: &Default::default(),
+				context: ExecutionContext::OffchainCall(None),
+				recorder: &None,
+			})
+			.map_err(|e| Error::Client(Box::new(e)))?;
+		let overlay = overlay.into_inner();
+
+		let prefixes = prefixes.unwrap_or_default();
+		let matches =
+			|key: &[u8]| prefixes.is_empty() || prefixes.iter().any(|p| key.starts_with(&p.0));
+		// Pairs the keys written by the block with their old values. Writes that leave the value
+		// as it was, e.g. a `mutate` that doesn't change anything, are not changes.
+		let diff =
+			|changes: Vec<(StorageKey, Option<StorageData>)>,
+			 old_value: &dyn Fn(&StorageKey) -> ClientResult<Option<StorageData>>| {
+				changes
+					.into_iter()
+					.filter_map(|(key, new_value)| match old_value(&key) {
+						Ok(old_value) if old_value == new_value => None,
+						Ok(old_value) => Some(Ok(StorageDiff { old_value, new_value, key })),
+						Err(e) => Some(Err(e)),
+					})
+					.collect::<ClientResult<Vec<_>>>()
+			};
+
+		let top = diff(
+			overlay
+				.changes()
+				.filter(|(key, _)| matches(key))
+				.map(|(key, value)| {
+					(StorageKey(key.clone()), value.value().cloned().map(StorageData))
+				})
+				.collect(),
+			&|key| self.client.storage(&parent_id, key),
+		)
+		.map_err(client_err)?;
+
+		let mut children = overlay
+			.children()
+			.filter(|(_, child_info)| matches(child_info.prefixed_storage_key().as_slice()))
+			.map(|(changes, child_info)| {
+				Ok(ChildStorageDiff {
+					child_storage_key: child_info.prefixed_storage_key(),
+					changes: diff(
+						changes
+							.map(|(key, value)| {
+								(StorageKey(key.clone()), value.value().cloned().map(StorageData))
+							})
+							.collect(),
+						&|key| self.client.child_storage(&parent_id, child_info, key),
+					)?,
+				})
+			})
+			.filter(|child| child.as_ref().map_or(true, |child| !child.changes.is_empty()))
+			.collect::<ClientResult<Vec<_>>>()
+			.map_err(client_err)?;
+		children.sort_by(|a, b| a.child_storage_key.cmp(&b.child_storage_key));
+
+		Ok(BlockStorageDiff { block, parent_hash, top, children })
+	}
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client>
@@ -633,3 +771,19 @@ fn invalid_block_range<B: BlockT>(
 fn invalid_block<B: BlockT>(from: B::Hash, to: Option<B::Hash>, details: String) -> Error {
 	Error::InvalidBlockRange { from: format!("{:?}", from), to: format!("{:?}", to), details }
 }
+
+/// Resolves the child tries of the keys passed to the read proof RPCs.
+fn child_read_proof_keys(
+	child_keys: Vec<ChildReadProofKeys>,
+) -> ClientResult<Vec<(ChildInfo, Vec<Vec<u8>>)>> {
+	child_keys
+		.into_iter()
+		.map(|ChildReadProofKeys { child_storage_key, keys }| {
+			let child_info = match ChildType::from_prefixed_key(&child_storage_key) {
+				Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
+				None => return Err(ClientError::InvalidChildStorageKey),
+			};
+			Ok((child_info, keys.into_iter().map(|key| key.0).collect()))
+		})
+		.collect()
+}
@@ -26,11 +26,11 @@ use jsonrpsee::{
 	types::{error::CallError as RpcCallError, EmptyParams, ErrorObject},
 };
 use sc_block_builder::BlockBuilderProvider;
-use sc_rpc_api::DenyUnsafe;
+use sc_rpc_api::{DenyUnsafe, RateLimit, RateLimitConfig};
 use sp_consensus::BlockOrigin;
 use sp_core::{hash::H256, storage::ChildInfo};
 use sp_io::hashing::blake2_256;
-use std::sync::Arc;
+use std::{num::NonZeroU32, sync::Arc};
 use substrate_test_runtime_client::{prelude::*, runtime};
 
 const STORAGE_KEY: &[u8] = b"child";
@@ -55,7 +55,8 @@ async fn should_return_storage() {
 		.add_extra_storage(b":map:acc2".to_vec(), vec![1, 2, 3])
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let (client, child) = new_full(Arc::new(client), test_executor(), DenyUnsafe::No, None);
+	let (client, child) =
+		new_full(Arc::new(client), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 	let key = StorageKey(KEY.to_vec());
 
 	assert_eq!(
@@ -100,7 +101,8 @@ async fn should_return_storage_entries() {
 		.add_extra_child_storage(&child_info, KEY2.to_vec(), CHILD_VALUE2.to_vec())
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(Arc::new(client), test_executor(), DenyUnsafe::No, None);
+	let (_client, child) =
+		new_full(Arc::new(client), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 	let keys = &[StorageKey(KEY1.to_vec()), StorageKey(KEY2.to_vec())];
 	assert_eq!(
@@ -131,7 +133,8 @@ async fn should_return_child_storage() {
 			.build(),
 	);
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(client, test_executor(), DenyUnsafe::No, None);
+	let (_client, child) =
+		new_full(client, test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
 
@@ -162,7 +165,8 @@ async fn should_return_child_storage_entries() {
 			.build(),
 	);
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(client, test_executor(), DenyUnsafe::No, None);
+	let (_client, child) =
+		new_full(client, test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 	let child_key = prefixed_storage_key();
 	let keys = vec![StorageKey(b"key1".to_vec()), StorageKey(b"key2".to_vec())];
 
@@ -193,7 +197,8 @@ async fn should_return_child_storage_entries() {
 async fn should_call_contract() {
 	let client = Arc::new(substrate_test_runtime_client::new());
 	let genesis_hash = client.genesis_hash();
-	let (client, _child) = new_full(client, test_executor(), DenyUnsafe::No, None);
+	let (client, _child) =
+		new_full(client, test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 	use jsonrpsee::{core::Error, types::error::CallError};
 
@@ -207,7 +212,8 @@ async fn should_call_contract() {
 async fn should_notify_about_storage_changes() {
 	let mut sub = {
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No, None);
+		let (api, _child) =
+			new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 		let api_rpc = api.into_rpc();
 		let sub = api_rpc.subscribe("state_subscribeStorage", EmptyParams::new()).await.unwrap();
@@ -239,7 +245,8 @@ async fn should_notify_about_storage_changes() {
 async fn should_send_initial_storage_changes_and_notifications() {
 	let mut sub = {
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No, None);
+		let (api, _child) =
+			new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 		let alice_balance_key =
 			blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
@@ -275,7 +282,8 @@ async fn should_send_initial_storage_changes_and_notifications() {
 #[tokio::test]
 async fn should_query_storage() {
 	async fn run_tests(mut client: Arc<TestClient>) {
-		let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No, None);
+		let (api, _child) =
+			new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 		let mut add_block = |nonce| {
 			let mut builder = client.new_block(Default::default()).unwrap();
@@ -477,7 +485,8 @@ async fn should_query_storage() {
 #[tokio::test]
 async fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No, None);
+	let (api, _child) =
+		new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":2,\"implVersion\":2,\"apis\":[[\"0xdf6acb689907609b\",4],\
@@ -498,7 +507,8 @@ async fn should_return_runtime_version() {
 async fn should_notify_on_runtime_version_initially() {
 	let mut sub = {
 		let client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client, test_executor(), DenyUnsafe::No, None);
+		let (api, _child) =
+			new_full(client, test_executor(), DenyUnsafe::No, RateLimit::default(), None);
 
 		let api_rpc = api.into_rpc();
 		let sub = api_rpc
@@ -527,7 +537,8 @@ fn should_deserialize_storage_key() {
 #[tokio::test]
 async fn wildcard_storage_subscriptions_are_rpc_unsafe() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client, test_executor(), DenyUnsafe::Yes, None);
+	let (api, _child) =
+		new_full(client, test_executor(), DenyUnsafe::Yes, RateLimit::default(), None);
 
 	let api_rpc = api.into_rpc();
 	let err = api_rpc.subscribe("state_subscribeStorage", EmptyParams::new()).await;
@@ -537,7 +548,8 @@ async fn wildcard_storage_subscriptions_are_rpc_unsafe() {
 #[tokio::test]
 async fn concrete_storage_subscriptions_are_rpc_safe() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client, test_executor(), DenyUnsafe::Yes, None);
+	let (api, _child) =
+		new_full(client, test_executor(), DenyUnsafe::Yes, RateLimit::default(), None);
 	let api_rpc = api.into_rpc();
 
 	let key = StorageKey(STORAGE_KEY.to_vec());
@@ -545,3 +557,162 @@ async fn concrete_storage_subscriptions_are_rpc_safe() {
 
 	assert!(sub.is_ok());
 }
+
+#[tokio::test]
+async fn should_trace_block_diff() {
+	const KEY: &[u8] = b"trace:key";
+
+	let mut client = Arc::new(
+		TestClientBuilder::new()
+			.add_extra_storage(KEY.to_vec(), vec![1])
+			.add_extra_storage(b"trace:same".to_vec(), vec![4])
+			.build(),
+	);
+	let (api, _child) =
+		new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(KEY.to_vec(), Some(vec![2])).unwrap();
+	builder.push_storage_change(b"trace:new".to_vec(), Some(vec![3])).unwrap();
+	builder.push_storage_change(b"trace:same".to_vec(), Some(vec![4])).unwrap();
+	let block = builder.build().unwrap().block;
+	let hash = block.header.hash();
+	client.import(BlockOrigin::Own, block).await.unwrap();
+
+	let diff = api.trace_block_diff(hash, Some(vec![StorageKey(b"trace:".to_vec())])).unwrap();
+	assert_eq!(diff.block, hash);
+	assert_eq!(diff.parent_hash, client.genesis_hash());
+	// `trace:same` was written with the value it already had, it is not a change.
+	assert_eq!(
+		diff.top,
+		vec![
+			StorageDiff {
+				key: StorageKey(KEY.to_vec()),
+				old_value: Some(StorageData(vec![1])),
+				new_value: Some(StorageData(vec![2])),
+			},
+			StorageDiff {
+				key: StorageKey(b"trace:new".to_vec()),
+				old_value: None,
+				new_value: Some(StorageData(vec![3])),
+			},
+		],
+	);
+	assert!(diff.children.is_empty());
+
+	// Without prefixes the changes done by the runtime itself are returned as well.
+	let diff = api.trace_block_diff(hash, None).unwrap();
+	assert!(diff.top.len() > 2);
+
+	assert_matches!(
+		api.trace_block_diff(H256::repeat_byte(0x42), None),
+		Err(RpcError::Call(RpcCallError::Custom(_)))
+	);
+}
+
+#[tokio::test]
+async fn trace_block_diff_is_rpc_unsafe() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) =
+		new_full(client, test_executor(), DenyUnsafe::Yes, RateLimit::default(), None);
+
+	assert_matches!(
+		api.trace_block_diff(genesis_hash, None),
+		Err(RpcError::Call(RpcCallError::Custom(e))) if e.message() == "RPC call is unsafe to be called externally"
+	);
+}
+
+#[tokio::test]
+async fn storage_calls_are_rate_limited() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let rate_limit = RateLimit::new(
+		RateLimitConfig {
+			method_limit: NonZeroU32::new(100),
+			expensive_limit: NonZeroU32::new(1),
+			..Default::default()
+		},
+		None,
+	)
+	.unwrap();
+	let (api, _child) = new_full(client, test_executor(), DenyUnsafe::No, rate_limit, None);
+	let api = api.into_rpc();
+
+	let key = StorageKey(STORAGE_KEY.to_vec());
+	let _: Vec<StorageKey> = api.call("state_getKeysPaged", (key.clone(), 10)).await.unwrap();
+	let err = api
+		.call::<_, Vec<StorageKey>>("state_queryStorageAt", [vec![key.clone()]])
+		.await;
+	assert_matches!(
+		err,
+		Err(RpcError::Call(RpcCallError::Custom(e)))
+			if e.message() == "Rate limit of RPC call `state_queryStorageAt` exceeded, try again later"
+	);
+
+	// Cheap calls are only subject to their own limit.
+	let _: Option<StorageData> = api.call("state_getStorage", [key]).await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_combined_read_proofs() {
+	const KEY: &[u8] = b":mock";
+
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let mut client = Arc::new(
+		TestClientBuilder::new()
+			.add_extra_storage(KEY.to_vec(), vec![1])
+			.add_extra_child_storage(&child_info, KEY.to_vec(), vec![2])
+			.build(),
+	);
+	let genesis_hash = client.genesis_hash();
+	let (api, child) =
+		new_full(client.clone(), test_executor(), DenyUnsafe::No, RateLimit::default(), None);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(KEY.to_vec(), Some(vec![3])).unwrap();
+	let block = builder.build().unwrap().block;
+	let hash = block.header.hash();
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	let key = StorageKey(KEY.to_vec());
+	let child_keys = vec![ChildReadProofKeys {
+		child_storage_key: prefixed_storage_key(),
+		keys: vec![key.clone()],
+	}];
+
+	// The combined proof is the union of the separate proofs, without duplicated nodes.
+	let top = api.read_proof(vec![key.clone()], Some(genesis_hash)).unwrap().proof;
+	let child_proof = child
+		.read_child_proof(prefixed_storage_key(), vec![key.clone()], Some(genesis_hash))
+		.unwrap();
+	let combined = api
+		.read_proof_with_children(vec![key.clone()], child_keys.clone(), Some(genesis_hash))
+		.unwrap();
+	assert_eq!(combined.at, genesis_hash);
+	let mut expected = top.iter().chain(child_proof.proof.iter()).cloned().collect::<Vec<_>>();
+	expected.sort();
+	expected.dedup();
+	let mut proof = combined.proof.clone();
+	proof.sort();
+	assert_eq!(proof, expected);
+	assert!(proof.len() < top.len() + child_proof.proof.len());
+
+	// The range proof covers both blocks, the unchanged child trie is only included once.
+	let range = api
+		.read_proof_range(vec![key.clone()], Some(child_keys.clone()), vec![genesis_hash, hash])
+		.unwrap();
+	assert_eq!(range.at, vec![genesis_hash, hash]);
+	let at_block = api.read_proof_with_children(vec![key.clone()], child_keys.clone(), Some(hash));
+	for node in combined.proof.iter().chain(at_block.unwrap().proof.iter()) {
+		assert!(range.proof.contains(node));
+	}
+
+	assert_matches!(
+		api.read_proof_range(vec![key.clone()], None, vec![H256::repeat_byte(0x42)]),
+		Err(RpcError::Call(RpcCallError::Custom(_)))
+	);
+	assert_matches!(
+		api.read_proof_range(vec![key], None, vec![genesis_hash; 257]),
+		Err(RpcError::Call(RpcCallError::Custom(e))) if e.message().contains("count exceeds maximum value")
+	);
+}
@@ -124,6 +124,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> RpcModule<System<Block>> {
 						starting_block: 1,
 						current_block: 2,
 						highest_block: Some(3),
+						gap_sync: None,
 					});
 				},
 			};
@@ -299,7 +300,7 @@ async fn system_sync_state() {
 		api(None).call("system_syncState", EmptyParams::new()).await.unwrap();
 	assert_eq!(
 		sync_state,
-		SyncState { starting_block: 1, current_block: 2, highest_block: Some(3) }
+		SyncState { starting_block: 1, current_block: 2, highest_block: Some(3), gap_sync: None }
 	);
 }
 
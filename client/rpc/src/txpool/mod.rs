@@ -0,0 +1,60 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Implementation of the transaction pool inspection API on top of a [`TransactionPool`].
+
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+
+use jsonrpsee::core::{async_trait, RpcResult};
+pub use sc_rpc_api::txpool::*;
+use sc_rpc_api::DenyUnsafe;
+use sc_transaction_pool_api::{PoolInspection, PoolStatus, TransactionPool, TxHash};
+
+/// Transaction pool API
+pub struct TxPool<P> {
+	/// Transactions pool
+	pool: Arc<P>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<P> TxPool<P> {
+	/// Create new instance of transaction pool API.
+	pub fn new(pool: Arc<P>, deny_unsafe: DenyUnsafe) -> Self {
+		TxPool { pool, deny_unsafe }
+	}
+}
+
+#[async_trait]
+impl<P> TxPoolApiServer<TxHash<P>> for TxPool<P>
+where
+	P: TransactionPool + Sync + Send + 'static,
+{
+	fn status(&self) -> RpcResult<PoolStatus> {
+		Ok(self.pool.status())
+	}
+
+	fn inspect(&self) -> RpcResult<PoolInspection<TxHash<P>>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		Ok(self.pool.inspect())
+	}
+}
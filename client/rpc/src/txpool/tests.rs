@@ -0,0 +1,105 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+use super::*;
+
+use assert_matches::assert_matches;
+use futures::executor::block_on;
+use jsonrpsee::{
+	core::Error as RpcError,
+	types::{error::CallError, EmptyParams},
+};
+use sc_transaction_pool::{BasicPool, FullChainApi};
+use sc_transaction_pool_api::TransactionSource;
+use sp_core::H256;
+use sp_runtime::generic::BlockId;
+use substrate_test_runtime_client::{
+	runtime::{Block, Extrinsic, Transfer},
+	AccountKeyring, Backend, Client, DefaultTestClientBuilderExt, TestClientBuilderExt,
+};
+
+type FullTransactionPool = BasicPool<FullChainApi<Client<Backend>, Block>, Block>;
+
+fn uxt(sender: AccountKeyring, nonce: u64) -> Extrinsic {
+	let tx = Transfer {
+		amount: Default::default(),
+		nonce,
+		from: sender.into(),
+		to: AccountKeyring::Bob.into(),
+	};
+	tx.into_signed_tx()
+}
+
+fn pool() -> Arc<FullTransactionPool> {
+	let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new().build());
+	let spawner = sp_core::testing::TaskExecutor::new();
+	BasicPool::new_full(Default::default(), true.into(), None, spawner, client)
+}
+
+fn submit(pool: &FullTransactionPool, xt: Extrinsic) -> H256 {
+	block_on(pool.submit_one(&BlockId::number(0), TransactionSource::External, xt)).unwrap()
+}
+
+#[tokio::test]
+async fn txpool_should_return_status() {
+	let pool = pool();
+	submit(&pool, uxt(AccountKeyring::Alice, 0));
+	submit(&pool, uxt(AccountKeyring::Alice, 2));
+	let api = TxPool::new(pool, DenyUnsafe::No).into_rpc();
+
+	let status: PoolStatus = api.call("txpool_status", EmptyParams::new()).await.unwrap();
+	assert_eq!(status.ready, 1);
+	assert_eq!(status.future, 1);
+}
+
+#[tokio::test]
+async fn txpool_should_inspect_transactions() {
+	let pool = pool();
+	let alice0 = submit(&pool, uxt(AccountKeyring::Alice, 0));
+	let alice1 = submit(&pool, uxt(AccountKeyring::Alice, 1));
+	let bob0 = submit(&pool, uxt(AccountKeyring::Bob, 0));
+	let api = TxPool::new(pool, DenyUnsafe::No).into_rpc();
+
+	let inspection: PoolInspection<H256> =
+		api.call("txpool_inspect", EmptyParams::new()).await.unwrap();
+	assert_eq!(inspection.ready.len(), 3);
+	assert!(inspection.future.is_empty());
+
+	let alice1 = inspection.ready.iter().find(|tx| tx.hash == alice1).unwrap();
+	assert_eq!(alice1.unlocked_by, vec![alice0]);
+	assert_eq!(alice1.chain, alice0);
+	assert_eq!(alice1.source, TransactionSource::External);
+
+	let mut chains = inspection.chains.iter().map(|c| (c.first, c.ready)).collect::<Vec<_>>();
+	chains.sort();
+	let mut expected = vec![(alice0, 2), (bob0, 1)];
+	expected.sort();
+	assert_eq!(chains, expected);
+}
+
+#[test]
+fn txpool_inspect_considered_unsafe() {
+	let api = TxPool::new(pool(), DenyUnsafe::Yes);
+
+	assert_matches!(api.status(), Ok(_));
+	assert_matches!(
+		api.inspect(),
+		Err(RpcError::Call(CallError::Custom(err))) => {
+			assert_eq!(err.message(), "RPC call is unsafe to be called externally")
+		}
+	);
+}
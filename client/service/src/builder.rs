@@ -33,7 +33,7 @@ use sc_client_api::{
 	execution_extensions::ExecutionExtensions, proof_provider::ProofProvider, BadBlocks,
 	BlockBackend, BlockchainEvents, ExecutorProvider, ForkBlocks, StorageProvider, UsageProvider,
 };
-use sc_client_db::{Backend, DatabaseSettings};
+use sc_client_db::{Backend, BlockTimestamp, DatabaseSettings};
 use sc_consensus::import_queue::ImportQueue;
 use sc_executor::RuntimeVersionOf;
 use sc_keystore::LocalKeystore;
@@ -51,8 +51,10 @@ use sc_rpc::{
 	offchain::OffchainApiServer,
 	state::{ChildStateApiServer, StateApiServer},
 	system::SystemApiServer,
-	DenyUnsafe, SubscriptionTaskExecutor,
+	txpool::TxPoolApiServer,
+	DenyUnsafe, RateLimit, SubscriptionTaskExecutor,
 };
+use sc_rpc_spec_v2::{chain_head::ChainHeadApiServer, transaction::TransactionApiServer};
 use sc_telemetry::{telemetry, ConnectionMessage, Telemetry, TelemetryHandle, SUBSTRATE_INFO};
 use sc_transaction_pool_api::MaintainedTransactionPool;
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
@@ -177,11 +179,32 @@ where
 }
 
 /// Create the initial parts of a full node.
+///
+/// Fails if the state is pruned by age, use [`new_full_parts_with_block_timestamp`] for chains
+/// that can tell when their blocks were authored.
 pub fn new_full_parts<TBl, TRtApi, TExec>(
 	config: &Configuration,
 	telemetry: Option<TelemetryHandle>,
 	executor: TExec,
 ) -> Result<TFullParts<TBl, TRtApi, TExec>, Error>
+where
+	TBl: BlockT,
+	TExec: CodeExecutor + RuntimeVersionOf + Clone,
+{
+	new_full_parts_with_block_timestamp(config, telemetry, executor, None)
+}
+
+/// Create the initial parts of a full node, whose database determines the time a block was
+/// authored at with `block_timestamp`.
+///
+/// The time is needed to prune the state by age. Fails if the state is pruned by age and no
+/// `block_timestamp` is given.
+pub fn new_full_parts_with_block_timestamp<TBl, TRtApi, TExec>(
+	config: &Configuration,
+	telemetry: Option<TelemetryHandle>,
+	executor: TExec,
+	block_timestamp: Option<BlockTimestamp<TBl>>,
+) -> Result<TFullParts<TBl, TRtApi, TExec>, Error>
 where
 	TBl: BlockT,
 	TExec: CodeExecutor + RuntimeVersionOf + Clone,
@@ -204,14 +227,24 @@ where
 
 	let (client, backend) = {
 		let db_config = sc_client_db::DatabaseSettings {
-			state_cache_size: config.state_cache_size,
-			state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+			trie_cache_maximum_size: config.trie_cache_maximum_size,
 			state_pruning: config.state_pruning.clone(),
 			source: config.database.clone(),
 			keep_blocks: config.keep_blocks,
+			keep_justifications: config.keep_justifications,
 		};
 
 		let backend = new_db_backend(db_config)?;
+		match block_timestamp {
+			Some(block_timestamp) => backend.set_block_timestamp(block_timestamp),
+			None if backend.requires_block_timestamp() =>
+				return Err(Error::Other(
+					"The state is pruned by age, but this node can't tell when blocks were \
+					 authored. Prune the state by a number of blocks instead."
+						.into(),
+				)),
+			None => {},
+		}
 
 		let extensions = sc_client_api::execution_extensions::ExecutionExtensions::new(
 			config.execution_strategies.clone(),
@@ -483,22 +516,24 @@ where
 	let rpc_id_provider = config.rpc_id_provider.take();
 
 	// jsonrpsee RPC
-	let gen_rpc_module = |deny_unsafe: DenyUnsafe| {
+	let gen_rpc_module = |deny_unsafe: DenyUnsafe, rate_limit: RateLimit| {
 		gen_rpc_module(
 			deny_unsafe,
+			rate_limit,
 			task_manager.spawn_handle(),
 			client.clone(),
 			transaction_pool.clone(),
 			keystore.clone(),
 			system_rpc_tx.clone(),
 			&config,
-			backend.offchain_storage(),
+			backend.clone(),
 			&*rpc_builder,
 		)
 	};
 
 	let rpc = start_rpc_servers(&config, gen_rpc_module, rpc_id_provider)?;
-	let rpc_handlers = RpcHandlers(Arc::new(gen_rpc_module(sc_rpc::DenyUnsafe::No)?.into()));
+	let rpc_handlers =
+		RpcHandlers(Arc::new(gen_rpc_module(sc_rpc::DenyUnsafe::No, RateLimit::default())?.into()));
 
 	// Spawn informant task
 	spawn_handle.spawn(
@@ -578,13 +613,14 @@ fn init_telemetry<TBl: BlockT, TCl: BlockBackend<TBl>>(
 
 fn gen_rpc_module<TBl, TBackend, TCl, TRpc, TExPool>(
 	deny_unsafe: DenyUnsafe,
+	rate_limit: RateLimit,
 	spawn_handle: SpawnTaskHandle,
 	client: Arc<TCl>,
 	transaction_pool: Arc<TExPool>,
 	keystore: SyncCryptoStorePtr,
 	system_rpc_tx: TracingUnboundedSender<sc_rpc::system::Request<TBl>>,
 	config: &Configuration,
-	offchain_storage: Option<<TBackend as sc_client_api::backend::Backend<TBl>>::OffchainStorage>,
+	backend: Arc<TBackend>,
 	rpc_builder: &(dyn Fn(DenyUnsafe, SubscriptionTaskExecutor) -> Result<RpcModule<TRpc>, Error>),
 ) -> Result<RpcModule<()>, Error>
 where
@@ -624,6 +660,7 @@ where
 			client.clone(),
 			task_executor.clone(),
 			deny_unsafe,
+			rate_limit,
 			config.rpc_max_payload,
 		);
 		let state = state.into_rpc();
@@ -632,6 +669,22 @@ where
 		(chain, state, child_state)
 	};
 
+	let chain_head = sc_rpc_spec_v2::chain_head::ChainHead::new(
+		client.clone(),
+		backend.clone(),
+		task_executor.clone(),
+	)
+	.into_rpc();
+
+	let transaction_v2 = sc_rpc_spec_v2::transaction::Transaction::new(
+		client.clone(),
+		transaction_pool.clone(),
+		task_executor.clone(),
+	)
+	.into_rpc();
+
+	let txpool = sc_rpc::txpool::TxPool::new(transaction_pool.clone(), deny_unsafe).into_rpc();
+
 	let author = sc_rpc::author::Author::new(
 		client.clone(),
 		transaction_pool,
@@ -643,14 +696,17 @@ where
 
 	let system = sc_rpc::system::System::new(system_info, system_rpc_tx, deny_unsafe).into_rpc();
 
-	if let Some(storage) = offchain_storage {
+	if let Some(storage) = backend.offchain_storage() {
 		let offchain = sc_rpc::offchain::Offchain::new(storage, deny_unsafe).into_rpc();
 
 		rpc_api.merge(offchain).map_err(|e| Error::Application(e.into()))?;
 	}
 
 	rpc_api.merge(chain).map_err(|e| Error::Application(e.into()))?;
+	rpc_api.merge(chain_head).map_err(|e| Error::Application(e.into()))?;
+	rpc_api.merge(transaction_v2).map_err(|e| Error::Application(e.into()))?;
 	rpc_api.merge(author).map_err(|e| Error::Application(e.into()))?;
+	rpc_api.merge(txpool).map_err(|e| Error::Application(e.into()))?;
 	rpc_api.merge(system).map_err(|e| Error::Application(e.into()))?;
 	rpc_api.merge(state).map_err(|e| Error::Application(e.into()))?;
 	rpc_api.merge(child_state).map_err(|e| Error::Application(e.into()))?;
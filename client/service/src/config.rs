@@ -19,7 +19,7 @@
 //! Service configuration.
 
 pub use sc_client_api::execution_extensions::{ExecutionStrategies, ExecutionStrategy};
-pub use sc_client_db::{Database, DatabaseSource, KeepBlocks, PruningMode};
+pub use sc_client_db::{Database, DatabaseSource, KeepBlocks, KeepJustifications, PruningMode};
 pub use sc_executor::WasmExecutionMethod;
 #[cfg(feature = "wasmtime")]
 pub use sc_executor::WasmtimeInstantiationStrategy;
@@ -70,16 +70,20 @@ pub struct Configuration {
 	pub keystore_remote: Option<String>,
 	/// Configuration for the database.
 	pub database: DatabaseSource,
-	/// Size of internal state cache in Bytes
-	pub state_cache_size: usize,
-	/// Size in percent of cache size dedicated to child tries
-	pub state_cache_child_ratio: Option<usize>,
+	/// Maximum size of the internal trie cache in bytes.
+	///
+	/// If `None` is given the cache is disabled.
+	pub trie_cache_maximum_size: Option<usize>,
 	/// State pruning settings.
 	pub state_pruning: Option<PruningMode>,
 	/// Number of blocks to keep in the db.
 	///
 	/// NOTE: only finalized blocks are subject for removal!
 	pub keep_blocks: KeepBlocks,
+	/// Number of justifications to keep in the db.
+	///
+	/// NOTE: only finalized blocks are subject for removal!
+	pub keep_justifications: KeepJustifications,
 	/// Chain configuration.
 	pub chain_spec: Box<dyn ChainSpec>,
 	/// Wasm execution method.
@@ -116,6 +120,14 @@ pub struct Configuration {
 	///
 	/// Default: 1024.
 	pub rpc_max_subs_per_conn: Option<usize>,
+	/// Rate limits of the RPC calls.
+	///
+	/// Default: no limits.
+	pub rpc_rate_limit: crate::RpcRateLimit,
+	/// Rate limits of the RPC requests of every IP address and connection.
+	///
+	/// Default: no limits.
+	pub rpc_peer_rate_limit: crate::RpcPeerRateLimit,
 	/// Maximum size of the output buffer capacity for websocket connections.
 	pub ws_max_out_buffer_capacity: Option<usize>,
 	/// Prometheus endpoint configuration. `None` if disabled.
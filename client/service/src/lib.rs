@@ -53,14 +53,16 @@ use sp_runtime::{
 pub use self::{
 	builder::{
 		build_network, build_offchain_workers, new_client, new_db_backend, new_full_client,
-		new_full_parts, spawn_tasks, BuildNetworkParams, KeystoreContainer, NetworkStarter,
-		SpawnTasksParams, TFullBackend, TFullCallExecutor, TFullClient,
+		new_full_parts, new_full_parts_with_block_timestamp, spawn_tasks, BuildNetworkParams,
+		KeystoreContainer, NetworkStarter, SpawnTasksParams, TFullBackend, TFullCallExecutor,
+		TFullClient,
 	},
 	client::{ClientConfig, LocalCallExecutor},
 	error::Error,
 };
 pub use config::{
-	BasePath, Configuration, DatabaseSource, KeepBlocks, PruningMode, Role, RpcMethods, TaskType,
+	BasePath, Configuration, DatabaseSource, KeepBlocks, KeepJustifications, PruningMode, Role,
+	RpcMethods, TaskType,
 };
 pub use sc_chain_spec::{
 	ChainSpec, ChainType, Extension as ChainSpecExtension, GenericChainSpec, NoExtension,
@@ -72,8 +74,10 @@ pub use sc_executor::NativeExecutionDispatch;
 #[doc(hidden)]
 pub use sc_network::config::{TransactionImport, TransactionImportFuture};
 pub use sc_rpc::{
-	RandomIntegerSubscriptionId, RandomStringSubscriptionId, RpcSubscriptionIdProvider,
+	RandomIntegerSubscriptionId, RandomStringSubscriptionId, RateLimitConfig as RpcRateLimit,
+	RpcSubscriptionIdProvider, RATE_LIMITED_METHODS as RPC_RATE_LIMITED_METHODS,
 };
+pub use sc_rpc_server::PeerRateLimitConfig as RpcPeerRateLimit;
 pub use sc_tracing::TracingReceiver;
 pub use sc_transaction_pool::Options as TransactionPoolOptions;
 pub use sc_transaction_pool_api::{error::IntoPoolError, InPoolTransaction, TransactionPool};
@@ -260,12 +264,19 @@ async fn build_network_future<
 						let _ = sender.send(vec![node_role]);
 					}
 					sc_rpc::system::Request::SyncState(sender) => {
-						use sc_rpc::system::SyncState;
+						use sc_rpc::system::{GapSyncState, SyncState};
 
+						let gap_sync = network.sync_state().gap_sync.map(|gap_sync| GapSyncState {
+							starting_block: gap_sync.start,
+							target_block: gap_sync.target,
+							lowest_header: gap_sync.lowest_header,
+							current_block: gap_sync.best_queued_number,
+						});
 						let _ = sender.send(SyncState {
 							starting_block,
 							current_block: client.info().best_number,
 							highest_block: network.best_seen_block(),
+							gap_sync,
 						});
 					}
 				}
@@ -311,7 +322,7 @@ fn start_rpc_servers<R>(
 	rpc_id_provider: Option<Box<dyn RpcSubscriptionIdProvider>>,
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error>
 where
-	R: Fn(sc_rpc::DenyUnsafe) -> Result<RpcModule<()>, Error>,
+	R: Fn(sc_rpc::DenyUnsafe, sc_rpc::RateLimit) -> Result<RpcModule<()>, Error>,
 {
 	let (max_request_size, ws_max_response_size, http_max_response_size) =
 		legacy_cli_parsing(config);
@@ -339,6 +350,13 @@ where
 	let http_addr2 = random_port(http_addr);
 
 	let metrics = sc_rpc_server::RpcMetrics::new(config.prometheus_registry())?;
+	// Both servers share the same budgets.
+	let rate_limit =
+		sc_rpc::RateLimit::new(config.rpc_rate_limit.clone(), config.prometheus_registry())?;
+	let peer_rate_limit = sc_rpc_server::PeerRateLimit::new(
+		config.rpc_peer_rate_limit,
+		config.prometheus_registry(),
+	)?;
 
 	let http_fut = sc_rpc_server::start_http(
 		[http_addr, http_addr2],
@@ -346,7 +364,8 @@ where
 		max_request_size,
 		http_max_response_size,
 		metrics.clone(),
-		gen_rpc_module(deny_unsafe(ws_addr, &config.rpc_methods))?,
+		peer_rate_limit.clone(),
+		gen_rpc_module(deny_unsafe(ws_addr, &config.rpc_methods), rate_limit.clone())?,
 		config.tokio_handle.clone(),
 	);
 
@@ -362,7 +381,8 @@ where
 		config.rpc_cors.as_ref(),
 		ws_config,
 		metrics,
-		gen_rpc_module(deny_unsafe(http_addr, &config.rpc_methods))?,
+		peer_rate_limit,
+		gen_rpc_module(deny_unsafe(http_addr, &config.rpc_methods), rate_limit)?,
 		config.tokio_handle.clone(),
 		rpc_id_provider,
 	);
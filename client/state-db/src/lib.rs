@@ -46,22 +46,27 @@ mod pruning;
 #[cfg(test)]
 mod test;
 
-use codec::Codec;
+use codec::{Codec, Decode, Encode};
 use log::trace;
 use noncanonical::NonCanonicalOverlay;
 use parity_util_mem::{malloc_size, MallocSizeOf};
 use parking_lot::RwLock;
+pub use pruning::PruningWindowBuilder;
 use pruning::RefWindow;
 use sc_client_api::{MemorySize, StateDbMemoryInfo};
 use std::{
 	collections::{hash_map::Entry, HashMap},
 	fmt,
+	time::Duration,
 };
 
 const PRUNING_MODE: &[u8] = b"mode";
+const PRUNING_MODE_LIMIT: &[u8] = b"mode_limit";
 const PRUNING_MODE_ARCHIVE: &[u8] = b"archive";
 const PRUNING_MODE_ARCHIVE_CANON: &[u8] = b"archive_canonical";
 const PRUNING_MODE_CONSTRAINED: &[u8] = b"constrained";
+const PRUNING_MODE_CONSTRAINED_AGE: &[u8] = b"constrained_age";
+const PRUNING_MODE_CONSTRAINED_INSERTED_BYTES: &[u8] = b"constrained_inserted_bytes";
 
 /// Database value type.
 pub type DBValue = Vec<u8>;
@@ -223,6 +228,23 @@ pub enum PruningMode {
 	ArchiveAll,
 	/// Canonicalization discards non-canonical nodes. All the canonical nodes are kept in the DB.
 	ArchiveCanonical,
+	/// Maintain a pruning window that keeps the states of the blocks whose timestamp is at most
+	/// `max_age` older than the timestamp of the last canonicalized block.
+	ConstrainedAge {
+		/// Maximum age of the kept states.
+		max_age: Duration,
+	},
+	/// Maintain a pruning window that keeps as many recent states as possible, as long as the
+	/// trie nodes inserted by their blocks add up to at most `max_inserted_bytes` bytes.
+	///
+	/// The encoded keys and values of the inserted nodes are counted as they are written, this
+	/// does not bound the space the states take on disk: nodes shared with older states are
+	/// counted again, removed keys and the overhead of the database are not counted, and the
+	/// database may compress the nodes.
+	ConstrainedInsertedBytes {
+		/// Maximum number of bytes of the trie nodes inserted by the blocks in the window.
+		max_inserted_bytes: u64,
+	},
 }
 
 impl PruningMode {
@@ -231,20 +253,73 @@ impl PruningMode {
 		PruningMode::Constrained(Constraints { max_blocks: Some(n), max_mem: None })
 	}
 
+	/// Create a mode that keeps the states of the blocks that are at most `max_age` old.
+	pub fn keep_age(max_age: Duration) -> PruningMode {
+		PruningMode::ConstrainedAge { max_age }
+	}
+
+	/// Create a mode that keeps recent states as long as the trie nodes inserted by their blocks
+	/// add up to at most `max_inserted_bytes` bytes.
+	pub fn keep_inserted_bytes(max_inserted_bytes: u64) -> PruningMode {
+		PruningMode::ConstrainedInsertedBytes { max_inserted_bytes }
+	}
+
 	/// Is this an archive (either ArchiveAll or ArchiveCanonical) pruning mode?
 	pub fn is_archive(&self) -> bool {
 		match *self {
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => true,
-			PruningMode::Constrained(_) => false,
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => false,
 		}
 	}
 
+	/// Does this mode require the timestamps of the canonicalized blocks?
+	pub fn requires_timestamps(&self) -> bool {
+		matches!(self, PruningMode::ConstrainedAge { .. })
+	}
+
 	/// Returns the pruning mode
 	pub fn id(&self) -> &[u8] {
 		match self {
 			PruningMode::ArchiveAll => PRUNING_MODE_ARCHIVE,
 			PruningMode::ArchiveCanonical => PRUNING_MODE_ARCHIVE_CANON,
 			PruningMode::Constrained(_) => PRUNING_MODE_CONSTRAINED,
+			PruningMode::ConstrainedAge { .. } => PRUNING_MODE_CONSTRAINED_AGE,
+			PruningMode::ConstrainedInsertedBytes { .. } => PRUNING_MODE_CONSTRAINED_INSERTED_BYTES,
+		}
+	}
+
+	/// Returns the limit of the pruning window that is stored next to the mode, if any.
+	fn limit(&self) -> Option<u64> {
+		match self {
+			PruningMode::ConstrainedAge { max_age } => Some(max_age.as_millis() as u64),
+			PruningMode::ConstrainedInsertedBytes { max_inserted_bytes } =>
+				Some(*max_inserted_bytes),
+			PruningMode::ArchiveAll |
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) => None,
+		}
+	}
+
+	/// Does a pruning window with the given number of blocks, memory usage, inserted bytes and
+	/// time span exceed the limits of this mode?
+	pub(crate) fn window_exceeded(
+		&self,
+		blocks: u64,
+		mem_used: usize,
+		inserted_bytes: u64,
+		time_span: Option<u64>,
+	) -> bool {
+		match self {
+			PruningMode::Constrained(constraints) =>
+				blocks > constraints.max_blocks.unwrap_or(0) as u64 &&
+					!constraints.max_mem.map_or(false, |m| mem_used > m),
+			PruningMode::ConstrainedAge { max_age } =>
+				time_span.map_or(false, |span| span > max_age.as_millis() as u64),
+			PruningMode::ConstrainedInsertedBytes { max_inserted_bytes } =>
+				inserted_bytes > *max_inserted_bytes,
+			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => false,
 		}
 	}
 
@@ -276,6 +351,19 @@ fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	buffer
 }
 
+/// Add the meta-data that stores `mode` to `commit`.
+fn store_pruning_mode<Key: Hash>(mode: &PruningMode, commit: &mut CommitSet<Key>) {
+	commit
+		.meta
+		.inserted
+		.push((to_meta_key(PRUNING_MODE, &()), mode.id().to_owned()));
+	let key = to_meta_key(PRUNING_MODE_LIMIT, &());
+	match mode.limit() {
+		Some(limit) => commit.meta.inserted.push((key, limit.encode())),
+		None => commit.meta.deleted.push(key),
+	}
+}
+
 struct StateDbSync<BlockHash: Hash, Key: Hash> {
 	mode: PruningMode,
 	non_canonical: NonCanonicalOverlay<BlockHash, Key>,
@@ -294,7 +382,9 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 		let non_canonical: NonCanonicalOverlay<BlockHash, Key> = NonCanonicalOverlay::new(db)?;
 		let pruning: Option<RefWindow<BlockHash, Key>> = match mode {
 			PruningMode::Constrained(Constraints { max_mem: Some(_), .. }) => unimplemented!(),
-			PruningMode::Constrained(_) => Some(RefWindow::new(db, ref_counting)?),
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => Some(RefWindow::new(db, ref_counting)?),
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => None,
 		};
 
@@ -314,7 +404,10 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 				// write changes immediately
 				Ok(CommitSet { data: changeset, meta: Default::default() })
 			},
-			PruningMode::Constrained(_) | PruningMode::ArchiveCanonical => self
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } |
+			PruningMode::ArchiveCanonical => self
 				.non_canonical
 				.insert(hash, number, parent_hash, changeset)
 				.map_err(Into::into),
@@ -324,6 +417,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 	fn canonicalize_block<E: fmt::Debug>(
 		&mut self,
 		hash: &BlockHash,
+		timestamp: Option<u64>,
 	) -> Result<CommitSet<Key>, Error<E>> {
 		let mut commit = CommitSet::default();
 		if self.mode == PruningMode::ArchiveAll {
@@ -337,7 +431,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 			Err(e) => return Err(e.into()),
 		};
 		if let Some(ref mut pruning) = self.pruning {
-			pruning.note_canonical(hash, &mut commit);
+			pruning.note_canonical(hash, timestamp, &mut commit);
 		}
 		self.prune(&mut commit);
 		Ok(commit)
@@ -350,7 +444,10 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 	fn is_pruned(&self, hash: &BlockHash, number: u64) -> bool {
 		match self.mode {
 			PruningMode::ArchiveAll => false,
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => {
 				if self.best_canonical().map(|c| number > c).unwrap_or(true) {
 					!self.non_canonical.have_block(hash)
 				} else {
@@ -363,15 +460,15 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 	}
 
 	fn prune(&mut self, commit: &mut CommitSet<Key>) {
-		if let (&mut Some(ref mut pruning), &PruningMode::Constrained(ref constraints)) =
-			(&mut self.pruning, &self.mode)
-		{
+		if let Some(ref mut pruning) = self.pruning {
 			loop {
-				if pruning.window_size() <= constraints.max_blocks.unwrap_or(0) as u64 {
-					break
-				}
-
-				if constraints.max_mem.map_or(false, |m| pruning.mem_used() > m) {
+				let exceeded = self.mode.window_exceeded(
+					pruning.window_size(),
+					pruning.mem_used(),
+					pruning.inserted_bytes(),
+					pruning.time_span(),
+				);
+				if !exceeded {
 					break
 				}
 
@@ -390,23 +487,30 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 	fn revert_one(&mut self) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.revert_one(),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => self.non_canonical.revert_one(),
 		}
 	}
 
 	fn remove(&mut self, hash: &BlockHash) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.remove(hash),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => self.non_canonical.remove(hash),
 		}
 	}
 
 	fn pin(&mut self, hash: &BlockHash) -> Result<(), PinError> {
 		match self.mode {
 			PruningMode::ArchiveAll => Ok(()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::ConstrainedAge { .. } |
+			PruningMode::ConstrainedInsertedBytes { .. } => {
 				if self.non_canonical.have_block(hash) ||
 					self.pruning.as_ref().map_or(false, |pruning| pruning.have_block(hash))
 				{
@@ -524,18 +628,18 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 			(false, Some(stored), Some(requested)) => choose_pruning_mode(stored, requested)?,
 		};
 
-		let db_init_commit_set = if should_init {
-			let mut cs: CommitSet<Key> = Default::default();
-
+		let mut db_init_commit_set: CommitSet<Key> = Default::default();
+		if should_init {
 			let key = to_meta_key(PRUNING_MODE, &());
 			let value = selected_mode.id().to_owned();
 
-			cs.meta.inserted.push((key, value));
-
-			cs
-		} else {
-			Default::default()
-		};
+			db_init_commit_set.meta.inserted.push((key, value));
+		}
+		// The limit of the window may be changed on restart, always store the current one.
+		if let Some(limit) = selected_mode.limit() {
+			let key = to_meta_key(PRUNING_MODE_LIMIT, &());
+			db_init_commit_set.meta.inserted.push((key, limit.encode()));
+		}
 
 		let state_db =
 			StateDb { db: RwLock::new(StateDbSync::new(selected_mode, ref_counting, db)?) };
@@ -559,11 +663,15 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 	}
 
 	/// Finalize a previously inserted block.
+	///
+	/// `timestamp` is the timestamp of the block in milliseconds. It is only used by pruning
+	/// modes that [require timestamps](PruningMode::requires_timestamps).
 	pub fn canonicalize_block<E: fmt::Debug>(
 		&self,
 		hash: &BlockHash,
+		timestamp: Option<u64>,
 	) -> Result<CommitSet<Key>, Error<E>> {
-		self.db.write().canonicalize_block(hash)
+		self.db.write().canonicalize_block(hash, timestamp)
 	}
 
 	/// Prevents pruning of specified block and its descendants.
@@ -629,23 +737,98 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 	}
 }
 
-fn fetch_stored_pruning_mode<D: MetaDb>(db: &D) -> Result<Option<PruningMode>, Error<D::Error>> {
+/// Returns `true` if `key` is the meta key of a non-canonical or pruning journal record.
+///
+/// Journal records contain trie node keys in the format used by the backing database, so they
+/// have to be taken into account when the database is moved to a different backend.
+pub fn is_journal_key(key: &[u8]) -> bool {
+	key.ends_with(noncanonical::NON_CANONICAL_JOURNAL) || key.ends_with(pruning::PRUNING_JOURNAL)
+}
+
+/// Returns the pruning mode stored in the meta-data of `db`, if any.
+pub fn fetch_stored_pruning_mode<D: MetaDb>(
+	db: &D,
+) -> Result<Option<PruningMode>, Error<D::Error>> {
 	let meta_key_mode = to_meta_key(PRUNING_MODE, &());
 	if let Some(stored_mode) = db.get_meta(&meta_key_mode).map_err(Error::Db)? {
-		if let Some(mode) = PruningMode::from_id(&stored_mode) {
-			Ok(Some(mode))
-		} else {
-			Err(StateDbError::Metadata(format!(
-				"Invalid value stored for PRUNING_MODE: {:02x?}",
-				stored_mode
-			))
-			.into())
+		let stored_limit = || -> Result<u64, Error<D::Error>> {
+			let meta_key_limit = to_meta_key(PRUNING_MODE_LIMIT, &());
+			match db.get_meta(&meta_key_limit).map_err(Error::Db)? {
+				Some(limit) => Ok(u64::decode(&mut limit.as_slice())?),
+				None => Err(StateDbError::Metadata(format!(
+					"No PRUNING_MODE_LIMIT stored for PRUNING_MODE: {:02x?}",
+					stored_mode
+				))
+				.into()),
+			}
+		};
+
+		match &stored_mode[..] {
+			PRUNING_MODE_CONSTRAINED_AGE =>
+				Ok(Some(PruningMode::keep_age(Duration::from_millis(stored_limit()?)))),
+			PRUNING_MODE_CONSTRAINED_INSERTED_BYTES =>
+				Ok(Some(PruningMode::keep_inserted_bytes(stored_limit()?))),
+			id => match PruningMode::from_id(id) {
+				Some(mode) => Ok(Some(mode)),
+				None => Err(StateDbError::Metadata(format!(
+					"Invalid value stored for PRUNING_MODE: {:02x?}",
+					stored_mode
+				))
+				.into()),
+			},
 		}
 	} else {
 		Ok(None)
 	}
 }
 
+/// Change the pruning mode stored in `db` to `requested`.
+///
+/// Returns the commit that stores the new mode and prunes the blocks that no longer fit into the
+/// pruning window. Only switching between the archive modes and between the constrained modes is
+/// supported: the states pruned by a constrained mode can't be restored, and an archive database
+/// does not know which nodes to delete, its window has to be created with a
+/// [`PruningWindowBuilder`] instead.
+///
+/// If `requested` [requires timestamps](PruningMode::requires_timestamps), `block_timestamp` is
+/// called for the blocks of the window that were canonicalized without a timestamp. It returns
+/// the timestamp of the block in milliseconds, if known.
+pub fn reconfigure_pruning<BlockHash, Key, D>(
+	db: &D,
+	requested: PruningMode,
+	ref_counting: bool,
+	block_timestamp: impl FnMut(&BlockHash) -> Result<Option<u64>, D::Error>,
+) -> Result<CommitSet<Key>, Error<D::Error>>
+where
+	BlockHash: Hash + MallocSizeOf,
+	Key: Hash + MallocSizeOf,
+	D: MetaDb,
+{
+	let stored = fetch_stored_pruning_mode(db)?.ok_or_else(|| {
+		StateDbError::Metadata(
+			"The StateDb does not have PRUNING_MODE stored in its meta-data".into(),
+		)
+	})?;
+	if stored.is_archive() != requested.is_archive() {
+		return Err(StateDbError::IncompatiblePruningModes { stored, requested }.into())
+	}
+
+	let mut commit = CommitSet::default();
+	store_pruning_mode(&requested, &mut commit);
+	if requested.is_archive() {
+		return Ok(commit)
+	}
+
+	let mut state_db = StateDbSync::<BlockHash, Key>::new(requested, ref_counting, db)?;
+	if let Some(pruning) = &mut state_db.pruning {
+		if state_db.mode.requires_timestamps() {
+			pruning.fill_timestamps(db, block_timestamp, &mut commit)?;
+		}
+	}
+	state_db.prune(&mut commit);
+	Ok(commit)
+}
+
 fn choose_pruning_mode(
 	stored: PruningMode,
 	requested: PruningMode,
@@ -656,6 +839,12 @@ fn choose_pruning_mode(
 			Ok(PruningMode::ArchiveCanonical),
 		(PruningMode::Constrained(_), PruningMode::Constrained(requested)) =>
 			Ok(PruningMode::Constrained(requested)),
+		(PruningMode::ConstrainedAge { .. }, requested @ PruningMode::ConstrainedAge { .. }) =>
+			Ok(requested),
+		(
+			PruningMode::ConstrainedInsertedBytes { .. },
+			requested @ PruningMode::ConstrainedInsertedBytes { .. },
+		) => Ok(requested),
 		(stored, requested) => Err(StateDbError::IncompatiblePruningModes { requested, stored }),
 	}
 }
@@ -663,11 +852,14 @@ fn choose_pruning_mode(
 #[cfg(test)]
 mod tests {
 	use crate::{
+		reconfigure_pruning,
 		test::{make_changeset, make_db, TestDb},
-		Constraints, Error, PruningMode, StateDb, StateDbError,
+		Constraints, Error, PruningMode, PruningWindowBuilder, StateDb, StateDbError,
 	};
 	use sp_core::H256;
-	use std::io;
+	use std::{io, time::Duration};
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
 
 	fn make_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256>) {
 		let mut db = make_db(&[91, 921, 922, 93, 94]);
@@ -716,7 +908,11 @@ mod tests {
 				.unwrap(),
 		);
 		state_db.apply_pending();
-		db.commit(&state_db.canonicalize_block::<io::Error>(&H256::from_low_u64_be(1)).unwrap());
+		db.commit(
+			&state_db
+				.canonicalize_block::<io::Error>(&H256::from_low_u64_be(1), None)
+				.unwrap(),
+		);
 		state_db.apply_pending();
 		db.commit(
 			&state_db
@@ -729,9 +925,17 @@ mod tests {
 				.unwrap(),
 		);
 		state_db.apply_pending();
-		db.commit(&state_db.canonicalize_block::<io::Error>(&H256::from_low_u64_be(21)).unwrap());
+		db.commit(
+			&state_db
+				.canonicalize_block::<io::Error>(&H256::from_low_u64_be(21), None)
+				.unwrap(),
+		);
 		state_db.apply_pending();
-		db.commit(&state_db.canonicalize_block::<io::Error>(&H256::from_low_u64_be(3)).unwrap());
+		db.commit(
+			&state_db
+				.canonicalize_block::<io::Error>(&H256::from_low_u64_be(3), None)
+				.unwrap(),
+		);
 		state_db.apply_pending();
 
 		(db, state_db)
@@ -879,8 +1083,217 @@ mod tests {
 				Some(PruningMode::ArchiveCanonical),
 				Ok(PruningMode::ArchiveCanonical),
 			),
+			(Some(PruningMode::keep_age(DAY)), None, Ok(PruningMode::keep_age(DAY))),
+			(
+				Some(PruningMode::keep_age(DAY)),
+				Some(PruningMode::keep_age(DAY * 7)),
+				Ok(PruningMode::keep_age(DAY * 7)),
+			),
+			(Some(PruningMode::keep_age(DAY)), Some(PruningMode::keep_blocks(256)), Err(())),
+			(
+				Some(PruningMode::keep_age(DAY)),
+				Some(PruningMode::keep_inserted_bytes(1024)),
+				Err(()),
+			),
+			(Some(PruningMode::keep_age(DAY)), Some(PruningMode::ArchiveAll), Err(())),
+			(
+				Some(PruningMode::keep_inserted_bytes(1024)),
+				None,
+				Ok(PruningMode::keep_inserted_bytes(1024)),
+			),
+			(
+				Some(PruningMode::keep_inserted_bytes(1024)),
+				Some(PruningMode::keep_inserted_bytes(2048)),
+				Ok(PruningMode::keep_inserted_bytes(2048)),
+			),
+			(
+				Some(PruningMode::keep_inserted_bytes(1024)),
+				Some(PruningMode::keep_blocks(256)),
+				Err(()),
+			),
+			(Some(PruningMode::keep_blocks(256)), Some(PruningMode::keep_age(DAY)), Err(())),
+			(
+				Some(PruningMode::ArchiveCanonical),
+				Some(PruningMode::keep_inserted_bytes(1024)),
+				Err(()),
+			),
 		] {
 			check_stored_and_requested_mode_compatibility(created, reopened, expected);
 		}
 	}
+
+	#[test]
+	fn changed_window_limit_is_persisted() {
+		let mut db = make_db(&[]);
+		let (init, _) =
+			StateDb::<H256, H256>::open(&mut db, Some(PruningMode::keep_age(DAY)), false, true)
+				.unwrap();
+		db.commit(&init);
+		let (init, _) = StateDb::<H256, H256>::open(
+			&mut db,
+			Some(PruningMode::keep_age(DAY * 7)),
+			false,
+			false,
+		)
+		.unwrap();
+		db.commit(&init);
+
+		let (_, state_db) = StateDb::<H256, H256>::open(&mut db, None, false, false).unwrap();
+		assert_eq!(state_db.pruning_mode(), PruningMode::keep_age(DAY * 7));
+	}
+
+	/// Canonicalize a chain of four blocks. Block `n` inserts node `n`, deletes node `90 + n` and
+	/// has the timestamp `n` seconds.
+	fn make_linear_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256>) {
+		let mut db = make_db(&[91, 92, 93, 94]);
+		let (state_db_init, state_db) =
+			StateDb::open(&mut db, Some(settings.clone()), false, true).unwrap();
+		db.commit(&state_db_init);
+
+		for n in 1..=4u64 {
+			db.commit(
+				&state_db
+					.insert_block::<io::Error>(
+						&H256::from_low_u64_be(n),
+						n,
+						&H256::from_low_u64_be(n - 1),
+						make_changeset(&[n], &[90 + n]),
+					)
+					.unwrap(),
+			);
+			state_db.apply_pending();
+			db.commit(
+				&state_db
+					.canonicalize_block::<io::Error>(
+						&H256::from_low_u64_be(n),
+						settings.requires_timestamps().then(|| n * 1000),
+					)
+					.unwrap(),
+			);
+			state_db.apply_pending();
+		}
+
+		(db, state_db)
+	}
+
+	#[test]
+	fn prune_by_age() {
+		let (db, sdb) = make_linear_test_db(PruningMode::keep_age(Duration::from_millis(1500)));
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(2), 2));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 93, 94])));
+	}
+
+	#[test]
+	fn prune_by_inserted_bytes() {
+		// Every block inserts a single node of 64 bytes.
+		let (db, sdb) = make_linear_test_db(PruningMode::keep_inserted_bytes(128));
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(2), 2));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 93, 94])));
+	}
+
+	#[test]
+	fn reconfigure_to_smaller_window() {
+		let (mut db, _) = make_linear_test_db(PruningMode::keep_blocks(4));
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 91, 92, 93, 94])));
+
+		let commit =
+			reconfigure_pruning::<H256, H256, _>(&db, PruningMode::keep_blocks(2), true, |_| {
+				Ok(None)
+			})
+			.unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 93, 94])));
+
+		let (_, sdb) =
+			StateDb::<H256, H256>::open(&db, Some(PruningMode::keep_blocks(2)), true, false)
+				.unwrap();
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(1), 1));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+	}
+
+	#[test]
+	fn reconfigure_to_age_looks_up_timestamps() {
+		let (mut db, _) = make_linear_test_db(PruningMode::keep_blocks(4));
+
+		let max_age = PruningMode::keep_age(Duration::from_millis(1500));
+		let commit = reconfigure_pruning::<H256, H256, _>(&db, max_age.clone(), true, |hash| {
+			Ok(Some(hash.to_low_u64_be() * 1000))
+		})
+		.unwrap();
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 93, 94])));
+
+		let (_, sdb) = StateDb::<H256, H256>::open(&db, None, true, false).unwrap();
+		assert_eq!(sdb.pruning_mode(), max_age);
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(1), 1));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+	}
+
+	#[test]
+	fn reconfigure_between_archive_and_constrained_fails() {
+		let (db, _) = make_linear_test_db(PruningMode::ArchiveCanonical);
+		assert!(matches!(
+			reconfigure_pruning::<H256, H256, _>(&db, PruningMode::keep_blocks(2), true, |_| Ok(
+				None
+			)),
+			Err(Error::StateDb(StateDbError::IncompatiblePruningModes { .. }))
+		));
+		assert!(reconfigure_pruning::<H256, H256, _>(&db, PruningMode::ArchiveAll, true, |_| {
+			Ok(None)
+		})
+		.is_ok());
+
+		let (db, _) = make_linear_test_db(PruningMode::keep_blocks(4));
+		assert!(matches!(
+			reconfigure_pruning::<H256, H256, _>(&db, PruningMode::ArchiveCanonical, true, |_| {
+				Ok(None)
+			}),
+			Err(Error::StateDb(StateDbError::IncompatiblePruningModes { .. }))
+		));
+	}
+
+	#[test]
+	fn build_window_of_archive_database() {
+		let (mut db, _) = make_linear_test_db(PruningMode::ArchiveCanonical);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 91, 92, 93, 94])));
+
+		let mut builder = PruningWindowBuilder::new(
+			PruningMode::keep_blocks(2),
+			true,
+			H256::from_low_u64_be(4),
+			4,
+		);
+		for n in (1..=4).rev() {
+			let block_fits =
+				builder.push_front(H256::from_low_u64_be(n), None, make_changeset(&[n], &[90 + n]));
+			assert_eq!(block_fits, n > 2);
+		}
+		assert_eq!(builder.first_block(), 3);
+		db.commit(&builder.into_commit());
+		// The nodes of the states before the window are removed by the caller.
+		db.data.remove(&H256::from_low_u64_be(91));
+		db.data.remove(&H256::from_low_u64_be(92));
+
+		let (_, sdb) =
+			StateDb::<H256, H256>::open(&db, Some(PruningMode::keep_blocks(2)), true, false)
+				.unwrap();
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(2), 2));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+
+		db.commit(
+			&sdb.insert_block::<io::Error>(
+				&H256::from_low_u64_be(5),
+				5,
+				&H256::from_low_u64_be(4),
+				make_changeset(&[5], &[]),
+			)
+			.unwrap(),
+		);
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(5), None).unwrap());
+		sdb.apply_pending();
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 5, 94])));
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+	}
 }
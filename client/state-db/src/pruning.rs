@@ -24,13 +24,16 @@
 //! the death list.
 //! The changes are journaled in the DB.
 
-use crate::{to_meta_key, CommitSet, Error, Hash, MetaDb};
+use crate::{
+	noncanonical::LAST_CANONICAL, store_pruning_mode, to_meta_key, ChangeSet, CommitSet, DBValue,
+	Error, Hash, MetaDb, PruningMode, StateDbError,
+};
 use codec::{Decode, Encode};
 use log::{trace, warn};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 const LAST_PRUNED: &[u8] = b"last_pruned";
-const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
+pub(crate) const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
 
 /// See module documentation.
 #[derive(parity_util_mem_derive::MallocSizeOf)]
@@ -41,6 +44,8 @@ pub struct RefWindow<BlockHash: Hash, Key: Hash> {
 	death_index: HashMap<Key, u64>,
 	/// Block number that corresponds to the front of `death_rows`.
 	pending_number: u64,
+	/// Number of bytes of the trie nodes inserted by the blocks in `death_rows`.
+	inserted_bytes: u64,
 	/// Number of call of `note_canonical` after
 	/// last call `apply_pending` or `revert_pending`
 	pending_canonicalizations: usize,
@@ -58,6 +63,8 @@ struct DeathRow<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
 	journal_key: Vec<u8>,
 	deleted: HashSet<Key>,
+	timestamp: Option<u64>,
+	inserted_bytes: u64,
 }
 
 #[derive(Encode, Decode)]
@@ -67,10 +74,30 @@ struct JournalRecord<BlockHash: Hash, Key: Hash> {
 	deleted: Vec<Key>,
 }
 
+/// Block details that are stored right after the `JournalRecord`.
+///
+/// Journals written by older versions end after the `JournalRecord`, the default is used for
+/// them.
+#[derive(Encode, Decode, Default)]
+struct JournalRecordInfo {
+	/// Timestamp of the block in milliseconds, if known.
+	timestamp: Option<u64>,
+	/// Number of bytes of the trie nodes inserted by the block.
+	inserted_bytes: u64,
+}
+
 fn to_journal_key(block: u64) -> Vec<u8> {
 	to_meta_key(PRUNING_JOURNAL, &block)
 }
 
+/// Number of bytes of the trie nodes inserted by a block, keys included.
+///
+/// This is what the block added to the database before compression and before the trie nodes
+/// that are shared with other states are deduplicated, not the space it takes on disk.
+fn count_inserted_bytes<Key: Hash>(inserted: &[(Key, DBValue)]) -> u64 {
+	inserted.iter().map(|(k, v)| (k.encoded_size() + v.len()) as u64).sum()
+}
+
 impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 	pub fn new<D: MetaDb>(
 		db: &D,
@@ -86,6 +113,7 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 			death_rows: Default::default(),
 			death_index: Default::default(),
 			pending_number,
+			inserted_bytes: 0,
 			pending_canonicalizations: 0,
 			pending_prunings: 0,
 			count_insertions,
@@ -96,14 +124,20 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 			let journal_key = to_journal_key(block);
 			match db.get_meta(&journal_key).map_err(Error::Db)? {
 				Some(record) => {
-					let record: JournalRecord<BlockHash, Key> =
-						Decode::decode(&mut record.as_slice())?;
+					let mut input = record.as_slice();
+					let record: JournalRecord<BlockHash, Key> = Decode::decode(&mut input)?;
+					let info = if input.is_empty() {
+						JournalRecordInfo::default()
+					} else {
+						JournalRecordInfo::decode(&mut input)?
+					};
 					trace!(target: "state-db", "Pruning journal entry {} ({} inserted, {} deleted)", block, record.inserted.len(), record.deleted.len());
 					pruning.import(
 						&record.hash,
 						journal_key,
 						record.inserted.into_iter(),
 						record.deleted,
+						info,
 					);
 				},
 				None => break,
@@ -119,6 +153,7 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		journal_key: Vec<u8>,
 		inserted: I,
 		deleted: Vec<Key>,
+		info: JournalRecordInfo,
 	) {
 		if self.count_insertions {
 			// remove all re-inserted keys from death rows
@@ -134,10 +169,13 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 				self.death_index.insert(k.clone(), imported_block);
 			}
 		}
+		self.inserted_bytes += info.inserted_bytes;
 		self.death_rows.push_back(DeathRow {
 			hash: hash.clone(),
 			deleted: deleted.into_iter().collect(),
 			journal_key,
+			timestamp: info.timestamp,
+			inserted_bytes: info.inserted_bytes,
 		});
 	}
 
@@ -153,6 +191,27 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		0
 	}
 
+	/// Number of bytes of the trie nodes inserted by the blocks in the window.
+	pub fn inserted_bytes(&self) -> u64 {
+		let pruned: u64 = self
+			.death_rows
+			.iter()
+			.take(self.pending_prunings)
+			.map(|r| r.inserted_bytes)
+			.sum();
+		self.inserted_bytes - pruned
+	}
+
+	/// Time between the oldest and the newest block in the window, in milliseconds.
+	///
+	/// Returns `None` if the window is empty or the timestamp of the newest block is unknown.
+	/// Blocks with an unknown timestamp are considered to be infinitely old.
+	pub fn time_span(&self) -> Option<u64> {
+		let newest = self.death_rows.back()?.timestamp?;
+		let oldest = self.death_rows.get(self.pending_prunings)?.timestamp.unwrap_or(0);
+		Some(newest.saturating_sub(oldest))
+	}
+
 	pub fn pending(&self) -> u64 {
 		self.pending_number + self.pending_prunings as u64
 	}
@@ -176,8 +235,19 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 	}
 
 	/// Add a change set to the window. Creates a journal record and pushes it to `commit`
-	pub fn note_canonical(&mut self, hash: &BlockHash, commit: &mut CommitSet<Key>) {
+	///
+	/// `timestamp` is the timestamp of the block in milliseconds, if known.
+	pub fn note_canonical(
+		&mut self,
+		hash: &BlockHash,
+		timestamp: Option<u64>,
+		commit: &mut CommitSet<Key>,
+	) {
 		trace!(target: "state-db", "Adding to pruning window: {:?} ({} inserted, {} deleted)", hash, commit.data.inserted.len(), commit.data.deleted.len());
+		let info = JournalRecordInfo {
+			timestamp,
+			inserted_bytes: count_inserted_bytes(&commit.data.inserted),
+		};
 		let inserted = if self.count_insertions {
 			commit.data.inserted.iter().map(|(k, _)| k.clone()).collect()
 		} else {
@@ -187,16 +257,47 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		let journal_record = JournalRecord { hash: hash.clone(), inserted, deleted };
 		let block = self.pending_number + self.death_rows.len() as u64;
 		let journal_key = to_journal_key(block);
-		commit.meta.inserted.push((journal_key.clone(), journal_record.encode()));
+		commit
+			.meta
+			.inserted
+			.push((journal_key.clone(), (&journal_record, &info).encode()));
 		self.import(
 			&journal_record.hash,
 			journal_key,
 			journal_record.inserted.into_iter(),
 			journal_record.deleted,
+			info,
 		);
 		self.pending_canonicalizations += 1;
 	}
 
+	/// Set the timestamps of the blocks in the window that were canonicalized without one.
+	///
+	/// `block_timestamp` returns the timestamp of a block in milliseconds, if known. The updated
+	/// journal records are added to `commit`.
+	pub fn fill_timestamps<D: MetaDb>(
+		&mut self,
+		db: &D,
+		mut block_timestamp: impl FnMut(&BlockHash) -> Result<Option<u64>, D::Error>,
+		commit: &mut CommitSet<Key>,
+	) -> Result<(), Error<D::Error>> {
+		for row in self.death_rows.iter_mut().filter(|row| row.timestamp.is_none()) {
+			let timestamp = match block_timestamp(&row.hash).map_err(Error::Db)? {
+				Some(timestamp) => timestamp,
+				None => continue,
+			};
+			let record = db.get_meta(&row.journal_key).map_err(Error::Db)?.ok_or_else(|| {
+				StateDbError::Metadata(format!("Missing pruning journal record of {:?}", row.hash))
+			})?;
+			let record: JournalRecord<BlockHash, Key> = Decode::decode(&mut record.as_slice())?;
+			row.timestamp = Some(timestamp);
+			let info =
+				JournalRecordInfo { timestamp: row.timestamp, inserted_bytes: row.inserted_bytes };
+			commit.meta.inserted.push((row.journal_key.clone(), (&record, &info).encode()));
+		}
+		Ok(())
+	}
+
 	/// Apply all pending changes
 	pub fn apply_pending(&mut self) {
 		self.pending_canonicalizations = 0;
@@ -206,6 +307,7 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 				.pop_front()
 				.expect("pending_prunings is always < death_rows.len()");
 			trace!(target: "state-db", "Applying pruning {:?} ({} deleted)", pruned.hash, pruned.deleted.len());
+			self.inserted_bytes -= pruned.inserted_bytes;
 			if self.count_insertions {
 				for k in pruned.deleted.iter() {
 					self.death_index.remove(k);
@@ -223,7 +325,10 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		// `death_index` We don't bother to track and revert that for now. This means that a few
 		// nodes might end up no being deleted in case transaction fails and `revert_pending` is
 		// called.
-		self.death_rows.truncate(self.death_rows.len() - self.pending_canonicalizations);
+		let reverted = self.death_rows.len() - self.pending_canonicalizations;
+		self.inserted_bytes -=
+			self.death_rows.iter().skip(reverted).map(|r| r.inserted_bytes).sum::<u64>();
+		self.death_rows.truncate(reverted);
 		if self.count_insertions {
 			let new_max_block = self.death_rows.len() as u64 + self.pending_number;
 			self.death_index.retain(|_, block| *block < new_max_block);
@@ -233,13 +338,112 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 	}
 }
 
+/// Creates the pruning window of a database that kept its states with one of the archive pruning
+/// modes, so that it can switch to a constrained mode without importing the blocks again.
+///
+/// Blocks are added from the last canonicalized block backwards until the window is full. Every
+/// block comes with the changes of its state compared to the state of its parent, these are the
+/// changes the block would have been canonicalized with.
+pub struct PruningWindowBuilder<BlockHash: Hash, Key: Hash> {
+	mode: PruningMode,
+	count_insertions: bool,
+	last_canonical: (BlockHash, u64),
+	records: VecDeque<(JournalRecord<BlockHash, Key>, JournalRecordInfo)>,
+	inserted_bytes: u64,
+}
+
+impl<BlockHash: Hash, Key: Hash> PruningWindowBuilder<BlockHash, Key> {
+	/// Create a builder for a window that ends with the block `hash` with the given `number`.
+	///
+	/// `mode` has to be one of the constrained pruning modes.
+	pub fn new(mode: PruningMode, ref_counting: bool, hash: BlockHash, number: u64) -> Self {
+		debug_assert!(!mode.is_archive(), "An archive mode does not have a pruning window");
+		PruningWindowBuilder {
+			mode,
+			count_insertions: ref_counting,
+			last_canonical: (hash, number),
+			records: Default::default(),
+			inserted_bytes: 0,
+		}
+	}
+
+	/// Number of the oldest block that will be kept in the window.
+	pub fn first_block(&self) -> u64 {
+		self.last_canonical.1 + 1 - self.records.len() as u64
+	}
+
+	/// Add the block that precedes the oldest block of the window.
+	///
+	/// `timestamp` is the timestamp of the block in milliseconds, if known. Returns `false` and
+	/// leaves the window untouched if the block does not fit into the window anymore.
+	pub fn push_front(
+		&mut self,
+		hash: BlockHash,
+		timestamp: Option<u64>,
+		changeset: ChangeSet<Key>,
+	) -> bool {
+		if self.first_block() == 0 {
+			return false
+		}
+
+		let inserted_bytes = count_inserted_bytes(&changeset.inserted);
+		let newest = self.records.back().map_or(timestamp, |(_, info)| info.timestamp);
+		let time_span = newest.map(|newest| newest.saturating_sub(timestamp.unwrap_or(0)));
+		if self.mode.window_exceeded(
+			self.records.len() as u64 + 1,
+			0,
+			self.inserted_bytes + inserted_bytes,
+			time_span,
+		) {
+			return false
+		}
+
+		let inserted = if self.count_insertions {
+			changeset.inserted.into_iter().map(|(k, _)| k).collect()
+		} else {
+			Default::default()
+		};
+		let record = JournalRecord { hash, inserted, deleted: changeset.deleted };
+		self.records
+			.push_front((record, JournalRecordInfo { timestamp, inserted_bytes }));
+		self.inserted_bytes += inserted_bytes;
+		true
+	}
+
+	/// Returns the commit that stores the pruning mode and the journal of the window.
+	///
+	/// The trie nodes that don't belong to any of the states in the window are not part of the
+	/// commit, they have to be removed from the database separately.
+	pub fn into_commit(self) -> CommitSet<Key> {
+		let mut commit = CommitSet::default();
+		store_pruning_mode(&self.mode, &mut commit);
+
+		let first_block = self.first_block();
+		if first_block > 0 {
+			commit
+				.meta
+				.inserted
+				.push((to_meta_key(LAST_PRUNED, &()), (first_block - 1).encode()));
+		}
+		for (block, (record, info)) in (first_block..).zip(self.records) {
+			commit.meta.inserted.push((to_journal_key(block), (&record, &info).encode()));
+		}
+		commit
+			.meta
+			.inserted
+			.push((to_meta_key(LAST_CANONICAL, &()), self.last_canonical.encode()));
+		commit
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::RefWindow;
+	use super::{to_journal_key, JournalRecord, RefWindow};
 	use crate::{
 		test::{make_commit, make_db, TestDb},
 		CommitSet,
 	};
+	use codec::Encode;
 	use sp_core::H256;
 
 	fn check_journal(pruning: &RefWindow<H256, H256>, db: &TestDb) {
@@ -277,7 +481,7 @@ mod tests {
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
 		let mut commit = make_commit(&[4, 5], &[1, 3]);
 		let h = H256::random();
-		pruning.note_canonical(&h, &mut commit);
+		pruning.note_canonical(&h, None, &mut commit);
 		db.commit(&commit);
 		assert!(pruning.have_block(&h));
 		pruning.apply_pending();
@@ -305,10 +509,10 @@ mod tests {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
 		let mut commit = make_commit(&[4], &[1]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[5], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		pruning.apply_pending();
 		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 5])));
@@ -333,10 +537,10 @@ mod tests {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
 		let mut commit = make_commit(&[4], &[1]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[5], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		assert!(db.data_eq(&make_db(&[1, 2, 3, 4, 5])));
 		let mut commit = CommitSet::default();
@@ -356,13 +560,13 @@ mod tests {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[2], &[]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		assert!(db.data_eq(&make_db(&[1, 2, 3])));
 		pruning.apply_pending();
@@ -389,13 +593,13 @@ mod tests {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[2], &[]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		assert!(db.data_eq(&make_db(&[1, 2, 3])));
 
@@ -419,13 +623,13 @@ mod tests {
 		let mut db = make_db(&[1, 2, 3]);
 		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, false).unwrap();
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[2], &[]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		let mut commit = make_commit(&[], &[2]);
-		pruning.note_canonical(&H256::random(), &mut commit);
+		pruning.note_canonical(&H256::random(), None, &mut commit);
 		db.commit(&commit);
 		assert!(db.data_eq(&make_db(&[1, 2, 3])));
 		pruning.apply_pending();
@@ -438,4 +642,43 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 3])));
 		assert!(pruning.death_index.is_empty());
 	}
+
+	#[test]
+	fn tracks_inserted_bytes_and_time_span() {
+		let mut db = make_db(&[1, 2, 3]);
+		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
+		let mut commit = make_commit(&[4], &[1]);
+		pruning.note_canonical(&H256::random(), Some(1000), &mut commit);
+		db.commit(&commit);
+		let mut commit = make_commit(&[5, 6], &[2]);
+		pruning.note_canonical(&H256::random(), Some(3000), &mut commit);
+		db.commit(&commit);
+		pruning.apply_pending();
+		assert_eq!(pruning.inserted_bytes(), 3 * 64);
+		assert_eq!(pruning.time_span(), Some(2000));
+		check_journal(&pruning, &db);
+
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit);
+		assert_eq!(pruning.inserted_bytes(), 2 * 64);
+		assert_eq!(pruning.time_span(), Some(0));
+		pruning.revert_pending();
+		assert_eq!(pruning.inserted_bytes(), 3 * 64);
+	}
+
+	#[test]
+	fn reads_journal_without_block_info() {
+		let mut db = make_db(&[1]);
+		let record = JournalRecord::<H256, H256> {
+			hash: H256::random(),
+			inserted: Vec::new(),
+			deleted: vec![H256::from_low_u64_be(1)],
+		};
+		db.meta.insert(to_journal_key(0), record.encode());
+
+		let pruning: RefWindow<H256, H256> = RefWindow::new(&db, true).unwrap();
+		assert_eq!(pruning.window_size(), 1);
+		assert_eq!(pruning.inserted_bytes(), 0);
+		assert_eq!(pruning.time_span(), None);
+	}
 }
@@ -55,6 +55,14 @@ pub enum Error {
 		/// Transaction entering the pool.
 		new: Priority,
 	},
+
+	#[error("Too low priority increase ({} < {})", new, required)]
+	TooLowPriorityIncrease {
+		/// Minimum priority required to replace the transaction already in the pool.
+		required: Priority,
+		/// Transaction entering the pool.
+		new: Priority,
+	},
 	#[error("Transaction with cyclic dependency")]
 	CycleDetected,
 
@@ -69,6 +77,15 @@ pub enum Error {
 
 	#[error("The pool is not accepting future transactions")]
 	RejectedFutureTransaction,
+
+	#[error("The transaction to replace is not in the pool")]
+	ReplacedTransactionNotFound,
+
+	#[error("The transaction does not provide any of the tags of the transaction to replace")]
+	InvalidReplacement,
+
+	#[error("The bundle does not contain any transaction")]
+	EmptyBundle,
 }
 
 /// Transaction pool error conversion.
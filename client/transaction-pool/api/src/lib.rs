@@ -23,6 +23,7 @@ pub mod error;
 
 use futures::{Future, Stream};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sp_core::Bytes;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Member, NumberFor},
@@ -34,7 +35,8 @@ pub use sp_runtime::transaction_validity::{
 };
 
 /// Transaction pool status.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PoolStatus {
 	/// Number of transactions in the ready queue.
 	pub ready: usize,
@@ -53,6 +55,91 @@ impl PoolStatus {
 	}
 }
 
+/// Detailed view of the transactions in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolInspection<Hash> {
+	/// Transactions in the ready queue, best first.
+	pub ready: Vec<InspectedTransaction<Hash>>,
+	/// Transactions in the future queue, oldest first.
+	pub future: Vec<InspectedTransaction<Hash>>,
+	/// Number of transactions of each chain, largest first.
+	pub chains: Vec<ChainTransactions<Hash>>,
+}
+
+/// Details of a transaction in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedTransaction<Hash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// Priority of the transaction.
+	pub priority: TransactionPriority,
+	/// Block number at which the transaction becomes invalid.
+	pub valid_till: TransactionLongevity,
+	/// Size of the encoded transaction.
+	pub bytes: usize,
+	/// Tags required by the transaction.
+	pub requires: Vec<Bytes>,
+	/// Tags provided by the transaction.
+	pub provides: Vec<Bytes>,
+	/// Required tags that are not provided by any transaction in the pool yet.
+	pub missing_tags: Vec<Bytes>,
+	/// Source of the transaction.
+	pub source: TransactionSource,
+	/// Milliseconds since the transaction entered the pool.
+	pub time_in_pool: u64,
+	/// Transactions in the pool providing the tags required by this one.
+	pub unlocked_by: Vec<Hash>,
+	/// Transactions in the pool requiring the tags provided by this one.
+	pub unlocks: Vec<Hash>,
+	/// First transaction of the chain the transaction is part of.
+	///
+	/// The pool doesn't know about accounts. Transactions that depend on each other through their
+	/// tags (like the consecutive nonces of an account) form a chain, which is identified by the
+	/// hash of its first transaction and stands in for a sender in the sender limit. The first
+	/// transaction may have left the pool already, like when it was included in a block.
+	pub chain: Hash,
+}
+
+/// Number of transactions of a chain in the pool.
+///
+/// See [`InspectedTransaction::chain`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTransactions<Hash> {
+	/// First transaction of the chain.
+	pub first: Hash,
+	/// Number of transactions of the chain in the ready queue.
+	pub ready: usize,
+	/// Number of transactions of the chain in the future queue.
+	pub future: usize,
+}
+
+/// Result of a transaction included in a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionResult {
+	/// Result of the transaction, as reported by the runtime.
+	#[serde(flatten)]
+	pub inclusion: sp_transaction_pool::runtime_api::InclusionResult,
+	/// Error of a pallet the transaction failed with, decoded from the metadata of the runtime.
+	///
+	/// `None` if the transaction didn't fail with the error of a pallet, or if the error could
+	/// not be decoded.
+	pub module_error: Option<ModuleError>,
+}
+
+/// Error of a pallet, decoded from the metadata of the runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleError {
+	/// Name of the pallet.
+	pub pallet: String,
+	/// Name of the error.
+	pub error: String,
+}
+
 /// Possible transaction status events.
 ///
 /// This events are being emitted by `TransactionPool` watchers,
@@ -64,15 +151,18 @@ impl PoolStatus {
 /// 		- `Ready`
 /// 2. Inside `Ready` queue:
 /// 		- `Broadcast`
+/// 		- `ReadyAtLeaf` or `InvalidAtLeaf`
 /// 3. Leaving the pool:
-/// 		- `InBlock`
+/// 		- `InBlock` or `InBlockWithResult`
 /// 		- `Invalid`
 /// 		- `Usurped`
 /// 		- `Dropped`
+/// 		- `SenderLimitExceeded`
+/// 		- `SourceQuotaExceeded`
 /// 	4. Re-entering the pool:
 /// 		- `Retracted`
 /// 	5. Block finalized:
-/// 		- `Finalized`
+/// 		- `Finalized` or `FinalizedWithResult`
 /// 		- `FinalityTimeout`
 ///
 /// The events will always be received in the order described above, however
@@ -85,13 +175,22 @@ impl PoolStatus {
 /// Note that there are conditions that may cause transactions to reappear in the pool.
 /// 1. Due to possible forks, the transaction that ends up being in included
 /// in one block, may later re-enter the pool or be marked as invalid.
-/// 2. Transaction `Dropped` at one point, may later re-enter the pool if some other
-/// transactions are removed.
+/// 2. Transaction `Dropped` (or evicted because of the sender or source limits) at one point,
+/// may later re-enter the pool if some other transactions are removed.
 /// 3. `Invalid` transaction may become valid at some point in the future.
 /// (Note that runtimes are encouraged to use `UnknownValidity` to inform the pool about
 /// such case).
 /// 4. `Retracted` transactions might be included in some next block.
 ///
+/// The `ReadyAtLeaf` and `InvalidAtLeaf` events report the status of the transaction at
+/// recent leaves that are not the best block, for which the pool keeps separate views. They
+/// don't change the status at the best block, and a transaction that is `InvalidAtLeaf` stays
+/// in the pool. The inclusion in a block of such a leaf is reported with `InBlock`.
+///
+/// The `InBlockWithResult` and `FinalizedWithResult` events replace `InBlock` and `Finalized`
+/// if the pool is configured to report the results of the included transactions, and the
+/// runtime provides them.
+///
 /// The stream is considered finished only when either `Finalized` or `FinalityTimeout`
 /// event is triggered. You are however free to unsubscribe from notifications at any point.
 /// The first one will be emitted when the block, in which transaction was included gets
@@ -108,8 +207,19 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	Ready,
 	/// The transaction has been broadcast to the given peers.
 	Broadcast(Vec<String>),
+	/// Transaction is ready at the given leaf, which is not the best block.
+	ReadyAtLeaf(BlockHash),
+	/// Transaction is invalid at the given leaf, which is not the best block.
+	InvalidAtLeaf(BlockHash),
 	/// Transaction has been included in block with given hash.
 	InBlock(BlockHash),
+	/// Transaction has been included in block with given hash, with the given result.
+	InBlockWithResult {
+		/// Hash of the block.
+		block: BlockHash,
+		/// Result of the transaction in the block.
+		result: InclusionResult,
+	},
 	/// The block this transaction was included in has been retracted.
 	Retracted(BlockHash),
 	/// Maximum number of finality watchers has been reached,
@@ -117,11 +227,24 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	FinalityTimeout(BlockHash),
 	/// Transaction has been finalized by a finality-gadget, e.g GRANDPA
 	Finalized(BlockHash),
+	/// Transaction has been finalized by a finality-gadget, with the given result.
+	FinalizedWithResult {
+		/// Hash of the block.
+		block: BlockHash,
+		/// Result of the transaction in the block.
+		result: InclusionResult,
+	},
 	/// Transaction has been replaced in the pool, by another transaction
 	/// that provides the same tags. (e.g. same (sender, nonce)).
 	Usurped(Hash),
 	/// Transaction has been dropped from the pool because of the limit.
 	Dropped,
+	/// Transaction has been dropped from the pool because its sender had more transactions
+	/// in the pool than allowed.
+	SenderLimitExceeded,
+	/// Transaction has been dropped from the pool because the transactions of its source
+	/// exceeded their share of the pool.
+	SourceQuotaExceeded,
 	/// Transaction is no longer valid in the current state.
 	Invalid,
 }
@@ -171,6 +294,11 @@ pub trait InPoolTransaction {
 	fn provides(&self) -> &[TransactionTag];
 	/// Return a flag indicating if the transaction should be propagated to other peers.
 	fn is_propagable(&self) -> bool;
+	/// Get the hashes of the transactions of the bundle this transaction is part of, in the
+	/// order they have to be included.
+	fn bundle(&self) -> Option<&[Self::Hash]>;
+	/// Get the order of arrival of the transaction in the pool (lower = earlier).
+	fn arrival(&self) -> u64;
 }
 
 /// Transaction pool interface.
@@ -214,6 +342,51 @@ pub trait TransactionPool: Send + Sync {
 		xt: TransactionFor<Self>,
 	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error>;
 
+	/// Returns a future that replaces the transaction `old` in the pool with an unverified one.
+	///
+	/// The replacement needs to increase the priority by the minimum bump required by the pool.
+	/// If it can't be imported the old transaction stays in the pool, otherwise the watchers of
+	/// the old transaction are notified that it was `Usurped`.
+	fn replace(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		old: TxHash<Self>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error>;
+
+	/// Returns a future that replaces the transaction `old` in the pool with an unverified one
+	/// and starts to watch the progress of the new one.
+	///
+	/// See [`Self::replace`] for the requirements of the replacement. The stream of the new
+	/// transaction starts with the queue it entered, the watchers of the old transaction are
+	/// notified that it was `Usurped`.
+	fn replace_and_watch(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		old: TxHash<Self>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error>;
+
+	/// Returns a future that imports an ordered bundle of unverified transactions to the pool.
+	///
+	/// The transactions are included in a block together, contiguously and in the given order,
+	/// or not at all. Each of them has to be valid on its own, and they all get the lowest
+	/// priority among them. Bundles are never propagated to other peers.
+	fn submit_bundle(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<TxHash<Self>>, Self::Error>;
+
+	/// Starts to watch a transaction that is already in the pool.
+	///
+	/// The stream starts with the current status of the transaction. Returns `None` if the
+	/// transaction is not in the pool.
+	fn watch(&self, hash: &TxHash<Self>) -> Option<Pin<Box<TransactionStatusStreamFor<Self>>>>;
+
 	// *** Block production / Networking
 	/// Get an iterator for ready transactions ordered by priority.
 	///
@@ -230,6 +403,21 @@ pub trait TransactionPool: Send + Sync {
 		>,
 	>;
 
+	/// Get an iterator for ready transactions ordered by priority, valid on top of the block
+	/// with the given hash.
+	///
+	/// Unlike `ready_at`, `at` does not have to be on the best chain.
+	fn ready_at_hash(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	>;
+
 	/// Get an iterator for ready transactions ordered by priority.
 	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>;
 
@@ -241,6 +429,10 @@ pub trait TransactionPool: Send + Sync {
 	/// Returns pool status.
 	fn status(&self) -> PoolStatus;
 
+	// *** RPC
+	/// Returns the details of all the transactions in the pool.
+	fn inspect(&self) -> PoolInspection<TxHash<Self>>;
+
 	// *** logging / RPC / networking
 	/// Return an event stream of transactions imported to the pool.
 	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>>;
@@ -288,6 +480,11 @@ pub enum ChainEvent<B: BlockT> {
 		/// If `None`, no re-org happened on import.
 		tree_route: Option<Arc<sp_blockchain::TreeRoute<B>>>,
 	},
+	/// New block that is not the best block has been added to the chain.
+	NewBlock {
+		/// Hash of the block.
+		hash: B::Hash,
+	},
 	/// An existing block has been finalized.
 	Finalized {
 		/// Hash of just finalized block.
@@ -121,6 +121,13 @@ impl ChainApi for TestApi {
 	) -> Result<Option<<Self::Block as BlockT>::Header>, Self::Error> {
 		Ok(None)
 	}
+
+	fn inclusion_results(
+		&self,
+		_: &BlockId<Self::Block>,
+	) -> Result<Vec<(H256, sc_transaction_pool_api::InclusionResult)>, Self::Error> {
+		Ok(Vec::new())
+	}
 }
 
 fn uxt(transfer: Transfer) -> Extrinsic {
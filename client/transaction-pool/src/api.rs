@@ -18,7 +18,8 @@
 
 //! Chain api required for the transaction pool.
 
-use codec::Encode;
+use codec::{Decode, Encode};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV14};
 use futures::{
 	channel::{mpsc, oneshot},
 	future::{ready, Future, FutureExt, Ready},
@@ -29,14 +30,17 @@ use std::{marker::PhantomData, pin::Pin, sync::Arc};
 
 use prometheus_endpoint::Registry as PrometheusRegistry;
 use sc_client_api::{blockchain::HeaderBackend, BlockBackend};
-use sp_api::{ApiExt, ProvideRuntimeApi};
+use sc_transaction_pool_api::{InclusionResult, ModuleError as DecodedModuleError};
+use scale_info::TypeDef;
+use sp_api::{ApiExt, Metadata, ProvideRuntimeApi};
 use sp_core::traits::SpawnEssentialNamed;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{self, Block as BlockT, BlockIdTo},
 	transaction_validity::{TransactionSource, TransactionValidity},
+	DispatchError, ModuleError,
 };
-use sp_transaction_pool::runtime_api::TaggedTransactionQueue;
+use sp_transaction_pool::runtime_api::{TaggedTransactionQueue, TransactionInclusionApi};
 
 use crate::{
 	error::{self, Error},
@@ -50,6 +54,18 @@ pub struct FullChainApi<Client, Block> {
 	_marker: PhantomData<Block>,
 	metrics: Option<Arc<ApiMetrics>>,
 	validation_pool: Arc<Mutex<mpsc::Sender<Pin<Box<dyn Future<Output = ()> + Send>>>>>,
+	inclusion_results: Option<Box<dyn InclusionResults<Client, Block>>>,
+}
+
+/// Reads the results of the transactions included in a block.
+trait InclusionResults<Client, Block>: Send + Sync {
+	fn inclusion_results(
+		&self,
+		client: &Client,
+		at: &BlockId<Block>,
+	) -> error::Result<Vec<(Block::Hash, InclusionResult)>>
+	where
+		Block: BlockT;
 }
 
 /// Spawn a validation task that will be used by the transaction pool to validate transactions.
@@ -104,10 +120,27 @@ impl<Client, Block> FullChainApi<Client, Block> {
 			validation_pool: Arc::new(Mutex::new(sender)),
 			_marker: Default::default(),
 			metrics,
+			inclusion_results: None,
 		}
 	}
 }
 
+impl<Client, Block> FullChainApi<Client, Block>
+where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block> + BlockBackend<Block>,
+	Client::Api: TransactionInclusionApi<Block> + Metadata<Block>,
+{
+	/// Report the results of the transactions included in blocks to their watchers.
+	///
+	/// Only available for runtimes that implement `TransactionInclusionApi`, blocks of runtime
+	/// versions without the api are still skipped.
+	pub fn with_inclusion_results(mut self) -> Self {
+		self.inclusion_results = Some(Box::new(RuntimeInclusionResults));
+		self
+	}
+}
+
 impl<Client, Block> graph::ChainApi for FullChainApi<Client, Block>
 where
 	Block: BlockT,
@@ -190,6 +223,96 @@ where
 	) -> Result<Option<<Self::Block as BlockT>::Header>, Self::Error> {
 		self.client.header(*at).map_err(Into::into)
 	}
+
+	fn inclusion_results(
+		&self,
+		at: &BlockId<Self::Block>,
+	) -> error::Result<Vec<(graph::ExtrinsicHash<Self>, InclusionResult)>> {
+		match &self.inclusion_results {
+			Some(inclusion_results) => inclusion_results.inclusion_results(&*self.client, at),
+			None => Ok(Vec::new()),
+		}
+	}
+}
+
+/// Reads the results of the transactions included in blocks with the `TransactionInclusionApi`
+/// and decodes the errors of pallets with the metadata of the runtime.
+struct RuntimeInclusionResults;
+
+impl<Client, Block> InclusionResults<Client, Block> for RuntimeInclusionResults
+where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block> + BlockBackend<Block>,
+	Client::Api: TransactionInclusionApi<Block> + Metadata<Block>,
+{
+	fn inclusion_results(
+		&self,
+		client: &Client,
+		at: &BlockId<Block>,
+	) -> error::Result<Vec<(Block::Hash, InclusionResult)>> {
+		let runtime_api = client.runtime_api();
+		let has_api = runtime_api
+			.has_api::<dyn TransactionInclusionApi<Block>>(at)
+			.map_err(|e| Error::RuntimeApi(e.to_string()))?;
+		if !has_api {
+			return Ok(Vec::new())
+		}
+
+		let body = client
+			.block_body(at)?
+			.ok_or_else(|| Error::RuntimeApi(format!("Could not get body of block `{:?}`.", at)))?;
+		let results = runtime_api
+			.inclusion_results(at)
+			.map_err(|e| Error::RuntimeApi(e.to_string()))?;
+
+		// The metadata is only needed to decode the errors of pallets.
+		let failed_in_pallet = results
+			.iter()
+			.any(|result| matches!(result.result, Err(DispatchError::Module(_))));
+		let metadata = if failed_in_pallet {
+			let metadata =
+				runtime_api.metadata(at).map_err(|e| Error::RuntimeApi(e.to_string()))?;
+			match RuntimeMetadataPrefixed::decode(&mut &metadata[..]) {
+				Ok(RuntimeMetadataPrefixed(_, RuntimeMetadata::V14(metadata))) => Some(metadata),
+				Ok(_) => {
+					log::debug!(target: "txpool", "Unsupported metadata version at {:?}", at);
+					None
+				},
+				Err(e) => {
+					log::debug!(target: "txpool", "Failed to decode the metadata at {:?}: {}", at, e);
+					None
+				},
+			}
+		} else {
+			None
+		};
+
+		Ok(results
+			.into_iter()
+			.filter_map(|inclusion| {
+				let xt = body.get(inclusion.index as usize)?;
+				let module_error = match (&inclusion.result, &metadata) {
+					(Err(DispatchError::Module(error)), Some(metadata)) =>
+						module_error(metadata, error),
+					_ => None,
+				};
+				let hash = xt.using_encoded(<traits::HashFor<Block> as traits::Hash>::hash);
+				Some((hash, InclusionResult { inclusion, module_error }))
+			})
+			.collect())
+	}
+}
+
+/// Decode the pallet and the name of the error of a pallet from the metadata of the runtime.
+fn module_error(metadata: &RuntimeMetadataV14, error: &ModuleError) -> Option<DecodedModuleError> {
+	let pallet = metadata.pallets.iter().find(|pallet| pallet.index == error.index)?;
+	let variants = match metadata.types.resolve(pallet.error.as_ref()?.ty.id())?.type_def() {
+		TypeDef::Variant(errors) => errors.variants(),
+		_ => return None,
+	};
+	let variant = variants.iter().find(|variant| variant.index() == error.error[0])?;
+
+	Some(DecodedModuleError { pallet: pallet.name.clone(), error: variant.name().clone() })
 }
 
 /// Helper function to validate a transaction using a full chain API.
@@ -282,3 +405,43 @@ where
 		validate_transaction_blocking(&*self.client, at, source, uxt)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::v14::{ExtrinsicMetadata, PalletErrorMetadata, PalletMetadata};
+	use scale_info::meta_type;
+
+	#[allow(dead_code)]
+	#[derive(scale_info::TypeInfo)]
+	enum Error {
+		First,
+		Second,
+	}
+
+	#[test]
+	fn should_decode_module_errors_from_metadata() {
+		let pallet = PalletMetadata {
+			name: "Test",
+			storage: None,
+			calls: None,
+			event: None,
+			constants: Vec::new(),
+			error: Some(PalletErrorMetadata { ty: meta_type::<Error>() }),
+			index: 3,
+		};
+		let extrinsic =
+			ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: Vec::new() };
+		let metadata = RuntimeMetadataV14::new(vec![pallet], extrinsic, meta_type::<()>());
+		let error = |index, error| ModuleError { index, error: [error, 0, 0, 0], message: None };
+
+		assert_eq!(
+			module_error(&metadata, &error(3, 1)),
+			Some(DecodedModuleError { pallet: "Test".into(), error: "Second".into() }),
+		);
+		// unknown error of the pallet
+		assert_eq!(module_error(&metadata, &error(3, 2)), None);
+		// unknown pallet
+		assert_eq!(module_error(&metadata, &error(4, 0)), None);
+	}
+}
@@ -20,18 +20,27 @@
 //!
 //! For a more full-featured pool, have a look at the `pool` module.
 
-use std::{cmp::Ordering, collections::HashSet, fmt, hash, sync::Arc};
+use std::{
+	cmp::{Ordering, Reverse},
+	collections::{HashMap, HashSet},
+	fmt, hash,
+	sync::Arc,
+	time::Instant,
+};
 
 use log::{debug, trace, warn};
-use sc_transaction_pool_api::{error, InPoolTransaction, PoolStatus};
+use sc_transaction_pool_api::{
+	error, ChainTransactions, InPoolTransaction, InspectedTransaction, PoolInspection, PoolStatus,
+};
 use serde::Serialize;
-use sp_core::hexdisplay::HexDisplay;
+use sp_core::{hexdisplay::HexDisplay, Bytes};
 use sp_runtime::{
 	traits::Member,
 	transaction_validity::{
 		TransactionLongevity as Longevity, TransactionPriority as Priority,
 		TransactionSource as Source, TransactionTag as Tag,
 	},
+	Percent,
 };
 
 use super::{
@@ -71,6 +80,17 @@ impl<Hash, Ex> Imported<Hash, Ex> {
 	}
 }
 
+/// Result of replacing a transaction.
+#[derive(Debug)]
+pub struct Replaced<Hash, Ex> {
+	/// Import of the replacement transaction.
+	pub imported: Imported<Hash, Ex>,
+	/// Transactions that depended on the replaced one and were imported again.
+	pub restored: Vec<Imported<Hash, Ex>>,
+	/// The replaced transaction and the ones depending on it that could not be imported again.
+	pub removed: Vec<Arc<Transaction<Hash, Ex>>>,
+}
+
 /// Status of pruning the queue.
 #[derive(Debug)]
 pub struct PruneStatus<Hash, Ex> {
@@ -104,6 +124,13 @@ pub struct Transaction<Hash, Extrinsic> {
 	pub propagate: bool,
 	/// Source of that transaction.
 	pub source: Source,
+	/// Transactions of the bundle that transaction is part of.
+	#[ignore_malloc_size_of = "shared with the other transactions of the bundle"]
+	pub bundle: Option<Arc<[Hash]>>,
+	/// Order of arrival of the transaction in the pool (lower = earlier).
+	///
+	/// Assigned when the transaction is imported for the first time, `0` before.
+	pub arrival: u64,
 }
 
 impl<Hash, Extrinsic> AsRef<Extrinsic> for Transaction<Hash, Extrinsic> {
@@ -143,6 +170,14 @@ impl<Hash, Extrinsic> InPoolTransaction for Transaction<Hash, Extrinsic> {
 	fn is_propagable(&self) -> bool {
 		self.propagate
 	}
+
+	fn bundle(&self) -> Option<&[Hash]> {
+		self.bundle.as_deref()
+	}
+
+	fn arrival(&self) -> u64 {
+		self.arrival
+	}
 }
 
 impl<Hash: Clone, Extrinsic: Clone> Transaction<Hash, Extrinsic> {
@@ -162,6 +197,8 @@ impl<Hash: Clone, Extrinsic: Clone> Transaction<Hash, Extrinsic> {
 			requires: self.requires.clone(),
 			provides: self.provides.clone(),
 			propagate: self.propagate,
+			bundle: self.bundle.clone(),
+			arrival: self.arrival,
 		}
 	}
 }
@@ -194,6 +231,37 @@ where
 	}
 }
 
+/// Checks that a transaction with the `new` priority can replace transactions with the `old`
+/// (collective) priority.
+///
+/// The priority needs to be higher by at least `bump`, so that replacing a transaction and
+/// propagating the replacement is not almost free.
+pub fn check_replacement_priority(
+	old: Priority,
+	new: Priority,
+	bump: Percent,
+) -> error::Result<()> {
+	if old >= new {
+		return Err(error::Error::TooLowPriority { old, new })
+	}
+
+	let required = old.saturating_add(bump.mul_ceil(old));
+	if new < required {
+		return Err(error::Error::TooLowPriorityIncrease { required, new })
+	}
+
+	Ok(())
+}
+
+/// Transactions that are included in a block together, in order, or not at all.
+#[derive(Debug, Clone)]
+struct Bundle<Hash> {
+	/// Hashes of the transactions, in order.
+	transactions: Arc<[Hash]>,
+	/// Priority of all the transactions of the bundle.
+	priority: Priority,
+}
+
 /// Store last pruned tags for given number of invocations.
 const RECENTLY_PRUNED_TAGS: usize = 2;
 
@@ -210,6 +278,9 @@ const RECENTLY_PRUNED_TAGS: usize = 2;
 #[derive(Debug, parity_util_mem::MallocSizeOf)]
 pub struct BasePool<Hash: hash::Hash + Eq, Ex> {
 	reject_future_transactions: bool,
+	/// Minimum increase of the priority required to replace transactions.
+	#[ignore_malloc_size_of = "no heap allocations"]
+	replacement_bump: Percent,
 	future: FutureTransactions<Hash, Ex>,
 	ready: ReadyTransactions<Hash, Ex>,
 	/// Store recently pruned tags (for last two invocations).
@@ -218,23 +289,38 @@ pub struct BasePool<Hash: hash::Hash + Eq, Ex> {
 	/// transactions to future in case they were just stuck in verification.
 	recently_pruned: [HashSet<Tag>; RECENTLY_PRUNED_TAGS],
 	recently_pruned_index: usize,
+	/// Bundles of the transactions in the pool, by hash of their transactions.
+	///
+	/// Kept until the pool notices that a bundle is not complete anymore, so that the
+	/// transactions stay bundled when they are imported again.
+	#[ignore_malloc_size_of = "small and shared between the transactions of a bundle"]
+	bundles: HashMap<Hash, Bundle<Hash>>,
+	/// Arrival of the last transaction imported for the first time.
+	last_arrival: u64,
+	/// Senders of the transactions in the pool.
+	senders: Senders<Hash>,
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> Default for BasePool<Hash, Ex> {
 	fn default() -> Self {
-		Self::new(false)
+		Self::new(false, Percent::zero())
 	}
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash, Ex> {
-	/// Create new pool given reject_future_transactions flag.
-	pub fn new(reject_future_transactions: bool) -> Self {
+	/// Create new pool given reject_future_transactions flag and the minimum increase of the
+	/// priority required to replace transactions.
+	pub fn new(reject_future_transactions: bool, replacement_bump: Percent) -> Self {
 		Self {
 			reject_future_transactions,
+			replacement_bump,
 			future: Default::default(),
 			ready: Default::default(),
 			recently_pruned: Default::default(),
 			recently_pruned_index: 0,
+			bundles: Default::default(),
+			last_arrival: 0,
+			senders: Default::default(),
 		}
 	}
 
@@ -266,11 +352,22 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	/// other transactions in the pool.
 	/// The latter contains transactions that have all the requirements satisfied and are
 	/// ready to be included in the block.
-	pub fn import(&mut self, tx: Transaction<Hash, Ex>) -> error::Result<Imported<Hash, Ex>> {
+	pub fn import(&mut self, mut tx: Transaction<Hash, Ex>) -> error::Result<Imported<Hash, Ex>> {
 		if self.is_imported(&tx.hash) {
 			return Err(error::Error::AlreadyImported(Box::new(tx.hash)))
 		}
 
+		if tx.arrival == 0 {
+			self.last_arrival += 1;
+			tx.arrival = self.last_arrival;
+		}
+
+		if let Some(bundle) = self.bundles.get(&tx.hash) {
+			tx.priority = bundle.priority;
+			tx.propagate = false;
+			tx.bundle = Some(bundle.transactions.clone());
+		}
+
 		let tx = WaitingTransaction::new(tx, self.ready.provided_tags(), &self.recently_pruned);
 		trace!(target: "txpool", "[{:?}] {:?}", tx.transaction.hash, tx);
 		debug!(
@@ -287,11 +384,73 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			}
 
 			let hash = tx.transaction.hash.clone();
+			self.senders.insert(&tx.transaction);
 			self.future.import(tx);
 			return Ok(Imported::Future { hash })
 		}
 
-		self.import_to_ready(tx)
+		// The sender is only registered once the ready queue took the transaction: a rejected
+		// replacement must not take over the tags of the transaction it failed to replace.
+		let transaction = tx.transaction.clone();
+		let imported = self.import_to_ready(tx)?;
+		self.senders.insert(&transaction);
+		Ok(imported)
+	}
+
+	/// Imports a bundle of transactions to the pool.
+	///
+	/// All the transactions of the bundle get the lowest priority among them and are not
+	/// propagated. If any of them can't be imported, none of them stays in the pool.
+	pub fn import_bundle(
+		&mut self,
+		txs: Vec<Transaction<Hash, Ex>>,
+	) -> error::Result<Vec<Imported<Hash, Ex>>> {
+		let priority = txs.iter().map(|tx| tx.priority).min().ok_or(error::Error::EmptyBundle)?;
+		let transactions = txs.iter().map(|tx| tx.hash.clone()).collect::<Arc<[_]>>();
+		if let Some(hash) = transactions.iter().find(|hash| self.is_imported(hash)) {
+			return Err(error::Error::AlreadyImported(Box::new(hash.clone())))
+		}
+
+		let bundle = Bundle { transactions: transactions.clone(), priority };
+		for hash in transactions.iter() {
+			self.bundles.insert(hash.clone(), bundle.clone());
+		}
+
+		let mut imported = Vec::with_capacity(txs.len());
+		for tx in txs {
+			match self.import(tx) {
+				Ok(tx) => imported.push(tx),
+				Err(e) => {
+					self.remove_subtree(&transactions);
+					transactions.iter().for_each(|hash| {
+						self.bundles.remove(hash);
+					});
+					return Err(e)
+				},
+			}
+		}
+
+		Ok(imported)
+	}
+
+	/// Removes the transactions of the bundles that lost some of their transactions, and
+	/// forgets the bundles without any transaction in the pool.
+	///
+	/// Returns the removed transactions, including the ones depending on them.
+	pub fn remove_incomplete_bundles(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		let incomplete = self
+			.bundles
+			.values()
+			.filter(|bundle| !bundle.transactions.iter().all(|hash| self.is_imported(hash)))
+			.flat_map(|bundle| bundle.transactions.iter().cloned())
+			.collect::<HashSet<_>>()
+			.into_iter()
+			.collect::<Vec<_>>();
+		incomplete.iter().for_each(|hash| {
+			self.bundles.remove(hash);
+		});
+
+		self.remove_subtree(&incomplete)
 	}
 
 	/// Imports transaction to ready queue.
@@ -316,7 +475,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 
 			// import this transaction
 			let current_hash = tx.transaction.hash.clone();
-			match self.ready.import(tx) {
+			match self.ready.import(tx, self.replacement_bump) {
 				Ok(mut replaced) => {
 					if !first {
 						promoted.push(current_hash);
@@ -329,8 +488,13 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 				Err(e) =>
 					if first {
 						debug!(target: "txpool", "[{:?}] Error importing: {:?}", current_hash, e);
+						// The transactions it unlocked already left the future queue.
+						for tx in to_import {
+							self.senders.remove(&tx.transaction.hash);
+						}
 						return Err(e)
 					} else {
+						self.senders.remove(&current_hash);
 						failed.push(current_hash);
 					},
 			}
@@ -342,10 +506,13 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		// future transactions pushed out current transaction.
 		// This means that there is a cycle and the transactions should
 		// be moved back to future, since we can't resolve it.
+		removed.iter().for_each(|tx| self.senders.remove(&tx.hash));
 		if removed.iter().any(|tx| tx.hash == hash) {
 			// We still need to remove all transactions that we promoted
 			// since they depend on each other and will never get to the best iterator.
-			self.ready.remove_subtree(&promoted);
+			for tx in self.ready.remove_subtree(&promoted) {
+				self.senders.remove(&tx.hash);
+			}
 
 			debug!(target: "txpool", "[{:?}] Cycle detected, bailing.", hash);
 			return Err(error::Error::CycleDetected)
@@ -354,6 +521,62 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		Ok(Imported::Ready { hash, promoted, failed, removed })
 	}
 
+	/// Replaces the transaction `old` with `tx`.
+	///
+	/// The replacement needs to provide at least one of the tags of the replaced transaction, so
+	/// that only a transaction taking the same slot (like the same nonce of an account) can
+	/// replace it, and to increase the priority by the minimum bump. Unlike a replacement through
+	/// the tags, this also works for transactions in the future queue. Transactions that depend on
+	/// the replaced one are imported again and only returned among the removed ones if that fails.
+	/// If `tx` can't be imported, the pool is left as it was.
+	pub fn replace(
+		&mut self,
+		old: &Hash,
+		tx: Transaction<Hash, Ex>,
+	) -> error::Result<Replaced<Hash, Ex>>
+	where
+		Ex: Clone,
+	{
+		let replaced = self
+			.by_hashes(std::slice::from_ref(old))
+			.pop()
+			.flatten()
+			.ok_or(error::Error::ReplacedTransactionNotFound)?;
+		if !tx.provides.iter().any(|tag| replaced.provides.contains(tag)) {
+			return Err(error::Error::InvalidReplacement)
+		}
+		check_replacement_priority(replaced.priority, tx.priority, self.replacement_bump)?;
+
+		let removed = self.remove_subtree(std::slice::from_ref(old));
+		let imported = match self.import(tx) {
+			Ok(imported) => imported,
+			Err(e) => {
+				// The replaced transaction comes first, so the dependent ones can be imported
+				// again after it.
+				for tx in removed {
+					if let Err(e) = self.import(tx.duplicate()) {
+						warn!(target: "txpool", "[{:?}] Failed to restore: {:?}", tx.hash, e);
+					}
+				}
+				return Err(e)
+			},
+		};
+
+		let mut restored = vec![];
+		let mut removed_for_good = vec![replaced];
+		for tx in removed.into_iter().filter(|tx| tx.hash != *old) {
+			match self.import(tx.duplicate()) {
+				Ok(imported) => restored.push(imported),
+				Err(e) => {
+					debug!(target: "txpool", "[{:?}] Dropped after replacement: {:?}", tx.hash, e);
+					removed_for_good.push(tx);
+				},
+			}
+		}
+
+		Ok(Replaced { imported, restored, removed: removed_for_good })
+	}
+
 	/// Returns an iterator over ready transactions in the pool.
 	pub fn ready(&self) -> BestIterator<Hash, Ex> {
 		self.ready.get()
@@ -383,63 +606,243 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	/// Makes sure that the transactions in the queues stay within provided limits.
 	///
 	/// Removes and returns worst transactions from the queues and all transactions that depend on
-	/// them. Technically the worst transaction should be evaluated by computing the entire pending
-	/// set. We use a simplified approach to remove transactions with the lowest priority first or
-	/// those that occupy the pool for the longest time in case priority is the same.
+	/// them, together with the reason of their eviction. The limits are enforced in order:
+	/// senders that got more than `sender_limit` transactions since the last call lose their
+	/// latest ones first, then the transactions of each source are kept within their quota of the
+	/// queues and finally the queues are kept within the `ready` and `future` limits.
+	///
+	/// Checking the limits only reads counters that are kept up to date as transactions enter and
+	/// leave the queues, the queues are only scanned for the worst transaction once a limit is
+	/// exceeded.
+	///
+	/// Technically the worst transaction should be evaluated by computing the entire pending
+	/// set. We use a simplified approach instead of a score combining priority, age and the share
+	/// of the sender: transactions of senders that hold more than an average share of the pool
+	/// are removed first, whatever their priority. Among the candidates the ones with the lowest
+	/// priority go first, then the latest ones of their sender and then those that entered the
+	/// queue most recently. So a sender over its share loses its transactions before a fair
+	/// sender loses any, even one with a lower priority.
 	pub fn enforce_limits(
 		&mut self,
 		ready: &Limit,
 		future: &Limit,
-	) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		sender_limit: Option<usize>,
+		quotas: &SourceQuotas,
+	) -> Vec<(Arc<Transaction<Hash, Ex>>, EvictionReason)> {
 		let mut removed = vec![];
 
-		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
-			// find the worst transaction
-			let worst = self.ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
+		let excess = self.senders.take_excess(sender_limit);
+		if !excess.is_empty() {
+			removed.extend(self.evict(&excess, EvictionReason::SenderLimit));
+		}
+
+		for source in [Source::InBlock, Source::Local, Source::External] {
+			let quota = quotas.get(source);
+			if quota.is_one() {
+				continue
+			}
+
+			let (ready, future) = (ready.share(quota), future.share(quota));
+			while let Some(worst) = self.worst_exceeding(&ready, &future, Some(source)) {
+				removed.extend(self.evict(&[worst], EvictionReason::SourceQuota));
+			}
+		}
+
+		while let Some(worst) = self.worst_exceeding(ready, future, None) {
+			removed.extend(self.evict(&[worst], EvictionReason::Limit));
+		}
+
+		removed
+	}
+
+	/// Returns the worst transaction of a queue in which the transactions of `source`, or all of
+	/// them, exceed the given limit.
+	fn worst_exceeding(
+		&mut self,
+		ready: &Limit,
+		future: &Limit,
+		source: Option<Source>,
+	) -> Option<Hash> {
+		let filter = |tx: &Transaction<Hash, Ex>| source.map_or(true, |source| tx.source == source);
+
+		let (count, bytes) = match source {
+			Some(source) => self.ready.usage(source),
+			None => (self.ready.len(), self.ready.bytes()),
+		};
+		if ready.is_exceeded(count, bytes) {
+			return self.worst_ready(&filter)
+		}
+
+		let (count, bytes) = match source {
+			Some(source) => self.future.usage(source),
+			None => (self.future.len(), self.future.bytes()),
+		};
+		if future.is_exceeded(count, bytes) {
+			return self.worst_future(&filter)
+		}
+
+		None
+	}
+
+	/// Returns the worst ready transaction matching `filter`.
+	fn worst_ready(&mut self, filter: &dyn Fn(&Transaction<Hash, Ex>) -> bool) -> Option<Hash> {
+		let (ready, senders) = (&mut self.ready, &self.senders);
+		let candidates = |tx: &Transaction<Hash, Ex>| filter(tx) && senders.is_over_share(&tx.hash);
+		let mut worst = |filter: &dyn Fn(&Transaction<Hash, Ex>) -> bool| {
+			ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
 				let transaction = &current.transaction;
+				if !filter(&transaction.transaction) {
+					return worst
+				}
+
 				worst
 					.map(|worst| {
 						// Here we don't use `TransactionRef`'s ordering implementation because
 						// while it prefers priority like need here, it also prefers older
 						// transactions for inclusion purposes and limit enforcement needs to prefer
-						// newer transactions instead and drop the older ones.
-						match worst.transaction.priority.cmp(&transaction.transaction.priority) {
+						// newer transactions instead and drop the older ones. Latest transactions
+						// of a sender are dropped before the ones they depend on though, so that
+						// the whole chain of the sender is not lost at once.
+						let depth =
+							|tx: &TransactionRef<Hash, Ex>| senders.depth(&tx.transaction.hash);
+						let order = worst
+							.transaction
+							.priority
+							.cmp(&transaction.transaction.priority)
+							.then_with(|| depth(transaction).cmp(&depth(&worst)))
+							.then_with(|| worst.insertion_id.cmp(&transaction.insertion_id));
+						match order {
 							Ordering::Less => worst,
-							Ordering::Equal =>
-								if worst.insertion_id > transaction.insertion_id {
-									transaction.clone()
-								} else {
-									worst
-								},
-							Ordering::Greater => transaction.clone(),
+							_ => transaction.clone(),
 						}
 					})
 					.or_else(|| Some(transaction.clone()))
-			});
+			})
+		};
 
-			if let Some(worst) = worst {
-				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
-			} else {
-				break
-			}
-		}
+		worst(&candidates)
+			.or_else(|| worst(filter))
+			.map(|worst| worst.transaction.hash.clone())
+	}
 
-		while future.is_exceeded(self.future.len(), self.future.bytes()) {
-			// find the worst transaction
-			let worst = self.future.fold(|worst, current| match worst {
-				None => Some(current.clone()),
-				Some(ref tx) if tx.imported_at > current.imported_at => Some(current.clone()),
-				other => other,
-			});
-
-			if let Some(worst) = worst {
-				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
-			} else {
-				break
-			}
+	/// Returns the worst future transaction matching `filter`.
+	fn worst_future(&mut self, filter: &dyn Fn(&Transaction<Hash, Ex>) -> bool) -> Option<Hash> {
+		let (future, senders) = (&mut self.future, &self.senders);
+		let candidates = |tx: &Transaction<Hash, Ex>| filter(tx) && senders.is_over_share(&tx.hash);
+		let mut worst = |filter: &dyn Fn(&Transaction<Hash, Ex>) -> bool| {
+			future.fold(|worst, current| {
+				if !filter(&current.transaction) {
+					return worst
+				}
+
+				let depth = |tx: &WaitingTransaction<Hash, Ex>| senders.depth(&tx.transaction.hash);
+				match worst {
+					None => Some(current.clone()),
+					Some(ref tx)
+						if depth(current)
+							.cmp(&depth(tx))
+							.then_with(|| tx.imported_at.cmp(&current.imported_at)) ==
+							Ordering::Greater =>
+						Some(current.clone()),
+					other => other,
+				}
+			})
+		};
+
+		worst(&candidates)
+			.or_else(|| worst(filter))
+			.map(|worst| worst.transaction.hash.clone())
+	}
+
+	/// Removes the transactions and their dependencies, marking them with the eviction `reason`.
+	fn evict(
+		&mut self,
+		hashes: &[Hash],
+		reason: EvictionReason,
+	) -> impl Iterator<Item = (Arc<Transaction<Hash, Ex>>, EvictionReason)> {
+		self.remove_subtree(hashes).into_iter().map(move |tx| (tx, reason))
+	}
+
+	/// Returns the details of all the transactions in the pool.
+	pub fn inspect(&self) -> PoolInspection<Hash> {
+		let ready = self.ready.all();
+		let future = self.future.waiting().collect::<Vec<_>>();
+		let transactions = ready
+			.iter()
+			.map(|tx| &*tx.transaction.transaction)
+			.chain(future.iter().map(|tx| &*tx.transaction))
+			.collect::<Vec<_>>();
+
+		let mut providers = HashMap::<_, Vec<_>>::new();
+		let mut dependents = HashMap::<_, Vec<_>>::new();
+		for tx in &transactions {
+			tx.provides
+				.iter()
+				.for_each(|tag| providers.entry(tag).or_default().push(&tx.hash));
+			tx.requires
+				.iter()
+				.for_each(|tag| dependents.entry(tag).or_default().push(&tx.hash));
 		}
+		let linked = |tags: &[Tag], links: &HashMap<&Tag, Vec<&Hash>>| {
+			let mut seen = HashSet::new();
+			tags.iter()
+				.flat_map(|tag| links.get(tag).into_iter().flatten())
+				.filter(|hash| seen.insert(**hash))
+				.map(|hash| (*hash).clone())
+				.collect::<Vec<_>>()
+		};
+		let inspect =
+			|tx: &Transaction<Hash, Ex>, imported_at: Instant, missing_tags: Vec<Bytes>| {
+				InspectedTransaction {
+					hash: tx.hash.clone(),
+					priority: tx.priority,
+					valid_till: tx.valid_till,
+					bytes: tx.bytes,
+					requires: tx.requires.iter().cloned().map(Into::into).collect(),
+					provides: tx.provides.iter().cloned().map(Into::into).collect(),
+					missing_tags,
+					source: tx.source,
+					time_in_pool: imported_at.elapsed().as_millis() as u64,
+					unlocked_by: linked(&tx.requires, &providers),
+					unlocks: linked(&tx.provides, &dependents),
+					chain: self
+						.senders
+						.of
+						.get(&tx.hash)
+						.map_or_else(|| tx.hash.clone(), |of| of.sender.clone()),
+				}
+			};
+
+		let mut ready = ready
+			.iter()
+			.map(|tx| inspect(&tx.transaction.transaction, tx.imported_at, vec![]))
+			.collect::<Vec<_>>();
+		ready.sort_by(|a, b| {
+			b.priority.cmp(&a.priority).then_with(|| b.time_in_pool.cmp(&a.time_in_pool))
+		});
+		let mut future = future
+			.iter()
+			.map(|tx| {
+				let missing_tags = tx.missing_tags.iter().cloned().map(Into::into).collect();
+				inspect(&tx.transaction, tx.imported_at, missing_tags)
+			})
+			.collect::<Vec<_>>();
+		future.sort_by_key(|tx| Reverse(tx.time_in_pool));
+
+		let mut counts = HashMap::<_, (usize, usize)>::new();
+		ready.iter().for_each(|tx| counts.entry(&tx.chain).or_default().0 += 1);
+		future.iter().for_each(|tx| counts.entry(&tx.chain).or_default().1 += 1);
+		let mut chains = counts
+			.into_iter()
+			.map(|(first, (ready, future))| ChainTransactions {
+				first: first.clone(),
+				ready,
+				future,
+			})
+			.collect::<Vec<_>>();
+		chains.sort_by_key(|chain| Reverse(chain.ready + chain.future));
 
-		removed
+		PoolInspection { ready, future, chains }
 	}
 
 	/// Removes all transactions represented by the hashes and all other transactions
@@ -453,12 +856,15 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	pub fn remove_subtree(&mut self, hashes: &[Hash]) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		let mut removed = self.ready.remove_subtree(hashes);
 		removed.extend(self.future.remove(hashes));
+		removed.iter().for_each(|tx| self.senders.remove(&tx.hash));
 		removed
 	}
 
 	/// Removes and returns all transactions from the future queue.
 	pub fn clear_future(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
-		self.future.clear()
+		let removed = self.future.clear();
+		removed.iter().for_each(|tx| self.senders.remove(&tx.hash));
+		removed
 	}
 
 	/// Prunes transactions that provide given list of tags.
@@ -491,10 +897,12 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 				Ok(res) => promoted.push(res),
 				Err(e) => {
 					warn!(target: "txpool", "[{:?}] Failed to promote during pruning: {:?}", hash, e);
+					self.senders.remove(&hash);
 					failed.push(hash)
 				},
 			}
 		}
+		pruned.iter().for_each(|tx| self.senders.remove(&tx.hash));
 
 		PruneStatus { pruned, failed, promoted }
 	}
@@ -510,6 +918,279 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	}
 }
 
+/// Reason of evicting a transaction from the pool in [`BasePool::enforce_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+	/// The ready or future queue exceeded its limit.
+	Limit,
+	/// The sender of the transaction had more transactions in the pool than allowed.
+	SenderLimit,
+	/// The transactions of the source of the transaction exceeded their quota.
+	SourceQuota,
+}
+
+/// Transactions of the pool grouped by sender.
+///
+/// The pool knows nothing about accounts, so senders are approximated with the tags: a
+/// transaction that requires a tag provided by another transaction in the pool (like the previous
+/// nonce of an account) has the same sender as that transaction, any other one starts a new
+/// sender. The senders are kept up to date as transactions enter and leave the pool, so a sender
+/// keeps its transactions when the first one is included in a block, and a transaction joins the
+/// sender of the transactions that were waiting for it.
+#[derive(Debug, parity_util_mem::MallocSizeOf)]
+struct Senders<Hash: hash::Hash + Eq> {
+	/// Transaction providing each tag.
+	providers: HashMap<Tag, Hash>,
+	/// First transactions of senders, by the tags they require that were not provided.
+	wanting: HashMap<Tag, HashSet<Hash>>,
+	/// Sender of each transaction.
+	of: HashMap<Hash, SenderOf<Hash>>,
+	/// Transactions of each sender.
+	transactions: HashMap<Hash, HashSet<Hash>>,
+	/// Senders that got new transactions since the sender limit was last enforced.
+	grown: HashSet<Hash>,
+}
+
+/// Sender of a transaction in the pool.
+#[derive(Debug, parity_util_mem::MallocSizeOf)]
+struct SenderOf<Hash> {
+	/// Sender, identified by its first transaction.
+	sender: Hash,
+	/// Depth of the transaction in the chain of its sender.
+	depth: usize,
+	/// Tags required by the transaction.
+	requires: Vec<Tag>,
+	/// Tags provided by the transaction.
+	provides: Vec<Tag>,
+}
+
+impl<Hash: hash::Hash + Eq> Default for Senders<Hash> {
+	fn default() -> Self {
+		Self {
+			providers: Default::default(),
+			wanting: Default::default(),
+			of: Default::default(),
+			transactions: Default::default(),
+			grown: Default::default(),
+		}
+	}
+}
+
+impl<Hash: hash::Hash + Member> Senders<Hash> {
+	/// Adds a transaction that entered the pool.
+	fn insert<Ex>(&mut self, tx: &Transaction<Hash, Ex>) {
+		let parent = tx
+			.requires
+			.iter()
+			.find_map(|tag| self.providers.get(tag))
+			.and_then(|parent| self.of.get(parent));
+		let (sender, depth) = match parent {
+			Some(parent) => (parent.sender.clone(), parent.depth + 1),
+			None => {
+				for tag in &tx.requires {
+					self.wanting.entry(tag.clone()).or_default().insert(tx.hash.clone());
+				}
+				(tx.hash.clone(), 0)
+			},
+		};
+
+		for tag in &tx.provides {
+			self.providers.insert(tag.clone(), tx.hash.clone());
+		}
+		self.transactions.entry(sender.clone()).or_default().insert(tx.hash.clone());
+		self.grown.insert(sender.clone());
+		self.of.insert(
+			tx.hash.clone(),
+			SenderOf {
+				sender: sender.clone(),
+				depth,
+				requires: tx.requires.clone(),
+				provides: tx.provides.clone(),
+			},
+		);
+
+		// The senders that were waiting for the transaction continue its chain.
+		let waiting = tx
+			.provides
+			.iter()
+			.filter_map(|tag| self.wanting.remove(tag))
+			.flatten()
+			.collect::<Vec<_>>();
+		for first in waiting {
+			self.merge(&first, &sender, depth + 1);
+		}
+	}
+
+	/// Moves the transactions of the sender starting with `first` to `sender`, `depth` deeper.
+	fn merge(&mut self, first: &Hash, sender: &Hash, depth: usize) {
+		let requires = match self.of.get(first) {
+			Some(of) if of.sender == *first && first != sender => of.requires.clone(),
+			_ => return,
+		};
+		for tag in &requires {
+			self.unwant(tag, first);
+		}
+
+		let transactions = self.transactions.remove(first).unwrap_or_default();
+		for hash in &transactions {
+			if let Some(of) = self.of.get_mut(hash) {
+				of.sender = sender.clone();
+				of.depth += depth;
+			}
+		}
+		self.transactions.entry(sender.clone()).or_default().extend(transactions);
+		self.grown.remove(first);
+		self.grown.insert(sender.clone());
+	}
+
+	/// Removes a transaction that left the pool.
+	fn remove(&mut self, hash: &Hash) {
+		let of = match self.of.remove(hash) {
+			Some(of) => of,
+			None => return,
+		};
+
+		for tag in &of.provides {
+			// Another transaction may provide the tag now, like a replacement.
+			if self.providers.get(tag) == Some(hash) {
+				self.providers.remove(tag);
+			}
+		}
+		for tag in &of.requires {
+			self.unwant(tag, hash);
+		}
+		if let Some(transactions) = self.transactions.get_mut(&of.sender) {
+			transactions.remove(hash);
+			if transactions.is_empty() {
+				self.transactions.remove(&of.sender);
+				self.grown.remove(&of.sender);
+			}
+		}
+	}
+
+	/// Forgets that the transaction `hash` waits for `tag`.
+	fn unwant(&mut self, tag: &Tag, hash: &Hash) {
+		if let Some(wanting) = self.wanting.get_mut(tag) {
+			wanting.remove(hash);
+			if wanting.is_empty() {
+				self.wanting.remove(tag);
+			}
+		}
+	}
+
+	/// Returns the depth of the transaction in the chain of its sender.
+	fn depth(&self, hash: &Hash) -> usize {
+		self.of.get(hash).map_or(0, |of| of.depth)
+	}
+
+	/// Returns true if the sender of the transaction has more transactions than an average one.
+	fn is_over_share(&self, hash: &Hash) -> bool {
+		self.of
+			.get(hash)
+			.and_then(|of| self.transactions.get(&of.sender))
+			.map_or(false, |transactions| {
+				transactions.len() * self.transactions.len() > self.of.len()
+			})
+	}
+
+	/// Returns the latest transactions of the senders that got more than `limit` of them since
+	/// the last call.
+	fn take_excess(&mut self, limit: Option<usize>) -> Vec<Hash> {
+		let grown = std::mem::take(&mut self.grown);
+		let limit = match limit {
+			Some(limit) => limit,
+			None => return Vec::new(),
+		};
+
+		grown
+			.iter()
+			.filter_map(|sender| self.transactions.get(sender))
+			.filter(|transactions| transactions.len() > limit)
+			.flat_map(|transactions| {
+				let mut chain =
+					transactions.iter().map(|hash| (self.depth(hash), hash)).collect::<Vec<_>>();
+				chain.sort_by_key(|(depth, _)| *depth);
+				chain.into_iter().skip(limit).map(|(_, hash)| hash.clone())
+			})
+			.collect()
+	}
+}
+
+/// Number and size of the transactions of each source in a queue.
+#[derive(Debug, Default)]
+pub struct SourceUsage {
+	in_block: (usize, usize),
+	local: (usize, usize),
+	external: (usize, usize),
+}
+
+impl SourceUsage {
+	fn of(&mut self, source: Source) -> &mut (usize, usize) {
+		match source {
+			Source::InBlock => &mut self.in_block,
+			Source::Local => &mut self.local,
+			Source::External => &mut self.external,
+		}
+	}
+
+	/// Accounts for a transaction entering the queue.
+	pub fn add<Hash, Ex>(&mut self, tx: &Transaction<Hash, Ex>) {
+		let (count, bytes) = self.of(tx.source);
+		*count += 1;
+		*bytes += tx.bytes;
+	}
+
+	/// Accounts for a transaction leaving the queue.
+	pub fn remove<Hash, Ex>(&mut self, tx: &Transaction<Hash, Ex>) {
+		let (count, bytes) = self.of(tx.source);
+		*count = count.saturating_sub(1);
+		*bytes = bytes.saturating_sub(tx.bytes);
+	}
+
+	/// Returns the number and size of the transactions of `source`.
+	pub fn get(&self, source: Source) -> (usize, usize) {
+		match source {
+			Source::InBlock => self.in_block,
+			Source::Local => self.local,
+			Source::External => self.external,
+		}
+	}
+
+	/// Returns the number and size of all the transactions.
+	pub fn total(&self) -> (usize, usize) {
+		let sum = |a: (usize, usize), b: (usize, usize)| (a.0 + b.0, a.1 + b.1);
+		sum(sum(self.in_block, self.local), self.external)
+	}
+}
+
+/// Share of the queue limits that the transactions of each source may take.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceQuotas {
+	/// Quota of the transactions from retracted blocks.
+	pub in_block: Percent,
+	/// Quota of the transactions submitted by the node itself.
+	pub local: Percent,
+	/// Quota of the transactions received from the network or through RPC.
+	pub external: Percent,
+}
+
+impl SourceQuotas {
+	/// Returns the quota of the given source.
+	pub fn get(&self, source: Source) -> Percent {
+		match source {
+			Source::InBlock => self.in_block,
+			Source::Local => self.local,
+			Source::External => self.external,
+		}
+	}
+}
+
+impl Default for SourceQuotas {
+	fn default() -> Self {
+		Self { in_block: Percent::one(), local: Percent::one(), external: Percent::one() }
+	}
+}
+
 /// Queue limits
 #[derive(Debug, Clone)]
 pub struct Limit {
@@ -524,6 +1205,11 @@ impl Limit {
 	pub fn is_exceeded(&self, count: usize, bytes: usize) -> bool {
 		self.count < count || self.total_bytes < bytes
 	}
+
+	/// Returns the given share of the limit.
+	pub fn share(&self, share: Percent) -> Self {
+		Self { count: share.mul_floor(self.count), total_bytes: share.mul_floor(self.total_bytes) }
+	}
 }
 
 #[cfg(test)]
@@ -546,8 +1232,34 @@ mod tests {
 		provides: vec![],
 		propagate: true,
 		source: Source::External,
+		bundle: None,
+		arrival: 0,
 	};
 
+	#[test]
+	fn should_keep_order_of_arrival_when_imported_again() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction { data: vec![1u8], provides: vec![vec![1]], ..DEFAULT_TX.clone() })
+			.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed = pool.remove_subtree(&[1]);
+		pool.import(removed[0].duplicate()).unwrap();
+
+		// then
+		let mut arrivals = pool.ready().map(|tx| (tx.hash, tx.arrival)).collect::<Vec<_>>();
+		arrivals.sort();
+		assert_eq!(arrivals, vec![(1, 1), (2, 2)]);
+	}
+
 	#[test]
 	fn should_import_transaction_to_ready() {
 		// given
@@ -1060,4 +1772,405 @@ source: TransactionSource::External, requires: [03, 02], provides: [04], data: [
 		assert_eq!(pool.reject_future_transactions, true);
 		assert_eq!(pool.future.len(), 1);
 	}
+
+	fn evicted(
+		removed: Vec<(Arc<Transaction<Hash, Vec<u8>>>, EvictionReason)>,
+	) -> Vec<(Hash, EvictionReason)> {
+		let mut removed =
+			removed.into_iter().map(|(tx, reason)| (tx.hash, reason)).collect::<Vec<_>>();
+		removed.sort_by_key(|(hash, _)| *hash);
+		removed
+	}
+
+	#[test]
+	fn should_limit_transactions_of_a_sender() {
+		// given
+		let mut pool = pool();
+		let unlimited = Limit { count: 100, total_bytes: 100 };
+		for nonce in 1u8..=3 {
+			pool.import(Transaction {
+				data: vec![nonce],
+				hash: nonce as u64,
+				requires: if nonce > 1 { vec![vec![nonce - 1]] } else { vec![] },
+				provides: vec![vec![nonce]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+		pool.import(Transaction {
+			data: vec![10u8],
+			hash: 10,
+			requires: vec![vec![10]],
+			provides: vec![vec![11]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![11u8],
+			hash: 11,
+			provides: vec![vec![10]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed =
+			pool.enforce_limits(&unlimited, &unlimited, Some(2), &SourceQuotas::default());
+
+		// then
+		assert_eq!(evicted(removed), vec![(3, EvictionReason::SenderLimit)]);
+		let mut ready = pool.ready().map(|tx| tx.hash).collect::<Vec<_>>();
+		ready.sort();
+		assert_eq!(ready, vec![1, 2, 10, 11]);
+	}
+
+	#[test]
+	fn should_limit_transactions_of_a_sender_imported_out_of_order() {
+		// given
+		let mut pool = pool();
+		let unlimited = Limit { count: 100, total_bytes: 100 };
+		for nonce in [3u8, 2, 1] {
+			pool.import(Transaction {
+				data: vec![nonce],
+				hash: nonce as u64,
+				requires: if nonce > 1 { vec![vec![nonce - 1]] } else { vec![] },
+				provides: vec![vec![nonce]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+
+		// when
+		let removed =
+			pool.enforce_limits(&unlimited, &unlimited, Some(2), &SourceQuotas::default());
+
+		// then
+		assert_eq!(evicted(removed), vec![(3, EvictionReason::SenderLimit)]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2]);
+	}
+
+	#[test]
+	fn should_limit_transactions_of_a_sender_after_a_rejected_replacement() {
+		// given
+		let mut pool = pool();
+		let unlimited = Limit { count: 100, total_bytes: 100 };
+		let nonce = |nonce: u8| Transaction {
+			data: vec![nonce],
+			hash: nonce as u64,
+			priority: 10,
+			requires: if nonce > 1 { vec![vec![nonce - 1]] } else { vec![] },
+			provides: vec![vec![nonce]],
+			..DEFAULT_TX.clone()
+		};
+		pool.import(nonce(1)).unwrap();
+		let rejected =
+			pool.import(Transaction { data: vec![10u8], hash: 10, priority: 5, ..nonce(1) });
+		assert!(matches!(rejected, Err(error::Error::TooLowPriority { old: 10, new: 5 })));
+		pool.import(nonce(2)).unwrap();
+		pool.import(nonce(3)).unwrap();
+
+		// when
+		let removed =
+			pool.enforce_limits(&unlimited, &unlimited, Some(2), &SourceQuotas::default());
+
+		// then
+		assert_eq!(evicted(removed), vec![(3, EvictionReason::SenderLimit)]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2]);
+	}
+
+	#[test]
+	fn should_track_senders_and_sources_of_the_transactions_in_the_pool() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction { data: vec![1u8], provides: vec![vec![1]], ..DEFAULT_TX.clone() })
+			.unwrap();
+		pool.import(Transaction {
+			data: vec![3u8],
+			hash: 3,
+			bytes: 5,
+			requires: vec![vec![2]],
+			provides: vec![vec![3]],
+			source: Source::Local,
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		assert_eq!(pool.ready.usage(Source::External), (1, 1));
+		assert_eq!(pool.future.usage(Source::Local), (1, 5));
+		assert_eq!(pool.future.bytes(), 5);
+
+		// when
+		pool.prune_tags(vec![vec![1]]);
+		pool.clear_future();
+
+		// then
+		assert_eq!(pool.ready.usage(Source::External), (0, 0));
+		assert_eq!(pool.future.usage(Source::Local), (0, 0));
+		assert!(pool.senders.of.is_empty());
+		assert!(pool.senders.transactions.is_empty());
+		assert!(pool.senders.providers.is_empty());
+		assert!(pool.senders.wanting.is_empty());
+	}
+
+	#[test]
+	fn should_enforce_source_quotas() {
+		// given
+		let mut pool = pool();
+		let limit = Limit { count: 4, total_bytes: 100 };
+		let quotas = SourceQuotas { external: Percent::from_percent(50), ..Default::default() };
+		for hash in 1u8..=3 {
+			pool.import(Transaction {
+				data: vec![hash],
+				hash: hash as u64,
+				priority: 10 - hash as u64,
+				provides: vec![vec![hash]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+		pool.import(Transaction {
+			data: vec![4u8],
+			hash: 4,
+			priority: 1,
+			provides: vec![vec![4]],
+			source: Source::Local,
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed = pool.enforce_limits(&limit, &limit, None, &quotas);
+
+		// then
+		assert_eq!(evicted(removed), vec![(3, EvictionReason::SourceQuota)]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2, 4]);
+	}
+
+	#[test]
+	fn should_evict_transactions_of_senders_over_their_share_first() {
+		// given
+		let mut pool = pool();
+		let limit = Limit { count: 3, total_bytes: 100 };
+		for nonce in 1u8..=3 {
+			pool.import(Transaction {
+				data: vec![nonce],
+				hash: nonce as u64,
+				priority: 10,
+				requires: if nonce > 1 { vec![vec![nonce - 1]] } else { vec![] },
+				provides: vec![vec![nonce]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+		pool.import(Transaction {
+			data: vec![10u8],
+			hash: 10,
+			priority: 1,
+			provides: vec![vec![10]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed = pool.enforce_limits(&limit, &limit, None, &SourceQuotas::default());
+
+		// then
+		assert_eq!(evicted(removed), vec![(3, EvictionReason::Limit)]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2, 10]);
+	}
+
+	#[test]
+	fn should_evict_transactions_with_lowest_priority_of_fair_senders() {
+		// given
+		let mut pool = pool();
+		let limit = Limit { count: 2, total_bytes: 100 };
+		for hash in 1u8..=3 {
+			pool.import(Transaction {
+				data: vec![hash],
+				hash: hash as u64,
+				priority: if hash == 2 { 1 } else { 10 },
+				provides: vec![vec![hash]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+
+		// when
+		let removed = pool.enforce_limits(&limit, &limit, None, &SourceQuotas::default());
+
+		// then
+		assert_eq!(evicted(removed), vec![(2, EvictionReason::Limit)]);
+		assert_eq!(pool.ready().count(), 2);
+	}
+
+	#[test]
+	fn should_require_minimum_priority_increase_to_replace() {
+		// given
+		let mut pool = BasePool::new(false, Percent::from_percent(10));
+		pool.import(Transaction {
+			data: vec![1u8],
+			priority: 100,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let too_low = pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			priority: 105,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		});
+		let imported = pool.import(Transaction {
+			data: vec![3u8],
+			hash: 3,
+			priority: 110,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		});
+
+		// then
+		assert!(matches!(
+			too_low,
+			Err(error::Error::TooLowPriorityIncrease { required: 110, new: 105 })
+		));
+		assert!(
+			matches!(imported, Ok(Imported::Ready { hash: 3, ref removed, .. }) if removed.len() == 1)
+		);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![3]);
+	}
+
+	#[test]
+	fn should_replace_transaction_and_keep_its_dependencies() {
+		// given
+		let mut pool = BasePool::new(false, Percent::from_percent(10));
+		pool.import(Transaction {
+			data: vec![1u8],
+			priority: 10,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let replaced = pool
+			.replace(
+				&1,
+				Transaction {
+					data: vec![3u8],
+					hash: 3,
+					priority: 20,
+					provides: vec![vec![1]],
+					..DEFAULT_TX.clone()
+				},
+			)
+			.unwrap();
+
+		// then
+		assert_eq!(replaced.imported.hash(), &3);
+		assert_eq!(replaced.restored.iter().map(|i| *i.hash()).collect::<Vec<_>>(), vec![2]);
+		assert_eq!(replaced.removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![3, 2]);
+	}
+
+	#[test]
+	fn should_keep_transaction_if_replacement_fails() {
+		// given
+		let mut pool = BasePool::new(true, Percent::from_percent(10));
+		let replacement = Transaction {
+			data: vec![3u8],
+			hash: 3,
+			priority: 20,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		};
+		pool.import(Transaction {
+			data: vec![1u8],
+			priority: 10,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let unknown = pool.replace(&4, replacement.clone());
+		let unrelated =
+			pool.replace(&1, Transaction { provides: vec![vec![3]], ..replacement.clone() });
+		let too_low = pool.replace(&1, Transaction { priority: 10, ..replacement.clone() });
+		let future = pool.replace(&1, Transaction { requires: vec![vec![0]], ..replacement });
+
+		// then
+		assert!(matches!(unknown, Err(error::Error::ReplacedTransactionNotFound)));
+		assert!(matches!(unrelated, Err(error::Error::InvalidReplacement)));
+		assert!(matches!(too_low, Err(error::Error::TooLowPriority { old: 10, new: 10 })));
+		assert!(matches!(future, Err(error::Error::RejectedFutureTransaction)));
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2]);
+	}
+
+	#[test]
+	fn should_inspect_transactions_and_their_dependencies() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction {
+			data: vec![1u8],
+			priority: 10,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![4u8],
+			hash: 4,
+			requires: vec![vec![2], vec![3]],
+			provides: vec![vec![4]],
+			source: Source::Local,
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let inspection = pool.inspect();
+
+		// then
+		assert_eq!(inspection.ready.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1, 2]);
+		assert_eq!(inspection.ready[0].unlocked_by, Vec::<Hash>::new());
+		assert_eq!(inspection.ready[0].unlocks, vec![2]);
+		assert_eq!(inspection.ready[1].unlocked_by, vec![1]);
+		assert_eq!(inspection.ready[1].unlocks, vec![4]);
+
+		let future = &inspection.future[0];
+		assert_eq!(future.hash, 4);
+		assert_eq!(future.source, Source::Local);
+		assert_eq!(future.requires, vec![Bytes(vec![2]), Bytes(vec![3])]);
+		assert_eq!(future.missing_tags, vec![Bytes(vec![3])]);
+		assert_eq!(future.unlocked_by, vec![2]);
+		assert_eq!(future.chain, 1);
+
+		assert_eq!(inspection.chains, vec![ChainTransactions { first: 1, ready: 2, future: 1 }]);
+	}
 }
@@ -23,10 +23,10 @@ use std::{
 };
 
 use sp_core::hexdisplay::HexDisplay;
-use sp_runtime::transaction_validity::TransactionTag as Tag;
+use sp_runtime::transaction_validity::{TransactionSource, TransactionTag as Tag};
 use std::time::Instant;
 
-use super::base_pool::Transaction;
+use super::base_pool::{SourceUsage, Transaction};
 
 #[derive(parity_util_mem::MallocSizeOf)]
 /// Transaction with partially satisfied dependencies.
@@ -114,11 +114,18 @@ pub struct FutureTransactions<Hash: hash::Hash + Eq, Ex> {
 	wanted_tags: HashMap<Tag, HashSet<Hash>>,
 	/// Transactions waiting for a particular other transaction
 	waiting: HashMap<Hash, WaitingTransaction<Hash, Ex>>,
+	/// Number and size of the transactions of each source.
+	#[ignore_malloc_size_of = "no heap allocations"]
+	by_source: SourceUsage,
 }
 
 impl<Hash: hash::Hash + Eq, Ex> Default for FutureTransactions<Hash, Ex> {
 	fn default() -> Self {
-		Self { wanted_tags: Default::default(), waiting: Default::default() }
+		Self {
+			wanted_tags: Default::default(),
+			waiting: Default::default(),
+			by_source: Default::default(),
+		}
 	}
 }
 
@@ -150,6 +157,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		}
 
 		// Add the transaction to a by-hash waiting map
+		self.by_source.add(&tx.transaction);
 		self.waiting.insert(tx.transaction.hash.clone(), tx);
 	}
 
@@ -187,6 +195,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 
 					if is_ready {
 						let tx = self.waiting.remove(&hash).expect(WAITING_PROOF);
+						self.by_source.remove(&tx.transaction);
 						became_ready.push(tx);
 					}
 				}
@@ -203,6 +212,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		let mut removed = vec![];
 		for hash in hashes {
 			if let Some(waiting_tx) = self.waiting.remove(hash) {
+				self.by_source.remove(&waiting_tx.transaction);
 				// remove from wanted_tags as well
 				for tag in waiting_tx.missing_tags {
 					let remove = if let Some(wanted) = self.wanted_tags.get_mut(&tag) {
@@ -230,6 +240,11 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		self.waiting.values().fold(None, f)
 	}
 
+	/// Returns iterator over all future transactions and their missing tags.
+	pub fn waiting(&self) -> impl Iterator<Item = &WaitingTransaction<Hash, Ex>> {
+		self.waiting.values()
+	}
+
 	/// Returns iterator over all future transactions
 	pub fn all(&self) -> impl Iterator<Item = &Transaction<Hash, Ex>> {
 		self.waiting.values().map(|waiting| &*waiting.transaction)
@@ -238,6 +253,7 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 	/// Removes and returns all future transactions.
 	pub fn clear(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		self.wanted_tags.clear();
+		self.by_source = Default::default();
 		self.waiting.drain().map(|(_, tx)| tx.transaction).collect()
 	}
 
@@ -248,7 +264,12 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 
 	/// Returns sum of encoding lengths of all transactions in this queue.
 	pub fn bytes(&self) -> usize {
-		self.waiting.values().fold(0, |acc, tx| acc + tx.transaction.bytes)
+		self.by_source.total().1
+	}
+
+	/// Returns the number and size of the transactions of `source` in this queue.
+	pub fn usage(&self, source: TransactionSource) -> (usize, usize) {
+		self.by_source.get(source)
 	}
 }
 
@@ -271,6 +292,8 @@ mod tests {
 				provides: vec![vec![3], vec![4]],
 				propagate: true,
 				source: TransactionSource::External,
+				bundle: None,
+				arrival: 0,
 			}
 			.into(),
 			missing_tags: vec![vec![1u8], vec![2u8]].into_iter().collect(),
@@ -20,15 +20,16 @@ use std::{collections::HashMap, fmt::Debug, hash};
 
 use linked_hash_map::LinkedHashMap;
 use log::{debug, trace};
+use sc_transaction_pool_api::InclusionResult;
 use serde::Serialize;
 use sp_runtime::traits;
 
-use super::{watcher, BlockHash, ChainApi, ExtrinsicHash};
+use super::{base_pool::EvictionReason, watcher, BlockHash, ChainApi, ExtrinsicHash};
 
 /// Extrinsic pool default listener.
 pub struct Listener<H: hash::Hash + Eq, C: ChainApi> {
 	watchers: HashMap<H, watcher::Sender<H, ExtrinsicHash<C>>>,
-	finality_watchers: LinkedHashMap<ExtrinsicHash<C>, Vec<H>>,
+	finality_watchers: LinkedHashMap<ExtrinsicHash<C>, Vec<(H, Option<InclusionResult>)>>,
 }
 
 /// Maximum number of blocks awaiting finality at any time.
@@ -57,6 +58,11 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		}
 	}
 
+	/// Whether the given extrinsic has watchers.
+	pub fn is_watched(&self, hash: &H) -> bool {
+		self.watchers.contains_key(hash)
+	}
+
 	/// Creates a new watcher for given verified extrinsic.
 	///
 	/// The watcher can be used to subscribe to life-cycle events of that extrinsic.
@@ -95,21 +101,46 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		})
 	}
 
+	/// Transaction was removed from the pool to keep it within its limits.
+	pub fn evicted(&mut self, tx: &H, reason: EvictionReason) {
+		trace!(target: "txpool", "[{:?}] Evicted ({:?})", tx, reason);
+		self.fire(tx, |watcher| match reason {
+			EvictionReason::Limit => watcher.dropped(),
+			EvictionReason::SenderLimit => watcher.sender_limit_exceeded(),
+			EvictionReason::SourceQuota => watcher.source_quota_exceeded(),
+		})
+	}
+
 	/// Transaction was removed as invalid.
 	pub fn invalid(&mut self, tx: &H) {
 		debug!(target: "txpool", "[{:?}] Extrinsic invalid", tx);
 		self.fire(tx, |watcher| watcher.invalid());
 	}
 
-	/// Transaction was pruned from the pool.
-	pub fn pruned(&mut self, block_hash: BlockHash<C>, tx: &H) {
+	/// Transaction is ready in the view of the pool at a leaf that is not the best block.
+	pub fn ready_at_leaf(&mut self, tx: &H, block_hash: BlockHash<C>) {
+		trace!(target: "txpool", "[{:?}] Ready at {:?}", tx, block_hash);
+		self.fire(tx, |watcher| watcher.ready_at_leaf(block_hash));
+	}
+
+	/// Transaction is invalid in the view of the pool at a leaf that is not the best block.
+	pub fn invalid_at_leaf(&mut self, tx: &H, block_hash: BlockHash<C>) {
+		trace!(target: "txpool", "[{:?}] Invalid at {:?}", tx, block_hash);
+		self.fire(tx, |watcher| watcher.invalid_at_leaf(block_hash));
+	}
+
+	/// Transaction was pruned from the pool, with the given result of its inclusion if known.
+	pub fn pruned(&mut self, block_hash: BlockHash<C>, tx: &H, result: Option<InclusionResult>) {
 		debug!(target: "txpool", "[{:?}] Pruned at {:?}", tx, block_hash);
-		self.fire(tx, |s| s.in_block(block_hash));
-		self.finality_watchers.entry(block_hash).or_insert(vec![]).push(tx.clone());
+		self.fire(tx, |s| s.in_block(block_hash, result.clone()));
+		self.finality_watchers
+			.entry(block_hash)
+			.or_insert(vec![])
+			.push((tx.clone(), result));
 
 		while self.finality_watchers.len() > MAX_FINALITY_WATCHERS {
 			if let Some((hash, txs)) = self.finality_watchers.pop_front() {
-				for tx in txs {
+				for (tx, _) in txs {
 					self.fire(&tx, |s| s.finality_timeout(hash));
 				}
 			}
@@ -119,7 +150,7 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 	/// The block this transaction was included in has been retracted.
 	pub fn retracted(&mut self, block_hash: BlockHash<C>) {
 		if let Some(hashes) = self.finality_watchers.remove(&block_hash) {
-			for hash in hashes {
+			for (hash, _) in hashes {
 				self.fire(&hash, |s| s.retracted(block_hash))
 			}
 		}
@@ -128,9 +159,9 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 	/// Notify all watchers that transactions have been finalized
 	pub fn finalized(&mut self, block_hash: BlockHash<C>) {
 		if let Some(hashes) = self.finality_watchers.remove(&block_hash) {
-			for hash in hashes {
+			for (hash, result) in hashes {
 				log::debug!(target: "txpool", "[{:?}] Sent finalization event (block {:?})", hash, block_hash);
-				self.fire(&hash, |s| s.finalized(block_hash))
+				self.fire(&hash, |s| s.finalized(block_hash, result))
 			}
 		}
 	}
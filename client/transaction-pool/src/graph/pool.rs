@@ -16,16 +16,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use futures::{channel::mpsc::Receiver, Future};
-use sc_transaction_pool_api::error;
+use sc_transaction_pool_api::{error, InclusionResult};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{self, Block as BlockT, SaturatedConversion},
 	transaction_validity::{
 		TransactionSource, TransactionTag as Tag, TransactionValidity, TransactionValidityError,
 	},
+	Percent,
 };
 use std::time::Instant;
 
@@ -97,6 +98,15 @@ pub trait ChainApi: Send + Sync {
 		&self,
 		at: &BlockId<Self::Block>,
 	) -> Result<Option<<Self::Block as BlockT>::Header>, Self::Error>;
+
+	/// Returns the results of the extrinsics included in the given block, together with the
+	/// hashes of the extrinsics.
+	///
+	/// Returns no results if the runtime can't report them.
+	fn inclusion_results(
+		&self,
+		at: &BlockId<Self::Block>,
+	) -> Result<Vec<(ExtrinsicHash<Self>, InclusionResult)>, Self::Error>;
 }
 
 /// Pool configuration options.
@@ -106,8 +116,24 @@ pub struct Options {
 	pub ready: base::Limit,
 	/// Future queue limits.
 	pub future: base::Limit,
+	/// Maximum number of transactions of a single sender in the pool, if any.
+	pub sender_limit: Option<usize>,
+	/// Share of the queue limits that the transactions of each source may take.
+	pub source_quotas: base::SourceQuotas,
+	/// Minimum increase of the priority required to replace a transaction.
+	pub replacement_bump: Percent,
 	/// Reject future transactions.
 	pub reject_future_transactions: bool,
+	/// Path of the journal that keeps the transactions and bans across restarts, if any.
+	///
+	/// Accepted transactions are appended to it as they are submitted, and it is compacted
+	/// periodically. The transactions are imported again when the pool is created.
+	pub journal: Option<PathBuf>,
+	/// Report the results of included transactions to their watchers.
+	///
+	/// This requires executing a runtime call for every block that includes watched
+	/// transactions.
+	pub inclusion_results: bool,
 }
 
 impl Default for Options {
@@ -115,7 +141,12 @@ impl Default for Options {
 		Self {
 			ready: base::Limit { count: 8192, total_bytes: 20 * 1024 * 1024 },
 			future: base::Limit { count: 512, total_bytes: 1 * 1024 * 1024 },
+			sender_limit: None,
+			source_quotas: Default::default(),
+			replacement_bump: Percent::from_percent(10),
 			reject_future_transactions: false,
+			journal: None,
+			inclusion_results: false,
 		}
 	}
 }
@@ -185,6 +216,56 @@ impl<B: ChainApi> Pool<B> {
 		res.expect("One extrinsic passed; one result returned; qed")
 	}
 
+	/// Imports an ordered bundle of unverified extrinsics to the pool.
+	///
+	/// Either all the extrinsics are imported, or none of them.
+	pub async fn submit_bundle(
+		&self,
+		at: &BlockId<B::Block>,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<B>>,
+	) -> Result<Vec<ExtrinsicHash<B>>, B::Error> {
+		let block_number = self.resolve_block_number(at)?;
+		let validated_transactions =
+			futures::future::join_all(xts.into_iter().map(|xt| {
+				self.verify_one(at, block_number, source, xt, CheckBannedBeforeVerify::Yes)
+			}))
+			.await;
+		self.validated_pool
+			.submit_bundle(validated_transactions.into_iter().map(|(_, tx)| tx).collect())
+	}
+
+	/// Replaces the transaction `old` in the pool with an unverified extrinsic.
+	pub async fn replace(
+		&self,
+		at: &BlockId<B::Block>,
+		source: TransactionSource,
+		old: &ExtrinsicHash<B>,
+		xt: ExtrinsicFor<B>,
+	) -> Result<ExtrinsicHash<B>, B::Error> {
+		let block_number = self.resolve_block_number(at)?;
+		let (_, tx) = self
+			.verify_one(at, block_number, source, xt, CheckBannedBeforeVerify::Yes)
+			.await;
+		self.validated_pool.replace(old, tx)
+	}
+
+	/// Replaces the transaction `old` in the pool with an unverified extrinsic and starts to
+	/// watch the progress of the new one.
+	pub async fn replace_and_watch(
+		&self,
+		at: &BlockId<B::Block>,
+		source: TransactionSource,
+		old: &ExtrinsicHash<B>,
+		xt: ExtrinsicFor<B>,
+	) -> Result<Watcher<ExtrinsicHash<B>, ExtrinsicHash<B>>, B::Error> {
+		let block_number = self.resolve_block_number(at)?;
+		let (_, tx) = self
+			.verify_one(at, block_number, source, xt, CheckBannedBeforeVerify::Yes)
+			.await;
+		self.validated_pool.replace_and_watch(old, tx)
+	}
+
 	/// Import a single extrinsic and starts to watch its progress in the pool.
 	pub async fn submit_and_watch(
 		&self,
@@ -444,8 +525,9 @@ mod tests {
 	use assert_matches::assert_matches;
 	use futures::executor::block_on;
 	use parking_lot::Mutex;
-	use sc_transaction_pool_api::TransactionStatus;
-	use sp_runtime::transaction_validity::TransactionSource;
+	use sc_transaction_pool_api::{InPoolTransaction, InclusionResult, TransactionStatus};
+	use sp_runtime::{transaction_validity::TransactionSource, DispatchError, ModuleError};
+	use sp_transaction_pool::runtime_api;
 	use std::{collections::HashMap, time::Instant};
 	use substrate_test_runtime::{AccountId, Extrinsic, Transfer, H256};
 
@@ -514,6 +596,93 @@ mod tests {
 		assert_matches!(res.unwrap_err(), error::Error::Unactionable);
 	}
 
+	#[test]
+	fn should_import_bundle_with_lowest_priority() {
+		// given
+		let pool = pool();
+		let transfer = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		});
+
+		// when
+		let hashes = block_on(pool.submit_bundle(
+			&BlockId::Number(0),
+			SOURCE,
+			vec![Extrinsic::IncludeData(vec![42]), transfer],
+		))
+		.unwrap();
+
+		// then
+		let ready = pool.validated_pool().ready().collect::<Vec<_>>();
+		assert_eq!(ready.len(), 2);
+		for tx in ready {
+			assert_eq!(tx.priority, 4);
+			assert!(!tx.is_propagable());
+			assert_eq!(tx.bundle(), Some(&hashes[..]));
+		}
+	}
+
+	#[test]
+	fn should_not_import_any_transaction_of_invalid_bundle() {
+		// given
+		let pool = pool();
+		let transfer = |nonce| {
+			uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce,
+			})
+		};
+
+		// when
+		let res = block_on(pool.submit_bundle(
+			&BlockId::Number(1),
+			SOURCE,
+			vec![transfer(1), transfer(0)],
+		));
+		let empty = block_on(pool.submit_bundle(&BlockId::Number(1), SOURCE, vec![]));
+
+		// then
+		assert_matches!(res.unwrap_err(), error::Error::InvalidTransaction(_));
+		assert_matches!(empty.unwrap_err(), error::Error::EmptyBundle);
+		assert_eq!(pool.validated_pool().status().ready, 0);
+		assert_eq!(pool.validated_pool().status().future, 0);
+	}
+
+	#[test]
+	fn should_remove_incomplete_bundles() {
+		// given
+		let pool = pool();
+		let transfer = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		});
+		let hashes = block_on(pool.submit_bundle(
+			&BlockId::Number(0),
+			SOURCE,
+			vec![transfer, Extrinsic::IncludeData(vec![42])],
+		))
+		.unwrap();
+		let watcher = pool.validated_pool().watch(hashes[1]).unwrap().1;
+
+		// when
+		pool.validated_pool().remove_invalid(&hashes[..1]);
+		assert_eq!(pool.validated_pool().status().ready, 1);
+		pool.validated_pool().clear_stale(&BlockId::Number(0)).unwrap();
+
+		// then
+		assert_eq!(pool.validated_pool().status().ready, 0);
+		let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+		assert_eq!(stream.next(), Some(TransactionStatus::Dropped));
+		assert_eq!(stream.next(), None);
+	}
+
 	#[test]
 	fn should_notify_about_pool_events() {
 		let (stream, hash0, hash1) = {
@@ -808,6 +977,87 @@ mod tests {
 			);
 		}
 
+		#[test]
+		fn should_report_inclusion_results_to_watchers() {
+			// given
+			let api = TestApi::default();
+			let options = Options { inclusion_results: true, ..Default::default() };
+			let pool = Pool::new(options, true.into(), api.clone().into());
+			let xt = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 0,
+			});
+			let watcher = block_on(pool.submit_and_watch(&BlockId::Number(0), SOURCE, xt)).unwrap();
+			let error = ModuleError { index: 3, error: [1, 0, 0, 0], message: None };
+			let result = InclusionResult {
+				inclusion: runtime_api::InclusionResult {
+					index: 1,
+					dispatch_info: runtime_api::DispatchInfo {
+						weight: 100,
+						class: runtime_api::ExtrinsicClass::Normal,
+						pays_fee: true,
+					},
+					result: Err(DispatchError::Module(error)),
+					fee: Some(42),
+				},
+				module_error: Some(sc_transaction_pool_api::ModuleError {
+					pallet: "Balances".into(),
+					error: "InsufficientBalance".into(),
+				}),
+			};
+			api.inclusion_results.lock().push((*watcher.hash(), result.clone()));
+
+			// when
+			let block = H256::from_low_u64_be(2);
+			block_on(pool.prune_tags(&BlockId::Number(2), vec![vec![0u8]], vec![])).unwrap();
+			block_on(pool.validated_pool().on_block_finalized(block)).unwrap();
+
+			// then
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+			assert_eq!(
+				stream.next(),
+				Some(TransactionStatus::InBlockWithResult { block, result: result.clone() }),
+			);
+			assert_eq!(
+				stream.next(),
+				Some(TransactionStatus::FinalizedWithResult { block, result }),
+			);
+			assert_eq!(stream.next(), None);
+		}
+
+		#[test]
+		fn should_watch_transactions_already_in_the_pool() {
+			// given
+			let pool = pool();
+			let hash = block_on(pool.submit_one(
+				&BlockId::Number(0),
+				SOURCE,
+				uxt(Transfer {
+					from: AccountId::from_h256(H256::from_low_u64_be(1)),
+					to: AccountId::from_h256(H256::from_low_u64_be(2)),
+					amount: 5,
+					nonce: 0,
+				}),
+			))
+			.unwrap();
+			assert!(pool.validated_pool().watch(H256::from_low_u64_be(3)).is_none());
+
+			// when
+			let (status, watcher) = pool.validated_pool().watch(hash).unwrap();
+			block_on(pool.prune_tags(&BlockId::Number(2), vec![vec![0u8]], vec![])).unwrap();
+
+			// then
+			assert_eq!(status, TransactionStatus::Ready);
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(
+				stream.next(),
+				Some(TransactionStatus::InBlock(H256::from_low_u64_be(2).into())),
+			);
+		}
+
 		#[test]
 		fn should_trigger_future_and_ready_after_promoted() {
 			// given
@@ -991,6 +1241,72 @@ mod tests {
 			}
 		}
 
+		#[test]
+		fn should_trigger_sender_limit_exceeded() {
+			// given
+			let options = Options { sender_limit: Some(1), ..Default::default() };
+
+			let pool = Pool::new(options, true.into(), TestApi::default().into());
+
+			let xt = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 1,
+			});
+			let watcher = block_on(pool.submit_and_watch(&BlockId::Number(0), SOURCE, xt)).unwrap();
+			assert_eq!(pool.validated_pool().status().future, 1);
+
+			// when
+			let xt = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 0,
+			});
+			block_on(pool.submit_one(&BlockId::Number(0), SOURCE, xt)).unwrap();
+
+			// then
+			assert_eq!(pool.validated_pool().status().ready, 1);
+			assert_eq!(pool.validated_pool().status().future, 0);
+			assert!(!pool.validated_pool().is_banned(watcher.hash()));
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Future));
+			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+			assert_eq!(stream.next(), Some(TransactionStatus::SenderLimitExceeded));
+			assert_eq!(stream.next(), None);
+		}
+
+		#[test]
+		fn should_trigger_usurped_and_ready_on_replacement() {
+			// given
+			let pool = pool();
+			let xt = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 42,
+			});
+			let watcher =
+				block_on(pool.submit_and_watch(&BlockId::Number(42), SOURCE, xt)).unwrap();
+			assert_eq!(pool.validated_pool().status().ready, 1);
+
+			// when
+			let xt = Extrinsic::IncludeData(Vec::new());
+			let replacement =
+				block_on(pool.replace_and_watch(&BlockId::Number(42), SOURCE, watcher.hash(), xt))
+					.unwrap();
+
+			// then
+			assert_eq!(pool.validated_pool().status().ready, 1);
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+			assert_eq!(stream.next(), Some(TransactionStatus::Usurped(*replacement.hash())));
+			assert_eq!(stream.next(), None);
+			let mut stream = futures::executor::block_on_stream(replacement.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+		}
+
 		#[test]
 		fn should_handle_pruning_in_the_middle_of_import() {
 			// given
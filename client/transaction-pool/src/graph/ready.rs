@@ -21,15 +21,20 @@ use std::{
 	collections::{BTreeSet, HashMap, HashSet},
 	hash,
 	sync::Arc,
+	time::Instant,
 };
 
 use log::{debug, trace};
 use sc_transaction_pool_api::error;
 use serde::Serialize;
-use sp_runtime::{traits::Member, transaction_validity::TransactionTag as Tag};
+use sp_runtime::{
+	traits::Member,
+	transaction_validity::{TransactionSource, TransactionTag as Tag},
+	Percent,
+};
 
 use super::{
-	base_pool::Transaction,
+	base_pool::{check_replacement_priority, SourceUsage, Transaction},
 	future::WaitingTransaction,
 	tracked_map::{self, TrackedMap},
 };
@@ -85,6 +90,8 @@ pub struct ReadyTx<Hash, Ex> {
 	/// Some transactions might be already pruned from the queue,
 	/// so when we compute ready set we may consider this transactions ready earlier.
 	pub requires_offset: usize,
+	/// Time of import to the pool.
+	pub imported_at: Instant,
 }
 
 impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
@@ -93,6 +100,7 @@ impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
 			transaction: self.transaction.clone(),
 			unlocks: self.unlocks.clone(),
 			requires_offset: self.requires_offset,
+			imported_at: self.imported_at,
 		}
 	}
 }
@@ -117,6 +125,9 @@ pub struct ReadyTransactions<Hash: hash::Hash + Eq, Ex> {
 	/// Best transactions that are ready to be included to the block without any other previous
 	/// transaction.
 	best: BTreeSet<TransactionRef<Hash, Ex>>,
+	/// Number and size of the transactions of each source.
+	#[ignore_malloc_size_of = "no heap allocations"]
+	by_source: SourceUsage,
 }
 
 impl<Hash, Ex> tracked_map::Size for ReadyTx<Hash, Ex> {
@@ -132,6 +143,7 @@ impl<Hash: hash::Hash + Eq, Ex> Default for ReadyTransactions<Hash, Ex> {
 			provided_tags: Default::default(),
 			ready: Default::default(),
 			best: Default::default(),
+			by_source: Default::default(),
 		}
 	}
 }
@@ -172,11 +184,13 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 	/// Imports transactions to the pool of ready transactions.
 	///
 	/// The transaction needs to have all tags satisfied (be ready) by transactions
-	/// that are in this queue.
+	/// that are in this queue. Replacing transactions that provide the same tags requires
+	/// an increase of the priority by at least `replacement_bump`.
 	/// Returns transactions that were replaced by the one imported.
 	pub fn import(
 		&mut self,
 		tx: WaitingTransaction<Hash, Ex>,
+		replacement_bump: Percent,
 	) -> error::Result<Vec<Arc<Transaction<Hash, Ex>>>> {
 		assert!(
 			tx.is_ready(),
@@ -191,9 +205,10 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		self.insertion_id += 1;
 		let insertion_id = self.insertion_id;
 		let hash = tx.transaction.hash.clone();
+		let imported_at = tx.imported_at;
 		let transaction = tx.transaction;
 
-		let (replaced, unlocks) = self.replace_previous(&transaction)?;
+		let (replaced, unlocks) = self.replace_previous(&transaction, replacement_bump)?;
 
 		let mut goes_to_best = true;
 		let mut ready = self.ready.write();
@@ -226,11 +241,17 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		}
 
 		// insert to Ready
-		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset });
+		self.by_source.add(&transaction.transaction);
+		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset, imported_at });
 
 		Ok(replaced)
 	}
 
+	/// Returns all the transactions in this queue.
+	pub fn all(&self) -> Vec<ReadyTx<Hash, Ex>> {
+		self.ready.read().values().cloned().collect()
+	}
+
 	/// Fold a list of ready transactions to compute a single value.
 	pub fn fold<R, F: FnMut(Option<R>, &ReadyTx<Hash, Ex>) -> Option<R>>(
 		&mut self,
@@ -282,6 +303,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		let mut ready = self.ready.write();
 		while let Some(hash) = to_remove.pop() {
 			if let Some(mut tx) = ready.remove(&hash) {
+				self.by_source.remove(&tx.transaction.transaction);
 				let invalidated = tx.transaction.transaction.provides.iter().filter(|tag| {
 					provides_tag_filter
 						.as_ref()
@@ -338,6 +360,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 				.and_then(|hash| self.ready.write().remove(&hash));
 
 			if let Some(tx) = res {
+				self.by_source.remove(&tx.transaction.transaction);
 				let unlocks = tx.unlocks;
 
 				// Make sure we remove it from best txs
@@ -412,14 +435,15 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 	/// Checks if the transaction is providing the same tags as other transactions.
 	///
 	/// In case that's true it determines if the priority of transactions that
-	/// we are about to replace is lower than the priority of the replacement transaction.
-	/// We remove/replace old transactions in case they have lower priority.
+	/// we are about to replace is lower than the priority of the replacement transaction,
+	/// by at least `replacement_bump`. We remove/replace old transactions in that case.
 	///
 	/// In case replacement is successful returns a list of removed transactions
 	/// and a list of hashes that are still in pool and gets unlocked by the new transaction.
 	fn replace_previous(
 		&mut self,
 		tx: &Transaction<Hash, Ex>,
+		replacement_bump: Percent,
 	) -> error::Result<(Vec<Arc<Transaction<Hash, Ex>>>, Vec<Hash>)> {
 		let (to_remove, unlocks) = {
 			// check if we are replacing a transaction
@@ -446,9 +470,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 			};
 
 			// bail - the transaction has too low priority to replace the old ones
-			if old_priority >= tx.priority {
-				return Err(error::Error::TooLowPriority { old: old_priority, new: tx.priority })
-			}
+			check_replacement_priority(old_priority, tx.priority, replacement_bump)?;
 
 			// construct a list of unlocked transactions
 			let unlocks = {
@@ -480,6 +502,11 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 	pub fn bytes(&self) -> usize {
 		self.ready.bytes()
 	}
+
+	/// Returns the number and size of the transactions of `source` in this queue.
+	pub fn usage(&self, source: TransactionSource) -> (usize, usize) {
+		self.by_source.get(source)
+	}
 }
 
 /// Iterator of ready transactions ordered by priority.
@@ -602,6 +629,8 @@ mod tests {
 			provides: vec![vec![3], vec![4]],
 			propagate: true,
 			source: Source::External,
+			bundle: None,
+			arrival: 0,
 		}
 	}
 
@@ -610,7 +639,7 @@ mod tests {
 		tx: Transaction<H, Ex>,
 	) -> error::Result<Vec<Arc<Transaction<H, Ex>>>> {
 		let x = WaitingTransaction::new(tx, ready.provided_tags(), &[]);
-		ready.import(x)
+		ready.import(x, Percent::zero())
 	}
 
 	#[test]
@@ -712,6 +741,8 @@ mod tests {
 			provides: vec![],
 			propagate: true,
 			source: Source::External,
+			bundle: None,
+			arrival: 0,
 		};
 
 		// when
@@ -755,6 +786,8 @@ mod tests {
 			provides: vec![],
 			propagate: true,
 			source: Source::External,
+			bundle: None,
+			arrival: 0,
 		};
 		import(&mut ready, tx).unwrap();
 
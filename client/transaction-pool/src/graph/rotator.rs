@@ -73,6 +73,20 @@ impl<Hash: hash::Hash + Eq + Clone> PoolRotator<Hash> {
 		}
 	}
 
+	/// Bans the given hashes until the given times.
+	pub fn ban_until(&self, bans: impl IntoIterator<Item = (Hash, Instant)>) {
+		self.banned_until.write().extend(bans);
+	}
+
+	/// Returns the banned hashes, with the time their ban ends.
+	pub fn banned(&self) -> Vec<(Hash, Instant)> {
+		self.banned_until
+			.read()
+			.iter()
+			.map(|(hash, until)| (hash.clone(), *until))
+			.collect()
+	}
+
 	/// Bans extrinsic if it's stale.
 	///
 	/// Returns `true` if extrinsic is stale and got banned.
@@ -122,6 +136,8 @@ mod tests {
 			provides: vec![],
 			propagate: true,
 			source: TransactionSource::External,
+			bundle: None,
+			arrival: 0,
 		};
 
 		(hash, tx)
@@ -173,6 +189,23 @@ mod tests {
 		assert!(!rotator.is_banned(&hash));
 	}
 
+	#[test]
+	fn should_restore_bans() {
+		// given
+		let rotator = rotator();
+		let until = Instant::now() + Duration::from_secs(60);
+
+		// when
+		rotator.ban_until(vec![(1, until), (2, until)]);
+
+		// then
+		assert!(rotator.is_banned(&1));
+		assert!(rotator.is_banned(&2));
+		let mut banned = rotator.banned();
+		banned.sort();
+		assert_eq!(banned, vec![(1, until), (2, until)]);
+	}
+
 	#[test]
 	fn should_garbage_collect() {
 		// given
@@ -188,6 +221,8 @@ mod tests {
 				provides: vec![],
 				propagate: true,
 				source: TransactionSource::External,
+				bundle: None,
+				arrival: 0,
 			}
 		}
 
@@ -25,7 +25,9 @@ use std::{
 use futures::channel::mpsc::{channel, Sender};
 use parking_lot::{Mutex, RwLock};
 use retain_mut::RetainMut;
-use sc_transaction_pool_api::{error, PoolStatus, ReadyTransactions};
+use sc_transaction_pool_api::{
+	error, PoolInspection, PoolStatus, ReadyTransactions, TransactionStatus,
+};
 use serde::Serialize;
 use sp_runtime::{
 	generic::BlockId,
@@ -77,6 +79,8 @@ impl<Hash, Ex, Error> ValidatedTransaction<Hash, Ex, Error> {
 			provides: validity.provides,
 			propagate: validity.propagate,
 			valid_till: at.saturated_into::<u64>().saturating_add(validity.longevity),
+			bundle: None,
+			arrival: 0,
 		})
 	}
 }
@@ -85,6 +89,12 @@ impl<Hash, Ex, Error> ValidatedTransaction<Hash, Ex, Error> {
 pub type ValidatedTransactionFor<B> =
 	ValidatedTransaction<ExtrinsicHash<B>, ExtrinsicFor<B>, <B as ChainApi>::Error>;
 
+/// Current status of a transaction in the pool and a watcher of its progress.
+type WatchedTransaction<B> = (
+	TransactionStatus<ExtrinsicHash<B>, BlockHash<B>>,
+	Watcher<ExtrinsicHash<B>, ExtrinsicHash<B>>,
+);
+
 /// A closure that returns true if the local node is a validator that can author blocks.
 pub struct IsValidator(Box<dyn Fn() -> bool + Send + Sync>);
 
@@ -124,7 +134,8 @@ where
 impl<B: ChainApi> ValidatedPool<B> {
 	/// Create a new transaction pool.
 	pub fn new(options: Options, is_validator: IsValidator, api: Arc<B>) -> Self {
-		let base_pool = base::BasePool::new(options.reject_future_transactions);
+		let base_pool =
+			base::BasePool::new(options.reject_future_transactions, options.replacement_bump);
 		Self {
 			is_validator,
 			options,
@@ -141,6 +152,16 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.rotator.ban(now, hashes)
 	}
 
+	/// Bans the given hashes until the given times.
+	pub fn ban_until(&self, bans: impl IntoIterator<Item = (ExtrinsicHash<B>, Instant)>) {
+		self.rotator.ban_until(bans)
+	}
+
+	/// Returns the banned hashes, with the time their ban ends.
+	pub fn banned(&self) -> Vec<(ExtrinsicHash<B>, Instant)> {
+		self.rotator.banned()
+	}
+
 	/// Returns true if transaction with given hash is currently banned from the pool.
 	pub fn is_banned(&self, hash: &ExtrinsicHash<B>) -> bool {
 		self.rotator.is_banned(hash)
@@ -201,25 +222,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				}
 
 				let imported = self.pool.write().import(tx)?;
-
-				if let base::Imported::Ready { ref hash, .. } = imported {
-					RetainMut::retain_mut(&mut *self.import_notification_sinks.lock(), |sink| {
-						match sink.try_send(*hash) {
-							Ok(()) => true,
-							Err(e) =>
-								if e.is_full() {
-									log::warn!(
-										target: "txpool",
-										"[{:?}] Trying to notify an import but the channel is full",
-										hash,
-									);
-									true
-								} else {
-									false
-								},
-						}
-					});
-				}
+				self.notify_import(&imported);
 
 				let mut listener = self.listener.write();
 				fire_events(&mut *listener, &imported);
@@ -236,32 +239,162 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
+	/// Imports a bundle of pre-validated transactions to the pool.
+	///
+	/// Either all the transactions of the bundle are imported, or none.
+	pub fn submit_bundle(
+		&self,
+		txs: Vec<ValidatedTransactionFor<B>>,
+	) -> Result<Vec<ExtrinsicHash<B>>, B::Error> {
+		let txs = txs
+			.into_iter()
+			.map(|tx| match tx {
+				ValidatedTransaction::Valid(tx) => Ok(tx),
+				ValidatedTransaction::Invalid(hash, err) => {
+					self.rotator.ban(&Instant::now(), std::iter::once(hash));
+					Err(err)
+				},
+				ValidatedTransaction::Unknown(_, err) => Err(err),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		if !(self.is_validator.0)() {
+			return Err(error::Error::Unactionable.into())
+		}
+
+		let imported = self.pool.write().import_bundle(txs)?;
+		imported.iter().for_each(|imported| self.notify_import(imported));
+		{
+			let mut listener = self.listener.write();
+			imported.iter().for_each(|imported| fire_events(&mut *listener, imported));
+		}
+
+		let hashes = imported.iter().map(|imported| *imported.hash()).collect::<Vec<_>>();
+		let removed = self.enforce_limits();
+		if hashes.iter().any(|hash| removed.contains(hash)) {
+			return Err(error::Error::ImmediatelyDropped.into())
+		}
+
+		Ok(hashes)
+	}
+
+	/// Replaces the transaction `old` with a pre-validated transaction.
+	///
+	/// Watchers of the replaced transaction, and of the transactions depending on it that can't
+	/// stay in the pool, are notified that the new transaction usurped it.
+	pub fn replace(
+		&self,
+		old: &ExtrinsicHash<B>,
+		tx: ValidatedTransactionFor<B>,
+	) -> Result<ExtrinsicHash<B>, B::Error> {
+		let tx = match tx {
+			ValidatedTransaction::Valid(tx) => tx,
+			ValidatedTransaction::Invalid(_, err) | ValidatedTransaction::Unknown(_, err) =>
+				return Err(err),
+		};
+		if !tx.propagate && !(self.is_validator.0)() {
+			return Err(error::Error::Unactionable.into())
+		}
+
+		let replaced = self.pool.write().replace(old, tx)?;
+		let hash = *replaced.imported.hash();
+		self.notify_import(&replaced.imported);
+		replaced.restored.iter().for_each(|imported| self.notify_import(imported));
+
+		{
+			let mut listener = self.listener.write();
+			fire_events(&mut *listener, &replaced.imported);
+			replaced
+				.restored
+				.iter()
+				.for_each(|imported| fire_events(&mut *listener, imported));
+			replaced.removed.iter().for_each(|tx| listener.dropped(&tx.hash, Some(&hash)));
+		}
+
+		if self.enforce_limits().contains(&hash) {
+			return Err(error::Error::ImmediatelyDropped.into())
+		}
+
+		Ok(hash)
+	}
+
+	/// Replaces the transaction `old` with a pre-validated transaction and starts to watch the
+	/// progress of the new one.
+	///
+	/// The watchers of the replaced transaction are notified that it was usurped, the returned
+	/// watcher that the new transaction entered the pool.
+	pub fn replace_and_watch(
+		&self,
+		old: &ExtrinsicHash<B>,
+		tx: ValidatedTransactionFor<B>,
+	) -> Result<Watcher<ExtrinsicHash<B>, ExtrinsicHash<B>>, B::Error> {
+		let tx = match tx {
+			ValidatedTransaction::Valid(tx) => tx,
+			ValidatedTransaction::Invalid(_, err) | ValidatedTransaction::Unknown(_, err) =>
+				return Err(err),
+		};
+		let watcher = self.listener.write().create_watcher(tx.hash);
+		self.replace(old, ValidatedTransaction::Valid(tx)).map(|_| watcher)
+	}
+
+	/// Notifies the import sinks about a transaction that entered the ready queue.
+	fn notify_import(&self, imported: &base::Imported<ExtrinsicHash<B>, ExtrinsicFor<B>>) {
+		if let base::Imported::Ready { ref hash, .. } = imported {
+			RetainMut::retain_mut(&mut *self.import_notification_sinks.lock(), |sink| {
+				match sink.try_send(*hash) {
+					Ok(()) => true,
+					Err(e) =>
+						if e.is_full() {
+							log::warn!(
+								target: "txpool",
+								"[{:?}] Trying to notify an import but the channel is full",
+								hash,
+							);
+							true
+						} else {
+							false
+						},
+				}
+			});
+		}
+	}
+
 	fn enforce_limits(&self) -> HashSet<ExtrinsicHash<B>> {
 		let status = self.pool.read().status();
 		let ready_limit = &self.options.ready;
 		let future_limit = &self.options.future;
+		let sender_limit = self.options.sender_limit;
+		let source_quotas = &self.options.source_quotas;
 
 		log::debug!(target: "txpool", "Pool Status: {:?}", status);
 		if ready_limit.is_exceeded(status.ready, status.ready_bytes) ||
-			future_limit.is_exceeded(status.future, status.future_bytes)
+			future_limit.is_exceeded(status.future, status.future_bytes) ||
+			sender_limit.is_some() ||
+			*source_quotas != Default::default()
 		{
 			log::debug!(
 				target: "txpool",
-				"Enforcing limits ({}/{}kB ready, {}/{}kB future",
+				"Enforcing limits ({}/{}kB ready, {}/{}kB future, {:?} per sender, {:?})",
 				ready_limit.count, ready_limit.total_bytes / 1024,
 				future_limit.count, future_limit.total_bytes / 1024,
+				sender_limit, source_quotas,
 			);
 
 			// clean up the pool
 			let removed = {
 				let mut pool = self.pool.write();
 				let removed = pool
-					.enforce_limits(ready_limit, future_limit)
+					.enforce_limits(ready_limit, future_limit, sender_limit, source_quotas)
 					.into_iter()
-					.map(|x| x.hash)
-					.collect::<HashSet<_>>();
-				// ban all removed transactions
-				self.rotator.ban(&Instant::now(), removed.iter().copied());
+					.map(|(tx, reason)| (tx.hash, reason))
+					.collect::<HashMap<_, _>>();
+				// Ban the transactions that didn't fit in the pool, like before the sender limit
+				// and the source quotas existed. The transactions evicted because of those may
+				// be valid and fit later, when their sender or source has fewer transactions.
+				let banned = removed
+					.iter()
+					.filter(|(_, reason)| **reason == base::EvictionReason::Limit)
+					.map(|(hash, _)| *hash);
+				self.rotator.ban(&Instant::now(), banned);
 				removed
 			};
 			if !removed.is_empty() {
@@ -270,11 +403,11 @@ impl<B: ChainApi> ValidatedPool<B> {
 
 			// run notifications
 			let mut listener = self.listener.write();
-			for h in &removed {
-				listener.dropped(h, None);
+			for (h, reason) in &removed {
+				listener.evicted(h, *reason);
 			}
 
-			removed
+			removed.into_keys().collect()
 		} else {
 			Default::default()
 		}
@@ -302,6 +435,24 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
+	/// Starts to watch the progress of an extrinsic that is already in the pool.
+	///
+	/// Returns the current status of the extrinsic along with the watcher, or `None` if the
+	/// extrinsic is not in the pool.
+	pub fn watch(&self, hash: ExtrinsicHash<B>) -> Option<WatchedTransaction<B>> {
+		// Keep the pool locked until the watcher exists, so that no status change is missed.
+		let pool = self.pool.read();
+		let status = if pool.ready_by_hash(&hash).is_some() {
+			TransactionStatus::Ready
+		} else if pool.by_hashes(&[hash])[0].is_some() {
+			TransactionStatus::Future
+		} else {
+			return None
+		};
+
+		Some((status, self.listener.write().create_watcher(hash)))
+	}
+
 	/// Resubmits revalidated transactions back to the pool.
 	///
 	/// Removes and then submits passed transactions and all dependent transactions.
@@ -344,7 +495,11 @@ impl<B: ChainApi> ValidatedPool<B> {
 				for removed_tx in removed {
 					let removed_hash = removed_tx.hash;
 					let updated_transaction = updated_transactions.remove(&removed_hash);
-					let tx_to_resubmit = if let Some(updated_tx) = updated_transaction {
+					let tx_to_resubmit = if let Some(mut updated_tx) = updated_transaction {
+						// the transaction keeps its place in the order of arrival
+						if let ValidatedTransaction::Valid(ref mut tx) = updated_tx {
+							tx.arrival = removed_tx.arrival;
+						}
 						updated_tx
 					} else {
 						// in most cases we'll end up in successful `try_unwrap`, but if not
@@ -519,16 +674,34 @@ impl<B: ChainApi> ValidatedPool<B> {
 			.api
 			.block_id_to_hash(at)?
 			.ok_or_else(|| error::Error::InvalidBlockId(format!("{:?}", at)))?;
-		let mut listener = self.listener.write();
+		// `hashes` has possibly duplicate hashes.
+		// we'd like to send out the `InBlock` notification only once.
 		let mut set = HashSet::with_capacity(hashes.size_hint().0);
-		for h in hashes {
-			// `hashes` has possibly duplicate hashes.
-			// we'd like to send out the `InBlock` notification only once.
-			if !set.contains(&h) {
-				listener.pruned(header_hash, &h);
-				set.insert(h);
+		let hashes = hashes.filter(|h| set.insert(*h)).collect::<Vec<_>>();
+
+		let mut results = HashMap::new();
+		if self.options.inclusion_results {
+			let is_watched = {
+				let listener = self.listener.read();
+				hashes.iter().any(|h| listener.is_watched(h))
+			};
+			if is_watched {
+				match self.api.inclusion_results(at) {
+					Ok(r) => results.extend(r),
+					Err(e) => log::debug!(
+						target: "txpool",
+						"Failed to get the inclusion results at {:?}: {:?}",
+						at,
+						e,
+					),
+				}
 			}
 		}
+
+		let mut listener = self.listener.write();
+		for h in hashes {
+			listener.pruned(header_hash, &h, results.remove(&h));
+		}
 		Ok(())
 	}
 
@@ -563,6 +736,12 @@ impl<B: ChainApi> ValidatedPool<B> {
 		// removing old transactions
 		self.remove_invalid(&to_remove);
 		self.remove_invalid(&futures_to_remove);
+		// removing what is left of bundles that can't be included completely anymore
+		let incomplete = self.pool.write().remove_incomplete_bundles();
+		if !incomplete.is_empty() {
+			let mut listener = self.listener.write();
+			incomplete.iter().for_each(|tx| listener.dropped(&tx.hash, None));
+		}
 		// clear banned transactions timeouts
 		self.rotator.clear_timeouts(&now);
 
@@ -633,11 +812,30 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.pool.read().futures().map(|tx| (tx.hash, tx.data.clone())).collect()
 	}
 
+	/// Returns the sources and extrinsics of all the ready and future transactions.
+	///
+	/// Transactions of bundles are left out, since they would lose their bundle when imported
+	/// again one by one.
+	pub fn transactions(&self) -> Vec<(TransactionSource, ExtrinsicFor<B>)> {
+		let pool = self.pool.read();
+		let future = pool.futures().filter(|tx| tx.bundle.is_none());
+		pool.ready()
+			.filter(|tx| tx.bundle.is_none())
+			.map(|tx| (tx.source, tx.data.clone()))
+			.chain(future.map(|tx| (tx.source, tx.data.clone())))
+			.collect()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
 	}
 
+	/// Returns the details of all the transactions in the pool.
+	pub fn inspect(&self) -> PoolInspection<ExtrinsicHash<B>> {
+		self.pool.read().inspect()
+	}
+
 	/// Notify all watchers that transactions in the block with hash have been finalized
 	pub async fn on_block_finalized(&self, block_hash: BlockHash<B>) -> Result<(), B::Error> {
 		log::trace!(target: "txpool", "Attempting to notify watchers of finalization for {}", block_hash);
@@ -649,6 +847,23 @@ impl<B: ChainApi> ValidatedPool<B> {
 	pub fn on_block_retracted(&self, block_hash: BlockHash<B>) {
 		self.listener.write().retracted(block_hash)
 	}
+
+	/// Notify the listener of the transactions that are ready or invalid in the view of the pool
+	/// at the leaf `block_hash`.
+	pub fn on_leaf_statuses(
+		&self,
+		block_hash: BlockHash<B>,
+		ready: &[ExtrinsicHash<B>],
+		invalid: &[ExtrinsicHash<B>],
+	) {
+		let mut listener = self.listener.write();
+		for hash in ready {
+			listener.ready_at_leaf(hash, block_hash);
+		}
+		for hash in invalid {
+			listener.invalid_at_leaf(hash, block_hash);
+		}
+	}
 }
 
 fn fire_events<H, B, Ex>(listener: &mut Listener<H, B>, imported: &base::Imported<H, Ex>)
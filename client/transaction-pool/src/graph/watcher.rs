@@ -19,7 +19,7 @@
 //! Extrinsics status updates.
 
 use futures::Stream;
-use sc_transaction_pool_api::TransactionStatus;
+use sc_transaction_pool_api::{InclusionResult, TransactionStatus};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 
 /// Extrinsic watcher.
@@ -83,14 +83,20 @@ impl<H: Clone, BH: Clone> Sender<H, BH> {
 		self.is_finalized = true;
 	}
 
-	/// Extrinsic has been included in block with given hash.
-	pub fn in_block(&mut self, hash: BH) {
-		self.send(TransactionStatus::InBlock(hash));
+	/// Extrinsic has been included in block with given hash, with the given result if known.
+	pub fn in_block(&mut self, hash: BH, result: Option<InclusionResult>) {
+		self.send(match result {
+			Some(result) => TransactionStatus::InBlockWithResult { block: hash, result },
+			None => TransactionStatus::InBlock(hash),
+		});
 	}
 
-	/// Extrinsic has been finalized by a finality gadget.
-	pub fn finalized(&mut self, hash: BH) {
-		self.send(TransactionStatus::Finalized(hash));
+	/// Extrinsic has been finalized by a finality gadget, with the given result if known.
+	pub fn finalized(&mut self, hash: BH, result: Option<InclusionResult>) {
+		self.send(match result {
+			Some(result) => TransactionStatus::FinalizedWithResult { block: hash, result },
+			None => TransactionStatus::Finalized(hash),
+		});
 		self.is_finalized = true;
 	}
 
@@ -118,6 +124,29 @@ impl<H: Clone, BH: Clone> Sender<H, BH> {
 		self.is_finalized = true;
 	}
 
+	/// Transaction has been dropped from the pool because its sender had too many transactions.
+	pub fn sender_limit_exceeded(&mut self) {
+		self.send(TransactionStatus::SenderLimitExceeded);
+		self.is_finalized = true;
+	}
+
+	/// Transaction has been dropped from the pool because the transactions of its source
+	/// exceeded their quota.
+	pub fn source_quota_exceeded(&mut self) {
+		self.send(TransactionStatus::SourceQuotaExceeded);
+		self.is_finalized = true;
+	}
+
+	/// Extrinsic became ready at a leaf that is not the best block.
+	pub fn ready_at_leaf(&mut self, hash: BH) {
+		self.send(TransactionStatus::ReadyAtLeaf(hash))
+	}
+
+	/// Extrinsic is invalid at a leaf that is not the best block.
+	pub fn invalid_at_leaf(&mut self, hash: BH) {
+		self.send(TransactionStatus::InvalidAtLeaf(hash))
+	}
+
 	/// The extrinsic has been broadcast to the given peers.
 	pub fn broadcast(&mut self, peers: Vec<String>) {
 		self.send(TransactionStatus::Broadcast(peers))
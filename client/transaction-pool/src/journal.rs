@@ -0,0 +1,341 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Journal keeping the transactions of the pool across restarts.
+//!
+//! Every transaction accepted by the pool is appended to the journal together with its source.
+//! While the pool is maintained, at most once every [`COMPACT_INTERVAL`], and when the pool is
+//! dropped, the journal is rewritten with the transactions still in the pool and the ones banned
+//! from it. A record that was only partly written when the node stopped is dropped.
+//!
+//! On startup the bans are restored and the transactions are revalidated at the best block and
+//! imported again, before the pool is handed out. This happens for both revalidation types.
+//!
+//! A compaction holds the lock of the file while it reads the pool, so a transaction that enters
+//! the pool after the pool was read is appended to the new file rather than lost.
+
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::PathBuf,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sc_transaction_pool_api::TransactionSource;
+
+use crate::graph;
+
+/// Version of the format of the journal file.
+const JOURNAL_VERSION: u8 = 2;
+
+/// Minimum time between two compactions of the journal while maintaining the pool.
+const COMPACT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Record of the journal.
+#[derive(Encode, Decode)]
+enum Record<Hash, Ex> {
+	/// Transaction accepted by the pool.
+	Transaction(TransactionSource, Ex),
+	/// Transaction banned from the pool, until the given time in seconds since the UNIX epoch.
+	Banned(Hash, u64),
+}
+
+/// Transactions and bans read from the journal.
+pub(crate) struct Contents<Hash, Ex> {
+	/// Transactions, in the order they were accepted.
+	pub transactions: Vec<(TransactionSource, Ex)>,
+	/// Banned transactions, with the time their ban ends.
+	pub banned: Vec<(Hash, SystemTime)>,
+}
+
+/// File journal of the transactions in the pool.
+pub(crate) struct Journal {
+	path: PathBuf,
+	/// File the transactions are appended to, opened on the first append after a compaction.
+	file: Mutex<Option<File>>,
+	last_compaction: Mutex<Instant>,
+}
+
+impl Journal {
+	pub fn new(path: PathBuf) -> Self {
+		Self { path, file: Mutex::new(None), last_compaction: Mutex::new(Instant::now()) }
+	}
+
+	/// Read the transactions and bans of the journal.
+	///
+	/// Returns nothing if the journal does not exist. A record that was only partly written is
+	/// removed from the journal.
+	pub fn read<Hash: Decode, Ex: Decode>(&self) -> io::Result<Contents<Hash, Ex>> {
+		let mut contents = Contents { transactions: Vec::new(), banned: Vec::new() };
+		let data = match fs::read(&self.path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(contents),
+			Err(e) => return Err(e),
+		};
+
+		let invalid_data = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+		let mut input = match data.split_first() {
+			Some((&JOURNAL_VERSION, records)) => records,
+			Some((version, _)) =>
+				return Err(invalid_data(format!("unsupported journal version {}", version))),
+			None => return Ok(contents),
+		};
+
+		let mut valid_len = data.len() - input.len();
+		while !input.is_empty() {
+			// The last record may not have been completely written.
+			let record = match Vec::<u8>::decode(&mut input) {
+				Ok(record) => record,
+				Err(_) => break,
+			};
+			match Record::decode(&mut &record[..]).map_err(|e| invalid_data(e.to_string()))? {
+				Record::Transaction(source, xt) => contents.transactions.push((source, xt)),
+				Record::Banned(hash, until) =>
+					if let Some(until) = UNIX_EPOCH.checked_add(Duration::from_secs(until)) {
+						contents.banned.push((hash, until))
+					},
+			}
+			valid_len = data.len() - input.len();
+		}
+
+		if valid_len < data.len() {
+			OpenOptions::new().write(true).open(&self.path)?.set_len(valid_len as u64)?;
+		}
+		Ok(contents)
+	}
+
+	/// Append a transaction accepted by the pool.
+	pub fn append<Hash: Encode, Ex: Encode>(
+		&self,
+		source: TransactionSource,
+		xt: &Ex,
+	) -> io::Result<()> {
+		let mut file = self.file.lock();
+		if file.is_none() {
+			if let Some(dir) = self.path.parent() {
+				fs::create_dir_all(dir)?;
+			}
+			let mut new_file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+			if new_file.metadata()?.len() == 0 {
+				new_file.write_all(&[JOURNAL_VERSION])?;
+			}
+			*file = Some(new_file);
+		}
+		let file = file.as_mut().expect("opened above; qed");
+		file.write_all(&Record::<Hash, &Ex>::Transaction(source, xt).encode().encode())
+	}
+
+	/// Replace the contents of the journal with the transactions and bans returned by `contents`.
+	///
+	/// `contents` is called once appends are blocked, so that the transactions appended after it
+	/// read them go to the new file.
+	pub fn compact<Hash: Encode, Ex: Encode>(
+		&self,
+		contents: impl FnOnce() -> Contents<Hash, Ex>,
+	) -> io::Result<Contents<Hash, Ex>> {
+		*self.last_compaction.lock() = Instant::now();
+		// Appends wait for the new file.
+		let mut file = self.file.lock();
+		*file = None;
+		let contents = contents();
+
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		// Write a new file first, so that the journal is never left incomplete.
+		let mut data = vec![JOURNAL_VERSION];
+		for (source, xt) in &contents.transactions {
+			Record::<&Hash, &Ex>::Transaction(*source, xt).encode().encode_to(&mut data);
+		}
+		for (hash, until) in &contents.banned {
+			let until = until.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+			Record::<&Hash, &Ex>::Banned(hash, until).encode().encode_to(&mut data);
+		}
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, data)?;
+		fs::rename(tmp_path, &self.path)?;
+		Ok(contents)
+	}
+
+	/// Read the journal of `pool` and restore its bans.
+	///
+	/// Returns the transactions to import again.
+	pub fn read_pool<PoolApi: graph::ChainApi>(
+		&self,
+		pool: &graph::Pool<PoolApi>,
+	) -> Vec<(TransactionSource, graph::ExtrinsicFor<PoolApi>)> {
+		let contents = match self.read() {
+			Ok(contents) => contents,
+			Err(e) => {
+				log::warn!(target: "txpool", "Failed to read the pool journal {:?}: {}", self.path, e);
+				return Vec::new()
+			},
+		};
+
+		let (now, system_now) = (Instant::now(), SystemTime::now());
+		pool.validated_pool()
+			.ban_until(contents.banned.into_iter().filter_map(|(hash, until)| {
+				// Bans that ended while the node was stopped are dropped.
+				Some((hash, now + until.duration_since(system_now).ok()?))
+			}));
+		contents.transactions
+	}
+
+	/// Append a transaction accepted by `pool`, logging any error.
+	pub fn append_transaction<PoolApi: graph::ChainApi>(
+		&self,
+		source: TransactionSource,
+		xt: &graph::ExtrinsicFor<PoolApi>,
+	) {
+		if let Err(e) = self.append::<graph::ExtrinsicHash<PoolApi>, _>(source, xt) {
+			log::warn!(target: "txpool", "Failed to append to the pool journal {:?}: {}", self.path, e);
+		}
+	}
+
+	/// Rewrite the journal with the transactions and bans of `pool`, unless it was compacted
+	/// recently and `force` is `false`.
+	pub fn compact_pool<PoolApi: graph::ChainApi>(&self, pool: &graph::Pool<PoolApi>, force: bool) {
+		if !force && self.last_compaction.lock().elapsed() < COMPACT_INTERVAL {
+			return
+		}
+
+		let contents = || {
+			let (now, system_now) = (Instant::now(), SystemTime::now());
+			let banned = pool
+				.validated_pool()
+				.banned()
+				.into_iter()
+				.map(|(hash, until)| (hash, system_now + until.saturating_duration_since(now)))
+				.collect();
+			Contents { transactions: pool.validated_pool().transactions(), banned }
+		};
+		match self.compact(contents) {
+			Ok(contents) => log::debug!(
+				target: "txpool",
+				"Wrote {} transactions and {} bans to the pool journal",
+				contents.transactions.len(),
+				contents.banned.len(),
+			),
+			Err(e) => log::warn!(
+				target: "txpool",
+				"Failed to write the pool journal {:?}: {}",
+				self.path,
+				e,
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn read(journal: &Journal) -> Contents<u64, Vec<u8>> {
+		journal.read().unwrap()
+	}
+
+	#[test]
+	fn missing_journal_is_empty() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("txpool").join("journal"));
+		let contents = read(&journal);
+		assert!(contents.transactions.is_empty());
+		assert!(contents.banned.is_empty());
+	}
+
+	#[test]
+	fn appended_transactions_are_read_back() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("txpool").join("journal"));
+		journal.append::<u64, _>(TransactionSource::Local, &vec![1u8, 2]).unwrap();
+		journal.append::<u64, _>(TransactionSource::External, &vec![3u8]).unwrap();
+
+		let journal = Journal::new(dir.path().join("txpool").join("journal"));
+		assert_eq!(
+			read(&journal).transactions,
+			vec![(TransactionSource::Local, vec![1, 2]), (TransactionSource::External, vec![3])],
+		);
+		journal.append::<u64, _>(TransactionSource::InBlock, &vec![4u8]).unwrap();
+		assert_eq!(read(&journal).transactions.len(), 3);
+	}
+
+	#[test]
+	fn compaction_replaces_transactions_and_keeps_bans() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("txpool").join("journal"));
+		journal.append::<u64, _>(TransactionSource::Local, &vec![1u8]).unwrap();
+
+		let until = UNIX_EPOCH + Duration::from_secs(1_000_000);
+		journal
+			.compact(|| Contents {
+				transactions: vec![(TransactionSource::External, vec![2u8])],
+				banned: vec![(5u64, until)],
+			})
+			.unwrap();
+		// Appends go to the compacted journal.
+		journal.append::<u64, _>(TransactionSource::Local, &vec![3u8]).unwrap();
+
+		let contents = read(&journal);
+		assert_eq!(
+			contents.transactions,
+			vec![(TransactionSource::External, vec![2]), (TransactionSource::Local, vec![3])],
+		);
+		assert_eq!(contents.banned, vec![(5, until)]);
+	}
+
+	#[test]
+	fn appends_wait_while_the_compacted_transactions_are_read() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("journal"));
+
+		journal
+			.compact(|| {
+				assert!(journal.file.try_lock().is_none());
+				Contents::<u64, Vec<u8>> { transactions: Vec::new(), banned: Vec::new() }
+			})
+			.unwrap();
+	}
+
+	#[test]
+	fn partly_written_record_is_dropped() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("journal");
+		let journal = Journal::new(path.clone());
+		journal.append::<u64, _>(TransactionSource::Local, &vec![1u8]).unwrap();
+		let len = fs::metadata(&path).unwrap().len();
+		journal.append::<u64, _>(TransactionSource::Local, &vec![2u8; 100]).unwrap();
+		OpenOptions::new().write(true).open(&path).unwrap().set_len(len + 10).unwrap();
+
+		let journal = Journal::new(path.clone());
+		assert_eq!(read(&journal).transactions, vec![(TransactionSource::Local, vec![1])]);
+		assert_eq!(fs::metadata(&path).unwrap().len(), len);
+	}
+
+	#[test]
+	fn unknown_version_is_rejected() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("journal");
+		fs::write(&path, [JOURNAL_VERSION + 1, 0]).unwrap();
+
+		let err = Journal::new(path).read::<u64, Vec<u8>>().unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
+}
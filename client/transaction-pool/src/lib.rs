@@ -25,19 +25,23 @@
 mod api;
 pub mod error;
 mod graph;
+mod journal;
 mod metrics;
 mod revalidation;
 #[cfg(test)]
 mod tests;
+mod view;
 
 pub use crate::api::FullChainApi;
 use futures::{
 	channel::oneshot,
 	future::{self, ready},
 	prelude::*,
+	stream,
 };
 pub use graph::{
-	base_pool::Limit as PoolLimit, ChainApi, Options, Pool, Transaction, ValidatedTransaction,
+	base_pool::{Limit as PoolLimit, SourceQuotas as PoolSourceQuotas},
+	ChainApi, Options, Pool, Transaction, ValidatedTransaction,
 };
 use parking_lot::Mutex;
 use std::{
@@ -49,8 +53,8 @@ use std::{
 use graph::{ExtrinsicHash, IsValidator};
 use sc_transaction_pool_api::{
 	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, MaintainedTransactionPool,
-	PoolFuture, PoolStatus, ReadyTransactions, TransactionFor, TransactionPool, TransactionSource,
-	TransactionStatusStreamFor, TxHash,
+	PoolFuture, PoolInspection, PoolStatus, ReadyTransactions, TransactionFor, TransactionPool,
+	TransactionSource, TransactionStatusStreamFor, TxHash,
 };
 use sp_core::traits::SpawnEssentialNamed;
 use sp_runtime::{
@@ -74,6 +78,11 @@ type PolledIterator<PoolApi> = Pin<Box<dyn Future<Output = ReadyIteratorFor<Pool
 pub type FullPool<Block, Client> = BasicPool<FullChainApi<Client, Block>, Block>;
 
 /// Basic implementation of transaction pool that can be customized by providing PoolApi.
+///
+/// Besides the pool following the best block, views of the pool are kept at a few recent
+/// leaves that are not the best block, for authoring blocks on them with `ready_at_hash`. The
+/// watchers get the statuses at the best block, plus `ReadyAtLeaf` and `InvalidAtLeaf` for the
+/// views, and `InBlock` and `Retracted` for the blocks of the views.
 pub struct BasicPool<PoolApi, Block>
 where
 	Block: BlockT,
@@ -84,6 +93,8 @@ where
 	revalidation_strategy: Arc<Mutex<RevalidationStrategy<NumberFor<Block>>>>,
 	revalidation_queue: Arc<revalidation::RevalidationQueue<PoolApi>>,
 	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<PoolApi>, Block>>>,
+	fork_views: Arc<Mutex<view::ForkViews<PoolApi>>>,
+	journal: Option<Arc<journal::Journal>>,
 	metrics: PrometheusMetrics,
 }
 
@@ -140,6 +151,18 @@ where
 	}
 }
 
+impl<PoolApi, Block> Drop for BasicPool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: graph::ChainApi<Block = Block>,
+{
+	fn drop(&mut self) {
+		if let Some(journal) = &self.journal {
+			journal.compact_pool(&self.pool, true);
+		}
+	}
+}
+
 /// Type of revalidation.
 pub enum RevalidationType {
 	/// Light revalidation type.
@@ -174,6 +197,8 @@ where
 				revalidation_queue: Arc::new(revalidation_queue),
 				revalidation_strategy: Arc::new(Mutex::new(RevalidationStrategy::Always)),
 				ready_poll: Default::default(),
+				fork_views: Arc::new(Mutex::new(view::ForkViews::new(Default::default()))),
+				journal: None,
 				metrics: Default::default(),
 			},
 			background_task,
@@ -191,6 +216,8 @@ where
 		spawner: impl SpawnEssentialNamed,
 		best_block_number: NumberFor<Block>,
 	) -> Self {
+		let fork_views = view::ForkViews::new(options.clone());
+		let journal = options.journal.clone().map(|path| Arc::new(journal::Journal::new(path)));
 		let pool = Arc::new(graph::Pool::new(options, is_validator, pool_api.clone()));
 		let (revalidation_queue, background_task) = match revalidation_type {
 			RevalidationType::Light =>
@@ -202,6 +229,16 @@ where
 			},
 		};
 
+		// The transactions are validated again before the pool is returned, so that they are in
+		// the pool before the network starts and before the first revalidation.
+		if let Some(journal) = &journal {
+			let transactions = journal.read_pool(&pool);
+			futures::executor::block_on(restore_journal(
+				pool.clone(),
+				best_block_number,
+				transactions,
+			));
+		}
 		if let Some(background_task) = background_task {
 			spawner.spawn_essential("txpool-background", Some("transaction-pool"), background_task);
 		}
@@ -216,6 +253,8 @@ where
 				RevalidationType::Full => RevalidationStrategy::Always,
 			})),
 			ready_poll: Arc::new(Mutex::new(ReadyPoll::new(best_block_number))),
+			fork_views: Arc::new(Mutex::new(fork_views)),
+			journal,
 			metrics: PrometheusMetrics::new(prometheus),
 		}
 	}
@@ -248,12 +287,29 @@ where
 		xts: Vec<TransactionFor<Self>>,
 	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
 		let pool = self.pool.clone();
+		let views = self.fork_views.lock().pools();
+		let journal = self.journal.clone();
 		let at = *at;
 
 		self.metrics
 			.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
 
-		async move { pool.submit_at(&at, source, xts).await }.boxed()
+		async move {
+			if views.is_empty() && journal.is_none() {
+				return pool.submit_at(&at, source, xts).await
+			}
+
+			let results = pool.submit_at(&at, source, xts.clone()).await?;
+			let imported = xts
+				.into_iter()
+				.zip(&results)
+				.filter(|(_, result)| result.is_ok())
+				.map(|(xt, _)| (source, xt))
+				.collect();
+			on_imported(&pool, journal, views, imported).await;
+			Ok(results)
+		}
+		.boxed()
 	}
 
 	fn submit_one(
@@ -263,11 +319,18 @@ where
 		xt: TransactionFor<Self>,
 	) -> PoolFuture<TxHash<Self>, Self::Error> {
 		let pool = self.pool.clone();
+		let views = self.fork_views.lock().pools();
+		let journal = self.journal.clone();
 		let at = *at;
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
-		async move { pool.submit_one(&at, source, xt).await }.boxed()
+		async move {
+			let hash = pool.submit_one(&at, source, xt.clone()).await?;
+			on_imported(&pool, journal, views, vec![(source, xt)]).await;
+			Ok(hash)
+		}
+		.boxed()
 	}
 
 	fn submit_and_watch(
@@ -278,19 +341,90 @@ where
 	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
 		let at = *at;
 		let pool = self.pool.clone();
+		let views = self.fork_views.lock().pools();
+		let journal = self.journal.clone();
+
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		async move {
+			let watcher = pool.submit_and_watch(&at, source, xt.clone()).await?;
+			on_imported(&pool, journal, views, vec![(source, xt)]).await;
+
+			Ok(watcher.into_stream().boxed())
+		}
+		.boxed()
+	}
+
+	fn replace(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		old: TxHash<Self>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		let pool = self.pool.clone();
+		let views = self.fork_views.lock().pools();
+		let journal = self.journal.clone();
+		let at = *at;
+
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		async move {
+			let hash = pool.replace(&at, source, &old, xt.clone()).await?;
+			on_imported(&pool, journal, views, vec![(source, xt)]).await;
+			Ok(hash)
+		}
+		.boxed()
+	}
+
+	fn replace_and_watch(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		old: TxHash<Self>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		let pool = self.pool.clone();
+		let views = self.fork_views.lock().pools();
+		let journal = self.journal.clone();
+		let at = *at;
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
 		async move {
-			let watcher = pool.submit_and_watch(&at, source, xt).await?;
+			let watcher = pool.replace_and_watch(&at, source, &old, xt.clone()).await?;
+			on_imported(&pool, journal, views, vec![(source, xt)]).await;
 
 			Ok(watcher.into_stream().boxed())
 		}
 		.boxed()
 	}
 
+	fn submit_bundle(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<TxHash<Self>>, Self::Error> {
+		let pool = self.pool.clone();
+		let at = *at;
+
+		self.metrics
+			.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
+
+		async move { pool.submit_bundle(&at, source, xts).await }.boxed()
+	}
+
+	fn watch(&self, hash: &TxHash<Self>) -> Option<Pin<Box<TransactionStatusStreamFor<Self>>>> {
+		let (status, watcher) = self.pool.validated_pool().watch(*hash)?;
+		Some(stream::once(ready(status)).chain(watcher.into_stream()).boxed())
+	}
+
 	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
 		let removed = self.pool.validated_pool().remove_invalid(hashes);
+		for (_, view) in self.fork_views.lock().pools() {
+			view.validated_pool().remove_invalid(hashes);
+		}
 		self.metrics
 			.report(|metrics| metrics.validations_invalid.inc_by(removed.len() as u64));
 		removed
@@ -300,6 +434,10 @@ where
 		self.pool.validated_pool().status()
 	}
 
+	fn inspect(&self) -> PoolInspection<TxHash<Self>> {
+		self.pool.validated_pool().inspect()
+	}
+
 	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
 		self.pool.validated_pool().import_notification_stream()
 	}
@@ -344,6 +482,41 @@ where
 			.boxed()
 	}
 
+	fn ready_at_hash(&self, at: Block::Hash) -> PolledIterator<PoolApi> {
+		// The views are kept in sync by `maintain` and the submissions, they are only read here.
+		let mut fork_views = self.fork_views.lock();
+		if let Some(view) = fork_views.view(&at) {
+			let iterator: ReadyIteratorFor<PoolApi> = Box::new(view.validated_pool().ready());
+			return async move { iterator }.boxed()
+		}
+		if let Some(receiver) = fork_views.wait_view(at) {
+			let pool = self.pool.clone();
+			return async move {
+				match receiver.await {
+					Ok(Some(view)) =>
+						Box::new(view.validated_pool().ready()) as ReadyIteratorFor<PoolApi>,
+					// The view could not be built, or the main pool follows `at` now.
+					Ok(None) => Box::new(pool.validated_pool().ready()),
+					Err(e) => {
+						log::warn!("Error receiving view: {:?}", e);
+						Box::new(std::iter::empty())
+					},
+				}
+			}
+			.boxed()
+		}
+		drop(fork_views);
+
+		match self.api.block_id_to_number(&BlockId::Hash(at)) {
+			Ok(Some(number)) => self.ready_at(number),
+			_ => {
+				log::debug!(target: "txpool", "No number for block {:?}, returning ready set", at);
+				let iterator = self.ready();
+				async move { iterator }.boxed()
+			},
+		}
+	}
+
 	fn ready(&self) -> ReadyIteratorFor<PoolApi> {
 		Box::new(self.pool.validated_pool().ready())
 	}
@@ -371,11 +544,22 @@ where
 		spawner: impl SpawnEssentialNamed,
 		client: Arc<Client>,
 	) -> Arc<Self> {
-		let pool_api = Arc::new(FullChainApi::new(client.clone(), prometheus, &spawner));
+		let pool_api = FullChainApi::new(client.clone(), prometheus, &spawner);
+		Self::with_full_api(options, is_validator, prometheus, spawner, client, pool_api)
+	}
+
+	fn with_full_api(
+		options: graph::Options,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+		pool_api: FullChainApi<Client, Block>,
+	) -> Arc<Self> {
 		let pool = Arc::new(Self::with_revalidation_type(
 			options,
 			is_validator,
-			pool_api,
+			Arc::new(pool_api),
 			prometheus,
 			RevalidationType::Full,
 			spawner,
@@ -389,6 +573,40 @@ where
 	}
 }
 
+impl<Block, Client> FullPool<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sc_client_api::ExecutorProvider<Block>
+		+ sc_client_api::UsageProvider<Block>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>
+		+ sp_transaction_pool::runtime_api::TransactionInclusionApi<Block>
+		+ sp_api::Metadata<Block>,
+{
+	/// Create new basic transaction pool for a full node that reports the results of the
+	/// transactions included in blocks to their watchers.
+	///
+	/// Requires a runtime that implements `TransactionInclusionApi`, see
+	/// [`FullChainApi::with_inclusion_results`].
+	pub fn new_full_with_inclusion_results(
+		options: graph::Options,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+	) -> Arc<Self> {
+		let pool_api =
+			FullChainApi::new(client.clone(), prometheus, &spawner).with_inclusion_results();
+		Self::with_full_api(options, is_validator, prometheus, spawner, client, pool_api)
+	}
+}
+
 impl<Block, Client> sc_transaction_pool_api::LocalTransactionPool
 	for BasicPool<FullChainApi<Client, Block>, Block>
 where
@@ -433,12 +651,17 @@ where
 			block_number.saturated_into::<u64>(),
 			hash,
 			TransactionSource::Local,
-			xt,
+			xt.clone(),
 			bytes,
 			validity,
 		);
 
-		self.pool.validated_pool().submit(vec![validated]).remove(0)
+		let hash = self.pool.validated_pool().submit(vec![validated]).remove(0)?;
+		if let Some(journal) = &self.journal {
+			journal
+				.append_transaction::<FullChainApi<Client, Block>>(TransactionSource::Local, &xt);
+		}
+		Ok(hash)
 	}
 }
 
@@ -595,6 +818,8 @@ where
 				let revalidation_strategy = self.revalidation_strategy.clone();
 				let revalidation_queue = self.revalidation_queue.clone();
 				let ready_poll = self.ready_poll.clone();
+				let fork_views = self.fork_views.clone();
+				let journal = self.journal.clone();
 				let metrics = self.metrics.clone();
 
 				async move {
@@ -698,6 +923,13 @@ where
 					ready_poll.lock().trigger(block_number, move || {
 						Box::new(extra_pool.validated_pool().ready())
 					});
+					// The main pool follows this block now.
+					fork_views.lock().remove(hash);
+					// Validate the transactions resubmitted to the main pool at the views too.
+					let views = fork_views.lock().pools();
+					for (at, view) in views {
+						view::sync(&pool, &view, at).await;
+					}
 
 					if next_action.revalidate {
 						let hashes = pool.validated_pool().ready().map(|tx| tx.hash).collect();
@@ -705,11 +937,68 @@ where
 
 						revalidation_strategy.lock().clear();
 					}
+
+					if let Some(journal) = journal {
+						journal.compact_pool(&pool, false);
+					}
+				}
+				.boxed()
+			},
+			ChainEvent::NewBlock { hash } => {
+				let id = BlockId::hash(hash);
+				let block_number = match self.api.block_id_to_number(&id) {
+					Ok(Some(number)) => number,
+					_ => {
+						log::trace!(
+							target: "txpool",
+							"Skipping chain event - no number for that block {:?}",
+							id,
+						);
+						return Box::pin(ready(()))
+					},
+				};
+
+				// Authors only build on recent leaves.
+				let best_block_number = self.ready_poll.lock().updated_at();
+				if block_number + view::MAX_VIEW_DEPTH.into() < best_block_number ||
+					!self.fork_views.lock().start(hash)
+				{
+					return Box::pin(ready(()))
+				}
+
+				let pool = self.pool.clone();
+				let api = self.api.clone();
+				let fork_views = self.fork_views.clone();
+				let options = fork_views.lock().options().clone();
+
+				async move {
+					let view = view::build(api, &pool, options, hash).await;
+
+					let mut fork_views = fork_views.lock();
+					if let Some((_, included)) = &view {
+						// Report the inclusion in this fork to the watchers.
+						if let Err(e) =
+							pool.validated_pool().fire_pruned(&id, included.iter().cloned())
+						{
+							log::debug!(target: "txpool", "[{:?}] Error notifying watchers: {}", id, e);
+						}
+						fork_views.note_fork_block(block_number, hash);
+					}
+					fork_views.finish(hash, view.map(|(view, _)| (block_number, view)));
 				}
 				.boxed()
 			},
 			ChainEvent::Finalized { hash, tree_route } => {
 				let pool = self.pool.clone();
+				let stale_fork_blocks = match self.api.block_id_to_number(&BlockId::hash(hash)) {
+					Ok(Some(number)) => {
+						let finalized =
+							tree_route.iter().chain(&[hash]).cloned().collect::<Vec<_>>();
+						self.fork_views.lock().finalize(number, &finalized)
+					},
+					_ => Vec::new(),
+				};
+
 				async move {
 					for hash in tree_route.iter().chain(&[hash]) {
 						if let Err(e) = pool.validated_pool().on_block_finalized(*hash).await {
@@ -720,6 +1009,11 @@ where
 							)
 						}
 					}
+
+					// Blocks of other forks will never be finalized.
+					for hash in stale_fork_blocks {
+						pool.validated_pool().on_block_retracted(hash);
+					}
 				}
 				.boxed()
 			},
@@ -727,6 +1021,57 @@ where
 	}
 }
 
+/// Append the `transactions` imported to the main `pool` to the `journal` and validate them at
+/// the `views` as well.
+async fn on_imported<PoolApi: graph::ChainApi>(
+	pool: &graph::Pool<PoolApi>,
+	journal: Option<Arc<journal::Journal>>,
+	views: Vec<(graph::BlockHash<PoolApi>, Arc<graph::Pool<PoolApi>>)>,
+	transactions: Vec<(TransactionSource, graph::ExtrinsicFor<PoolApi>)>,
+) {
+	if let Some(journal) = journal {
+		for (source, xt) in &transactions {
+			journal.append_transaction::<PoolApi>(*source, xt);
+		}
+	}
+	for (at, view) in views {
+		view::import(pool, &view, at, transactions.clone()).await;
+	}
+}
+
+/// Import the `transactions` read from the journal again, validated at the block `at`.
+async fn restore_journal<PoolApi: graph::ChainApi>(
+	pool: Arc<graph::Pool<PoolApi>>,
+	at: NumberFor<PoolApi::Block>,
+	transactions: Vec<(TransactionSource, graph::ExtrinsicFor<PoolApi>)>,
+) {
+	let total = transactions.len();
+	let mut restored = 0;
+	for source in
+		[TransactionSource::InBlock, TransactionSource::Local, TransactionSource::External]
+	{
+		let xts = transactions
+			.iter()
+			.filter(|(s, _)| *s == source)
+			.map(|(_, xt)| xt.clone())
+			.collect::<Vec<_>>();
+		if xts.is_empty() {
+			continue
+		}
+		match pool.submit_at(&BlockId::Number(at), source, xts).await {
+			Ok(results) => restored += results.iter().filter(|result| result.is_ok()).count(),
+			Err(e) => log::warn!(target: "txpool", "Failed to restore the pool journal: {}", e),
+		}
+	}
+
+	log::info!(
+		target: "txpool",
+		"Restored {} of {} transactions from the pool journal",
+		restored,
+		total,
+	);
+}
+
 /// Inform the transaction pool about imported and finalized blocks.
 pub async fn notification_future<Client, Pool, Block>(client: Arc<Client>, txpool: Arc<Pool>)
 where
@@ -734,10 +1079,7 @@ where
 	Client: sc_client_api::BlockchainEvents<Block>,
 	Pool: MaintainedTransactionPool<Block = Block>,
 {
-	let import_stream = client
-		.import_notification_stream()
-		.filter_map(|n| ready(n.try_into().ok()))
-		.fuse();
+	let import_stream = client.import_notification_stream().map(Into::into).fuse();
 	let finality_stream = client.finality_notification_stream().map(Into::into).fuse();
 
 	futures::stream::select(import_stream, finality_stream)
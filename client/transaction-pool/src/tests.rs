@@ -21,7 +21,7 @@
 use crate::graph::{BlockHash, ChainApi, ExtrinsicFor, NumberFor, Pool};
 use codec::Encode;
 use parking_lot::Mutex;
-use sc_transaction_pool_api::error;
+use sc_transaction_pool_api::{error, InclusionResult};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Hash},
@@ -42,6 +42,7 @@ pub(crate) struct TestApi {
 	pub clear_requirements: Arc<Mutex<HashSet<H256>>>,
 	pub add_requirements: Arc<Mutex<HashSet<H256>>>,
 	pub validation_requests: Arc<Mutex<Vec<Extrinsic>>>,
+	pub inclusion_results: Arc<Mutex<Vec<(H256, InclusionResult)>>>,
 }
 
 impl TestApi {
@@ -173,6 +174,13 @@ impl ChainApi for TestApi {
 	) -> Result<Option<<Self::Block as BlockT>::Header>, Self::Error> {
 		Ok(None)
 	}
+
+	fn inclusion_results(
+		&self,
+		_: &BlockId<Self::Block>,
+	) -> Result<Vec<(H256, InclusionResult)>, Self::Error> {
+		Ok(self.inclusion_results.lock().clone())
+	}
 }
 
 pub(crate) fn uxt(transfer: Transfer) -> Extrinsic {
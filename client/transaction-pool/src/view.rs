@@ -0,0 +1,465 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Views of the pool at leaves that are not on the best chain.
+//!
+//! The main pool follows the best block only, but block authors may build on other recent
+//! leaves too. For each of the few most recent of those leaves a separate pool is validated at
+//! the leaf. It is seeded with the transactions of the main pool and the transactions of the
+//! best chain blocks that are not part of the fork.
+//!
+//! Submitted transactions are validated at the views once they are imported to the main pool,
+//! see [`import`]. The views are also synced with the main pool in the background whenever a
+//! block is imported, see [`sync`], so that authoring only reads them. The state of the block of
+//! a view never changes, so its transactions are not revalidated, but the ones reported invalid
+//! by block authoring are removed from the views as well as from the main pool.
+//!
+//! Watchers get the statuses at the best block, plus `ReadyAtLeaf` and `InvalidAtLeaf` when a
+//! view imports the transaction or rejects it. The inclusion in a block of a view is reported as
+//! `InBlock`, and as `Retracted` once that block can no longer be finalized.
+
+use std::{
+	collections::{HashSet, VecDeque},
+	sync::Arc,
+};
+
+use futures::channel::oneshot;
+use sc_transaction_pool_api::{
+	error::{self, IntoPoolError},
+	TransactionSource,
+};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Extrinsic, Header as HeaderT, One},
+};
+
+use crate::graph::{self, BlockHash, ChainApi, ExtrinsicFor, ExtrinsicHash, NumberFor};
+
+/// Maximum number of views kept at the same time.
+const MAX_VIEWS: usize = 4;
+
+/// Views are only built for blocks at most this many blocks behind the best block.
+pub(crate) const MAX_VIEW_DEPTH: u32 = 4;
+
+/// Maximum number of blocks on either side of a fork for which a view is built.
+const MAX_FORK_LENGTH: usize = 32;
+
+/// Sends the view once built, or `None` if it could not be built.
+type ViewSender<PoolApi> = oneshot::Sender<Option<Arc<graph::Pool<PoolApi>>>>;
+
+struct View<PoolApi: ChainApi> {
+	hash: BlockHash<PoolApi>,
+	number: NumberFor<PoolApi>,
+	pool: Arc<graph::Pool<PoolApi>>,
+}
+
+/// Views of the pool at recent leaves that are not the best block.
+pub(crate) struct ForkViews<PoolApi: ChainApi> {
+	options: graph::Options,
+	/// Views, the oldest first.
+	views: VecDeque<View<PoolApi>>,
+	/// Blocks whose view is being built.
+	building: HashSet<BlockHash<PoolApi>>,
+	/// Receivers of the views that are being built, which get `None` if the view could not be
+	/// built or the main pool follows its block now.
+	pollers: Vec<(BlockHash<PoolApi>, ViewSender<PoolApi>)>,
+	/// Blocks not on the best chain whose transactions were reported as included.
+	fork_blocks: Vec<(NumberFor<PoolApi>, BlockHash<PoolApi>)>,
+}
+
+impl<PoolApi: ChainApi + 'static> ForkViews<PoolApi> {
+	pub fn new(options: graph::Options) -> Self {
+		Self {
+			options,
+			views: Default::default(),
+			building: Default::default(),
+			pollers: Default::default(),
+			fork_blocks: Default::default(),
+		}
+	}
+
+	/// Options of the pools of the views.
+	pub fn options(&self) -> &graph::Options {
+		&self.options
+	}
+
+	/// The view at `at`, if there is one.
+	pub fn view(&self, at: &BlockHash<PoolApi>) -> Option<Arc<graph::Pool<PoolApi>>> {
+		self.views.iter().find(|view| view.hash == *at).map(|view| view.pool.clone())
+	}
+
+	/// Wait for the view at `at`, if it is being built.
+	pub fn wait_view(
+		&mut self,
+		at: BlockHash<PoolApi>,
+	) -> Option<oneshot::Receiver<Option<Arc<graph::Pool<PoolApi>>>>> {
+		if !self.building.contains(&at) {
+			return None
+		}
+
+		let (sender, receiver) = oneshot::channel();
+		self.pollers.push((at, sender));
+		Some(receiver)
+	}
+
+	/// All the views, with the hashes of their blocks.
+	pub fn pools(&self) -> Vec<(BlockHash<PoolApi>, Arc<graph::Pool<PoolApi>>)> {
+		self.views.iter().map(|view| (view.hash, view.pool.clone())).collect()
+	}
+
+	/// Start building the view at `at`.
+	///
+	/// Returns `false` if the view exists already or is being built.
+	pub fn start(&mut self, at: BlockHash<PoolApi>) -> bool {
+		!self.views.iter().any(|view| view.hash == at) && self.building.insert(at)
+	}
+
+	/// Finish building the view at `at`, which is `None` if it could not be built.
+	pub fn finish(
+		&mut self,
+		at: BlockHash<PoolApi>,
+		view: Option<(NumberFor<PoolApi>, Arc<graph::Pool<PoolApi>>)>,
+	) {
+		if !self.building.remove(&at) {
+			// The block became the best block in the meantime.
+			return
+		}
+
+		match view {
+			Some((number, pool)) => {
+				self.views.push_back(View { hash: at, number, pool: pool.clone() });
+				while self.views.len() > MAX_VIEWS {
+					self.views.pop_front();
+				}
+				self.trigger(at, Some(pool));
+			},
+			None => self.trigger(at, None),
+		}
+	}
+
+	/// Remove the view at `at`, because the main pool follows that block now.
+	pub fn remove(&mut self, at: BlockHash<PoolApi>) {
+		self.views.retain(|view| view.hash != at);
+		self.building.remove(&at);
+		self.trigger(at, None);
+	}
+
+	/// Remember that the transactions of `at` were reported as included.
+	pub fn note_fork_block(&mut self, number: NumberFor<PoolApi>, at: BlockHash<PoolApi>) {
+		self.fork_blocks.push((number, at));
+	}
+
+	/// Remove the views at or below the finalized block `number`.
+	///
+	/// Returns the blocks not on the best chain whose transactions were reported as included
+	/// and that can not be finalized anymore, except for `finalized`.
+	pub fn finalize(
+		&mut self,
+		number: NumberFor<PoolApi>,
+		finalized: &[BlockHash<PoolApi>],
+	) -> Vec<BlockHash<PoolApi>> {
+		self.views.retain(|view| view.number > number);
+
+		let mut stale = Vec::new();
+		self.fork_blocks.retain(|(block_number, hash)| {
+			if *block_number > number {
+				return true
+			}
+			if !finalized.contains(hash) {
+				stale.push(*hash);
+			}
+			false
+		});
+		stale
+	}
+
+	fn trigger(&mut self, at: BlockHash<PoolApi>, view: Option<Arc<graph::Pool<PoolApi>>>) {
+		let mut idx = 0;
+		while idx < self.pollers.len() {
+			if self.pollers[idx].0 == at {
+				let poller_sender = self.pollers.swap_remove(idx);
+				log::debug!(target: "txpool", "Sending ready signal of view at block {:?}", at);
+				let _ = poller_sender.1.send(view.clone());
+			} else {
+				idx += 1;
+			}
+		}
+	}
+}
+
+/// Blocks of a fork and of the best chain since their common ancestor.
+struct ForkRoute<Hash> {
+	/// Blocks of the fork, the leaf first.
+	enacted: Vec<Hash>,
+	/// Blocks of the best chain.
+	retracted: Vec<Hash>,
+}
+
+fn fork_route<PoolApi: ChainApi>(
+	api: &PoolApi,
+	leaf: BlockHash<PoolApi>,
+) -> Result<Option<ForkRoute<BlockHash<PoolApi>>>, PoolApi::Error> {
+	let mut enacted = Vec::new();
+	let mut current = leaf;
+	let ancestor = loop {
+		let header = match api.block_header(&BlockId::Hash(current))? {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+		let number = *header.number();
+		if api.block_id_to_hash(&BlockId::Number(number))? == Some(current) {
+			break number
+		}
+
+		enacted.push(current);
+		if enacted.len() > MAX_FORK_LENGTH {
+			return Ok(None)
+		}
+		current = *header.parent_hash();
+	};
+
+	let mut retracted = Vec::new();
+	let mut number = ancestor + One::one();
+	while let Some(hash) = api.block_id_to_hash(&BlockId::Number(number))? {
+		retracted.push(hash);
+		if retracted.len() > MAX_FORK_LENGTH {
+			return Ok(None)
+		}
+		number += One::one();
+	}
+
+	Ok(Some(ForkRoute { enacted, retracted }))
+}
+
+async fn block_body<PoolApi: ChainApi>(
+	api: &PoolApi,
+	at: BlockHash<PoolApi>,
+) -> Vec<ExtrinsicFor<PoolApi>> {
+	api.block_body(&BlockId::Hash(at))
+		.await
+		.unwrap_or_else(|e| {
+			log::warn!("Failed to fetch block body: {}", e);
+			None
+		})
+		.unwrap_or_default()
+}
+
+/// Build the view at the block `at` that is not on the best chain.
+///
+/// Returns the pool of the view and the hashes of the transactions included in `at`, or `None`
+/// if `at` is on the best chain or too far away from it.
+pub(crate) async fn build<PoolApi: ChainApi>(
+	api: Arc<PoolApi>,
+	pool: &graph::Pool<PoolApi>,
+	options: graph::Options,
+	at: BlockHash<PoolApi>,
+) -> Option<(Arc<graph::Pool<PoolApi>>, Vec<ExtrinsicHash<PoolApi>>)> {
+	let route = match fork_route(&*api, at) {
+		Ok(Some(route)) if !route.enacted.is_empty() => route,
+		Ok(_) => {
+			log::debug!(target: "txpool", "No view built for block {:?}", at);
+			return None
+		},
+		Err(e) => {
+			log::warn!(target: "txpool", "Error computing fork route of {:?}: {}", at, e);
+			return None
+		},
+	};
+
+	// Transactions of the fork are known to be included at `at`.
+	let mut included = Vec::new();
+	let mut known = HashSet::new();
+	for (idx, hash) in route.enacted.iter().enumerate() {
+		let hashes = block_body(&*api, *hash).await.iter().map(|xt| pool.hash_of(xt)).collect();
+		if idx == 0 {
+			included = hashes;
+			known.extend(included.iter().cloned());
+		} else {
+			known.extend(hashes);
+		}
+	}
+
+	let mut transactions = Vec::new();
+	for hash in route.retracted {
+		transactions.extend(
+			block_body(&*api, hash)
+				.await
+				.into_iter()
+				.filter(|xt| xt.is_signed().unwrap_or(true))
+				.map(|xt| (TransactionSource::External, xt)),
+		);
+	}
+	// Bundles are left out, the view would import their transactions one by one.
+	transactions.extend(pool.validated_pool().transactions());
+	transactions.retain(|(_, xt)| known.insert(pool.hash_of(xt)));
+
+	let view = Arc::new(graph::Pool::new(options, true.into(), api));
+	if let Err(e) = submit(pool, &view, at, transactions).await {
+		log::warn!(target: "txpool", "[{:?}] Error submitting transactions to view: {}", at, e);
+		return None
+	}
+
+	log::debug!(
+		target: "txpool",
+		"Built view at block {:?}: {:?}",
+		at,
+		view.validated_pool().status(),
+	);
+	Some((view, included))
+}
+
+/// Validate the transactions of the main `pool` that the view at `at` doesn't know yet.
+///
+/// Called in the background when blocks are imported, so that the view contains the
+/// transactions that it missed, e.g. the ones resubmitted to the main pool.
+pub(crate) async fn sync<PoolApi: ChainApi>(
+	pool: &graph::Pool<PoolApi>,
+	view: &graph::Pool<PoolApi>,
+	at: BlockHash<PoolApi>,
+) {
+	import(pool, view, at, pool.validated_pool().transactions()).await
+}
+
+/// Validate the `transactions` of the main `pool` that the view at `at` doesn't know yet and
+/// import them to the view.
+pub(crate) async fn import<PoolApi: ChainApi>(
+	pool: &graph::Pool<PoolApi>,
+	view: &graph::Pool<PoolApi>,
+	at: BlockHash<PoolApi>,
+	mut transactions: Vec<(TransactionSource, ExtrinsicFor<PoolApi>)>,
+) {
+	transactions
+		.retain(|(_, xt)| view.validated_pool().check_is_known(&pool.hash_of(xt), false).is_ok());
+	if transactions.is_empty() {
+		return
+	}
+
+	log::debug!(target: "txpool", "[{:?}] Importing {} transactions to view", at, transactions.len());
+	if let Err(e) = submit(pool, view, at, transactions).await {
+		log::warn!(target: "txpool", "[{:?}] Error importing transactions to view: {}", at, e);
+	}
+}
+
+/// Validate `transactions` at `at` and import them to `view`, without checking whether they are
+/// banned from the view.
+///
+/// The watchers of the main `pool` are notified of the transactions that are ready or invalid at
+/// `at`.
+async fn submit<PoolApi: ChainApi>(
+	pool: &graph::Pool<PoolApi>,
+	view: &graph::Pool<PoolApi>,
+	at: BlockHash<PoolApi>,
+	transactions: Vec<(TransactionSource, ExtrinsicFor<PoolApi>)>,
+) -> Result<(), PoolApi::Error> {
+	let mut imported = Vec::new();
+	let mut invalid = Vec::new();
+	for source in
+		[TransactionSource::Local, TransactionSource::InBlock, TransactionSource::External]
+	{
+		let xts = transactions
+			.iter()
+			.filter(|(tx_source, _)| *tx_source == source)
+			.map(|(_, xt)| xt.clone())
+			.collect::<Vec<_>>();
+		if xts.is_empty() {
+			continue
+		}
+
+		let results = view.resubmit_at(&BlockId::Hash(at), source, xts.iter().cloned()).await?;
+		for (xt, result) in xts.iter().zip(results) {
+			match result.map_err(IntoPoolError::into_pool_error) {
+				Ok(hash) => imported.push(hash),
+				Err(Ok(error::Error::InvalidTransaction(_))) => invalid.push(view.hash_of(xt)),
+				Err(e) => {
+					log::trace!(target: "txpool", "[{:?}] Transaction not imported to view: {:?}", at, e);
+				},
+			}
+		}
+	}
+
+	// Transactions imported as future may have been promoted by the ones imported after them.
+	imported.retain(|hash| view.validated_pool().ready_by_hash(hash).is_some());
+	pool.validated_pool().on_leaf_statuses(at, &imported, &invalid);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{pool, TestApi};
+	use futures::FutureExt;
+	use substrate_test_runtime::H256;
+
+	fn hash(n: u64) -> H256 {
+		H256::from_low_u64_be(1000 + n)
+	}
+
+	#[test]
+	fn waiting_for_view_resolves_once_built() {
+		let mut views = ForkViews::<TestApi>::new(Default::default());
+		assert!(views.wait_view(hash(1)).is_none());
+
+		assert!(views.start(hash(1)));
+		assert!(!views.start(hash(1)));
+		let mut receiver = views.wait_view(hash(1)).unwrap();
+		assert!(receiver.try_recv().unwrap().is_none());
+
+		views.finish(hash(1), Some((1, Arc::new(pool()))));
+		assert!(receiver.now_or_never().unwrap().unwrap().is_some());
+		assert!(views.view(&hash(1)).is_some());
+		assert!(!views.start(hash(1)));
+
+		// The main pool follows a block whose view is being built.
+		assert!(views.start(hash(2)));
+		let receiver = views.wait_view(hash(2)).unwrap();
+		views.remove(hash(2));
+		assert!(receiver.now_or_never().unwrap().unwrap().is_none());
+	}
+
+	#[test]
+	fn only_recent_views_are_kept() {
+		let mut views = ForkViews::<TestApi>::new(Default::default());
+		for n in 0..=MAX_VIEWS as u64 {
+			assert!(views.start(hash(n)));
+			views.finish(hash(n), Some((1, Arc::new(pool()))));
+		}
+
+		assert_eq!(views.pools().len(), MAX_VIEWS);
+		assert!(views.view(&hash(0)).is_none());
+		assert!(views.view(&hash(MAX_VIEWS as u64)).is_some());
+
+		views.remove(hash(1));
+		assert!(views.view(&hash(1)).is_none());
+		assert_eq!(views.pools().len(), MAX_VIEWS - 1);
+	}
+
+	#[test]
+	fn finalization_removes_views_and_returns_stale_blocks() {
+		let mut views = ForkViews::<TestApi>::new(Default::default());
+		for (n, number) in [(1, 1), (2, 1), (3, 2)] {
+			assert!(views.start(hash(n)));
+			views.finish(hash(n), Some((number, Arc::new(pool()))));
+			views.note_fork_block(number, hash(n));
+		}
+
+		assert_eq!(views.finalize(1, &[hash(1)]), vec![hash(2)]);
+		assert!(views.view(&hash(1)).is_none());
+		assert!(views.view(&hash(2)).is_none());
+		assert!(views.view(&hash(3)).is_some());
+	}
+}
@@ -862,6 +862,102 @@ fn ready_set_should_eventually_resolve_when_block_update_arrives() {
 	}
 }
 
+#[test]
+fn ready_at_fork_should_contain_transactions_of_retracted_blocks() {
+	let (pool, api, _guard) = maintained_pool();
+	let xt1 = uxt(Alice, 209);
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, xt1.clone())).expect("1. Imported");
+
+	let best = api.push_block(1, vec![xt1.clone()], true);
+	block_on(pool.maintain(block_event(best.clone())));
+	assert_eq!(pool.status().ready, 0);
+
+	let fork = api.push_block_with_parent(best.parent_hash, vec![], false);
+	block_on(pool.maintain(ChainEvent::NewBlock { hash: fork.hash() }));
+
+	// transactions submitted later are added to the view as well
+	let xt2 = uxt(Bob, 0);
+	block_on(pool.submit_one(&BlockId::number(1), SOURCE, xt2.clone())).expect("2. Imported");
+
+	let ready = pool
+		.ready_at_hash(fork.hash())
+		.now_or_never()
+		.expect("View is built")
+		.map(|tx| tx.data.clone())
+		.collect::<Vec<_>>();
+	assert_eq!(ready, vec![xt1, xt2.clone()]);
+
+	let ready = pool
+		.ready_at_hash(best.hash())
+		.now_or_never()
+		.expect("Best block is processed")
+		.map(|tx| tx.data.clone())
+		.collect::<Vec<_>>();
+	assert_eq!(ready, vec![xt2]);
+}
+
+#[test]
+fn invalid_transactions_should_be_removed_from_views() {
+	let (pool, api, _guard) = maintained_pool();
+	let xt = uxt(Alice, 209);
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, xt.clone())).expect("1. Imported");
+
+	let fork = api.push_block(1, vec![], false);
+	block_on(pool.maintain(ChainEvent::NewBlock { hash: fork.hash() }));
+	assert_eq!(pool.ready_at_hash(fork.hash()).now_or_never().expect("View is built").count(), 1);
+
+	pool.remove_invalid(&[pool.hash_of(&xt)]);
+
+	let ready = pool.ready_at_hash(fork.hash()).now_or_never().expect("View is built");
+	assert_eq!(ready.count(), 0);
+}
+
+#[test]
+fn should_report_ready_at_views() {
+	let (pool, api, _guard) = maintained_pool();
+	let xt1 = uxt(Alice, 209);
+	let watcher1 =
+		block_on(pool.submit_and_watch(&BlockId::number(0), SOURCE, xt1)).expect("1. Imported");
+
+	let fork = api.push_block(1, vec![], false);
+	block_on(pool.maintain(ChainEvent::NewBlock { hash: fork.hash() }));
+
+	// transactions submitted later are validated at the view right away
+	let xt2 = uxt(Alice, 210);
+	let watcher2 =
+		block_on(pool.submit_and_watch(&BlockId::number(0), SOURCE, xt2)).expect("2. Imported");
+
+	let mut stream = futures::executor::block_on_stream(watcher1);
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::ReadyAtLeaf(fork.hash())));
+	let mut stream = futures::executor::block_on_stream(watcher2);
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::ReadyAtLeaf(fork.hash())));
+}
+
+#[test]
+fn should_report_inclusion_in_fork_blocks() {
+	let (pool, api, _guard) = maintained_pool();
+	let xt = uxt(Alice, 209);
+	let watcher = block_on(pool.submit_and_watch(&BlockId::number(0), SOURCE, xt.clone()))
+		.expect("1. Imported");
+
+	let fork = api.push_block(1, vec![xt], false);
+	block_on(pool.maintain(ChainEvent::NewBlock { hash: fork.hash() }));
+	assert_eq!(pool.status().ready, 1);
+
+	let best = api.push_block_with_parent(fork.parent_hash, vec![], true);
+	block_on(pool.maintain(block_event(best.clone())));
+	block_on(
+		pool.maintain(ChainEvent::Finalized { hash: best.hash(), tree_route: Arc::from(vec![]) }),
+	);
+
+	let mut stream = futures::executor::block_on_stream(watcher);
+	assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+	assert_eq!(stream.next(), Some(TransactionStatus::InBlock(fork.hash())));
+	assert_eq!(stream.next(), Some(TransactionStatus::Retracted(fork.hash())));
+}
+
 #[test]
 fn should_not_accept_old_signatures() {
 	let client = Arc::new(substrate_test_runtime_client::new());
@@ -997,3 +1093,29 @@ fn stale_transactions_are_pruned() {
 	assert_eq!(pool.status().future, 0);
 	assert_eq!(pool.status().ready, 0);
 }
+
+#[test]
+fn should_restore_the_journal_with_light_revalidation() {
+	let dir = tempfile::tempdir().unwrap();
+	let options = Options { journal: Some(dir.path().join("journal")), ..Default::default() };
+	let api = Arc::new(TestApi::with_alice_nonce(209));
+	let new_pool = || {
+		BasicPool::with_revalidation_type(
+			options.clone(),
+			true.into(),
+			api.clone(),
+			None,
+			RevalidationType::Light,
+			sp_core::testing::TaskExecutor::new(),
+			0,
+		)
+	};
+
+	let pool = new_pool();
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209))).unwrap();
+	drop(pool);
+
+	// The transactions are in the pool as soon as it is created.
+	let pool = new_pool();
+	assert_eq!(pool.status().ready, 1);
+}
@@ -0,0 +1,109 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Token bucket for rate limiting.
+
+use std::time::{Duration, Instant};
+
+/// Bucket of tokens refilled continuously, up to its capacity.
+///
+/// More tokens than available may be consumed, the bucket then has to refill its debt before
+/// tokens are available again.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+	/// Maximum number of tokens.
+	capacity: f64,
+	/// Number of tokens added per second.
+	rate: f64,
+	/// Number of available tokens. Negative if more tokens than available were consumed.
+	tokens: f64,
+	/// Last time tokens were added.
+	last_refill: Instant,
+}
+
+impl TokenBucket {
+	/// Create a full bucket of `capacity` tokens, refilled with `capacity` tokens every `period`.
+	pub fn new(capacity: u64, period: Duration, now: Instant) -> Self {
+		let capacity = capacity as f64;
+		Self { capacity, rate: capacity / period.as_secs_f64(), tokens: capacity, last_refill: now }
+	}
+
+	/// Maximum number of tokens.
+	pub fn capacity(&self) -> f64 {
+		self.capacity
+	}
+
+	/// Number of available tokens, as of the last refill.
+	pub fn tokens(&self) -> f64 {
+		self.tokens
+	}
+
+	/// Add the tokens accumulated since the last refill.
+	pub fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+		self.last_refill = now;
+	}
+
+	/// Take `tokens` tokens out of the bucket, even if fewer are available.
+	pub fn consume(&mut self, tokens: f64) {
+		self.tokens -= tokens;
+	}
+
+	/// Returns the time until more than `reserve` tokens are available, if they aren't yet.
+	pub fn wait_for(&self, reserve: f64) -> Option<Duration> {
+		(self.tokens <= reserve)
+			.then(|| Duration::from_secs_f64((reserve - self.tokens + 1.0) / self.rate))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn refills_up_to_capacity() {
+		let now = Instant::now();
+		let mut bucket = TokenBucket::new(60, Duration::from_secs(60), now);
+		bucket.consume(60.0);
+		assert_eq!(bucket.wait_for(0.0), Some(Duration::from_secs(1)));
+
+		bucket.refill(now + Duration::from_secs(30));
+		assert_eq!(bucket.tokens(), 30.0);
+		assert_eq!(bucket.wait_for(0.0), None);
+
+		bucket.refill(now + Duration::from_secs(120));
+		assert_eq!(bucket.tokens(), bucket.capacity());
+	}
+
+	#[test]
+	fn debt_is_refilled_first() {
+		let now = Instant::now();
+		let mut bucket = TokenBucket::new(10, Duration::from_secs(1), now);
+		bucket.consume(30.0);
+		assert_eq!(bucket.tokens(), -20.0);
+		assert_eq!(bucket.wait_for(0.0), Some(Duration::from_secs_f64(2.1)));
+
+		bucket.refill(now + Duration::from_secs(2));
+		assert_eq!(bucket.wait_for(0.0), Some(Duration::from_secs_f64(0.1)));
+		// Tokens set aside are not available.
+		bucket.refill(now + Duration::from_millis(2500));
+		assert_eq!(bucket.wait_for(0.0), None);
+		assert!(bucket.wait_for(5.0).is_some());
+	}
+}
@@ -125,6 +125,7 @@ use frame_support::{
 	},
 	weights::{DispatchClass, DispatchInfo, GetDispatchInfo},
 };
+use sp_block_builder::{BlockResources, ClassLimits, ExtrinsicClass, ExtrinsicResources, PerClass};
 use sp_runtime::{
 	generic::Digest,
 	traits::{
@@ -456,6 +457,52 @@ where
 		Ok(r.map(|_| ()).map_err(|e| e.error))
 	}
 
+	/// Get the resources consumed by the block being built, and the limits on them.
+	pub fn block_resources() -> BlockResources {
+		let weights = <System::BlockWeights as frame_support::traits::Get<_>>::get();
+		let length = <System::BlockLength as frame_support::traits::Get<_>>::get();
+		let consumed_weight = <frame_system::Pallet<System>>::block_weight();
+
+		BlockResources {
+			max_weight: weights.max_block,
+			limits: PerClass::new(|class| {
+				let class = dispatch_class(class);
+				let class_weights = weights.get(class);
+				ClassLimits {
+					max_extrinsic: class_weights
+						.max_extrinsic
+						.map(|max| max.saturating_add(class_weights.base_extrinsic)),
+					max_total: class_weights.max_total,
+					reserved: class_weights.reserved,
+					max_length: *length.max.get(class),
+				}
+			}),
+			weight: PerClass::new(|class| *consumed_weight.get(dispatch_class(class))),
+			length: <frame_system::Pallet<System>>::all_extrinsics_len(),
+		}
+	}
+
+	/// Get the resources the given extrinsic would consume when applied.
+	pub fn extrinsic_resources(uxt: Block::Extrinsic) -> ExtrinsicResources
+	where
+		Block::Extrinsic: GetDispatchInfo,
+	{
+		let dispatch_info = uxt.get_dispatch_info();
+		let base_extrinsic = <System::BlockWeights as frame_support::traits::Get<_>>::get()
+			.get(dispatch_info.class)
+			.base_extrinsic;
+
+		ExtrinsicResources {
+			class: match dispatch_info.class {
+				DispatchClass::Normal => ExtrinsicClass::Normal,
+				DispatchClass::Operational => ExtrinsicClass::Operational,
+				DispatchClass::Mandatory => ExtrinsicClass::Mandatory,
+			},
+			weight: dispatch_info.weight.saturating_add(base_extrinsic),
+			length: uxt.encoded_size() as u32,
+		}
+	}
+
 	fn final_checks(header: &System::Header) {
 		sp_tracing::enter_span!(sp_tracing::Level::TRACE, "final_checks");
 		// remove temporaries
@@ -544,6 +591,15 @@ where
 	}
 }
 
+/// The dispatch class matching the given extrinsic class.
+fn dispatch_class(class: ExtrinsicClass) -> DispatchClass {
+	match class {
+		ExtrinsicClass::Normal => DispatchClass::Normal,
+		ExtrinsicClass::Operational => DispatchClass::Operational,
+		ExtrinsicClass::Mandatory => DispatchClass::Mandatory,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1094,6 +1150,44 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn block_resources_follow_applied_extrinsics() {
+		let xt = TestXt::new(
+			Call::Balances(BalancesCall::transfer { dest: 33, value: 0 }),
+			sign_extra(1, 0, 0),
+		);
+		let len = xt.clone().encode().len() as u32;
+		new_test_ext(1).execute_with(|| {
+			Executive::initialize_block(&Header::new(
+				1,
+				H256::default(),
+				H256::default(),
+				[69u8; 32].into(),
+				Digest::default(),
+			));
+			let mut resources = Executive::block_resources();
+			assert_eq!(resources.length, 0);
+
+			let extrinsic_resources = Executive::extrinsic_resources(xt.clone());
+			// default weight for `TestXt` == encoded length.
+			assert_eq!(
+				extrinsic_resources,
+				ExtrinsicResources {
+					class: ExtrinsicClass::Normal,
+					weight: len as Weight +
+						<Runtime as frame_system::Config>::BlockWeights::get()
+							.get(DispatchClass::Normal)
+							.base_extrinsic,
+					length: len,
+				},
+			);
+
+			assert!(Executive::apply_extrinsic(xt).unwrap().is_ok());
+			assert!(resources.consume(&extrinsic_resources));
+			assert_eq!(Executive::block_resources(), resources);
+		});
+	}
+
 	#[test]
 	fn validate_unsigned() {
 		let valid = TestXt::new(Call::Custom(custom::Call::allowed_unsigned {}), None);
@@ -1388,7 +1482,8 @@ mod tests {
 				frame_system::Pallet::<Runtime>::block_weight().total(),
 				custom_runtime_upgrade_weight +
 					runtime_upgrade_weight +
-					on_initialize_weight + base_block_weight,
+					on_initialize_weight +
+					base_block_weight,
 			);
 		});
 	}
@@ -23,6 +23,26 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::{traits::Block as BlockT, RuntimeDebug};
+use sp_std::vec::Vec;
+
+/// Components of the inclusion fee of an extrinsic, see
+/// `pallet_transaction_payment::InclusionFee`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct FeeBreakdown {
+	/// Minimum amount a user pays for a transaction.
+	pub base_fee: u128,
+	/// Amount paid for the encoded length of the extrinsic.
+	pub len_fee: u128,
+	/// Amount paid for the weight of the extrinsic, adjusted by the fee multiplier.
+	pub adjusted_weight_fee: u128,
+}
+
 sp_api::decl_runtime_apis! {
 	/// The API to query account nonce (aka transaction index).
 	pub trait AccountNonceApi<AccountId, Index> where
@@ -32,4 +52,20 @@ sp_api::decl_runtime_apis! {
 		/// Get current account nonce of given `AccountId`.
 		fn account_nonce(account: AccountId) -> Index;
 	}
+
+	/// The API to query the effects of extrinsics applied on top of a block, used to dry run
+	/// them.
+	pub trait DryRunApi {
+		/// Get the SCALE encoded `frame_system::EventRecord`s deposited while applying the
+		/// extrinsic at `index` of the current block.
+		fn extrinsic_events(index: u32) -> Vec<u8>;
+
+		/// Get the inclusion fee of the given extrinsic with the given encoded length, `None`
+		/// if it doesn't pay one.
+		fn fee_breakdown(uxt: <Block as BlockT>::Extrinsic, len: u32) -> Option<FeeBreakdown>;
+
+		/// Get the names of the pallets of the runtime, which prefix the storage keys of their
+		/// storage items.
+		fn pallet_names() -> Vec<Vec<u8>>;
+	}
 }
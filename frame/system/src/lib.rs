@@ -1423,6 +1423,18 @@ impl<T: Config> Pallet<T> {
 		Events::<T>::get()
 	}
 
+	/// Get the events deposited while applying the extrinsic at `index` of the current block.
+	///
+	/// Should only be called if you know what you are doing and outside of the runtime block
+	/// execution else it can have a large impact on the PoV size of a block.
+	pub fn extrinsic_events(index: u32) -> Vec<EventRecord<T::Event, T::Hash>> {
+		Self::read_events_no_consensus()
+			.into_iter()
+			.filter(|e| e.phase == Phase::ApplyExtrinsic(index))
+			.map(|e| *e)
+			.collect()
+	}
+
 	/// Set the block number to something in particular. Can be used as an alternative to
 	/// `initialize` for tests that don't need to bother with the other environment entries.
 	#[cfg(any(feature = "std", feature = "runtime-benchmarks", test))]
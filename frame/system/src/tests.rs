@@ -273,6 +273,42 @@ fn deposit_event_uses_actual_weight() {
 	});
 }
 
+#[test]
+fn extrinsic_events_are_filtered_by_index() {
+	new_test_ext().execute_with(|| {
+		System::reset_events();
+		System::initialize(&1, &[0u8; 32].into(), &Default::default());
+		System::deposit_event(SysEvent::CodeUpdated);
+		System::note_finished_initialize();
+
+		let info = DispatchInfo { weight: 100, ..Default::default() };
+		System::note_applied_extrinsic(&Ok(().into()), info);
+		System::deposit_event(SysEvent::NewAccount { account: 32 });
+		System::note_applied_extrinsic(&Err(DispatchError::BadOrigin.into()), info);
+
+		assert_eq!(
+			System::extrinsic_events(1),
+			vec![
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(1),
+					event: SysEvent::NewAccount { account: 32 }.into(),
+					topics: vec![]
+				},
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(1),
+					event: SysEvent::ExtrinsicFailed {
+						dispatch_error: DispatchError::BadOrigin.into(),
+						dispatch_info: info,
+					}
+					.into(),
+					topics: vec![]
+				},
+			]
+		);
+		assert!(System::extrinsic_events(2).is_empty());
+	});
+}
+
 #[test]
 fn deposit_event_topics() {
 	new_test_ext().execute_with(|| {
@@ -60,7 +60,8 @@ use sp_runtime::{
 	},
 	FixedPointNumber, FixedPointOperand, FixedU128, Perquintill, RuntimeDebug,
 };
-use sp_std::prelude::*;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+use sp_transaction_pool::runtime_api::{ExtrinsicClass, InclusionResult};
 
 use frame_support::{
 	dispatch::DispatchResult,
@@ -445,6 +446,56 @@ where
 		}
 	}
 
+	/// Get the results of the extrinsics of the current block, as reported by the events of
+	/// `frame_system` and of this pallet.
+	pub fn inclusion_results() -> Vec<InclusionResult>
+	where
+		<T as frame_system::Config>::Event: TryInto<frame_system::Event<T>> + TryInto<Event<T>>,
+	{
+		let mut fees = BTreeMap::new();
+		let mut results = Vec::new();
+		for record in <frame_system::Pallet<T>>::read_events_no_consensus() {
+			let index = match record.phase {
+				frame_system::Phase::ApplyExtrinsic(index) => index,
+				_ => continue,
+			};
+
+			if let Ok(Event::<T>::TransactionFeePaid { actual_fee, .. }) =
+				record.event.clone().try_into()
+			{
+				fees.insert(index, actual_fee.saturated_into());
+				continue
+			}
+
+			let (dispatch_info, result) = match record.event.try_into() {
+				Ok(frame_system::Event::<T>::ExtrinsicSuccess { dispatch_info }) =>
+					(dispatch_info, Ok(())),
+				Ok(frame_system::Event::<T>::ExtrinsicFailed { dispatch_error, dispatch_info }) =>
+					(dispatch_info, Err(dispatch_error)),
+				_ => continue,
+			};
+			results.push(InclusionResult {
+				index,
+				dispatch_info: sp_transaction_pool::runtime_api::DispatchInfo {
+					weight: dispatch_info.weight,
+					class: match dispatch_info.class {
+						DispatchClass::Normal => ExtrinsicClass::Normal,
+						DispatchClass::Operational => ExtrinsicClass::Operational,
+						DispatchClass::Mandatory => ExtrinsicClass::Mandatory,
+					},
+					pays_fee: dispatch_info.pays_fee == Pays::Yes,
+				},
+				result,
+				fee: None,
+			});
+		}
+
+		for result in &mut results {
+			result.fee = fees.remove(&result.index);
+		}
+		results
+	}
+
 	/// Compute the final fee value for a particular transaction.
 	pub fn compute_fee(len: u32, info: &DispatchInfoOf<T::Call>, tip: BalanceOf<T>) -> BalanceOf<T>
 	where
@@ -1619,4 +1670,58 @@ mod tests {
 				assert_eq!(refund_based_fee, actual_fee);
 			});
 	}
+
+	#[test]
+	fn inclusion_results_are_read_from_events() {
+		ExtBuilder::default()
+			.balance_factor(10)
+			.base_weight(5)
+			.build()
+			.execute_with(|| {
+				// So events are emitted
+				System::set_block_number(10);
+				System::note_finished_initialize();
+				let len = 10;
+				let info = info_from_weight(100);
+
+				let pre = ChargeTransactionPayment::<Runtime>::from(0)
+					.pre_dispatch(&2, CALL, &info, len)
+					.unwrap();
+				assert_ok!(ChargeTransactionPayment::<Runtime>::post_dispatch(
+					Some(pre),
+					&info,
+					&default_post_info(),
+					len,
+					&Ok(())
+				));
+				System::note_applied_extrinsic(&Ok(default_post_info()), info);
+
+				let error = sp_runtime::DispatchError::BadOrigin;
+				System::note_applied_extrinsic(
+					&Err(sp_runtime::DispatchErrorWithPostInfo {
+						post_info: default_post_info(),
+						error,
+					}),
+					info,
+				);
+
+				let dispatch_info = sp_transaction_pool::runtime_api::DispatchInfo {
+					weight: 100,
+					class: ExtrinsicClass::Normal,
+					pays_fee: true,
+				};
+				assert_eq!(
+					TransactionPayment::inclusion_results(),
+					vec![
+						InclusionResult {
+							index: 0,
+							dispatch_info: dispatch_info.clone(),
+							result: Ok(()),
+							fee: Some(5 + 10 + 100),
+						},
+						InclusionResult { index: 1, dispatch_info, result: Err(error), fee: None },
+					],
+				);
+			});
+	}
 }
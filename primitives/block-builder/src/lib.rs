@@ -19,8 +19,154 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
 use sp_inherents::{CheckInherentsResult, InherentData};
-use sp_runtime::{traits::Block as BlockT, ApplyExtrinsicResult};
+use sp_runtime::{traits::Block as BlockT, ApplyExtrinsicResult, RuntimeDebug};
+
+/// Class of an extrinsic, deciding which resources of the block it may consume.
+///
+/// Mirrors the dispatch classes of FRAME.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ExtrinsicClass {
+	/// A normal extrinsic.
+	Normal,
+	/// An operational extrinsic, which may use the resources reserved for the operation of the
+	/// chain.
+	Operational,
+	/// A mandatory extrinsic, included in the block whatever its resources.
+	Mandatory,
+}
+
+/// A value for each [`ExtrinsicClass`].
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct PerClass<T> {
+	/// Value for [`ExtrinsicClass::Normal`].
+	pub normal: T,
+	/// Value for [`ExtrinsicClass::Operational`].
+	pub operational: T,
+	/// Value for [`ExtrinsicClass::Mandatory`].
+	pub mandatory: T,
+}
+
+impl<T> PerClass<T> {
+	/// Create a new instance, using `f` to compute the value of each class.
+	pub fn new(mut f: impl FnMut(ExtrinsicClass) -> T) -> Self {
+		Self {
+			normal: f(ExtrinsicClass::Normal),
+			operational: f(ExtrinsicClass::Operational),
+			mandatory: f(ExtrinsicClass::Mandatory),
+		}
+	}
+
+	/// Get the value of the given class.
+	pub fn get(&self, class: ExtrinsicClass) -> &T {
+		match class {
+			ExtrinsicClass::Normal => &self.normal,
+			ExtrinsicClass::Operational => &self.operational,
+			ExtrinsicClass::Mandatory => &self.mandatory,
+		}
+	}
+
+	/// Get the value of the given class mutably.
+	pub fn get_mut(&mut self, class: ExtrinsicClass) -> &mut T {
+		match class {
+			ExtrinsicClass::Normal => &mut self.normal,
+			ExtrinsicClass::Operational => &mut self.operational,
+			ExtrinsicClass::Mandatory => &mut self.mandatory,
+		}
+	}
+}
+
+/// Limits on the resources the extrinsics of a class may consume in a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ClassLimits {
+	/// Maximal weight of a single extrinsic, including the base weight of an extrinsic.
+	///
+	/// `None` if not limited.
+	pub max_extrinsic: Option<u64>,
+	/// Maximal total weight of the extrinsics of the class.
+	///
+	/// `None` if not limited.
+	pub max_total: Option<u64>,
+	/// Maximal total weight of the extrinsics of the class once the weight of the block
+	/// exceeds [`BlockResources::max_weight`].
+	///
+	/// `None` if not limited.
+	pub reserved: Option<u64>,
+	/// Maximal length in bytes of all the extrinsics of the block, when including an extrinsic
+	/// of the class.
+	pub max_length: u32,
+}
+
+/// Resources consumed by the block being built, and the limits on them.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BlockResources {
+	/// Maximal weight of the block, without the reserved weight of the classes.
+	pub max_weight: u64,
+	/// Limits of each class.
+	pub limits: PerClass<ClassLimits>,
+	/// Weight consumed so far by each class.
+	pub weight: PerClass<u64>,
+	/// Length in bytes of all the extrinsics so far.
+	pub length: u32,
+}
+
+impl BlockResources {
+	/// Consume the resources of the given extrinsic.
+	///
+	/// Returns `false`, leaving `self` untouched, if the extrinsic would exhaust the resources
+	/// of the block.
+	///
+	/// This is only an estimate following the checks of the `CheckWeight` extension of FRAME,
+	/// made without calling into the runtime. The runtime decides when the extrinsic is applied,
+	/// and its result wins: an extrinsic may still fail to be applied because it exhausts the
+	/// resources of the block, for instance if its actual weight differs or the runtime checks
+	/// more than this.
+	pub fn consume(&mut self, extrinsic: &ExtrinsicResources) -> bool {
+		let limits = self.limits.get(extrinsic.class);
+
+		if limits.max_extrinsic.map_or(false, |max| extrinsic.weight > max) {
+			return false
+		}
+
+		let length = self.length.saturating_add(extrinsic.length);
+		if length > limits.max_length {
+			return false
+		}
+
+		let class_weight = self.weight.get(extrinsic.class).saturating_add(extrinsic.weight);
+		if limits.max_total.map_or(false, |max| class_weight > max) {
+			return false
+		}
+
+		let total_weight = self
+			.weight
+			.normal
+			.saturating_add(self.weight.operational)
+			.saturating_add(self.weight.mandatory)
+			.saturating_add(extrinsic.weight);
+		if total_weight > self.max_weight &&
+			limits.reserved.map_or(false, |reserved| class_weight > reserved)
+		{
+			return false
+		}
+
+		*self.weight.get_mut(extrinsic.class) = class_weight;
+		self.length = length;
+		true
+	}
+}
+
+/// Resources consumed by an extrinsic.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ExtrinsicResources {
+	/// Class of the extrinsic.
+	pub class: ExtrinsicClass,
+	/// Weight of the extrinsic, including the base weight of an extrinsic.
+	pub weight: u64,
+	/// Length of the encoded extrinsic in bytes.
+	pub length: u32,
+}
 
 sp_api::decl_runtime_apis! {
 	/// The `BlockBuilder` api trait that provides the required functionality for building a block.
@@ -49,4 +195,83 @@ sp_api::decl_runtime_apis! {
 		/// Check that the inherents are valid. The inherent data will vary from chain to chain.
 		fn check_inherents(block: Block, data: InherentData) -> CheckInherentsResult;
 	}
+
+	/// The `BlockResourcesApi` api trait that lets the block author fill the block for each class
+	/// of extrinsics.
+	///
+	/// Optional, block authors check whether the runtime provides it.
+	pub trait BlockResourcesApi {
+		/// Get the resources consumed by the block being built, and the limits on them.
+		fn block_resources() -> BlockResources;
+
+		/// Get the resources the given extrinsic would consume when applied.
+		fn extrinsic_resources(extrinsic: <Block as BlockT>::Extrinsic) -> ExtrinsicResources;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn resources() -> BlockResources {
+		let limits = |max_total, reserved| ClassLimits {
+			max_extrinsic: Some(50),
+			max_total,
+			reserved,
+			max_length: 100,
+		};
+		BlockResources {
+			max_weight: 100,
+			limits: PerClass {
+				normal: limits(Some(75), Some(0)),
+				operational: limits(Some(100), Some(25)),
+				mandatory: limits(None, None),
+			},
+			weight: Default::default(),
+			length: 0,
+		}
+	}
+
+	fn extrinsic(class: ExtrinsicClass, weight: u64, length: u32) -> ExtrinsicResources {
+		ExtrinsicResources { class, weight, length }
+	}
+
+	#[test]
+	fn should_consume_resources_of_fitting_extrinsics() {
+		let mut resources = resources();
+
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Normal, 40, 10)));
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Operational, 20, 5)));
+
+		assert_eq!(resources.weight, PerClass { normal: 40, operational: 20, mandatory: 0 });
+		assert_eq!(resources.length, 15);
+	}
+
+	#[test]
+	fn should_not_consume_resources_of_exhausting_extrinsics() {
+		let mut resources = resources();
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Normal, 40, 10)));
+		let before = resources.clone();
+
+		// too heavy on its own
+		assert!(!resources.consume(&extrinsic(ExtrinsicClass::Operational, 51, 10)));
+		// too long
+		assert!(!resources.consume(&extrinsic(ExtrinsicClass::Normal, 1, 91)));
+		// over the limit of the class
+		assert!(!resources.consume(&extrinsic(ExtrinsicClass::Normal, 36, 10)));
+
+		assert_eq!(resources, before);
+	}
+
+	#[test]
+	fn should_use_reserved_weight_once_block_is_full() {
+		let mut resources = resources();
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Normal, 50, 10)));
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Mandatory, 40, 10)));
+
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Operational, 20, 10)));
+		assert!(!resources.consume(&extrinsic(ExtrinsicClass::Operational, 10, 10)));
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Operational, 5, 10)));
+		assert!(resources.consume(&extrinsic(ExtrinsicClass::Mandatory, 50, 10)));
+	}
 }
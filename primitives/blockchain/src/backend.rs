@@ -260,6 +260,15 @@ pub struct Info<Block: BlockT> {
 	pub number_leaves: usize,
 	/// Missing blocks after warp sync. (start, end).
 	pub block_gap: Option<(NumberFor<Block>, NumberFor<Block>)>,
+	/// Lowest block number from which on the bodies of all finalized blocks are kept.
+	///
+	/// `None` if block bodies are never pruned.
+	pub bodies_from: Option<NumberFor<Block>>,
+	/// Lowest block number from which on the justifications of all finalized blocks are kept.
+	///
+	/// Older justifications may still be kept for some blocks, e.g. the ones at era or session
+	/// boundaries. `None` if justifications are never pruned.
+	pub justifications_from: Option<NumberFor<Block>>,
 }
 
 /// Block status.
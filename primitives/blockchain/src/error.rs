@@ -20,7 +20,7 @@
 use codec::Error as CodecError;
 use sp_api::ApiError;
 use sp_consensus;
-use sp_runtime::transaction_validity::TransactionValidityError;
+use sp_runtime::{transaction_validity::TransactionValidityError, DispatchError};
 use sp_state_machine;
 use std::{self, result};
 
@@ -38,6 +38,12 @@ pub enum ApplyExtrinsicFailed {
 	#[error("Extrinsic is not valid: {0:?}")]
 	Validity(#[from] TransactionValidityError),
 
+	/// The transaction was applied, but its dispatch failed.
+	///
+	/// Only reported when the transaction must not be included if it fails.
+	#[error("Extrinsic dispatch failed: {0:?}")]
+	Dispatch(DispatchError),
+
 	#[error("Application specific error")]
 	Application(#[source] Box<dyn 'static + std::error::Error + Send + Sync>),
 }
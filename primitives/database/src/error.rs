@@ -19,6 +19,13 @@
 #[derive(Debug)]
 pub struct DatabaseError(pub Box<dyn std::error::Error + Send + Sync + 'static>);
 
+impl DatabaseError {
+	/// An error for an operation, described by `operation`, that the database doesn't support.
+	pub fn unsupported(operation: impl Into<String>) -> Self {
+		DatabaseError(format!("The database does not support {}", operation.into()).into())
+	}
+}
+
 impl std::fmt::Display for DatabaseError {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(f, "{}", self.0)
@@ -18,7 +18,7 @@
 /// A wrapper around `kvdb::Database` that implements `sp_database::Database` trait
 use ::kvdb::{DBTransaction, KeyValueDB};
 
-use crate::{error, Change, ColumnId, Database, Transaction};
+use crate::{error, Change, ColumnId, ColumnIter, Database, Transaction};
 
 struct DbAdapter<D: KeyValueDB + 'static>(D);
 
@@ -115,4 +115,14 @@ impl<D: KeyValueDB, H: Clone + AsRef<[u8]>> Database<H> for DbAdapter<D> {
 	fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
 		handle_err(self.0.has_key(col, key))
 	}
+
+	fn iter_column<'a>(&'a self, col: ColumnId) -> error::Result<ColumnIter<'a>> {
+		Ok(Box::new(self.0.iter(col).map(|(k, v)| (k.into_vec(), v.into_vec()))))
+	}
+
+	fn iter_prefix<'a>(&'a self, col: ColumnId, prefix: &'a [u8]) -> error::Result<ColumnIter<'a>> {
+		Ok(Box::new(
+			self.0.iter_with_prefix(col, prefix).map(|(k, v)| (k.into_vec(), v.into_vec())),
+		))
+	}
 }
@@ -27,6 +27,12 @@ pub use mem::MemDb;
 /// An identifier for a column.
 pub type ColumnId = u32;
 
+/// An iterator over the `(key, value)` pairs of a column.
+pub type ColumnIter<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
+/// An iterator over the values of a column.
+pub type ValueIter<'a> = Box<dyn Iterator<Item = Vec<u8>> + 'a>;
+
 /// An alteration to the database.
 #[derive(Clone)]
 pub enum Change<H> {
@@ -104,6 +110,37 @@ pub trait Database<H: Clone + AsRef<[u8]>>: Send + Sync {
 		self.get(col, key).map(|v| f(&v));
 	}
 
+	/// Iterate over all `(key, value)` pairs stored in `col`, in lexicographic order of the keys.
+	///
+	/// Returns an error if the database does not support iterating the given column, which is the
+	/// default. Pairs are returned the way they are stored by the underlying database, so columns
+	/// that use internal ref counting may yield additional bookkeeping entries.
+	fn iter_column<'a>(&'a self, col: ColumnId) -> error::Result<ColumnIter<'a>> {
+		Err(error::DatabaseError::unsupported(format!("iterating column {}", col)))
+	}
+
+	/// Iterate over all `(key, value)` pairs stored in `col` whose key starts with `prefix`, in
+	/// lexicographic order of the keys.
+	///
+	/// Returns an error if the database does not support iterating the given column. The default
+	/// implementation filters the output of [`Database::iter_column`].
+	fn iter_prefix<'a>(&'a self, col: ColumnId, prefix: &'a [u8]) -> error::Result<ColumnIter<'a>> {
+		let iter = self.iter_column(col)?;
+		Ok(Box::new(iter.filter(move |(key, _)| key.starts_with(prefix))))
+	}
+
+	/// Iterate over all the values stored in `col`, in no particular order.
+	///
+	/// Unlike [`Database::iter_column`], this doesn't need the database to keep the keys of the
+	/// column, so it also works on the columns that only index the hashes of their keys, such as
+	/// the ParityDb columns created without an ordered index. Returns an error if the database does
+	/// not support iterating the given column. The default implementation takes the values of
+	/// [`Database::iter_column`].
+	fn iter_column_values<'a>(&'a self, col: ColumnId) -> error::Result<ValueIter<'a>> {
+		let iter = self.iter_column(col)?;
+		Ok(Box::new(iter.map(|(_, value)| value)))
+	}
+
 	/// Check if database supports internal ref counting for state data.
 	///
 	/// For backwards compatibility returns `false` by default.
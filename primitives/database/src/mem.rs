@@ -17,7 +17,7 @@
 
 //! In-memory implementation of `Database`
 
-use crate::{error, Change, ColumnId, Database, Transaction};
+use crate::{error, Change, ColumnId, ColumnIter, Database, Transaction};
 use parking_lot::RwLock;
 use std::collections::{hash_map::Entry, HashMap};
 
@@ -73,6 +73,16 @@ where
 		let s = self.0.read();
 		s.get(&col).and_then(|c| c.get(key).map(|(_, v)| v.clone()))
 	}
+
+	fn iter_column<'a>(&'a self, col: ColumnId) -> error::Result<ColumnIter<'a>> {
+		let s = self.0.read();
+		let mut pairs: Vec<_> = s
+			.get(&col)
+			.map(|c| c.iter().map(|(k, (_, v))| (k.clone(), v.clone())).collect())
+			.unwrap_or_default();
+		pairs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+		Ok(Box::new(pairs.into_iter()))
+	}
 }
 
 impl MemDb {
@@ -87,3 +97,40 @@ impl MemDb {
 		s.get(&col).map(|c| c.len()).unwrap_or(0)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn iterates_columns_in_key_order() {
+		let db = MemDb::new();
+		let mut tx = Transaction::<[u8; 2]>::new();
+		tx.set(0, b"b1", b"2");
+		tx.set(0, b"a1", b"1");
+		tx.set(0, b"b0", b"3");
+		tx.set(1, b"b2", b"4");
+		tx.store(0, *b"c0", b"5".to_vec());
+		tx.store(0, *b"c0", b"5".to_vec());
+		Database::commit(&db, tx).unwrap();
+
+		let pairs: Vec<_> = Database::<[u8; 2]>::iter_column(&db, 0).unwrap().collect();
+		assert_eq!(
+			pairs,
+			vec![
+				(b"a1".to_vec(), b"1".to_vec()),
+				(b"b0".to_vec(), b"3".to_vec()),
+				(b"b1".to_vec(), b"2".to_vec()),
+				(b"c0".to_vec(), b"5".to_vec()),
+			],
+		);
+
+		let keys: Vec<_> = Database::<[u8; 2]>::iter_prefix(&db, 0, b"b")
+			.unwrap()
+			.map(|(k, _)| k)
+			.collect();
+		assert_eq!(keys, vec![b"b0".to_vec(), b"b1".to_vec()]);
+
+		assert_eq!(Database::<[u8; 2]>::iter_column(&db, 2).unwrap().count(), 0);
+	}
+}
@@ -229,6 +229,7 @@ impl From<UnknownTransaction> for TransactionValidity {
 #[derive(
 	Copy, Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, parity_util_mem::MallocSizeOf,
 )]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum TransactionSource {
 	/// Transaction is already included in block.
 	///
@@ -40,6 +40,16 @@ where
 		TrieBackend { essence: TrieBackendEssence::new(storage, root) }
 	}
 
+	/// Create new trie-based backend that reads through the given trie cache.
+	#[cfg(feature = "std")]
+	pub fn new_with_cache(
+		storage: S,
+		root: H::Out,
+		trie_cache: sp_trie::cache::LocalTrieCache<H>,
+	) -> Self {
+		TrieBackend { essence: TrieBackendEssence::new_with_cache(storage, root, trie_cache) }
+	}
+
 	/// Get backend essence reference.
 	pub fn essence(&self) -> &TrieBackendEssence<S, H> {
 		&self.essence
@@ -25,6 +25,8 @@ use hash_db::{self, AsHashDB, HashDB, HashDBRef, Hasher, Prefix};
 use parking_lot::RwLock;
 use sp_core::storage::{ChildInfo, ChildType, StateVersion};
 use sp_std::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use sp_trie::cache::LocalTrieCache;
 use sp_trie::{
 	child_delta_trie_root, delta_trie_root, empty_child_trie_root, read_child_trie_value,
 	read_trie_value,
@@ -74,6 +76,8 @@ pub struct TrieBackendEssence<S: TrieBackendStorage<H>, H: Hasher> {
 	empty: H::Out,
 	#[cfg(feature = "std")]
 	pub(crate) cache: Arc<RwLock<Cache<H::Out>>>,
+	#[cfg(feature = "std")]
+	trie_cache: Option<LocalTrieCache<H>>,
 }
 
 impl<S: TrieBackendStorage<H>, H: Hasher> TrieBackendEssence<S, H>
@@ -88,9 +92,17 @@ where
 			empty: H::hash(&[0u8]),
 			#[cfg(feature = "std")]
 			cache: Arc::new(RwLock::new(Cache::new())),
+			#[cfg(feature = "std")]
+			trie_cache: None,
 		}
 	}
 
+	/// Create new trie-based backend that reads trie nodes and values through the given cache.
+	#[cfg(feature = "std")]
+	pub fn new_with_cache(storage: S, root: H::Out, trie_cache: LocalTrieCache<H>) -> Self {
+		TrieBackendEssence { trie_cache: Some(trie_cache), ..Self::new(storage, root) }
+	}
+
 	/// Get backend storage reference.
 	pub fn backend_storage(&self) -> &S {
 		&self.storage
@@ -214,9 +226,11 @@ where
 
 	/// Get the value of storage at given key.
 	pub fn storage(&self, key: &[u8]) -> Result<Option<StorageValue>> {
-		let map_e = |e| format!("Trie lookup error: {}", e);
+		self.with_value_cache(&self.root, key, || {
+			let map_e = |e| format!("Trie lookup error: {}", e);
 
-		read_trie_value::<Layout<H>, _>(self, &self.root, key).map_err(map_e)
+			read_trie_value::<Layout<H>, _>(self, &self.root, key).map_err(map_e)
+		})
 	}
 
 	/// Get the value of child storage at given key.
@@ -230,10 +244,45 @@ where
 			None => return Ok(None),
 		};
 
-		let map_e = |e| format!("Trie lookup error: {}", e);
+		self.with_value_cache(&root, key, || {
+			let map_e = |e| format!("Trie lookup error: {}", e);
+
+			read_child_trie_value::<Layout<H>, _>(child_info.keyspace(), self, &root, key)
+				.map_err(map_e)
+		})
+	}
+
+	/// Lookup `key` of the trie with the given `root` in the value cache and fall back to
+	/// `read` on a cache miss.
+	#[cfg(feature = "std")]
+	fn with_value_cache(
+		&self,
+		root: &H::Out,
+		key: &[u8],
+		read: impl FnOnce() -> Result<Option<StorageValue>>,
+	) -> Result<Option<StorageValue>> {
+		let trie_cache = match self.trie_cache.as_ref() {
+			Some(trie_cache) => trie_cache,
+			None => return read(),
+		};
+
+		if let Some(value) = trie_cache.lookup_value(*root, key) {
+			return Ok(value)
+		}
+
+		let value = read()?;
+		trie_cache.cache_value(*root, key, value.clone());
+		Ok(value)
+	}
 
-		read_child_trie_value::<Layout<H>, _>(child_info.keyspace(), self, &root, key)
-			.map_err(map_e)
+	#[cfg(not(feature = "std"))]
+	fn with_value_cache(
+		&self,
+		_root: &H::Out,
+		_key: &[u8],
+		read: impl FnOnce() -> Result<Option<StorageValue>>,
+	) -> Result<Option<StorageValue>> {
+		read()
 	}
 
 	/// Retrieve all entries keys of storage and call `f` for each of those keys.
@@ -651,8 +700,20 @@ impl<S: TrieBackendStorage<H>, H: Hasher> HashDB<H, DBValue> for TrieBackendEsse
 		if *key == self.empty {
 			return Some([0u8].to_vec())
 		}
+
+		#[cfg(feature = "std")]
+		if let Some(node) = self.trie_cache.as_ref().and_then(|c| c.get_node(key)) {
+			return Some(node)
+		}
+
 		match self.storage.get(key, prefix) {
-			Ok(x) => x,
+			Ok(x) => {
+				#[cfg(feature = "std")]
+				if let (Some(trie_cache), Some(node)) = (self.trie_cache.as_ref(), x.as_ref()) {
+					trie_cache.insert_node(*key, node.clone());
+				}
+				x
+			},
 			Err(e) => {
 				warn!(target: "trie", "Failed to read from DB: {}", e);
 				None
@@ -743,4 +804,43 @@ mod test {
 		assert_eq!(essence_2.next_child_storage_key(child_info, b"5"), Ok(Some(b"6".to_vec())));
 		assert_eq!(essence_2.next_child_storage_key(child_info, b"6"), Ok(None));
 	}
+
+	#[test]
+	fn reads_are_served_from_trie_cache() {
+		let child_info = ChildInfo::new_default(b"MyChild");
+		let mut child_root = H256::default();
+		let mut root = H256::default();
+
+		let mut mdb = PrefixedMemoryDB::<Blake2Hasher>::default();
+		{
+			let mut mdb = KeySpacedDBMut::new(&mut mdb, child_info.keyspace());
+			let mut trie = TrieDBMut::new(&mut mdb, &mut child_root);
+			trie.insert(b"child", &[2]).expect("insert failed");
+		}
+		{
+			let mut trie = TrieDBMut::new(&mut mdb, &mut root);
+			trie.insert(b"key", &[1]).expect("insert failed");
+			trie.insert(child_info.prefixed_storage_key().as_slice(), child_root.as_ref())
+				.expect("insert failed");
+		}
+
+		let shared_cache = sp_trie::cache::SharedTrieCache::<Blake2Hasher>::new(1024 * 1024);
+		{
+			let essence = TrieBackendEssence::new_with_cache(mdb, root, shared_cache.local_cache());
+			assert_eq!(essence.storage(b"key"), Ok(Some(vec![1])));
+			assert_eq!(essence.storage(b"missing"), Ok(None));
+			assert_eq!(essence.child_storage(&child_info, b"child"), Ok(Some(vec![2])));
+		}
+
+		// Everything that was read before must now be served from the cache alone.
+		let essence = TrieBackendEssence::new_with_cache(
+			PrefixedMemoryDB::<Blake2Hasher>::default(),
+			root,
+			shared_cache.local_cache(),
+		);
+		assert_eq!(essence.storage(b"key"), Ok(Some(vec![1])));
+		assert_eq!(essence.storage(b"missing"), Ok(None));
+		assert_eq!(essence.child_storage(&child_info, b"child"), Ok(Some(vec![2])));
+		assert_eq!(essence.next_storage_key(b"key"), Ok(None));
+	}
 }
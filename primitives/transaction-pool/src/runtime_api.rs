@@ -17,10 +17,58 @@
 
 //! Tagged Transaction Queue Runtime API.
 
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_runtime::{
 	traits::Block as BlockT,
 	transaction_validity::{TransactionSource, TransactionValidity},
+	DispatchResult, RuntimeDebug,
 };
+use sp_std::vec::Vec;
+
+/// Class of an extrinsic.
+///
+/// Mirrors the dispatch classes of FRAME.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum ExtrinsicClass {
+	/// A normal extrinsic.
+	Normal,
+	/// An operational extrinsic.
+	Operational,
+	/// A mandatory extrinsic.
+	Mandatory,
+}
+
+/// Dispatch information of an extrinsic, see `frame_support::weights::DispatchInfo`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct DispatchInfo {
+	/// Weight of the extrinsic.
+	pub weight: u64,
+	/// Class of the extrinsic.
+	pub class: ExtrinsicClass,
+	/// Whether the extrinsic pays a fee.
+	pub pays_fee: bool,
+}
+
+/// Result of an extrinsic included in a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct InclusionResult {
+	/// Index of the extrinsic in the block.
+	pub index: u32,
+	/// Dispatch information of the extrinsic.
+	pub dispatch_info: DispatchInfo,
+	/// Whether the extrinsic was dispatched successfully.
+	pub result: DispatchResult,
+	/// Fee paid for the extrinsic, `None` if it didn't pay one.
+	pub fee: Option<u128>,
+}
 
 sp_api::decl_runtime_apis! {
 	/// The `TaggedTransactionQueue` api trait for interfering with the transaction queue.
@@ -52,4 +100,16 @@ sp_api::decl_runtime_apis! {
 			block_hash: Block::Hash,
 		) -> TransactionValidity;
 	}
+
+	/// The `TransactionInclusionApi` api trait for reporting the results of included
+	/// transactions.
+	///
+	/// This api is optional, the transaction pool reports the results of the included
+	/// transactions to their watchers only if the runtime provides it.
+	pub trait TransactionInclusionApi {
+		/// Get the results of the extrinsics of the block, as reported by its events.
+		///
+		/// This method is invoked by the transaction pool at the state of an imported block.
+		fn inclusion_results() -> Vec<InclusionResult>;
+	}
 }
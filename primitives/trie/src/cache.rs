@@ -0,0 +1,291 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Trie node and value cache.
+//!
+//! The [`SharedTrieCache`] is meant to be instantiated once per node and shared between all
+//! state instances. It holds two size bounded LRU maps:
+//!
+//! - A node cache, mapping the hash of an encoded trie node to the encoded node.
+//! - A value cache, mapping `(storage_root, key)` to the value stored under `key` in the trie
+//!   with the given root.
+//!
+//! Both maps are keyed by content (node hash or trie root), so an entry can never become
+//! invalid because of a reorg and there is nothing to sync when forks are switched.
+//!
+//! Every state instance gets its own [`LocalTrieCache`] through
+//! [`SharedTrieCache::local_cache`]. It buffers the values read while executing a block and
+//! merges them into the shared value cache once it is dropped, which keeps the lock on the
+//! shared value cache out of the hot path.
+
+use crate::DBValue;
+use hash_db::Hasher;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+/// Share of the maximum cache size that is dedicated to the node cache, in percent.
+///
+/// The rest is used by the value cache.
+const NODE_CACHE_SHARE: usize = 70;
+
+/// Maximum size in bytes of the values buffered by a [`LocalTrieCache`].
+///
+/// Values read after this limit is reached go straight to the shared value cache.
+const LOCAL_VALUE_CACHE_MAX_SIZE: usize = 2 * 1024 * 1024;
+
+/// LRU map that is bounded by the estimated size of its entries instead of their number.
+///
+/// The estimated size of every entry is stored next to its value.
+struct SizedLru<K: Hash + Eq, V> {
+	lru: LruCache<K, (V, usize)>,
+	used_size: usize,
+	max_size: usize,
+}
+
+impl<K: Hash + Eq, V> SizedLru<K, V> {
+	fn new(max_size: usize) -> Self {
+		Self { lru: LruCache::unbounded(), used_size: 0, max_size }
+	}
+
+	fn get(&mut self, key: &K) -> Option<&V> {
+		self.lru.get(key).map(|(v, _)| v)
+	}
+
+	/// Insert `value` under `key`, `size` being the estimated size of the entry in bytes.
+	fn insert(&mut self, key: K, value: V, size: usize) {
+		if size > self.max_size {
+			return
+		}
+
+		if let Some((_, old_size)) = self.lru.put(key, (value, size)) {
+			self.used_size -= old_size;
+		}
+		self.used_size += size;
+
+		while self.used_size > self.max_size {
+			match self.lru.pop_lru() {
+				Some((_, (_, size))) => self.used_size -= size,
+				None => break,
+			}
+		}
+	}
+
+	fn clear(&mut self) {
+		self.lru.clear();
+		self.used_size = 0;
+	}
+}
+
+/// Key of the value cache: the storage root of the trie and the key inside of this trie.
+type ValueCacheKey<H> = (H, Vec<u8>);
+
+/// Estimated size of a value cache entry in bytes.
+fn value_entry_size<H: AsRef<[u8]>>(key: &ValueCacheKey<H>, value: &Option<Vec<u8>>) -> usize {
+	key.0.as_ref().len() + key.1.len() + value.as_ref().map_or(0, |v| v.len())
+}
+
+struct SharedTrieCacheInner<H: Hasher> {
+	node_cache: SizedLru<H::Out, DBValue>,
+	value_cache: SizedLru<ValueCacheKey<H::Out>, Option<Vec<u8>>>,
+}
+
+/// The shared trie cache.
+///
+/// Cheap to clone, all clones refer to the same underlying cache.
+pub struct SharedTrieCache<H: Hasher> {
+	inner: Arc<Mutex<SharedTrieCacheInner<H>>>,
+}
+
+impl<H: Hasher> Clone for SharedTrieCache<H> {
+	fn clone(&self) -> Self {
+		Self { inner: self.inner.clone() }
+	}
+}
+
+impl<H: Hasher> SharedTrieCache<H> {
+	/// Create a new cache that will use at most `max_size` bytes.
+	pub fn new(max_size: usize) -> Self {
+		let node_cache_size = max_size / 100 * NODE_CACHE_SHARE;
+		let value_cache_size = max_size - node_cache_size;
+
+		Self {
+			inner: Arc::new(Mutex::new(SharedTrieCacheInner {
+				node_cache: SizedLru::new(node_cache_size),
+				value_cache: SizedLru::new(value_cache_size),
+			})),
+		}
+	}
+
+	/// Create a new [`LocalTrieCache`] backed by this shared cache.
+	pub fn local_cache(&self) -> LocalTrieCache<H> {
+		LocalTrieCache { shared: self.clone(), value_cache: Default::default() }
+	}
+
+	/// Returns the estimated memory used by the cache in bytes.
+	pub fn used_memory_size(&self) -> usize {
+		let inner = self.inner.lock();
+		inner.node_cache.used_size + inner.value_cache.used_size
+	}
+
+	/// Remove all entries from the cache.
+	pub fn reset(&self) {
+		let mut inner = self.inner.lock();
+		inner.node_cache.clear();
+		inner.value_cache.clear();
+	}
+
+	fn get_node(&self, hash: &H::Out) -> Option<DBValue> {
+		self.inner.lock().node_cache.get(hash).cloned()
+	}
+
+	fn insert_node(&self, hash: H::Out, node: DBValue) {
+		let size = hash.as_ref().len() + node.len();
+		self.inner.lock().node_cache.insert(hash, node, size);
+	}
+
+	fn lookup_value(&self, key: &ValueCacheKey<H::Out>) -> Option<Option<Vec<u8>>> {
+		self.inner.lock().value_cache.get(key).cloned()
+	}
+}
+
+#[derive(Default)]
+struct LocalValueCache<H> {
+	values: HashMap<ValueCacheKey<H>, Option<Vec<u8>>>,
+	used_size: usize,
+}
+
+/// The local trie cache of a single state instance.
+///
+/// Nodes are read from and written to the [`SharedTrieCache`] directly. Values are buffered
+/// locally and merged into the shared cache on drop.
+pub struct LocalTrieCache<H: Hasher> {
+	shared: SharedTrieCache<H>,
+	value_cache: Mutex<LocalValueCache<H::Out>>,
+}
+
+impl<H: Hasher> LocalTrieCache<H> {
+	/// Get the encoded trie node with the given `hash`.
+	pub fn get_node(&self, hash: &H::Out) -> Option<DBValue> {
+		self.shared.get_node(hash)
+	}
+
+	/// Cache the encoded trie `node` with the given `hash`.
+	pub fn insert_node(&self, hash: H::Out, node: DBValue) {
+		self.shared.insert_node(hash, node)
+	}
+
+	/// Lookup the value stored under `key` in the trie with the given `storage_root`.
+	///
+	/// Returns `Some(None)` if the key is known to not exist in the trie.
+	pub fn lookup_value(&self, storage_root: H::Out, key: &[u8]) -> Option<Option<Vec<u8>>> {
+		let cache_key = (storage_root, key.to_vec());
+		if let Some(value) = self.value_cache.lock().values.get(&cache_key) {
+			return Some(value.clone())
+		}
+
+		self.shared.lookup_value(&cache_key)
+	}
+
+	/// Cache the `value` stored under `key` in the trie with the given `storage_root`.
+	///
+	/// `None` caches that the key does not exist in the trie.
+	pub fn cache_value(&self, storage_root: H::Out, key: &[u8], value: Option<Vec<u8>>) {
+		let cache_key = (storage_root, key.to_vec());
+		let size = value_entry_size(&cache_key, &value);
+
+		let mut local = self.value_cache.lock();
+		if local.used_size + size > LOCAL_VALUE_CACHE_MAX_SIZE {
+			drop(local);
+			self.shared.inner.lock().value_cache.insert(cache_key, value, size);
+			return
+		}
+
+		local.used_size += size;
+		if let Some(old) = local.values.insert(cache_key, value) {
+			local.used_size -= storage_root.as_ref().len() + key.len() + old.map_or(0, |v| v.len());
+		}
+	}
+}
+
+impl<H: Hasher> Drop for LocalTrieCache<H> {
+	fn drop(&mut self) {
+		let local = std::mem::take(&mut *self.value_cache.lock());
+		if local.values.is_empty() {
+			return
+		}
+
+		let mut shared = self.shared.inner.lock();
+		for (key, value) in local.values {
+			let size = value_entry_size(&key, &value);
+			shared.value_cache.insert(key, value, size);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{Blake2Hasher, H256};
+
+	type Cache = SharedTrieCache<Blake2Hasher>;
+
+	#[test]
+	fn node_cache_respects_max_size() {
+		let cache = Cache::new(1000);
+		let local = cache.local_cache();
+
+		for i in 0..100u8 {
+			local.insert_node(H256::repeat_byte(i), vec![i; 32]);
+		}
+
+		assert!(cache.used_memory_size() <= 700);
+		// The most recently inserted node is still there, the first one got evicted.
+		assert_eq!(local.get_node(&H256::repeat_byte(99)), Some(vec![99; 32]));
+		assert_eq!(local.get_node(&H256::repeat_byte(0)), None);
+	}
+
+	#[test]
+	fn values_are_merged_into_shared_cache_on_drop() {
+		let cache = Cache::new(1024 * 1024);
+		let root = H256::repeat_byte(1);
+
+		let local = cache.local_cache();
+		local.cache_value(root, b"key", Some(b"value".to_vec()));
+		local.cache_value(root, b"missing", None);
+		assert_eq!(cache.used_memory_size(), 0);
+		drop(local);
+
+		let local = cache.local_cache();
+		assert_eq!(local.lookup_value(root, b"key"), Some(Some(b"value".to_vec())));
+		assert_eq!(local.lookup_value(root, b"missing"), Some(None));
+		assert_eq!(local.lookup_value(H256::repeat_byte(2), b"key"), None);
+	}
+
+	#[test]
+	fn reset_clears_everything() {
+		let cache = Cache::new(1024 * 1024);
+		let local = cache.local_cache();
+		local.insert_node(H256::repeat_byte(1), vec![1; 32]);
+		local.cache_value(H256::repeat_byte(1), b"key", None);
+		drop(local);
+
+		assert!(cache.used_memory_size() > 0);
+		cache.reset();
+		assert_eq!(cache.used_memory_size(), 0);
+	}
+}
@@ -735,6 +735,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_transaction_pool::runtime_api::TransactionInclusionApi<Block> for Runtime {
+				fn inclusion_results() -> Vec<sp_transaction_pool::runtime_api::InclusionResult> {
+					Vec::new()
+				}
+			}
+
 			impl sp_block_builder::BlockBuilder<Block> for Runtime {
 				fn apply_extrinsic(extrinsic: <Block as BlockT>::Extrinsic) -> ApplyExtrinsicResult {
 					system::execute_transaction(extrinsic)
@@ -753,6 +759,22 @@ cfg_if! {
 				}
 			}
 
+			impl sp_block_builder::BlockResourcesApi<Block> for Runtime {
+				fn block_resources() -> sp_block_builder::BlockResources {
+					system::block_resources()
+				}
+
+				fn extrinsic_resources(
+					extrinsic: <Block as BlockT>::Extrinsic,
+				) -> sp_block_builder::ExtrinsicResources {
+					sp_block_builder::ExtrinsicResources {
+						class: sp_block_builder::ExtrinsicClass::Normal,
+						weight: 0,
+						length: extrinsic.encoded_size() as u32,
+					}
+				}
+			}
+
 			impl self::TestAPI<Block> for Runtime {
 				fn balance_of(id: AccountId) -> u64 {
 					system::balance_of(id)
@@ -962,6 +984,23 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::DryRunApi<Block> for Runtime {
+				fn extrinsic_events(_index: u32) -> Vec<u8> {
+					Vec::<()>::new().encode()
+				}
+
+				fn fee_breakdown(
+					_uxt: <Block as BlockT>::Extrinsic,
+					_len: u32,
+				) -> Option<frame_system_rpc_runtime_api::FeeBreakdown> {
+					None
+				}
+
+				fn pallet_names() -> Vec<Vec<u8>> {
+					vec![b"TestRuntime".to_vec()]
+				}
+			}
 		}
 	} else {
 		impl_runtime_apis! {
@@ -1005,6 +1044,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_transaction_pool::runtime_api::TransactionInclusionApi<Block> for Runtime {
+				fn inclusion_results() -> Vec<sp_transaction_pool::runtime_api::InclusionResult> {
+					Vec::new()
+				}
+			}
+
 			impl sp_block_builder::BlockBuilder<Block> for Runtime {
 				fn apply_extrinsic(extrinsic: <Block as BlockT>::Extrinsic) -> ApplyExtrinsicResult {
 					system::execute_transaction(extrinsic)
@@ -1023,6 +1068,22 @@ cfg_if! {
 				}
 			}
 
+			impl sp_block_builder::BlockResourcesApi<Block> for Runtime {
+				fn block_resources() -> sp_block_builder::BlockResources {
+					system::block_resources()
+				}
+
+				fn extrinsic_resources(
+					extrinsic: <Block as BlockT>::Extrinsic,
+				) -> sp_block_builder::ExtrinsicResources {
+					sp_block_builder::ExtrinsicResources {
+						class: sp_block_builder::ExtrinsicClass::Normal,
+						weight: 0,
+						length: extrinsic.encoded_size() as u32,
+					}
+				}
+			}
+
 			impl self::TestAPI<Block> for Runtime {
 				fn balance_of(id: AccountId) -> u64 {
 					system::balance_of(id)
@@ -1193,6 +1254,23 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::DryRunApi<Block> for Runtime {
+				fn extrinsic_events(_index: u32) -> Vec<u8> {
+					Vec::<()>::new().encode()
+				}
+
+				fn fee_breakdown(
+					_uxt: <Block as BlockT>::Extrinsic,
+					_len: u32,
+				) -> Option<frame_system_rpc_runtime_api::FeeBreakdown> {
+					None
+				}
+
+				fn pallet_names() -> Vec<Vec<u8>> {
+					vec![b"TestRuntime".to_vec()]
+				}
+			}
 		}
 	}
 }
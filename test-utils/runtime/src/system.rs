@@ -192,6 +192,27 @@ pub fn execute_transaction(utx: Extrinsic) -> ApplyExtrinsicResult {
 	result
 }
 
+/// Resources consumed by the block being built.
+///
+/// The resources of the blocks are not limited.
+pub fn block_resources() -> sp_block_builder::BlockResources {
+	let extrinsic_index: u32 =
+		storage::unhashed::get(well_known_keys::EXTRINSIC_INDEX).unwrap_or_default();
+	let length = (0..extrinsic_index).map(|i| ExtrinsicData::get(i).len() as u32).sum();
+
+	sp_block_builder::BlockResources {
+		max_weight: u64::MAX,
+		limits: sp_block_builder::PerClass::new(|_| sp_block_builder::ClassLimits {
+			max_extrinsic: None,
+			max_total: None,
+			reserved: None,
+			max_length: u32::MAX,
+		}),
+		weight: Default::default(),
+		length,
+	}
+}
+
 /// Finalize the block.
 pub fn finalize_block() -> Header {
 	use sp_core::storage::StateVersion;
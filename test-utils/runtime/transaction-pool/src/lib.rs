@@ -335,6 +335,12 @@ impl sc_transaction_pool::ChainApi for TestApi {
 				self.chain.read().block_by_hash.get(hash).map(|b| b.header().clone()),
 		})
 	}
+	fn inclusion_results(
+		&self,
+		_: &BlockId<Self::Block>,
+	) -> Result<Vec<(Hash, sc_transaction_pool_api::InclusionResult)>, Self::Error> {
+		Ok(Vec::new())
+	}
 }
 
 impl sp_blockchain::HeaderMetadata<Block> for TestApi {
@@ -0,0 +1,293 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dry run of extrinsics reporting all the effects of their execution.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt::Display,
+	sync::Arc,
+};
+
+use codec::{Decode, Encode};
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use serde::{Deserialize, Serialize};
+
+use sc_client_api::backend::{Backend, StateBackendFor};
+use sc_rpc_api::DenyUnsafe;
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_api::{ApiExt, ApiRef, Core, ProvideRuntimeApi, TransactionOutcome};
+use sp_block_builder::{BlockBuilder, BlockResourcesApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::twox_128, Bytes};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{self, Header as _, One},
+};
+use sp_transaction_pool::runtime_api::{InclusionResult, TransactionInclusionApi};
+
+use crate::Error;
+pub use frame_system_rpc_runtime_api::{DryRunApi, FeeBreakdown};
+
+/// Changes of the main storage, with `None` for the removed keys.
+type StorageCollection = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// Dry run RPC methods.
+#[rpc(client, server)]
+pub trait DryRunEffectsApi<BlockHash> {
+	/// Dry run an extrinsic at a given block and return all the effects of its execution.
+	///
+	/// The extrinsic is applied in a new block on top of the given block, or of the best block
+	/// if none is given. If `pending` is `true`, the ready transactions of the pool are applied
+	/// first.
+	#[method(name = "system_dryRunWithEffects")]
+	async fn dry_run_with_effects(
+		&self,
+		extrinsic: Bytes,
+		at: Option<BlockHash>,
+		pending: Option<bool>,
+	) -> RpcResult<ExtrinsicEffects>;
+}
+
+/// Effects of the execution of an extrinsic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicEffects {
+	/// SCALE encoded `ApplyExtrinsicResult` of the extrinsic.
+	pub result: Bytes,
+	/// SCALE encoded `frame_system::EventRecord`s deposited by the extrinsic.
+	pub events: Bytes,
+	/// Changes made by the extrinsic to the main storage, grouped by pallet.
+	pub storage_changes: Vec<PalletStorageChanges>,
+	/// Weight declared by the extrinsic, `None` if the runtime can't report it.
+	pub declared_weight: Option<u64>,
+	/// Inclusion fee of the extrinsic before any refund, `None` if it doesn't pay one.
+	pub fee_breakdown: Option<FeeBreakdown>,
+	/// Result of the inclusion of the extrinsic, with the weight it actually consumed and the
+	/// fee it actually paid.
+	///
+	/// `None` if the extrinsic was not included or if the runtime can't report it.
+	pub inclusion: Option<InclusionResult>,
+}
+
+/// Changes made to the storage of a pallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PalletStorageChanges {
+	/// Name of the pallet, `None` for the keys that don't belong to any pallet.
+	pub pallet: Option<String>,
+	/// Changed keys with their new value, `None` if the key was removed.
+	pub changes: Vec<(Bytes, Option<Bytes>)>,
+}
+
+/// An implementation of the dry run RPC methods on full client.
+pub struct DryRun<P, C, B, BA> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	backend: Arc<BA>,
+	deny_unsafe: DenyUnsafe,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<P, C, B, BA> DryRun<P, C, B, BA> {
+	/// Create new `DryRun` given client, transaction pool and backend.
+	pub fn new(client: Arc<C>, pool: Arc<P>, backend: Arc<BA>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { client, pool, backend, deny_unsafe, _marker: Default::default() }
+	}
+}
+
+fn runtime_error(e: impl Display) -> CallError {
+	CallError::Custom(ErrorObject::owned(
+		Error::RuntimeError.into(),
+		"Unable to dry run extrinsic.",
+		Some(e.to_string()),
+	))
+}
+
+impl<P, C, Block, BA> DryRun<P, C, Block, BA>
+where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: BlockBuilder<Block> + ApiExt<Block, StateBackend = StateBackendFor<BA, Block>>,
+	BA: Backend<Block>,
+	Block: traits::Block,
+{
+	/// Initialize a new block on top of `parent` and apply the given extrinsics, skipping the
+	/// ones that can't be included.
+	///
+	/// Returns the number of extrinsics included.
+	fn prepare(
+		&self,
+		api: &ApiRef<C::Api>,
+		parent: &Block::Header,
+		extrinsics: &[Block::Extrinsic],
+	) -> Result<u32, CallError> {
+		let at = BlockId::Hash(parent.hash());
+		let header = <Block::Header as traits::Header>::new(
+			*parent.number() + One::one(),
+			Default::default(),
+			Default::default(),
+			parent.hash(),
+			Default::default(),
+		);
+		api.initialize_block(&at, &header).map_err(runtime_error)?;
+
+		let mut included = 0;
+		for xt in extrinsics {
+			included +=
+				api.execute_in_transaction(|api| match api.apply_extrinsic(&at, xt.clone()) {
+					Ok(Ok(_)) => TransactionOutcome::Commit(1),
+					_ => TransactionOutcome::Rollback(0),
+				});
+		}
+		Ok(included)
+	}
+
+	/// Changes made to the main storage by the calls made on `api`.
+	fn storage_changes(
+		&self,
+		api: &ApiRef<C::Api>,
+		parent_hash: Block::Hash,
+	) -> Result<StorageCollection, CallError> {
+		let state = self.backend.state_at(BlockId::Hash(parent_hash)).map_err(runtime_error)?;
+		let changes = api.into_storage_changes(&state, parent_hash).map_err(runtime_error)?;
+		Ok(changes.main_storage_changes)
+	}
+}
+
+#[async_trait]
+impl<P, C, Block, BA> DryRunEffectsApiServer<<Block as traits::Block>::Hash>
+	for DryRun<P, C, Block, BA>
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block>,
+	C: Send + Sync + 'static,
+	C::Api: BlockBuilder<Block>,
+	C::Api: BlockResourcesApi<Block>,
+	C::Api: TransactionInclusionApi<Block>,
+	C::Api: DryRunApi<Block>,
+	C::Api: ApiExt<Block, StateBackend = StateBackendFor<BA, Block>>,
+	BA: Backend<Block> + 'static,
+	P: TransactionPool<Block = Block> + 'static,
+	Block: traits::Block,
+{
+	async fn dry_run_with_effects(
+		&self,
+		extrinsic: Bytes,
+		at: Option<<Block as traits::Block>::Hash>,
+		pending: Option<bool>,
+	) -> RpcResult<ExtrinsicEffects> {
+		self.deny_unsafe.check_if_safe()?;
+		let at = BlockId::<Block>::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let uxt: <Block as traits::Block>::Extrinsic =
+			Decode::decode(&mut &*extrinsic).map_err(|e| {
+				CallError::Custom(ErrorObject::owned(
+					Error::DecodeError.into(),
+					"Unable to dry run extrinsic",
+					Some(e.to_string()),
+				))
+			})?;
+
+		let parent =
+			self.client.header(at).map_err(runtime_error)?.ok_or_else(|| {
+				runtime_error(format!("Could not find header of block `{:?}`.", at))
+			})?;
+		let parent_hash = parent.hash();
+
+		let pending = if pending.unwrap_or(false) {
+			self.pool.ready().map(|tx| tx.data().clone()).collect()
+		} else {
+			Vec::new()
+		};
+
+		// The effects of the extrinsic are the changes made on top of the ones made by the
+		// initialization of the block and by the pending extrinsics.
+		let api = self.client.runtime_api();
+		if !api.has_api::<dyn DryRunApi<Block>>(&at).map_err(runtime_error)? {
+			return Err(runtime_error(format!(
+				"Could not find `DryRunApi` api for block `{:?}`.",
+				at
+			))
+			.into())
+		}
+		self.prepare(&api, &parent, &pending)?;
+		let initial_changes: HashMap<_, _> =
+			self.storage_changes(&api, parent_hash)?.into_iter().collect();
+
+		let api = self.client.runtime_api();
+		let index = self.prepare(&api, &parent, &pending)?;
+
+		let declared_weight =
+			if api.has_api::<dyn BlockResourcesApi<Block>>(&at).map_err(runtime_error)? {
+				Some(api.extrinsic_resources(&at, uxt.clone()).map_err(runtime_error)?.weight)
+			} else {
+				None
+			};
+		let fee_breakdown = api
+			.fee_breakdown(&at, uxt.clone(), extrinsic.len() as u32)
+			.map_err(runtime_error)?;
+
+		let result = api.apply_extrinsic(&at, uxt).map_err(runtime_error)?;
+
+		let events = api.extrinsic_events(&at, index).map_err(runtime_error)?;
+		let inclusion =
+			if api.has_api::<dyn TransactionInclusionApi<Block>>(&at).map_err(runtime_error)? {
+				api.inclusion_results(&at)
+					.map_err(runtime_error)?
+					.into_iter()
+					.find(|r| r.index == index)
+			} else {
+				None
+			};
+
+		let pallets: HashMap<_, _> = api
+			.pallet_names(&at)
+			.map_err(runtime_error)?
+			.into_iter()
+			.map(|name| (twox_128(&name), String::from_utf8_lossy(&name).into_owned()))
+			.collect();
+		let mut storage_changes = BTreeMap::<_, Vec<_>>::new();
+		for (key, value) in self.storage_changes(&api, parent_hash)? {
+			if initial_changes.get(&key) == Some(&value) {
+				continue
+			}
+			let pallet = key.get(..16).and_then(|prefix| pallets.get(prefix)).cloned();
+			storage_changes
+				.entry(pallet)
+				.or_default()
+				.push((key.into(), value.map(Into::into)));
+		}
+
+		Ok(ExtrinsicEffects {
+			result: result.encode().into(),
+			events: events.into(),
+			storage_changes: storage_changes
+				.into_iter()
+				.map(|(pallet, changes)| PalletStorageChanges { pallet, changes })
+				.collect(),
+			declared_weight,
+			fee_breakdown,
+			inclusion,
+		})
+	}
+}
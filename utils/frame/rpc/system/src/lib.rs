@@ -34,6 +34,12 @@ use sp_blockchain::HeaderBackend;
 use sp_core::{hexdisplay::HexDisplay, Bytes};
 use sp_runtime::{generic::BlockId, legacy, traits};
 
+mod dry_run;
+
+pub use dry_run::{
+	DryRun, DryRunApi, DryRunEffectsApiClient, DryRunEffectsApiServer, ExtrinsicEffects,
+	FeeBreakdown, PalletStorageChanges,
+};
 pub use frame_system_rpc_runtime_api::AccountNonceApi;
 
 /// System RPC methods.
@@ -219,11 +225,15 @@ mod tests {
 	use futures::executor::block_on;
 	use jsonrpsee::{core::Error as JsonRpseeError, types::error::CallError};
 	use sc_transaction_pool::BasicPool;
+	use sp_core::blake2_256;
 	use sp_runtime::{
 		transaction_validity::{InvalidTransaction, TransactionValidityError},
 		ApplyExtrinsicResult,
 	};
-	use substrate_test_runtime_client::{runtime::Transfer, AccountKeyring};
+	use substrate_test_runtime_client::{
+		runtime::{system::balance_of_key, Transfer},
+		AccountKeyring, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
 
 	#[tokio::test]
 	async fn should_return_next_nonce_for_some_account() {
@@ -334,4 +344,97 @@ mod tests {
 		let apply_res: ApplyExtrinsicResult = Decode::decode(&mut bytes.as_ref()).unwrap();
 		assert_eq!(apply_res, Err(TransactionValidityError::Invalid(InvalidTransaction::Stale)));
 	}
+
+	#[tokio::test]
+	async fn dry_run_with_effects_should_report_storage_changes() {
+		sp_tracing::try_init_simple();
+
+		// given
+		let (client, backend) = TestClientBuilder::new().build_with_backend();
+		let client = Arc::new(client);
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let pool =
+			BasicPool::new_full(Default::default(), true.into(), None, spawner, client.clone());
+
+		let dry_run = DryRun::new(client, pool, backend, DenyUnsafe::No);
+
+		let tx = Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 5,
+			nonce: 0,
+		}
+		.into_signed_tx();
+
+		// when
+		let effects = dry_run
+			.dry_run_with_effects(tx.encode().into(), None, None)
+			.await
+			.expect("Call is successful");
+
+		// then
+		let apply_res: ApplyExtrinsicResult = Decode::decode(&mut effects.result.as_ref()).unwrap();
+		assert_eq!(apply_res, Ok(Ok(())));
+		assert_eq!(effects.declared_weight, Some(0));
+		assert_eq!(effects.inclusion, None);
+
+		let pallets = effects.storage_changes.iter().map(|c| c.pallet.clone()).collect::<Vec<_>>();
+		assert_eq!(pallets, vec![None, Some("TestRuntime".into())]);
+		let bob_balance = blake2_256(&balance_of_key(AccountKeyring::Bob.into())).to_vec();
+		assert!(effects.storage_changes[0].changes.iter().any(|(key, _)| key.0 == bob_balance));
+		// Only the extrinsic data is written to the storage of the runtime by the extrinsic.
+		assert_eq!(
+			effects.storage_changes[1]
+				.changes
+				.iter()
+				.map(|(_, v)| v.clone())
+				.collect::<Vec<_>>(),
+			vec![Some(tx.encode().encode().into())],
+		);
+	}
+
+	#[tokio::test]
+	async fn dry_run_with_effects_should_apply_pending_transactions() {
+		sp_tracing::try_init_simple();
+
+		// given
+		let (client, backend) = TestClientBuilder::new().build_with_backend();
+		let client = Arc::new(client);
+		let spawner = sp_core::testing::TaskExecutor::new();
+		let pool =
+			BasicPool::new_full(Default::default(), true.into(), None, spawner, client.clone());
+
+		let source = sp_runtime::transaction_validity::TransactionSource::External;
+		let new_transaction = |nonce: u64| {
+			let t = Transfer {
+				from: AccountKeyring::Alice.into(),
+				to: AccountKeyring::Bob.into(),
+				amount: 5,
+				nonce,
+			};
+			t.into_signed_tx()
+		};
+		block_on(pool.submit_one(&BlockId::number(0), source, new_transaction(0))).unwrap();
+
+		let dry_run = DryRun::new(client, pool, backend, DenyUnsafe::No);
+		let tx = new_transaction(1);
+
+		// when
+		let on_block = dry_run
+			.dry_run_with_effects(tx.encode().into(), None, None)
+			.await
+			.expect("Call is successful");
+		let on_pending = dry_run
+			.dry_run_with_effects(tx.encode().into(), None, Some(true))
+			.await
+			.expect("Call is successful");
+
+		// then
+		let apply_res: ApplyExtrinsicResult =
+			Decode::decode(&mut on_block.result.as_ref()).unwrap();
+		assert_eq!(apply_res, Err(TransactionValidityError::Invalid(InvalidTransaction::Stale)));
+		let apply_res: ApplyExtrinsicResult =
+			Decode::decode(&mut on_pending.result.as_ref()).unwrap();
+		assert_eq!(apply_res, Ok(Ok(())));
+	}
 }